@@ -0,0 +1,81 @@
+//! Capture a mainnet deposit or withdrawal as a test fixture
+//!
+//! cargo run --example capture_execution --features test-utils -- <deposit|withdraw> <asset mint> <amount> <keypair>
+//!
+//! Sends the transaction with the wallet at `<keypair>` (against `RPC_URL`, mainnet by default)
+//! and writes `tests/fixtures/deposits/<signature>.json` or
+//! `tests/fixtures/redemptions/<signature>.json`. `amount` is the asset amount to deposit or the
+//! CRT to redeem, in base units.
+//!
+//! The fixture's proof is read before sending, so it only describes the state the program saw
+//! when nothing changed in between: the capture fails, writing nothing, if other vault activity
+//! landed or an oracle price moved.
+
+use std::{path::Path, str::FromStr};
+
+use anyhow::{bail, Context};
+use carrot_sdk_v3::{test_utils::ExecutionFixture, CarrotClient, Nav, QuoteSide};
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [side, mint, amount, keypair_path] = args.as_slice() else {
+        bail!("usage: capture_execution <deposit|withdraw> <asset mint> <amount> <keypair>");
+    };
+    let (side, dir) = match side.as_str() {
+        "deposit" => (QuoteSide::Deposit, "deposits"),
+        "withdraw" => (QuoteSide::Withdraw, "redemptions"),
+        other => bail!("unknown side {}", other),
+    };
+    let mint = Pubkey::from_str(mint).context("invalid asset mint")?;
+    let amount: u64 = amount.parse().context("invalid amount")?;
+    let keypair = read_keypair_file(Path::new(keypair_path))
+        .map_err(|e| anyhow::anyhow!("failed to read keypair: {}", e))?;
+    let rpc_url = std::env::var("RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let client = CarrotClient::new(rpc_url);
+
+    let cursor = client.latest_vault_activity_cursor()?.context("vault has no history")?;
+    let (before, proof) = client.fetch_snapshot_with_proof(None)?;
+    let signature = match side {
+        QuoteSide::Deposit => client.deposit(&keypair, &mint, amount)?,
+        QuoteSide::Withdraw => client.withdraw(&keypair, &mint, amount)?,
+    };
+    println!("Sent {}", signature);
+
+    let others: Vec<String> = client
+        .fetch_vault_activity_after(&cursor)?
+        .iter()
+        .filter(|entry| entry.signature != signature)
+        .map(|entry| entry.signature.to_string())
+        .collect();
+    if !others.is_empty() {
+        bail!("other vault activity landed around the capture: {}", others.join(", "));
+    }
+    let (after, _) = client.fetch_snapshot_with_proof(None)?;
+    let prices = |nav: Nav| -> Vec<(Pubkey, i64, i32)> {
+        nav.assets.iter().map(|asset| (asset.mint, asset.price.price, asset.price.exponent)).collect()
+    };
+    if prices(Nav::from_snapshot(&before)?) != prices(Nav::from_snapshot(&after)?) {
+        bail!("an oracle price moved during the capture");
+    }
+
+    let report = client.fetch_execution_report(&signature)?;
+    let [leg] = report.legs.as_slice() else {
+        bail!("expected one leg in {}, found {}", signature, report.legs.len());
+    };
+    let fixture = ExecutionFixture {
+        signature: signature.to_string(),
+        asset_mint: mint.to_string(),
+        block_time: report.block_time.context("the node didn't report the block time")?,
+        shares: leg.shares,
+        asset_amount: leg.asset_amount,
+        proof,
+    };
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", signature));
+    std::fs::write(&path, serde_json::to_string_pretty(&fixture)?)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
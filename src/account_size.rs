@@ -0,0 +1,188 @@
+#[cfg(feature = "blocking")]
+use std::time::Duration;
+
+use crate::{
+    alerts::{Alert, AlertKind, Severity},
+    Vault,
+};
+#[cfg(feature = "blocking")]
+use crate::{
+    client::CarrotClient,
+    error::{CarrotError, Result},
+    events::ClientEvent,
+    supervisor::TaskGroup,
+    VAULT_ADDRESS,
+};
+
+/// Serialized size of one `Asset` entry (id, mint, decimals, ATA, oracle)
+pub const ASSET_ENTRY_SIZE: usize = 2 + 32 + 1 + 32 + 32;
+
+/// Serialized size of one `StrategyRecord` entry (strategy id, asset id, balance, earnings)
+pub const STRATEGY_ENTRY_SIZE: usize = 2 + 2 + 8 + 8;
+
+/// Most an account can grow by in one instruction (`MAX_PERMITTED_DATA_INCREASE`)
+pub const MAX_REALLOC_INCREASE: usize = 10 * 1024;
+
+/// Allocated and used space of the vault account, and its rent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultAccountSize {
+    /// Slot the account was read at
+    pub slot: u64,
+    /// Allocated data length
+    pub data_len: usize,
+    /// Bytes the current vault state serializes to, discriminator included
+    pub used_len: usize,
+    pub lamports: u64,
+    /// Rent-exempt minimum balance for `data_len`
+    pub rent_exempt_minimum: u64,
+}
+
+impl VaultAccountSize {
+    /// Sizes of `vault` stored in an account of `data_len` bytes holding `lamports`
+    pub fn new(slot: u64, vault: &Vault, data_len: usize, lamports: u64, rent_exempt_minimum: u64) -> Self {
+        Self {
+            slot,
+            data_len,
+            used_len: vault.to_account_data().len(),
+            lamports,
+            rent_exempt_minimum,
+        }
+    }
+
+    /// Unused bytes at the end of the account
+    pub fn headroom(&self) -> usize {
+        self.data_len.saturating_sub(self.used_len)
+    }
+
+    /// Assets that can be added without reallocating the account
+    pub fn additional_assets(&self) -> usize {
+        self.headroom() / ASSET_ENTRY_SIZE
+    }
+
+    /// Strategies that can be added without reallocating the account
+    pub fn additional_strategies(&self) -> usize {
+        self.headroom() / STRATEGY_ENTRY_SIZE
+    }
+
+    /// Lamports missing for rent exemption (0 when exempt)
+    pub fn rent_shortfall(&self) -> u64 {
+        self.rent_exempt_minimum.saturating_sub(self.lamports)
+    }
+}
+
+/// Raises an alert when the vault account has room for fewer than `min_assets` more assets,
+/// `Critical` once not even one fits
+///
+/// Each level alerts once, not on every observation while it persists; growing the account
+/// past its allocation takes a realloc of at most `MAX_REALLOC_INCREASE` bytes per instruction.
+#[derive(Debug, Clone)]
+pub struct VaultSizeMonitor {
+    min_assets: usize,
+    previous: Option<Severity>,
+}
+
+impl VaultSizeMonitor {
+    /// Monitor warning below room for `min_assets` more assets
+    pub fn new(min_assets: usize) -> Self {
+        Self {
+            min_assets,
+            previous: None,
+        }
+    }
+
+    /// Check `size` against the threshold
+    pub fn observe(&mut self, size: &VaultAccountSize) -> Vec<Alert> {
+        let additional_assets = size.additional_assets();
+        let severity = match additional_assets {
+            0 => Some(Severity::Critical),
+            n if n < self.min_assets => Some(Severity::Warning),
+            _ => None,
+        };
+        let raised = severity.filter(|severity| self.previous != Some(*severity));
+        self.previous = severity;
+        raised
+            .map(|severity| Alert {
+                severity,
+                slot: size.slot,
+                kind: AlertKind::VaultAccountNearlyFull {
+                    data_len: size.data_len,
+                    used_len: size.used_len,
+                    additional_assets,
+                },
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Data length, used space, rent and asset/strategy headroom of the vault account
+    pub fn fetch_vault_account_size(&self) -> Result<VaultAccountSize> {
+        let response = self
+            .rpc_client()
+            .get_account_with_commitment(&VAULT_ADDRESS, self.rpc_client().commitment())?;
+        let account = response
+            .value
+            .ok_or_else(|| CarrotError::AccountNotFound("Vault account not found".to_string()))?;
+        let vault = Vault::from_account_data(&account.data)?;
+        let rent_exempt_minimum = self.rpc_client().get_minimum_balance_for_rent_exemption(account.data.len())?;
+        Ok(VaultAccountSize::new(
+            response.context.slot,
+            &vault,
+            account.data.len(),
+            account.lamports,
+            rent_exempt_minimum,
+        ))
+    }
+
+    /// Check the vault account's size every `interval` in a supervised task and emit
+    /// `ClientEvent::Alert` when it has room for fewer than `min_assets` more assets
+    pub fn watch_vault_account_size(&self, group: &mut TaskGroup, interval: Duration, min_assets: usize) {
+        let client = self.clone();
+        let mut monitor = VaultSizeMonitor::new(min_assets);
+        group.spawn_periodic("vault-size-monitor", interval, move || {
+            let size = client.fetch_vault_account_size()?;
+            if size.rent_shortfall() > 0 {
+                log::warn!("vault account is {} lamports short of rent exemption", size.rent_shortfall());
+            }
+            for alert in monitor.observe(&size) {
+                log::warn!("{}", alert);
+                client.emit(ClientEvent::Alert(alert));
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::usdc_vault;
+
+    #[test]
+    fn test_headroom_and_alerts() {
+        let vault = usdc_vault().vault;
+        let used_len = vault.to_account_data().len();
+        let size = |data_len| VaultAccountSize::new(7, &vault, data_len, 1_000, 2_000);
+
+        let roomy = size(used_len + 3 * ASSET_ENTRY_SIZE + 10);
+        assert_eq!(roomy.headroom(), 3 * ASSET_ENTRY_SIZE + 10);
+        assert_eq!(roomy.additional_assets(), 3);
+        assert_eq!(roomy.additional_strategies(), (3 * ASSET_ENTRY_SIZE + 10) / STRATEGY_ENTRY_SIZE);
+        assert_eq!(roomy.rent_shortfall(), 1_000);
+
+        let mut monitor = VaultSizeMonitor::new(2);
+        assert!(monitor.observe(&roomy).is_empty());
+        let tight = size(used_len + ASSET_ENTRY_SIZE);
+        assert_eq!(monitor.observe(&tight)[0].severity, Severity::Warning);
+        assert!(monitor.observe(&tight).is_empty());
+        let full = size(used_len);
+        let alerts = monitor.observe(&full);
+        assert_eq!(alerts[0].severity, Severity::Critical);
+        assert!(alerts[0].to_string().contains("room for 0 more assets"));
+        // Recovering and filling up again alerts again
+        assert!(monitor.observe(&roomy).is_empty());
+        assert_eq!(monitor.observe(&full).len(), 1);
+    }
+}
@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solana_transaction_status_client_types::UiTransactionTokenBalance;
+
+use crate::{
+    error::{CarrotError, Result},
+    instructions::CarrotInstruction,
+    nav::CRT_DECIMALS,
+    quote::QuoteSide,
+    CRT_MINT,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, history::decode_history_entries};
+
+/// Symbol CRT is booked under
+const CRT_SYMBOL: &str = "CRT";
+
+/// One deposit or withdrawal within an executed transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionLeg {
+    pub side: QuoteSide,
+    pub asset_mint: Pubkey,
+    /// Symbol the asset is booked under (e.g. `USDC`)
+    pub asset_symbol: String,
+    pub asset_decimals: u8,
+    /// Asset spent (deposit) or received net of the redemption fee (withdrawal)
+    pub asset_amount: u64,
+    /// CRT minted (deposit) or burned (withdrawal)
+    pub shares: u64,
+    /// Redemption fee withheld, in asset units (0 for deposits)
+    pub fee: u64,
+}
+
+/// What an executed Carrot transaction did for its signer, read from its token balance changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionReport {
+    pub signature: Signature,
+    pub slot: u64,
+    /// Unix timestamp of the block, if the node reports it
+    pub block_time: Option<i64>,
+    pub user: Pubkey,
+    /// Transaction fee paid by the fee payer, in lamports
+    pub network_fee: u64,
+    pub legs: Vec<ExecutionLeg>,
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Report of the deposits and withdrawals executed by the transaction `signature`
+    ///
+    /// Amounts are the signer's actual balance changes. The redemption fee isn't visible in
+    /// them, so it is derived from the vault's current redemption fee rate.
+    pub fn fetch_execution_report(&self, signature: &Signature) -> Result<ExecutionReport> {
+        let fetched = self.fetch_carrot_transaction(signature)?;
+        let meta = fetched
+            .confirmed
+            .transaction
+            .meta
+            .as_ref()
+            .ok_or_else(|| CarrotError::TransactionFailed(format!("no status meta for {}", signature)))?;
+        if meta.err.is_some() {
+            return Err(CarrotError::TransactionFailed(format!("{} failed on-chain", signature)));
+        }
+        let instructions: Vec<CarrotInstruction> =
+            decode_history_entries(&fetched.transaction, &fetched.loaded_addresses, None)
+                .into_iter()
+                .map(|(_, instruction)| instruction)
+                .collect();
+        let user = match instructions.first() {
+            Some(CarrotInstruction::Issue { user, .. } | CarrotInstruction::Redeem { user, .. }) => *user,
+            None => return Err(CarrotError::TransactionFailed(format!("no Carrot instruction in {}", signature))),
+        };
+
+        let pre: Option<&Vec<UiTransactionTokenBalance>> = meta.pre_token_balances.as_ref().into();
+        let post: Option<&Vec<UiTransactionTokenBalance>> = meta.post_token_balances.as_ref().into();
+        let deltas = token_deltas(&user, pre.map_or(&[], Vec::as_slice), post.map_or(&[], Vec::as_slice))?;
+        let redemption_fee_bps = self.fetch_vault()?.fee.redemption_fee_bps;
+        let legs = build_legs(&instructions, &deltas, redemption_fee_bps, |mint| {
+            Ok(self.resolve_mint_metadata(mint)?.symbol)
+        })?;
+
+        Ok(ExecutionReport {
+            signature: *signature,
+            slot: fetched.confirmed.slot,
+            block_time: fetched.confirmed.block_time,
+            user,
+            network_fee: meta.fee,
+            legs,
+        })
+    }
+}
+
+/// Net change and decimals of `owner`'s balance of every mint, across all its token accounts
+fn token_deltas(
+    owner: &Pubkey,
+    pre: &[UiTransactionTokenBalance],
+    post: &[UiTransactionTokenBalance],
+) -> Result<HashMap<Pubkey, (i128, u8)>> {
+    let mut deltas: HashMap<Pubkey, (i128, u8)> = HashMap::new();
+    let owner = owner.to_string();
+    for (balances, sign) in [(pre, -1), (post, 1)] {
+        for balance in balances {
+            if Option::<&String>::from(balance.owner.as_ref()) != Some(&owner) {
+                continue;
+            }
+            let invalid = || CarrotError::TransactionFailed(format!("invalid token balance of {}", balance.mint));
+            let mint: Pubkey = balance.mint.parse().map_err(|_| invalid())?;
+            let amount: u64 = balance.ui_token_amount.amount.parse().map_err(|_| invalid())?;
+            let entry = deltas.entry(mint).or_insert((0, balance.ui_token_amount.decimals));
+            entry.0 += sign * amount as i128;
+        }
+    }
+    Ok(deltas)
+}
+
+/// Pair each instruction with the balance changes it caused
+///
+/// Withdrawals burn exactly the instruction's CRT amount, and deposits spend exactly its asset
+/// amount; the other side comes from the balance deltas, which must not be shared ambiguously.
+fn build_legs<F>(
+    instructions: &[CarrotInstruction],
+    deltas: &HashMap<Pubkey, (i128, u8)>,
+    redemption_fee_bps: u16,
+    mut symbol: F,
+) -> Result<Vec<ExecutionLeg>>
+where
+    F: FnMut(&Pubkey) -> Result<String>,
+{
+    let ambiguous = |what: &str| CarrotError::TransactionFailed(format!("can't attribute {} to one instruction", what));
+    let delta = |mint: &Pubkey| deltas.get(mint).copied().unwrap_or((0, 0));
+    let count = |mint: &Pubkey| {
+        instructions
+            .iter()
+            .filter(|ix| match ix {
+                CarrotInstruction::Issue { asset_mint, .. } | CarrotInstruction::Redeem { asset_mint, .. } => {
+                    asset_mint == mint
+                }
+            })
+            .count()
+    };
+    let issues = instructions.iter().filter(|ix| matches!(ix, CarrotInstruction::Issue { .. })).count();
+    let burned: u64 = instructions
+        .iter()
+        .map(|ix| match ix {
+            CarrotInstruction::Redeem { amount, .. } => *amount,
+            CarrotInstruction::Issue { .. } => 0,
+        })
+        .sum();
+
+    instructions
+        .iter()
+        .map(|instruction| {
+            let (side, asset_mint, instruction_amount) = match instruction {
+                CarrotInstruction::Issue { asset_mint, amount, .. } => (QuoteSide::Deposit, *asset_mint, *amount),
+                CarrotInstruction::Redeem { asset_mint, amount, .. } => (QuoteSide::Withdraw, *asset_mint, *amount),
+            };
+            if count(&asset_mint) > 1 {
+                return Err(ambiguous(&format!("the {} balance change", asset_mint)));
+            }
+            let (asset_delta, decimals) = delta(&asset_mint);
+            let to_u64 = |value: i128| u64::try_from(value).map_err(|_| CarrotError::MathOverflow);
+            let (asset_amount, shares, fee) = match side {
+                QuoteSide::Deposit => {
+                    if issues > 1 {
+                        return Err(ambiguous("the CRT minted"));
+                    }
+                    // CRT delta nets the mint against any CRT burned in the same transaction
+                    let minted = to_u64(delta(&CRT_MINT).0 + burned as i128)?;
+                    (instruction_amount, minted, 0)
+                }
+                QuoteSide::Withdraw => {
+                    let received = to_u64(asset_delta)?;
+                    (received, instruction_amount, redemption_fee(received, redemption_fee_bps)?)
+                }
+            };
+            Ok(ExecutionLeg {
+                side,
+                asset_mint,
+                asset_symbol: symbol(&asset_mint)?,
+                asset_decimals: decimals,
+                asset_amount,
+                shares,
+                fee,
+            })
+        })
+        .collect()
+}
+
+/// Fee withheld from a redemption that paid out `net`: `gross * bps / 10_000` with `net = gross - fee`
+fn redemption_fee(net: u64, fee_bps: u16) -> Result<u64> {
+    if fee_bps >= 10_000 {
+        return Err(CarrotError::InvalidVaultData);
+    }
+    let gross = net as u128 * 10_000 / (10_000 - fee_bps as u128);
+    u64::try_from(gross - net as u128).map_err(|_| CarrotError::MathOverflow)
+}
+
+/// Side of a journal line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntrySide {
+    Debit,
+    Credit,
+}
+
+/// Quantity of a token, in base units with its decimals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount {
+    pub units: u64,
+    pub decimals: u8,
+    pub commodity: String,
+}
+
+impl Amount {
+    fn new(units: u64, decimals: u8, commodity: &str) -> Self {
+        Self {
+            units,
+            decimals,
+            commodity: commodity.to_string(),
+        }
+    }
+
+    /// Decimal quantity without the commodity, e.g. `1.500000`
+    pub fn quantity(&self) -> String {
+        let scale = 10u64.pow(self.decimals as u32);
+        if self.decimals == 0 {
+            return self.units.to_string();
+        }
+        format!("{}.{:0width$}", self.units / scale, self.units % scale, width = self.decimals as usize)
+    }
+}
+
+/// One posting of a journal entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalLine {
+    pub account: String,
+    pub side: EntrySide,
+    pub amount: Amount,
+    /// Asset value CRT is booked at: cost on deposit, redemption value on withdrawal
+    pub cost: Option<Amount>,
+}
+
+/// Balanced double-entry record of one deposit or withdrawal, or of a network fee
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix timestamp of the block, if known
+    pub timestamp: Option<i64>,
+    /// Transaction signature
+    pub reference: String,
+    pub description: String,
+    pub lines: Vec<JournalLine>,
+}
+
+/// Ledger accounts a `Journal` posts to
+///
+/// Wallet accounts get the token symbol appended, e.g. `Assets:Wallet:USDC`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartOfAccounts {
+    /// Prefix of the wallet's token holdings
+    pub wallet: String,
+    /// CRT position, carried at cost
+    pub investment: String,
+    pub redemption_fees: String,
+    pub network_fees: String,
+}
+
+impl Default for ChartOfAccounts {
+    fn default() -> Self {
+        Self {
+            wallet: "Assets:Wallet".to_string(),
+            investment: "Assets:Investments:Carrot".to_string(),
+            redemption_fees: "Expenses:Fees:Carrot Redemption".to_string(),
+            network_fees: "Expenses:Fees:Solana".to_string(),
+        }
+    }
+}
+
+/// Double-entry records of executed deposits and withdrawals, for accounting system import
+///
+/// A deposit debits CRT at cost (the asset spent) and credits the asset. A withdrawal debits the
+/// asset received and the redemption fee, and credits the CRT burned at its redemption value;
+/// relieving cost lots and booking the realized gain is left to the accounting system.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    accounts: ChartOfAccounts,
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new(accounts: ChartOfAccounts) -> Self {
+        Self {
+            accounts,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// Book every leg of `report`, then its network fee
+    pub fn record(&mut self, report: &ExecutionReport) {
+        let accounts = &self.accounts;
+        let entry = |description: String, lines| JournalEntry {
+            timestamp: report.block_time,
+            reference: report.signature.to_string(),
+            description,
+            lines,
+        };
+        let line = |account: &str, side, amount, cost| JournalLine {
+            account: account.to_string(),
+            side,
+            amount,
+            cost,
+        };
+
+        for leg in &report.legs {
+            let wallet = format!("{}:{}", accounts.wallet, leg.asset_symbol);
+            let asset = |units| Amount::new(units, leg.asset_decimals, &leg.asset_symbol);
+            let shares = Amount::new(leg.shares, CRT_DECIMALS, CRT_SYMBOL);
+            self.entries.push(match leg.side {
+                QuoteSide::Deposit => entry(
+                    format!("Carrot deposit {} {}", asset(leg.asset_amount).quantity(), leg.asset_symbol),
+                    vec![
+                        line(&accounts.investment, EntrySide::Debit, shares, Some(asset(leg.asset_amount))),
+                        line(&wallet, EntrySide::Credit, asset(leg.asset_amount), None),
+                    ],
+                ),
+                QuoteSide::Withdraw => {
+                    let mut lines = vec![line(&wallet, EntrySide::Debit, asset(leg.asset_amount), None)];
+                    if leg.fee > 0 {
+                        lines.push(line(&accounts.redemption_fees, EntrySide::Debit, asset(leg.fee), None));
+                    }
+                    let value = asset(leg.asset_amount + leg.fee);
+                    lines.push(line(&accounts.investment, EntrySide::Credit, shares.clone(), Some(value)));
+                    entry(format!("Carrot withdrawal {} CRT", shares.quantity()), lines)
+                }
+            });
+        }
+
+        if report.network_fee > 0 {
+            let sol = || Amount::new(report.network_fee, 9, "SOL");
+            self.entries.push(entry(
+                "Solana network fee".to_string(),
+                vec![
+                    line(&accounts.network_fees, EntrySide::Debit, sol(), None),
+                    line(&format!("{}:SOL", accounts.wallet), EntrySide::Credit, sol(), None),
+                ],
+            ));
+        }
+    }
+
+    /// One row per line: date, reference, description, account, debit, credit, commodity, cost
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("date,reference,description,account,debit,credit,commodity,cost,cost_commodity\n");
+        for entry in &self.entries {
+            for line in &entry.lines {
+                let quantity = line.amount.quantity();
+                let (debit, credit) = match line.side {
+                    EntrySide::Debit => (quantity.as_str(), ""),
+                    EntrySide::Credit => ("", quantity.as_str()),
+                };
+                let (cost, cost_commodity) = match &line.cost {
+                    Some(cost) => (cost.quantity(), cost.commodity.as_str()),
+                    None => (String::new(), ""),
+                };
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{}",
+                    date(entry.timestamp),
+                    entry.reference,
+                    csv_field(&entry.description),
+                    csv_field(&line.account),
+                    debit,
+                    credit,
+                    csv_field(&line.amount.commodity),
+                    cost,
+                    csv_field(cost_commodity)
+                );
+            }
+        }
+        out
+    }
+
+    /// The entries as a JSON array
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.entries)
+            .map_err(|e| CarrotError::InvalidConfig(format!("failed to encode journal: {}", e)))
+    }
+
+    /// Plain-text journal for ledger-cli and hledger, with CRT at its total cost (`@@`)
+    pub fn to_ledger(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            let _ = writeln!(out, "{} {}  ; {}", date(entry.timestamp), entry.description, entry.reference);
+            for line in &entry.lines {
+                let sign = match line.side {
+                    EntrySide::Debit => "",
+                    EntrySide::Credit => "-",
+                };
+                let _ = write!(
+                    out,
+                    "    {}  {}{} {}",
+                    line.account,
+                    sign,
+                    line.amount.quantity(),
+                    commodity(&line.amount.commodity)
+                );
+                if let Some(cost) = &line.cost {
+                    let _ = write!(out, " @@ {} {}", cost.quantity(), commodity(&cost.commodity));
+                }
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// `YYYY-MM-DD` (UTC) of a Unix timestamp; `1970-01-01` when unknown
+fn date(timestamp: Option<i64>) -> String {
+    // Days to civil date (Howard Hinnant's algorithm)
+    let days = timestamp.unwrap_or(0).div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Quote a CSV field containing a separator or quote
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Ledger commodity, quoted unless purely alphabetic
+fn commodity(symbol: &str) -> String {
+    if symbol.chars().all(|c| c.is_ascii_alphabetic()) {
+        symbol.to_string()
+    } else {
+        format!("\"{}\"", symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{USDC_MINT, USDT_MINT};
+
+    fn report(legs: Vec<ExecutionLeg>) -> ExecutionReport {
+        ExecutionReport {
+            signature: Signature::default(),
+            slot: 1,
+            block_time: Some(1_735_689_600),
+            user: Pubkey::new_unique(),
+            network_fee: 5_000,
+            legs,
+        }
+    }
+
+    #[test]
+    fn test_legs_from_balance_changes() {
+        let user = Pubkey::new_unique();
+        let instructions = [
+            CarrotInstruction::Issue {
+                user,
+                asset_mint: USDC_MINT,
+                amount: 1_000_000,
+            },
+            CarrotInstruction::Redeem {
+                user,
+                asset_mint: USDT_MINT,
+                amount: 400_000_000,
+            },
+        ];
+        // 900 CRT minted, 400 burned: the CRT account grew by 500
+        let deltas = HashMap::from([
+            (USDC_MINT, (-1_000_000, 6)),
+            (USDT_MINT, (399_600, 6)),
+            (CRT_MINT, (500_000_000, 9)),
+        ]);
+        let legs = build_legs(&instructions, &deltas, 10, |_| Ok("USD".to_string())).unwrap();
+        assert_eq!((legs[0].asset_amount, legs[0].shares, legs[0].fee), (1_000_000, 900_000_000, 0));
+        assert_eq!((legs[1].asset_amount, legs[1].shares, legs[1].fee), (399_600, 400_000_000, 400));
+
+        let two_deposits = [instructions[0].clone(), instructions[0].clone()];
+        assert!(build_legs(&two_deposits, &deltas, 10, |_| Ok(String::new())).is_err());
+    }
+
+    #[test]
+    fn test_journal_entries_balance() {
+        let mut journal = Journal::default();
+        journal.record(&report(vec![
+            ExecutionLeg {
+                side: QuoteSide::Deposit,
+                asset_mint: USDC_MINT,
+                asset_symbol: "USDC".to_string(),
+                asset_decimals: 6,
+                asset_amount: 1_000_000,
+                shares: 950_000_000,
+                fee: 0,
+            },
+            ExecutionLeg {
+                side: QuoteSide::Withdraw,
+                asset_mint: USDC_MINT,
+                asset_symbol: "USDC".to_string(),
+                asset_decimals: 6,
+                asset_amount: 999_000,
+                shares: 950_000_000,
+                fee: 1_000,
+            },
+        ]));
+        assert_eq!(journal.entries().len(), 3);
+
+        // Each entry balances in the asset the CRT is valued in
+        for entry in &journal.entries()[..2] {
+            let value = |side| -> u64 {
+                entry
+                    .lines
+                    .iter()
+                    .filter(|l| l.side == side)
+                    .map(|l| l.cost.as_ref().unwrap_or(&l.amount).units)
+                    .sum()
+            };
+            assert_eq!(value(EntrySide::Debit), value(EntrySide::Credit));
+        }
+
+        assert_eq!(
+            journal.to_ledger().lines().take(4).collect::<Vec<_>>(),
+            [
+                "2025-01-01 Carrot deposit 1.000000 USDC  ; 1111111111111111111111111111111111111111111111111111111111111111",
+                "    Assets:Investments:Carrot  0.950000000 CRT @@ 1.000000 USDC",
+                "    Assets:Wallet:USDC  -1.000000 USDC",
+                "",
+            ]
+        );
+        let csv = journal.to_csv();
+        assert!(csv.contains(",Expenses:Fees:Carrot Redemption,0.001000,,USDC,,\n"));
+        let entries: Vec<JournalEntry> = serde_json::from_str(&journal.to_json().unwrap()).unwrap();
+        assert_eq!(entries, journal.entries());
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(Some(0)), "1970-01-01");
+        assert_eq!(date(Some(951_782_400)), "2000-02-29");
+        assert_eq!(date(Some(1_735_689_599)), "2024-12-31");
+    }
+}
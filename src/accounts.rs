@@ -1,6 +1,13 @@
+use std::fmt;
+
 use solana_sdk::pubkey::Pubkey;
-use spl_associated_token_account::{get_associated_token_address, get_associated_token_address_with_program_id};
-use crate::{CARROT_PROGRAM_ID, CRT_MINT, VAULT_ADDRESS, PYUSD_MINT};
+use spl_associated_token_account::get_associated_token_address;
+use crate::{
+    error::Result,
+    instructions::{IssueAccounts, ISSUE_REDEEM_ACCOUNT_NAMES},
+    token::TokenProgram,
+    VaultHandle, CARROT_PROGRAM_ID, CRT_MINT, VAULT_ADDRESS, PYUSD_MINT,
+};
 
 /// Derive the vault PDA address
 /// Seeds: ["vault", shares_mint]
@@ -20,11 +27,7 @@ pub fn is_token_2022_mint(mint: &Pubkey) -> bool {
 /// Get the token program ID for a given mint
 /// Returns Token-2022 for pyUSD and CRT, standard Token for USDC/USDT
 pub fn get_token_program_id(mint: &Pubkey) -> Pubkey {
-    if is_token_2022_mint(mint) || mint == &CRT_MINT {
-        spl_token_2022_interface::id()
-    } else {
-        spl_token::id()
-    }
+    TokenProgram::for_mint(mint).id()
 }
 
 /// Get the associated token account address for a wallet and mint
@@ -34,21 +37,187 @@ pub fn get_ata_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
 
 /// Get user's CRT token account address (uses Token-2022 program)
 pub fn get_user_crt_ata(user: &Pubkey) -> Pubkey {
-    get_associated_token_address_with_program_id(user, &CRT_MINT, &spl_token_2022_interface::id())
+    TokenProgram::Token2022.associated_token_address(user, &CRT_MINT)
 }
 
 /// Get user's asset token account address (USDC, USDT, pyUSD)
 /// Uses Token-2022 program for pyUSD, standard Token program for USDC/USDT
+/// (assets added to the vault later: see `VaultHandle::asset_token_program`)
 pub fn get_user_asset_ata(user: &Pubkey, asset_mint: &Pubkey) -> Pubkey {
-    let token_program = get_token_program_id(asset_mint);
-    get_associated_token_address_with_program_id(user, asset_mint, &token_program)
+    TokenProgram::for_mint(asset_mint).associated_token_address(user, asset_mint)
 }
 
 /// Get vault's asset token account address
 /// Uses Token-2022 program for pyUSD, standard Token program for USDC/USDT
 pub fn get_vault_asset_ata(asset_mint: &Pubkey) -> Pubkey {
-    let token_program = get_token_program_id(asset_mint);
-    get_associated_token_address_with_program_id(&VAULT_ADDRESS, asset_mint, &token_program)
+    TokenProgram::for_mint(asset_mint).associated_token_address(&VAULT_ADDRESS, asset_mint)
+}
+
+/// Find the associated token account of `owner` for `mint`, with its bump
+/// Seeds: [owner, token_program, mint] under the associated token account program
+pub fn find_associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: TokenProgram) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.id().as_ref(), mint.as_ref()],
+        &spl_associated_token_account::id(),
+    )
+}
+
+/// `get_user_crt_ata` with its bump
+/// Seeds: [user, Token-2022 program, CRT mint] under the associated token account program
+pub fn find_user_crt_ata(user: &Pubkey) -> (Pubkey, u8) {
+    find_associated_token_address(user, &CRT_MINT, TokenProgram::Token2022)
+}
+
+/// `get_user_asset_ata` with its bump
+/// Seeds: [user, asset token program, asset mint] under the associated token account program
+pub fn find_user_asset_ata(user: &Pubkey, asset_mint: &Pubkey) -> (Pubkey, u8) {
+    find_associated_token_address(user, asset_mint, TokenProgram::for_mint(asset_mint))
+}
+
+/// `get_vault_asset_ata` with its bump
+/// Seeds: [vault, asset token program, asset mint] under the associated token account program
+pub fn find_vault_asset_ata(asset_mint: &Pubkey) -> (Pubkey, u8) {
+    find_associated_token_address(&VAULT_ADDRESS, asset_mint, TokenProgram::for_mint(asset_mint))
+}
+
+/// Where an address in a `derivations_report` comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressSource {
+    /// Program-derived address of `program_id` from `seeds` and `bump`
+    Pda {
+        program_id: Pubkey,
+        seeds: Vec<Vec<u8>>,
+        bump: u8,
+    },
+    /// Supplied by the caller (the user, the asset being moved)
+    Input,
+    /// Fixed program or mint address
+    Constant,
+    /// Stored in the vault account
+    VaultState,
+}
+
+/// An address the SDK touches, with its role and how it's obtained
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Derivation {
+    /// Account role (as in `ISSUE_REDEEM_ACCOUNT_NAMES`) or program name
+    pub role: String,
+    pub address: Pubkey,
+    pub source: AddressSource,
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<24} {:<44} ", self.role, self.address)?;
+        match &self.source {
+            AddressSource::Pda { program_id, seeds, bump } => {
+                let seeds: Vec<String> = seeds.iter().map(|seed| format_seed(seed)).collect();
+                write!(f, "PDA of {} seeds [{}] bump {}", program_id, seeds.join(", "), bump)
+            }
+            AddressSource::Input => write!(f, "input"),
+            AddressSource::Constant => write!(f, "constant"),
+            AddressSource::VaultState => write!(f, "vault state"),
+        }
+    }
+}
+
+/// Every address a deposit or withdrawal of `asset_mint` by `user` touches (both use the same
+/// accounts), with PDA seeds and bumps, including the ATA and compute budget programs of the
+/// transaction around the Carrot instruction
+///
+/// Derived addresses are recomputed from their seeds; an address the vault stores that doesn't
+/// match its derivation is reported as `VaultState`. Carrot itself has no strategy PDAs that
+/// deposits or withdrawals touch.
+pub fn derivations_report(vault: &VaultHandle, user: &Pubkey, asset_mint: &Pubkey) -> Result<Vec<Derivation>> {
+    let accounts = IssueAccounts::new(vault, user, asset_mint)?;
+    let asset_program = vault.asset_token_program(asset_mint)?;
+
+    let (vault_pda, vault_bump) = derive_vault_address(&accounts.shares_mint);
+    let vault_source = if vault_pda == accounts.vault {
+        AddressSource::Pda {
+            program_id: CARROT_PROGRAM_ID,
+            seeds: vec![b"vault".to_vec(), accounts.shares_mint.to_bytes().to_vec()],
+            bump: vault_bump,
+        }
+    } else {
+        AddressSource::Input
+    };
+    let ata_source = |owner: &Pubkey, mint: &Pubkey, program: TokenProgram, address: &Pubkey| {
+        let (derived, bump) = find_associated_token_address(owner, mint, program);
+        if &derived != address {
+            return AddressSource::VaultState;
+        }
+        AddressSource::Pda {
+            program_id: spl_associated_token_account::id(),
+            seeds: vec![owner.to_bytes().to_vec(), program.id().to_bytes().to_vec(), mint.to_bytes().to_vec()],
+            bump,
+        }
+    };
+
+    let sources = [
+        vault_source,
+        AddressSource::VaultState,
+        ata_source(user, &accounts.shares_mint, TokenProgram::Token2022, &accounts.user_shares_ata),
+        AddressSource::Input,
+        ata_source(&accounts.vault, asset_mint, asset_program, &accounts.vault_asset_ata),
+        ata_source(user, asset_mint, asset_program, &accounts.user_asset_ata),
+        AddressSource::Input,
+        AddressSource::Constant,
+        AddressSource::Constant,
+        AddressSource::Constant,
+        AddressSource::Constant,
+    ];
+    let fixed = [
+        accounts.vault,
+        accounts.shares_mint,
+        accounts.user_shares_ata,
+        accounts.asset_mint,
+        accounts.vault_asset_ata,
+        accounts.user_asset_ata,
+        accounts.user,
+        accounts.system_program,
+        accounts.asset_token_program,
+        accounts.shares_token_program,
+        accounts.log_program,
+    ];
+
+    let mut report: Vec<Derivation> = ISSUE_REDEEM_ACCOUNT_NAMES
+        .iter()
+        .zip(fixed)
+        .zip(sources)
+        .map(|((role, address), source)| Derivation {
+            role: role.to_string(),
+            address,
+            source,
+        })
+        .collect();
+    report.extend(accounts.remaining_accounts.iter().enumerate().map(|(index, address)| Derivation {
+        role: format!("remaining_account[{}]", index),
+        address: *address,
+        source: AddressSource::VaultState,
+    }));
+    for (role, address) in [
+        ("associated_token_program", spl_associated_token_account::id()),
+        ("compute_budget_program", solana_sdk_ids::compute_budget::id()),
+    ] {
+        report.push(Derivation {
+            role: role.to_string(),
+            address,
+            source: AddressSource::Constant,
+        });
+    }
+    Ok(report)
+}
+
+/// Seeds shown as pubkeys when 32 bytes long, as strings when printable, hex otherwise
+fn format_seed(seed: &[u8]) -> String {
+    if let Ok(bytes) = <[u8; 32]>::try_from(seed) {
+        return Pubkey::new_from_array(bytes).to_string();
+    }
+    match std::str::from_utf8(seed) {
+        Ok(text) if text.chars().all(|c| c.is_ascii_graphic()) => format!("{:?}", text),
+        _ => seed.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
 }
 
 #[cfg(test)]
@@ -72,4 +241,27 @@ mod tests {
         let expected = Pubkey::from_str("DQQ7otzQpMZmZE4RAdicJnAQbacFAwoxz4XzhUtPK7u9").unwrap();
         assert_eq!(crt_ata, expected);
     }
+
+    #[test]
+    fn test_find_helpers_match_getters() {
+        let user = Pubkey::new_unique();
+        assert_eq!(find_user_crt_ata(&user).0, get_user_crt_ata(&user));
+        assert_eq!(find_user_asset_ata(&user, &PYUSD_MINT).0, get_user_asset_ata(&user, &PYUSD_MINT));
+        assert_eq!(find_vault_asset_ata(&PYUSD_MINT).0, get_vault_asset_ata(&PYUSD_MINT));
+
+        let (address, bump) = find_user_crt_ata(&user);
+        let token_program = TokenProgram::Token2022.id();
+        let seeds: [&[u8]; 4] = [user.as_ref(), token_program.as_ref(), CRT_MINT.as_ref(), &[bump]];
+        assert_eq!(
+            Pubkey::create_program_address(&seeds, &spl_associated_token_account::id()).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn test_format_seed() {
+        assert_eq!(format_seed(b"vault"), "\"vault\"");
+        assert_eq!(format_seed(CRT_MINT.as_ref()), CRT_MINT.to_string());
+        assert_eq!(format_seed(&[0, 255]), "00ff");
+    }
 }
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "blocking")]
+use std::sync::mpsc::RecvTimeoutError;
+
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "blocking")]
+use serde_json::json;
+
+use crate::{digest::PositionDigest, error::Result, snapshot::VaultSnapshot, Fee};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, error::CarrotError, events::ClientEvent, supervisor::TaskGroup};
+
+/// Timeout of a webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the alert forwarder checks for shutdown while no alerts arrive
+const FORWARD_POLL: Duration = Duration::from_millis(500);
+
+/// How urgently an alert needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+/// What an alert is about; values are USD in `VALUE_DECIMALS` unless noted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertKind {
+    /// Vault value fell from its peak within the detection window
+    TvlDrop { peak: u128, current: u128, drop_bps: u64 },
+    /// The value of one CRT jumped between two consecutive samples
+    SharePriceDiscontinuity { previous: u128, current: u128, change_bps: u64 },
+    /// A strategy's balance (in asset units) was almost entirely written off
+    StrategyWiped { strategy_id: u16, previous: u64, current: u64 },
+    /// The vault authority paused deposits and withdrawals
+    VaultPaused,
+    /// The vault was unpaused
+    VaultUnpaused,
+    /// A fee rate changed (in bps)
+    FeeChanged { fee: FeeKind, previous: u16, current: u16 },
+    /// The vault authority differs from the pinned (or previously seen) one
+    VaultAuthorityChanged { expected: Pubkey, current: Pubkey },
+    /// The program's upgrade authority differs from the pinned (or previously seen) one;
+    /// `None` means the program is immutable
+    UpgradeAuthorityChanged { expected: Option<Pubkey>, current: Option<Pubkey> },
+    /// The vault account has little room left before it must be reallocated
+    VaultAccountNearlyFull { data_len: usize, used_len: usize, additional_assets: usize },
+    /// Scheduled summary of a wallet's position, sent by `spawn_position_digests`
+    PositionDigest(PositionDigest),
+}
+
+/// One of the vault's fee rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeKind {
+    Redemption,
+    Management,
+    Performance,
+}
+
+impl fmt::Display for FeeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FeeKind::Redemption => "redemption",
+            FeeKind::Management => "management",
+            FeeKind::Performance => "performance",
+        })
+    }
+}
+
+/// Notification raised by the SDK's monitors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub severity: Severity,
+    /// Slot of the state that triggered the alert
+    pub slot: u64,
+    pub kind: AlertKind,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] slot {}: ", self.severity, self.slot)?;
+        match &self.kind {
+            AlertKind::TvlDrop { peak, current, drop_bps } => write!(
+                f,
+                "vault value dropped {:.2}% (${} -> ${})",
+                *drop_bps as f64 / 100.0,
+                usd(*peak),
+                usd(*current)
+            ),
+            AlertKind::SharePriceDiscontinuity { previous, current, change_bps } => write!(
+                f,
+                "CRT price {} {:.2}% (${} -> ${})",
+                if current < previous { "fell" } else { "rose" },
+                *change_bps as f64 / 100.0,
+                usd(*previous),
+                usd(*current)
+            ),
+            AlertKind::StrategyWiped { strategy_id, previous, current } => write!(
+                f,
+                "strategy {} balance fell from {} to {}",
+                strategy_id, previous, current
+            ),
+            AlertKind::VaultPaused => write!(f, "vault paused"),
+            AlertKind::VaultUnpaused => write!(f, "vault unpaused"),
+            AlertKind::FeeChanged { fee, previous, current } => write!(
+                f,
+                "{} fee {} from {} to {} bps",
+                fee,
+                if current > previous { "raised" } else { "lowered" },
+                previous,
+                current
+            ),
+            AlertKind::VaultAuthorityChanged { expected, current } => {
+                write!(f, "vault authority changed from {} to {}", expected, current)
+            }
+            AlertKind::UpgradeAuthorityChanged { expected, current } => write!(
+                f,
+                "program upgrade authority changed from {} to {}",
+                upgrade_authority(expected),
+                upgrade_authority(current)
+            ),
+            AlertKind::VaultAccountNearlyFull { data_len, used_len, additional_assets } => write!(
+                f,
+                "vault account uses {} of {} bytes, room for {} more assets before a realloc",
+                used_len, data_len, additional_assets
+            ),
+            AlertKind::PositionDigest(digest) => write!(f, "{}", digest),
+        }
+    }
+}
+
+/// Upgrade authority, or `none` for an immutable program
+fn upgrade_authority(authority: &Option<Pubkey>) -> String {
+    authority.map_or_else(|| "none (immutable)".to_string(), |authority| authority.to_string())
+}
+
+/// Raises alerts when the vault's configuration changes between snapshots
+///
+/// Pausing is `Critical` and a fee increase a `Warning`; unpausing and fee cuts are `Info`.
+#[derive(Debug, Clone, Default)]
+pub struct VaultChangeDetector {
+    previous: Option<(bool, Fee)>,
+}
+
+impl VaultChangeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare `snapshot` with the previous one observed
+    pub fn observe(&mut self, snapshot: &VaultSnapshot) -> Vec<Alert> {
+        let vault = &snapshot.vault;
+        let mut alerts = Vec::new();
+        let mut alert = |severity, kind| alerts.push(Alert { severity, slot: snapshot.slot, kind });
+
+        if let Some((was_paused, previous_fee)) = &self.previous {
+            match (was_paused, vault.paused) {
+                (false, true) => alert(Severity::Critical, AlertKind::VaultPaused),
+                (true, false) => alert(Severity::Info, AlertKind::VaultUnpaused),
+                _ => {}
+            }
+            let fees = [
+                (FeeKind::Redemption, previous_fee.redemption_fee_bps, vault.fee.redemption_fee_bps),
+                (FeeKind::Management, previous_fee.management_fee_bps, vault.fee.management_fee_bps),
+                (FeeKind::Performance, previous_fee.performance_fee_bps, vault.fee.performance_fee_bps),
+            ];
+            for (fee, previous, current) in fees {
+                if current != previous {
+                    let severity = if current > previous { Severity::Warning } else { Severity::Info };
+                    alert(severity, AlertKind::FeeChanged { fee, previous, current });
+                }
+            }
+        }
+        self.previous = Some((vault.paused, vault.fee.clone()));
+        alerts
+    }
+}
+
+/// Destination alerts are delivered to
+pub trait AlertSink: Send {
+    fn send(&mut self, alert: &Alert) -> Result<()>;
+}
+
+/// Chat service a `WebhookSink` posts to
+#[cfg(feature = "blocking")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookTarget {
+    /// Slack incoming webhook URL
+    Slack { webhook_url: String },
+    /// Discord channel webhook URL
+    Discord { webhook_url: String },
+    /// Telegram bot token and the chat it posts to
+    Telegram { bot_token: String, chat_id: String },
+}
+
+/// Posts alerts to a Slack, Discord or Telegram channel
+#[cfg(feature = "blocking")]
+pub struct WebhookSink {
+    target: WebhookTarget,
+    min_severity: Severity,
+    http: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "blocking")]
+impl WebhookSink {
+    /// Sink posting alerts of every severity to `target`
+    pub fn new(target: WebhookTarget) -> Result<Self> {
+        let http = reqwest::blocking::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| CarrotError::InvalidConfig(format!("failed to build HTTP client: {}", e)))?;
+        Ok(Self {
+            target,
+            min_severity: Severity::Info,
+            http,
+        })
+    }
+
+    /// Only post alerts at least this severe
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl AlertSink for WebhookSink {
+    fn send(&mut self, alert: &Alert) -> Result<()> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+        let text = format!("Carrot vault alert {}", alert);
+        let (url, body) = match &self.target {
+            WebhookTarget::Slack { webhook_url } => (webhook_url.clone(), json!({ "text": text })),
+            WebhookTarget::Discord { webhook_url } => (webhook_url.clone(), json!({ "content": text })),
+            WebhookTarget::Telegram { bot_token, chat_id } => (
+                format!("https://api.telegram.org/bot{}/sendMessage", bot_token),
+                json!({ "chat_id": chat_id, "text": text }),
+            ),
+        };
+        self.http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            // Leave the URL out: webhook URLs and bot tokens are credentials
+            .map_err(|e| CarrotError::PublishFailed(format!("alert webhook: {}", e.without_url())))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Send every `ClientEvent::Alert` emitted from now on to `sinks`, from a supervised task
+    ///
+    /// A sink that fails is logged and skipped for that alert; the other sinks still get it.
+    pub fn forward_alerts(&self, group: &mut TaskGroup, mut sinks: Vec<Box<dyn AlertSink>>) {
+        let events = self.subscribe();
+        group.spawn("alert-forwarder", move |shutdown| {
+            while !shutdown.is_triggered() {
+                let alert = match events.recv_timeout(FORWARD_POLL) {
+                    Ok(ClientEvent::Alert(alert)) => alert,
+                    Ok(_) | Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                for sink in sinks.iter_mut() {
+                    if let Err(err) = sink.send(&alert) {
+                        log::warn!("failed to deliver alert ({}): {}", alert, err);
+                    }
+                }
+            }
+            Ok(())
+        });
+    }
+
+    /// Check the vault every `interval` in a supervised task and emit `ClientEvent::Alert`
+    /// when it is paused, unpaused or its fees change
+    pub fn watch_vault_changes(&self, group: &mut TaskGroup, interval: Duration) {
+        let client = self.clone();
+        let mut detector = VaultChangeDetector::new();
+        group.spawn_periodic("vault-change-detector", interval, move || {
+            for alert in detector.observe(&client.fetch_snapshot()?) {
+                log::info!("{}", alert);
+                client.emit(ClientEvent::Alert(alert));
+            }
+            Ok(())
+        });
+    }
+}
+
+/// USD value (`VALUE_DECIMALS`) with cents
+fn usd(value: u128) -> String {
+    format!("{:.2}", value as f64 / 10f64.powi(crate::nav::VALUE_DECIMALS as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Vault, CRT_MINT};
+
+    fn snapshot(slot: u64, paused: bool, redemption_fee_bps: u16) -> VaultSnapshot {
+        VaultSnapshot {
+            slot,
+            shares_supply: 0,
+            assets: Vec::new(),
+            vault: Vault {
+                authority: Pubkey::default(),
+                shares: CRT_MINT,
+                fee: Fee {
+                    redemption_fee_bps,
+                    management_fee_bps: 50,
+                    performance_fee_bps: 1_000,
+                    ..Default::default()
+                },
+                paused,
+                asset_index: 0,
+                strategy_index: 0,
+                assets: Vec::new(),
+                strategies: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_pause_and_fee_changes_raise_alerts() {
+        let mut detector = VaultChangeDetector::new();
+        assert!(detector.observe(&snapshot(1, false, 10)).is_empty());
+        assert!(detector.observe(&snapshot(2, false, 10)).is_empty());
+
+        let alerts = detector.observe(&snapshot(3, true, 25));
+        assert_eq!(
+            alerts,
+            [
+                Alert {
+                    severity: Severity::Critical,
+                    slot: 3,
+                    kind: AlertKind::VaultPaused
+                },
+                Alert {
+                    severity: Severity::Warning,
+                    slot: 3,
+                    kind: AlertKind::FeeChanged {
+                        fee: FeeKind::Redemption,
+                        previous: 10,
+                        current: 25
+                    }
+                },
+            ]
+        );
+        assert_eq!(alerts[1].to_string(), "[warning] slot 3: redemption fee raised from 10 to 25 bps");
+
+        let severities: Vec<_> = detector.observe(&snapshot(4, false, 5)).iter().map(|a| a.severity).collect();
+        assert_eq!(severities, [Severity::Info, Severity::Info]);
+    }
+}
@@ -0,0 +1,123 @@
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, transaction::Transaction};
+
+use crate::{
+    error::{CarrotError, Result},
+    token::TokenProgram,
+    CARROT_PROGRAM_ID,
+};
+
+/// SPL Memo program ID
+pub const MEMO_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// Legacy SPL Memo (v1) program ID
+pub const MEMO_V1_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo");
+
+/// Programs a transaction may invoke before the client agrees to sign it
+///
+/// Guards against instructions for unexpected programs slipping into a
+/// transaction assembled from several sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramAllowlist {
+    programs: Vec<Pubkey>,
+}
+
+impl Default for ProgramAllowlist {
+    fn default() -> Self {
+        Self {
+            programs: vec![
+                CARROT_PROGRAM_ID,
+                TokenProgram::Token.id(),
+                TokenProgram::Token2022.id(),
+                spl_associated_token_account::id(),
+                solana_sdk_ids::compute_budget::id(),
+                MEMO_PROGRAM_ID,
+                MEMO_V1_PROGRAM_ID,
+            ],
+        }
+    }
+}
+
+impl ProgramAllowlist {
+    /// Default allowlist extended with `extra` programs
+    pub fn with_extra(extra: &[Pubkey]) -> Self {
+        let mut allowlist = Self::default();
+        for program in extra {
+            if !allowlist.programs.contains(program) {
+                allowlist.programs.push(*program);
+            }
+        }
+        allowlist
+    }
+
+    /// Programs on the allowlist
+    pub fn programs(&self) -> &[Pubkey] {
+        &self.programs
+    }
+
+    /// Whether `program_id` is allowed
+    pub fn contains(&self, program_id: &Pubkey) -> bool {
+        self.programs.contains(program_id)
+    }
+
+    /// Fail with `ProgramNotAllowed` if any instruction targets a program outside the allowlist
+    pub fn verify(&self, instructions: &[Instruction]) -> Result<()> {
+        match instructions.iter().find(|ix| !self.contains(&ix.program_id)) {
+            Some(ix) => Err(CarrotError::ProgramNotAllowed(ix.program_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as `verify`, for an already compiled transaction
+    ///
+    /// Fails with `TransactionFailed` if a program index is out of bounds.
+    pub fn verify_transaction(&self, tx: &Transaction) -> Result<()> {
+        for instruction in &tx.message.instructions {
+            let index = instruction.program_id_index;
+            let program_id = tx.message.account_keys.get(index as usize).ok_or_else(|| {
+                CarrotError::TransactionFailed(format!("program index {} out of bounds", index))
+            })?;
+            if !self.contains(program_id) {
+                return Err(CarrotError::ProgramNotAllowed(*program_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+
+    #[test]
+    fn test_verify() {
+        let allowlist = ProgramAllowlist::default();
+        let budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(200_000);
+        assert!(allowlist.verify(std::slice::from_ref(&budget_ix)).is_ok());
+
+        let unknown = Pubkey::new_unique();
+        let foreign_ix = Instruction::new_with_bytes(unknown, &[], vec![]);
+        assert!(matches!(
+            allowlist.verify(&[budget_ix, foreign_ix.clone()]),
+            Err(CarrotError::ProgramNotAllowed(p)) if p == unknown
+        ));
+
+        assert!(ProgramAllowlist::with_extra(&[unknown]).verify(&[foreign_ix]).is_ok());
+    }
+
+    #[test]
+    fn test_verify_transaction_out_of_bounds_program() {
+        let payer = Pubkey::new_unique();
+        let budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(200_000);
+        let mut tx = Transaction::new_with_payer(&[budget_ix], Some(&payer));
+        assert!(ProgramAllowlist::default().verify_transaction(&tx).is_ok());
+
+        tx.message.instructions[0].program_id_index = 42;
+        assert!(matches!(
+            ProgramAllowlist::default().verify_transaction(&tx),
+            Err(CarrotError::TransactionFailed(_))
+        ));
+    }
+}
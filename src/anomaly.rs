@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::{
+    alerts::{Alert, AlertKind, Severity},
+    error::Result,
+    nav::{Nav, Rounding, CRT_DECIMALS},
+    snapshot::VaultSnapshot,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, events::ClientEvent, supervisor::TaskGroup};
+
+/// Vault metrics sampled from one snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultMetrics {
+    pub slot: u64,
+    /// Unix timestamp the sample was taken at
+    pub timestamp: i64,
+    /// Vault value in USD (`VALUE_DECIMALS`), net of the pending management fee
+    pub total_value: u128,
+    /// USD value (`VALUE_DECIMALS`) of one whole CRT
+    pub share_value: u128,
+    /// `(strategy_id, balance)` of every strategy
+    pub strategies: Vec<(u16, u64)>,
+}
+
+impl VaultMetrics {
+    /// Sample the metrics of `snapshot`, valued at `now` (Unix timestamp)
+    pub fn from_snapshot(snapshot: &VaultSnapshot, now: i64) -> Result<Self> {
+        let nav = Nav::from_snapshot_with_fees(snapshot, now)?;
+        Ok(Self {
+            slot: snapshot.slot,
+            timestamp: now,
+            total_value: nav.total_value()?,
+            share_value: nav.value_of_shares(10u64.pow(CRT_DECIMALS as u32), Rounding::Down)?,
+            strategies: snapshot
+                .vault
+                .strategies
+                .iter()
+                .map(|s| (s.strategy_id, s.balance))
+                .collect(),
+        })
+    }
+}
+
+/// When `AnomalyDetector` raises an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnomalyThresholds {
+    /// How long samples are kept to compare against
+    pub window: Duration,
+    /// Drop of the vault value from its peak within the window, in bps
+    pub tvl_drop_bps: u64,
+    /// Change of the CRT value between consecutive samples, in bps
+    pub share_price_change_bps: u64,
+    /// Drop of a strategy balance between consecutive samples, in bps
+    pub strategy_drop_bps: u64,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60 * 60),
+            tvl_drop_bps: 2_000,
+            share_price_change_bps: 100,
+            strategy_drop_bps: 9_000,
+        }
+    }
+}
+
+/// Flags sudden changes in vault metrics that indicate a protocol incident
+///
+/// Every alert it raises is `Critical`, except a CRT price rising, which is a `Warning`.
+/// Large withdrawals also move the vault value, so size `tvl_drop_bps` above normal outflows.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyDetector {
+    thresholds: AnomalyThresholds,
+    samples: VecDeque<VaultMetrics>,
+}
+
+impl AnomalyDetector {
+    pub fn new(thresholds: AnomalyThresholds) -> Self {
+        Self {
+            thresholds,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record a sample and return the alerts it triggers against the earlier ones
+    pub fn observe(&mut self, metrics: VaultMetrics) -> Vec<Alert> {
+        let window = self.thresholds.window.as_secs() as i64;
+        self.samples.retain(|s| metrics.timestamp.saturating_sub(s.timestamp) <= window);
+
+        let mut alerts = Vec::new();
+        let critical = |kind| Alert {
+            severity: Severity::Critical,
+            slot: metrics.slot,
+            kind,
+        };
+
+        let peak = self.samples.iter().map(|s| s.total_value).max().unwrap_or(0);
+        let drop_bps = change_bps(peak, metrics.total_value);
+        if metrics.total_value < peak && drop_bps >= self.thresholds.tvl_drop_bps {
+            alerts.push(critical(AlertKind::TvlDrop {
+                peak,
+                current: metrics.total_value,
+                drop_bps,
+            }));
+        }
+
+        if let Some(previous) = self.samples.back() {
+            let price_change_bps = change_bps(previous.share_value, metrics.share_value);
+            if price_change_bps >= self.thresholds.share_price_change_bps {
+                let kind = AlertKind::SharePriceDiscontinuity {
+                    previous: previous.share_value,
+                    current: metrics.share_value,
+                    change_bps: price_change_bps,
+                };
+                alerts.push(if metrics.share_value < previous.share_value {
+                    critical(kind)
+                } else {
+                    Alert {
+                        severity: Severity::Warning,
+                        ..critical(kind)
+                    }
+                });
+            }
+
+            for &(strategy_id, current) in &metrics.strategies {
+                let previous = previous.strategies.iter().find(|(id, _)| *id == strategy_id);
+                if let Some(&(_, previous)) = previous {
+                    let drop_bps = change_bps(previous as u128, current as u128);
+                    if current < previous && drop_bps >= self.thresholds.strategy_drop_bps {
+                        alerts.push(critical(AlertKind::StrategyWiped {
+                            strategy_id,
+                            previous,
+                            current,
+                        }));
+                    }
+                }
+            }
+        }
+
+        self.samples.push_back(metrics);
+        alerts
+    }
+}
+
+/// Relative change from `from` to `to` in bps (0 when `from` is 0)
+fn change_bps(from: u128, to: u128) -> u64 {
+    if from == 0 {
+        return 0;
+    }
+    let bps = from.abs_diff(to).saturating_mul(10_000) / from;
+    u64::try_from(bps).unwrap_or(u64::MAX)
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Sample the vault every `interval` in a supervised task and emit `ClientEvent::Alert`
+    /// for each anomaly `detector` flags
+    pub fn watch_vault_anomalies(&self, group: &mut TaskGroup, interval: Duration, mut detector: AnomalyDetector) {
+        let client = self.clone();
+        group.spawn_periodic("anomaly-detector", interval, move || {
+            let metrics = VaultMetrics::from_snapshot(&client.fetch_snapshot()?, client.now())?;
+            for alert in detector.observe(metrics) {
+                log::warn!("{}", alert);
+                client.emit(ClientEvent::Alert(alert));
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USD: u128 = 1_000_000_000_000;
+
+    fn sample(timestamp: i64, total_value: u128, share_value: u128, strategy_balance: u64) -> VaultMetrics {
+        VaultMetrics {
+            slot: timestamp as u64,
+            timestamp,
+            total_value,
+            share_value,
+            strategies: vec![(1, strategy_balance)],
+        }
+    }
+
+    #[test]
+    fn test_normal_activity_raises_nothing() {
+        let mut detector = AnomalyDetector::default();
+        assert!(detector.observe(sample(0, 1_000 * USD, USD, 500)).is_empty());
+        // 10% outflow and a little yield
+        assert!(detector.observe(sample(60, 900 * USD, USD + USD / 10_000, 450)).is_empty());
+    }
+
+    #[test]
+    fn test_incidents_raise_alerts() {
+        let mut detector = AnomalyDetector::default();
+        detector.observe(sample(0, 1_000 * USD, USD, 500));
+        detector.observe(sample(60, 850 * USD, USD, 500));
+        // Drained gradually: each step is small, the drop from the window's peak is not
+        let alerts = detector.observe(sample(120, 700 * USD, USD * 97 / 100, 10));
+        let kinds: Vec<_> = alerts.iter().map(|a| (a.severity, &a.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    Severity::Critical,
+                    &AlertKind::TvlDrop {
+                        peak: 1_000 * USD,
+                        current: 700 * USD,
+                        drop_bps: 3_000
+                    }
+                ),
+                (
+                    Severity::Critical,
+                    &AlertKind::SharePriceDiscontinuity {
+                        previous: USD,
+                        current: USD * 97 / 100,
+                        change_bps: 300
+                    }
+                ),
+                (
+                    Severity::Critical,
+                    &AlertKind::StrategyWiped {
+                        strategy_id: 1,
+                        previous: 500,
+                        current: 10
+                    }
+                ),
+            ]
+        );
+        assert_eq!(alerts[0].to_string(), "[critical] slot 120: vault value dropped 30.00% ($1000.00 -> $700.00)");
+
+        // The peak ages out of the window
+        assert!(detector.observe(sample(4_000, 680 * USD, USD * 97 / 100, 10)).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+};
+
+use crate::{
+    error::{CarrotError, Result},
+    inspect::TransactionInspection,
+    prepared::PreparedTransaction,
+};
+
+/// Domain prefix of approval messages, so an approval can't be replayed as a transaction signature
+const APPROVAL_DOMAIN: &[u8] = b"carrot-sdk approval v1\n";
+
+/// A signed deposit or withdrawal waiting for a second person's approval before broadcast
+///
+/// Produced by `CarrotClient::request_deposit` / `request_withdraw`; sent with
+/// `CarrotClient::send_approved` once an approver has signed it.
+#[derive(Debug, Clone)]
+pub struct PendingOperation {
+    /// Signed transaction, not yet broadcast
+    pub prepared: PreparedTransaction,
+    /// Signer who requested the operation (fee payer)
+    pub requester: Pubkey,
+}
+
+impl PendingOperation {
+    /// Wrap a prepared transaction requested by its fee payer
+    pub fn new(prepared: PreparedTransaction) -> Self {
+        let requester = prepared
+            .transaction
+            .message
+            .account_keys
+            .first()
+            .copied()
+            .unwrap_or_default();
+        Self { prepared, requester }
+    }
+
+    /// Signature the transaction will land under
+    pub fn signature(&self) -> Signature {
+        self.prepared.signature()
+    }
+
+    /// Programs, accounts and Carrot instructions the approver is asked to sign off on
+    pub fn inspect(&self) -> Result<TransactionInspection> {
+        self.prepared.inspect()
+    }
+
+    /// Bytes an approver signs: the domain prefix followed by the exact transaction message
+    pub fn approval_message(&self) -> Vec<u8> {
+        let mut message = APPROVAL_DOMAIN.to_vec();
+        message.extend(self.prepared.transaction.message_data());
+        message
+    }
+}
+
+/// An approver's signature over a `PendingOperation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Approval {
+    /// Approving key
+    pub approver: Pubkey,
+    /// Signature over `PendingOperation::approval_message`
+    pub signature: Signature,
+}
+
+impl Approval {
+    /// Approve `operation` with `approver`'s key
+    pub fn sign(approver: &dyn Signer, operation: &PendingOperation) -> Self {
+        Self {
+            approver: approver.pubkey(),
+            signature: approver.sign_message(&operation.approval_message()),
+        }
+    }
+
+    /// Fail with `InvalidApproval` unless this is a valid approval of `operation` by someone
+    /// other than its requester and, if `approvers` is given, by one of them
+    pub fn verify(&self, operation: &PendingOperation, approvers: Option<&[Pubkey]>) -> Result<()> {
+        if self.approver == operation.requester {
+            return Err(CarrotError::InvalidApproval(format!(
+                "{} can't approve their own operation",
+                self.approver
+            )));
+        }
+        if let Some(approvers) = approvers {
+            if !approvers.contains(&self.approver) {
+                return Err(CarrotError::InvalidApproval(format!("{} is not an approver", self.approver)));
+            }
+        }
+        if !self
+            .signature
+            .verify(self.approver.as_ref(), &operation.approval_message())
+        {
+            return Err(CarrotError::InvalidApproval(format!(
+                "signature by {} doesn't match operation {}",
+                self.approver,
+                operation.signature()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{hash::Hash, message::Message, signature::Keypair, transaction::Transaction};
+    use solana_sdk_ids::system_program;
+
+    fn pending(requester: &Keypair, nonce: u8) -> PendingOperation {
+        let ix = solana_sdk::instruction::Instruction::new_with_bytes(system_program::id(), &[nonce], vec![]);
+        let message = Message::new(&[ix], Some(&requester.pubkey()));
+        PendingOperation::new(PreparedTransaction {
+            transaction: Transaction::new(&[requester], message, Hash::default()),
+            last_valid_block_height: 0,
+            expires_at: None,
+        })
+    }
+
+    #[test]
+    fn test_approval_round_trip() {
+        let (requester, approver) = (Keypair::new(), Keypair::new());
+        let operation = pending(&requester, 1);
+        assert_eq!(operation.requester, requester.pubkey());
+
+        let approval = Approval::sign(&approver, &operation);
+        assert!(approval.verify(&operation, None).is_ok());
+        assert!(approval.verify(&operation, Some(&[approver.pubkey()])).is_ok());
+        assert!(approval.verify(&operation, Some(&[Pubkey::new_unique()])).is_err());
+    }
+
+    #[test]
+    fn test_approval_is_bound_to_operation_and_second_person() {
+        let (requester, approver) = (Keypair::new(), Keypair::new());
+        let operation = pending(&requester, 1);
+
+        // Approving a different transaction doesn't carry over
+        let other = Approval::sign(&approver, &pending(&requester, 2));
+        assert!(matches!(other.verify(&operation, None), Err(CarrotError::InvalidApproval(_))));
+
+        // The requester can't approve themselves
+        let own = Approval::sign(&requester, &operation);
+        assert!(own.verify(&operation, None).is_err());
+    }
+}
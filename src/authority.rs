@@ -0,0 +1,239 @@
+#[cfg(feature = "blocking")]
+use std::time::Duration;
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk_ids::bpf_loader_upgradeable;
+
+use crate::{
+    alerts::{Alert, AlertKind, Severity},
+    error::{CarrotError, Result},
+};
+#[cfg(feature = "blocking")]
+use crate::{
+    client::CarrotClient, events::ClientEvent, supervisor::TaskGroup, Vault, CARROT_PROGRAM_ID, VAULT_ADDRESS,
+};
+
+/// `UpgradeableLoaderState::ProgramData` variant tag
+const PROGRAM_DATA_TAG: u32 = 3;
+
+/// Keys able to change the vault's configuration or the Carrot program's code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Authorities {
+    /// Authority stored in the vault account
+    pub vault_authority: Pubkey,
+    /// Upgrade authority of the Carrot program (`None` once the program is immutable)
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+/// Current authorities, compared with pinned values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorityReport {
+    /// Slot the authorities were read at
+    pub slot: u64,
+    pub current: Authorities,
+    /// Values the report was checked against, if any
+    pub pinned: Option<Authorities>,
+}
+
+impl AuthorityReport {
+    /// Whether the vault authority differs from the pinned one
+    pub fn vault_authority_changed(&self) -> bool {
+        self.pinned
+            .is_some_and(|pinned| pinned.vault_authority != self.current.vault_authority)
+    }
+
+    /// Whether the upgrade authority differs from the pinned one
+    pub fn upgrade_authority_changed(&self) -> bool {
+        self.pinned
+            .is_some_and(|pinned| pinned.upgrade_authority != self.current.upgrade_authority)
+    }
+}
+
+/// Raises `Critical` alerts when an authority differs from its pinned value, or, without pins,
+/// from the first one observed
+///
+/// Each new value alerts once, not on every observation while it persists.
+#[derive(Debug, Clone, Default)]
+pub struct AuthorityMonitor {
+    pinned: Option<Authorities>,
+    previous: Option<Authorities>,
+}
+
+impl AuthorityMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare against `pinned` instead of the first observation
+    pub fn with_pinned(mut self, pinned: Authorities) -> Self {
+        self.pinned = Some(pinned);
+        self
+    }
+
+    /// Compare `current`, read at `slot`, with the pinned or previously observed authorities
+    pub fn observe(&mut self, slot: u64, current: &Authorities) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let previous = self.previous;
+        let unseen = |changed: fn(&Authorities, &Authorities) -> bool| previous.is_none_or(|p| changed(&p, current));
+        if let Some(expected) = self.pinned.or(previous) {
+            if expected.vault_authority != current.vault_authority
+                && unseen(|a, b| a.vault_authority != b.vault_authority)
+            {
+                alerts.push(AlertKind::VaultAuthorityChanged {
+                    expected: expected.vault_authority,
+                    current: current.vault_authority,
+                });
+            }
+            if expected.upgrade_authority != current.upgrade_authority
+                && unseen(|a, b| a.upgrade_authority != b.upgrade_authority)
+            {
+                alerts.push(AlertKind::UpgradeAuthorityChanged {
+                    expected: expected.upgrade_authority,
+                    current: current.upgrade_authority,
+                });
+            }
+        }
+        self.previous = Some(*current);
+        alerts
+            .into_iter()
+            .map(|kind| Alert {
+                severity: Severity::Critical,
+                slot,
+                kind,
+            })
+            .collect()
+    }
+}
+
+/// Program data account holding the code and upgrade authority of upgradeable `program_id`
+pub fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+/// Upgrade authority stored in a program data account (`None` if the program is immutable)
+pub fn parse_upgrade_authority(data: &[u8]) -> Result<Option<Pubkey>> {
+    // u32 variant tag, u64 deployment slot, then an `Option<Pubkey>`
+    let invalid = || CarrotError::InvalidConfig("not a program data account".to_string());
+    let tag = data.get(..4).and_then(|bytes| bytes.try_into().ok()).map(u32::from_le_bytes);
+    if tag != Some(PROGRAM_DATA_TAG) {
+        return Err(invalid());
+    }
+    match data.get(12) {
+        Some(0) => Ok(None),
+        Some(1) => data
+            .get(13..45)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(|bytes| Some(Pubkey::new_from_array(bytes)))
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Read the vault authority and the program's upgrade authority at the same slot,
+    /// flagging any that differ from `pinned`
+    pub fn fetch_authority_report(&self, pinned: Option<Authorities>) -> Result<AuthorityReport> {
+        let program_data = program_data_address(&CARROT_PROGRAM_ID);
+        let response = self
+            .rpc_client()
+            .get_multiple_accounts_with_commitment(&[VAULT_ADDRESS, program_data], self.rpc_client().commitment())?;
+        let mut accounts = response.value.into_iter();
+        let vault = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| CarrotError::AccountNotFound("Vault account not found".to_string()))?;
+        let program_data_account = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| CarrotError::AccountNotFound(format!("program data account {}", program_data)))?;
+        Ok(AuthorityReport {
+            slot: response.context.slot,
+            current: Authorities {
+                vault_authority: Vault::from_account_data(&vault.data)?.authority,
+                upgrade_authority: parse_upgrade_authority(&program_data_account.data)?,
+            },
+            pinned,
+        })
+    }
+
+    /// Check the authorities every `interval` in a supervised task and emit `ClientEvent::Alert`
+    /// when one differs from `pinned` (or, without pins, from the first value read)
+    pub fn watch_authorities(&self, group: &mut TaskGroup, interval: Duration, pinned: Option<Authorities>) {
+        let client = self.clone();
+        let mut monitor = AuthorityMonitor::new();
+        if let Some(pinned) = pinned {
+            monitor = monitor.with_pinned(pinned);
+        }
+        group.spawn_periodic("authority-monitor", interval, move || {
+            let report = client.fetch_authority_report(pinned)?;
+            for alert in monitor.observe(report.slot, &report.current) {
+                log::warn!("{}", alert);
+                client.emit(ClientEvent::Alert(alert));
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_data(authority: Option<Pubkey>) -> Vec<u8> {
+        let mut data = PROGRAM_DATA_TAG.to_le_bytes().to_vec();
+        data.extend(42u64.to_le_bytes());
+        match authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend(authority.to_bytes());
+            }
+            None => data.push(0),
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_upgrade_authority() {
+        let authority = Pubkey::new_unique();
+        assert_eq!(parse_upgrade_authority(&program_data(Some(authority))).unwrap(), Some(authority));
+        assert_eq!(parse_upgrade_authority(&program_data(None)).unwrap(), None);
+        assert!(parse_upgrade_authority(&[2, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_monitor_alerts_once_per_change() {
+        let pinned = Authorities {
+            vault_authority: Pubkey::new_unique(),
+            upgrade_authority: Some(Pubkey::new_unique()),
+        };
+        let mut monitor = AuthorityMonitor::new().with_pinned(pinned);
+        assert!(monitor.observe(1, &pinned).is_empty());
+
+        let rotated = Authorities {
+            upgrade_authority: Some(Pubkey::new_unique()),
+            ..pinned
+        };
+        let alerts = monitor.observe(2, &rotated);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, Severity::Critical);
+        assert_eq!(
+            alerts[0].kind,
+            AlertKind::UpgradeAuthorityChanged {
+                expected: pinned.upgrade_authority,
+                current: rotated.upgrade_authority
+            }
+        );
+        // Still differs from the pin, but was already reported
+        assert!(monitor.observe(3, &rotated).is_empty());
+
+        // Without pins the first observation is the baseline
+        let mut monitor = AuthorityMonitor::new();
+        assert!(monitor.observe(1, &pinned).is_empty());
+        let immutable = Authorities {
+            upgrade_authority: None,
+            ..pinned
+        };
+        assert_eq!(monitor.observe(2, &immutable).len(), 1);
+    }
+}
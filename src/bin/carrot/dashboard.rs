@@ -0,0 +1,289 @@
+//! `carrot dashboard`: live share price, position, allocation and protocol events
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::time::Duration;
+
+use anyhow::Context;
+use carrot_sdk_v3::{
+    nav::{Rounding, CRT_DECIMALS, VALUE_DECIMALS},
+    Alert, CarrotClient, CarrotInstruction, HistoryEntry, Nav, TaskGroup, VaultChangeDetector,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    widgets::{Block, List, ListItem, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::Options;
+
+/// Default interval between vault refreshes
+const DEFAULT_REFRESH: Duration = Duration::from_secs(5);
+
+/// How long to wait for a key press before redrawing
+const INPUT_POLL: Duration = Duration::from_millis(250);
+
+/// Protocol events kept on screen
+const MAX_EVENTS: usize = 50;
+
+/// How long to wait for the refresher to stop on quit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Vault state fetched in the background
+struct Update {
+    nav: Nav,
+    crt_balance: Option<u64>,
+    /// Symbols of the vault assets resolved so far
+    symbols: HashMap<Pubkey, String>,
+    /// Pauses, unpauses and fee changes since the previous refresh
+    alerts: Vec<Alert>,
+}
+
+#[derive(Default)]
+struct State {
+    latest: Option<Update>,
+    error: Option<String>,
+    events: VecDeque<String>,
+}
+
+impl State {
+    /// Show `event` first, dropping the oldest beyond `MAX_EVENTS`
+    fn push_event(&mut self, event: String) {
+        self.events.push_front(event);
+        self.events.truncate(MAX_EVENTS);
+    }
+}
+
+pub(crate) fn run(options: &Options) -> anyhow::Result<()> {
+    let wallet = options.wallet()?;
+    let refresh = match options.get("refresh") {
+        Some(secs) => Duration::from_secs(secs.parse().context("invalid --refresh")?),
+        None => DEFAULT_REFRESH,
+    };
+    let client = options.client();
+    let mut tasks = TaskGroup::new().with_events(&client);
+    let activity = subscribe_activity(&mut tasks, &client, refresh);
+    let updates = spawn_refresher(&mut tasks, client, wallet, refresh);
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, wallet, &updates, &activity);
+    ratatui::restore();
+    tasks.shutdown(SHUTDOWN_TIMEOUT);
+    result
+}
+
+/// Deposits and withdrawals into the vault from its newest transaction on, decoded by the
+/// vault activity subscription
+fn subscribe_activity(tasks: &mut TaskGroup, client: &CarrotClient, poll_interval: Duration) -> Receiver<HistoryEntry> {
+    let (sender, receiver) = channel();
+    client.subscribe_vault_activity(tasks, None, poll_interval, move |entry| {
+        // The receiver is only gone once the dashboard quit, which shuts the task down anyway
+        let _ = sender.send(entry.clone());
+        Ok(())
+    });
+    receiver
+}
+
+/// Fetch the NAV (and wallet balance) every `refresh` in a supervised task, comparing each
+/// vault snapshot with the previous one for pauses and fee changes
+///
+/// Asset symbols are resolved once per asset, so assets added to the vault show up by name.
+fn spawn_refresher(
+    tasks: &mut TaskGroup,
+    client: CarrotClient,
+    wallet: Option<Pubkey>,
+    refresh: Duration,
+) -> Receiver<Result<Update, String>> {
+    let (sender, receiver) = channel();
+    let mut symbols = HashMap::new();
+    let mut changes = VaultChangeDetector::new();
+    tasks.spawn_periodic("dashboard-refresh", refresh, move || {
+        let update = client.fetch_snapshot().and_then(|snapshot| {
+            let nav = Nav::from_snapshot_with_fees(&snapshot, client.config().clock.unix_timestamp())?;
+            for asset in &nav.assets {
+                if let Entry::Vacant(entry) = symbols.entry(asset.mint) {
+                    // Retried on the next refresh if the lookup fails
+                    if let Ok(metadata) = client.resolve_mint_metadata(&asset.mint) {
+                        entry.insert(metadata.symbol);
+                    }
+                }
+            }
+            let crt_balance = wallet.map(|w| client.get_crt_balance(&w)).transpose()?;
+            Ok(Update {
+                nav,
+                crt_balance,
+                symbols: symbols.clone(),
+                alerts: changes.observe(&snapshot),
+            })
+        });
+        // Refresh errors are shown on screen and retried next interval, the task keeps running;
+        // the receiver is only gone once the dashboard quit, which shuts the task down anyway
+        let _ = sender.send(update.map_err(|e| e.to_string()));
+        Ok(())
+    });
+    receiver
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    wallet: Option<Pubkey>,
+    updates: &Receiver<Result<Update, String>>,
+    activity: &Receiver<HistoryEntry>,
+) -> anyhow::Result<()> {
+    let mut state = State::default();
+    loop {
+        loop {
+            match updates.try_recv() {
+                Ok(Ok(update)) => {
+                    for alert in &update.alerts {
+                        state.push_event(alert.to_string());
+                    }
+                    state.latest = Some(update);
+                    state.error = None;
+                }
+                Ok(Err(err)) => state.error = Some(err),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => anyhow::bail!("refresh thread stopped"),
+            }
+        }
+        for entry in activity.try_iter() {
+            state.push_event(describe_activity(&entry, state.latest.as_ref()));
+        }
+
+        terminal.draw(|frame| draw(frame, wallet, &state))?;
+
+        if event::poll(INPUT_POLL)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, wallet: Option<Pubkey>, state: &State) {
+    let [summary_area, allocation_area, events_area] = Layout::vertical([
+        Constraint::Length(6),
+        Constraint::Min(6),
+        Constraint::Length(10),
+    ])
+    .areas(frame.area());
+
+    let mut summary = Vec::new();
+    match &state.latest {
+        Some(update) => {
+            let nav = &update.nav;
+            summary.push(format!("Slot:        {}", nav.slot));
+            summary.push(match nav.share_price() {
+                Ok(price) => format!("Share price: ${:.6}", price),
+                Err(err) => format!("Share price: {}", err),
+            });
+            summary.push(format!(
+                "TVL:         {}   CRT supply: {:.4}",
+                nav.total_value().map(usd).unwrap_or_else(|e| e.to_string()),
+                units(nav.shares_supply as u128, CRT_DECIMALS as u32)
+            ));
+            if let (Some(wallet), Some(balance)) = (wallet, update.crt_balance) {
+                let value = nav
+                    .value_of_shares(balance, Rounding::Down)
+                    .map(usd)
+                    .unwrap_or_else(|e| e.to_string());
+                summary.push(format!(
+                    "Position:    {:.4} CRT ({}) held by {}",
+                    units(balance as u128, CRT_DECIMALS as u32),
+                    value,
+                    wallet
+                ));
+            }
+        }
+        None => summary.push("Loading vault...".to_string()),
+    }
+    if let Some(err) = &state.error {
+        summary.push(format!("Last refresh failed: {}", err));
+    }
+    frame.render_widget(
+        Paragraph::new(summary.join("\n")).block(Block::bordered().title(" Carrot vault (q to quit) ")),
+        summary_area,
+    );
+
+    let rows: Vec<Row> = state
+        .latest
+        .iter()
+        .flat_map(|update| allocation_rows(&update.nav, &update.symbols))
+        .collect();
+    let widths = [
+        Constraint::Length(46),
+        Constraint::Length(18),
+        Constraint::Length(18),
+        Constraint::Length(20),
+        Constraint::Length(8),
+    ];
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(Row::new(["Asset", "Idle", "In strategies", "Value", "Share"]))
+            .block(Block::bordered().title(" Allocation ")),
+        allocation_area,
+    );
+
+    let events: Vec<ListItem> = state.events.iter().map(|e| ListItem::new(e.as_str())).collect();
+    frame.render_widget(
+        List::new(events).block(Block::bordered().title(" Protocol events ")),
+        events_area,
+    );
+}
+
+/// One table row per vault asset with its share of the total value
+fn allocation_rows(nav: &Nav, symbols: &HashMap<Pubkey, String>) -> Vec<Row<'static>> {
+    let total = nav.total_value().unwrap_or(0);
+    nav.assets
+        .iter()
+        .map(|asset| {
+            let value = asset.value_of(asset.total_balance(), Rounding::Down).unwrap_or(0);
+            let share = if total == 0 { 0.0 } else { value as f64 / total as f64 * 100.0 };
+            let decimals = asset.decimals as u32;
+            Row::new([
+                symbols.get(&asset.mint).cloned().unwrap_or_else(|| asset.mint.to_string()),
+                format!("{:.2}", units(asset.vault_balance as u128, decimals)),
+                format!("{:.2}", units(asset.strategy_balance as u128, decimals)),
+                usd(value),
+                format!("{:.1}%", share),
+            ])
+        })
+        .collect()
+}
+
+/// One line per deposit or withdrawal, in whole units once the asset's decimals are known
+fn describe_activity(entry: &HistoryEntry, latest: Option<&Update>) -> String {
+    let asset_mint = entry.asset_mint();
+    let symbol = latest
+        .and_then(|update| update.symbols.get(&asset_mint).cloned())
+        .unwrap_or_else(|| asset_mint.to_string());
+    let (user, amount) = match entry.instruction {
+        CarrotInstruction::Issue { user, amount, .. } => {
+            let asset = latest.and_then(|update| update.nav.assets.iter().find(|asset| asset.mint == asset_mint));
+            let amount = match asset {
+                Some(asset) => format!("{:.2} {}", units(amount as u128, asset.decimals as u32), symbol),
+                None => format!("{} base units of {}", amount, symbol),
+            };
+            (user, amount)
+        }
+        CarrotInstruction::Redeem { user, amount, .. } => (
+            user,
+            format!("{:.4} CRT for {}", units(amount as u128, CRT_DECIMALS as u32), symbol),
+        ),
+    };
+    format!("slot {}: {} {} by {}", entry.slot, entry.kind(), amount, user)
+}
+
+/// Format a `VALUE_DECIMALS` USD value
+fn usd(value: u128) -> String {
+    format!("${:.2}", units(value, VALUE_DECIMALS))
+}
+
+/// Convert base units to whole units (for display only)
+fn units(amount: u128, decimals: u32) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
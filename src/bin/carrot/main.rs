@@ -0,0 +1,148 @@
+//! Command line interface for the Carrot Protocol
+//!
+//! ```text
+//! carrot history --wallet <pubkey> [--format csv|json] [--since <YYYY-MM-DD|unix>] [--rpc-url <url>]
+//! carrot vault-info [--format table|json] [--rpc-url <url>]
+//! carrot dashboard [--wallet <pubkey>] [--refresh <secs>] [--rpc-url <url>]   (feature `dashboard`)
+//! ```
+//!
+//! The RPC URL defaults to `$CARROT_RPC_URL`, then to the public mainnet endpoint.
+
+use std::env;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use carrot_sdk_v3::{
+    history::{history_to_csv, history_to_json},
+    CarrotClient,
+};
+use solana_sdk::pubkey::Pubkey;
+
+#[cfg(feature = "dashboard")]
+mod dashboard;
+
+const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+
+const USAGE: &str = "usage:
+  carrot history --wallet <pubkey> [--format csv|json] [--since <YYYY-MM-DD|unix>] [--rpc-url <url>]
+  carrot vault-info [--format table|json] [--rpc-url <url>]
+  carrot dashboard [--wallet <pubkey>] [--refresh <secs>] [--rpc-url <url>]";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> anyhow::Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        bail!(USAGE);
+    };
+    let options = Options::parse(rest)?;
+    match command.as_str() {
+        "history" => history(&options),
+        "vault-info" => vault_info(&options),
+        #[cfg(feature = "dashboard")]
+        "dashboard" => dashboard::run(&options),
+        #[cfg(not(feature = "dashboard"))]
+        "dashboard" => bail!("the dashboard requires building with `--features dashboard`"),
+        _ => bail!("unknown command `{}`\n{}", command, USAGE),
+    }
+}
+
+/// `--flag value` pairs shared by all commands
+pub(crate) struct Options {
+    pairs: Vec<(String, String)>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let mut pairs = Vec::new();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let name = flag
+                .strip_prefix("--")
+                .ok_or_else(|| anyhow!("unexpected argument `{}`\n{}", flag, USAGE))?;
+            let value = args.next().ok_or_else(|| anyhow!("missing value for --{}", name))?;
+            pairs.push((name.to_string(), value.clone()));
+        }
+        Ok(Self { pairs })
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn client(&self) -> CarrotClient {
+        let rpc_url = self
+            .get("rpc-url")
+            .map(str::to_string)
+            .or_else(|| env::var("CARROT_RPC_URL").ok())
+            .unwrap_or_else(|| DEFAULT_RPC_URL.to_string());
+        CarrotClient::new(rpc_url)
+    }
+
+    /// Parse `--wallet` if given
+    pub(crate) fn wallet(&self) -> anyhow::Result<Option<Pubkey>> {
+        self.get("wallet")
+            .map(|wallet| Pubkey::from_str(wallet).context("invalid --wallet"))
+            .transpose()
+    }
+}
+
+/// Print the deposit/withdraw ledger of a wallet
+fn history(options: &Options) -> anyhow::Result<()> {
+    let wallet = options.wallet()?.ok_or_else(|| anyhow!("--wallet is required"))?;
+    let since = options.get("since").map(parse_since).transpose()?;
+
+    let entries = options.client().fetch_history(&wallet, since)?;
+    match options.get("format").unwrap_or("csv") {
+        "csv" => print!("{}", history_to_csv(&entries)),
+        "json" => println!("{}", history_to_json(&entries)?),
+        other => bail!("unknown format `{}` (expected csv or json)", other),
+    }
+    Ok(())
+}
+
+/// Print the fully decoded vault
+fn vault_info(options: &Options) -> anyhow::Result<()> {
+    let info = options.client().fetch_vault_info()?;
+    match options.get("format").unwrap_or("table") {
+        "table" => print!("{}", info.to_table()),
+        "json" => println!("{}", info.to_json()?),
+        other => bail!("unknown format `{}` (expected table or json)", other),
+    }
+    Ok(())
+}
+
+/// Parse a `YYYY-MM-DD` date (UTC midnight) or a Unix timestamp
+fn parse_since(value: &str) -> anyhow::Result<i64> {
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return Ok(timestamp);
+    }
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        bail!("invalid --since `{}` (expected YYYY-MM-DD or a Unix timestamp)", value);
+    };
+    let (year, month, day): (i64, i64, i64) = (year.parse()?, month.parse()?, day.parse()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        bail!("invalid --since date `{}`", value);
+    }
+    Ok(days_from_civil(year, month, day) * 86_400)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
@@ -0,0 +1,41 @@
+//! Where built transactions get their recent blockhash
+//!
+//! By default the client asks its RPC endpoint. A `BlockhashSource` set with
+//! `ClientConfig::with_blockhash_source` replaces that, e.g. a `FixedBlockhash` so that tests
+//! build byte-identical transactions, or a cache refreshed by another task.
+
+use std::fmt;
+
+use solana_sdk::hash::Hash;
+
+use crate::error::Result;
+
+/// Supplies the blockhash transactions are signed with
+///
+/// Called from the async client too, so implementations shouldn't block for long.
+pub trait BlockhashSource: fmt::Debug + Send + Sync {
+    /// Blockhash and the last block height at which transactions using it are valid
+    fn latest_blockhash(&self) -> Result<(Hash, u64)>;
+}
+
+/// The same blockhash every time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+}
+
+impl FixedBlockhash {
+    pub fn new(blockhash: Hash, last_valid_block_height: u64) -> Self {
+        Self {
+            blockhash,
+            last_valid_block_height,
+        }
+    }
+}
+
+impl BlockhashSource for FixedBlockhash {
+    fn latest_blockhash(&self) -> Result<(Hash, u64)> {
+        Ok((self.blockhash, self.last_valid_block_height))
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+use std::sync::Arc;
+
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+use solana_sdk_ids::system_program;
+
+use crate::error::{CarrotError, Result};
+
+/// Submits signed transactions to the cluster
+///
+/// The client only hands transactions to a broadcaster; it tracks confirmation itself over
+/// its RPC endpoint and resubmits until the transaction lands or its blockhash expires.
+pub trait Broadcaster: fmt::Debug + Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &str;
+
+    /// Submit `transaction`; `Ok` means the relay accepted it, not that it landed
+    fn broadcast(&self, transaction: &Transaction) -> Result<()>;
+
+    /// Instructions appended to every transaction built for this broadcaster (e.g. a tip)
+    fn tip_instructions(&self, _payer: &Pubkey) -> Vec<Instruction> {
+        Vec::new()
+    }
+}
+
+/// `sendTransaction` to a JSON-RPC endpoint
+pub struct RpcBroadcaster {
+    url: String,
+    client: RpcClient,
+    skip_preflight: bool,
+    max_retries: Option<usize>,
+}
+
+impl RpcBroadcaster {
+    /// Broadcaster posting to `url` with preflight checks and the node's default retries
+    pub fn new(url: impl Into<String>) -> Self {
+        let url = url.into();
+        Self {
+            client: RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed()),
+            url,
+            skip_preflight: false,
+            max_retries: None,
+        }
+    }
+
+    /// Skip the node's preflight simulation (the client already simulated the transaction)
+    pub fn with_skip_preflight(mut self, skip_preflight: bool) -> Self {
+        self.skip_preflight = skip_preflight;
+        self
+    }
+
+    /// How often the node itself rebroadcasts (`0` leaves resubmitting to the client)
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+}
+
+impl fmt::Debug for RpcBroadcaster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcBroadcaster")
+            .field("url", &self.url)
+            .field("skip_preflight", &self.skip_preflight)
+            .field("max_retries", &self.max_retries)
+            .finish()
+    }
+}
+
+impl Broadcaster for RpcBroadcaster {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        let config = RpcSendTransactionConfig {
+            skip_preflight: self.skip_preflight,
+            max_retries: self.max_retries,
+            ..Default::default()
+        };
+        self.client
+            .send_transaction_with_config(transaction, config)
+            .map(|_| ())
+            .map_err(|e| CarrotError::TransactionFailed(format!("broadcast via {}: {}", self.url, e)))
+    }
+}
+
+/// Helius Sender: routes transactions to validators and Jito in parallel
+///
+/// Sender skips preflight and requires a tip to one of the tip accounts listed in Helius'
+/// documentation, plus a priority fee (`ClientConfig::with_priority_fee`).
+#[derive(Debug)]
+pub struct HeliusSender {
+    rpc: RpcBroadcaster,
+    tip: Option<(Pubkey, u64)>,
+}
+
+impl HeliusSender {
+    /// Sender at `endpoint` (e.g. `https://sender.helius-rpc.com/fast`)
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            rpc: RpcBroadcaster::new(endpoint).with_skip_preflight(true).with_max_retries(0),
+            tip: None,
+        }
+    }
+
+    /// Tip `lamports` to `tip_account` in every transaction
+    pub fn with_tip(mut self, tip_account: Pubkey, lamports: u64) -> Self {
+        self.tip = Some((tip_account, lamports));
+        self
+    }
+}
+
+impl Broadcaster for HeliusSender {
+    fn name(&self) -> &str {
+        self.rpc.name()
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        self.rpc.broadcast(transaction)
+    }
+
+    fn tip_instructions(&self, payer: &Pubkey) -> Vec<Instruction> {
+        self.tip
+            .iter()
+            .map(|(tip_account, lamports)| system_transfer(payer, tip_account, *lamports))
+            .collect()
+    }
+}
+
+/// Fans each transaction out to several relays (e.g. Triton Cascade endpoints in different
+/// regions alongside a plain RPC), succeeding when any of them accepts it
+#[derive(Debug, Default)]
+pub struct CascadeBroadcaster {
+    relays: Vec<Arc<dyn Broadcaster>>,
+}
+
+impl CascadeBroadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also submit to `relay`
+    pub fn with_relay(mut self, relay: Arc<dyn Broadcaster>) -> Self {
+        self.relays.push(relay);
+        self
+    }
+}
+
+impl Broadcaster for CascadeBroadcaster {
+    fn name(&self) -> &str {
+        "cascade"
+    }
+
+    fn broadcast(&self, transaction: &Transaction) -> Result<()> {
+        let mut errors = Vec::new();
+        for relay in &self.relays {
+            match relay.broadcast(transaction) {
+                Ok(()) => {}
+                Err(err) => {
+                    log::debug!("relay {} rejected transaction: {}", relay.name(), err);
+                    errors.push(err.to_string());
+                }
+            }
+        }
+        if !self.relays.is_empty() && errors.len() < self.relays.len() {
+            return Ok(());
+        }
+        Err(CarrotError::TransactionFailed(format!(
+            "no relay accepted the transaction: {}",
+            if errors.is_empty() { "no relays configured".to_string() } else { errors.join("; ") }
+        )))
+    }
+
+    fn tip_instructions(&self, payer: &Pubkey) -> Vec<Instruction> {
+        self.relays.iter().flat_map(|relay| relay.tip_instructions(payer)).collect()
+    }
+}
+
+/// System program `Transfer` (instruction index 2)
+fn system_transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = 2u32.to_le_bytes().to_vec();
+    data.extend(lamports.to_le_bytes());
+    Instruction::new_with_bytes(
+        system_program::id(),
+        &data,
+        vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct Relay {
+        accept: bool,
+        submitted: AtomicUsize,
+    }
+
+    impl Broadcaster for Relay {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn broadcast(&self, _transaction: &Transaction) -> Result<()> {
+            self.submitted.fetch_add(1, Ordering::Relaxed);
+            if self.accept {
+                Ok(())
+            } else {
+                Err(CarrotError::TransactionFailed("rejected".to_string()))
+            }
+        }
+    }
+
+    #[test]
+    fn test_cascade_succeeds_if_any_relay_accepts() {
+        let rejecting = Arc::new(Relay::default());
+        let accepting = Arc::new(Relay {
+            accept: true,
+            ..Default::default()
+        });
+        let transaction = Transaction::default();
+
+        let cascade = CascadeBroadcaster::new()
+            .with_relay(rejecting.clone())
+            .with_relay(accepting.clone());
+        assert!(cascade.broadcast(&transaction).is_ok());
+        assert_eq!(rejecting.submitted.load(Ordering::Relaxed), 1);
+        assert_eq!(accepting.submitted.load(Ordering::Relaxed), 1);
+
+        let cascade = CascadeBroadcaster::new().with_relay(rejecting.clone());
+        assert!(cascade.broadcast(&transaction).is_err());
+        assert!(CascadeBroadcaster::new().broadcast(&transaction).is_err());
+    }
+
+    #[test]
+    fn test_helius_sender_tips() {
+        let payer = Pubkey::new_unique();
+        let tip_account = Pubkey::new_unique();
+        assert!(HeliusSender::new("http://localhost:8899").tip_instructions(&payer).is_empty());
+
+        let sender = HeliusSender::new("http://localhost:8899").with_tip(tip_account, 200_000);
+        let cascade = CascadeBroadcaster::new().with_relay(Arc::new(sender));
+        let tips = cascade.tip_instructions(&payer);
+        assert_eq!(tips.len(), 1);
+        assert_eq!(tips[0].program_id, system_program::id());
+        assert_eq!(tips[0].accounts[1].pubkey, tip_account);
+        assert_eq!(tips[0].data[4..], 200_000u64.to_le_bytes());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk_ids::address_lookup_table;
+
+use crate::{
+    error::{CarrotError, Result},
+    oracle::OraclePrice,
+    token::TokenProgram,
+    Asset,
+};
+
+/// Default lifetime of the cached vault asset list
+pub const DEFAULT_VAULT_ASSETS_TTL: Duration = Duration::from_secs(60);
+
+/// Size of `LookupTableMeta`; addresses follow it
+const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// Offset of the `Option<Pubkey>` authority tag in `LookupTableMeta`
+const LOOKUP_TABLE_AUTHORITY_OFFSET: usize = 21;
+
+/// Lookups that don't need a fresh read on every call
+///
+/// Mint decimals and token program owners can't change, and neither can a frozen lookup
+/// table (one without an authority), so they are kept for the client's lifetime. The vault's
+/// asset list only changes when the vault authority adds an asset, so it is kept for a
+/// configurable TTL. Balances, prices and vault totals never go through the cache; the last
+/// price decoded from each oracle is only kept as the fallback of partial valuations.
+#[derive(Debug, Default)]
+pub(crate) struct RpcCache {
+    mint_decimals: Mutex<HashMap<Pubkey, u8>>,
+    token_programs: Mutex<HashMap<Pubkey, TokenProgram>>,
+    lookup_tables: Mutex<HashMap<Pubkey, Vec<Pubkey>>>,
+    vault_assets: Mutex<Option<(Instant, Vec<Asset>)>>,
+    oracle_prices: Mutex<HashMap<Pubkey, OraclePrice>>,
+}
+
+impl RpcCache {
+    pub(crate) fn mint_decimals(&self, mint: &Pubkey) -> Option<u8> {
+        lock(&self.mint_decimals).get(mint).copied()
+    }
+
+    pub(crate) fn set_mint_decimals(&self, mint: Pubkey, decimals: u8) {
+        lock(&self.mint_decimals).insert(mint, decimals);
+    }
+
+    pub(crate) fn token_program(&self, mint: &Pubkey) -> Option<TokenProgram> {
+        lock(&self.token_programs).get(mint).copied()
+    }
+
+    pub(crate) fn set_token_program(&self, mint: Pubkey, program: TokenProgram) {
+        lock(&self.token_programs).insert(mint, program);
+    }
+
+    pub(crate) fn lookup_table(&self, table: &Pubkey) -> Option<Vec<Pubkey>> {
+        lock(&self.lookup_tables).get(table).cloned()
+    }
+
+    /// Cache a lookup table's addresses, only if the table is frozen
+    pub(crate) fn set_lookup_table(&self, table: Pubkey, lookup_table: &LookupTable) {
+        if lookup_table.frozen {
+            lock(&self.lookup_tables).insert(table, lookup_table.addresses.clone());
+        }
+    }
+
+    /// Asset list cached less than `ttl` ago
+    pub(crate) fn vault_assets(&self, ttl: Duration, now: Instant) -> Option<Vec<Asset>> {
+        lock(&self.vault_assets)
+            .as_ref()
+            .filter(|(fetched_at, _)| now.duration_since(*fetched_at) < ttl)
+            .map(|(_, assets)| assets.clone())
+    }
+
+    pub(crate) fn set_vault_assets(&self, assets: Vec<Asset>, now: Instant) {
+        *lock(&self.vault_assets) = Some((now, assets));
+    }
+
+    /// Last price decoded from `oracle`, if published at most `max_age` seconds before `now`
+    pub(crate) fn oracle_price(&self, oracle: &Pubkey, max_age: u64, now: i64) -> Option<OraclePrice> {
+        lock(&self.oracle_prices)
+            .get(oracle)
+            .copied()
+            .filter(|price| now.saturating_sub(price.publish_time) <= max_age as i64)
+    }
+
+    /// Remember `price` for `oracle` unless a newer one is already kept
+    pub(crate) fn set_oracle_price(&self, oracle: Pubkey, price: OraclePrice) {
+        let mut prices = lock(&self.oracle_prices);
+        let kept = prices.entry(oracle).or_insert(price);
+        if price.publish_time > kept.publish_time {
+            *kept = price;
+        }
+    }
+
+    /// Forget everything, e.g. after switching clusters in tests
+    pub(crate) fn clear(&self) {
+        lock(&self.mint_decimals).clear();
+        lock(&self.token_programs).clear();
+        lock(&self.lookup_tables).clear();
+        *lock(&self.vault_assets) = None;
+        lock(&self.oracle_prices).clear();
+    }
+}
+
+/// Decoded address lookup table account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LookupTable {
+    pub(crate) addresses: Vec<Pubkey>,
+    /// No authority: the table can never be extended or closed
+    pub(crate) frozen: bool,
+}
+
+impl LookupTable {
+    /// Decode an account owned by the address lookup table program
+    pub(crate) fn from_account(owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        if owner != &address_lookup_table::id() || data.len() < LOOKUP_TABLE_META_SIZE {
+            return Err(CarrotError::InvalidConfig("not an address lookup table".to_string()));
+        }
+        let addresses = data[LOOKUP_TABLE_META_SIZE..]
+            .chunks_exact(32)
+            .map(|chunk| Pubkey::new_from_array(chunk.try_into().expect("chunks are 32 bytes")))
+            .collect();
+        Ok(Self {
+            addresses,
+            frozen: data[LOOKUP_TABLE_AUTHORITY_OFFSET] == 0,
+        })
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // Entries are replaced whole, a panic elsewhere can't leave them inconsistent
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup_table_data(authority: Option<Pubkey>, addresses: &[Pubkey]) -> Vec<u8> {
+        let mut data = vec![0u8; LOOKUP_TABLE_META_SIZE];
+        data[0] = 1; // LookupTable discriminator
+        if let Some(authority) = authority {
+            data[LOOKUP_TABLE_AUTHORITY_OFFSET] = 1;
+            data[LOOKUP_TABLE_AUTHORITY_OFFSET + 1..LOOKUP_TABLE_AUTHORITY_OFFSET + 33]
+                .copy_from_slice(authority.as_ref());
+        }
+        for address in addresses {
+            data.extend_from_slice(address.as_ref());
+        }
+        data
+    }
+
+    fn asset(asset_id: u16) -> Asset {
+        Asset {
+            asset_id,
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            ata: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn test_only_frozen_lookup_tables_are_cached() {
+        let cache = RpcCache::default();
+        let addresses = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let owner = address_lookup_table::id();
+        let (frozen, mutable) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let table = LookupTable::from_account(&owner, &lookup_table_data(None, &addresses)).unwrap();
+        assert_eq!(table.addresses, addresses);
+        cache.set_lookup_table(frozen, &table);
+
+        let data = lookup_table_data(Some(Pubkey::new_unique()), &addresses);
+        let table = LookupTable::from_account(&owner, &data).unwrap();
+        assert!(!table.frozen);
+        cache.set_lookup_table(mutable, &table);
+
+        assert_eq!(cache.lookup_table(&frozen), Some(addresses));
+        assert_eq!(cache.lookup_table(&mutable), None);
+        assert!(LookupTable::from_account(&Pubkey::new_unique(), &data).is_err());
+    }
+
+    #[test]
+    fn test_vault_assets_expire_after_ttl() {
+        let cache = RpcCache::default();
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        assert!(cache.vault_assets(ttl, now).is_none());
+
+        cache.set_vault_assets(vec![asset(0), asset(1)], now);
+        assert_eq!(cache.vault_assets(ttl, now + Duration::from_secs(59)).unwrap().len(), 2);
+        assert!(cache.vault_assets(ttl, now + ttl).is_none());
+        // A zero TTL disables the cache
+        assert!(cache.vault_assets(Duration::ZERO, now).is_none());
+
+        cache.set_mint_decimals(Pubkey::new_unique(), 6);
+        cache.clear();
+        assert!(cache.vault_assets(ttl, now).is_none());
+    }
+
+    #[test]
+    fn test_oracle_price_fallback_ages_out() {
+        let cache = RpcCache::default();
+        let oracle = Pubkey::new_unique();
+        let price = |price, publish_time| OraclePrice {
+            price,
+            conf: 0,
+            exponent: -8,
+            publish_time,
+        };
+        cache.set_oracle_price(oracle, price(100, 1_000));
+        // An older price never replaces a newer one
+        cache.set_oracle_price(oracle, price(90, 900));
+        assert_eq!(cache.oracle_price(&oracle, 60, 1_060), Some(price(100, 1_000)));
+        assert_eq!(cache.oracle_price(&oracle, 60, 1_061), None);
+        assert_eq!(cache.oracle_price(&Pubkey::new_unique(), 60, 1_000), None);
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Write;
+use std::ops::Range;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use crate::{
+    display::DisplayRounding,
+    error::{CarrotError, Result},
+    nav::VALUE_DECIMALS,
+    twap::{SharePriceHistory, SharePriceSample},
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Open, high, low and close CRT price over one interval
+///
+/// Serializes with the prices as USD numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PriceCandle {
+    /// Unix timestamp the interval starts at (a multiple of the granularity)
+    pub start: i64,
+    /// Prices are USD values (`VALUE_DECIMALS`) of one whole CRT
+    #[serde(serialize_with = "usd_number")]
+    pub open: u128,
+    #[serde(serialize_with = "usd_number")]
+    pub high: u128,
+    #[serde(serialize_with = "usd_number")]
+    pub low: u128,
+    #[serde(serialize_with = "usd_number")]
+    pub close: u128,
+    /// Slots of the first and last sample in the interval
+    pub first_slot: u64,
+    pub last_slot: u64,
+    /// Samples in the interval; 0 for an interval without samples, carried from the previous close
+    pub samples: usize,
+}
+
+impl PriceCandle {
+    fn open_at(start: i64, sample: &SharePriceSample) -> Self {
+        Self {
+            start,
+            open: sample.price,
+            high: sample.price,
+            low: sample.price,
+            close: sample.price,
+            first_slot: sample.slot,
+            last_slot: sample.slot,
+            samples: 1,
+        }
+    }
+
+    fn add(&mut self, sample: &SharePriceSample) {
+        if self.samples == 0 {
+            *self = Self::open_at(self.start, sample);
+            return;
+        }
+        self.high = self.high.max(sample.price);
+        self.low = self.low.min(sample.price);
+        self.close = sample.price;
+        self.last_slot = sample.slot;
+        self.samples += 1;
+    }
+
+    /// Flat candle at `start` holding the previous close
+    fn carried(start: i64, previous: &PriceCandle) -> Self {
+        Self {
+            start,
+            open: previous.close,
+            high: previous.close,
+            low: previous.close,
+            close: previous.close,
+            first_slot: previous.last_slot,
+            last_slot: previous.last_slot,
+            samples: 0,
+        }
+    }
+}
+
+impl SharePriceHistory {
+    /// OHLC candles of `granularity` covering the Unix timestamps in `range`
+    ///
+    /// Intervals are aligned to multiples of `granularity` since the epoch. Intervals without
+    /// samples repeat the previous close (with `samples == 0`), so the series has no gaps once
+    /// it starts; the first candle is the first interval in `range` with a sample.
+    pub fn candles(&self, granularity: Duration, range: Range<i64>) -> Result<Vec<PriceCandle>> {
+        let step = granularity.as_secs() as i64;
+        if step == 0 {
+            return Err(CarrotError::InvalidConfig("candle granularity must be at least a second".to_string()));
+        }
+        let bucket = |timestamp: i64| timestamp.div_euclid(step) * step;
+
+        let mut candles: Vec<PriceCandle> = Vec::new();
+        for sample in self.samples().filter(|sample| range.contains(&sample.timestamp)) {
+            let start = bucket(sample.timestamp);
+            if let Some(last) = candles.last_mut().filter(|last| last.start == start) {
+                last.add(sample);
+                continue;
+            }
+            if let Some(previous) = candles.last().copied() {
+                let mut gap = previous.start + step;
+                while gap < start {
+                    candles.push(PriceCandle::carried(gap, &previous));
+                    gap += step;
+                }
+            }
+            candles.push(PriceCandle::open_at(start, sample));
+        }
+        // Carry the last close to the end of the range
+        if let Some(previous) = candles.last().copied() {
+            let mut gap = previous.start + step;
+            while gap < range.end {
+                candles.push(PriceCandle::carried(gap, &previous));
+                gap += step;
+            }
+        }
+        Ok(candles)
+    }
+}
+
+/// Render candles as CSV with a header row, prices as decimal USD
+pub fn candles_to_csv(candles: &[PriceCandle]) -> String {
+    let mut out = String::from("start,open,high,low,close,first_slot,last_slot,samples\n");
+    for candle in candles {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{}",
+            candle.start,
+            usd(candle.open),
+            usd(candle.high),
+            usd(candle.low),
+            usd(candle.close),
+            candle.first_slot,
+            candle.last_slot,
+            candle.samples
+        );
+    }
+    out
+}
+
+/// Render candles as a JSON array, prices as decimal USD numbers
+pub fn candles_to_json(candles: &[PriceCandle]) -> Result<String> {
+    serde_json::to_string(candles).map_err(|e| CarrotError::InvalidConfig(format!("failed to encode candles: {}", e)))
+}
+
+/// Exact decimal form of a `VALUE_DECIMALS` value
+fn usd(value: u128) -> String {
+    DisplayRounding::Floor.format(value, VALUE_DECIMALS, VALUE_DECIMALS)
+}
+
+/// A `VALUE_DECIMALS` value as a JSON number; share prices have few enough digits to round-trip
+fn usd_number<S: Serializer>(value: &u128, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(*value as f64 / 10f64.powi(VALUE_DECIMALS as i32))
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// OHLC share price candles of `granularity` over the Unix timestamps in `range`, for charts
+    /// and external risk systems
+    ///
+    /// Built from the samples `spawn_share_price_sampler` keeps in the configured state store
+    /// (`ClientConfig::with_state_store`); the candles can only be as fine as the sampling.
+    pub fn export_price_history(&self, granularity: Duration, range: Range<i64>) -> Result<Vec<PriceCandle>> {
+        let store = self.config().state_store.as_ref().ok_or_else(|| {
+            CarrotError::InvalidConfig("export_price_history needs a state store with share price samples".to_string())
+        })?;
+        SharePriceHistory::load_from(store.as_ref())?.candles(granularity, range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: i64, price: u128) -> SharePriceSample {
+        SharePriceSample {
+            timestamp,
+            slot: timestamp as u64 * 2,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_candles_aggregate_and_fill_gaps() {
+        let mut history = SharePriceHistory::new();
+        for (timestamp, price) in [(5, 100), (30, 120), (50, 90), (59, 110), (190, 130)] {
+            history.record(sample(timestamp, price));
+        }
+
+        let candles = history.candles(Duration::from_secs(60), 0..240).unwrap();
+        assert_eq!(candles.iter().map(|c| c.start).collect::<Vec<_>>(), [0, 60, 120, 180]);
+        assert_eq!(
+            candles[0],
+            PriceCandle {
+                start: 0,
+                open: 100,
+                high: 120,
+                low: 90,
+                close: 110,
+                first_slot: 10,
+                last_slot: 118,
+                samples: 4,
+            }
+        );
+        // No samples: the previous close carries over
+        assert_eq!((candles[1].open, candles[1].close, candles[1].samples), (110, 110, 0));
+        assert_eq!((candles[3].open, candles[3].samples), (130, 1));
+
+        // The range filters samples, and nothing precedes the first sample in it
+        let candles = history.candles(Duration::from_secs(60), 40..120).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!((candles[0].open, candles[0].samples), (90, 2));
+
+        assert!(history.candles(Duration::ZERO, 0..240).is_err());
+    }
+
+    #[test]
+    fn test_candle_export_formats() {
+        let candles = [PriceCandle {
+            start: 60,
+            open: 1_100_000_000_000,
+            high: 1_100_000_000_000,
+            low: 1_100_000_000_000,
+            close: 1_100_000_000_000,
+            first_slot: 7,
+            last_slot: 7,
+            samples: 1,
+        }];
+        let csv = candles_to_csv(&candles);
+        assert_eq!(csv.lines().nth(1), Some("60,1.100000000000,1.100000000000,1.100000000000,1.100000000000,7,7,1"));
+        let json: serde_json::Value = serde_json::from_str(&candles_to_json(&candles).unwrap()).unwrap();
+        assert_eq!(json[0]["close"].as_f64(), Some(1.1));
+        assert_eq!(json[0]["last_slot"], 7);
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+
+use crate::error::{CarrotError, Result};
+
+/// Default consecutive failures after which an endpoint is skipped
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default time an endpoint is skipped for once its circuit opens
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// When an RPC endpoint is taken out of the send/failover rotation
+///
+/// After `failure_threshold` consecutive transport failures (connection errors, timeouts,
+/// HTTP errors) the endpoint's circuit opens and it is skipped for `cooldown`. Then one trial
+/// request is let through: success closes the circuit, failure opens it for another cooldown.
+/// Transaction errors don't count, the endpoint answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit skips the endpoint
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    /// Set while the circuit is open (or a half-open trial is in flight)
+    open_until: Option<Instant>,
+}
+
+/// Per-endpoint circuit state; endpoint `0` is the primary, `n` the n-th fallback
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    policy: Option<CircuitBreakerPolicy>,
+    endpoints: Mutex<Vec<EndpointState>>,
+}
+
+impl CircuitBreaker {
+    /// Breaker for `endpoints` endpoints; never opens without a policy
+    pub(crate) fn new(policy: Option<CircuitBreakerPolicy>, endpoints: usize) -> Self {
+        Self {
+            policy,
+            endpoints: Mutex::new(vec![EndpointState::default(); endpoints]),
+        }
+    }
+
+    /// Fail with `CircuitOpen` if `endpoint` is cooling down
+    ///
+    /// Once the cooldown has passed, the first caller gets the trial request and the
+    /// circuit stays open for everyone else until it reports back.
+    pub(crate) fn check(&self, endpoint: usize, now: Instant) -> Result<()> {
+        let Some(policy) = self.policy else {
+            return Ok(());
+        };
+        let mut endpoints = self.lock();
+        let state = &mut endpoints[endpoint];
+        match state.open_until {
+            Some(until) if now < until => Err(CarrotError::CircuitOpen {
+                endpoint,
+                retry_after: until - now,
+            }),
+            Some(_) => {
+                state.open_until = Some(now + policy.cooldown);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record the outcome of a request to `endpoint`; returns the cooldown if this opened the circuit
+    pub(crate) fn record<T>(
+        &self,
+        endpoint: usize,
+        result: &std::result::Result<T, ClientError>,
+        now: Instant,
+    ) -> Option<Duration> {
+        let policy = self.policy?;
+        let mut endpoints = self.lock();
+        let state = &mut endpoints[endpoint];
+        match result {
+            Err(err) if is_endpoint_failure(err) => {
+                state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+                if state.consecutive_failures >= policy.failure_threshold {
+                    state.open_until = Some(now + policy.cooldown);
+                    return Some(policy.cooldown);
+                }
+                None
+            }
+            _ => {
+                *state = EndpointState::default();
+                None
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<EndpointState>> {
+        // States are plain values replaced whole, a panic elsewhere can't leave them inconsistent
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether the endpoint itself failed (unreachable, timed out, HTTP error), as opposed to
+/// answering with an RPC or transaction error
+fn is_endpoint_failure(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::Io(_)
+            | ClientErrorKind::Reqwest(_)
+            | ClientErrorKind::Middleware(_)
+            | ClientErrorKind::RpcError(RpcError::RpcRequestError(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure() -> std::result::Result<(), ClientError> {
+        Err(ClientErrorKind::Io(std::io::Error::other("connection refused")).into())
+    }
+
+    fn breaker() -> CircuitBreaker {
+        let policy = CircuitBreakerPolicy {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(10),
+        };
+        CircuitBreaker::new(Some(policy), 2)
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = breaker();
+        let now = Instant::now();
+        assert_eq!(breaker.record(0, &failure(), now), None);
+        assert!(breaker.check(0, now).is_ok());
+        assert_eq!(breaker.record(0, &failure(), now), Some(Duration::from_secs(10)));
+        assert!(matches!(
+            breaker.check(0, now + Duration::from_secs(4)),
+            Err(CarrotError::CircuitOpen { endpoint: 0, retry_after }) if retry_after == Duration::from_secs(6)
+        ));
+        // Other endpoints are unaffected
+        assert!(breaker.check(1, now).is_ok());
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let breaker = breaker();
+        let now = Instant::now();
+        breaker.record(0, &failure(), now);
+        breaker.record(0, &Ok(()), now);
+        assert_eq!(breaker.record(0, &failure(), now), None);
+        assert!(breaker.check(0, now).is_ok());
+    }
+
+    #[test]
+    fn test_half_open_allows_single_trial() {
+        let breaker = breaker();
+        let now = Instant::now();
+        breaker.record(0, &failure(), now);
+        breaker.record(0, &failure(), now);
+
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.check(0, later).is_ok());
+        assert!(breaker.check(0, later).is_err());
+        // A failed trial opens the circuit again straight away
+        assert!(breaker.record(0, &failure(), later).is_some());
+
+        let much_later = later + Duration::from_secs(10);
+        assert!(breaker.check(0, much_later).is_ok());
+        breaker.record(0, &Ok(()), much_later);
+        assert!(breaker.check(0, much_later).is_ok());
+    }
+
+    #[test]
+    fn test_rpc_errors_from_a_live_endpoint_dont_count() {
+        let breaker = breaker();
+        let now = Instant::now();
+        let answered: std::result::Result<(), ClientError> =
+            Err(ClientErrorKind::RpcError(RpcError::ForUser("blockhash not found".to_string())).into());
+        breaker.record(0, &answered, now);
+        breaker.record(0, &answered, now);
+        assert!(breaker.check(0, now).is_ok());
+
+        let disabled = CircuitBreaker::new(None, 1);
+        disabled.record(0, &failure(), now);
+        disabled.record(0, &failure(), now);
+        assert!(disabled.check(0, now).is_ok());
+    }
+}
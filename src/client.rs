@@ -1,83 +1,654 @@
-use borsh::BorshDeserialize;
-use solana_client::rpc_client::RpcClient;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(feature = "blocking")]
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Receiver, Arc},
+    thread::sleep,
+    time::Instant,
+};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig},
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+    rpc_request::RpcError,
+    rpc_response::{Response, RpcSimulateTransactionResult},
+};
 use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::http_sender::HttpSender;
+use solana_transaction_status_client_types::TransactionStatus;
 use solana_sdk::{
-    instruction::Instruction,
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
     pubkey::Pubkey,
-    signature::{Keypair, Signature},
-    signer::Signer,
+    signature::Signature,
     transaction::Transaction,
 };
-use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
-use spl_token_2022_interface;
+#[cfg(feature = "blocking")]
+use solana_client::{
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_config::RpcSimulateTransactionAccountsConfig,
+};
+#[cfg(feature = "blocking")]
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+#[cfg(feature = "blocking")]
+use solana_packet::PACKET_DATA_SIZE;
+#[cfg(feature = "blocking")]
+use solana_sdk::{hash::Hash, signer::Signer};
+#[cfg(feature = "blocking")]
+use solana_transaction_error::TransactionError;
 
 use crate::{
-    accounts::{get_token_program_id, get_user_asset_ata, get_user_crt_ata},
-    error::{CarrotError, Result},
-    instructions::{build_issue_instruction, build_redeem_instruction},
-    Vault, VAULT_ADDRESS,
+    allowlist::ProgramAllowlist,
+    compute_units::{compute_budget_instructions, MAX_COMPUTE_UNIT_LIMIT},
+    config::ClientConfig,
+    error::{CarrotError, ErrorContext, Operation, Result, ResultExt},
+    instructions::{
+        build_issue_instruction_with_accounts, build_redeem_instruction_with_accounts, log_account_metas,
+        IssueAccounts, RedeemAccounts,
+    },
+    limits::VaultLimits,
+    memo::{MemoAction, SdkMemo},
+    nav::{Nav, Rounding},
+    snapshot::VaultSnapshot,
+    token::TokenProgram,
+    Vault, VaultHandle, CRT_MINT,
 };
+#[cfg(feature = "blocking")]
+use crate::{
+    accounts::get_user_crt_ata,
+    approval::{Approval, PendingOperation},
+    broadcast::Broadcaster,
+    cache::{LookupTable, RpcCache},
+    circuit_breaker::CircuitBreaker,
+    compute_units::{limit_with_margin, ComputeUnitLimit, ComputeUnitPrice, DepositOptions},
+    effects::{BalanceEffects, ExpectedEffects},
+    events::{ClientEvent, EventBus},
+    inspect::inspect,
+    instructions::CarrotInstruction,
+    ledger::{validate_intent, LedgerEntry, SignatureLedger},
+    liquidity::{ExitRanking, RedemptionPlan, WithdrawalLiquidity},
+    metadata::{metaplex_metadata_address, MintMetadata, METAPLEX_METADATA_PROGRAM_ID},
+    nav::{deduct_fee_bps, AssetAmount, CrtAmount, PartialNav},
+    policy::{DepositExposure, PolicyAction, PolicyLegs},
+    prepared::PreparedTransaction,
+    priority_fee::{PriorityFeeEstimator, PriorityFeePolicy},
+    snapshot::{build_snapshot, snapshot_accounts},
+    stream::BoundedStream,
+    token::{check_token_account, transfer_fee, unpack_mint_decimals, unpack_token_amount},
+    transfer_hook::HookedTransfer,
+    vault_info::VaultInfo,
+    Asset, VAULT_ADDRESS,
+};
+
+/// Interval between signature status checks while waiting for finalization
+const FINALIZATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(feature = "blocking")]
+/// How long to wait for a transaction sent through a `Broadcaster` before submitting it again
+const BROADCAST_RESEND_INTERVAL: Duration = Duration::from_secs(2);
 
+/// Attempts at reading a single-slot vault snapshot before giving up
+const MAX_SNAPSHOT_ATTEMPTS: usize = 5;
+
+/// Delay before retrying a snapshot read that hit a lagging endpoint
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_millis(400);
+
+#[cfg(feature = "blocking")]
+/// Client for the Carrot Protocol
+///
+/// Cloning is cheap: all clones share the same underlying RPC client, so a single
+/// instance can be handed to multiple threads, tokio tasks or axum handlers.
+#[derive(Clone)]
 pub struct CarrotClient {
+    inner: Arc<ClientInner>,
+}
+
+#[cfg(feature = "blocking")]
+/// Shared state behind a `CarrotClient`
+struct ClientInner {
+    rpc: Arc<RpcPool>,
+    config: ClientConfig,
+    program_allowlist: ProgramAllowlist,
+    /// Newest slot observed in a snapshot read, used as `min_context_slot`
+    last_context_slot: AtomicU64,
+    events: EventBus,
+    /// Transactions sent per intent by `deposit_once`/`withdraw_once`
+    ledger: SignatureLedger,
+    /// Immutable and semi-static lookups
+    cache: RpcCache,
+}
+
+#[cfg(feature = "blocking")]
+/// RPC connections of a client, shared with the clients created by `with_shared_rpc`
+struct RpcPool {
     rpc_client: RpcClient,
+    fallback_rpc_clients: Vec<RpcClient>,
+    /// Endpoints skipped for sending after repeated failures
+    circuit_breaker: CircuitBreaker,
 }
 
+#[cfg(feature = "blocking")]
+// Compile-time check that the client can be shared across threads and tasks
+const _: () = {
+    const fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+    assert_send_sync_clone::<CarrotClient>();
+};
+
+#[cfg(feature = "blocking")]
 impl CarrotClient {
     /// Create a new Carrot client with the given RPC URL
     pub fn new(rpc_url: String) -> Self {
-        let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
-        Self { rpc_client }
+        Self::with_config(ClientConfig::new(rpc_url)).expect("default client config is valid")
+    }
+
+    /// Create a new Carrot client from a full configuration
+    /// (custom timeout, auth headers for private RPC endpoints, commitment)
+    pub fn with_config(config: ClientConfig) -> Result<Self> {
+        let rpc_client = build_rpc_client(&config, &config.rpc_url)?;
+        if config.ws_url.is_some() || !config.fallback_ws_urls.is_empty() {
+            config.ws_endpoints()?;
+        }
+        let fallback_rpc_clients = config
+            .fallback_rpc_urls
+            .iter()
+            .map(|url| build_rpc_client(&config, url))
+            .collect::<Result<Vec<_>>>()?;
+        let circuit_breaker = CircuitBreaker::new(config.circuit_breaker, 1 + fallback_rpc_clients.len());
+        let rpc = RpcPool {
+            rpc_client,
+            fallback_rpc_clients,
+            circuit_breaker,
+        };
+        Self::with_rpc_pool(config, Arc::new(rpc))
+    }
+
+    /// Client with its own configuration (policies, limits, fees, memo IDs, state store, ...)
+    /// sending through this client's RPC connections
+    ///
+    /// The endpoints, headers, timeout, commitment and circuit breaker of `config` are replaced
+    /// by this client's, and endpoint failures count toward the same circuits.
+    pub fn with_shared_rpc(&self, mut config: ClientConfig) -> Result<Self> {
+        let pool = self.config();
+        config.rpc_url = pool.rpc_url.clone();
+        config.fallback_rpc_urls = pool.fallback_rpc_urls.clone();
+        config.ws_url = pool.ws_url.clone();
+        config.fallback_ws_urls = pool.fallback_ws_urls.clone();
+        config.circuit_breaker = pool.circuit_breaker;
+        config.commitment = pool.commitment;
+        config.timeout = pool.timeout;
+        config.headers = pool.headers.clone();
+        Self::with_rpc_pool(config, Arc::clone(&self.inner.rpc))
+    }
+
+    fn with_rpc_pool(config: ClientConfig, rpc: Arc<RpcPool>) -> Result<Self> {
+        config.validate_policies()?;
+        let program_allowlist = ProgramAllowlist::with_extra(&config.extra_allowed_programs);
+        let ledger = match (&config.state_store, &config.signature_ledger) {
+            (Some(store), _) => SignatureLedger::with_store(Arc::clone(store))?,
+            (None, Some(path)) => SignatureLedger::open(path)?,
+            (None, None) => SignatureLedger::in_memory(),
+        };
+        let client = Self {
+            inner: Arc::new(ClientInner {
+                rpc,
+                program_allowlist,
+                config,
+                last_context_slot: AtomicU64::new(0),
+                events: EventBus::default(),
+                ledger,
+                cache: RpcCache::default(),
+            }),
+        };
+        if client.config().validate_on_startup {
+            client.validate()?.into_result()?;
+        }
+        Ok(client)
+    }
+
+    /// The configuration this client was created with
+    pub fn config(&self) -> &ClientConfig {
+        &self.inner.config
+    }
+
+    /// Access the underlying RPC client
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.inner.rpc.rpc_client
+    }
+
+    /// Current Unix timestamp per `ClientConfig::clock`
+    pub(crate) fn now(&self) -> i64 {
+        self.config().clock.unix_timestamp()
+    }
+
+    /// Blockhash to sign with and its last valid block height, from the configured
+    /// `BlockhashSource` or else the RPC endpoint
+    pub(crate) fn latest_blockhash(&self) -> Result<(Hash, u64)> {
+        match &self.config().blockhash_source {
+            Some(source) => source.latest_blockhash(),
+            None => Ok(self
+                .rpc_client()
+                .get_latest_blockhash_with_commitment(self.rpc_client().commitment())?),
+        }
+    }
+
+    /// Subscribe to lifecycle events (vault reads, retries, sent and confirmed transactions)
+    ///
+    /// Events are buffered in the returned channel until received; dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe(&self) -> Receiver<ClientEvent> {
+        self.inner.events.subscribe()
+    }
+
+    pub(crate) fn subscribe_stream(&self, capacity: usize) -> BoundedStream<ClientEvent> {
+        self.inner.events.subscribe_stream(capacity)
+    }
+
+    pub(crate) fn emit(&self, event: ClientEvent) {
+        self.inner.events.emit(event);
     }
 
     /// Fetch and deserialize vault data from the blockchain
     pub fn fetch_vault(&self) -> Result<Vault> {
         let account = self
-            .rpc_client
+            .rpc_client()
             .get_account(&VAULT_ADDRESS)
-            .map_err(|_| CarrotError::AccountNotFound("Vault account not found".to_string()))?;
-
-        // Account data starts with 8-byte discriminator (Anchor), skip it
-        let data = &account.data[8..];
-        
-        Vault::try_from_slice(data).map_err(|e| {
-            eprintln!("Failed to deserialize vault data: {:?}", e);
-            CarrotError::InvalidVaultData
-        })
+            .map_err(|_| CarrotError::AccountNotFound("Vault account not found".to_string()))
+            .during(Operation::FetchVault)?;
+
+        let vault = Vault::from_account_data(&account.data)?;
+        self.inner.cache.set_vault_assets(vault.assets.clone(), Instant::now());
+        self.emit(ClientEvent::VaultFetched);
+        Ok(vault)
+    }
+
+    /// The vault's asset list, served from cache for `ClientConfig::vault_assets_ttl`
+    ///
+    /// Assets are only added by the vault authority, so bots listing ATAs and oracles don't
+    /// need to re-read the vault each time. Use `fetch_vault` for pause state and balances.
+    pub fn get_vault_assets(&self) -> Result<Vec<Asset>> {
+        match self.inner.cache.vault_assets(self.config().vault_assets_ttl, Instant::now()) {
+            Some(assets) => Ok(assets),
+            None => Ok(self.fetch_vault()?.assets),
+        }
+    }
+
+    /// Decimals of `mint`, cached for the client's lifetime
+    pub fn get_mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        if let Some(decimals) = self.inner.cache.mint_decimals(mint) {
+            return Ok(decimals);
+        }
+        let account = self.rpc_client().get_account(mint)?;
+        let decimals = unpack_mint_decimals(&account.data)?;
+        self.inner.cache.set_mint_decimals(*mint, decimals);
+        Ok(decimals)
+    }
+
+    /// Token program owning `mint`, cached for the client's lifetime
+    pub fn get_token_program(&self, mint: &Pubkey) -> Result<TokenProgram> {
+        if let Some(program) = self.inner.cache.token_program(mint) {
+            return Ok(program);
+        }
+        let account = self.rpc_client().get_account(mint)?;
+        let program = TokenProgram::from_id(&account.owner)
+            .ok_or_else(|| CarrotError::InvalidAsset(format!("{} is not owned by a token program", mint)))?;
+        self.inner.cache.set_token_program(*mint, program);
+        Ok(program)
+    }
+
+    /// Symbol, name, logo URI and decimals of `mint`, for displaying vault assets
+    ///
+    /// Reads the mint (for Token-2022 metadata) and its Metaplex metadata account in one
+    /// request, falling back to the SDK's table of known mints. Not cached: metadata can be
+    /// updated by its authority.
+    pub fn resolve_mint_metadata(&self, mint: &Pubkey) -> Result<MintMetadata> {
+        let metaplex_address = metaplex_metadata_address(mint);
+        let mut accounts = self
+            .rpc_client()
+            .get_multiple_accounts(&[*mint, metaplex_address])?
+            .into_iter();
+        let mint_account = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| CarrotError::AccountNotFound(format!("mint {} not found", mint)))?;
+        let metaplex_account = accounts
+            .next()
+            .flatten()
+            .filter(|account| account.owner == METAPLEX_METADATA_PROGRAM_ID);
+
+        let metadata = MintMetadata::from_accounts(
+            mint,
+            &mint_account.data,
+            metaplex_account.as_ref().map(|account| account.data.as_slice()),
+        )?;
+        self.inner.cache.set_mint_decimals(*mint, metadata.decimals);
+        if let Some(program) = TokenProgram::from_id(&mint_account.owner) {
+            self.inner.cache.set_token_program(*mint, program);
+        }
+        Ok(metadata)
+    }
+
+    /// Addresses stored in an address lookup table
+    ///
+    /// Frozen tables (no authority) can never change and are cached for the client's
+    /// lifetime; tables that can still be extended are read fresh every time.
+    pub fn get_lookup_table_addresses(&self, table: &Pubkey) -> Result<Vec<Pubkey>> {
+        if let Some(addresses) = self.inner.cache.lookup_table(table) {
+            return Ok(addresses);
+        }
+        let account = self.rpc_client().get_account(table)?;
+        let lookup_table = LookupTable::from_account(&account.owner, &account.data)?;
+        self.inner.cache.set_lookup_table(*table, &lookup_table);
+        Ok(lookup_table.addresses)
+    }
+
+    /// Drop all cached lookups
+    pub fn clear_cache(&self) {
+        self.inner.cache.clear();
+    }
+
+    /// Fetch the vault, CRT supply, vault asset balances and oracle accounts as of a single slot
+    ///
+    /// Reads never go back in time: every request carries `min_context_slot` set to the
+    /// newest slot this client has seen, and is retried when the endpoint lags behind it
+    /// or the vault's asset list changes between the two reads.
+    pub fn fetch_snapshot(&self) -> Result<VaultSnapshot> {
+        self.fetch_snapshot_with_accounts().map(|(snapshot, _)| snapshot)
+    }
+
+    /// `fetch_snapshot` with the raw accounts it was built from, in `snapshot_accounts` order
+    pub(crate) fn fetch_snapshot_with_accounts(&self) -> Result<(VaultSnapshot, Vec<Option<Account>>)> {
+        for attempt in 0..MAX_SNAPSHOT_ATTEMPTS {
+            if attempt > 0 {
+                self.emit(ClientEvent::RetryScheduled {
+                    operation: "fetch_snapshot",
+                    attempt,
+                    delay: SNAPSHOT_RETRY_DELAY,
+                });
+                sleep(SNAPSHOT_RETRY_DELAY);
+            }
+
+            let min_slot = self.inner.last_context_slot.load(Ordering::Acquire);
+            let Some((vault_slot, accounts)) = self
+                .get_accounts_at_slot(&[VAULT_ADDRESS], min_slot)
+                .during(Operation::FetchSnapshot)?
+            else {
+                continue;
+            };
+            let vault = snapshot_vault(accounts)?;
+
+            // Re-read the vault together with everything else so all values share one slot
+            let Some((slot, accounts)) = self
+                .get_accounts_at_slot(&snapshot_accounts(&vault), vault_slot)
+                .during(Operation::FetchSnapshot)?
+            else {
+                continue;
+            };
+            if let Some(snapshot) = build_snapshot(slot, &vault, accounts.clone())? {
+                self.emit(ClientEvent::SnapshotFetched { slot });
+                return Ok((snapshot, accounts));
+            }
+        }
+
+        inconsistent_snapshot()
+    }
+
+    /// Compute the vault's net asset value from a single-slot snapshot,
+    /// net of the management fee accrued since the vault's last fee update
+    pub fn fetch_nav(&self) -> Result<Nav> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        self.remember_oracle_prices(&snapshot, &nav);
+        Ok(nav)
+    }
+
+    /// `fetch_nav` that values the assets whose oracles can be read instead of failing
+    /// when one can't
+    ///
+    /// With `ClientConfig::stale_price_max_age` set, such an asset is valued at the last price
+    /// this client decoded from its oracle if recent enough, and left out otherwise; either
+    /// way `PartialNav::issues` names it and says why.
+    pub fn fetch_nav_partial(&self) -> Result<PartialNav> {
+        self.partial_nav(&self.fetch_snapshot()?)
+    }
+
+    /// NAV of `snapshot` as computed by `fetch_nav_partial`
+    pub(crate) fn partial_nav(&self, snapshot: &VaultSnapshot) -> Result<PartialNav> {
+        let now = self.now();
+        let max_age = self.config().stale_price_max_age;
+        let partial = Nav::from_snapshot_partial(snapshot, now, |oracle| {
+            max_age.and_then(|max_age| self.inner.cache.oracle_price(oracle, max_age.as_secs(), now))
+        })?;
+        self.remember_oracle_prices(snapshot, &partial.nav);
+        Ok(partial)
+    }
+
+    /// Keep the prices `nav` was computed at as fallbacks for `fetch_nav_partial`
+    fn remember_oracle_prices(&self, snapshot: &VaultSnapshot, nav: &Nav) {
+        for valuation in &nav.assets {
+            if let Some(asset) = snapshot.asset(&valuation.mint) {
+                self.inner.cache.set_oracle_price(asset.asset.oracle, valuation.price);
+            }
+        }
+    }
+
+    /// Preview the CRT minted for depositing `amount` of `asset_mint` at the current NAV
+    pub fn preview_deposit(&self, asset_mint: &Pubkey, amount: u64) -> Result<u64> {
+        let nav = self.fetch_nav()?;
+        let shares = AssetAmount::new(*asset_mint, amount).to_expected_shares(&nav, Rounding::Down)?;
+        Ok(shares.0)
+    }
+
+    /// Preview the amount of `asset_mint` received for redeeming `shares` CRT at the current NAV,
+    /// net of the redemption fee
+    pub fn preview_withdraw(&self, asset_mint: &Pubkey, shares: u64) -> Result<u64> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        let gross = CrtAmount(shares).to_asset_value(&nav, asset_mint, Rounding::Down)?.amount;
+        deduct_fee_bps(gross, snapshot.vault.fee.redemption_fee_bps)
+    }
+
+    /// Current deposit limits: pause state, vault value and the configured bounds
+    pub fn get_limits(&self) -> Result<VaultLimits> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        self.limits_at(&snapshot, &nav)
+    }
+
+    /// Fail with a descriptive error if depositing `amount` of `asset_mint` would exceed the
+    /// configured bounds or the vault is paused
+    pub fn check_deposit_limits(&self, asset_mint: &Pubkey, amount: u64) -> Result<()> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        let value = nav.asset(asset_mint)?.value_of(amount, Rounding::Down)?;
+        self.limits_at(&snapshot, &nav)?.check_deposit(value)
+    }
+
+    /// Check whether the vault's idle balance of `asset_mint` covers redeeming `shares`
+    pub fn check_withdrawal_liquidity(&self, asset_mint: &Pubkey, shares: u64) -> Result<WithdrawalLiquidity> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        WithdrawalLiquidity::from_nav(&nav, snapshot.vault.fee.redemption_fee_bps, asset_mint, shares)
+    }
+
+    /// Rank the vault's assets by what redeeming `crt_amount` for each leaves the user with
+    ///
+    /// Weighs the redemption fee, oracle prices, Token-2022 transfer fees on the payout and the
+    /// swap costs in `ClientConfig::exit_swap_costs`, and puts assets whose idle balance covers
+    /// the payout first. `withdraw_best_available` redeems in this order.
+    pub fn best_exit_asset(&self, crt_amount: u64) -> Result<ExitRanking> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        self.exit_ranking(&snapshot, &nav, crt_amount)
+    }
+
+    fn exit_ranking(&self, snapshot: &VaultSnapshot, nav: &Nav, crt_amount: u64) -> Result<ExitRanking> {
+        // Only Token-2022 mints can charge transfer fees
+        let vault = VaultHandle::new(VAULT_ADDRESS, snapshot.vault.clone());
+        let token_2022_mints: Vec<Pubkey> = vault
+            .vault
+            .assets
+            .iter()
+            .map(|asset| asset.mint)
+            .filter(|mint| vault.asset_token_program(mint).ok() == Some(TokenProgram::Token2022))
+            .collect();
+        let mut mints = HashMap::new();
+        let mut epoch = 0;
+        if !token_2022_mints.is_empty() {
+            let accounts = self.rpc_client().get_multiple_accounts(&token_2022_mints)?;
+            mints = token_2022_mints
+                .into_iter()
+                .zip(accounts)
+                .filter_map(|(mint, account)| Some((mint, account?.data)))
+                .collect();
+            epoch = self.rpc_client().get_epoch_info()?.epoch;
+        }
+        ExitRanking::from_nav(
+            nav,
+            snapshot.vault.fee.redemption_fee_bps,
+            crt_amount,
+            |mint, amount| match mints.get(mint) {
+                Some(data) => transfer_fee(data, epoch, amount),
+                None => Ok(0),
+            },
+            &self.config().exit_swap_costs,
+        )
+    }
+
+    /// Value `user` has deposited per asset, as counted for `Policy::max_asset_share_bps`
+    ///
+    /// Counts deposits broadcast by clients sharing `ClientConfig::policy_usage` plus any seeded
+    /// with `seed_deposit_exposure`.
+    pub fn deposit_exposure(&self, user: &Pubkey) -> Result<DepositExposure> {
+        self.config().policy_usage.exposure(self.config(), user)
+    }
+
+    /// Count deposits (asset, USD value) `user` made outside this client toward the asset share
+    /// caps
+    pub fn seed_deposit_exposure(&self, user: &Pubkey, deposits: &[(Pubkey, u128)]) -> Result<()> {
+        self.config().policy_usage.seed_exposure(self.config(), user, deposits)
+    }
+
+    /// Split a deposit worth `value` (USD, `VALUE_DECIMALS`) across `assets`, in order of
+    /// preference, keeping every asset within `user`'s share caps
+    ///
+    /// Returns (asset, amount) legs in base units at current oracle prices. Balances aren't
+    /// checked: only pass assets the user holds enough of.
+    pub fn plan_deposit(&self, user: &Pubkey, value: u128, assets: &[Pubkey]) -> Result<Vec<(Pubkey, u64)>> {
+        let policy = self.config().policy_for(user).cloned().unwrap_or_default();
+        let legs = policy.route_deposit(value, assets, &self.deposit_exposure(user)?)?;
+        let nav = self.fetch_nav()?;
+        let mut planned = Vec::with_capacity(legs.len());
+        for (asset, value) in legs {
+            let amount = nav.asset(&asset)?.amount_for_value(value, Rounding::Down)?;
+            if amount > 0 {
+                planned.push((asset, amount));
+            }
+        }
+        Ok(planned)
+    }
+
+    /// Deposit `value` (USD, `VALUE_DECIMALS`) split across `assets` by `plan_deposit`,
+    /// in a single transaction
+    pub fn deposit_routed(&self, user: &dyn Signer, value: u128, assets: &[Pubkey]) -> Result<Signature> {
+        let user_pubkey = user.pubkey();
+        let legs = self.plan_deposit(&user_pubkey, value, assets)?;
+        let instructions = self.deposit_legs_instructions(&user_pubkey, &legs, None)?;
+        self.send_transaction(&instructions, user)
+    }
+
+    /// Check the policy applying to `user` without counting the transaction; usage is charged
+    /// when it is broadcast (`charge_policy`)
+    ///
+    /// `legs` are (asset, amount) pairs: asset amounts for deposits, CRT for withdrawals.
+    fn check_policy(&self, user: &Pubkey, action: PolicyAction, legs: &[(Pubkey, u64)]) -> Result<()> {
+        let legs = PolicyLegs::new(*user, action, legs.to_vec());
+        let nav = if legs.needs_value(self.config()) { Some(self.fetch_nav()?) } else { None };
+        self.config().policy_usage.check(self.config(), &legs.value(nav.as_ref())?, self.now())
+    }
+
+    /// Count the deposits and withdrawals in `transaction` toward their users' policy usage,
+    /// failing with `PolicyViolation` if that would exceed a limit
+    ///
+    /// A transaction already charged (a rebroadcast) isn't counted again.
+    fn charge_policy(&self, transaction: &Transaction) -> Result<()> {
+        let usage = &self.config().policy_usage;
+        let legs = usage.uncharged_legs(self.config(), transaction)?;
+        let nav = if legs.iter().any(|legs| legs.needs_value(self.config())) {
+            Some(self.fetch_nav()?)
+        } else {
+            None
+        };
+        usage.charge_transaction(self.config(), transaction, &legs, nav.as_ref(), self.now())
+    }
+
+    /// Stop counting the transaction signed `signature` toward policy usage, once it can't land
+    pub(crate) fn refund_policy(&self, signature: &Signature) {
+        if let Err(err) = self.config().policy_usage.refund(self.config(), signature) {
+            log::warn!("failed to refund the policy usage of {}: {}", signature, err);
+        }
+    }
+
+    fn limits_at(&self, snapshot: &VaultSnapshot, nav: &Nav) -> Result<VaultLimits> {
+        vault_limits(self.config(), snapshot, nav)
+    }
+
+    /// Read accounts in a single request at or after `min_slot`
+    /// Returns `None` if the endpoint hasn't reached `min_slot` yet
+    fn get_accounts_at_slot(
+        &self,
+        pubkeys: &[Pubkey],
+        min_slot: u64,
+    ) -> Result<Option<(u64, Vec<Option<Account>>)>> {
+        let config = accounts_at_slot_config(self.rpc_client().commitment(), min_slot);
+        let response = self.rpc_client().get_multiple_accounts_with_config(pubkeys, config);
+        accounts_at_slot(response, min_slot, &self.inner.last_context_slot)
+    }
+
+    /// Fetch the vault together with its address, for building instructions
+    pub fn fetch_vault_handle(&self) -> Result<VaultHandle> {
+        Ok(VaultHandle::new(VAULT_ADDRESS, self.fetch_vault()?))
+    }
+
+    /// Decoded view of the vault: fees, pause state, assets with balances and oracle prices,
+    /// and strategies, with asset symbols resolved from mint metadata where available
+    pub fn fetch_vault_info(&self) -> Result<VaultInfo> {
+        let snapshot = self.fetch_snapshot()?;
+        let symbols = snapshot
+            .vault
+            .assets
+            .iter()
+            .filter_map(|asset| {
+                let metadata = self.resolve_mint_metadata(&asset.mint).ok()?;
+                Some((asset.mint, metadata.symbol))
+            })
+            .collect();
+        Ok(VaultInfo::from_snapshot(VAULT_ADDRESS, &snapshot, &symbols))
     }
 
     /// Get remaining accounts (asset ATAs and oracles) from vault data
     pub fn get_remaining_accounts(&self) -> Result<Vec<Pubkey>> {
-        let vault = self.fetch_vault()?;
-        Ok(vault.get_remaining_accounts())
+        let assets = self.get_vault_assets()?;
+        Ok(assets.iter().flat_map(|asset| [asset.ata, asset.oracle]).collect())
+    }
+
+    /// Issue/redeem accounts with remaining accounts from the configured resolver
+    fn vault_accounts(&self, vault: &VaultHandle, user: &Pubkey, asset_mint: &Pubkey) -> Result<IssueAccounts> {
+        payout_accounts(self.config(), vault, user, asset_mint, None)
     }
 
     /// Deposit asset tokens (USDC, USDT, pyUSD) and receive CRT shares
     pub fn deposit(
         &self,
-        user: &Keypair,
+        user: &dyn Signer,
         asset_mint: &Pubkey,
         amount: u64,
     ) -> Result<Signature> {
-        let user_pubkey = user.pubkey();
-        
-        // Get remaining accounts from vault
-        let remaining_accounts = self.get_remaining_accounts()?;
-
-        // Build instructions
-        let mut instructions = Vec::new();
-
-        // Create ATA for CRT if needed (idempotent)
-        let create_crt_ata_ix = create_associated_token_account_idempotent(
-            &user_pubkey,
-            &user_pubkey,
-            &crate::CRT_MINT,
-            &spl_token_2022_interface::id(),
-        );
-        instructions.push(create_crt_ata_ix);
-
-        // Build issue instruction
-        let issue_ix = build_issue_instruction(&user_pubkey, asset_mint, amount, remaining_accounts)?;
-        instructions.push(issue_ix);
+        let instructions = self.deposit_instructions(&user.pubkey(), asset_mint, amount, None)?;
 
         // Create and send transaction
         self.send_transaction(&instructions, user)
@@ -86,84 +657,2077 @@ impl CarrotClient {
     /// Withdraw CRT shares and receive asset tokens
     pub fn withdraw(
         &self,
-        user: &Keypair,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        let instructions = self.withdraw_instructions(&user.pubkey(), asset_mint, amount, None)?;
+
+        // Create and send transaction
+        self.send_transaction(&instructions, user)
+    }
+
+    /// `deposit` with its own compute-unit limit and price, e.g. a higher priority fee while
+    /// the network is congested
+    pub fn deposit_with_options(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        options: &DepositOptions,
+    ) -> Result<Signature> {
+        let instructions = self.deposit_instructions(&user.pubkey(), asset_mint, amount, None)?;
+        self.send_transaction_with_options(&instructions, user, options)
+    }
+
+    /// `withdraw` with its own compute-unit limit and price
+    pub fn withdraw_with_options(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        options: &DepositOptions,
+    ) -> Result<Signature> {
+        let instructions = self.withdraw_instructions(&user.pubkey(), asset_mint, amount, None)?;
+        self.send_transaction_with_options(&instructions, user, options)
+    }
+
+    /// Deposit from `token_account` instead of the user's ATA of `asset_mint`
+    ///
+    /// The account is checked with `validate_token_account` before anything is built.
+    pub fn deposit_from_account(
+        &self,
+        user: &dyn Signer,
         asset_mint: &Pubkey,
         amount: u64,
+        token_account: &Pubkey,
     ) -> Result<Signature> {
         let user_pubkey = user.pubkey();
-        
-        // Get remaining accounts from vault
-        let remaining_accounts = self.get_remaining_accounts()?;
-
-        // Build instructions
-        let mut instructions = Vec::new();
-
-        // Create ATA for asset if needed (idempotent)
-        let asset_token_program = get_token_program_id(asset_mint);
-        let create_asset_ata_ix = create_associated_token_account_idempotent(
-            &user_pubkey,
-            &user_pubkey,
-            asset_mint,
-            &asset_token_program,
-        );
-        instructions.push(create_asset_ata_ix);
+        self.validate_token_account(token_account, &user_pubkey, asset_mint)?;
+        let instructions = self.deposit_instructions(&user_pubkey, asset_mint, amount, Some(token_account))?;
+        self.send_transaction(&instructions, user)
+    }
+
+    /// Withdraw CRT with the proceeds paid into `token_account` instead of the user's ATA
+    ///
+    /// The account must be the user's own (see `validate_token_account`); use `withdraw_to`
+    /// to pay another wallet.
+    pub fn withdraw_to_account(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        token_account: &Pubkey,
+    ) -> Result<Signature> {
+        let user_pubkey = user.pubkey();
+        self.validate_token_account(token_account, &user_pubkey, asset_mint)?;
+        let instructions = self.withdraw_instructions(&user_pubkey, asset_mint, amount, Some(token_account))?;
+        self.send_transaction(&instructions, user)
+    }
 
-        // Build redeem instruction
-        let redeem_ix = build_redeem_instruction(&user_pubkey, asset_mint, amount, remaining_accounts)?;
-        instructions.push(redeem_ix);
+    /// Check that `token_account` can replace `owner`'s ATA of `mint` in a deposit or withdrawal:
+    /// an initialized, unfrozen account of `mint` under its token program, owned by `owner`
+    pub fn validate_token_account(&self, token_account: &Pubkey, owner: &Pubkey, mint: &Pubkey) -> Result<()> {
+        let account = self
+            .rpc_client()
+            .get_account_with_commitment(token_account, self.rpc_client().commitment())?
+            .value
+            .ok_or_else(|| CarrotError::AccountNotFound(format!("token account {}", token_account)))?;
+        check_token_account(token_account, &account, self.get_token_program(mint)?, owner, mint)
+    }
 
-        // Create and send transaction
+    /// Withdraw CRT and forward the proceeds to `recipient`'s token account in the same transaction
+    ///
+    /// The forwarded amount is the output expected at the NAV of the snapshot the withdrawal is
+    /// checked against, net of the redemption fee. Fails with `InvalidTokenAccount` if the
+    /// user's asset account isn't empty, so a shortfall can't be made up from funds already
+    /// held: if the vault pays out less, the transfer fails and the whole transaction reverts.
+    /// If it pays out more (the share price moved before the transaction landed), the
+    /// difference stays on the user's asset account. `recipient` must be on the configured
+    /// withdrawal destination allowlist, if any.
+    pub fn withdraw_to(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        recipient: &Pubkey,
+    ) -> Result<Signature> {
+        let instructions = self.withdraw_to_instructions(&user.pubkey(), asset_mint, amount, recipient)?;
         self.send_transaction(&instructions, user)
     }
 
-    /// Send a transaction with the given instructions
-    fn send_transaction(&self, instructions: &[Instruction], signer: &Keypair) -> Result<Signature> {
-        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
-        
-        let transaction = Transaction::new_signed_with_payer(
-            instructions,
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
+    /// Deposit and transfer the minted CRT to `custody`'s CRT account in the same transaction
+    ///
+    /// The forwarded amount is the CRT expected at the NAV of the snapshot the deposit is
+    /// checked against. Fails with `InvalidTokenAccount` if the user's CRT account isn't empty,
+    /// so a shortfall can't be made up from shares already held: if the vault mints less, the
+    /// transfer fails and the whole transaction reverts. If it mints more (the share price
+    /// moved before the transaction landed), the difference stays on the user's CRT account.
+    /// `custody` must be on the configured withdrawal destination allowlist, if any.
+    pub fn deposit_and_forward(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        custody: &Pubkey,
+    ) -> Result<Signature> {
+        let instructions = self.deposit_and_forward_instructions(&user.pubkey(), asset_mint, amount, custody)?;
+        self.send_transaction(&instructions, user)
+    }
 
-        let signature = self
-            .rpc_client
-            .send_and_confirm_transaction(&transaction)
-            .map_err(|e| CarrotError::TransactionFailed(e.to_string()))?;
+    /// Redeem `crt_amount` CRT across the vault's assets according to their idle liquidity
+    ///
+    /// Assets are drawn from in `best_exit_asset` order, so the cheapest exit that covers the
+    /// whole amount is used alone. All redemptions go into one transaction if it fits in a
+    /// packet, otherwise one transaction is sent per asset. Returns the signatures in send order.
+    ///
+    /// Separate transactions aren't atomic: if one fails after others were sent, those stay
+    /// redeemed and the error is `PartialWithdrawal`, with their signatures and the redemptions
+    /// left to retry. A failure of the first transaction is returned as is.
+    pub fn withdraw_best_available(&self, user: &dyn Signer, crt_amount: u64) -> Result<Vec<Signature>> {
+        let user_pubkey = user.pubkey();
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        let ranking = self.exit_ranking(&snapshot, &nav, crt_amount)?;
+        let plan = RedemptionPlan::from_nav_preferring(
+            &nav,
+            snapshot.vault.fee.redemption_fee_bps,
+            crt_amount,
+            &ranking.preference(),
+        )?;
+        let policy_legs: Vec<(Pubkey, u64)> = plan.legs.iter().map(|leg| (leg.asset_mint, leg.shares)).collect();
+        self.check_policy(&user_pubkey, PolicyAction::Withdraw, &policy_legs)?;
+        let vault = VaultHandle::new(VAULT_ADDRESS, snapshot.vault);
 
-        Ok(signature)
+        let legs = plan
+            .legs
+            .iter()
+            .map(|leg| {
+                let payout = self.vault_accounts(&vault, &user_pubkey, &leg.asset_mint)?;
+                let hook_accounts = self.payout_hook_accounts(&vault, &leg.asset_mint, &payout.user_asset_ata)?;
+                redeem_instructions(&vault, &payout, leg.shares, &hook_accounts)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let combined = legs.concat();
+        if fits_in_packet(&combined, &user_pubkey) {
+            return Ok(vec![self.send_transaction(&combined, user)?]);
+        }
+        let mut sent = Vec::with_capacity(legs.len());
+        for (index, instructions) in legs.iter().enumerate() {
+            match self.send_transaction(instructions, user) {
+                Ok(signature) => sent.push(signature),
+                Err(err) if sent.is_empty() => return Err(err),
+                Err(err) => {
+                    return Err(CarrotError::PartialWithdrawal {
+                        sent,
+                        remaining: plan.legs[index..].iter().map(|leg| (leg.asset_mint, leg.shares)).collect(),
+                        source: Box::new(err),
+                    })
+                }
+            }
+        }
+        Ok(sent)
     }
 
-    /// Check user's asset token balance
-    pub fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64> {
-        let ata = get_user_asset_ata(user, asset_mint);
-        
-        match self.rpc_client.get_token_account_balance(&ata) {
-            Ok(balance) => Ok(balance.amount.parse().unwrap_or(0)),
-            Err(_) => Ok(0), // Account doesn't exist yet
+    /// Deposit at most once per `intent`, even across restarts (with a persistent ledger)
+    ///
+    /// If a transaction was already sent for `intent`, it is not rebuilt: its signature is
+    /// returned if it landed, and it is rebroadcast as-is while its blockhash is still valid.
+    /// Only a transaction that failed on-chain or expired without landing is replaced.
+    pub fn deposit_once(&self, intent: &str, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        self.send_once(intent, || self.build_deposit_transaction(user, asset_mint, amount))
+    }
+
+    /// Withdraw at most once per `intent`; see `deposit_once`
+    pub fn withdraw_once(
+        &self,
+        intent: &str,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        self.send_once(intent, || self.build_withdraw_transaction(user, asset_mint, amount))
+    }
+
+    /// Transaction recorded for `intent`, if any
+    pub fn ledger_entry(&self, intent: &str) -> Option<LedgerEntry> {
+        self.inner.ledger.get(intent)
+    }
+
+    fn send_once<F>(&self, intent: &str, build: F) -> Result<Signature>
+    where
+        F: FnOnce() -> Result<PreparedTransaction>,
+    {
+        self.check_approval_not_required()?;
+        validate_intent(intent)?;
+        if let Some(entry) = self.inner.ledger.get(intent) {
+            let status = self.rpc_client().get_signature_status_with_commitment_and_history(
+                &entry.signature,
+                self.rpc_client().commitment(),
+                true,
+            )?;
+            match status {
+                Some(Ok(())) => return Ok(entry.signature),
+                // Failed on-chain: nothing moved, a new attempt is safe
+                Some(Err(_)) => {}
+                None if self.rpc_client().get_block_height()? <= entry.last_valid_block_height => {
+                    // May still land; resending the same transaction can't execute it twice
+                    return self.send_signed(&entry.transaction()?);
+                }
+                // Expired without landing
+                None => {}
+            }
         }
+
+        let prepared = build()?;
+        // Write-ahead: recorded before broadcast so a crash mid-send is recoverable
+        self.inner.ledger.record(LedgerEntry::new(intent, &prepared)?)?;
+        self.send_signed(&prepared.transaction)
     }
 
-    /// Check user's CRT token balance
-    pub fn get_crt_balance(&self, user: &Pubkey) -> Result<u64> {
-        let ata = get_user_crt_ata(user);
-        
-        match self.rpc_client.get_token_account_balance(&ata) {
-            Ok(balance) => Ok(balance.amount.parse().unwrap_or(0)),
-            Err(_) => Ok(0), // Account doesn't exist yet
+    /// Fail with `DestinationNotAllowed` unless `recipient` may receive withdrawal proceeds
+    pub fn check_withdrawal_destination(&self, user: &Pubkey, recipient: &Pubkey) -> Result<()> {
+        match &self.config().withdrawal_destinations {
+            Some(allowed) if recipient != user && !allowed.contains(recipient) => {
+                Err(CarrotError::DestinationNotAllowed(*recipient))
+            }
+            _ => Ok(()),
         }
     }
-}
 
-/// Convenience function to deposit USDC
-pub fn deposit_usdc(rpc_url: String, user: &Keypair, amount_usdc: u64) -> Result<Signature> {
-    let client = CarrotClient::new(rpc_url);
-    client.deposit(user, &crate::USDC_MINT, amount_usdc)
-}
+    /// Instructions for depositing `amount` of `asset_mint` from `user`, for wallets that
+    /// assemble, sign and send transactions themselves
+    ///
+    /// CRT ATA creation + issue, with remaining accounts resolved and the same pause, limit and
+    /// policy checks as `deposit`. No compute budget or memo instructions are added. Preparing
+    /// doesn't count toward policy usage: only transactions this client broadcasts do, e.g.
+    /// through `send_raw_transaction`.
+    pub fn prepare_deposit(&self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<Vec<Instruction>> {
+        self.deposit_instructions(user, asset_mint, amount, None)
+    }
 
-/// Convenience function to withdraw CRT for USDC
-pub fn withdraw_crt(rpc_url: String, user: &Keypair, amount_crt: u64) -> Result<Signature> {
-    let client = CarrotClient::new(rpc_url);
-    client.withdraw(user, &crate::USDC_MINT, amount_crt)
+    /// Instructions for redeeming `amount` CRT of `user` for `asset_mint`; see `prepare_deposit`
+    ///
+    /// Asset ATA creation + redeem, with transfer hook accounts resolved and withdrawal
+    /// liquidity checked.
+    pub fn prepare_withdraw(&self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<Vec<Instruction>> {
+        self.withdraw_instructions(user, asset_mint, amount, None)
+    }
+
+    /// Build and sign a deposit transaction without sending it
+    /// Send it later with `send_prepared`, which refuses it once expired
+    pub fn build_deposit_transaction(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<PreparedTransaction> {
+        let instructions = self.deposit_instructions(&user.pubkey(), asset_mint, amount, None)?;
+        self.build_transaction(&instructions, user)
+    }
+
+    /// Build and sign a withdrawal transaction without sending it
+    /// Send it later with `send_prepared`, which refuses it once expired
+    pub fn build_withdraw_transaction(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<PreparedTransaction> {
+        let instructions = self.withdraw_instructions(&user.pubkey(), asset_mint, amount, None)?;
+        self.build_transaction(&instructions, user)
+    }
+
+    /// Build a deposit whose network fees, and the rent of any token account it creates, are
+    /// paid by `fee_payer`; signed by both, `fee_payer` first
+    pub fn build_sponsored_deposit_transaction(
+        &self,
+        user: &dyn Signer,
+        fee_payer: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<PreparedTransaction> {
+        let instructions =
+            self.sponsored_deposit_instructions(&user.pubkey(), &fee_payer.pubkey(), asset_mint, amount)?;
+        self.build_transaction_with_payer(&instructions, &fee_payer.pubkey(), &[fee_payer, user])
+    }
+
+    /// Build a withdrawal paid for by `fee_payer`; see `build_sponsored_deposit_transaction`
+    pub fn build_sponsored_withdraw_transaction(
+        &self,
+        user: &dyn Signer,
+        fee_payer: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<PreparedTransaction> {
+        let mut instructions = self.withdraw_instructions(&user.pubkey(), asset_mint, amount, None)?;
+        fund_account_creation(&mut instructions, &fee_payer.pubkey());
+        self.build_transaction_with_payer(&instructions, &fee_payer.pubkey(), &[fee_payer, user])
+    }
+
+    /// Deposit `user`'s tokens with `fee_payer` (e.g. a gas-sponsoring service) paying the
+    /// network fees and token account rent, so `user` needs no SOL
+    pub fn deposit_sponsored(
+        &self,
+        user: &dyn Signer,
+        fee_payer: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        let prepared = self.build_sponsored_deposit_transaction(user, fee_payer, asset_mint, amount)?;
+        self.send_signed(&prepared.transaction)
+    }
+
+    /// Withdraw with `fee_payer` paying the network fees and token account rent
+    pub fn withdraw_sponsored(
+        &self,
+        user: &dyn Signer,
+        fee_payer: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        let prepared = self.build_sponsored_withdraw_transaction(user, fee_payer, asset_mint, amount)?;
+        self.send_signed(&prepared.transaction)
+    }
+
+    /// Send a prepared transaction, refusing it if its blockhash or deadline has expired
+    pub fn send_prepared(&self, prepared: &PreparedTransaction) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        self.send_unexpired(prepared)
+    }
+
+    /// Sign a deposit and hold it for approval instead of sending it
+    pub fn request_deposit(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<PendingOperation> {
+        Ok(PendingOperation::new(self.build_deposit_transaction(user, asset_mint, amount)?))
+    }
+
+    /// Sign a withdrawal and hold it for approval instead of sending it
+    pub fn request_withdraw(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<PendingOperation> {
+        Ok(PendingOperation::new(self.build_withdraw_transaction(user, asset_mint, amount)?))
+    }
+
+    /// Send a pending operation once `approval` is verified: signed over this exact transaction,
+    /// by someone other than the requester, and by a configured approver if any are set
+    pub fn send_approved(&self, operation: &PendingOperation, approval: &Approval) -> Result<Signature> {
+        approval.verify(operation, self.config().approvers.as_deref())?;
+        self.send_unexpired(&operation.prepared)
+    }
+
+    /// Ask `approver` (e.g. a callback prompting a second operator or a remote signing service)
+    /// for an approval and send the operation if it is valid
+    pub fn send_with_approver<F>(&self, operation: &PendingOperation, approver: F) -> Result<Signature>
+    where
+        F: FnOnce(&PendingOperation) -> Result<Approval>,
+    {
+        let approval = approver(operation)?;
+        self.send_approved(operation, &approval)
+    }
+
+    /// Fail with `ApprovalRequired` if operations must be approved before broadcast
+    pub(crate) fn check_approval_not_required(&self) -> Result<()> {
+        match self.config().approvers {
+            Some(_) => Err(CarrotError::ApprovalRequired),
+            None => Ok(()),
+        }
+    }
+
+    fn send_unexpired(&self, prepared: &PreparedTransaction) -> Result<Signature> {
+        let block_height = self.rpc_client().get_block_height().during(Operation::Send)?;
+        prepared.check_expiry(block_height, self.config().clock.now())?;
+        self.send_signed(&prepared.transaction)
+    }
+
+    /// Simulate a prepared transaction and return how it changes the user's CRT and asset balances
+    ///
+    /// The user and asset are taken from the transaction's first Carrot instruction.
+    pub fn simulate_effects(&self, prepared: &PreparedTransaction) -> Result<BalanceEffects> {
+        let (user, asset_mint) = match inspect(&prepared.transaction)?.carrot_instructions.first() {
+            Some((_, CarrotInstruction::Issue { user, asset_mint, .. }))
+            | Some((_, CarrotInstruction::Redeem { user, asset_mint, .. })) => (*user, *asset_mint),
+            None => {
+                return Err(CarrotError::SimulationFailed(
+                    "transaction contains no Carrot instruction".to_string(),
+                ))
+            }
+        };
+        let watched = [get_user_crt_ata(&user), self.user_asset_ata(&user, &asset_mint)?];
+
+        let pre_balances: Vec<u64> = self
+            .rpc_client()
+            .get_multiple_accounts(&watched)?
+            .iter()
+            .map(|account| match account {
+                Some(account) => unpack_token_amount(&account.data),
+                None => Ok(0),
+            })
+            .collect::<Result<_>>()?;
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.rpc_client().commitment()),
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: watched.iter().map(|a| a.to_string()).collect(),
+            }),
+            ..Default::default()
+        };
+        let result = self
+            .rpc_client()
+            .simulate_transaction_with_config(&prepared.transaction, config)?
+            .value;
+        if let Some(err) = result.err {
+            let logs = result.logs.unwrap_or_default().join("\n");
+            return Err(CarrotError::SimulationFailed(format!("{}\n{}", err, logs)));
+        }
+
+        let post_accounts = result.accounts.unwrap_or_default();
+        let mut post_balances = [0u64; 2];
+        for (i, balance) in post_balances.iter_mut().enumerate() {
+            if let Some(account) = post_accounts.get(i).and_then(|a| a.as_ref()) {
+                let account: Account = account.decode().ok_or_else(|| {
+                    CarrotError::SimulationFailed("undecodable simulated account".to_string())
+                })?;
+                *balance = unpack_token_amount(&account.data)?;
+            }
+        }
+
+        Ok(BalanceEffects {
+            crt_delta: post_balances[0] as i128 - pre_balances[0] as i128,
+            asset_delta: post_balances[1] as i128 - pre_balances[1] as i128,
+        })
+    }
+
+    /// Simulate a prepared transaction and fail with `EffectsMismatch` unless its balance
+    /// changes fall within `expected`
+    pub fn assert_effects(
+        &self,
+        prepared: &PreparedTransaction,
+        expected: &ExpectedEffects,
+    ) -> Result<BalanceEffects> {
+        let effects = self.simulate_effects(prepared)?;
+        expected.check(&effects)?;
+        Ok(effects)
+    }
+
+    /// Send a prepared transaction only if its simulated balance changes match `expected`
+    pub fn send_prepared_with_effects(
+        &self,
+        prepared: &PreparedTransaction,
+        expected: &ExpectedEffects,
+    ) -> Result<Signature> {
+        self.assert_effects(prepared, expected)?;
+        self.send_prepared(prepared)
+    }
+
+    /// Instructions for a deposit: CRT ATA creation + issue, paid from `asset_account` if set
+    fn deposit_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        asset_account: Option<&Pubkey>,
+    ) -> Result<Vec<Instruction>> {
+        self.deposit_legs_instructions(user_pubkey, &[(*asset_mint, amount)], asset_account)
+    }
+
+    /// Instructions depositing every (asset, amount) leg in one transaction: CRT ATA creation +
+    /// one issue per leg, checked against the limits and policy as a whole
+    fn deposit_legs_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        legs: &[(Pubkey, u64)],
+        asset_account: Option<&Pubkey>,
+    ) -> Result<Vec<Instruction>> {
+        let context = deposit_context(legs);
+        if self.config().deposit_limits.is_set() {
+            let snapshot = self.fetch_snapshot().during(context)?;
+            let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+            return self.snapshot_deposit_instructions(user_pubkey, legs, asset_account, &snapshot, &nav);
+        }
+        let vault = self.fetch_vault_handle().during(context)?;
+        if vault.vault.paused {
+            return Err(CarrotError::VaultPaused);
+        }
+        self.check_policy(user_pubkey, PolicyAction::Deposit, legs)
+            .during(context)?;
+
+        issue_instructions(self.config(), &vault, user_pubkey, legs, asset_account)
+    }
+
+    /// `deposit_legs_instructions` checked against `snapshot` valued at `nav`
+    fn snapshot_deposit_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        legs: &[(Pubkey, u64)],
+        asset_account: Option<&Pubkey>,
+        snapshot: &VaultSnapshot,
+        nav: &Nav,
+    ) -> Result<Vec<Instruction>> {
+        let context = deposit_context(legs);
+        if snapshot.vault.paused {
+            return Err(CarrotError::VaultPaused);
+        }
+        check_deposit_limits(self.config(), snapshot, nav, legs).during(context)?;
+        self.check_policy(user_pubkey, PolicyAction::Deposit, legs)
+            .during(context)?;
+
+        let vault = VaultHandle::new(VAULT_ADDRESS, snapshot.vault.clone());
+        issue_instructions(self.config(), &vault, user_pubkey, legs, asset_account)
+    }
+
+    /// Instructions for a withdrawal forwarded to `recipient`: withdrawal + recipient ATA creation + transfer
+    ///
+    /// The forwarded proceeds are estimated from the snapshot the withdrawal is checked against.
+    /// The user's asset account must be empty, so the transfer can only move what this
+    /// withdrawal paid out.
+    fn withdraw_to_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        recipient: &Pubkey,
+    ) -> Result<Vec<Instruction>> {
+        self.check_withdrawal_destination(user_pubkey, recipient)?;
+
+        if recipient == user_pubkey {
+            return self.withdraw_instructions(user_pubkey, asset_mint, amount, None);
+        }
+        let (mut instructions, snapshot, nav) =
+            self.checked_withdraw_instructions(user_pubkey, asset_mint, amount, None)?;
+
+        // Forward the expected proceeds, net of the redemption fee
+        let gross = CrtAmount(amount).to_asset_value(&nav, asset_mint, Rounding::Down)?.amount;
+        let net = deduct_fee_bps(gross, snapshot.vault.fee.redemption_fee_bps)?;
+        let decimals = nav.asset(asset_mint)?.decimals;
+
+        let asset_token_program = VaultHandle::new(VAULT_ADDRESS, snapshot.vault).asset_token_program(asset_mint)?;
+        instructions.push(asset_token_program.create_associated_token_account(user_pubkey, recipient, asset_mint));
+        let source = asset_token_program.associated_token_address(user_pubkey, asset_mint);
+        self.ensure_empty_token_account(&source, "the redeemed assets")?;
+        let destination = asset_token_program.associated_token_address(recipient, asset_mint);
+        let mut transfer =
+            asset_token_program.transfer_checked(&source, asset_mint, &destination, user_pubkey, net, decimals)?;
+        if asset_token_program == TokenProgram::Token2022 {
+            transfer.accounts.extend(self.transfer_hook_accounts(&HookedTransfer {
+                source,
+                mint: *asset_mint,
+                destination,
+                authority: *user_pubkey,
+                amount: Some(net),
+            })?);
+        }
+        instructions.push(transfer);
+
+        Ok(instructions)
+    }
+
+    /// Instructions for a deposit whose token account rent is paid by `fee_payer`
+    pub(crate) fn sponsored_deposit_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        fee_payer: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Vec<Instruction>> {
+        let mut instructions = self.deposit_instructions(user_pubkey, asset_mint, amount, None)?;
+        fund_account_creation(&mut instructions, fee_payer);
+        Ok(instructions)
+    }
+
+    /// Instructions for a deposit forwarded to `custody`: deposit + custody CRT ATA creation + transfer
+    ///
+    /// The deposit is checked and the forwarded shares estimated from the same snapshot. The
+    /// user's CRT account must be empty, so the transfer can only move shares this deposit minted.
+    fn deposit_and_forward_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        custody: &Pubkey,
+    ) -> Result<Vec<Instruction>> {
+        self.check_withdrawal_destination(user_pubkey, custody)?;
+        if custody == user_pubkey {
+            return self.deposit_instructions(user_pubkey, asset_mint, amount, None);
+        }
+
+        let legs = [(*asset_mint, amount)];
+        let snapshot = self.fetch_snapshot().during(deposit_context(&legs))?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        let mut instructions = self.snapshot_deposit_instructions(user_pubkey, &legs, None, &snapshot, &nav)?;
+        self.ensure_empty_token_account(&get_user_crt_ata(user_pubkey), "the minted CRT")?;
+
+        let shares = AssetAmount::new(*asset_mint, amount).to_expected_shares(&nav, Rounding::Down)?;
+        instructions.extend(self.transfer_crt_instructions(user_pubkey, custody, shares.0)?);
+        Ok(instructions)
+    }
+
+    /// Refuse to forward from `address` while it holds tokens: a forwarded estimate above what
+    /// the transaction actually received would be made up from them instead of failing
+    fn ensure_empty_token_account(&self, address: &Pubkey, forwarded: &str) -> Result<()> {
+        match self.get_token_amount(address)? {
+            0 => Ok(()),
+            balance => Err(CarrotError::InvalidTokenAccount {
+                account: *address,
+                reason: format!("holds {} base units, which forwarding {} could draw on", balance, forwarded),
+            }),
+        }
+    }
+
+    /// Instructions for a withdrawal: asset ATA creation + redeem, paid into `asset_account` if set
+    fn withdraw_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        asset_account: Option<&Pubkey>,
+    ) -> Result<Vec<Instruction>> {
+        Ok(self.checked_withdraw_instructions(user_pubkey, asset_mint, amount, asset_account)?.0)
+    }
+
+    /// `withdraw_instructions` with the snapshot they were checked against and its NAV
+    fn checked_withdraw_instructions(
+        &self,
+        user_pubkey: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        asset_account: Option<&Pubkey>,
+    ) -> Result<(Vec<Instruction>, VaultSnapshot, Nav)> {
+        let context = ErrorContext::new(Operation::BuildRedeem)
+            .with_asset(*asset_mint)
+            .with_amount(amount);
+        self.check_policy(user_pubkey, PolicyAction::Withdraw, &[(*asset_mint, amount)])
+            .during(context)?;
+        let snapshot = self.fetch_snapshot().during(context)?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now()).during(context)?;
+        // Redemptions are paid from idle funds; fail early instead of on-chain
+        WithdrawalLiquidity::from_nav(&nav, snapshot.vault.fee.redemption_fee_bps, asset_mint, amount)?
+            .ensure_sufficient()?;
+        let vault = VaultHandle::new(VAULT_ADDRESS, snapshot.vault.clone());
+        let payout = payout_accounts(self.config(), &vault, user_pubkey, asset_mint, asset_account)?;
+        let hook_accounts = self.payout_hook_accounts(&vault, asset_mint, &payout.user_asset_ata)?;
+
+        Ok((redeem_instructions(&vault, &payout, amount, &hook_accounts)?, snapshot, nav))
+    }
+
+    /// Estimate the compute-unit price (micro-lamports) for issue/redeem transactions
+    /// from fees recently paid for the vault and CRT mint
+    pub fn estimate_priority_fee(&self, policy: &PriorityFeePolicy) -> Result<u64> {
+        PriorityFeeEstimator::new(*policy).estimate(self.rpc_client())
+    }
+
+    /// Simulate the instructions and return the compute units they consume
+    pub fn simulate_compute_units(&self, instructions: &[Instruction], payer: &Pubkey) -> Result<u64> {
+        let result = self
+            .rpc_client()
+            .simulate_transaction_with_config(
+                &simulation_transaction(instructions, payer),
+                simulation_config(self.rpc_client().commitment()),
+            )?
+            .value;
+        simulated_units(result)
+    }
+
+    /// Compute budget instructions to prepend according to `options` and the client configuration
+    fn compute_budget_instructions(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        options: &DepositOptions,
+    ) -> Result<Vec<Instruction>> {
+        let price = match options.compute_unit_price(self.config().priority_fee.as_ref()) {
+            ComputeUnitPrice::Fixed(price) => Some(price),
+            ComputeUnitPrice::Estimated(policy) => Some(self.estimate_priority_fee(policy)?),
+            ComputeUnitPrice::Unset => None,
+        };
+        let limit = match options.compute_unit_limit(self.config().compute_unit_limit)? {
+            ComputeUnitLimit::Simulated { margin } => {
+                let mut sim_instructions = compute_budget_instructions(None, price);
+                sim_instructions.extend_from_slice(instructions);
+                let units = self.simulate_compute_units(&sim_instructions, payer)?;
+                Some(limit_with_margin(units, margin))
+            }
+            ComputeUnitLimit::Fixed(limit) => Some(limit),
+            ComputeUnitLimit::RuntimeDefault => None,
+        };
+        Ok(compute_budget_instructions(limit, price))
+    }
+
+    /// Send a transaction with the given instructions
+    pub(crate) fn send_transaction(&self, instructions: &[Instruction], signer: &dyn Signer) -> Result<Signature> {
+        self.send_transaction_with_options(instructions, signer, &DepositOptions::default())
+    }
+
+    /// `send_transaction` with the compute budget set by `options`
+    fn send_transaction_with_options(
+        &self,
+        instructions: &[Instruction],
+        signer: &dyn Signer,
+        options: &DepositOptions,
+    ) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        let prepared = self.build_transaction_with_options(instructions, &signer.pubkey(), &[signer], options)?;
+        self.send_signed(&prepared.transaction)
+    }
+
+    /// Programs this client agrees to sign transactions for
+    pub fn program_allowlist(&self) -> &ProgramAllowlist {
+        &self.inner.program_allowlist
+    }
+
+    /// Build and sign a transaction, prepending compute budget instructions and appending the
+    /// broadcaster's tip, if any
+    fn build_transaction(&self, instructions: &[Instruction], signer: &dyn Signer) -> Result<PreparedTransaction> {
+        self.build_transaction_with_payer(instructions, &signer.pubkey(), &[signer])
+    }
+
+    /// `build_transaction` with a fee payer of its own, signed by `signers`
+    ///
+    /// Signatures of keys missing from `signers` (e.g. a relayer paying the fee) are left empty.
+    pub(crate) fn build_transaction_with_payer(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[&dyn Signer],
+    ) -> Result<PreparedTransaction> {
+        self.build_transaction_with_options(instructions, payer, signers, &DepositOptions::default())
+    }
+
+    /// `build_transaction_with_payer` with the compute budget set by `options`
+    fn build_transaction_with_options(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[&dyn Signer],
+        options: &DepositOptions,
+    ) -> Result<PreparedTransaction> {
+        let tips = match &self.config().broadcaster {
+            Some(broadcaster) => broadcaster.tip_instructions(payer),
+            None => Vec::new(),
+        };
+        let instructions = checked_instructions(self.config(), self.program_allowlist(), instructions, tips)?;
+        let budget = self
+            .compute_budget_instructions(&instructions, payer, options)
+            .during(Operation::BuildTransaction)?;
+        let all_instructions = with_compute_budget(self.config(), budget, instructions);
+
+        let (recent_blockhash, last_valid_block_height) =
+            self.latest_blockhash().during(Operation::BuildTransaction)?;
+
+        let mut transaction = Transaction::new_unsigned(Message::new(&all_instructions, Some(payer)));
+        transaction
+            .try_partial_sign(signers, recent_blockhash)
+            .map_err(|e| CarrotError::TransactionFailed(format!("failed to sign: {}", e)))?;
+
+        Ok(PreparedTransaction {
+            transaction,
+            last_valid_block_height,
+            expires_at: self
+                .config()
+                .prepared_transaction_ttl
+                .map(|ttl| self.config().clock.now() + ttl),
+        })
+    }
+
+    /// Send a signed transaction and wait for the configured commitment
+    ///
+    /// The transaction is charged against its users' policies first, and refunded if it fails
+    /// for certain; a transaction whose fate is unknown (see `CarrotError::is_unconfirmed`) keeps
+    /// counting.
+    pub(crate) fn send_signed(&self, transaction: &Transaction) -> Result<Signature> {
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let sent = self
+            .charge_policy(transaction)
+            .and_then(|()| self.send_and_confirm(transaction))
+            .during(Operation::Send);
+        let result = sent.and_then(|signature| {
+            self.emit(ClientEvent::TxConfirmed { signature });
+            if self.config().wait_for_finalized {
+                self.wait_for_finalization(&signature, &transaction.message.recent_blockhash)
+                    .during(Operation::Confirm)?;
+                self.emit(ClientEvent::TxFinalized { signature });
+            }
+            Ok(signature)
+        });
+        if let Err(err) = &result {
+            if !err.is_unconfirmed() {
+                self.refund_policy(&signature);
+            }
+            self.emit(ClientEvent::TxFailed {
+                signature,
+                error: err.to_string(),
+            });
+        }
+        result
+    }
+
+    /// Broadcast an externally signed, bincode-serialized transaction and wait for the configured
+    /// commitment, with the same allowlist check, fallback rebroadcast and finalization handling
+    /// as `deposit`/`withdraw`
+    pub fn send_raw_transaction(&self, bytes: &[u8]) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        let transaction: Transaction = bincode::deserialize(bytes)
+            .map_err(|e| CarrotError::TransactionFailed(format!("invalid transaction bytes: {}", e)))?;
+        transaction
+            .verify()
+            .map_err(|e| CarrotError::TransactionFailed(format!("invalid signatures: {}", e)))?;
+        if self.config().verify_programs {
+            self.program_allowlist().verify_transaction(&transaction)?;
+        }
+        self.send_signed(&transaction)
+    }
+
+    /// Wait until a transaction sent elsewhere reaches the configured commitment
+    /// (finalized when `wait_for_finalized` is set), for up to the finalization timeout
+    ///
+    /// Fails with `TransactionFailed` if it executed with an error and `ConfirmationTimeout`
+    /// if it isn't confirmed in time.
+    pub fn confirm_signature(&self, signature: &Signature) -> Result<()> {
+        let commitment = if self.config().wait_for_finalized {
+            CommitmentConfig::finalized()
+        } else {
+            self.rpc_client().commitment()
+        };
+        let result = self.wait_for_commitment(signature, commitment, None).during(Operation::Confirm);
+        match &result {
+            Ok(()) => self.emit(ClientEvent::TxConfirmed { signature: *signature }),
+            Err(err) => self.emit(ClientEvent::TxFailed {
+                signature: *signature,
+                error: err.to_string(),
+            }),
+        }
+        result
+    }
+
+    /// Block until a confirmed transaction reaches finalized commitment
+    ///
+    /// Fails with `TransactionDropped` if the signature disappears from the cluster
+    /// after its blockhash expired (the confirming fork was abandoned), and with
+    /// `ConfirmationTimeout` if it isn't finalized within the configured timeout.
+    pub fn wait_for_finalization(&self, signature: &Signature, recent_blockhash: &Hash) -> Result<()> {
+        self.wait_for_commitment(signature, CommitmentConfig::finalized(), Some(recent_blockhash))
+    }
+
+    /// Poll a signature until it reaches `commitment` or the finalization timeout passes
+    /// With `recent_blockhash`, an unknown signature whose blockhash expired counts as dropped
+    fn wait_for_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+        recent_blockhash: Option<&Hash>,
+    ) -> Result<()> {
+        let deadline = Instant::now() + self.config().finalization_timeout;
+
+        loop {
+            let status = self
+                .rpc_client()
+                .get_signature_statuses(&[*signature])?
+                .value
+                .pop()
+                .flatten();
+
+            match signature_progress(status, commitment)? {
+                SignatureProgress::Reached => return Ok(()),
+                SignatureProgress::Pending => {}
+                SignatureProgress::Unknown => {
+                    // No longer known: if the blockhash expired it can never land again
+                    if let Some(recent_blockhash) = recent_blockhash {
+                        let blockhash_valid = self
+                            .rpc_client()
+                            .is_blockhash_valid(recent_blockhash, CommitmentConfig::processed())?;
+                        if !blockhash_valid {
+                            return Err(CarrotError::TransactionDropped(signature.to_string()));
+                        }
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(confirmation_timeout(signature, commitment, self.config().finalization_timeout));
+            }
+            sleep(FINALIZATION_POLL_INTERVAL);
+        }
+    }
+
+    /// Send and confirm a signed transaction, falling back to the alternate endpoints
+    /// when the primary one doesn't know the blockhash or can't confirm before expiry
+    ///
+    /// Endpoints whose circuit is open (see `CircuitBreakerPolicy`) are skipped. With a configured
+    /// `Broadcaster`, the transaction goes through it instead (see `send_via_broadcaster`).
+    fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        if let Some(broadcaster) = &self.config().broadcaster {
+            return self.send_via_broadcaster(broadcaster.as_ref(), transaction);
+        }
+        let signature = transaction.signatures[0];
+        let primary_err = match self.inner.rpc.circuit_breaker.check(0, Instant::now()) {
+            Ok(()) => {
+                self.emit(ClientEvent::TxSent { signature, endpoint: 0 });
+                let result = self.rpc_client().send_and_confirm_transaction(transaction);
+                self.record_endpoint_result(0, &result);
+                match result {
+                    Ok(signature) => return Ok(signature),
+                    Err(err) if !is_blockhash_error(&err) => {
+                        return Err(CarrotError::TransactionFailed(err.to_string()))
+                    }
+                    Err(err) => CarrotError::TransactionFailed(err.to_string()),
+                }
+            }
+            // Primary skipped: go straight to the fallbacks
+            Err(open) => open,
+        };
+
+        for (index, fallback) in self.inner.rpc.fallback_rpc_clients.iter().enumerate() {
+            let endpoint = index + 1;
+            if self.inner.rpc.circuit_breaker.check(endpoint, Instant::now()).is_err() {
+                continue;
+            }
+
+            // The transaction may have landed even though the primary endpoint didn't see it
+            let status = fallback.get_signature_status(&signature);
+            self.record_endpoint_result(endpoint, &status);
+            match status {
+                Ok(Some(Ok(()))) => return Ok(signature),
+                Ok(Some(Err(err))) => return Err(CarrotError::TransactionFailed(err.to_string())),
+                _ => {}
+            }
+
+            self.emit(ClientEvent::TxSent { signature, endpoint });
+            let result = fallback.send_and_confirm_transaction(transaction);
+            self.record_endpoint_result(endpoint, &result);
+            match result {
+                Ok(signature) => return Ok(signature),
+                Err(err) if err.get_transaction_error() == Some(TransactionError::AlreadyProcessed) => {
+                    return Ok(signature)
+                }
+                Err(err) if is_blockhash_error(&err) => continue,
+                Err(err) => return Err(CarrotError::TransactionFailed(err.to_string())),
+            }
+        }
+
+        Err(primary_err)
+    }
+
+    /// Submit through `broadcaster` and poll the RPC endpoint until the transaction reaches its
+    /// commitment, resubmitting every `BROADCAST_RESEND_INTERVAL` until the blockhash expires
+    fn send_via_broadcaster(&self, broadcaster: &dyn Broadcaster, transaction: &Transaction) -> Result<Signature> {
+        let signature = transaction.signatures[0];
+        let commitment = self.rpc_client().commitment();
+        let deadline = Instant::now() + self.config().finalization_timeout;
+
+        loop {
+            self.emit(ClientEvent::TxSent { signature, endpoint: 0 });
+            if let Err(err) = broadcaster.broadcast(transaction) {
+                // Another submission may already have landed; the status check decides
+                log::warn!("broadcast via {} failed: {}", broadcaster.name(), err);
+            }
+
+            let resend_at = Instant::now() + BROADCAST_RESEND_INTERVAL;
+            while Instant::now() < resend_at {
+                sleep(FINALIZATION_POLL_INTERVAL);
+                let status = self
+                    .rpc_client()
+                    .get_signature_statuses(&[signature])?
+                    .value
+                    .pop()
+                    .flatten();
+                if signature_progress(status, commitment)? == SignatureProgress::Reached {
+                    return Ok(signature);
+                }
+            }
+
+            let blockhash_valid = self
+                .rpc_client()
+                .is_blockhash_valid(&transaction.message.recent_blockhash, CommitmentConfig::processed())?;
+            if !blockhash_valid {
+                return Err(CarrotError::TransactionExpired(signature.to_string()));
+            }
+            if Instant::now() >= deadline {
+                return Err(confirmation_timeout(&signature, commitment, self.config().finalization_timeout));
+            }
+        }
+    }
+
+    /// Count a request's outcome toward `endpoint`'s circuit, announcing it if it opens
+    fn record_endpoint_result<T>(&self, endpoint: usize, result: &std::result::Result<T, ClientError>) {
+        if let Some(cooldown) = self.inner.rpc.circuit_breaker.record(endpoint, result, Instant::now()) {
+            log::warn!("RPC endpoint {} failed repeatedly, skipping it for {:?}", endpoint, cooldown);
+            self.emit(ClientEvent::CircuitOpened { endpoint, cooldown });
+        }
+    }
+
+    /// Check user's asset token balance
+    /// Returns 0 if the token account doesn't exist yet; RPC failures are returned as errors
+    pub fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64> {
+        let ata = self.user_asset_ata(user, asset_mint)?;
+        self.get_token_amount(&ata)
+    }
+
+    /// Check user's CRT token balance
+    /// Returns 0 if the token account doesn't exist yet; RPC failures are returned as errors
+    pub fn get_crt_balance(&self, user: &Pubkey) -> Result<u64> {
+        let ata = get_user_crt_ata(user);
+        self.get_token_amount(&ata)
+    }
+
+    /// Read the user's associated token account for `mint` (an asset or CRT) together with
+    /// the mint's decimals, in one request (only the token account once decimals are cached)
+    pub fn get_balance_detailed(&self, user: &Pubkey, mint: &Pubkey) -> Result<TokenBalance> {
+        let address = self.user_asset_ata(user, mint)?;
+        let (token_account, decimals) = match self.inner.cache.mint_decimals(mint) {
+            Some(decimals) => {
+                let response = self
+                    .rpc_client()
+                    .get_account_with_commitment(&address, self.rpc_client().commitment())?;
+                (response.value, decimals)
+            }
+            None => {
+                let mut accounts = self
+                    .rpc_client()
+                    .get_multiple_accounts(&[address, *mint])?
+                    .into_iter();
+                let token_account = accounts.next().flatten();
+                let mint_account = accounts
+                    .next()
+                    .flatten()
+                    .ok_or_else(|| CarrotError::AccountNotFound(format!("mint {} not found", mint)))?;
+                let decimals = unpack_mint_decimals(&mint_account.data)?;
+                self.inner.cache.set_mint_decimals(*mint, decimals);
+                (token_account, decimals)
+            }
+        };
+        let amount = match &token_account {
+            Some(account) => unpack_token_amount(&account.data)?,
+            None => 0,
+        };
+        Ok(TokenBalance {
+            address,
+            exists: token_account.is_some(),
+            amount,
+            decimals,
+            ui_amount: amount as f64 / 10f64.powi(decimals as i32),
+        })
+    }
+
+    /// `user`'s associated token account for `mint`, under the token program that owns the mint
+    /// (looked up once and cached for mints the SDK has no constant for)
+    fn user_asset_ata(&self, user: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+        let program = match TokenProgram::known(mint) {
+            Some(program) => program,
+            None => self.get_token_program(mint)?,
+        };
+        Ok(program.associated_token_address(user, mint))
+    }
+
+    /// Amount held by a token account, 0 if it doesn't exist
+    fn get_token_amount(&self, address: &Pubkey) -> Result<u64> {
+        let response = self
+            .rpc_client()
+            .get_account_with_commitment(address, self.rpc_client().commitment())?;
+        match response.value {
+            Some(account) => unpack_token_amount(&account.data),
+            None => Ok(0),
+        }
+    }
+}
+
+/// A user's token account balance with its mint's decimals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenBalance {
+    /// Associated token account address
+    pub address: Pubkey,
+    /// Whether the token account exists (`amount` is 0 if not)
+    pub exists: bool,
+    /// Raw amount in base units
+    pub amount: u64,
+    /// Decimals of the mint
+    pub decimals: u8,
+    /// Amount in whole tokens (for display only)
+    pub ui_amount: f64,
+}
+
+/// Instructions for redeeming `amount` CRT for `asset_mint`: asset ATA creation + redeem
+pub(crate) fn redeem_instructions(
+    vault: &VaultHandle,
+    accounts: &RedeemAccounts,
+    amount: u64,
+    hook_accounts: &[AccountMeta],
+) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+
+    // Create ATA for asset if needed (idempotent); other accounts must already exist
+    let asset_token_program = vault.asset_token_program(&accounts.asset_mint)?;
+    if accounts.user_asset_ata == asset_token_program.associated_token_address(&accounts.user, &accounts.asset_mint) {
+        instructions.push(asset_token_program.create_associated_token_account(
+            &accounts.user,
+            &accounts.user,
+            &accounts.asset_mint,
+        ));
+    }
+
+    // Build redeem instruction
+    let mut redeem_ix = build_redeem_instruction_with_accounts(accounts, amount)?;
+    redeem_ix.accounts.extend_from_slice(hook_accounts);
+    instructions.push(redeem_ix);
+
+    Ok(instructions)
+}
+
+/// Issue/redeem accounts with remaining accounts from the configured resolver, paying into or
+/// out of `asset_account` if set
+fn payout_accounts(
+    config: &ClientConfig,
+    vault: &VaultHandle,
+    user: &Pubkey,
+    asset_mint: &Pubkey,
+    asset_account: Option<&Pubkey>,
+) -> Result<IssueAccounts> {
+    let accounts = IssueAccounts::new_with_resolver(vault, user, asset_mint, config.remaining_accounts.as_ref())?;
+    Ok(match asset_account {
+        Some(asset_account) => accounts.with_user_asset_account(*asset_account),
+        None => accounts,
+    })
+}
+
+/// Instructions depositing every (asset, amount) leg: CRT ATA creation + one issue per leg,
+/// paid from `asset_account` if set
+fn issue_instructions(
+    config: &ClientConfig,
+    vault: &VaultHandle,
+    user: &Pubkey,
+    legs: &[(Pubkey, u64)],
+    asset_account: Option<&Pubkey>,
+) -> Result<Vec<Instruction>> {
+    // Create ATA for CRT if needed (idempotent)
+    let mut instructions = vec![TokenProgram::Token2022.create_associated_token_account(user, user, &CRT_MINT)];
+    for (asset_mint, amount) in legs {
+        let accounts = payout_accounts(config, vault, user, asset_mint, asset_account)?;
+        instructions.push(build_issue_instruction_with_accounts(&accounts, *amount)?);
+    }
+    Ok(instructions)
+}
+
+/// The vault's deposit limits as of `snapshot`, valued at `nav`
+fn vault_limits(config: &ClientConfig, snapshot: &VaultSnapshot, nav: &Nav) -> Result<VaultLimits> {
+    Ok(VaultLimits {
+        paused: snapshot.vault.paused,
+        total_value: nav.total_value()?,
+        limits: config.deposit_limits,
+    })
+}
+
+/// Error context of building a deposit of `legs`, naming the asset and amount of a single leg
+fn deposit_context(legs: &[(Pubkey, u64)]) -> ErrorContext {
+    let context = ErrorContext::new(Operation::BuildIssue);
+    match legs {
+        [(asset_mint, amount)] => context.with_asset(*asset_mint).with_amount(*amount),
+        _ => context,
+    }
+}
+
+/// Fail unless depositing every (asset, amount) leg keeps the vault within the configured limits
+fn check_deposit_limits(
+    config: &ClientConfig,
+    snapshot: &VaultSnapshot,
+    nav: &Nav,
+    legs: &[(Pubkey, u64)],
+) -> Result<()> {
+    let value = legs.iter().try_fold(0u128, |sum, (asset, amount)| {
+        Ok::<_, CarrotError>(sum.saturating_add(nav.asset(asset)?.value_of(*amount, Rounding::Down)?))
+    })?;
+    vault_limits(config, snapshot, nav)?.check_deposit(value)
+}
+
+/// The caller's `instructions`, checked against `allowlist` when `verify_programs` is set, then
+/// the SDK memo and `tips`
+///
+/// Tips and the memo go last, after the allowlist check, which only covers the caller's
+/// instructions.
+fn checked_instructions(
+    config: &ClientConfig,
+    allowlist: &ProgramAllowlist,
+    instructions: &[Instruction],
+    tips: Vec<Instruction>,
+) -> Result<Vec<Instruction>> {
+    if config.verify_programs {
+        allowlist.verify(instructions)?;
+    }
+    let mut instructions = instructions.to_vec();
+    if let Some(memo) = sdk_memo(config, &instructions) {
+        instructions.push(memo.instruction()?);
+    }
+    instructions.extend(tips);
+    Ok(instructions)
+}
+
+/// `budget` followed by `instructions`, with their accounts logged when `audit_accounts` is set
+fn with_compute_budget(
+    config: &ClientConfig,
+    budget: Vec<Instruction>,
+    instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
+    let mut all_instructions = budget;
+    all_instructions.extend(instructions);
+    if config.audit_accounts {
+        all_instructions.iter().for_each(log_account_metas);
+    }
+    all_instructions
+}
+
+/// Unsigned transaction of `instructions` for simulation, under the maximum compute-unit limit
+/// so the estimate isn't cut short by the default
+fn simulation_transaction(instructions: &[Instruction], payer: &Pubkey) -> Transaction {
+    let mut sim_instructions = compute_budget_instructions(Some(MAX_COMPUTE_UNIT_LIMIT), None);
+    sim_instructions.extend_from_slice(instructions);
+    Transaction::new_unsigned(Message::new(&sim_instructions, Some(payer)))
+}
+
+fn simulation_config(commitment: CommitmentConfig) -> RpcSimulateTransactionConfig {
+    RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(commitment),
+        ..Default::default()
+    }
+}
+
+/// Compute units consumed by a simulation, failing with its logs if it failed
+fn simulated_units(result: RpcSimulateTransactionResult) -> Result<u64> {
+    if let Some(err) = result.err {
+        let logs = result.logs.unwrap_or_default().join("\n");
+        return Err(CarrotError::SimulationFailed(format!("{}\n{}", err, logs)));
+    }
+    result
+        .units_consumed
+        .ok_or_else(|| CarrotError::SimulationFailed("RPC did not report consumed compute units".to_string()))
+}
+
+/// Vault decoded from the first read of a snapshot, `accounts` being `[VAULT_ADDRESS]`'s
+fn snapshot_vault(accounts: Vec<Option<Account>>) -> Result<Vault> {
+    let vault_account = accounts
+        .into_iter()
+        .next()
+        .flatten()
+        .ok_or_else(|| CarrotError::AccountNotFound("Vault account not found".to_string()))
+        .during(Operation::FetchSnapshot)?;
+    Vault::from_account_data(&vault_account.data)
+}
+
+fn inconsistent_snapshot<T>() -> Result<T> {
+    Err(CarrotError::InconsistentSnapshot(format!(
+        "no consistent vault state after {} attempts",
+        MAX_SNAPSHOT_ATTEMPTS
+    )))
+    .during(Operation::FetchSnapshot)
+}
+
+/// Request config reading accounts at or after `min_slot`
+pub(crate) fn accounts_at_slot_config(commitment: CommitmentConfig, min_slot: u64) -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64Zstd),
+        commitment: Some(commitment),
+        min_context_slot: Some(min_slot),
+        data_slice: None,
+    }
+}
+
+/// Accounts and slot of a read at or after `min_slot`, `None` if the endpoint hasn't reached it
+/// yet; the slot is recorded in `last_context_slot`
+fn accounts_at_slot(
+    response: std::result::Result<Response<Vec<Option<Account>>>, ClientError>,
+    min_slot: u64,
+    last_context_slot: &AtomicU64,
+) -> Result<Option<(u64, Vec<Option<Account>>)>> {
+    match response {
+        Ok(response) if response.context.slot >= min_slot => {
+            let slot = response.context.slot;
+            last_context_slot.fetch_max(slot, Ordering::AcqRel);
+            Ok(Some((slot, response.value)))
+        }
+        Ok(_) => Ok(None),
+        Err(err) if is_min_context_slot_error(&err) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Where a sent transaction stands relative to a commitment level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignatureProgress {
+    Reached,
+    /// Seen by the cluster but not at the commitment yet
+    Pending,
+    /// Not known to the endpoint
+    Unknown,
+}
+
+/// Progress of a transaction from its signature `status`, failing with `TransactionFailed` if
+/// it executed with an error
+fn signature_progress(status: Option<TransactionStatus>, commitment: CommitmentConfig) -> Result<SignatureProgress> {
+    match status {
+        Some(status) if status.err.is_some() => {
+            let err = status.err.map(|e| e.to_string()).unwrap_or_default();
+            Err(CarrotError::TransactionFailed(err))
+        }
+        Some(status) if status.satisfies_commitment(commitment) => Ok(SignatureProgress::Reached),
+        Some(_) => Ok(SignatureProgress::Pending),
+        None => Ok(SignatureProgress::Unknown),
+    }
+}
+
+fn confirmation_timeout(signature: &Signature, commitment: CommitmentConfig, timeout: Duration) -> CarrotError {
+    CarrotError::ConfirmationTimeout(format!("{} not {:?} after {:?}", signature, commitment.commitment, timeout))
+}
+
+#[cfg(feature = "blocking")]
+/// Whether a transaction with `instructions` plus compute budget instructions fits in one packet
+fn fits_in_packet(instructions: &[Instruction], payer: &Pubkey) -> bool {
+    let mut all_instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT),
+        ComputeBudgetInstruction::set_compute_unit_price(0),
+    ];
+    all_instructions.extend_from_slice(instructions);
+    let transaction = Transaction::new_unsigned(Message::new(&all_instructions, Some(payer)));
+    bincode::serialized_size(&transaction).is_ok_and(|size| size as usize <= PACKET_DATA_SIZE)
+}
+
+#[cfg(feature = "blocking")]
+/// Make `funder` pay the rent of the associated token accounts `instructions` create
+fn fund_account_creation(instructions: &mut [Instruction], funder: &Pubkey) {
+    instructions
+        .iter_mut()
+        .filter(|instruction| instruction.program_id == spl_associated_token_account::id())
+        .for_each(|instruction| instruction.accounts[0] = AccountMeta::new(*funder, true));
+}
+
+/// Memo identifying a transaction of `instructions`, when an integrator or correlation ID
+/// is configured
+fn sdk_memo(config: &ClientConfig, instructions: &[Instruction]) -> Option<SdkMemo> {
+    if config.integrator_id.is_none() && config.correlation_id.is_none() {
+        return None;
+    }
+    Some(SdkMemo {
+        integrator_id: config.integrator_id.clone(),
+        correlation_id: config.correlation_id.clone(),
+        ..SdkMemo::new(MemoAction::of(instructions))
+    })
+}
+
+#[cfg(feature = "blocking")]
+/// Whether a send/confirm failure may come from the endpoint not knowing the blockhash
+/// (or the blockhash expiring before confirmation), so another endpoint may still land it
+fn is_blockhash_error(err: &ClientError) -> bool {
+    if err.get_transaction_error() == Some(TransactionError::BlockhashNotFound) {
+        return true;
+    }
+    let message = err.to_string();
+    message.contains("Blockhash not found") || message.contains("unable to confirm transaction")
+}
+
+/// Whether the endpoint rejected a read because it hasn't reached `min_context_slot` yet
+fn is_min_context_slot_error(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+    )
+}
+
+#[cfg(feature = "blocking")]
+/// Build an RPC client for `rpc_url` that applies the configured timeout and extra headers
+fn build_rpc_client(config: &ClientConfig, rpc_url: &str) -> Result<RpcClient> {
+    Ok(RpcClient::new_sender(
+        build_http_sender(config, rpc_url)?,
+        RpcClientConfig::with_commitment(config.commitment),
+    ))
+}
+
+/// HTTP transport for `rpc_url` with the configured timeout and extra headers
+fn build_http_sender(config: &ClientConfig, rpc_url: &str) -> Result<HttpSender> {
+    let mut headers: HeaderMap = HttpSender::default_headers();
+    for (name, value) in &config.headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| CarrotError::InvalidConfig(format!("invalid header name: {}", name)))?;
+        // Header values are often secrets, so keep them out of error messages
+        let mut header_value = HeaderValue::from_str(value)
+            .map_err(|_| CarrotError::InvalidConfig(format!("invalid value for header {}", name)))?;
+        header_value.set_sensitive(true);
+        headers.insert(header_name, header_value);
+    }
+
+    let http_client = reqwest::Client::builder()
+        .default_headers(headers)
+        .timeout(config.timeout)
+        .pool_idle_timeout(config.timeout)
+        .build()
+        .map_err(|e| CarrotError::InvalidConfig(format!("failed to build HTTP client: {}", e)))?;
+
+    Ok(HttpSender::new_with_client(rpc_url, http_client))
+}
+
+#[cfg(feature = "blocking")]
+/// Convenience function to deposit USDC
+pub fn deposit_usdc(rpc_url: String, user: &dyn Signer, amount_usdc: u64) -> Result<Signature> {
+    let client = CarrotClient::new(rpc_url);
+    client.deposit(user, &crate::USDC_MINT, amount_usdc)
+}
+
+#[cfg(feature = "blocking")]
+/// Convenience function to withdraw CRT for USDC
+pub fn withdraw_crt(rpc_url: String, user: &dyn Signer, amount_crt: u64) -> Result<Signature> {
+    let client = CarrotClient::new(rpc_url);
+    client.withdraw(user, &crate::USDC_MINT, amount_crt)
+}
+
+#[cfg(feature = "async")]
+pub use nonblocking::AsyncCarrotClient;
+
+#[cfg(feature = "async")]
+mod nonblocking {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::RpcClientConfig};
+    use solana_commitment_config::CommitmentConfig;
+    use solana_sdk::{
+        account::Account,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        pubkey::Pubkey,
+        signature::Signature,
+        signer::Signer,
+        transaction::Transaction,
+    };
+
+    use super::{
+        accounts_at_slot, accounts_at_slot_config, build_http_sender, check_deposit_limits, checked_instructions,
+        confirmation_timeout, deposit_context, inconsistent_snapshot, issue_instructions, payout_accounts,
+        redeem_instructions, signature_progress, simulated_units, simulation_config, simulation_transaction,
+        snapshot_vault, with_compute_budget, SignatureProgress, FINALIZATION_POLL_INTERVAL, MAX_SNAPSHOT_ATTEMPTS,
+        SNAPSHOT_RETRY_DELAY,
+    };
+    use crate::{
+        accounts::get_user_crt_ata,
+        allowlist::ProgramAllowlist,
+        compute_units::{
+            compute_budget_instructions, limit_with_margin, ComputeUnitLimit, ComputeUnitPrice, DepositOptions,
+        },
+        config::ClientConfig,
+        error::{CarrotError, ErrorContext, Operation, Result, ResultExt},
+        liquidity::WithdrawalLiquidity,
+        nav::Nav,
+        policy::{PolicyAction, PolicyLegs},
+        priority_fee::{PriorityFeeEstimator, PriorityFeePolicy},
+        snapshot::{build_snapshot, snapshot_accounts, VaultSnapshot},
+        token::{unpack_token_amount, TokenProgram},
+        transfer_hook::{payout_transfer, HookResolution, HookStep, HookedTransfer},
+        Vault, VaultHandle, VAULT_ADDRESS,
+    };
+
+    /// `CarrotClient` for tokio services, on the nonblocking RPC client
+    ///
+    /// Covers vault reads, balances, deposits and withdrawals with the same checks as the
+    /// blocking client: pause state, deposit limits, policies, withdrawal liquidity, the
+    /// program allowlist and compute budget. Transactions go to `ClientConfig::rpc_url` only;
+    /// fallback endpoints, the circuit breaker, broadcasters, client events and startup
+    /// validation are left to `CarrotClient`. Cloning is cheap.
+    #[derive(Clone)]
+    pub struct AsyncCarrotClient {
+        inner: Arc<AsyncClientInner>,
+    }
+
+    struct AsyncClientInner {
+        rpc_client: RpcClient,
+        config: ClientConfig,
+        program_allowlist: ProgramAllowlist,
+        /// Newest slot observed in a snapshot read, used as `min_context_slot`
+        last_context_slot: AtomicU64,
+    }
+
+    impl AsyncCarrotClient {
+        /// Create a new async client with the given RPC URL
+        pub fn new(rpc_url: String) -> Self {
+            Self::with_config(ClientConfig::new(rpc_url)).expect("default client config is valid")
+        }
+
+        /// Create a new async client from a full configuration
+        ///
+        /// Fails with `InvalidConfig` if a `Broadcaster` is set: broadcasters are blocking.
+        pub fn with_config(config: ClientConfig) -> Result<Self> {
+            if config.broadcaster.is_some() {
+                return Err(CarrotError::InvalidConfig(
+                    "broadcasters are only supported by the blocking CarrotClient".to_string(),
+                ));
+            }
+            config.validate_policies()?;
+            let rpc_client = RpcClient::new_sender(
+                build_http_sender(&config, &config.rpc_url)?,
+                RpcClientConfig::with_commitment(config.commitment),
+            );
+            Ok(Self {
+                inner: Arc::new(AsyncClientInner {
+                    rpc_client,
+                    program_allowlist: ProgramAllowlist::with_extra(&config.extra_allowed_programs),
+                    config,
+                    last_context_slot: AtomicU64::new(0),
+                }),
+            })
+        }
+
+        /// The configuration this client was created with
+        pub fn config(&self) -> &ClientConfig {
+            &self.inner.config
+        }
+
+        /// Access the underlying nonblocking RPC client
+        pub fn rpc_client(&self) -> &RpcClient {
+            &self.inner.rpc_client
+        }
+
+        /// Current Unix timestamp per `ClientConfig::clock`
+        fn now(&self) -> i64 {
+            self.config().clock.unix_timestamp()
+        }
+
+        /// Fetch and deserialize vault data from the blockchain
+        pub async fn fetch_vault(&self) -> Result<Vault> {
+            let account = self
+                .rpc_client()
+                .get_account(&VAULT_ADDRESS)
+                .await
+                .map_err(|_| CarrotError::AccountNotFound("Vault account not found".to_string()))
+                .during(Operation::FetchVault)?;
+            Vault::from_account_data(&account.data)
+        }
+
+        /// Fetch the vault together with its address, for building instructions
+        pub async fn fetch_vault_handle(&self) -> Result<VaultHandle> {
+            Ok(VaultHandle::new(VAULT_ADDRESS, self.fetch_vault().await?))
+        }
+
+        /// Read the vault, its token accounts, the CRT mint and oracles at a single slot
+        pub async fn fetch_snapshot(&self) -> Result<VaultSnapshot> {
+            for attempt in 0..MAX_SNAPSHOT_ATTEMPTS {
+                if attempt > 0 {
+                    tokio::time::sleep(SNAPSHOT_RETRY_DELAY).await;
+                }
+
+                let min_slot = self.inner.last_context_slot.load(Ordering::Acquire);
+                let Some((vault_slot, accounts)) = self
+                    .get_accounts_at_slot(&[VAULT_ADDRESS], min_slot)
+                    .await
+                    .during(Operation::FetchSnapshot)?
+                else {
+                    continue;
+                };
+                let vault = snapshot_vault(accounts)?;
+
+                // Re-read the vault together with everything else so all values share one slot
+                let Some((slot, accounts)) = self
+                    .get_accounts_at_slot(&snapshot_accounts(&vault), vault_slot)
+                    .await
+                    .during(Operation::FetchSnapshot)?
+                else {
+                    continue;
+                };
+                if let Some(snapshot) = build_snapshot(slot, &vault, accounts)? {
+                    return Ok(snapshot);
+                }
+            }
+
+            inconsistent_snapshot()
+        }
+
+        /// Compute the vault's net asset value from a single-slot snapshot,
+        /// net of the management fee accrued since the vault's last fee update
+        pub async fn fetch_nav(&self) -> Result<Nav> {
+            let snapshot = self.fetch_snapshot().await?;
+            Nav::from_snapshot_with_fees(&snapshot, self.now())
+        }
+
+        /// Check user's asset token balance
+        /// Returns 0 if the token account doesn't exist yet; RPC failures are returned as errors
+        pub async fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64> {
+            let program = match TokenProgram::known(asset_mint) {
+                Some(program) => program,
+                None => {
+                    let mint = self.rpc_client().get_account(asset_mint).await?;
+                    TokenProgram::from_id(&mint.owner).ok_or_else(|| {
+                        CarrotError::InvalidAsset(format!("{} is not owned by a token program", asset_mint))
+                    })?
+                }
+            };
+            self.get_token_amount(&program.associated_token_address(user, asset_mint))
+                .await
+        }
+
+        /// Check user's CRT token balance
+        /// Returns 0 if the token account doesn't exist yet; RPC failures are returned as errors
+        pub async fn get_crt_balance(&self, user: &Pubkey) -> Result<u64> {
+            self.get_token_amount(&get_user_crt_ata(user)).await
+        }
+
+        /// Deposit asset tokens (USDC, USDT, pyUSD) and receive CRT shares
+        pub async fn deposit(
+            &self,
+            user: &(dyn Signer + Sync),
+            asset_mint: &Pubkey,
+            amount: u64,
+        ) -> Result<Signature> {
+            self.deposit_with_options(user, asset_mint, amount, &DepositOptions::default()).await
+        }
+
+        /// `deposit` with its own compute-unit limit and price
+        pub async fn deposit_with_options(
+            &self,
+            user: &(dyn Signer + Sync),
+            asset_mint: &Pubkey,
+            amount: u64,
+            options: &DepositOptions,
+        ) -> Result<Signature> {
+            self.check_approval_not_required()?;
+            let instructions = self.deposit_instructions(&user.pubkey(), asset_mint, amount).await?;
+            self.send_transaction(&instructions, user, options).await
+        }
+
+        /// Withdraw by burning CRT shares and receiving `asset_mint`
+        pub async fn withdraw(
+            &self,
+            user: &(dyn Signer + Sync),
+            asset_mint: &Pubkey,
+            amount: u64,
+        ) -> Result<Signature> {
+            self.withdraw_with_options(user, asset_mint, amount, &DepositOptions::default()).await
+        }
+
+        /// `withdraw` with its own compute-unit limit and price
+        pub async fn withdraw_with_options(
+            &self,
+            user: &(dyn Signer + Sync),
+            asset_mint: &Pubkey,
+            amount: u64,
+            options: &DepositOptions,
+        ) -> Result<Signature> {
+            self.check_approval_not_required()?;
+            let instructions = self.withdraw_instructions(&user.pubkey(), asset_mint, amount).await?;
+            self.send_transaction(&instructions, user, options).await
+        }
+
+        /// Estimate the compute-unit price (micro-lamports) for issue/redeem transactions
+        /// from fees recently paid for the vault and CRT mint
+        pub async fn estimate_priority_fee(&self, policy: &PriorityFeePolicy) -> Result<u64> {
+            let estimator = PriorityFeeEstimator::new(*policy);
+            let recent_fees = self
+                .rpc_client()
+                .get_recent_prioritization_fees(estimator.accounts())
+                .await?;
+            let fees: Vec<u64> = recent_fees.iter().map(|f| f.prioritization_fee).collect();
+            Ok(estimator.policy().select(&fees))
+        }
+
+        /// Simulate the instructions and return the compute units they consume
+        pub async fn simulate_compute_units(&self, instructions: &[Instruction], payer: &Pubkey) -> Result<u64> {
+            let result = self
+                .rpc_client()
+                .simulate_transaction_with_config(
+                    &simulation_transaction(instructions, payer),
+                    simulation_config(self.rpc_client().commitment()),
+                )
+                .await?
+                .value;
+            simulated_units(result)
+        }
+
+        /// Wait until a confirmed transaction reaches finalized commitment
+        ///
+        /// Fails with `TransactionDropped` if the signature disappears from the cluster after its
+        /// blockhash expired, and with `ConfirmationTimeout` if it isn't finalized in time.
+        pub async fn wait_for_finalization(&self, signature: &Signature, recent_blockhash: &Hash) -> Result<()> {
+            let deadline = Instant::now() + self.config().finalization_timeout;
+
+            loop {
+                let status = self
+                    .rpc_client()
+                    .get_signature_statuses(&[*signature])
+                    .await?
+                    .value
+                    .pop()
+                    .flatten();
+
+                match signature_progress(status, CommitmentConfig::finalized())? {
+                    SignatureProgress::Reached => return Ok(()),
+                    SignatureProgress::Pending => {}
+                    SignatureProgress::Unknown => {
+                        // No longer known: if the blockhash expired it can never land again
+                        let blockhash_valid = self
+                            .rpc_client()
+                            .is_blockhash_valid(recent_blockhash, CommitmentConfig::processed())
+                            .await?;
+                        if !blockhash_valid {
+                            return Err(CarrotError::TransactionDropped(signature.to_string()));
+                        }
+                    }
+                }
+
+                if Instant::now() >= deadline {
+                    return Err(confirmation_timeout(
+                        signature,
+                        CommitmentConfig::finalized(),
+                        self.config().finalization_timeout,
+                    ));
+                }
+                tokio::time::sleep(FINALIZATION_POLL_INTERVAL).await;
+            }
+        }
+
+        /// Fail with `ApprovalRequired` if operations must be approved before broadcast
+        fn check_approval_not_required(&self) -> Result<()> {
+            match self.config().approvers {
+                Some(_) => Err(CarrotError::ApprovalRequired),
+                None => Ok(()),
+            }
+        }
+
+        /// Read accounts in a single request at or after `min_slot`
+        /// Returns `None` if the endpoint hasn't reached `min_slot` yet
+        async fn get_accounts_at_slot(
+            &self,
+            pubkeys: &[Pubkey],
+            min_slot: u64,
+        ) -> Result<Option<(u64, Vec<Option<Account>>)>> {
+            let config = accounts_at_slot_config(self.rpc_client().commitment(), min_slot);
+            let response = self.rpc_client().get_multiple_accounts_with_config(pubkeys, config).await;
+            accounts_at_slot(response, min_slot, &self.inner.last_context_slot)
+        }
+
+        /// Amount held by a token account, 0 if it doesn't exist
+        async fn get_token_amount(&self, address: &Pubkey) -> Result<u64> {
+            let response = self
+                .rpc_client()
+                .get_account_with_commitment(address, self.rpc_client().commitment())
+                .await?;
+            match response.value {
+                Some(account) => unpack_token_amount(&account.data),
+                None => Ok(0),
+            }
+        }
+
+        /// Check the policy applying to `user` without counting the transaction; usage is
+        /// charged when it is broadcast (`charge_policy`)
+        ///
+        /// `legs` are (asset, amount) pairs: asset amounts for deposits, CRT for withdrawals.
+        async fn check_policy(&self, user: &Pubkey, action: PolicyAction, legs: &[(Pubkey, u64)]) -> Result<()> {
+            let legs = PolicyLegs::new(*user, action, legs.to_vec());
+            let nav = if legs.needs_value(self.config()) { Some(self.fetch_nav().await?) } else { None };
+            self.config().policy_usage.check(self.config(), &legs.value(nav.as_ref())?, self.now())
+        }
+
+        /// Count the deposits and withdrawals in `transaction` toward their users' policy usage,
+        /// failing with `PolicyViolation` if that would exceed a limit
+        async fn charge_policy(&self, transaction: &Transaction) -> Result<()> {
+            let usage = &self.config().policy_usage;
+            let legs = usage.uncharged_legs(self.config(), transaction)?;
+            let nav = if legs.iter().any(|legs| legs.needs_value(self.config())) {
+                Some(self.fetch_nav().await?)
+            } else {
+                None
+            };
+            usage.charge_transaction(self.config(), transaction, &legs, nav.as_ref(), self.now())
+        }
+
+        /// Instructions for a deposit: CRT ATA creation + issue
+        async fn deposit_instructions(
+            &self,
+            user_pubkey: &Pubkey,
+            asset_mint: &Pubkey,
+            amount: u64,
+        ) -> Result<Vec<Instruction>> {
+            let legs = [(*asset_mint, amount)];
+            let context = deposit_context(&legs);
+            // With limits, the pause flag, limits and instructions all come from one snapshot
+            let vault = if self.config().deposit_limits.is_set() {
+                let snapshot = self.fetch_snapshot().await.during(context)?;
+                if snapshot.vault.paused {
+                    return Err(CarrotError::VaultPaused);
+                }
+                let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+                check_deposit_limits(self.config(), &snapshot, &nav, &legs).during(context)?;
+                VaultHandle::new(VAULT_ADDRESS, snapshot.vault)
+            } else {
+                let vault = self.fetch_vault_handle().await.during(context)?;
+                if vault.vault.paused {
+                    return Err(CarrotError::VaultPaused);
+                }
+                vault
+            };
+            self.check_policy(user_pubkey, PolicyAction::Deposit, &legs)
+                .await
+                .during(context)?;
+
+            issue_instructions(self.config(), &vault, user_pubkey, &legs, None)
+        }
+
+        /// Instructions for a withdrawal: asset ATA creation + redeem
+        async fn withdraw_instructions(
+            &self,
+            user_pubkey: &Pubkey,
+            asset_mint: &Pubkey,
+            amount: u64,
+        ) -> Result<Vec<Instruction>> {
+            let context = ErrorContext::new(Operation::BuildRedeem)
+                .with_asset(*asset_mint)
+                .with_amount(amount);
+            self.check_policy(user_pubkey, PolicyAction::Withdraw, &[(*asset_mint, amount)])
+                .await
+                .during(context)?;
+            let snapshot = self.fetch_snapshot().await.during(context)?;
+            let nav = Nav::from_snapshot_with_fees(&snapshot, self.now()).during(context)?;
+            // Redemptions are paid from idle funds; fail early instead of on-chain
+            WithdrawalLiquidity::from_nav(&nav, snapshot.vault.fee.redemption_fee_bps, asset_mint, amount)?
+                .ensure_sufficient()?;
+            let vault = VaultHandle::new(VAULT_ADDRESS, snapshot.vault);
+            let payout = payout_accounts(self.config(), &vault, user_pubkey, asset_mint, None)?;
+            let hook_accounts = match payout_transfer(&vault, asset_mint, &payout.user_asset_ata)? {
+                Some(transfer) => self.transfer_hook_accounts(&transfer).await?,
+                None => Vec::new(),
+            };
+
+            redeem_instructions(&vault, &payout, amount, &hook_accounts)
+        }
+
+        /// Accounts to append to `transfer_checked` for `transfer`, empty if its mint has no hook
+        async fn transfer_hook_accounts(&self, transfer: &HookedTransfer) -> Result<Vec<AccountMeta>> {
+            let mut resolution = HookResolution::new(*transfer);
+            loop {
+                match resolution.step()? {
+                    HookStep::Fetch(address) => {
+                        let account = self.fetch_account(&address).await?;
+                        resolution.provide(address, account);
+                    }
+                    HookStep::Resolved(accounts) => return Ok(accounts),
+                }
+            }
+        }
+
+        async fn fetch_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+            let response = self
+                .rpc_client()
+                .get_account_with_commitment(address, self.rpc_client().commitment())
+                .await?;
+            Ok(response.value)
+        }
+
+        /// Sign and send a transaction of `instructions` with compute budget instructions and
+        /// the SDK memo, then wait for the configured commitment
+        async fn send_transaction(
+            &self,
+            instructions: &[Instruction],
+            signer: &(dyn Signer + Sync),
+            options: &DepositOptions,
+        ) -> Result<Signature> {
+            let payer = signer.pubkey();
+            let allowlist = &self.inner.program_allowlist;
+            let instructions = checked_instructions(self.config(), allowlist, instructions, Vec::new())?;
+            let budget = self
+                .compute_budget_instructions(&instructions, &payer, options)
+                .await
+                .during(Operation::BuildTransaction)?;
+            let all_instructions = with_compute_budget(self.config(), budget, instructions);
+
+            let recent_blockhash = match &self.config().blockhash_source {
+                Some(source) => source.latest_blockhash()?.0,
+                None => {
+                    self.rpc_client()
+                        .get_latest_blockhash_with_commitment(self.rpc_client().commitment())
+                        .await?
+                        .0
+                }
+            };
+            let mut transaction = Transaction::new_unsigned(Message::new(&all_instructions, Some(&payer)));
+            transaction
+                .try_sign(&[signer], recent_blockhash)
+                .map_err(|e| CarrotError::TransactionFailed(format!("failed to sign: {}", e)))?;
+
+            self.charge_policy(&transaction).await.during(Operation::Send)?;
+            let result = self.send_and_confirm(&transaction, &recent_blockhash).await;
+            if result.as_ref().is_err_and(|err| !err.is_unconfirmed()) {
+                self.refund_policy(&transaction.signatures[0]);
+            }
+            result
+        }
+
+        /// Stop counting the transaction signed `signature` toward policy usage, once it can't land
+        fn refund_policy(&self, signature: &Signature) {
+            if let Err(err) = self.config().policy_usage.refund(self.config(), signature) {
+                log::warn!("failed to refund the policy usage of {}: {}", signature, err);
+            }
+        }
+
+        /// Send `transaction` and wait for the configured commitment
+        async fn send_and_confirm(&self, transaction: &Transaction, recent_blockhash: &Hash) -> Result<Signature> {
+            let signature = self
+                .rpc_client()
+                .send_and_confirm_transaction(transaction)
+                .await
+                .map_err(|e| CarrotError::TransactionFailed(e.to_string()))
+                .during(Operation::Send)?;
+            if self.config().wait_for_finalized {
+                self.wait_for_finalization(&signature, recent_blockhash)
+                    .await
+                    .during(Operation::Confirm)?;
+            }
+            Ok(signature)
+        }
+
+        /// Compute budget instructions to prepend according to `options` and the client configuration
+        async fn compute_budget_instructions(
+            &self,
+            instructions: &[Instruction],
+            payer: &Pubkey,
+            options: &DepositOptions,
+        ) -> Result<Vec<Instruction>> {
+            let price = match options.compute_unit_price(self.config().priority_fee.as_ref()) {
+                ComputeUnitPrice::Fixed(price) => Some(price),
+                ComputeUnitPrice::Estimated(policy) => Some(self.estimate_priority_fee(policy).await?),
+                ComputeUnitPrice::Unset => None,
+            };
+            let limit = match options.compute_unit_limit(self.config().compute_unit_limit)? {
+                ComputeUnitLimit::Simulated { margin } => {
+                    let mut sim_instructions = compute_budget_instructions(None, price);
+                    sim_instructions.extend_from_slice(instructions);
+                    let units = self.simulate_compute_units(&sim_instructions, payer).await?;
+                    Some(limit_with_margin(units, margin))
+                }
+                ComputeUnitLimit::Fixed(limit) => Some(limit),
+                ComputeUnitLimit::RuntimeDefault => None,
+            };
+            Ok(compute_budget_instructions(limit, price))
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "blocking", feature = "async")))]
+mod tests {
+    use super::*;
+    #[cfg(feature = "blocking")]
+    use solana_client::{client_error::ClientErrorKind, rpc_request::RpcError};
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_is_blockhash_error() {
+        assert!(is_blockhash_error(&ClientError::from(TransactionError::BlockhashNotFound)));
+
+        let expired = ClientError::from(RpcError::ForUser(
+            "unable to confirm transaction. This can happen in situations such as transaction expiration and insufficient fee-payer funds".to_string(),
+        ));
+        assert!(is_blockhash_error(&expired));
+
+        assert!(!is_blockhash_error(&ClientError::from(TransactionError::InsufficientFundsForFee)));
+        assert!(!is_blockhash_error(&ClientError::from(ClientErrorKind::Custom(
+            "connection refused".to_string()
+        ))));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_fund_account_creation() {
+        let user = Pubkey::new_unique();
+        let sponsor = Pubkey::new_unique();
+        let mut instructions = vec![
+            TokenProgram::Token2022.create_associated_token_account(&user, &user, &crate::CRT_MINT),
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+        ];
+        let untouched = instructions[1].clone();
+        fund_account_creation(&mut instructions, &sponsor);
+
+        assert_eq!(instructions[0].accounts[0], AccountMeta::new(sponsor, true));
+        // The account is still created for the user
+        assert_eq!(
+            instructions[0].accounts[1].pubkey,
+            TokenProgram::Token2022.associated_token_address(&user, &crate::CRT_MINT)
+        );
+        assert_eq!(instructions[1], untouched);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_three_redemptions_fit_in_one_packet() {
+        use crate::{test_utils::stablecoin_vault, PYUSD_MINT, USDC_MINT, USDT_MINT};
+
+        let vault = stablecoin_vault(&[USDC_MINT, USDT_MINT, PYUSD_MINT]);
+        let user = Pubkey::new_unique();
+        let instructions: Vec<Instruction> = [USDC_MINT, USDT_MINT, PYUSD_MINT]
+            .iter()
+            .flat_map(|mint| redeem_instructions(&vault, &RedeemAccounts::new(&vault, &user, mint).unwrap(), 1, &[]).unwrap())
+            .collect();
+        assert!(fits_in_packet(&instructions, &user));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_deposit_options_set_compute_budget() {
+        // Both values given: nothing to estimate or simulate, so no RPC is made
+        let client = CarrotClient::new("http://127.0.0.1:1".to_string());
+        let options = DepositOptions::new().with_cu_limit(300_000).with_cu_price(25_000);
+        let budget = client.compute_budget_instructions(&[], &Pubkey::new_unique(), &options).unwrap();
+        assert_eq!(
+            budget,
+            [
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ComputeBudgetInstruction::set_compute_unit_price(25_000),
+            ]
+        );
+
+        let oversized = DepositOptions::new().with_cu_limit(MAX_COMPUTE_UNIT_LIMIT + 1);
+        let result = client.compute_budget_instructions(&[], &Pubkey::new_unique(), &oversized);
+        assert!(matches!(result, Err(CarrotError::InvalidConfig(_))));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_injected_sources_build_identical_transactions() {
+        use crate::{FixedBlockhash, FixedClock};
+
+        let clock = Arc::new(FixedClock::new(1_700_000_000));
+        let config = ClientConfig::new("http://127.0.0.1:1")
+            .with_compute_unit_limit(ComputeUnitLimit::Fixed(200_000))
+            .with_blockhash_source(Arc::new(FixedBlockhash::new(Hash::new_from_array([7; 32]), 1_000)))
+            .with_clock(clock.clone())
+            .with_integrator_id("tests");
+        let client = CarrotClient::with_config(config).unwrap();
+        let user = solana_sdk::signature::Keypair::new_from_array([3; 32]);
+        let instructions = [ComputeBudgetInstruction::set_compute_unit_price(1)];
+
+        // No RPC request is made: the limit is fixed and the blockhash injected
+        let first = client.build_transaction(&instructions, &user).unwrap();
+        let second = client.build_transaction(&instructions, &user).unwrap();
+        assert_eq!(bincode::serialize(&first.transaction).unwrap(), bincode::serialize(&second.transaction).unwrap());
+        assert_eq!(first.last_valid_block_height, 1_000);
+        assert_eq!(first.expires_at, second.expires_at);
+        assert_eq!(client.now(), 1_700_000_000);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(client.now(), 1_700_000_060);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_client() {
+        let broadcaster = std::sync::Arc::new(crate::RpcBroadcaster::new("http://127.0.0.1:1"));
+        let config = ClientConfig::new("http://127.0.0.1:1".to_string()).with_broadcaster(broadcaster);
+        assert!(matches!(AsyncCarrotClient::with_config(config), Err(CarrotError::InvalidConfig(_))));
+
+        // Approval is required before anything is read
+        let config = ClientConfig::new("http://127.0.0.1:1".to_string()).with_approvers(vec![Pubkey::new_unique()]);
+        let client = AsyncCarrotClient::with_config(config).unwrap();
+        // Futures can be spawned onto the runtime
+        let user = solana_sdk::signature::Keypair::new();
+        let deposit = tokio::spawn(async move { client.deposit(&user, &crate::USDC_MINT, 1).await });
+        assert!(matches!(deposit.await.unwrap(), Err(CarrotError::ApprovalRequired)));
+
+        let client = AsyncCarrotClient::new("http://127.0.0.1:1".to_string());
+        let balance = tokio::spawn(async move { client.get_crt_balance(&Pubkey::new_unique()).await });
+        assert!(balance.await.unwrap().is_err());
+    }
 }
@@ -0,0 +1,83 @@
+//! Wall-clock time seen by the client
+//!
+//! NAV accruals, policy windows, prepared transaction lifetimes and scheduled tasks read the
+//! time through `ClientConfig::clock`, so tests can pin it with a `FixedClock`.
+
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Source of the current time
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Current Unix timestamp in seconds
+    fn unix_timestamp(&self) -> i64 {
+        self.now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// The system's wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, with one-second resolution
+#[derive(Debug, Default)]
+pub struct FixedClock {
+    unix_timestamp: AtomicI64,
+}
+
+impl FixedClock {
+    pub fn new(unix_timestamp: i64) -> Self {
+        Self {
+            unix_timestamp: AtomicI64::new(unix_timestamp),
+        }
+    }
+
+    pub fn set(&self, unix_timestamp: i64) {
+        self.unix_timestamp.store(unix_timestamp, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.unix_timestamp.fetch_add(by.as_secs() as i64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        let seconds = self.unix_timestamp.load(Ordering::Relaxed);
+        match u64::try_from(seconds) {
+            Ok(seconds) => SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+            Err(_) => SystemTime::UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+        }
+    }
+
+    fn unix_timestamp(&self) -> i64 {
+        self.unix_timestamp.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_clock() {
+        let clock = FixedClock::new(1_700_000_000);
+        assert_eq!(clock.unix_timestamp(), 1_700_000_000);
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_090));
+        clock.set(-5);
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH - Duration::from_secs(5));
+        assert!(SystemClock.unix_timestamp() > 1_700_000_000);
+    }
+}
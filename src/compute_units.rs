@@ -0,0 +1,144 @@
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+
+use crate::{
+    error::{CarrotError, Result},
+    priority_fee::PriorityFeePolicy,
+};
+
+/// Maximum compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Default safety margin applied to simulated compute units
+pub const DEFAULT_COMPUTE_UNIT_MARGIN: f64 = 1.2;
+
+/// How the compute-unit limit of sent transactions is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComputeUnitLimit {
+    /// Simulate the transaction and request the consumed units times `margin`
+    Simulated { margin: f64 },
+    /// Request a fixed compute-unit limit
+    Fixed(u32),
+    /// Don't set a limit, leaving the runtime default (200k per instruction)
+    RuntimeDefault,
+}
+
+impl Default for ComputeUnitLimit {
+    fn default() -> Self {
+        ComputeUnitLimit::Simulated {
+            margin: DEFAULT_COMPUTE_UNIT_MARGIN,
+        }
+    }
+}
+
+/// Where the compute-unit price of a transaction comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ComputeUnitPrice<'a> {
+    Fixed(u64),
+    /// Estimated from recent fees with the policy
+    Estimated(&'a PriorityFeePolicy),
+    /// No price, paying the base fee only
+    Unset,
+}
+
+/// Compute budget of a single deposit or withdrawal, overriding `ClientConfig::compute_unit_limit`
+/// and `ClientConfig::priority_fee` where set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DepositOptions {
+    /// Compute-unit limit to request instead of the configured one
+    pub cu_limit: Option<u32>,
+    /// Compute-unit price instead of the one estimated by the configured `PriorityFeePolicy`
+    pub cu_price_micro_lamports: Option<u64>,
+    /// Estimate the compute-unit price with this policy instead of the configured one; ignored
+    /// when `cu_price_micro_lamports` is set
+    pub priority_fee: Option<PriorityFeePolicy>,
+}
+
+impl DepositOptions {
+    /// Options leaving the client configuration in charge
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cu_limit(mut self, limit: u32) -> Self {
+        self.cu_limit = Some(limit);
+        self
+    }
+
+    pub fn with_cu_price(mut self, micro_lamports: u64) -> Self {
+        self.cu_price_micro_lamports = Some(micro_lamports);
+        self
+    }
+
+    /// Estimate the price from recent fees, e.g. with `PriorityFeePolicy::high()`
+    pub fn with_priority_fee(mut self, policy: PriorityFeePolicy) -> Self {
+        self.priority_fee = Some(policy);
+        self
+    }
+
+    /// The limit to apply given the `configured` one; rejects limits the runtime would refuse
+    pub fn compute_unit_limit(&self, configured: ComputeUnitLimit) -> Result<ComputeUnitLimit> {
+        match self.cu_limit {
+            None => Ok(configured),
+            Some(limit) if limit == 0 || limit > MAX_COMPUTE_UNIT_LIMIT => Err(CarrotError::InvalidConfig(format!(
+                "compute-unit limit must be between 1 and {}, got {}",
+                MAX_COMPUTE_UNIT_LIMIT, limit
+            ))),
+            Some(limit) => Ok(ComputeUnitLimit::Fixed(limit)),
+        }
+    }
+
+    /// The price to apply given the `configured` priority fee policy
+    pub(crate) fn compute_unit_price<'a>(&'a self, configured: Option<&'a PriorityFeePolicy>) -> ComputeUnitPrice<'a> {
+        match (self.cu_price_micro_lamports, self.priority_fee.as_ref().or(configured)) {
+            (Some(price), _) => ComputeUnitPrice::Fixed(price),
+            (None, Some(policy)) => ComputeUnitPrice::Estimated(policy),
+            (None, None) => ComputeUnitPrice::Unset,
+        }
+    }
+}
+
+/// Compute budget instructions for `limit` and `price`, the limit first
+pub(crate) fn compute_budget_instructions(limit: Option<u32>, price: Option<u64>) -> Vec<Instruction> {
+    let limit = limit.map(ComputeBudgetInstruction::set_compute_unit_limit);
+    let price = price.map(ComputeBudgetInstruction::set_compute_unit_price);
+    limit.into_iter().chain(price).collect()
+}
+
+/// Apply a safety margin to simulated compute units, capped at the transaction maximum
+pub fn limit_with_margin(units_consumed: u64, margin: f64) -> u32 {
+    let limit = (units_consumed as f64 * margin.max(1.0)).ceil();
+    if limit >= MAX_COMPUTE_UNIT_LIMIT as f64 {
+        MAX_COMPUTE_UNIT_LIMIT
+    } else {
+        limit as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_with_margin() {
+        assert_eq!(limit_with_margin(100_000, 1.2), 120_000);
+        assert_eq!(limit_with_margin(33_333, 1.2), 40_000);
+        // Margins below 1.0 would guarantee failure, so they are ignored
+        assert_eq!(limit_with_margin(50_000, 0.5), 50_000);
+        assert_eq!(limit_with_margin(1_300_000, 1.2), MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_deposit_options_override_limit() {
+        let configured = ComputeUnitLimit::default();
+        assert_eq!(DepositOptions::new().compute_unit_limit(configured).unwrap(), configured);
+        let options = DepositOptions::new().with_cu_limit(250_000).with_cu_price(10_000);
+        assert_eq!(options.compute_unit_limit(configured).unwrap(), ComputeUnitLimit::Fixed(250_000));
+        assert_eq!(options.cu_price_micro_lamports, Some(10_000));
+        assert!(DepositOptions::new().with_cu_limit(0).compute_unit_limit(configured).is_err());
+        assert!(DepositOptions::new()
+            .with_cu_limit(MAX_COMPUTE_UNIT_LIMIT + 1)
+            .compute_unit_limit(configured)
+            .is_err());
+    }
+}
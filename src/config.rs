@@ -0,0 +1,530 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{
+    error::{CarrotError, Result},
+    blockhash::BlockhashSource, clock::{Clock, SystemClock},
+    broadcast::Broadcaster, cache::DEFAULT_VAULT_ASSETS_TTL, circuit_breaker::CircuitBreakerPolicy,
+    compute_units::ComputeUnitLimit, display::DisplayRounding,
+    instructions::{AssetOracleAccounts, RemainingAccountsResolver}, limits::DepositLimits,
+    policy::{Policy, PolicyUsage}, priority_fee::PriorityFeePolicy, store::StateStore,
+};
+
+/// Default per-request RPC timeout (matches the Solana client default)
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time to wait for a confirmed transaction to reach finalized commitment
+pub const DEFAULT_FINALIZATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default age beyond which quotes refuse an oracle price
+pub const DEFAULT_MAX_ORACLE_AGE: Duration = Duration::from_secs(300);
+
+/// Websocket URL served alongside the HTTP endpoint `rpc_url`, following the Solana CLI:
+/// `http` becomes `ws` and `https` `wss`, and an explicit port is incremented (8899 -> 8900)
+///
+/// Providers that serve websockets on another host or path need an explicit `ws_url`.
+pub fn websocket_url(rpc_url: &str) -> Result<String> {
+    let (scheme, rest) = if let Some(rest) = rpc_url.strip_prefix("https://") {
+        ("wss://", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        ("ws://", rest)
+    } else {
+        return Err(CarrotError::InvalidConfig(format!("RPC URL must be http:// or https://: {}", rpc_url)));
+    };
+    let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    let authority = match authority.rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => {
+                let ws_port = port
+                    .checked_add(1)
+                    .ok_or_else(|| CarrotError::InvalidConfig(format!("no websocket port after {}", port)))?;
+                format!("{}:{}", host, ws_port)
+            }
+            // An IPv6 address without a port
+            Err(_) => authority.to_string(),
+        },
+        None => authority.to_string(),
+    };
+    Ok(format!("{}{}{}", scheme, authority, path))
+}
+
+/// Configuration for a `CarrotClient`
+#[derive(Clone)]
+pub struct ClientConfig {
+    /// RPC endpoint URL
+    pub rpc_url: String,
+    /// Alternate RPC endpoints used to re-check and rebroadcast a transaction
+    /// when the primary endpoint reports an unknown or expired blockhash
+    pub fallback_rpc_urls: Vec<String>,
+    /// Websocket endpoint for subscriptions (derived from `rpc_url` when unset)
+    pub ws_url: Option<String>,
+    /// Websocket endpoints tried in order when `ws_url` fails, independently of
+    /// `fallback_rpc_urls`
+    pub fallback_ws_urls: Vec<String>,
+    /// When the primary and fallback endpoints are skipped for sending after repeated
+    /// failures (on by default; never skipped when unset)
+    pub circuit_breaker: Option<CircuitBreakerPolicy>,
+    /// Where transactions are submitted instead of the RPC endpoints (relays such as Helius
+    /// Sender or Triton Cascade); confirmation is still tracked over `rpc_url`
+    pub broadcaster: Option<Arc<dyn Broadcaster>>,
+    /// Commitment level used for reads and transaction confirmation
+    pub commitment: CommitmentConfig,
+    /// Timeout applied to each RPC request
+    pub timeout: Duration,
+    /// Extra HTTP headers sent with every RPC request (auth tokens, x-api-key, ...)
+    pub headers: Vec<(String, String)>,
+    /// Derive the compute-unit price from recent prioritization fees
+    /// (no priority fee is paid when unset)
+    pub priority_fee: Option<PriorityFeePolicy>,
+    /// How the compute-unit limit is chosen (simulated units + 20% by default)
+    pub compute_unit_limit: ComputeUnitLimit,
+    /// Block deposits/withdrawals until the transaction is finalized, not just confirmed
+    pub wait_for_finalized: bool,
+    /// How long to wait for finalization when `wait_for_finalized` is set
+    pub finalization_timeout: Duration,
+    /// Wall-clock lifetime of prepared transactions, on top of their blockhash expiry
+    pub prepared_transaction_ttl: Option<Duration>,
+    /// Refuse to sign transactions invoking programs outside the allowlist (on by default)
+    pub verify_programs: bool,
+    /// Programs allowed in addition to Carrot, token, ATA, compute budget and memo
+    pub extra_allowed_programs: Vec<Pubkey>,
+    /// Owners that `withdraw_to` may send proceeds to, and `deposit_and_forward` CRT to
+    /// (any owner when unset)
+    pub withdrawal_destinations: Option<Vec<Pubkey>>,
+    /// Log every account meta of built instructions at debug level
+    pub audit_accounts: bool,
+    /// Deposit bounds checked before building a deposit (none by default)
+    pub deposit_limits: DepositLimits,
+    /// Policy applied to users without their own entry in `user_policies`
+    pub policy: Option<Policy>,
+    /// Per-user policies, overriding `policy`
+    pub user_policies: HashMap<Pubkey, Policy>,
+    /// Usage counted against the policies' daily limits and asset share caps, shared by every
+    /// client created from this config or its clones (persisted in `state_store` when set)
+    pub policy_usage: Arc<PolicyUsage>,
+    /// Keys allowed to approve operations; when set, nothing is broadcast without an approval
+    pub approvers: Option<Vec<Pubkey>>,
+    /// File recording transactions sent by `deposit_once`/`withdraw_once`
+    /// (kept in memory only when unset)
+    pub signature_ledger: Option<PathBuf>,
+    /// Store backing the signature ledger and policy usage, taking precedence over
+    /// `signature_ledger`
+    pub state_store: Option<Arc<dyn StateStore>>,
+    /// How long `get_vault_assets` serves the vault's asset list from cache (zero disables it)
+    pub vault_assets_ttl: Duration,
+    /// Rounding of amounts formatted for display (`format_usd`, `format_amount`); protocol
+    /// math always rounds in favor of the vault regardless
+    pub display_rounding: DisplayRounding,
+    /// Source of the remaining accounts of issue and redeem instructions (every asset's vault
+    /// ATA and oracle by default)
+    pub remaining_accounts: Arc<dyn RemainingAccountsResolver>,
+    /// Service key signing execution receipts (`issue_receipt` is unavailable when unset)
+    pub receipt_signer: Option<Arc<Keypair>>,
+    /// ID recorded in the `SdkMemo` of every built transaction and attached to its telemetry spans
+    pub correlation_id: Option<String>,
+    /// Integrator recorded in the `SdkMemo` of every built transaction
+    pub integrator_id: Option<String>,
+    /// Run `CarrotClient::validate` when the client is created, failing on any failed check
+    pub validate_on_startup: bool,
+    /// How old a previously decoded oracle price may be for partial valuations to fall back on
+    /// it when the oracle can't be read (no fallback when unset)
+    pub stale_price_max_age: Option<Duration>,
+    /// How old an oracle price may be for quotes and their conservative `min_output` to be
+    /// computed from it (`DEFAULT_MAX_ORACLE_AGE` by default)
+    pub max_oracle_age: Duration,
+    /// Estimated cost (bps) of swapping each exit asset into what users finally want, weighed by
+    /// `CarrotClient::best_exit_asset`
+    pub exit_swap_costs: HashMap<Pubkey, u16>,
+    /// Time used for NAV accruals, policy windows, transaction lifetimes and schedules
+    /// (the system clock by default)
+    pub clock: Arc<dyn Clock>,
+    /// Blockhash built transactions are signed with (fetched from `rpc_url` when unset)
+    pub blockhash_source: Option<Arc<dyn BlockhashSource>>,
+}
+
+impl ClientConfig {
+    /// Create a config for the given RPC URL with default settings
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        Self {
+            rpc_url: rpc_url.into(),
+            fallback_rpc_urls: Vec::new(),
+            ws_url: None,
+            fallback_ws_urls: Vec::new(),
+            circuit_breaker: Some(CircuitBreakerPolicy::default()),
+            broadcaster: None,
+            commitment: CommitmentConfig::confirmed(),
+            timeout: DEFAULT_RPC_TIMEOUT,
+            headers: Vec::new(),
+            priority_fee: None,
+            compute_unit_limit: ComputeUnitLimit::default(),
+            wait_for_finalized: false,
+            finalization_timeout: DEFAULT_FINALIZATION_TIMEOUT,
+            prepared_transaction_ttl: None,
+            verify_programs: true,
+            extra_allowed_programs: Vec::new(),
+            withdrawal_destinations: None,
+            audit_accounts: false,
+            deposit_limits: DepositLimits::default(),
+            policy: None,
+            user_policies: HashMap::new(),
+            policy_usage: Arc::new(PolicyUsage::new()),
+            approvers: None,
+            signature_ledger: None,
+            state_store: None,
+            vault_assets_ttl: DEFAULT_VAULT_ASSETS_TTL,
+            display_rounding: DisplayRounding::default(),
+            receipt_signer: None,
+            remaining_accounts: Arc::new(AssetOracleAccounts),
+            correlation_id: None,
+            integrator_id: None,
+            validate_on_startup: false,
+            stale_price_max_age: None,
+            max_oracle_age: DEFAULT_MAX_ORACLE_AGE,
+            exit_swap_costs: HashMap::new(),
+            clock: Arc::new(SystemClock),
+            blockhash_source: None,
+        }
+    }
+
+    /// Add an alternate RPC endpoint for rebroadcasting transactions
+    pub fn with_fallback_rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.fallback_rpc_urls.push(rpc_url.into());
+        self
+    }
+
+    /// Subscribe over `ws_url` instead of the websocket endpoint derived from `rpc_url`
+    pub fn with_ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = Some(ws_url.into());
+        self
+    }
+
+    /// Add an alternate websocket endpoint for subscriptions
+    pub fn with_fallback_ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.fallback_ws_urls.push(ws_url.into());
+        self
+    }
+
+    /// Websocket endpoints in failover order: `ws_url` (or the one derived from `rpc_url`),
+    /// then `fallback_ws_urls`
+    pub fn ws_endpoints(&self) -> Result<Vec<String>> {
+        let primary = match &self.ws_url {
+            Some(ws_url) => ws_url.clone(),
+            None => websocket_url(&self.rpc_url)?,
+        };
+        let endpoints: Vec<String> = std::iter::once(primary).chain(self.fallback_ws_urls.iter().cloned()).collect();
+        if let Some(invalid) = endpoints.iter().find(|url| !url.starts_with("ws://") && !url.starts_with("wss://")) {
+            return Err(CarrotError::InvalidConfig(format!("websocket URL must be ws:// or wss://: {}", invalid)));
+        }
+        Ok(endpoints)
+    }
+
+    /// Skip endpoints for sending according to `policy` after repeated failures
+    pub fn with_circuit_breaker(mut self, policy: CircuitBreakerPolicy) -> Self {
+        self.circuit_breaker = Some(policy);
+        self
+    }
+
+    /// Always try every endpoint, however often it failed before
+    pub fn without_circuit_breaker(mut self) -> Self {
+        self.circuit_breaker = None;
+        self
+    }
+
+    /// Submit transactions through `broadcaster` (fallback endpoints and the circuit breaker
+    /// don't apply to it)
+    pub fn with_broadcaster(mut self, broadcaster: Arc<dyn Broadcaster>) -> Self {
+        self.broadcaster = Some(broadcaster);
+        self
+    }
+
+    /// Set the commitment level
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    /// Set the per-request RPC timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add an HTTP header sent with every RPC request
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add a bearer token `Authorization` header
+    pub fn with_bearer_token(self, token: impl AsRef<str>) -> Self {
+        let value = format!("Bearer {}", token.as_ref());
+        self.with_header("Authorization", value)
+    }
+
+    /// Pay a priority fee derived from recent fees using the given policy
+    pub fn with_priority_fee(mut self, policy: PriorityFeePolicy) -> Self {
+        self.priority_fee = Some(policy);
+        self
+    }
+
+    /// Wait for finalized commitment (up to `timeout`) before returning from deposit/withdraw
+    pub fn with_wait_for_finalized(mut self, timeout: Duration) -> Self {
+        self.wait_for_finalized = true;
+        self.finalization_timeout = timeout;
+        self
+    }
+
+    /// Refuse to send prepared transactions older than `ttl`
+    pub fn with_prepared_transaction_ttl(mut self, ttl: Duration) -> Self {
+        self.prepared_transaction_ttl = Some(ttl);
+        self
+    }
+
+    /// Allow transactions to invoke `program_id` in addition to the default allowlist
+    pub fn with_allowed_program(mut self, program_id: Pubkey) -> Self {
+        self.extra_allowed_programs.push(program_id);
+        self
+    }
+
+    /// Disable the program allowlist check before signing
+    pub fn without_program_verification(mut self) -> Self {
+        self.verify_programs = false;
+        self
+    }
+
+    /// Restrict `withdraw_to` and `deposit_and_forward` to the given recipient owners
+    pub fn with_withdrawal_destinations(mut self, owners: Vec<Pubkey>) -> Self {
+        self.withdrawal_destinations = Some(owners);
+        self
+    }
+
+    /// Log the name, writability and signer flag of every account in built instructions
+    /// (debug level, `carrot_sdk_v3::instructions` target)
+    pub fn with_account_audit(mut self) -> Self {
+        self.audit_accounts = true;
+        self
+    }
+
+    /// Reject deposits worth less than `value` (USD, `VALUE_DECIMALS`)
+    pub fn with_min_deposit_value(mut self, value: u128) -> Self {
+        self.deposit_limits.min_deposit_value = Some(value);
+        self
+    }
+
+    /// Reject deposits that would bring the vault's value above `value` (USD, `VALUE_DECIMALS`)
+    pub fn with_max_total_value(mut self, value: u128) -> Self {
+        self.deposit_limits.max_total_value = Some(value);
+        self
+    }
+
+    /// Enforce `policy` on deposits and withdrawals of every user without a per-user policy
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Enforce `policy` on deposits and withdrawals signed by `user`
+    pub fn with_user_policy(mut self, user: Pubkey, policy: Policy) -> Self {
+        self.user_policies.insert(user, policy);
+        self
+    }
+
+    /// Policy that applies to `user`, if any
+    pub fn policy_for(&self, user: &Pubkey) -> Option<&Policy> {
+        self.user_policies.get(user).or(self.policy.as_ref())
+    }
+
+    /// Count policy usage in `usage`, e.g. one shared with clients of other configs
+    pub fn with_policy_usage(mut self, usage: Arc<PolicyUsage>) -> Self {
+        self.policy_usage = usage;
+        self
+    }
+
+    /// Fail with `InvalidConfig` if any policy can't be enforced (see `Policy::validate`)
+    pub(crate) fn validate_policies(&self) -> Result<()> {
+        self.policy.iter().chain(self.user_policies.values()).try_for_each(Policy::validate)
+    }
+
+    /// Require a second person's approval (`CarrotClient::send_approved`) before broadcasting
+    pub fn with_approvers(mut self, approvers: Vec<Pubkey>) -> Self {
+        self.approvers = Some(approvers);
+        self
+    }
+
+    /// Persist the intents and signatures of `deposit_once`/`withdraw_once` to `path`,
+    /// so they survive a restart
+    pub fn with_signature_ledger(mut self, path: impl Into<PathBuf>) -> Self {
+        self.signature_ledger = Some(path.into());
+        self
+    }
+
+    /// Keep the `deposit_once`/`withdraw_once` ledger and the policy usage in `store` (e.g. SQLite
+    /// shared with the strategy indexer) instead of a CSV file and memory
+    pub fn with_state_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.state_store = Some(store);
+        self
+    }
+
+    /// Cache the vault's asset list for `ttl` (zero always reads it fresh)
+    pub fn with_vault_assets_ttl(mut self, ttl: Duration) -> Self {
+        self.vault_assets_ttl = ttl;
+        self
+    }
+
+    /// Round amounts formatted for display with `rounding` (floor by default)
+    pub fn with_display_rounding(mut self, rounding: DisplayRounding) -> Self {
+        self.display_rounding = rounding;
+        self
+    }
+
+    /// Sign execution receipts with `signer`, the key integrators publish to their customers
+    pub fn with_receipt_signer(mut self, signer: Arc<Keypair>) -> Self {
+        self.receipt_signer = Some(signer);
+        self
+    }
+
+    /// Resolve the remaining accounts of issue and redeem instructions with `resolver`
+    pub fn with_remaining_accounts_resolver(mut self, resolver: Arc<dyn RemainingAccountsResolver>) -> Self {
+        self.remaining_accounts = resolver;
+        self
+    }
+
+    /// Tag every built transaction with `correlation_id` in its `SdkMemo`, and its spans with
+    /// the matching trace ID (see `telemetry::correlation_trace_id`)
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Identify every built transaction as coming from `integrator_id` in its `SdkMemo`
+    pub fn with_integrator_id(mut self, integrator_id: impl Into<String>) -> Self {
+        self.integrator_id = Some(integrator_id.into());
+        self
+    }
+
+    /// Fail `CarrotClient::with_config` unless `CarrotClient::validate` passes every check
+    pub fn with_startup_validation(mut self) -> Self {
+        self.validate_on_startup = true;
+        self
+    }
+
+    /// Let `fetch_nav_partial` value an asset whose oracle can't be read at the last price
+    /// this client decoded from it, if published at most `max_age` ago
+    pub fn with_stale_price_fallback(mut self, max_age: Duration) -> Self {
+        self.stale_price_max_age = Some(max_age);
+        self
+    }
+
+    /// Refuse to quote from an oracle price published more than `max_age` ago, e.g. longer
+    /// than the default for feeds with a slow heartbeat
+    pub fn with_max_oracle_age(mut self, max_age: Duration) -> Self {
+        self.max_oracle_age = max_age;
+        self
+    }
+
+    /// Count `cost_bps` of every payout in `asset_mint` as lost to a downstream swap when
+    /// `CarrotClient::best_exit_asset` ranks exit assets
+    pub fn with_exit_swap_cost(mut self, asset_mint: Pubkey, cost_bps: u16) -> Self {
+        self.exit_swap_costs.insert(asset_mint, cost_bps);
+        self
+    }
+
+    /// Override how the compute-unit limit is chosen
+    pub fn with_compute_unit_limit(mut self, limit: ComputeUnitLimit) -> Self {
+        self.compute_unit_limit = limit;
+        self
+    }
+
+    /// Read the time from `clock`, e.g. a `FixedClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sign transactions with blockhashes from `source` instead of fetching them
+    pub fn with_blockhash_source(mut self, source: Arc<dyn BlockhashSource>) -> Self {
+        self.blockhash_source = Some(source);
+        self
+    }
+}
+
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Header values usually carry credentials, only print the names
+        let header_names: Vec<&str> = self.headers.iter().map(|(name, _)| name.as_str()).collect();
+        f.debug_struct("ClientConfig")
+            .field("rpc_url", &self.rpc_url)
+            .field("fallback_rpc_urls", &self.fallback_rpc_urls)
+            .field("ws_url", &self.ws_url)
+            .field("fallback_ws_urls", &self.fallback_ws_urls)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .field("broadcaster", &self.broadcaster)
+            .field("commitment", &self.commitment)
+            .field("timeout", &self.timeout)
+            .field("headers", &header_names)
+            .field("priority_fee", &self.priority_fee)
+            .field("compute_unit_limit", &self.compute_unit_limit)
+            .field("wait_for_finalized", &self.wait_for_finalized)
+            .field("finalization_timeout", &self.finalization_timeout)
+            .field("prepared_transaction_ttl", &self.prepared_transaction_ttl)
+            .field("verify_programs", &self.verify_programs)
+            .field("extra_allowed_programs", &self.extra_allowed_programs)
+            .field("withdrawal_destinations", &self.withdrawal_destinations)
+            .field("audit_accounts", &self.audit_accounts)
+            .field("deposit_limits", &self.deposit_limits)
+            .field("policy", &self.policy)
+            .field("user_policies", &self.user_policies)
+            .field("policy_usage", &self.policy_usage)
+            .field("approvers", &self.approvers)
+            .field("signature_ledger", &self.signature_ledger)
+            .field("state_store", &self.state_store)
+            .field("vault_assets_ttl", &self.vault_assets_ttl)
+            .field("display_rounding", &self.display_rounding)
+            // Only the public half of the service key
+            .field("receipt_signer", &self.receipt_signer.as_ref().map(|signer| signer.pubkey()))
+            .field("remaining_accounts", &self.remaining_accounts)
+            .field("correlation_id", &self.correlation_id)
+            .field("integrator_id", &self.integrator_id)
+            .field("validate_on_startup", &self.validate_on_startup)
+            .field("stale_price_max_age", &self.stale_price_max_age)
+            .field("max_oracle_age", &self.max_oracle_age)
+            .field("exit_swap_costs", &self.exit_swap_costs)
+            .field("clock", &self.clock)
+            .field("blockhash_source", &self.blockhash_source)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_endpoints() {
+        assert_eq!(websocket_url("https://api.mainnet-beta.solana.com").unwrap(), "wss://api.mainnet-beta.solana.com");
+        assert_eq!(websocket_url("http://127.0.0.1:8899").unwrap(), "ws://127.0.0.1:8900");
+        assert_eq!(
+            websocket_url("https://rpc.example.com/v1?api-key=k").unwrap(),
+            "wss://rpc.example.com/v1?api-key=k"
+        );
+        assert_eq!(websocket_url("http://[::1]:8899/").unwrap(), "ws://[::1]:8900/");
+        assert!(websocket_url("rpc.example.com").is_err());
+
+        let config = ClientConfig::new("https://mainnet.helius-rpc.com/?api-key=k")
+            .with_ws_url("wss://atlas-mainnet.helius-rpc.com/?api-key=k")
+            .with_fallback_ws_url("wss://api.mainnet-beta.solana.com");
+        assert_eq!(
+            config.ws_endpoints().unwrap(),
+            ["wss://atlas-mainnet.helius-rpc.com/?api-key=k", "wss://api.mainnet-beta.solana.com"]
+        );
+        let derived = ClientConfig::new("https://api.devnet.solana.com").ws_endpoints().unwrap();
+        assert_eq!(derived, ["wss://api.devnet.solana.com"]);
+        assert!(config.with_fallback_ws_url("https://not-a-socket.example.com").ws_endpoints().is_err());
+    }
+}
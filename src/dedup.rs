@@ -0,0 +1,122 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use solana_sdk::signature::Signature;
+
+use crate::history::HistoryEntry;
+
+/// Default number of keys a `Deduplicator` remembers
+pub const DEFAULT_DEDUP_CAPACITY: usize = 100_000;
+
+/// Stable unique key of a decoded event: its transaction and top-level instruction index
+///
+/// The same event gets the same key whichever stream delivered it (webhook, subscription,
+/// backfill), so it can be used for idempotent writes downstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventKey {
+    pub signature: Signature,
+    pub instruction_index: usize,
+}
+
+impl fmt::Display for EventKey {
+    /// `<signature>:<instruction index>`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.signature, self.instruction_index)
+    }
+}
+
+/// Drops events already seen, remembering the most recent `capacity` keys
+///
+/// Combine streams through one deduplicator to process each event at most once. Keys older
+/// than the capacity are forgotten, so size it to cover the overlap between streams.
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    capacity: usize,
+    seen: HashSet<EventKey>,
+    order: VecDeque<EventKey>,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_CAPACITY)
+    }
+}
+
+impl Deduplicator {
+    /// Deduplicator remembering up to `capacity` keys
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Record `key`; returns `true` the first time it is seen
+    pub fn insert(&mut self, key: EventKey) -> bool {
+        if !self.seen.insert(key) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Whether `entry` wasn't seen before (recording it)
+    pub fn first_seen(&mut self, entry: &HistoryEntry) -> bool {
+        self.insert(entry.key())
+    }
+
+    /// Keep only the entries not seen before, in order
+    pub fn filter(&mut self, entries: impl IntoIterator<Item = HistoryEntry>) -> Vec<HistoryEntry> {
+        entries.into_iter().filter(|entry| self.first_seen(entry)).collect()
+    }
+
+    /// Number of keys currently remembered
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(signature: Signature, instruction_index: usize) -> EventKey {
+        EventKey {
+            signature,
+            instruction_index,
+        }
+    }
+
+    #[test]
+    fn test_duplicates_are_dropped() {
+        let signature = Signature::new_unique();
+        let mut dedup = Deduplicator::default();
+        assert!(dedup.insert(key(signature, 0)));
+        assert!(dedup.insert(key(signature, 1)));
+        assert!(!dedup.insert(key(signature, 0)));
+        assert_eq!(dedup.len(), 2);
+        assert_eq!(key(signature, 1).to_string(), format!("{}:1", signature));
+    }
+
+    #[test]
+    fn test_oldest_keys_are_forgotten() {
+        let mut dedup = Deduplicator::new(2);
+        let keys: Vec<EventKey> = (0..3).map(|i| key(Signature::new_unique(), i)).collect();
+        for k in &keys {
+            assert!(dedup.insert(*k));
+        }
+        assert_eq!(dedup.len(), 2);
+        assert!(dedup.insert(keys[0]));
+        assert!(!dedup.insert(keys[2]));
+    }
+}
@@ -0,0 +1,291 @@
+//! Periodic position summaries for depositors
+//!
+//! A digest values a wallet's CRT at the current share price and compares that price with the
+//! one at the start of the period, taken from the samples `spawn_share_price_sampler` keeps in
+//! the state store. Yield is attributed to the wallet's current balance, so deposits or
+//! withdrawals within the period aren't accounted for separately.
+
+use std::fmt;
+use std::time::Duration;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    alerts::{Alert, AlertKind, Severity},
+    display::DisplayRounding,
+    error::{CarrotError, Result},
+    nav::{CRT_DECIMALS, VALUE_DECIMALS},
+    twap::{SharePriceHistory, SharePriceSample},
+};
+#[cfg(feature = "blocking")]
+use crate::{alerts::AlertSink, client::CarrotClient, supervisor::TaskGroup};
+
+/// Seconds from the Unix epoch (a Thursday) to the first Monday, 1970-01-05
+const FIRST_MONDAY: i64 = 4 * 86_400;
+
+/// How often digests are sent and the period each one covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestPeriod {
+    /// Every day at 00:00 UTC
+    Daily,
+    /// Every Monday at 00:00 UTC
+    Weekly,
+}
+
+impl DigestPeriod {
+    pub fn duration(self) -> Duration {
+        match self {
+            DigestPeriod::Daily => Duration::from_secs(86_400),
+            DigestPeriod::Weekly => Duration::from_secs(7 * 86_400),
+        }
+    }
+
+    /// First period boundary strictly after `now` (Unix timestamp)
+    pub fn next_due(self, now: i64) -> i64 {
+        let length = self.duration().as_secs() as i64;
+        let origin = match self {
+            DigestPeriod::Daily => 0,
+            DigestPeriod::Weekly => FIRST_MONDAY,
+        };
+        origin + ((now - origin).div_euclid(length) + 1) * length
+    }
+}
+
+impl fmt::Display for DigestPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DigestPeriod::Daily => "daily",
+            DigestPeriod::Weekly => "weekly",
+        })
+    }
+}
+
+/// Summary of a wallet's Carrot position over one period; values are USD in `VALUE_DECIMALS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionDigest {
+    pub wallet: Pubkey,
+    pub period: DigestPeriod,
+    /// Slot and Unix timestamp the position was valued at
+    pub slot: u64,
+    pub timestamp: i64,
+    pub crt_balance: u64,
+    pub value: u128,
+    /// Value of one whole CRT now
+    pub share_price: u128,
+    /// Value of one whole CRT at the start of the period (`None` if the history doesn't reach back)
+    pub start_share_price: Option<u128>,
+}
+
+impl PositionDigest {
+    /// Digest of `crt_balance` held by `wallet`, valued at `current` and compared with the last
+    /// sample in `history` at or before the start of the period
+    pub fn new(
+        wallet: Pubkey,
+        period: DigestPeriod,
+        crt_balance: u64,
+        current: &SharePriceSample,
+        history: &SharePriceHistory,
+    ) -> Result<Self> {
+        let start = current.timestamp - period.duration().as_secs() as i64;
+        let start_share_price = history
+            .samples()
+            .take_while(|sample| sample.timestamp <= start)
+            .last()
+            .map(|sample| sample.price);
+        Ok(Self {
+            wallet,
+            period,
+            slot: current.slot,
+            timestamp: current.timestamp,
+            crt_balance,
+            value: value_of(crt_balance, current.price)?,
+            share_price: current.price,
+            start_share_price,
+        })
+    }
+
+    /// Change of the share price over the period, in bps
+    pub fn share_price_change_bps(&self) -> Option<i64> {
+        let start = self.start_share_price.filter(|price| *price > 0)?;
+        let change = (self.share_price as i128 - start as i128) * 10_000 / start as i128;
+        i64::try_from(change).ok()
+    }
+
+    /// Value gained (or lost, if negative) by the current balance over the period
+    pub fn yield_accrued(&self) -> Option<i128> {
+        let start = value_of(self.crt_balance, self.start_share_price?).ok()?;
+        Some(self.value as i128 - start as i128)
+    }
+
+    /// The digest as an `Info` alert for `AlertSink`s
+    pub fn to_alert(&self) -> Alert {
+        Alert {
+            severity: Severity::Info,
+            slot: self.slot,
+            kind: AlertKind::PositionDigest(self.clone()),
+        }
+    }
+}
+
+impl fmt::Display for PositionDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} digest for {}: {} CRT worth ${}",
+            self.period,
+            self.wallet,
+            DisplayRounding::Floor.format(self.crt_balance as u128, CRT_DECIMALS as u32, 4),
+            usd(self.value as i128)
+        )?;
+        match (self.yield_accrued(), self.share_price_change_bps()) {
+            (Some(accrued), Some(change_bps)) => write!(
+                f,
+                ", {}${} yield, CRT price {:+.2}% to ${}",
+                if accrued < 0 { "-" } else { "+" },
+                usd(accrued.abs()),
+                change_bps as f64 / 100.0,
+                usd(self.share_price as i128)
+            ),
+            _ => write!(f, ", CRT price ${} (no history for the period)", usd(self.share_price as i128)),
+        }
+    }
+}
+
+/// USD value of `shares` CRT at `price` per whole CRT
+fn value_of(shares: u64, price: u128) -> Result<u128> {
+    (shares as u128)
+        .checked_mul(price)
+        .map(|value| value / 10u128.pow(CRT_DECIMALS as u32))
+        .ok_or(CarrotError::MathOverflow)
+}
+
+/// USD value (`VALUE_DECIMALS`) with cents
+fn usd(value: i128) -> String {
+    format!("{:.2}", value as f64 / 10f64.powi(VALUE_DECIMALS as i32))
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Digest of `wallet`'s position over the `period` ending now
+    ///
+    /// Compares with the samples `spawn_share_price_sampler` keeps in the configured state store
+    /// (`ClientConfig::with_state_store`); sample at least once per period for the yield to show.
+    pub fn position_digest(&self, wallet: &Pubkey, period: DigestPeriod) -> Result<PositionDigest> {
+        let history = self.stored_share_prices()?;
+        PositionDigest::new(*wallet, period, self.get_crt_balance(wallet)?, &self.sample_share_price()?, &history)
+    }
+
+    /// Send a digest of each of `wallets` to `sinks` at every period boundary (00:00 UTC, on
+    /// Mondays for weekly digests), from a supervised task
+    ///
+    /// A digest that can't be built or delivered is logged and skipped until the next boundary.
+    pub fn spawn_position_digests(
+        &self,
+        group: &mut TaskGroup,
+        wallets: Vec<Pubkey>,
+        period: DigestPeriod,
+        mut sinks: Vec<Box<dyn AlertSink>>,
+    ) -> Result<()> {
+        self.stored_share_prices()?;
+        let client = self.clone();
+        group.spawn("position-digests", move |shutdown| loop {
+            let now = client.now();
+            let wait = Duration::from_secs((period.next_due(now) - now) as u64);
+            if shutdown.wait_timeout(wait) {
+                return Ok(());
+            }
+            let prices = client
+                .stored_share_prices()
+                .and_then(|history| Ok((client.sample_share_price()?, history)));
+            let (current, history) = match prices {
+                Ok(prices) => prices,
+                Err(err) => {
+                    log::warn!("skipping {} digests: {}", period, err);
+                    continue;
+                }
+            };
+            for wallet in &wallets {
+                let digest = client
+                    .get_crt_balance(wallet)
+                    .and_then(|balance| PositionDigest::new(*wallet, period, balance, &current, &history));
+                let alert = match digest {
+                    Ok(digest) => digest.to_alert(),
+                    Err(err) => {
+                        log::warn!("skipping {} digest for {}: {}", period, wallet, err);
+                        continue;
+                    }
+                };
+                for sink in sinks.iter_mut() {
+                    if let Err(err) = sink.send(&alert) {
+                        log::warn!("failed to deliver digest ({}): {}", alert, err);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn stored_share_prices(&self) -> Result<SharePriceHistory> {
+        let store = self.config().state_store.as_ref().ok_or_else(|| {
+            CarrotError::InvalidConfig("position digests need a state store with share price samples".to_string())
+        })?;
+        SharePriceHistory::load_from(store.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRT: u64 = 1_000_000_000;
+    const DOLLAR: u128 = 1_000_000_000_000;
+
+    fn sample(timestamp: i64, price: u128) -> SharePriceSample {
+        SharePriceSample {
+            timestamp,
+            slot: timestamp as u64,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_next_due_aligns_to_utc_boundaries() {
+        // Thursday 2026-10-15 13:00 UTC
+        let now = 1_792_069_200;
+        assert_eq!(DigestPeriod::Daily.next_due(now), 1_792_108_800);
+        // Monday 2026-10-19 00:00 UTC
+        assert_eq!(DigestPeriod::Weekly.next_due(now), 1_792_368_000);
+        // A boundary itself schedules the next one
+        assert_eq!(DigestPeriod::Daily.next_due(1_792_108_800), 1_792_195_200);
+    }
+
+    #[test]
+    fn test_digest_yield_and_price_change() {
+        let wallet = Pubkey::new_unique();
+        let day = 86_400;
+        let mut history = SharePriceHistory::new();
+        history.record(sample(1_000, DOLLAR));
+        history.record(sample(1_000 + day / 2, DOLLAR + DOLLAR / 200));
+        let current = sample(1_000 + day, DOLLAR + DOLLAR / 100);
+
+        let digest = PositionDigest::new(wallet, DigestPeriod::Daily, 2_000 * CRT, &current, &history).unwrap();
+        assert_eq!(digest.value, 2_020 * DOLLAR);
+        assert_eq!(digest.start_share_price, Some(DOLLAR));
+        assert_eq!(digest.share_price_change_bps(), Some(100));
+        assert_eq!(digest.yield_accrued(), Some(20 * DOLLAR as i128));
+        assert_eq!(
+            digest.to_alert().to_string(),
+            format!(
+                "[info] slot {}: daily digest for {}: 2000.0000 CRT worth $2020.00, +$20.00 yield, \
+                 CRT price +1.00% to $1.01",
+                current.slot, wallet
+            )
+        );
+
+        // History starting inside the period: no baseline to compare with
+        let weekly = PositionDigest::new(wallet, DigestPeriod::Weekly, CRT, &current, &history).unwrap();
+        assert_eq!(weekly.start_share_price, None);
+        assert_eq!(weekly.yield_accrued(), None);
+        assert!(weekly.to_string().ends_with("CRT price $1.01 (no history for the period)"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Anchor discriminators computed from names at compile time
+//!
+//! The hard-coded discriminator arrays used across the SDK are checked against these
+//! at compile time, so a typo in a magic byte array fails the build.
+
+/// Anchor instruction discriminator: sha256("global:<name>")[..8]
+pub const fn instruction_discriminator(name: &str) -> [u8; 8] {
+    first_eight(sha256_concat(b"global:", name.as_bytes()))
+}
+
+/// Anchor account discriminator: sha256("account:<name>")[..8]
+pub const fn account_discriminator(name: &str) -> [u8; 8] {
+    first_eight(sha256_concat(b"account:", name.as_bytes()))
+}
+
+/// Discriminator of the `issue` instruction
+pub const ISSUE: [u8; 8] = instruction_discriminator("issue");
+
+/// Discriminator of the `redeem` instruction
+pub const REDEEM: [u8; 8] = instruction_discriminator("redeem");
+
+/// Discriminator of `Vault` accounts
+pub const VAULT: [u8; 8] = account_discriminator("Vault");
+
+/// Discriminator of Pyth `PriceUpdateV2` accounts
+pub const PRICE_UPDATE_V2: [u8; 8] = account_discriminator("PriceUpdateV2");
+
+// Self-check of the hard-coded arrays
+const _: () = {
+    assert!(eq(&ISSUE, &crate::instructions::ISSUE_DISCRIMINATOR));
+    assert!(eq(&REDEEM, &crate::instructions::REDEEM_DISCRIMINATOR));
+    assert!(eq(&VAULT, &crate::VAULT_DISCRIMINATOR));
+    assert!(eq(&PRICE_UPDATE_V2, &crate::oracle::PRICE_UPDATE_V2_DISCRIMINATOR));
+};
+
+const fn eq(a: &[u8; 8], b: &[u8; 8]) -> bool {
+    let mut i = 0;
+    while i < 8 {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn first_eight(hash: [u8; 32]) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    let mut i = 0;
+    while i < 8 {
+        out[i] = hash[i];
+        i += 1;
+    }
+    out
+}
+
+/// SHA-256 round constants
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 initial hash values
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Byte `i` of the padded message `prefix || name || 0x80 || 0.. || bit length`
+const fn padded_byte(prefix: &[u8], name: &[u8], padded_len: usize, i: usize) -> u8 {
+    let len = prefix.len() + name.len();
+    if i < prefix.len() {
+        prefix[i]
+    } else if i < len {
+        name[i - prefix.len()]
+    } else if i == len {
+        0x80
+    } else if i >= padded_len - 8 {
+        let bit_len = (len as u64) * 8;
+        (bit_len >> (8 * (padded_len - 1 - i))) as u8
+    } else {
+        0
+    }
+}
+
+/// SHA-256 of `prefix || name`
+pub(crate) const fn sha256_concat(prefix: &[u8], name: &[u8]) -> [u8; 32] {
+    let len = prefix.len() + name.len();
+    let padded_len = (len + 9).div_ceil(64) * 64;
+    let mut h = H0;
+
+    let mut block = 0;
+    while block < padded_len {
+        let mut w = [0u32; 64];
+        let mut t = 0;
+        while t < 16 {
+            let base = block + t * 4;
+            w[t] = u32::from_be_bytes([
+                padded_byte(prefix, name, padded_len, base),
+                padded_byte(prefix, name, padded_len, base + 1),
+                padded_byte(prefix, name, padded_len, base + 2),
+                padded_byte(prefix, name, padded_len, base + 3),
+            ]);
+            t += 1;
+        }
+        while t < 64 {
+            let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+            let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+            w[t] = w[t - 16].wrapping_add(s0).wrapping_add(w[t - 7]).wrapping_add(s1);
+            t += 1;
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        t = 0;
+        while t < 64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[t]).wrapping_add(w[t]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+            t += 1;
+        }
+
+        let state = [a, b, c, d, e, f, g, hh];
+        let mut i = 0;
+        while i < 8 {
+            h[i] = h[i].wrapping_add(state[i]);
+            i += 1;
+        }
+        block += 64;
+    }
+
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 8 {
+        let bytes = h[i].to_be_bytes();
+        out[i * 4] = bytes[0];
+        out[i * 4 + 1] = bytes[1];
+        out[i * 4 + 2] = bytes[2];
+        out[i * 4 + 3] = bytes[3];
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_known_vectors() {
+        // FIPS 180-2 test vectors
+        assert_eq!(
+            sha256_concat(b"ab", b"c")[..8],
+            [0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea]
+        );
+        assert_eq!(
+            sha256_concat(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq", b"")[..8],
+            [0x24, 0x8d, 0x6a, 0x61, 0xd2, 0x06, 0x38, 0xb8]
+        );
+    }
+
+    #[test]
+    fn test_runtime_names() {
+        let name = String::from("issue");
+        assert_eq!(instruction_discriminator(&name), ISSUE);
+        assert_ne!(account_discriminator("issue"), ISSUE);
+    }
+}
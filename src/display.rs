@@ -0,0 +1,116 @@
+//! Rounding for presenting amounts, separate from the protocol's own rounding
+//!
+//! Conversions that must match the program (`Nav`, quotes, fees) always use `nav::Rounding`,
+//! in favor of the vault. `DisplayRounding` only shortens already-computed amounts to fewer
+//! decimals for UIs and reports, so a choice made here can never change what is signed.
+
+use std::fmt;
+
+#[cfg(feature = "blocking")]
+use solana_sdk::pubkey::Pubkey;
+
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, error::Result, nav::VALUE_DECIMALS};
+
+/// How amounts are rounded when shown with fewer decimals than they carry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DisplayRounding {
+    /// Toward zero: never overstates a balance or output
+    #[default]
+    Floor,
+    /// Away from zero: never understates a cost
+    Ceil,
+    /// To the nearest value, ties to even (banker's rounding), unbiased across many lines
+    HalfEven,
+}
+
+impl fmt::Display for DisplayRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DisplayRounding::Floor => "floor",
+            DisplayRounding::Ceil => "ceil",
+            DisplayRounding::HalfEven => "half-even",
+        })
+    }
+}
+
+impl DisplayRounding {
+    /// `value` with `decimals` decimals, rounded to `places` decimals (still in integer form)
+    pub fn rescale(self, value: u128, decimals: u32, places: u32) -> u128 {
+        if places >= decimals {
+            return value.saturating_mul(10u128.saturating_pow(places - decimals));
+        }
+        let divisor = 10u128.saturating_pow(decimals - places);
+        let quotient = value / divisor;
+        let remainder = value % divisor;
+        let round_up = match self {
+            DisplayRounding::Floor => false,
+            DisplayRounding::Ceil => remainder != 0,
+            DisplayRounding::HalfEven => {
+                let half = divisor / 2;
+                remainder > half || (remainder == half && quotient % 2 == 1)
+            }
+        };
+        if round_up {
+            quotient.saturating_add(1)
+        } else {
+            quotient
+        }
+    }
+
+    /// `value` with `decimals` decimals as a decimal string with `places` decimals,
+    /// e.g. `1.50` for `1_499_999` at 6 decimals, 2 places, rounded half-even
+    pub fn format(self, value: u128, decimals: u32, places: u32) -> String {
+        let rounded = self.rescale(value, decimals, places);
+        if places == 0 {
+            return rounded.to_string();
+        }
+        let (whole, fraction) = match 10u128.checked_pow(places) {
+            Some(scale) => (rounded / scale, rounded % scale),
+            None => (0, rounded),
+        };
+        format!("{}.{:0width$}", whole, fraction, width = places as usize)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// USD value (`VALUE_DECIMALS`) as a string with `places` decimals, rounded per
+    /// `ClientConfig::display_rounding`
+    pub fn format_usd(&self, value: u128, places: u32) -> String {
+        self.config().display_rounding.format(value, VALUE_DECIMALS, places)
+    }
+
+    /// `amount` base units of `mint` with `places` decimals and its symbol (e.g. `1.50 USDC`),
+    /// rounded per `ClientConfig::display_rounding`
+    pub fn format_amount(&self, mint: &Pubkey, amount: u64, places: u32) -> Result<String> {
+        let metadata = self.resolve_mint_metadata(mint)?;
+        let rounded = self
+            .config()
+            .display_rounding
+            .format(amount as u128, metadata.decimals as u32, places);
+        Ok(format!("{} {}", rounded, metadata.symbol))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_modes() {
+        // 1.234500 and 1.235500 at 6 decimals, shown with 3
+        assert_eq!(DisplayRounding::Floor.format(1_234_500, 6, 3), "1.234");
+        assert_eq!(DisplayRounding::Ceil.format(1_234_500, 6, 3), "1.235");
+        assert_eq!(DisplayRounding::HalfEven.format(1_234_500, 6, 3), "1.234");
+        assert_eq!(DisplayRounding::HalfEven.format(1_235_500, 6, 3), "1.236");
+        assert_eq!(DisplayRounding::HalfEven.format(1_234_501, 6, 3), "1.235");
+        // Exact values are unaffected
+        assert_eq!(DisplayRounding::Ceil.format(1_000_000, 6, 2), "1.00");
+        assert_eq!(DisplayRounding::Floor.format(999, 6, 0), "0");
+        assert_eq!(DisplayRounding::Floor.format(7, 40, 40), format!("0.{}7", "0".repeat(39)));
+        assert_eq!(DisplayRounding::Ceil.format(999, 6, 0), "1");
+        // More places than decimals pads
+        assert_eq!(DisplayRounding::Floor.format(15, 1, 3), "1.500");
+    }
+}
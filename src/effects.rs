@@ -0,0 +1,103 @@
+use std::ops::RangeInclusive;
+
+use crate::error::{CarrotError, Result};
+
+/// Token balance changes of the user's CRT and asset accounts caused by a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceEffects {
+    /// Change of the user's CRT balance (positive when shares are minted)
+    pub crt_delta: i128,
+    /// Change of the user's asset balance (negative when depositing)
+    pub asset_delta: i128,
+}
+
+/// Acceptable balance changes for a transaction, checked against its simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedEffects {
+    /// Accepted range of the CRT balance change
+    pub crt_delta: RangeInclusive<i128>,
+    /// Accepted range of the asset balance change
+    pub asset_delta: RangeInclusive<i128>,
+}
+
+impl ExpectedEffects {
+    /// Expect a deposit spending exactly `amount` of the asset and minting at least `min_shares`
+    pub fn deposit(amount: u64, min_shares: u64) -> Self {
+        Self {
+            crt_delta: min_shares as i128..=i128::MAX,
+            asset_delta: -(amount as i128)..=-(amount as i128),
+        }
+    }
+
+    /// Expect a withdrawal burning exactly `shares` and paying out at least `min_amount_out`
+    pub fn withdraw(shares: u64, min_amount_out: u64) -> Self {
+        Self {
+            crt_delta: -(shares as i128)..=-(shares as i128),
+            asset_delta: min_amount_out as i128..=i128::MAX,
+        }
+    }
+
+    /// Fail with `EffectsMismatch` if `effects` fall outside the expected ranges
+    pub fn check(&self, effects: &BalanceEffects) -> Result<()> {
+        if !self.crt_delta.contains(&effects.crt_delta) {
+            return Err(CarrotError::EffectsMismatch(format!(
+                "CRT balance change {} outside {:?}",
+                effects.crt_delta, self.crt_delta
+            )));
+        }
+        if !self.asset_delta.contains(&effects.asset_delta) {
+            return Err(CarrotError::EffectsMismatch(format!(
+                "asset balance change {} outside {:?}",
+                effects.asset_delta, self.asset_delta
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_deposit_effects() {
+        let expected = ExpectedEffects::deposit(1_000_000, 900_000_000);
+        let ok = BalanceEffects {
+            crt_delta: 950_000_000,
+            asset_delta: -1_000_000,
+        };
+        assert!(expected.check(&ok).is_ok());
+
+        let too_few_shares = BalanceEffects {
+            crt_delta: 100,
+            ..ok
+        };
+        assert!(matches!(
+            expected.check(&too_few_shares),
+            Err(CarrotError::EffectsMismatch(_))
+        ));
+
+        let overspent = BalanceEffects {
+            asset_delta: -2_000_000,
+            ..ok
+        };
+        assert!(expected.check(&overspent).is_err());
+    }
+
+    #[test]
+    fn test_check_withdraw_effects() {
+        let expected = ExpectedEffects::withdraw(500_000_000, 590_000);
+        assert!(expected
+            .check(&BalanceEffects {
+                crt_delta: -500_000_000,
+                asset_delta: 600_000,
+            })
+            .is_ok());
+        assert!(expected
+            .check(&BalanceEffects {
+                crt_delta: -500_000_000,
+                asset_delta: 500_000,
+            })
+            .is_err());
+    }
+}
@@ -1,3 +1,7 @@
+use std::fmt;
+use std::path::Path;
+
+use solana_sdk::pubkey::Pubkey;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,14 +13,20 @@ pub enum CarrotError {
     SolanaSdk(#[from] solana_sdk::program_error::ProgramError),
     
     #[error("Token error: {0}")]
-    Token(#[from] spl_token::error::TokenError),
+    Token(#[from] spl_token_interface::error::TokenError),
     
     #[error("Borsh deserialization error: {0}")]
-    BorshDeserialize(#[from] std::io::Error),
+    BorshDeserialize(std::io::Error),
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
     
     #[error("Invalid vault data")]
     InvalidVaultData,
     
+    #[error("Account discriminator mismatch: expected {expected:?}, found {found:?}")]
+    InvalidAccountDiscriminator { expected: [u8; 8], found: [u8; 8] },
+    
     #[error("Invalid asset: {0}")]
     InvalidAsset(String),
     
@@ -28,6 +38,310 @@ pub enum CarrotError {
     
     #[error("Account not found: {0}")]
     AccountNotFound(String),
+    
+    #[error("Invalid client configuration: {0}")]
+    InvalidConfig(String),
+    
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(String),
+    
+    #[error("Timed out waiting for confirmation: {0}")]
+    ConfirmationTimeout(String),
+    
+    #[error("Transaction dropped before finalization (fork or expiry): {0}")]
+    TransactionDropped(String),
+    
+    #[error("Inconsistent snapshot: {0}")]
+    InconsistentSnapshot(String),
+    
+    #[error("Transaction expired: {0}")]
+    TransactionExpired(String),
+    
+    #[error("Program not on the allowlist: {0}")]
+    ProgramNotAllowed(solana_sdk::pubkey::Pubkey),
+    
+    #[error("Withdrawal destination not on the allowlist: {0}")]
+    DestinationNotAllowed(solana_sdk::pubkey::Pubkey),
+    
+    #[error("Simulated balance changes don't match expectations: {0}")]
+    EffectsMismatch(String),
+    
+    #[error("Accounts marked writable but not written in simulation: {0}")]
+    OverbroadWritable(String),
+    
+    #[error("Transaction of {size} bytes doesn't fit in a packet, even with the given lookup tables")]
+    TransactionTooLarge { size: usize },
+    
+    #[error("Quote expired: valid until slot {valid_until_slot}, current slot {slot}")]
+    QuoteExpired { valid_until_slot: u64, slot: u64 },
+    
+    #[error("Insufficient vault liquidity for {asset}: redemption pays {required}, vault holds {available}; at most {max_shares} CRT can be redeemed in this asset")]
+    InsufficientLiquidity { asset: solana_sdk::pubkey::Pubkey, required: u64, available: u64, max_shares: u64 },
+    
+    #[error("Idle vault liquidity covers at most {max_shares} of the {shares} CRT being redeemed")]
+    RedemptionExceedsLiquidity { shares: u64, max_shares: u64 },
+    
+    #[error("Operation requires approval; use request_deposit/request_withdraw and send_approved")]
+    ApprovalRequired,
+    
+    #[error("Invalid approval: {0}")]
+    InvalidApproval(String),
+    
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+    
+    #[error("Vault is paused")]
+    VaultPaused,
+    
+    #[error("Deposit worth {value} is below the minimum of {minimum} (USD, 12 decimals)")]
+    DepositBelowMinimum { value: u128, minimum: u128 },
+    
+    #[error("Deposit worth {value} exceeds the remaining vault capacity of {remaining} (USD, 12 decimals)")]
+    DepositExceedsCapacity { value: u128, remaining: u128 },
+    
+    #[error("Oracle error: {0}")]
+    OracleError(String),
+    
+    #[error("Oracle price of {mint} was published at {publish_time}, more than {max_age:?} before {now}")]
+    StaleOraclePrice { mint: solana_sdk::pubkey::Pubkey, publish_time: i64, now: i64, max_age: std::time::Duration },
+    
+    #[error("Oracle aggregate price isn't trading: {status}")]
+    OracleNotTrading { status: String },
+    
+    #[error("Math overflow")]
+    MathOverflow,
+    
+    #[error("RPC endpoint {endpoint} skipped after repeated failures; retry in {retry_after:?}")]
+    CircuitOpen { endpoint: usize, retry_after: std::time::Duration },
+    
+    #[error("Failed to publish: {0}")]
+    PublishFailed(String),
+    
+    #[error("Not enough history: {0}")]
+    InsufficientHistory(String),
+    
+    #[error("Invalid receipt: {0}")]
+    InvalidReceipt(String),
+    
+    #[error("Token account {account} can't be used: {reason}")]
+    InvalidTokenAccount { account: solana_sdk::pubkey::Pubkey, reason: String },
+    
+    #[error("Relayer rejected transaction: {0}")]
+    RelayRejected(String),
+    
+    #[error("Invalid SDK memo: {0}")]
+    InvalidMemo(String),
+    
+    #[error("Websocket subscription failed: {0}")]
+    Subscription(String),
+    
+    #[error("Invalid snapshot proof: {0}")]
+    InvalidProof(String),
+    
+    #[error("Invalid webhook signature: {0}")]
+    InvalidWebhookSignature(String),
+    
+    #[error("Tenant {tenant} exceeded its transaction rate limit; retry in {retry_after:?}")]
+    RateLimited { tenant: String, retry_after: std::time::Duration },
+    
+    #[error("Withdrawal stopped after {} of its transactions were sent: {source}", .sent.len())]
+    PartialWithdrawal {
+        /// Signatures of the transactions already sent, in send order
+        sent: Vec<solana_sdk::signature::Signature>,
+        /// Asset mint and CRT amount of each redemption not completed, starting with the failed one
+        remaining: Vec<(Pubkey, u64)>,
+        source: Box<CarrotError>,
+    },
+    
+    #[error("{context}: {source}")]
+    WithContext { context: ErrorContext, source: Box<CarrotError> },
+}
+
+impl CarrotError {
+    /// `Io` error of `action` on `path`, keeping the error kind and naming the file
+    pub(crate) fn io(action: &str, path: &Path, err: std::io::Error) -> Self {
+        CarrotError::Io(std::io::Error::new(
+            err.kind(),
+            format!("failed to {} {}: {}", action, path.display(), err),
+        ))
+    }
+
+    /// What the SDK was doing when the error occurred, if recorded
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            CarrotError::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error with all context removed, for matching on variants
+    pub fn root_cause(&self) -> &CarrotError {
+        match self {
+            CarrotError::WithContext { source, .. } => source.root_cause(),
+            err => err,
+        }
+    }
+
+    /// Whether a transaction sent when this error occurred may still land, as opposed to a
+    /// rejection
+    pub(crate) fn is_unconfirmed(&self) -> bool {
+        matches!(
+            self.root_cause(),
+            CarrotError::SolanaClient(_)
+                | CarrotError::ConfirmationTimeout(_)
+                | CarrotError::TransactionDropped(_)
+                | CarrotError::TransactionExpired(_)
+                | CarrotError::CircuitOpen { .. }
+        )
+    }
+
+    /// Whether the error came from talking to the cluster, as opposed to a validation
+    /// error that already says what was rejected
+    fn is_cluster_error(&self) -> bool {
+        matches!(
+            self.root_cause(),
+            CarrotError::SolanaClient(_)
+                | CarrotError::AccountNotFound(_)
+                | CarrotError::TransactionFailed(_)
+                | CarrotError::SimulationFailed(_)
+                | CarrotError::ConfirmationTimeout(_)
+                | CarrotError::TransactionDropped(_)
+                | CarrotError::InconsistentSnapshot(_)
+                | CarrotError::OracleError(_)
+                | CarrotError::StaleOraclePrice { .. }
+                | CarrotError::OracleNotTrading { .. }
+                | CarrotError::CircuitOpen { .. }
+        )
+    }
+}
+
+/// Step of an SDK call recorded in `ErrorContext`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    FetchVault,
+    FetchSnapshot,
+    BuildIssue,
+    BuildRedeem,
+    BuildTransaction,
+    Send,
+    Confirm,
+}
+
+/// What the SDK was doing when an RPC call failed: the step and, when known, asset and amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub during: Operation,
+    pub asset: Option<Pubkey>,
+    pub amount: Option<u64>,
+}
+
+impl ErrorContext {
+    /// Context of `during` with no asset or amount
+    pub fn new(during: Operation) -> Self {
+        Self {
+            during,
+            asset: None,
+            amount: None,
+        }
+    }
+
+    /// Record the asset involved
+    pub fn with_asset(mut self, asset: Pubkey) -> Self {
+        self.asset = Some(asset);
+        self
+    }
+
+    /// Record the amount involved (asset units for deposits, CRT for withdrawals)
+    pub fn with_amount(mut self, amount: u64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+}
+
+impl From<Operation> for ErrorContext {
+    fn from(during: Operation) -> Self {
+        Self::new(during)
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "during {:?}", self.during)?;
+        match (self.asset, self.amount) {
+            (Some(asset), Some(amount)) => write!(f, " (asset {}, amount {})", asset, amount),
+            (Some(asset), None) => write!(f, " (asset {})", asset),
+            (None, Some(amount)) => write!(f, " (amount {})", amount),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Attach `ErrorContext` to errors from the cluster; validation errors pass through unchanged
+/// so callers can keep matching on them directly
+pub(crate) trait ResultExt<T> {
+    fn during(self, context: impl Into<ErrorContext>) -> Result<T>;
 }
 
-pub type Result<T> = std::result::Result<T, CarrotError>;
\ No newline at end of file
+impl<T, E: Into<CarrotError>> ResultExt<T> for std::result::Result<T, E> {
+    fn during(self, context: impl Into<ErrorContext>) -> Result<T> {
+        self.map_err(|err| {
+            let err = err.into();
+            if err.is_cluster_error() {
+                CarrotError::WithContext {
+                    context: context.into(),
+                    source: Box::new(err),
+                }
+            } else {
+                err
+            }
+        })
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CarrotError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_wraps_cluster_errors() {
+        let asset = Pubkey::new_unique();
+        let context = ErrorContext::new(Operation::BuildIssue).with_asset(asset).with_amount(5);
+        let err = Err::<(), _>(CarrotError::AccountNotFound("vault".to_string()))
+            .during(Operation::FetchVault)
+            .during(context)
+            .unwrap_err();
+
+        assert_eq!(err.context(), Some(&context));
+        assert!(matches!(err.root_cause(), CarrotError::AccountNotFound(_)));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "during BuildIssue (asset {}, amount 5): during FetchVault: Account not found: vault",
+                asset
+            )
+        );
+    }
+
+    #[test]
+    fn test_validation_errors_pass_through() {
+        let err = Err::<(), _>(CarrotError::VaultPaused).during(Operation::BuildIssue).unwrap_err();
+        assert!(matches!(err, CarrotError::VaultPaused));
+        assert!(err.context().is_none());
+    }
+
+    #[test]
+    fn test_partial_withdrawal_reports_sent_transactions() {
+        let err = CarrotError::PartialWithdrawal {
+            sent: vec![solana_sdk::signature::Signature::default()],
+            remaining: vec![(Pubkey::new_unique(), 5)],
+            source: Box::new(CarrotError::TransactionFailed("insufficient liquidity".to_string())),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Withdrawal stopped after 1 of its transactions were sent: Transaction failed: insufficient liquidity"
+        );
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
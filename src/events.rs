@@ -0,0 +1,121 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use solana_sdk::signature::Signature;
+
+use crate::alerts::Alert;
+use crate::stream::{bounded, BoundedStream, StreamSender};
+
+/// Lifecycle event emitted by a `CarrotClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// The vault account was fetched and decoded
+    VaultFetched,
+    /// A consistent vault snapshot was read at `slot`
+    SnapshotFetched { slot: u64 },
+    /// A read or send is retried after `delay`
+    RetryScheduled {
+        operation: &'static str,
+        attempt: usize,
+        delay: Duration,
+    },
+    /// A signed transaction was submitted to an endpoint (`0` is the primary,
+    /// `n` the n-th fallback)
+    TxSent { signature: Signature, endpoint: usize },
+    /// A transaction reached the configured commitment
+    TxConfirmed { signature: Signature },
+    /// A transaction reached finalized commitment
+    TxFinalized { signature: Signature },
+    /// An endpoint failed repeatedly and is skipped for `cooldown` (`0` is the primary,
+    /// `n` the n-th fallback)
+    CircuitOpened { endpoint: usize, cooldown: Duration },
+    /// A supervised task failed and is restarted after `delay` (`attempt` counts consecutive
+    /// failures)
+    TaskRestarting {
+        task: String,
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// A vault activity subscription (re)started and caught up on `entries` events made after
+    /// `after_slot` (`None`: from the start of the vault's history)
+    ActivityBackfilled { after_slot: Option<u64>, entries: usize },
+    /// A monitor raised an alert
+    Alert(Alert),
+    /// A transaction failed, was dropped or timed out
+    TxFailed { signature: Signature, error: String },
+}
+
+/// Fan-out of client events to any number of subscribers
+#[derive(Default)]
+pub(crate) struct EventBus {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+/// Receiving end of the bus: an unbounded channel or a bounded stream
+enum Subscriber {
+    Channel(Sender<ClientEvent>),
+    Stream(StreamSender<ClientEvent>),
+}
+
+impl Subscriber {
+    /// Hand over `event`; `false` once the receiver is gone
+    fn deliver(&self, event: &ClientEvent) -> bool {
+        match self {
+            Subscriber::Channel(sender) => sender.send(event.clone()).is_ok(),
+            Subscriber::Stream(sender) => sender.send_dropping_oldest(event.clone()),
+        }
+    }
+}
+
+impl EventBus {
+    /// Register a new subscriber receiving every event emitted from now on
+    pub(crate) fn subscribe(&self) -> Receiver<ClientEvent> {
+        let (sender, receiver) = channel();
+        self.lock().push(Subscriber::Channel(sender));
+        receiver
+    }
+
+    /// Register a bounded stream of every event emitted from now on, dropping its oldest
+    /// events when full
+    pub(crate) fn subscribe_stream(&self, capacity: usize) -> BoundedStream<ClientEvent> {
+        let (sender, stream) = bounded(capacity);
+        self.lock().push(Subscriber::Stream(sender));
+        stream
+    }
+
+    /// Deliver `event` to all subscribers, dropping those whose receiver is gone
+    pub(crate) fn emit(&self, event: ClientEvent) {
+        let mut subscribers = self.lock();
+        subscribers.retain(|subscriber| subscriber.deliver(&event));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Subscriber>> {
+        // A panicking subscriber can't leave the list inconsistent, so ignore poisoning
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_to_subscribers() {
+        let bus = EventBus::default();
+        bus.emit(ClientEvent::VaultFetched); // no subscribers yet
+
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+        bus.emit(ClientEvent::SnapshotFetched { slot: 7 });
+        assert_eq!(first.try_recv().unwrap(), ClientEvent::SnapshotFetched { slot: 7 });
+        assert_eq!(second.try_recv().unwrap(), ClientEvent::SnapshotFetched { slot: 7 });
+        assert!(first.try_recv().is_err());
+
+        drop(second);
+        bus.emit(ClientEvent::VaultFetched);
+        assert_eq!(bus.lock().len(), 1);
+        assert_eq!(first.try_recv().unwrap(), ClientEvent::VaultFetched);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    error::{CarrotError, Result},
+    nav::{mul_div, Nav, Rounding, BPS_DENOMINATOR},
+    twap::{SharePriceHistory, SharePriceSample},
+    Fee,
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Protocol fees accrued by the vault, with USD valuations (`VALUE_DECIMALS`)
+///
+/// Accumulated fees are recorded by the vault in CRT base units and valued at the NAV's share price.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeAccruals {
+    /// Slot the vault was read at
+    pub slot: u64,
+    /// Redemption fees collected and not yet withdrawn (CRT base units)
+    pub redemption_fee_accumulated: u64,
+    /// USD value of `redemption_fee_accumulated`
+    pub redemption_fee_value: u128,
+    /// Management fees collected and not yet withdrawn (CRT base units)
+    pub management_fee_accumulated: u64,
+    /// USD value of `management_fee_accumulated`
+    pub management_fee_value: u128,
+    /// Management fee accrued since the last fee update, not yet collected (USD)
+    pub pending_management_fee_value: u128,
+    /// Unix timestamp of the vault's last management fee update
+    pub management_fee_last_update: i64,
+}
+
+impl FeeAccruals {
+    /// Value the vault's fee counters at `nav`
+    pub fn from_nav(nav: &Nav, fee: &Fee) -> Result<Self> {
+        Ok(Self {
+            slot: nav.slot,
+            redemption_fee_accumulated: fee.redemption_fee_accumulated,
+            redemption_fee_value: nav.value_of_shares(fee.redemption_fee_accumulated, Rounding::Down)?,
+            management_fee_accumulated: fee.management_fee_accumulated,
+            management_fee_value: nav.value_of_shares(fee.management_fee_accumulated, Rounding::Down)?,
+            pending_management_fee_value: nav.pending_management_fee,
+            management_fee_last_update: fee.management_fee_last_update,
+        })
+    }
+
+    /// USD value of all collected and pending fees
+    pub fn total_value(&self) -> Result<u128> {
+        self.redemption_fee_value
+            .checked_add(self.management_fee_value)
+            .and_then(|sum| sum.checked_add(self.pending_management_fee_value))
+            .ok_or(CarrotError::MathOverflow)
+    }
+}
+
+/// Share prices a history would have had under another performance fee
+///
+/// Prices and fees are per whole CRT, in USD (`VALUE_DECIMALS`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerformanceFeeSimulation {
+    /// Performance fee the observed prices were net of
+    pub current_bps: u16,
+    pub proposed_bps: u16,
+    /// The history's samples with simulated prices
+    pub simulated: Vec<SharePriceSample>,
+    /// Performance fee charged over the history, observed and simulated
+    pub actual_fees: u128,
+    pub simulated_fees: u128,
+    /// Share price change from the first sample to the last, observed and simulated
+    pub actual_return_bps: i64,
+    pub simulated_return_bps: i64,
+}
+
+impl PerformanceFeeSimulation {
+    /// Replay `history` with `proposed_bps` instead of the `current_bps` it was charged at
+    ///
+    /// The fee is modelled as a share of gains above the high-water mark, charged at every
+    /// sample. Observed gains above the mark are grossed up by `current_bps` to recover what
+    /// the strategies earned, and each period's gross change is then applied to the simulated
+    /// price, charging `proposed_bps` above its own mark. Histories spanning a fee change
+    /// should be split at the change.
+    pub fn from_history(history: &SharePriceHistory, current_bps: u16, proposed_bps: u16) -> Result<Self> {
+        for bps in [current_bps, proposed_bps] {
+            if bps as u64 > BPS_DENOMINATOR {
+                return Err(CarrotError::InvalidConfig(format!("performance fee of {} bps exceeds 100%", bps)));
+            }
+        }
+        let samples: Vec<&SharePriceSample> = history.samples().collect();
+        let [first, .., last] = samples.as_slice() else {
+            return Err(CarrotError::InsufficientHistory(
+                "simulating a performance fee needs at least two share price samples".to_string(),
+            ));
+        };
+
+        let mut simulated = vec![**first];
+        let (mut actual_mark, mut simulated_mark) = (first.price, first.price);
+        let (mut actual_fees, mut simulated_fees) = (0u128, 0u128);
+        for pair in samples.windows(2) {
+            let (previous, sample) = (pair[0].price, pair[1].price);
+            let mut gross_change = signed(sample)? - signed(previous)?;
+            if sample > actual_mark {
+                let net_gain = sample - actual_mark;
+                let gross_gain = mul_div(
+                    net_gain,
+                    BPS_DENOMINATOR as u128,
+                    (BPS_DENOMINATOR - current_bps as u64) as u128,
+                    Rounding::Down,
+                )?;
+                actual_fees = actual_fees.checked_add(gross_gain - net_gain).ok_or(CarrotError::MathOverflow)?;
+                gross_change += signed(gross_gain - net_gain)?;
+                actual_mark = sample;
+            }
+
+            let current = simulated.last().map_or(first.price, |sample| sample.price);
+            let mut price = u128::try_from((signed(current)? + gross_change).max(0)).unwrap_or(0);
+            if price > simulated_mark {
+                let fee = mul_div(
+                    price - simulated_mark,
+                    proposed_bps as u128,
+                    BPS_DENOMINATOR as u128,
+                    Rounding::Down,
+                )?;
+                simulated_fees = simulated_fees.checked_add(fee).ok_or(CarrotError::MathOverflow)?;
+                price -= fee;
+                simulated_mark = price;
+            }
+            simulated.push(SharePriceSample { price, ..*pair[1] });
+        }
+
+        let simulated_last = simulated.last().map_or(first.price, |sample| sample.price);
+        Ok(Self {
+            current_bps,
+            proposed_bps,
+            actual_return_bps: return_bps(first.price, last.price)?,
+            simulated_return_bps: return_bps(first.price, simulated_last)?,
+            simulated,
+            actual_fees,
+            simulated_fees,
+        })
+    }
+
+    /// How much more (negative: less) holders would have earned under the proposed fee
+    pub fn return_difference_bps(&self) -> i64 {
+        self.simulated_return_bps - self.actual_return_bps
+    }
+}
+
+fn signed(price: u128) -> Result<i128> {
+    i128::try_from(price).map_err(|_| CarrotError::MathOverflow)
+}
+
+/// Change from `start` to `end` in bps of `start`
+fn return_bps(start: u128, end: u128) -> Result<i64> {
+    let change = (signed(end)? - signed(start)?)
+        .checked_mul(BPS_DENOMINATOR as i128)
+        .ok_or(CarrotError::MathOverflow)?;
+    let bps = change.checked_div(signed(start)?).unwrap_or(0);
+    i64::try_from(bps).map_err(|_| CarrotError::MathOverflow)
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Read the vault and report its accrued fees, valued at the current NAV
+    pub fn fetch_fee_accruals(&self) -> Result<FeeAccruals> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        FeeAccruals::from_nav(&nav, &snapshot.vault.fee)
+    }
+
+    /// Replay the share price history under a performance fee of `proposed_bps`
+    ///
+    /// Reads the samples `spawn_share_price_sampler` keeps in the configured state store and
+    /// takes the vault's current performance fee as the one they were charged at.
+    pub fn simulate_performance_fee(&self, proposed_bps: u16) -> Result<PerformanceFeeSimulation> {
+        let store = self.config().state_store.as_ref().ok_or_else(|| {
+            CarrotError::InvalidConfig("fee simulation needs a state store with share price samples".to_string())
+        })?;
+        let history = SharePriceHistory::load_from(store.as_ref())?;
+        let current_bps = self.fetch_vault()?.fee.performance_fee_bps;
+        PerformanceFeeSimulation::from_history(&history, current_bps, proposed_bps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nav::tests::test_nav;
+    use crate::USDC_MINT;
+
+    #[test]
+    fn test_fee_accruals_valuation() {
+        // 1,000 CRT backed by 1,100 USDC: 1 CRT = 1.1 USD
+        let mut nav = test_nav(1_000_000_000_000, vec![(USDC_MINT, 1_100_000_000, 100_000_000)]);
+        nav.pending_management_fee = 5_000_000_000_000;
+        let fee = Fee {
+            redemption_fee_bps: 10,
+            redemption_fee_accumulated: 2_000_000_000,
+            management_fee_bps: 100,
+            management_fee_last_update: 1_700_000_000,
+            management_fee_accumulated: 10_000_000_000,
+            ..Default::default()
+        };
+
+        let accruals = FeeAccruals::from_nav(&nav, &fee).unwrap();
+        // Pending fee is excluded from the NAV: (1,100 - 5) / 1,000 = 1.095 USD per CRT
+        assert_eq!(accruals.redemption_fee_value, 2_190_000_000_000);
+        assert_eq!(accruals.management_fee_value, 10_950_000_000_000);
+        assert_eq!(accruals.total_value().unwrap(), 18_140_000_000_000);
+    }
+
+    fn history(prices: &[u128]) -> SharePriceHistory {
+        let mut history = SharePriceHistory::new();
+        for (index, price) in prices.iter().enumerate() {
+            history.record(SharePriceSample {
+                timestamp: index as i64 * 3_600,
+                slot: index as u64,
+                price: price * 1_000_000_000,
+            });
+        }
+        history
+    }
+
+    fn prices(simulation: &PerformanceFeeSimulation) -> Vec<u128> {
+        simulation.simulated.iter().map(|sample| sample.price / 1_000_000_000).collect()
+    }
+
+    #[test]
+    fn test_performance_fee_simulation() {
+        // Gains above the high-water mark are charged 20%; the dip to 110 and the recovery
+        // to the previous mark are not
+        let simulation = PerformanceFeeSimulation::from_history(&history(&[100, 120, 110, 130]), 0, 2_000).unwrap();
+        assert_eq!(prices(&simulation), vec![100, 116, 106, 124]);
+        assert_eq!(simulation.simulated_fees, 6_000_000_000);
+        assert_eq!(simulation.actual_fees, 0);
+        assert_eq!((simulation.actual_return_bps, simulation.simulated_return_bps), (3_000, 2_400));
+        assert_eq!(simulation.return_difference_bps(), -600);
+
+        // Removing the fee recovers the gross prices
+        let simulation = PerformanceFeeSimulation::from_history(&history(&[100, 116, 106, 124]), 2_000, 0).unwrap();
+        assert_eq!(prices(&simulation), vec![100, 120, 110, 130]);
+        assert_eq!(simulation.actual_fees, 6_000_000_000);
+
+        assert!(matches!(
+            PerformanceFeeSimulation::from_history(&history(&[100]), 0, 100),
+            Err(CarrotError::InsufficientHistory(_))
+        ));
+        assert!(matches!(
+            PerformanceFeeSimulation::from_history(&history(&[100, 110]), 0, 10_001),
+            Err(CarrotError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,419 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::VersionedTransaction};
+use solana_transaction_status_client_types::EncodedConfirmedTransactionWithStatusMeta;
+#[cfg(feature = "blocking")]
+use solana_client::{rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig};
+#[cfg(feature = "blocking")]
+use solana_transaction_status_client_types::{UiLoadedAddresses, UiTransactionEncoding};
+
+use crate::{
+    dedup::EventKey,
+    error::{CarrotError, Result},
+    instructions::{decode_instruction, CarrotInstruction},
+    CARROT_PROGRAM_ID,
+};
+#[cfg(feature = "blocking")]
+use crate::{accounts::get_user_crt_ata, client::CarrotClient, VAULT_ADDRESS};
+
+/// Signatures requested per `getSignaturesForAddress` page
+const SIGNATURE_PAGE_SIZE: usize = 1_000;
+
+/// A deposit or withdrawal made by a wallet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub signature: Signature,
+    /// Position of the instruction among the transaction's top-level instructions
+    pub instruction_index: usize,
+    pub slot: u64,
+    /// Position of the transaction in its block, when the source knows it
+    /// (signature history doesn't report it)
+    pub transaction_index: Option<usize>,
+    /// Unix timestamp of the block, if the node reports it
+    pub block_time: Option<i64>,
+    pub instruction: CarrotInstruction,
+}
+
+impl HistoryEntry {
+    /// Stable unique key of this event, for deduplication across streams
+    pub fn key(&self) -> EventKey {
+        EventKey {
+            signature: self.signature,
+            instruction_index: self.instruction_index,
+        }
+    }
+
+    /// `"deposit"` or `"withdraw"`
+    pub fn kind(&self) -> &'static str {
+        match self.instruction {
+            CarrotInstruction::Issue { .. } => "deposit",
+            CarrotInstruction::Redeem { .. } => "withdraw",
+        }
+    }
+
+    /// Asset deposited or received
+    pub fn asset_mint(&self) -> Pubkey {
+        match self.instruction {
+            CarrotInstruction::Issue { asset_mint, .. } | CarrotInstruction::Redeem { asset_mint, .. } => asset_mint,
+        }
+    }
+
+    /// Asset amount deposited, or CRT amount redeemed
+    pub fn amount(&self) -> u64 {
+        match self.instruction {
+            CarrotInstruction::Issue { amount, .. } | CarrotInstruction::Redeem { amount, .. } => amount,
+        }
+    }
+}
+
+/// Position in the vault's transaction history: the newest transaction already processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityCursor {
+    pub slot: u64,
+    pub signature: Signature,
+}
+
+impl ActivityCursor {
+    /// Cursor positioned at `entry`'s transaction
+    pub fn at(entry: &HistoryEntry) -> Self {
+        Self {
+            slot: entry.slot,
+            signature: entry.signature,
+        }
+    }
+}
+
+/// Where a history scan stops going back in time
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ScanBound {
+    /// Account creation
+    None,
+    /// Blocks before this Unix timestamp
+    Since(i64),
+    /// The cursor's transaction, or anything older than its slot if the node no longer has it
+    After(ActivityCursor),
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Fetch every successful deposit and withdrawal made by `wallet`, oldest first
+    ///
+    /// Scans the signatures of the wallet's CRT account (touched by every issue and redeem)
+    /// back to `since` (Unix timestamp), or to the account's creation when unset.
+    pub fn fetch_history(&self, wallet: &Pubkey, since: Option<i64>) -> Result<Vec<HistoryEntry>> {
+        let bound = since.map_or(ScanBound::None, ScanBound::Since);
+        Ok(self.scan_history(&get_user_crt_ata(wallet), Some(wallet), bound)?.0)
+    }
+
+    /// Fetch every successful deposit and withdrawal into the vault since `since`
+    /// (Unix timestamp), oldest first
+    ///
+    /// Fetches each transaction touching the vault account, so keep the window short.
+    pub fn fetch_vault_activity(&self, since: i64) -> Result<Vec<HistoryEntry>> {
+        Ok(self.scan_history(&VAULT_ADDRESS, None, ScanBound::Since(since))?.0)
+    }
+
+    /// Fetch every successful deposit and withdrawal into the vault made after `cursor`,
+    /// oldest first
+    ///
+    /// Used to fill the gap after a subscription dropped; entries in the cursor's own slot may
+    /// be returned again if the node has pruned the cursor's transaction.
+    pub fn fetch_vault_activity_after(&self, cursor: &ActivityCursor) -> Result<Vec<HistoryEntry>> {
+        Ok(self.scan_history(&VAULT_ADDRESS, None, ScanBound::After(*cursor))?.0)
+    }
+
+    /// Cursor at the vault's newest transaction, `None` if it has none
+    pub fn latest_vault_activity_cursor(&self) -> Result<Option<ActivityCursor>> {
+        let newest = self.rpc_client().get_signatures_for_address_with_config(
+            &VAULT_ADDRESS,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until: None,
+                limit: Some(1),
+                commitment: Some(self.rpc_client().commitment()),
+            },
+        )?;
+        newest
+            .first()
+            .map(|status| {
+                Ok(ActivityCursor {
+                    slot: status.slot,
+                    signature: parse_signature(&status.signature)?,
+                })
+            })
+            .transpose()
+    }
+
+    /// Decode the Carrot instructions (made by `wallet`, if set) in every successful
+    /// transaction touching `address` back to `bound`, oldest first, along with the newest
+    /// transaction scanned (whether or not it held any)
+    pub(crate) fn scan_history(
+        &self,
+        address: &Pubkey,
+        wallet: Option<&Pubkey>,
+        bound: ScanBound,
+    ) -> Result<(Vec<HistoryEntry>, Option<ActivityCursor>)> {
+        let mut entries = Vec::new();
+        let mut newest = None;
+        let mut before = None;
+        let until = match bound {
+            ScanBound::After(cursor) => Some(cursor.signature),
+            _ => None,
+        };
+
+        'pages: loop {
+            let page = self.rpc_client().get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(SIGNATURE_PAGE_SIZE),
+                    commitment: Some(self.rpc_client().commitment()),
+                },
+            )?;
+            let page_len = page.len();
+
+            for status in page {
+                let signature = parse_signature(&status.signature)?;
+                before = Some(signature);
+                let past_bound = match bound {
+                    ScanBound::None => false,
+                    ScanBound::Since(since) => status.block_time.is_some_and(|time| time < since),
+                    ScanBound::After(cursor) => signature == cursor.signature || status.slot < cursor.slot,
+                };
+                if past_bound {
+                    break 'pages;
+                }
+                newest.get_or_insert(ActivityCursor {
+                    slot: status.slot,
+                    signature,
+                });
+                if status.err.is_some() {
+                    continue;
+                }
+                entries.extend(self.fetch_history_entries(wallet, &signature)?);
+            }
+
+            if page_len < SIGNATURE_PAGE_SIZE {
+                break;
+            }
+        }
+
+        entries.reverse();
+        Ok((entries, newest))
+    }
+
+    /// Fetch a transaction and decode the Carrot instructions (made by `wallet`, if set) in it
+    fn fetch_history_entries(&self, wallet: Option<&Pubkey>, signature: &Signature) -> Result<Vec<HistoryEntry>> {
+        let fetched = self.fetch_carrot_transaction(signature)?;
+        Ok(decode_history_entries(&fetched.transaction, &fetched.loaded_addresses, wallet)
+            .into_iter()
+            .map(|(instruction_index, instruction)| HistoryEntry {
+                signature: *signature,
+                instruction_index,
+                slot: fetched.confirmed.slot,
+                transaction_index: None,
+                block_time: fetched.confirmed.block_time,
+                instruction,
+            })
+            .collect())
+    }
+
+    /// Fetch a confirmed transaction with its status meta and decode its message
+    pub(crate) fn fetch_carrot_transaction(&self, signature: &Signature) -> Result<FetchedTransaction> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(self.rpc_client().commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        let confirmed = self.rpc_client().get_transaction_with_config(signature, config)?;
+        let transaction = confirmed.transaction.transaction.decode().ok_or_else(|| {
+            CarrotError::TransactionFailed(format!("undecodable transaction {}", signature))
+        })?;
+
+        // Accounts loaded from lookup tables follow the static keys
+        let loaded = confirmed
+            .transaction
+            .meta
+            .as_ref()
+            .and_then(|meta| Option::from(meta.loaded_addresses.as_ref()))
+            .map(|a: &UiLoadedAddresses| [a.writable.as_slice(), a.readonly.as_slice()].concat())
+            .unwrap_or_default();
+        let loaded_addresses = loaded
+            .iter()
+            .map(|key| key.parse())
+            .collect::<std::result::Result<Vec<Pubkey>, _>>()
+            .map_err(|_| CarrotError::TransactionFailed(format!("invalid loaded address in {}", signature)))?;
+
+        Ok(FetchedTransaction {
+            confirmed,
+            transaction,
+            loaded_addresses,
+        })
+    }
+}
+
+/// A fetched transaction with its decoded message
+pub(crate) struct FetchedTransaction {
+    pub confirmed: EncodedConfirmedTransactionWithStatusMeta,
+    pub transaction: VersionedTransaction,
+    /// Accounts loaded from lookup tables, writable then readonly
+    pub loaded_addresses: Vec<Pubkey>,
+}
+
+fn parse_signature(signature: &str) -> Result<Signature> {
+    signature
+        .parse()
+        .map_err(|_| CarrotError::TransactionFailed(format!("invalid signature {}", signature)))
+}
+
+/// Decode the top-level Carrot instructions in `transaction` with their indices, keeping only
+/// those made by `wallet` if set
+pub(crate) fn decode_history_entries(
+    transaction: &VersionedTransaction,
+    loaded_addresses: &[Pubkey],
+    wallet: Option<&Pubkey>,
+) -> Vec<(usize, CarrotInstruction)> {
+    let keys: Vec<Pubkey> = transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .chain(loaded_addresses)
+        .copied()
+        .collect();
+
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .enumerate()
+        .filter(|(_, ix)| keys.get(ix.program_id_index as usize) == Some(&CARROT_PROGRAM_ID))
+        .filter_map(|(index, ix)| {
+            let accounts: Vec<Pubkey> = ix
+                .accounts
+                .iter()
+                .filter_map(|&i| keys.get(i as usize).copied())
+                .collect();
+            Some((index, decode_instruction(&ix.data, &accounts)?))
+        })
+        .filter(|(_, instruction)| match instruction {
+            CarrotInstruction::Issue { user, .. } | CarrotInstruction::Redeem { user, .. } => {
+                wallet.is_none_or(|wallet| user == wallet)
+            }
+        })
+        .collect()
+}
+
+/// Render entries as CSV with a header row
+pub fn history_to_csv(entries: &[HistoryEntry]) -> String {
+    let mut out = String::from("signature,instruction_index,slot,block_time,kind,asset_mint,amount\n");
+    for entry in entries {
+        let block_time = entry.block_time.map(|t| t.to_string()).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{}",
+            entry.signature,
+            entry.instruction_index,
+            entry.slot,
+            block_time,
+            entry.kind(),
+            entry.asset_mint(),
+            entry.amount()
+        );
+    }
+    out
+}
+
+/// One entry as exported by `history_to_json`
+#[derive(Serialize)]
+struct HistoryRow {
+    signature: String,
+    instruction_index: usize,
+    slot: u64,
+    block_time: Option<i64>,
+    kind: &'static str,
+    asset_mint: String,
+    amount: u64,
+}
+
+/// Render entries as a JSON array
+pub fn history_to_json(entries: &[HistoryEntry]) -> Result<String> {
+    let rows: Vec<HistoryRow> = entries
+        .iter()
+        .map(|entry| HistoryRow {
+            signature: entry.signature.to_string(),
+            instruction_index: entry.instruction_index,
+            slot: entry.slot,
+            block_time: entry.block_time,
+            kind: entry.kind(),
+            asset_mint: entry.asset_mint().to_string(),
+            amount: entry.amount(),
+        })
+        .collect();
+    serde_json::to_string(&rows).map_err(|e| CarrotError::InvalidConfig(format!("failed to encode history: {}", e)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::instructions::{build_issue_instruction, build_redeem_instruction};
+    use crate::test_utils::usdc_vault;
+    use crate::USDC_MINT;
+    use solana_sdk::{message::Message, transaction::Transaction};
+
+    #[test]
+    fn test_decode_history_entries_filters_by_wallet() {
+        let wallet = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                build_issue_instruction(&usdc_vault(), &wallet, &USDC_MINT, 5).unwrap(),
+                build_redeem_instruction(&usdc_vault(), &other, &USDC_MINT, 7).unwrap(),
+            ],
+            Some(&wallet),
+        );
+        let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+
+        assert_eq!(decode_history_entries(&transaction, &[], None).len(), 2);
+        let entries = decode_history_entries(&transaction, &[], Some(&wallet));
+        assert_eq!(
+            entries,
+            vec![(
+                0,
+                CarrotInstruction::Issue {
+                    user: wallet,
+                    asset_mint: USDC_MINT,
+                    amount: 5
+                }
+            )]
+        );
+        let others = decode_history_entries(&transaction, &[], Some(&other));
+        assert_eq!(others[0].0, 1);
+    }
+
+    #[test]
+    fn test_history_export_formats() {
+        let entry = HistoryEntry {
+            signature: Signature::default(),
+            instruction_index: 0,
+            slot: 10,
+            transaction_index: None,
+            block_time: None,
+            instruction: CarrotInstruction::Redeem {
+                user: Pubkey::new_unique(),
+                asset_mint: USDC_MINT,
+                amount: 42,
+            },
+        };
+        let csv = history_to_csv(std::slice::from_ref(&entry));
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().ends_with(&format!(",withdraw,{},42", USDC_MINT)));
+
+        let json: serde_json::Value = serde_json::from_str(&history_to_json(&[entry]).unwrap()).unwrap();
+        assert_eq!(json[0]["block_time"], serde_json::Value::Null);
+        assert_eq!(json[0]["kind"], "withdraw");
+        assert_eq!(json[0]["asset_mint"], USDC_MINT.to_string());
+        assert_eq!(json[0]["amount"], 42);
+    }
+}
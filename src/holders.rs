@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::time::Duration;
+#[cfg(feature = "blocking")]
+use std::thread::sleep;
+
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "blocking")]
+use solana_account_decoder_client_types::{UiAccountEncoding, UiDataSliceConfig};
+#[cfg(feature = "blocking")]
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, error::Result, token::TokenProgram, CRT_MINT};
+
+/// Offset of the owner in a token account
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// Bytes of a token account read per holder: owner (32) followed by amount (8)
+const OWNER_AND_AMOUNT_LEN: usize = 40;
+
+/// Delay between the partitioned requests of a holder scan, to stay under RPC rate limits
+pub const HOLDER_SCAN_REQUEST_DELAY: Duration = Duration::from_millis(100);
+
+/// A wallet's total CRT balance across its token accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrtHolder {
+    pub owner: Pubkey,
+    pub balance: u64,
+}
+
+/// A CRT token account's owner and balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrtTokenAccount {
+    pub address: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Read every CRT token account (owner and balance only) with `getProgramAccounts`
+    pub(crate) fn fetch_crt_token_accounts(&self, extra_filters: Vec<RpcFilterType>) -> Result<Vec<CrtTokenAccount>> {
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, CRT_MINT.as_ref()))];
+        filters.extend(extra_filters);
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: Some(UiDataSliceConfig {
+                    offset: TOKEN_ACCOUNT_OWNER_OFFSET,
+                    length: OWNER_AND_AMOUNT_LEN,
+                }),
+                commitment: Some(self.rpc_client().commitment()),
+                min_context_slot: None,
+            },
+            with_context: None,
+            sort_results: None,
+        };
+
+        let accounts = self
+            .rpc_client()
+            .get_program_accounts_with_config(&TokenProgram::Token2022.id(), config)?;
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(address, account)| parse_owner_and_amount(address, &account.data))
+            .collect())
+    }
+
+    /// List wallets holding at least `min_balance` CRT (base units), largest first
+    ///
+    /// The scan is split into 256 `getProgramAccounts` requests by the first byte of the
+    /// owner, spaced by `HOLDER_SCAN_REQUEST_DELAY`, so no single response is too large for
+    /// the endpoint.
+    pub fn list_crt_holders(&self, min_balance: u64) -> Result<Vec<CrtHolder>> {
+        let mut balances: HashMap<Pubkey, u64> = HashMap::new();
+        for first_byte in 0..=u8::MAX {
+            if first_byte > 0 {
+                sleep(HOLDER_SCAN_REQUEST_DELAY);
+            }
+            let partition = RpcFilterType::Memcmp(Memcmp::new_raw_bytes(TOKEN_ACCOUNT_OWNER_OFFSET, vec![first_byte]));
+            for account in self.fetch_crt_token_accounts(vec![partition])? {
+                let balance = balances.entry(account.owner).or_default();
+                *balance = balance.saturating_add(account.amount);
+            }
+        }
+        Ok(aggregate_holders(balances, min_balance))
+    }
+
+    /// Number of wallets holding a non-zero CRT balance
+    pub fn count_crt_holders(&self) -> Result<usize> {
+        let mut owners: Vec<Pubkey> = self
+            .fetch_crt_token_accounts(Vec::new())?
+            .into_iter()
+            .filter(|account| account.amount > 0)
+            .map(|account| account.owner)
+            .collect();
+        owners.sort_unstable();
+        owners.dedup();
+        Ok(owners.len())
+    }
+}
+
+/// Holders with at least `min_balance` (and more than zero), largest balance first
+fn aggregate_holders(balances: HashMap<Pubkey, u64>, min_balance: u64) -> Vec<CrtHolder> {
+    let mut holders: Vec<CrtHolder> = balances
+        .into_iter()
+        .filter(|&(_, balance)| balance > 0 && balance >= min_balance)
+        .map(|(owner, balance)| CrtHolder { owner, balance })
+        .collect();
+    holders.sort_by(|a, b| b.balance.cmp(&a.balance).then(a.owner.cmp(&b.owner)));
+    holders
+}
+
+/// Fraction of the listed supply held by the `n` largest holders (0.0 to 1.0)
+pub fn top_holder_share(holders: &[CrtHolder], n: usize) -> f64 {
+    let total: u128 = holders.iter().map(|h| h.balance as u128).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut balances: Vec<u64> = holders.iter().map(|h| h.balance).collect();
+    balances.sort_unstable_by(|a, b| b.cmp(a));
+    let top: u128 = balances.iter().take(n).map(|&b| b as u128).sum();
+    top as f64 / total as f64
+}
+
+/// Parse the owner/amount slice of a token account
+fn parse_owner_and_amount(address: Pubkey, data: &[u8]) -> Option<CrtTokenAccount> {
+    let owner = Pubkey::try_from(data.get(..32)?).ok()?;
+    let amount = u64::from_le_bytes(data.get(32..OWNER_AND_AMOUNT_LEN)?.try_into().ok()?);
+    Some(CrtTokenAccount { address, owner, amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_owner_and_amount() {
+        let owner = Pubkey::new_unique();
+        let mut data = owner.to_bytes().to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+
+        let account = parse_owner_and_amount(Pubkey::default(), &data).unwrap();
+        assert_eq!((account.owner, account.amount), (owner, 42));
+        assert!(parse_owner_and_amount(Pubkey::default(), &data[..39]).is_none());
+    }
+
+    #[test]
+    fn test_aggregate_holders() {
+        let (whale, small, empty) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let balances = HashMap::from([(whale, 900), (small, 100), (empty, 0)]);
+
+        let holders = aggregate_holders(balances.clone(), 0);
+        assert_eq!(holders, vec![
+            CrtHolder { owner: whale, balance: 900 },
+            CrtHolder { owner: small, balance: 100 },
+        ]);
+        assert_eq!(aggregate_holders(balances, 500).len(), 1);
+        assert!((top_holder_share(&holders, 1) - 0.9).abs() < 1e-12);
+        assert_eq!(top_holder_share(&[], 1), 0.0);
+    }
+}
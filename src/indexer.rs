@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "blocking")]
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use crate::{
+    error::{CarrotError, Result},
+    store::StateStore,
+    Vault,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, supervisor::TaskGroup};
+
+/// `StateStore` namespace of strategy history, keyed by `<strategy_id>:<epoch>`
+pub const INDEXER_NAMESPACE: &str = "strategy-indexer";
+
+/// A strategy's state as recorded at one epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrategyPoint {
+    pub epoch: u64,
+    /// Slot the vault was read at
+    pub slot: u64,
+    pub asset_id: u16,
+    pub balance: u64,
+    /// Cumulative net earnings reported by the vault
+    pub net_earnings: i64,
+}
+
+/// Net earnings of a strategy over one epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YieldPoint {
+    pub epoch: u64,
+    pub balance: u64,
+    /// Cumulative net earnings at the end of the epoch
+    pub net_earnings: i64,
+    /// Earnings since the previous recorded epoch (`None` for the first point)
+    pub earnings_delta: Option<i64>,
+}
+
+/// Per-epoch `StrategyRecord` history, persisted to a local CSV file or a `StateStore`
+///
+/// Call `CarrotClient::index_strategies` periodically (e.g. once per hour) to record
+/// each epoch's strategy state; recording an epoch twice keeps the first read.
+#[derive(Debug, Clone, Default)]
+pub struct StrategyIndexer {
+    points: BTreeMap<u16, BTreeMap<u64, StrategyPoint>>,
+}
+
+impl StrategyIndexer {
+    /// Empty, in-memory history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load history saved with `save`, or start empty if `path` doesn't exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut indexer = Self::new();
+        if !path.exists() {
+            return Ok(indexer);
+        }
+        let contents = fs::read_to_string(path).map_err(|e| CarrotError::io("read", path, e))?;
+        for (line_number, line) in contents.lines().enumerate().skip(1) {
+            let (strategy_id, point) = parse_line(line).ok_or_else(|| {
+                CarrotError::InvalidConfig(format!("{}:{}: malformed strategy record", path.display(), line_number + 1))
+            })?;
+            indexer.insert(strategy_id, point);
+        }
+        Ok(indexer)
+    }
+
+    /// Write the full history to `path` as CSV
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut out = String::from("strategy_id,epoch,slot,asset_id,balance,net_earnings\n");
+        for (strategy_id, points) in &self.points {
+            for p in points.values() {
+                out.push_str(&format_line(*strategy_id, p));
+                out.push('\n');
+            }
+        }
+        fs::write(path, out).map_err(|e| CarrotError::io("write", path, e))
+    }
+
+    /// Load history saved with `save_to`
+    pub fn load_from(store: &dyn StateStore) -> Result<Self> {
+        let mut indexer = Self::new();
+        for (key, value) in store.scan(INDEXER_NAMESPACE)? {
+            let (strategy_id, point) = std::str::from_utf8(&value)
+                .ok()
+                .and_then(parse_line)
+                .ok_or_else(|| CarrotError::InvalidConfig(format!("malformed strategy record {}", key)))?;
+            indexer.insert(strategy_id, point);
+        }
+        Ok(indexer)
+    }
+
+    /// Write every recorded epoch to `store` (existing records are overwritten with the same data)
+    pub fn save_to(&self, store: &dyn StateStore) -> Result<()> {
+        for (strategy_id, points) in &self.points {
+            for p in points.values() {
+                let key = format!("{}:{}", strategy_id, p.epoch);
+                store.put(INDEXER_NAMESPACE, &key, format_line(*strategy_id, p).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record every strategy of `vault` at `epoch`; returns whether anything new was recorded
+    pub fn record(&mut self, epoch: u64, slot: u64, vault: &Vault) -> bool {
+        let mut recorded = false;
+        for strategy in &vault.strategies {
+            recorded |= self.insert(
+                strategy.strategy_id,
+                StrategyPoint {
+                    epoch,
+                    slot,
+                    asset_id: strategy.asset_id,
+                    balance: strategy.balance,
+                    net_earnings: strategy.net_earnings,
+                },
+            );
+        }
+        recorded
+    }
+
+    fn insert(&mut self, strategy_id: u16, point: StrategyPoint) -> bool {
+        let points = self.points.entry(strategy_id).or_default();
+        if points.contains_key(&point.epoch) {
+            return false;
+        }
+        points.insert(point.epoch, point);
+        true
+    }
+
+    /// IDs of all strategies with recorded history
+    pub fn strategy_ids(&self) -> Vec<u16> {
+        self.points.keys().copied().collect()
+    }
+
+    /// Earnings of `strategy_id` over its last `window` recorded epochs, oldest first
+    pub fn strategy_yield_series(&self, strategy_id: u16, window: usize) -> Vec<YieldPoint> {
+        let Some(points) = self.points.get(&strategy_id) else {
+            return Vec::new();
+        };
+        // Include one extra epoch so the first point in the window has a delta
+        let skip = points.len().saturating_sub(window + 1);
+        let mut previous: Option<&StrategyPoint> = None;
+        let mut series: Vec<YieldPoint> = points
+            .values()
+            .skip(skip)
+            .map(|point| {
+                let earnings_delta = previous.map(|p| point.net_earnings.saturating_sub(p.net_earnings));
+                previous = Some(point);
+                YieldPoint {
+                    epoch: point.epoch,
+                    balance: point.balance,
+                    net_earnings: point.net_earnings,
+                    earnings_delta,
+                }
+            })
+            .collect();
+        if series.len() > window {
+            series.remove(0);
+        }
+        series
+    }
+}
+
+fn format_line(strategy_id: u16, p: &StrategyPoint) -> String {
+    format!(
+        "{},{},{},{},{},{}",
+        strategy_id, p.epoch, p.slot, p.asset_id, p.balance, p.net_earnings
+    )
+}
+
+fn parse_line(line: &str) -> Option<(u16, StrategyPoint)> {
+    let mut fields = line.split(',');
+    let mut next = || fields.next().map(str::trim);
+    let strategy_id = next()?.parse().ok()?;
+    let point = StrategyPoint {
+        epoch: next()?.parse().ok()?,
+        slot: next()?.parse().ok()?,
+        asset_id: next()?.parse().ok()?,
+        balance: next()?.parse().ok()?,
+        net_earnings: next()?.parse().ok()?,
+    };
+    Some((strategy_id, point))
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Record the vault's strategies for the current epoch into `indexer`
+    /// Returns whether a new epoch was recorded
+    pub fn index_strategies(&self, indexer: &mut StrategyIndexer) -> Result<bool> {
+        let epoch_info = self.rpc_client().get_epoch_info()?;
+        let vault = self.fetch_vault()?;
+        Ok(indexer.record(epoch_info.epoch, epoch_info.absolute_slot, &vault))
+    }
+
+    /// Keep the history at `path` up to date from a supervised task in `group`
+    ///
+    /// Strategies are recorded every `interval` and the file is rewritten whenever a new epoch
+    /// was recorded; RPC failures restart the task (reloading the file) after the group's backoff.
+    pub fn spawn_strategy_indexer(&self, group: &mut TaskGroup, path: impl Into<PathBuf>, interval: Duration) {
+        let client = self.clone();
+        let path = path.into();
+        group.spawn("strategy-indexer", move |shutdown| {
+            let mut indexer = StrategyIndexer::load(&path)?;
+            loop {
+                if client.index_strategies(&mut indexer)? {
+                    indexer.save(&path)?;
+                }
+                if shutdown.wait_timeout(interval) {
+                    return Ok(());
+                }
+            }
+        });
+    }
+
+    /// Like `spawn_strategy_indexer`, keeping the history in `store`
+    pub fn spawn_strategy_indexer_with_store(&self, group: &mut TaskGroup, store: Arc<dyn StateStore>, interval: Duration) {
+        let client = self.clone();
+        group.spawn("strategy-indexer", move |shutdown| {
+            let mut indexer = StrategyIndexer::load_from(store.as_ref())?;
+            loop {
+                if client.index_strategies(&mut indexer)? {
+                    indexer.save_to(store.as_ref())?;
+                }
+                if shutdown.wait_timeout(interval) {
+                    return Ok(());
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fee, StrategyRecord, CRT_MINT};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn vault_with_strategy(balance: u64, net_earnings: i64) -> Vault {
+        Vault {
+            authority: Pubkey::new_unique(),
+            shares: CRT_MINT,
+            fee: Fee::default(),
+            paused: false,
+            asset_index: 0,
+            strategy_index: 1,
+            assets: vec![],
+            strategies: vec![StrategyRecord {
+                strategy_id: 3,
+                asset_id: 0,
+                balance,
+                net_earnings,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_strategy_yield_series() {
+        let mut indexer = StrategyIndexer::new();
+        assert!(indexer.record(700, 1, &vault_with_strategy(100, 10)));
+        assert!(indexer.record(701, 2, &vault_with_strategy(100, 15)));
+        assert!(!indexer.record(701, 3, &vault_with_strategy(100, 99)));
+        assert!(indexer.record(702, 4, &vault_with_strategy(120, 12)));
+
+        let series = indexer.strategy_yield_series(3, 2);
+        assert_eq!(series.len(), 2);
+        assert_eq!((series[0].epoch, series[0].earnings_delta), (701, Some(5)));
+        assert_eq!((series[1].epoch, series[1].earnings_delta), (702, Some(-3)));
+
+        let full = indexer.strategy_yield_series(3, 10);
+        assert_eq!(full.len(), 3);
+        assert_eq!(full[0].earnings_delta, None);
+        assert!(indexer.strategy_yield_series(4, 10).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut indexer = StrategyIndexer::new();
+        indexer.record(700, 1, &vault_with_strategy(100, -10));
+        let path = std::env::temp_dir().join(format!("carrot-strategies-{}.csv", std::process::id()));
+        indexer.save(&path).unwrap();
+
+        let loaded = StrategyIndexer::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.strategy_ids(), vec![3]);
+        assert_eq!(loaded.strategy_yield_series(3, 1)[0].net_earnings, -10);
+
+        let store = crate::store::MemoryStore::new();
+        indexer.save_to(&store).unwrap();
+        let restored = StrategyIndexer::load_from(&store).unwrap();
+        assert_eq!(restored.strategy_yield_series(3, 1), loaded.strategy_yield_series(3, 1));
+
+        // A directory can't be written as a file
+        let err = indexer.save(std::env::temp_dir()).unwrap_err();
+        assert!(matches!(err, CarrotError::Io(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,74 @@
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+
+use crate::{
+    error::{CarrotError, Result},
+    instructions::{decode_instruction, CarrotInstruction},
+    CARROT_PROGRAM_ID,
+};
+
+/// Summary of what a transaction will do, for review before signing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionInspection {
+    /// Account paying the transaction fee
+    pub fee_payer: Pubkey,
+    /// Accounts that must sign
+    pub signers: Vec<Pubkey>,
+    /// Programs invoked by top-level instructions, in first-use order
+    pub programs: Vec<Pubkey>,
+    /// Accounts the transaction may write to
+    pub writable_accounts: Vec<Pubkey>,
+    /// Decoded Carrot instructions with their instruction index
+    pub carrot_instructions: Vec<(usize, CarrotInstruction)>,
+    /// Indexes of Carrot instructions that could not be decoded
+    pub unknown_carrot_instructions: Vec<usize>,
+}
+
+/// Inspect a (signed or unsigned) transaction
+///
+/// Fails with `TransactionFailed` if an instruction refers to an account index out of bounds.
+pub fn inspect(tx: &Transaction) -> Result<TransactionInspection> {
+    let message = &tx.message;
+    let keys = &message.account_keys;
+    let key = |index: u8| {
+        keys.get(index as usize)
+            .copied()
+            .ok_or_else(|| CarrotError::TransactionFailed(format!("account index {} out of bounds", index)))
+    };
+
+    let signers = (0..keys.len())
+        .filter(|&i| message.is_signer(i))
+        .map(|i| keys[i])
+        .collect();
+    let writable_accounts = (0..keys.len())
+        .filter(|&i| message.is_maybe_writable(i, None))
+        .map(|i| keys[i])
+        .collect();
+
+    let mut programs: Vec<Pubkey> = Vec::new();
+    let mut carrot_instructions = Vec::new();
+    let mut unknown_carrot_instructions = Vec::new();
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        let program_id = key(instruction.program_id_index)?;
+        if !programs.contains(&program_id) {
+            programs.push(program_id);
+        }
+        if program_id != CARROT_PROGRAM_ID {
+            continue;
+        }
+
+        let accounts = instruction.accounts.iter().map(|&i| key(i)).collect::<Result<Vec<_>>>()?;
+        match decode_instruction(&instruction.data, &accounts) {
+            Some(decoded) => carrot_instructions.push((index, decoded)),
+            None => unknown_carrot_instructions.push(index),
+        }
+    }
+
+    Ok(TransactionInspection {
+        fee_payer: keys.first().copied().unwrap_or_default(),
+        signers,
+        programs,
+        writable_accounts,
+        carrot_instructions,
+        unknown_carrot_instructions,
+    })
+}
@@ -1,110 +1,344 @@
+use std::fmt;
+
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
 };
 use solana_sdk_ids::system_program;
 use crate::{
-    accounts::{get_user_asset_ata, get_user_crt_ata, get_vault_asset_ata, get_token_program_id},
-    error::Result,
-    IssueArgs, RedeemArgs, CARROT_PROGRAM_ID, CRT_MINT, LOG_PROGRAM_ID, VAULT_ADDRESS,
+    error::{CarrotError, Result},
+    token::TokenProgram,
+    IssueArgs, RedeemArgs, VaultHandle, CARROT_PROGRAM_ID, LOG_PROGRAM_ID,
 };
 
 /// Instruction discriminators for Carrot Protocol
 /// These are derived from the instruction name using anchor's discriminator algorithm
 /// Calculated as: sha256("global:issue")[..8] and sha256("global:redeem")[..8]
-const ISSUE_DISCRIMINATOR: [u8; 8] = [190, 1, 98, 214, 81, 99, 222, 247];
-const REDEEM_DISCRIMINATOR: [u8; 8] = [184, 12, 86, 149, 70, 196, 97, 225];
+pub(crate) const ISSUE_DISCRIMINATOR: [u8; 8] = [190, 1, 98, 214, 81, 99, 222, 247];
+pub(crate) const REDEEM_DISCRIMINATOR: [u8; 8] = [184, 12, 86, 149, 70, 196, 97, 225];
+
+/// Position of the asset mint in issue/redeem account lists
+const ASSET_MINT_ACCOUNT_INDEX: usize = 3;
+/// Position of the user (signer) in issue/redeem account lists
+const USER_ACCOUNT_INDEX: usize = 6;
+
+/// Names of the fixed issue/redeem accounts, in order (remaining accounts follow)
+pub const ISSUE_REDEEM_ACCOUNT_NAMES: [&str; 11] = [
+    "vault",
+    "shares_mint",
+    "user_shares_ata",
+    "asset_mint",
+    "vault_asset_ata",
+    "user_asset_ata",
+    "user",
+    "system_program",
+    "asset_token_program",
+    "shares_token_program",
+    "log_program",
+];
+
+/// Role name of the account at `index` in an issue/redeem instruction
+pub fn account_name(index: usize) -> String {
+    match ISSUE_REDEEM_ACCOUNT_NAMES.get(index) {
+        Some(name) => name.to_string(),
+        None => format!("remaining_account[{}]", index - ISSUE_REDEEM_ACCOUNT_NAMES.len()),
+    }
+}
+
+/// Log every account meta of `instruction` at debug level, naming Carrot accounts by role
+pub(crate) fn log_account_metas(instruction: &Instruction) {
+    let is_carrot = instruction.program_id == CARROT_PROGRAM_ID;
+    log::debug!(
+        "instruction for program {} with {} accounts",
+        instruction.program_id,
+        instruction.accounts.len()
+    );
+    for (index, meta) in instruction.accounts.iter().enumerate() {
+        let name = if is_carrot {
+            account_name(index)
+        } else {
+            format!("account[{}]", index)
+        };
+        log::debug!(
+            "  {:>2} {:<24} {} writable={} signer={}",
+            index,
+            name,
+            meta.pubkey,
+            meta.is_writable,
+            meta.is_signer
+        );
+    }
+}
+
+/// Conversion of an accounts struct into instruction account metas, mirroring Anchor's trait
+/// so the structs below can be used where Anchor client code expects one
+pub trait ToAccountMetas {
+    /// Account metas in instruction order; `is_signer` overrides the signer flag of signer roles
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta>;
+}
+
+/// Supplies the remaining accounts appended to issue and redeem instructions
+///
+/// The builders take whatever the resolver returns, so a program version that prices the
+/// vault from other accounts (e.g. per-strategy accounts) needs a new resolver, not new
+/// builders, and tests can inject synthetic account sets.
+pub trait RemainingAccountsResolver: fmt::Debug + Send + Sync {
+    /// Remaining accounts for an issue or redeem of `asset_mint` against `vault`, in order
+    fn resolve(&self, vault: &VaultHandle, asset_mint: &Pubkey) -> Result<Vec<Pubkey>>;
+}
+
+/// The current program's remaining accounts: vault ATA and oracle of every vault asset
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AssetOracleAccounts;
+
+impl RemainingAccountsResolver for AssetOracleAccounts {
+    fn resolve(&self, vault: &VaultHandle, _asset_mint: &Pubkey) -> Result<Vec<Pubkey>> {
+        Ok(vault.vault.get_remaining_accounts())
+    }
+}
+
+/// A fixed account list, whatever the vault and asset
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StaticAccounts(pub Vec<Pubkey>);
+
+impl RemainingAccountsResolver for StaticAccounts {
+    fn resolve(&self, _vault: &VaultHandle, _asset_mint: &Pubkey) -> Result<Vec<Pubkey>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Accounts of an issue instruction, by role (order as in `ISSUE_REDEEM_ACCOUNT_NAMES`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueAccounts {
+    pub vault: Pubkey,
+    /// CRT mint
+    pub shares_mint: Pubkey,
+    pub user_shares_ata: Pubkey,
+    pub asset_mint: Pubkey,
+    pub vault_asset_ata: Pubkey,
+    pub user_asset_ata: Pubkey,
+    /// Signer depositing or redeeming
+    pub user: Pubkey,
+    pub system_program: Pubkey,
+    /// Token or Token-2022, whichever owns the asset mint
+    pub asset_token_program: Pubkey,
+    /// Token-2022
+    pub shares_token_program: Pubkey,
+    pub log_program: Pubkey,
+    /// Vault asset ATA and oracle of every vault asset, used to price the vault
+    pub remaining_accounts: Vec<Pubkey>,
+}
+
+/// Redeem takes the same accounts as issue
+pub type RedeemAccounts = IssueAccounts;
+
+impl IssueAccounts {
+    /// Accounts for `user` depositing (or redeeming for) `asset_mint` in `vault`
+    pub fn new(vault: &VaultHandle, user: &Pubkey, asset_mint: &Pubkey) -> Result<Self> {
+        Self::new_with_resolver(vault, user, asset_mint, &AssetOracleAccounts)
+    }
+
+    /// Like `new`, with the remaining accounts supplied by `resolver`
+    pub fn new_with_resolver(
+        vault: &VaultHandle,
+        user: &Pubkey,
+        asset_mint: &Pubkey,
+        resolver: &dyn RemainingAccountsResolver,
+    ) -> Result<Self> {
+        let shares_mint = vault.vault.shares;
+        let asset_token_program = vault.asset_token_program(asset_mint)?;
+        Ok(Self {
+            vault: vault.address,
+            shares_mint,
+            user_shares_ata: TokenProgram::Token2022.associated_token_address(user, &shares_mint),
+            asset_mint: *asset_mint,
+            vault_asset_ata: vault.asset(asset_mint)?.ata,
+            user_asset_ata: asset_token_program.associated_token_address(user, asset_mint),
+            user: *user,
+            system_program: system_program::id(),
+            // Token-2022 for pyUSD, Token for USDC/USDT; recognized from the vault for new assets
+            asset_token_program: asset_token_program.id(),
+            shares_token_program: TokenProgram::Token2022.id(),
+            log_program: LOG_PROGRAM_ID,
+            remaining_accounts: resolver.resolve(vault, asset_mint)?,
+        })
+    }
+
+    /// Use `token_account` instead of the user's ATA on the asset side
+    ///
+    /// For wallets holding the asset in an auxiliary account; it must be an account of
+    /// `asset_mint` owned by `user` (see `token::check_token_account`).
+    pub fn with_user_asset_account(mut self, token_account: Pubkey) -> Self {
+        self.user_asset_ata = token_account;
+        self
+    }
 
-/// Build the issue (deposit) instruction
+    /// Read the roles back from an instruction's account keys
+    /// Returns `None` if fewer than the fixed accounts are present
+    pub fn from_accounts(accounts: &[Pubkey]) -> Option<Self> {
+        let (fixed, remaining) = accounts.split_at_checked(ISSUE_REDEEM_ACCOUNT_NAMES.len())?;
+        let [
+            vault,
+            shares_mint,
+            user_shares_ata,
+            asset_mint,
+            vault_asset_ata,
+            user_asset_ata,
+            user,
+            system_program,
+            asset_token_program,
+            shares_token_program,
+            log_program,
+        ] = <[Pubkey; 11]>::try_from(fixed).ok()?;
+        Some(Self {
+            vault,
+            shares_mint,
+            user_shares_ata,
+            asset_mint,
+            vault_asset_ata,
+            user_asset_ata,
+            user,
+            system_program,
+            asset_token_program,
+            shares_token_program,
+            log_program,
+            remaining_accounts: remaining.to_vec(),
+        })
+    }
+}
+
+impl ToAccountMetas for IssueAccounts {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(self.shares_mint, false),
+            AccountMeta::new(self.user_shares_ata, false),
+            AccountMeta::new_readonly(self.asset_mint, false),
+            AccountMeta::new(self.vault_asset_ata, false),
+            AccountMeta::new(self.user_asset_ata, false),
+            AccountMeta::new(self.user, is_signer.unwrap_or(true)),
+            AccountMeta::new_readonly(self.system_program, false),
+            AccountMeta::new_readonly(self.asset_token_program, false),
+            AccountMeta::new_readonly(self.shares_token_program, false),
+            AccountMeta::new_readonly(self.log_program, false),
+        ];
+        accounts.extend(self.remaining_accounts.iter().map(|account| AccountMeta::new(*account, false)));
+        accounts
+    }
+}
+
+/// A decoded Carrot Protocol instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CarrotInstruction {
+    /// Deposit `amount` of `asset_mint` and mint CRT
+    Issue {
+        user: Pubkey,
+        asset_mint: Pubkey,
+        amount: u64,
+    },
+    /// Burn `amount` CRT and receive `asset_mint`
+    Redeem {
+        user: Pubkey,
+        asset_mint: Pubkey,
+        amount: u64,
+    },
+}
+
+/// Decode a Carrot instruction from its data and account keys
+/// Returns `None` for unknown discriminators or malformed data
+pub fn decode_instruction(data: &[u8], accounts: &[Pubkey]) -> Option<CarrotInstruction> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, args) = data.split_at(8);
+    let user = *accounts.get(USER_ACCOUNT_INDEX)?;
+    let asset_mint = *accounts.get(ASSET_MINT_ACCOUNT_INDEX)?;
+
+    if discriminator == ISSUE_DISCRIMINATOR {
+        let args: IssueArgs = borsh::from_slice(args).ok()?;
+        Some(CarrotInstruction::Issue {
+            user,
+            asset_mint,
+            amount: args.amount,
+        })
+    } else if discriminator == REDEEM_DISCRIMINATOR {
+        let args: RedeemArgs = borsh::from_slice(args).ok()?;
+        Some(CarrotInstruction::Redeem {
+            user,
+            asset_mint,
+            amount: args.amount,
+        })
+    } else {
+        None
+    }
+}
+
+/// Build the issue (deposit) instruction against `vault`
 /// This deposits asset tokens (USDC, USDT, pyUSD) and mints CRT shares
 pub fn build_issue_instruction(
+    vault: &VaultHandle,
     user: &Pubkey,
     asset_mint: &Pubkey,
     amount: u64,
-    remaining_accounts: Vec<Pubkey>,
 ) -> Result<Instruction> {
-    let user_shares_ata = get_user_crt_ata(user);
-    let user_asset_ata = get_user_asset_ata(user, asset_mint);
-    let vault_asset_ata = get_vault_asset_ata(asset_mint);
+    build_issue_instruction_with_accounts(&IssueAccounts::new(vault, user, asset_mint)?, amount)
+}
 
+/// Build the issue instruction from explicit accounts, e.g. with a non-ATA asset account
+pub fn build_issue_instruction_with_accounts(accounts: &IssueAccounts, amount: u64) -> Result<Instruction> {
     let args = IssueArgs { amount };
-    
+
     // Serialize instruction data: discriminator + args
     let mut data = ISSUE_DISCRIMINATOR.to_vec();
-    data.extend_from_slice(&borsh::to_vec(&args)?);
-
-    // Get correct token program for asset (Token-2022 for pyUSD, Token for USDC/USDT)
-    let asset_token_program = get_token_program_id(asset_mint);
-    
-    let mut accounts = vec![
-        AccountMeta::new(VAULT_ADDRESS, false),
-        AccountMeta::new(CRT_MINT, false),
-        AccountMeta::new(user_shares_ata, false),
-        AccountMeta::new_readonly(*asset_mint, false),
-        AccountMeta::new(vault_asset_ata, false),
-        AccountMeta::new(user_asset_ata, false),
-        AccountMeta::new(*user, true), // signer
-        AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(asset_token_program, false), // asset token program (Token or Token-2022)
-        AccountMeta::new_readonly(spl_token_2022_interface::id(), false), // shares token program (Token-2022)
-        AccountMeta::new_readonly(LOG_PROGRAM_ID, false),
-    ];
-
-    // Add remaining accounts (vault asset ATAs and oracles for each asset)
-    for account in remaining_accounts {
-        accounts.push(AccountMeta::new(account, false));
-    }
+    data.extend_from_slice(&borsh::to_vec(&args).map_err(CarrotError::BorshDeserialize)?);
 
-    Ok(Instruction {
-        program_id: CARROT_PROGRAM_ID,
-        accounts,
-        data,
-    })
+    Ok(build_vault_instruction(accounts, data))
 }
 
-/// Build the redeem (withdrawal) instruction
+/// Build the redeem (withdrawal) instruction against `vault`
 /// This burns CRT shares and returns asset tokens
 pub fn build_redeem_instruction(
+    vault: &VaultHandle,
     user: &Pubkey,
     asset_mint: &Pubkey,
     amount: u64,
-    remaining_accounts: Vec<Pubkey>,
 ) -> Result<Instruction> {
-    let user_shares_ata = get_user_crt_ata(user);
-    let user_asset_ata = get_user_asset_ata(user, asset_mint);
-    let vault_asset_ata = get_vault_asset_ata(asset_mint);
+    build_redeem_instruction_with_accounts(&RedeemAccounts::new(vault, user, asset_mint)?, amount)
+}
 
+/// Build the redeem instruction from explicit accounts, e.g. with a non-ATA asset account
+pub fn build_redeem_instruction_with_accounts(accounts: &RedeemAccounts, amount: u64) -> Result<Instruction> {
     let args = RedeemArgs { amount };
-    
+
     // Serialize instruction data: discriminator + args
     let mut data = REDEEM_DISCRIMINATOR.to_vec();
-    data.extend_from_slice(&borsh::to_vec(&args)?);
-
-    // Get correct token program for asset (Token-2022 for pyUSD, Token for USDC/USDT)
-    let asset_token_program = get_token_program_id(asset_mint);
-    
-    let mut accounts = vec![
-        AccountMeta::new(VAULT_ADDRESS, false),
-        AccountMeta::new(CRT_MINT, false),
-        AccountMeta::new(user_shares_ata, false),
-        AccountMeta::new_readonly(*asset_mint, false),
-        AccountMeta::new(vault_asset_ata, false),
-        AccountMeta::new(user_asset_ata, false),
-        AccountMeta::new(*user, true), // signer
-        AccountMeta::new_readonly(system_program::id(), false),
-        AccountMeta::new_readonly(asset_token_program, false), // asset token program (Token or Token-2022)
-        AccountMeta::new_readonly(spl_token_2022_interface::id(), false), // shares token program (Token-2022)
-        AccountMeta::new_readonly(LOG_PROGRAM_ID, false),
-    ];
-
-    // Add remaining accounts (vault asset ATAs and oracles for each asset)
-    for account in remaining_accounts {
-        accounts.push(AccountMeta::new(account, false));
-    }
+    data.extend_from_slice(&borsh::to_vec(&args).map_err(CarrotError::BorshDeserialize)?);
+
+    Ok(build_vault_instruction(accounts, data))
+}
 
-    Ok(Instruction {
+/// Build the redeem instruction for an asset whose mint has a Token-2022 transfer hook
+///
+/// `hook_accounts` (from `CarrotClient::transfer_hook_accounts` for the payout from the vault's
+/// asset ATA to the user's) are appended after the remaining accounts so the payout transfer can
+/// invoke the hook. Redeem burns CRT rather than transferring it, so CRT's own hook never runs.
+pub fn build_redeem_instruction_with_hook_accounts(
+    vault: &VaultHandle,
+    user: &Pubkey,
+    asset_mint: &Pubkey,
+    amount: u64,
+    hook_accounts: &[AccountMeta],
+) -> Result<Instruction> {
+    let mut instruction = build_redeem_instruction(vault, user, asset_mint, amount)?;
+    instruction.accounts.extend_from_slice(hook_accounts);
+    Ok(instruction)
+}
+
+/// Issue and redeem share the same account list
+fn build_vault_instruction(accounts: &IssueAccounts, data: Vec<u8>) -> Instruction {
+    Instruction {
         program_id: CARROT_PROGRAM_ID,
-        accounts,
+        accounts: accounts.to_account_metas(None),
         data,
-    })
+    }
 }
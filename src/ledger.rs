@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use solana_sdk::{signature::Signature, transaction::Transaction};
+
+use crate::{
+    error::{CarrotError, Result},
+    prepared::PreparedTransaction,
+    store::StateStore,
+};
+
+/// `StateStore` namespace of ledger entries, keyed by intent
+pub const LEDGER_NAMESPACE: &str = "ledger";
+
+/// A transaction broadcast (or about to be) for an intent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    /// Caller-chosen identifier of the operation (e.g. `"payroll-2024-06-01"`)
+    pub intent: String,
+    /// Signature the transaction lands under
+    pub signature: Signature,
+    /// Last block height at which the transaction can land
+    pub last_valid_block_height: u64,
+    /// Bincode-serialized signed transaction, for rebroadcasting
+    pub transaction: Vec<u8>,
+}
+
+impl LedgerEntry {
+    /// Entry for sending `prepared` on behalf of `intent`
+    pub fn new(intent: &str, prepared: &PreparedTransaction) -> Result<Self> {
+        validate_intent(intent)?;
+        let transaction = bincode::serialize(&prepared.transaction)
+            .map_err(|e| CarrotError::TransactionFailed(format!("failed to serialize transaction: {}", e)))?;
+        Ok(Self {
+            intent: intent.to_string(),
+            signature: prepared.signature(),
+            last_valid_block_height: prepared.last_valid_block_height,
+            transaction,
+        })
+    }
+
+    /// The recorded signed transaction
+    pub fn transaction(&self) -> Result<Transaction> {
+        bincode::deserialize(&self.transaction)
+            .map_err(|e| CarrotError::TransactionFailed(format!("invalid recorded transaction: {}", e)))
+    }
+}
+
+/// Signatures broadcast per intent, persisted to an append-only CSV file or a `StateStore`
+///
+/// Entries are written and synced to disk before the transaction is broadcast, so after a
+/// crash `CarrotClient::deposit_once` / `withdraw_once` can tell whether an intent's
+/// transaction may have landed instead of sending it again. The latest entry of an intent wins.
+#[derive(Debug, Default)]
+pub struct SignatureLedger {
+    path: Option<PathBuf>,
+    store: Option<Arc<dyn StateStore>>,
+    entries: Mutex<HashMap<String, LedgerEntry>>,
+}
+
+const HEADER: &str = "intent,signature,last_valid_block_height,transaction\n";
+
+impl SignatureLedger {
+    /// Ledger kept in memory only (lost on restart)
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Open the ledger at `path`, creating the file if it doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut entries = HashMap::new();
+        if path.exists() {
+            let contents = fs::read_to_string(path).map_err(|e| CarrotError::io("read", path, e))?;
+            for (line_number, line) in contents.lines().enumerate().skip(1) {
+                let entry = parse_line(line).ok_or_else(|| {
+                    CarrotError::InvalidConfig(format!("{}:{}: malformed ledger entry", path.display(), line_number + 1))
+                })?;
+                entries.insert(entry.intent.clone(), entry);
+            }
+        } else {
+            fs::write(path, HEADER).map_err(|e| CarrotError::io("write", path, e))?;
+        }
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            store: None,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Ledger kept in `store`, loading the entries already there
+    pub fn with_store(store: Arc<dyn StateStore>) -> Result<Self> {
+        let mut entries = HashMap::new();
+        for (intent, value) in store.scan(LEDGER_NAMESPACE)? {
+            let entry = std::str::from_utf8(&value)
+                .ok()
+                .and_then(parse_line)
+                .ok_or_else(|| CarrotError::InvalidConfig(format!("malformed ledger entry for {}", intent)))?;
+            entries.insert(entry.intent.clone(), entry);
+        }
+        Ok(Self {
+            path: None,
+            store: Some(store),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Latest entry recorded for `intent`
+    pub fn get(&self, intent: &str) -> Option<LedgerEntry> {
+        self.lock().get(intent).cloned()
+    }
+
+    /// Record `entry`, appending it to the file and syncing it to disk
+    pub fn record(&self, entry: LedgerEntry) -> Result<()> {
+        validate_intent(&entry.intent)?;
+        let mut entries = self.lock();
+        let line = format_line(&entry);
+        if let Some(path) = &self.path {
+            let mut file: File = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(|e| CarrotError::io("open", path, e))?;
+            file.write_all(format!("{}\n", line).as_bytes())
+                .and_then(|_| file.sync_data())
+                .map_err(|e| CarrotError::io("write", path, e))?;
+        }
+        if let Some(store) = &self.store {
+            store.put(LEDGER_NAMESPACE, &entry.intent, line.as_bytes())?;
+        }
+        entries.insert(entry.intent.clone(), entry);
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, LedgerEntry>> {
+        // Entries are only replaced whole, a panic elsewhere can't leave them inconsistent
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Intents are stored as a CSV field, so they can't contain separators
+pub(crate) fn validate_intent(intent: &str) -> Result<()> {
+    if intent.is_empty() || intent.contains([',', '\n', '\r']) {
+        return Err(CarrotError::InvalidConfig(format!(
+            "intent {:?} must be non-empty and free of commas and newlines",
+            intent
+        )));
+    }
+    Ok(())
+}
+
+fn format_line(entry: &LedgerEntry) -> String {
+    format!(
+        "{},{},{},{}",
+        entry.intent,
+        entry.signature,
+        entry.last_valid_block_height,
+        to_hex(&entry.transaction)
+    )
+}
+
+fn parse_line(line: &str) -> Option<LedgerEntry> {
+    let mut fields = line.split(',');
+    let entry = LedgerEntry {
+        intent: fields.next()?.to_string(),
+        signature: Signature::from_str(fields.next()?).ok()?,
+        last_valid_block_height: fields.next()?.parse().ok()?,
+        transaction: from_hex(fields.next()?)?,
+    };
+    fields.next().is_none().then_some(entry)
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{hash::Hash, message::Message, signature::Keypair, signer::Signer};
+
+    fn prepared() -> PreparedTransaction {
+        let payer = Keypair::new();
+        let message = Message::new(&[], Some(&payer.pubkey()));
+        PreparedTransaction {
+            transaction: Transaction::new(&[&payer], message, Hash::new_unique()),
+            last_valid_block_height: 42,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_ledger_persists_latest_entry() {
+        let path = std::env::temp_dir().join(format!("carrot-ledger-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let first = LedgerEntry::new("job-1", &prepared()).unwrap();
+        let retry = LedgerEntry::new("job-1", &prepared()).unwrap();
+        {
+            let ledger = SignatureLedger::open(&path).unwrap();
+            assert!(ledger.get("job-1").is_none());
+            ledger.record(first).unwrap();
+            ledger.record(retry.clone()).unwrap();
+        }
+
+        let reopened = SignatureLedger::open(&path).unwrap();
+        let entry = reopened.get("job-1").unwrap();
+        assert_eq!(entry, retry);
+        assert_eq!(entry.transaction().unwrap().signatures[0], retry.signature);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ledger_in_store() {
+        let store: Arc<dyn StateStore> = Arc::new(crate::store::MemoryStore::new());
+        let entry = LedgerEntry::new("job-2", &prepared()).unwrap();
+        SignatureLedger::with_store(Arc::clone(&store)).unwrap().record(entry.clone()).unwrap();
+        assert_eq!(SignatureLedger::with_store(store).unwrap().get("job-2"), Some(entry));
+    }
+
+    #[test]
+    fn test_invalid_intents_rejected() {
+        assert!(LedgerEntry::new("a,b", &prepared()).is_err());
+        assert!(LedgerEntry::new("", &prepared()).is_err());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(from_hex(&to_hex(&[0, 15, 255])).unwrap(), vec![0, 15, 255]);
+        assert!(from_hex("abc").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+}
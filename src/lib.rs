@@ -1,13 +1,182 @@
+// Caches, the circuit breaker, event bus and history decoding serve the blocking client; the async
+// client alone leaves parts of them unused
+#![cfg_attr(not(feature = "blocking"), allow(dead_code))]
+
 use borsh::{BorshDeserialize, BorshSerialize};
+use serde::Serialize;
 use solana_sdk::pubkey::Pubkey;
 
+pub mod account_size;
+pub mod accounting;
 pub mod accounts;
+pub mod alerts;
+pub mod allowlist;
+pub mod anomaly;
+pub mod approval;
+pub mod authority;
+pub mod blockhash;
+pub mod broadcast;
+pub mod cache;
+pub mod candles;
+pub mod circuit_breaker;
+pub mod clock;
+pub mod dedup;
+pub mod digest;
+pub mod discriminators;
+pub mod display;
+pub mod effects;
 pub mod error;
+pub mod events;
+pub mod fees;
+pub mod history;
+pub mod holders;
+pub mod indexer;
+pub mod inspect;
 pub mod instructions;
+pub mod ledger;
+pub mod limits;
+pub mod liquidity;
+pub mod memo;
+pub mod merge;
+pub mod metadata;
+pub mod nav;
+pub mod offline;
+pub mod packing;
+#[cfg(feature = "blocking")]
+pub mod paper;
+pub mod oracle;
+pub mod pause_history;
+pub mod policy;
+pub mod positions;
 pub mod client;
+pub mod compute_units;
+pub mod config;
+pub mod prepared;
+pub mod priority_fee;
+pub mod proof;
+#[cfg(feature = "blocking")]
+pub mod pubsub;
+pub mod quote;
+pub mod receipt;
+pub mod relayer;
+#[cfg(feature = "blocking")]
+pub mod retry;
+#[cfg(feature = "sandbox")]
+pub mod sandbox;
+pub mod schema;
+pub mod sink;
+pub mod snapshot;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "blocking")]
+pub mod subscription;
+pub mod store;
+#[cfg(feature = "blocking")]
+pub mod supervisor;
+pub mod telemetry;
+#[cfg(feature = "blocking")]
+pub mod tenant;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod token;
+pub mod transfer_hook;
+pub mod twap;
+pub mod validate;
+pub mod vault_info;
+pub mod volume;
+pub mod webhook;
+pub mod writability;
 
-pub use error::CarrotError;
+pub use account_size::{VaultAccountSize, VaultSizeMonitor};
+pub use accounting::{ChartOfAccounts, ExecutionLeg, ExecutionReport, Journal, JournalEntry};
+pub use alerts::{Alert, AlertKind, AlertSink, FeeKind, Severity, VaultChangeDetector};
+#[cfg(feature = "blocking")]
+pub use alerts::{WebhookSink, WebhookTarget};
+pub use allowlist::ProgramAllowlist;
+pub use anomaly::{AnomalyDetector, AnomalyThresholds, VaultMetrics};
+pub use approval::{Approval, PendingOperation};
+pub use authority::{Authorities, AuthorityMonitor, AuthorityReport};
+pub use blockhash::{BlockhashSource, FixedBlockhash};
+pub use broadcast::{Broadcaster, CascadeBroadcaster, HeliusSender, RpcBroadcaster};
+pub use candles::PriceCandle;
+pub use circuit_breaker::CircuitBreakerPolicy;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use dedup::{Deduplicator, EventKey};
+pub use digest::{DigestPeriod, PositionDigest};
+pub use display::DisplayRounding;
+pub use effects::{BalanceEffects, ExpectedEffects};
+pub use error::{CarrotError, ErrorContext, Operation};
+pub use history::{ActivityCursor, HistoryEntry};
+pub use holders::CrtHolder;
+pub use indexer::{StrategyIndexer, YieldPoint};
+pub use events::ClientEvent;
+pub use fees::{FeeAccruals, PerformanceFeeSimulation};
+pub use client::TokenBalance;
+#[cfg(feature = "blocking")]
 pub use client::{deposit_usdc, withdraw_crt, CarrotClient};
+#[cfg(feature = "async")]
+pub use client::AsyncCarrotClient;
+pub use compute_units::{ComputeUnitLimit, DepositOptions};
+pub use config::ClientConfig;
+pub use inspect::{inspect, TransactionInspection};
+pub use instructions::{AssetOracleAccounts, CarrotInstruction, RemainingAccountsResolver, StaticAccounts};
+pub use ledger::{LedgerEntry, SignatureLedger};
+pub use limits::{DepositLimits, VaultLimits};
+pub use memo::{MemoAction, SdkMemo};
+pub use merge::{EventPosition, StreamMerger};
+pub use liquidity::{ExitOption, ExitRanking, RedemptionLeg, RedemptionPlan, WithdrawalLiquidity};
+pub use metadata::{MetadataSource, MintMetadata};
+pub use nav::{AssetAmount, CrtAmount, Nav, OracleIssue, PartialNav, Rounding};
+pub use offline::OfflineBuilder;
+pub use packing::{PackedTransaction, Packer};
+#[cfg(feature = "blocking")]
+pub use paper::{PaperFill, PaperTrader, VaultTrader};
+pub use pause_history::{PauseChange, PauseHistory, PausePeriod};
+pub use policy::{DepositExposure, Policy, PolicyAction, PolicyUsage};
+pub use positions::{DriftSpotPosition, KTokenPosition, PositionDecoder, StrategyPosition, StrategyReconciliation};
+pub use prepared::PreparedTransaction;
+pub use priority_fee::{PriorityFeeEstimator, PriorityFeePolicy};
+pub use proof::{verify_chain, ProvenAccount, ProvenFigures, RawAccount, SnapshotProof};
+pub use quote::{Quote, QuoteCosts, QuoteSide};
+pub use receipt::{Receipt, SignedReceipt};
+pub use relayer::{RelayerFee, RelayerPolicy};
+#[cfg(feature = "blocking")]
+pub use retry::PinnedRetry;
+#[cfg(feature = "sandbox")]
+pub use sandbox::{Sandbox, SandboxOutcome};
+pub use schema::{IssueEvent, RedeemEvent, VaultEvent, VaultSnapshotRecord};
+pub use sink::{Encoding, EventSink, Publisher};
+#[cfg(feature = "kafka")]
+pub use sink::KafkaPublisher;
+#[cfg(feature = "nats")]
+pub use sink::NatsPublisher;
+pub use snapshot::{AssetSnapshot, VaultSnapshot};
+pub use stats::ProtocolStats;
+pub use stream::{BoundedStream, StreamMetrics};
+#[cfg(feature = "blocking")]
+pub use subscription::ActivitySubscription;
+pub use store::{MemoryStore, StateStore};
+#[cfg(feature = "sled")]
+pub use store::SledStore;
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+#[cfg(feature = "blocking")]
+pub use supervisor::{Backoff, Jitter, SeededJitter, ShutdownSignal, TaskGroup, TaskStatus};
+pub use telemetry::{Span, TelemetryBatch, TelemetryExporter, TelemetryRecorder};
+#[cfg(feature = "otlp")]
+pub use telemetry::OtlpExporter;
+#[cfg(feature = "blocking")]
+pub use tenant::{RateLimit, Tenant, TenantConfig, TenantManager, TenantMetrics};
+pub use token::TokenProgram;
+pub use transfer_hook::HookedTransfer;
+pub use twap::{SharePriceHistory, SharePriceSample, Twap};
+pub use validate::{ValidationCheck, ValidationReport};
+pub use vault_info::{AssetInfo, StrategyInfo, VaultInfo};
+pub use volume::{RollingVolume, VolumeMetrics};
+#[cfg(feature = "blocking")]
+pub use webhook::WebhookDispatcher;
+pub use webhook::{WebhookEndpoint, WebhookEvent, WebhookEventType};
+pub use writability::{UnwrittenAccount, WritabilityAudit};
 
 /// Carrot Protocol Program ID
 pub const CARROT_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("CarrotwivhMpDnm27EHmRLeQ683Z1PufuqEmBZvD282s");
@@ -31,7 +200,7 @@ pub const LOG_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("7Mc3vSdRWoThArpni6t5W4Xj
 pub const VAULT_ADDRESS: Pubkey = solana_sdk::pubkey!("FfCRL34rkJiMiX5emNDrYp3MdWH2mES3FvDQyFppqgpJ");
 
 /// Fee structure within vault
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize, Serialize)]
 pub struct Fee {
     pub redemption_fee_bps: u16,
     pub redemption_fee_accumulated: u64,
@@ -56,6 +225,20 @@ pub struct Asset {
     pub oracle: Pubkey,
 }
 
+impl Asset {
+    /// Token program of the asset, recognized from the vault's token account for it
+    ///
+    /// The vault holds each asset in its associated token account, whose address depends on
+    /// the token program, so assets added on-chain are handled without an SDK release.
+    /// Falls back to `TokenProgram::for_mint` if the account isn't an ATA of `vault`.
+    pub fn token_program(&self, vault: &Pubkey) -> TokenProgram {
+        [TokenProgram::Token, TokenProgram::Token2022]
+            .into_iter()
+            .find(|program| program.associated_token_address(vault, &self.mint) == self.ata)
+            .unwrap_or_else(|| TokenProgram::for_mint(&self.mint))
+    }
+}
+
 /// Strategy record within vault
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct StrategyRecord {
@@ -65,6 +248,9 @@ pub struct StrategyRecord {
     pub net_earnings: i64,
 }
 
+/// Anchor account discriminator of `Vault`: sha256("account:Vault")[..8]
+pub const VAULT_DISCRIMINATOR: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+
 /// Vault account structure (matches on-chain IDL)
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct Vault {
@@ -80,13 +266,40 @@ pub struct Vault {
     pub asset_index: u16,
     /// Current strategy index
     pub strategy_index: u16,
-    /// List of assets (USDC, USDT, pyUSD)
+    /// Accepted deposit assets (USDC, USDT, pyUSD, plus any the authority adds later)
     pub assets: Vec<Asset>,
     /// List of strategy records
     pub strategies: Vec<StrategyRecord>,
 }
 
 impl Vault {
+    /// Decode vault account data as stored on-chain (8-byte Anchor discriminator + Borsh body)
+    ///
+    /// Needs no client, so indexers can decode raw account updates (e.g. from Geyser) directly.
+    /// Fails with `InvalidAccountDiscriminator` if the data doesn't belong to a Vault account
+    /// and with `InvalidVaultData` if it is truncated or malformed; never panics.
+    pub fn from_account_data(data: &[u8]) -> error::Result<Self> {
+        let (discriminator, body) = data.split_at_checked(8).ok_or(CarrotError::InvalidVaultData)?;
+        if discriminator != VAULT_DISCRIMINATOR {
+            return Err(CarrotError::InvalidAccountDiscriminator {
+                expected: VAULT_DISCRIMINATOR,
+                found: discriminator.try_into().unwrap_or_default(),
+            });
+        }
+        Vault::try_from_slice(body).map_err(|e| {
+            log::debug!("Failed to deserialize vault data: {:?}", e);
+            CarrotError::InvalidVaultData
+        })
+    }
+
+    /// Encode the vault as stored on-chain, the inverse of `from_account_data`
+    pub fn to_account_data(&self) -> Vec<u8> {
+        let mut data = VAULT_DISCRIMINATOR.to_vec();
+        // Writing into a Vec can't fail
+        data.extend(borsh::to_vec(self).expect("vault serializes"));
+        data
+    }
+
     /// Get all asset ATAs and oracles as remaining accounts
     pub fn get_remaining_accounts(&self) -> Vec<Pubkey> {
         self.assets
@@ -96,6 +309,39 @@ impl Vault {
     }
 }
 
+/// A vault's address together with its decoded state
+///
+/// Instruction builders take a handle instead of reading the mainnet constants, so pre-fetched
+/// vault state can be reused and other vaults targeted.
+#[derive(Debug, Clone)]
+pub struct VaultHandle {
+    /// Vault account address
+    pub address: Pubkey,
+    /// Decoded vault state
+    pub vault: Vault,
+}
+
+impl VaultHandle {
+    /// Pair a vault's state with its address
+    pub fn new(address: Pubkey, vault: Vault) -> Self {
+        Self { address, vault }
+    }
+
+    /// Configuration of the vault asset with the given mint
+    pub fn asset(&self, mint: &Pubkey) -> error::Result<&Asset> {
+        self.vault
+            .assets
+            .iter()
+            .find(|asset| &asset.mint == mint)
+            .ok_or_else(|| CarrotError::InvalidAsset(mint.to_string()))
+    }
+
+    /// Token program of the vault asset with the given mint (see `Asset::token_program`)
+    pub fn asset_token_program(&self, mint: &Pubkey) -> error::Result<TokenProgram> {
+        Ok(self.asset(mint)?.token_program(&self.address))
+    }
+}
+
 /// Arguments for issue (deposit) instruction
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct IssueArgs {
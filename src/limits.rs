@@ -0,0 +1,108 @@
+use crate::error::{CarrotError, Result};
+
+/// Deposit bounds enforced before building a deposit, in USD (`VALUE_DECIMALS`)
+///
+/// The on-chain vault only has a pause flag; it stores no cap or minimum, so these bounds are
+/// set by the integrator through `ClientConfig::with_min_deposit_value` / `with_max_total_value`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DepositLimits {
+    /// Smallest accepted deposit
+    pub min_deposit_value: Option<u128>,
+    /// Largest vault value (TVL) a deposit may bring the vault to
+    pub max_total_value: Option<u128>,
+}
+
+impl DepositLimits {
+    /// Whether any bound is set
+    pub fn is_set(&self) -> bool {
+        self.min_deposit_value.is_some() || self.max_total_value.is_some()
+    }
+}
+
+/// Current deposit limits of the vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VaultLimits {
+    /// Whether the vault is paused (deposits are rejected on-chain)
+    pub paused: bool,
+    /// Vault value, net of the pending management fee (USD, `VALUE_DECIMALS`)
+    pub total_value: u128,
+    /// Configured bounds
+    pub limits: DepositLimits,
+}
+
+impl VaultLimits {
+    /// Value that can still be deposited before reaching `max_total_value` (`None` if uncapped)
+    pub fn remaining_capacity(&self) -> Option<u128> {
+        self.limits
+            .max_total_value
+            .map(|max| max.saturating_sub(self.total_value))
+    }
+
+    /// Fail if a deposit worth `value` would be rejected
+    pub fn check_deposit(&self, value: u128) -> Result<()> {
+        if self.paused {
+            return Err(CarrotError::VaultPaused);
+        }
+        if let Some(minimum) = self.limits.min_deposit_value {
+            if value < minimum {
+                return Err(CarrotError::DepositBelowMinimum { value, minimum });
+            }
+        }
+        if let Some(remaining) = self.remaining_capacity() {
+            if value > remaining {
+                return Err(CarrotError::DepositExceedsCapacity { value, remaining });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USD: u128 = 1_000_000_000_000;
+
+    fn limits(paused: bool, min: Option<u128>, max: Option<u128>) -> VaultLimits {
+        VaultLimits {
+            paused,
+            total_value: 900 * USD,
+            limits: DepositLimits {
+                min_deposit_value: min,
+                max_total_value: max,
+            },
+        }
+    }
+
+    #[test]
+    fn test_unbounded_vault_accepts_any_deposit() {
+        let limits = limits(false, None, None);
+        assert_eq!(limits.remaining_capacity(), None);
+        assert!(limits.check_deposit(1).is_ok());
+        assert!(limits.check_deposit(u128::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let limits = limits(false, Some(10 * USD), Some(1_000 * USD));
+        assert_eq!(limits.remaining_capacity(), Some(100 * USD));
+        assert!(limits.check_deposit(10 * USD).is_ok());
+        assert!(limits.check_deposit(100 * USD).is_ok());
+        assert!(matches!(
+            limits.check_deposit(9 * USD),
+            Err(CarrotError::DepositBelowMinimum { minimum, .. }) if minimum == 10 * USD
+        ));
+        assert!(matches!(
+            limits.check_deposit(101 * USD),
+            Err(CarrotError::DepositExceedsCapacity { remaining, .. }) if remaining == 100 * USD
+        ));
+    }
+
+    #[test]
+    fn test_paused_vault_rejects_deposits() {
+        assert!(matches!(
+            limits(true, None, None).check_deposit(USD),
+            Err(CarrotError::VaultPaused)
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    error::{CarrotError, Result},
+    nav::{deduct_fee_bps, CrtAmount, Nav, Rounding},
+};
+
+/// Whether the vault's idle balance of an asset covers a redemption
+///
+/// Redemptions are paid from the vault's token account; funds deployed into strategies can't
+/// be paid out, so a redemption larger than the idle balance fails on-chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawalLiquidity {
+    /// Asset the redemption pays out in
+    pub asset_mint: Pubkey,
+    /// CRT being redeemed
+    pub shares: u64,
+    /// Expected payout, net of the redemption fee
+    pub amount_out: u64,
+    /// Idle balance held in the vault's token account
+    pub available: u64,
+    /// Largest redemption (CRT) the idle balance covers, ignoring the fee
+    pub max_shares: u64,
+}
+
+impl WithdrawalLiquidity {
+    /// Check redeeming `shares` for `asset_mint` against the idle balance recorded in `nav`
+    pub fn from_nav(nav: &Nav, redemption_fee_bps: u16, asset_mint: &Pubkey, shares: u64) -> Result<Self> {
+        let valuation = nav.asset(asset_mint)?;
+        let gross = CrtAmount(shares).to_asset_value(nav, asset_mint, Rounding::Down)?.amount;
+        let available = valuation.vault_balance;
+        let available_value = valuation.value_of(available, Rounding::Down)?;
+        Ok(Self {
+            asset_mint: *asset_mint,
+            shares,
+            amount_out: deduct_fee_bps(gross, redemption_fee_bps)?,
+            available,
+            max_shares: nav.shares_for_value(available_value, Rounding::Down)?,
+        })
+    }
+
+    /// Whether the idle balance covers the payout
+    pub fn is_sufficient(&self) -> bool {
+        self.amount_out <= self.available
+    }
+
+    /// Fail with `InsufficientLiquidity`, suggesting `max_shares`, if the payout isn't covered
+    pub fn ensure_sufficient(&self) -> Result<()> {
+        if self.is_sufficient() {
+            return Ok(());
+        }
+        Err(CarrotError::InsufficientLiquidity {
+            asset: self.asset_mint,
+            required: self.amount_out,
+            available: self.available,
+            max_shares: self.max_shares,
+        })
+    }
+}
+
+/// One redemption of a `RedemptionPlan`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedemptionLeg {
+    /// Asset paid out
+    pub asset_mint: Pubkey,
+    /// CRT redeemed for this asset
+    pub shares: u64,
+    /// Expected payout, net of the redemption fee
+    pub amount_out: u64,
+}
+
+/// A redemption split across vault assets so every leg is covered by idle liquidity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedemptionPlan {
+    /// Redemptions, largest idle balance first
+    pub legs: Vec<RedemptionLeg>,
+}
+
+impl RedemptionPlan {
+    /// Split redeeming `shares` across the vault's assets
+    ///
+    /// Assets are drawn from in order of idle value, so a single asset is used whenever one
+    /// can cover the whole amount. Fails with `RedemptionExceedsLiquidity` if all idle funds
+    /// together fall short.
+    pub fn from_nav(nav: &Nav, redemption_fee_bps: u16, shares: u64) -> Result<Self> {
+        let mut assets = nav
+            .assets
+            .iter()
+            .map(|asset| Ok((asset.value_of(asset.vault_balance, Rounding::Down)?, asset.mint)))
+            .collect::<Result<Vec<_>>>()?;
+        assets.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+        let order: Vec<Pubkey> = assets.into_iter().map(|(_, mint)| mint).collect();
+        Self::from_nav_preferring(nav, redemption_fee_bps, shares, &order)
+    }
+
+    /// Split redeeming `shares` across `preferred` assets, drawing from each in order
+    ///
+    /// With `ExitRanking::preference` as the order, the cheapest asset that covers the whole
+    /// amount is used alone. Fails like `from_nav` if their idle funds together fall short.
+    pub fn from_nav_preferring(nav: &Nav, redemption_fee_bps: u16, shares: u64, preferred: &[Pubkey]) -> Result<Self> {
+        let mut legs = Vec::new();
+        let mut remaining = shares;
+        for mint in preferred {
+            if remaining == 0 {
+                break;
+            }
+            let liquidity = WithdrawalLiquidity::from_nav(nav, redemption_fee_bps, mint, remaining)?;
+            let leg = if liquidity.is_sufficient() {
+                liquidity
+            } else if liquidity.max_shares > 0 {
+                WithdrawalLiquidity::from_nav(nav, redemption_fee_bps, mint, liquidity.max_shares)?
+            } else {
+                continue;
+            };
+            remaining -= leg.shares;
+            legs.push(RedemptionLeg {
+                asset_mint: *mint,
+                shares: leg.shares,
+                amount_out: leg.amount_out,
+            });
+        }
+
+        if remaining > 0 {
+            return Err(CarrotError::RedemptionExceedsLiquidity {
+                shares,
+                max_shares: shares - remaining,
+            });
+        }
+        Ok(Self { legs })
+    }
+
+    /// CRT redeemed across all legs
+    pub fn total_shares(&self) -> u64 {
+        self.legs.iter().map(|leg| leg.shares).sum()
+    }
+}
+
+/// What redeeming for one asset leaves the user with, from `ExitRanking`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitOption {
+    pub asset_mint: Pubkey,
+    /// Payout net of the redemption fee
+    pub amount_out: u64,
+    /// Withheld from the payout by a Token-2022 transfer fee
+    pub transfer_fee: u64,
+    /// Expected loss swapping the payout downstream (`ClientConfig::exit_swap_costs`)
+    pub swap_cost: u64,
+    /// Left after the transfer fee and swap: what the user ends up with
+    pub net_amount: u64,
+    /// USD value (`VALUE_DECIMALS`) of `net_amount` at the oracle price
+    pub net_value: u128,
+    /// Whether the vault's idle balance covers the payout
+    pub sufficient: bool,
+}
+
+/// Exit assets for a redemption, best first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitRanking {
+    /// CRT being redeemed
+    pub shares: u64,
+    /// Assets whose idle balance covers the payout first, then by `net_value`, highest first
+    pub options: Vec<ExitOption>,
+}
+
+impl ExitRanking {
+    /// Rank redeeming `shares` for each asset in `nav`
+    ///
+    /// `transfer_fee` returns the fee withheld from a payout of an amount of an asset, and
+    /// `swap_costs_bps` the share of the payout lost swapping each asset downstream.
+    pub fn from_nav<F>(
+        nav: &Nav,
+        redemption_fee_bps: u16,
+        shares: u64,
+        mut transfer_fee: F,
+        swap_costs_bps: &HashMap<Pubkey, u16>,
+    ) -> Result<Self>
+    where
+        F: FnMut(&Pubkey, u64) -> Result<u64>,
+    {
+        let mut options = nav
+            .assets
+            .iter()
+            .map(|asset| {
+                let liquidity = WithdrawalLiquidity::from_nav(nav, redemption_fee_bps, &asset.mint, shares)?;
+                let amount_out = liquidity.amount_out;
+                let fee = transfer_fee(&asset.mint, amount_out)?.min(amount_out);
+                let received = amount_out - fee;
+                let swap_bps = swap_costs_bps.get(&asset.mint).copied().unwrap_or(0);
+                let net_amount = deduct_fee_bps(received, swap_bps)?;
+                Ok(ExitOption {
+                    asset_mint: asset.mint,
+                    amount_out,
+                    transfer_fee: fee,
+                    swap_cost: received - net_amount,
+                    net_amount,
+                    net_value: asset.value_of(net_amount, Rounding::Down)?,
+                    sufficient: liquidity.is_sufficient(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        options.sort_by_key(|option| (std::cmp::Reverse(option.sufficient), std::cmp::Reverse(option.net_value)));
+        Ok(Self { shares, options })
+    }
+
+    /// The highest-ranked asset whose idle balance covers the whole payout
+    pub fn best(&self) -> Option<&ExitOption> {
+        self.options.first().filter(|option| option.sufficient)
+    }
+
+    /// Assets in ranked order, for `RedemptionPlan::from_nav_preferring`
+    pub fn preference(&self) -> Vec<Pubkey> {
+        self.options.iter().map(|option| option.asset_mint).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nav::tests::test_nav;
+
+    #[test]
+    fn test_liquidity_covers_payout() {
+        let usdc = Pubkey::new_unique();
+        // 100 USDC idle, 1:1 share price (1 CRT = 10^9 base units per USD)
+        let mut nav = test_nav(1_000_000_000_000, vec![(usdc, 100_000_000, 100_000_000)]);
+        nav.assets[0].strategy_balance = 900_000_000;
+
+        let ok = WithdrawalLiquidity::from_nav(&nav, 0, &usdc, 50_000_000_000).unwrap();
+        assert_eq!(ok.amount_out, 50_000_000);
+        assert!(ok.ensure_sufficient().is_ok());
+
+        let short = WithdrawalLiquidity::from_nav(&nav, 0, &usdc, 200_000_000_000).unwrap();
+        assert!(!short.is_sufficient());
+        assert_eq!(short.max_shares, 100_000_000_000);
+        assert!(matches!(
+            short.ensure_sufficient(),
+            Err(CarrotError::InsufficientLiquidity { required: 200_000_000, available: 100_000_000, .. })
+        ));
+
+        // The suggested amount is covered
+        let suggested = WithdrawalLiquidity::from_nav(&nav, 0, &usdc, short.max_shares).unwrap();
+        assert!(suggested.is_sufficient());
+    }
+
+    #[test]
+    fn test_plan_prefers_single_asset() {
+        let (usdc, usdt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let nav = test_nav(
+            1_000_000_000_000,
+            vec![(usdc, 300_000_000, 100_000_000), (usdt, 700_000_000, 100_000_000)],
+        );
+
+        let plan = RedemptionPlan::from_nav(&nav, 0, 500_000_000_000).unwrap();
+        assert_eq!(
+            plan.legs,
+            vec![RedemptionLeg {
+                asset_mint: usdt,
+                shares: 500_000_000_000,
+                amount_out: 500_000_000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plan_splits_across_assets() {
+        let (usdc, usdt) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let nav = test_nav(
+            1_000_000_000_000,
+            vec![(usdc, 300_000_000, 100_000_000), (usdt, 700_000_000, 100_000_000)],
+        );
+
+        let plan = RedemptionPlan::from_nav(&nav, 0, 900_000_000_000).unwrap();
+        assert_eq!(plan.total_shares(), 900_000_000_000);
+        assert_eq!(plan.legs.len(), 2);
+        assert_eq!((plan.legs[0].asset_mint, plan.legs[0].amount_out), (usdt, 700_000_000));
+        assert_eq!((plan.legs[1].asset_mint, plan.legs[1].amount_out), (usdc, 200_000_000));
+
+        assert!(matches!(
+            RedemptionPlan::from_nav(&nav, 0, 1_001_000_000_000),
+            Err(CarrotError::RedemptionExceedsLiquidity { max_shares: 1_000_000_000_000, .. })
+        ));
+    }
+
+    #[test]
+    fn test_exit_ranking() {
+        let (usdc, usdt, pyusd) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let nav = test_nav(
+            1_000_000_000_000,
+            vec![
+                (usdc, 300_000_000, 100_000_000),
+                (usdt, 600_000_000, 100_000_000),
+                (pyusd, 100_000_000, 100_000_000),
+            ],
+        );
+        // 0.5% transfer fee on pyUSD, 0.3% to swap USDT downstream
+        let transfer_fee = |mint: &Pubkey, amount: u64| Ok(if *mint == pyusd { amount / 200 } else { 0 });
+        let swap_costs = HashMap::from([(usdt, 30)]);
+
+        let ranking = ExitRanking::from_nav(&nav, 0, 100_000_000_000, transfer_fee, &swap_costs).unwrap();
+        let order: Vec<(Pubkey, u64)> = ranking.options.iter().map(|o| (o.asset_mint, o.net_amount)).collect();
+        assert_eq!(order, vec![(usdc, 100_000_000), (usdt, 99_700_000), (pyusd, 99_500_000)]);
+        assert_eq!(ranking.best().unwrap().asset_mint, usdc);
+        assert_eq!(ranking.options[1].swap_cost, 300_000);
+
+        // USDC can't cover 400 USD: USDT is the cheapest that can, and leads a split plan
+        let ranking = ExitRanking::from_nav(&nav, 0, 400_000_000_000, transfer_fee, &swap_costs).unwrap();
+        assert_eq!(ranking.best().unwrap().asset_mint, usdt);
+        let plan = RedemptionPlan::from_nav_preferring(&nav, 0, 400_000_000_000, &ranking.preference()).unwrap();
+        assert_eq!(plan.legs.len(), 1);
+        assert_eq!(plan.legs[0].asset_mint, usdt);
+
+        let ranking = ExitRanking::from_nav(&nav, 0, 700_000_000_000, transfer_fee, &swap_costs).unwrap();
+        assert!(ranking.best().is_none());
+        let plan = RedemptionPlan::from_nav_preferring(&nav, 0, 700_000_000_000, &ranking.preference()).unwrap();
+        assert_eq!(plan.legs[0].asset_mint, usdc);
+        assert_eq!(plan.total_shares(), 700_000_000_000);
+    }
+}
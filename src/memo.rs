@@ -0,0 +1,241 @@
+//! Versioned memo identifying transactions built by the SDK
+//!
+//! The memo is one line, `carrot:<version>|<action>|<sdk version>|<integrator id>|<correlation
+//! id>`, with empty fields for unset IDs. Later versions only append fields, so a parser reads
+//! the fields it knows from a memo of any version.
+
+use std::fmt;
+
+use solana_sdk::{instruction::Instruction, message::VersionedMessage};
+#[cfg(feature = "blocking")]
+use solana_sdk::signature::Signature;
+
+use crate::{
+    allowlist::{MEMO_PROGRAM_ID, MEMO_V1_PROGRAM_ID},
+    error::{CarrotError, Result},
+    instructions::{decode_instruction, CarrotInstruction},
+    CARROT_PROGRAM_ID,
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Tag starting every SDK memo
+pub const SDK_MEMO_TAG: &str = "carrot:";
+
+/// Memo schema version written by this SDK
+pub const SDK_MEMO_VERSION: u8 = 1;
+
+/// Longest integrator or correlation ID accepted
+pub const MAX_MEMO_ID_LEN: usize = 64;
+
+const SEPARATOR: char = '|';
+
+/// What a transaction does, as recorded in its memo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoAction {
+    Deposit,
+    Withdraw,
+    /// Anything else, or several Carrot instructions of different kinds
+    Other,
+}
+
+impl MemoAction {
+    /// Action of a transaction made of `instructions`
+    pub fn of(instructions: &[Instruction]) -> Self {
+        let mut actions = instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == CARROT_PROGRAM_ID)
+            .map(|instruction| {
+                let accounts: Vec<_> = instruction.accounts.iter().map(|meta| meta.pubkey).collect();
+                match decode_instruction(&instruction.data, &accounts) {
+                    Some(CarrotInstruction::Issue { .. }) => MemoAction::Deposit,
+                    Some(CarrotInstruction::Redeem { .. }) => MemoAction::Withdraw,
+                    None => MemoAction::Other,
+                }
+            });
+        match actions.next() {
+            Some(first) if actions.all(|action| action == first) => first,
+            _ => MemoAction::Other,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            MemoAction::Deposit => "deposit",
+            MemoAction::Withdraw => "withdraw",
+            MemoAction::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for MemoAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Memo payload attached to SDK-built transactions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdkMemo {
+    /// Schema version the memo was written with
+    pub version: u8,
+    pub action: MemoAction,
+    /// Version of the SDK that built the transaction
+    pub client_version: String,
+    /// Who integrated the SDK (e.g. a wallet or exchange)
+    pub integrator_id: Option<String>,
+    /// ID tying the transaction to the request that caused it
+    pub correlation_id: Option<String>,
+}
+
+impl SdkMemo {
+    /// Memo for `action` from this SDK version
+    pub fn new(action: MemoAction) -> Self {
+        Self {
+            version: SDK_MEMO_VERSION,
+            action,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            integrator_id: None,
+            correlation_id: None,
+        }
+    }
+
+    pub fn with_integrator_id(mut self, integrator_id: impl Into<String>) -> Self {
+        self.integrator_id = Some(integrator_id.into());
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Memo text, failing for IDs that are too long or contain `|` or a line break
+    pub fn encode(&self) -> Result<String> {
+        for id in [&self.integrator_id, &self.correlation_id].into_iter().flatten() {
+            if id.len() > MAX_MEMO_ID_LEN || id.contains([SEPARATOR, '\n', '\r']) {
+                return Err(CarrotError::InvalidConfig(format!(
+                    "memo ID {:?} must be at most {} bytes without '|' or line breaks",
+                    id, MAX_MEMO_ID_LEN
+                )));
+            }
+        }
+        Ok(self.to_string())
+    }
+
+    /// Memo instruction carrying this payload
+    pub fn instruction(&self) -> Result<Instruction> {
+        Ok(Instruction::new_with_bytes(MEMO_PROGRAM_ID, self.encode()?.as_bytes(), vec![]))
+    }
+
+    /// Parse memo text written by any version of the SDK
+    pub fn parse(memo: &str) -> Result<Self> {
+        let invalid = |reason: &str| CarrotError::InvalidMemo(format!("{}: {:?}", reason, memo));
+        let fields = memo.strip_prefix(SDK_MEMO_TAG).ok_or_else(|| invalid("not an SDK memo"))?;
+        let mut fields = fields.split(SEPARATOR);
+        let version: u8 = fields
+            .next()
+            .and_then(|version| version.parse().ok())
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| invalid("invalid version"))?;
+        // Every version has at least the version 1 fields
+        let (Some(action), Some(client_version), Some(integrator_id), Some(correlation_id)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid("missing fields"));
+        };
+        let action = match action {
+            "deposit" => MemoAction::Deposit,
+            "withdraw" => MemoAction::Withdraw,
+            // Actions added by later versions
+            _ => MemoAction::Other,
+        };
+        let id = |field: &str| (!field.is_empty()).then(|| field.to_string());
+        Ok(Self {
+            version,
+            action,
+            client_version: client_version.to_string(),
+            integrator_id: id(integrator_id),
+            correlation_id: id(correlation_id),
+        })
+    }
+
+    /// The SDK memo among `message`'s top-level memo instructions, if any
+    pub fn from_message(message: &VersionedMessage) -> Option<Self> {
+        let keys = message.static_account_keys();
+        message
+            .instructions()
+            .iter()
+            .filter(|instruction| {
+                keys.get(instruction.program_id_index as usize)
+                    .is_some_and(|program| *program == MEMO_PROGRAM_ID || *program == MEMO_V1_PROGRAM_ID)
+            })
+            .find_map(|instruction| Self::parse(std::str::from_utf8(&instruction.data).ok()?).ok())
+    }
+}
+
+impl fmt::Display for SdkMemo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}|{}|{}|{}|{}",
+            SDK_MEMO_TAG,
+            self.version,
+            self.action,
+            self.client_version,
+            self.integrator_id.as_deref().unwrap_or_default(),
+            self.correlation_id.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// SDK memo of a confirmed transaction (`None` if it wasn't built by the SDK with one)
+    pub fn fetch_sdk_memo(&self, signature: &Signature) -> Result<Option<SdkMemo>> {
+        let fetched = self.fetch_carrot_transaction(signature)?;
+        Ok(SdkMemo::from_message(&fetched.transaction.message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::usdc_vault;
+    use crate::instructions::{build_issue_instruction, build_redeem_instruction};
+    use crate::USDC_MINT;
+    use solana_sdk::{message::Message, pubkey::Pubkey};
+
+    #[test]
+    fn test_round_trip_and_versions() {
+        let memo = SdkMemo::new(MemoAction::Deposit).with_integrator_id("acme-wallet").with_correlation_id("order-42");
+        let text = memo.encode().unwrap();
+        assert_eq!(text, format!("carrot:1|deposit|{}|acme-wallet|order-42", env!("CARGO_PKG_VERSION")));
+        assert_eq!(SdkMemo::parse(&text).unwrap(), memo);
+
+        let bare = SdkMemo::parse("carrot:1|withdraw|0.1.0||").unwrap();
+        assert_eq!((bare.integrator_id, bare.correlation_id), (None, None));
+        // A later version with an extra field and a new action still parses
+        let future = SdkMemo::parse("carrot:3|rebalance|0.9.0|acme||extra").unwrap();
+        assert_eq!((future.version, future.action), (3, MemoAction::Other));
+
+        assert!(matches!(SdkMemo::parse("hello"), Err(CarrotError::InvalidMemo(_))));
+        assert!(matches!(SdkMemo::parse("carrot:1|deposit"), Err(CarrotError::InvalidMemo(_))));
+        assert!(SdkMemo::new(MemoAction::Other).with_correlation_id("a|b").encode().is_err());
+    }
+
+    #[test]
+    fn test_action_and_lookup() {
+        let user = Pubkey::new_unique();
+        let vault = usdc_vault();
+        let issue = build_issue_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+        let redeem = build_redeem_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+        assert_eq!(MemoAction::of(std::slice::from_ref(&issue)), MemoAction::Deposit);
+        assert_eq!(MemoAction::of(std::slice::from_ref(&redeem)), MemoAction::Withdraw);
+        assert_eq!(MemoAction::of(&[issue.clone(), redeem]), MemoAction::Other);
+
+        let memo = SdkMemo::new(MemoAction::Deposit).with_correlation_id("order-42");
+        let message = Message::new(&[issue, memo.instruction().unwrap()], Some(&user));
+        assert_eq!(SdkMemo::from_message(&VersionedMessage::Legacy(message)), Some(memo));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use solana_sdk::signature::Signature;
+
+use crate::{dedup::Deduplicator, history::HistoryEntry};
+
+/// Sort key of an event: slot, transaction within the block, instruction within the transaction
+///
+/// Transactions whose block index is unknown sort before the known ones of their slot, ordered
+/// by signature, so the order is still deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EventPosition {
+    pub slot: u64,
+    pub transaction_index: Option<usize>,
+    pub signature: Signature,
+    pub instruction_index: usize,
+}
+
+impl HistoryEntry {
+    /// Where this event sorts in the chain's order
+    pub fn position(&self) -> EventPosition {
+        EventPosition {
+            slot: self.slot,
+            transaction_index: self.transaction_index,
+            signature: self.signature,
+            instruction_index: self.instruction_index,
+        }
+    }
+}
+
+/// Merges event sources into one stream ordered by `EventPosition`
+///
+/// Each source must deliver its own events in slot order (a backfill replayed oldest first,
+/// a live subscription). An event is released once every open source has moved past its
+/// slot, so sources that lag don't produce out-of-order output. Events delivered by more
+/// than one source, as in the window where a backfill catches up with a live subscription,
+/// are released once.
+#[derive(Debug, Clone)]
+pub struct StreamMerger {
+    /// Per source: every event before this slot has been pushed (`None`: nothing reported yet)
+    complete_before: Vec<Option<u64>>,
+    closed: Vec<bool>,
+    pending: BTreeMap<EventPosition, HistoryEntry>,
+    dedup: Deduplicator,
+    /// Events pushed for a slot that was already released
+    late: usize,
+    released_before: u64,
+}
+
+impl StreamMerger {
+    /// Merger for `sources` sources, identified by index
+    pub fn new(sources: usize) -> Self {
+        Self::with_deduplicator(sources, Deduplicator::default())
+    }
+
+    /// Merger remembering keys of released events with `dedup` (to size its capacity)
+    pub fn with_deduplicator(sources: usize, dedup: Deduplicator) -> Self {
+        Self {
+            complete_before: vec![None; sources],
+            closed: vec![false; sources],
+            pending: BTreeMap::new(),
+            dedup,
+            late: 0,
+            released_before: 0,
+        }
+    }
+
+    /// Add an event from `source`; all of the source's earlier slots are complete
+    pub fn push(&mut self, source: usize, entry: HistoryEntry) {
+        self.mark_complete_before(source, entry.slot);
+        if !self.dedup.first_seen(&entry) {
+            return;
+        }
+        if entry.slot < self.released_before {
+            self.late += 1;
+        }
+        self.pending.insert(entry.position(), entry);
+    }
+
+    /// `source` has delivered every event up to and including `slot` (e.g. a poll finished)
+    pub fn advance(&mut self, source: usize, slot: u64) {
+        self.mark_complete_before(source, slot.saturating_add(1));
+    }
+
+    /// `source` won't deliver anything more (e.g. a backfill reached the live stream)
+    pub fn close(&mut self, source: usize) {
+        self.closed[source] = true;
+    }
+
+    /// Remove and return, in order, the events no open source can still precede
+    ///
+    /// Nothing is released until every open source reported progress.
+    pub fn drain_ready(&mut self) -> Vec<HistoryEntry> {
+        let open = (0..self.closed.len()).filter(|&source| !self.closed[source]);
+        let safe_before = match open.map(|source| self.complete_before[source]).min() {
+            Some(Some(slot)) => slot,
+            Some(None) => return Vec::new(),
+            None => u64::MAX,
+        };
+        let first_kept = self.pending.keys().find(|position| position.slot >= safe_before).copied();
+        let remaining = match first_kept {
+            Some(first_kept) => self.pending.split_off(&first_kept),
+            None => BTreeMap::new(),
+        };
+        let released = std::mem::replace(&mut self.pending, remaining);
+        self.released_before = self.released_before.max(safe_before);
+        released.into_values().collect()
+    }
+
+    /// Release everything still pending, in order, regardless of source progress
+    pub fn flush(&mut self) -> Vec<HistoryEntry> {
+        std::mem::take(&mut self.pending).into_values().collect()
+    }
+
+    /// Events held back waiting for a lagging source
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// New events that arrived after their slot had already been released; they are released
+    /// with the next batch, out of order. A non-zero count means a source broke slot order.
+    pub fn late_events(&self) -> usize {
+        self.late
+    }
+
+    fn mark_complete_before(&mut self, source: usize, slot: u64) {
+        let complete = &mut self.complete_before[source];
+        *complete = Some(complete.map_or(slot, |current| current.max(slot)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instructions::CarrotInstruction, USDC_MINT};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn entry(slot: u64, transaction_index: Option<usize>, instruction_index: usize) -> HistoryEntry {
+        HistoryEntry {
+            // One signature per (slot, transaction) so keys are stable across sources
+            signature: Signature::from([slot as u8 + transaction_index.unwrap_or(99) as u8; 64]),
+            instruction_index,
+            slot,
+            transaction_index,
+            block_time: None,
+            instruction: CarrotInstruction::Issue {
+                user: Pubkey::default(),
+                asset_mint: USDC_MINT,
+                amount: 1,
+            },
+        }
+    }
+
+    fn slots(entries: &[HistoryEntry]) -> Vec<(u64, Option<usize>, usize)> {
+        entries
+            .iter()
+            .map(|e| (e.slot, e.transaction_index, e.instruction_index))
+            .collect()
+    }
+
+    #[test]
+    fn test_merges_overlapping_sources_in_order() {
+        const BACKFILL: usize = 0;
+        const LIVE: usize = 1;
+        let mut merger = StreamMerger::new(2);
+
+        merger.push(LIVE, entry(20, Some(3), 0));
+        merger.push(BACKFILL, entry(10, Some(1), 0));
+        // The live source is ahead, but the backfill may still deliver slots up to 10
+        assert_eq!(slots(&merger.drain_ready()), []);
+
+        merger.push(BACKFILL, entry(15, Some(0), 1));
+        merger.push(BACKFILL, entry(15, Some(0), 0));
+        assert_eq!(slots(&merger.drain_ready()), [(10, Some(1), 0)]);
+
+        // Overlap: the backfill reaches events the live source already delivered
+        merger.push(BACKFILL, entry(20, Some(3), 0));
+        merger.push(BACKFILL, entry(20, Some(1), 0));
+        merger.close(BACKFILL);
+        assert_eq!(slots(&merger.drain_ready()), [(15, Some(0), 0), (15, Some(0), 1)]);
+
+        merger.advance(LIVE, 20);
+        assert_eq!(slots(&merger.drain_ready()), [(20, Some(1), 0), (20, Some(3), 0)]);
+        assert_eq!(merger.pending(), 0);
+        assert_eq!(merger.late_events(), 0);
+
+        // A duplicate of a released event is dropped, a new event for a released slot is late
+        merger.push(LIVE, entry(20, Some(3), 0));
+        merger.push(LIVE, entry(20, Some(4), 0));
+        assert_eq!(merger.late_events(), 1);
+        assert_eq!(slots(&merger.flush()), [(20, Some(4), 0)]);
+    }
+}
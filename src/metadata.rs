@@ -0,0 +1,253 @@
+use std::fmt;
+
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022_interface::{
+    extension::{BaseStateWithExtensions, StateWithExtensions},
+    state::Mint,
+};
+use spl_token_metadata_interface::state::TokenMetadata;
+
+use crate::{error::Result, CRT_MINT, PYUSD_MINT, USDC_MINT, USDT_MINT};
+
+/// Metaplex Token Metadata program ID
+pub const METAPLEX_METADATA_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// `Key::MetadataV1`, the first byte of a Metaplex metadata account
+const METAPLEX_METADATA_V1: u8 = 4;
+
+/// Offset of the name in a Metaplex metadata account (key, update authority, mint)
+const METAPLEX_NAME_OFFSET: usize = 1 + 32 + 32;
+
+/// Names and symbols of the mints the SDK ships constants for
+const BUILTIN_METADATA: [(Pubkey, &str, &str); 4] = [
+    (CRT_MINT, "Carrot", "CRT"),
+    (USDC_MINT, "USD Coin", "USDC"),
+    (USDT_MINT, "USDT", "USDT"),
+    (PYUSD_MINT, "PayPal USD", "PYUSD"),
+];
+
+/// Where a `MintMetadata` was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    /// Token-2022 metadata extension stored on the mint itself
+    Token2022,
+    /// Metaplex Token Metadata account of the mint
+    Metaplex,
+    /// The SDK's table of known mints
+    Builtin,
+    /// Nothing found; the symbol is an abbreviated mint address
+    Unknown,
+}
+
+/// Display information of a mint: symbol, name, logo/metadata URI and decimals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintMetadata {
+    pub mint: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    /// Off-chain JSON (usually holding the logo), when published
+    pub uri: Option<String>,
+    pub decimals: u8,
+    pub source: MetadataSource,
+}
+
+impl MintMetadata {
+    /// Resolve metadata from the mint account and, if it exists, its Metaplex metadata account
+    ///
+    /// Token-2022 metadata takes precedence over Metaplex, which takes precedence over the
+    /// built-in table; a mint without any gets an abbreviated address as its symbol.
+    pub fn from_accounts(mint: &Pubkey, mint_data: &[u8], metaplex_data: Option<&[u8]>) -> Result<Self> {
+        let state = StateWithExtensions::<Mint>::unpack(mint_data)?;
+        let decimals = state.base.decimals;
+
+        let embedded = state
+            .get_variable_len_extension::<TokenMetadata>()
+            .ok()
+            .filter(|metadata| Pubkey::from(metadata.mint.to_bytes()) == *mint)
+            .map(|metadata| (metadata.name, metadata.symbol, metadata.uri, MetadataSource::Token2022));
+        let (name, symbol, uri, source) = embedded
+            .or_else(|| {
+                let (name, symbol, uri) = metaplex_data.and_then(parse_metaplex_metadata)?;
+                Some((name, symbol, uri, MetadataSource::Metaplex))
+            })
+            .filter(|(_, symbol, _, _)| !symbol.is_empty())
+            .unwrap_or_else(|| match builtin(mint) {
+                Some((name, symbol)) => (name.to_string(), symbol.to_string(), String::new(), MetadataSource::Builtin),
+                None => (mint.to_string(), abbreviate(mint), String::new(), MetadataSource::Unknown),
+            });
+
+        Ok(Self {
+            mint: *mint,
+            name,
+            symbol,
+            uri: Some(uri).filter(|uri| !uri.is_empty()),
+            decimals,
+            source,
+        })
+    }
+
+    /// `amount` in whole units with the symbol, e.g. `1.500000 USDC`
+    pub fn format_amount(&self, amount: u64) -> String {
+        if self.decimals == 0 {
+            return format!("{} {}", amount, self.symbol);
+        }
+        let amount = amount as u128;
+        let (whole, fraction) = match 10u128.checked_pow(self.decimals as u32) {
+            Some(scale) => (amount / scale, amount % scale),
+            // More decimals than any u64 amount has digits
+            None => (0, amount),
+        };
+        format!("{}.{:0width$} {}", whole, fraction, self.symbol, width = self.decimals as usize)
+    }
+}
+
+impl fmt::Display for MintMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}, {} decimals)", self.symbol, self.name, self.decimals)
+    }
+}
+
+/// Address of the Metaplex metadata account of `mint`
+/// Seeds: ["metadata", metadata program, mint] under the metadata program
+pub fn metaplex_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METAPLEX_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METAPLEX_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Name and symbol of a mint the SDK ships a constant for
+pub fn builtin(mint: &Pubkey) -> Option<(&'static str, &'static str)> {
+    BUILTIN_METADATA
+        .iter()
+        .find(|(known, _, _)| known == mint)
+        .map(|(_, name, symbol)| (*name, *symbol))
+}
+
+/// Name, symbol and URI of a Metaplex metadata account
+///
+/// Only the leading fields are decoded; Metaplex pads them with NUL bytes.
+fn parse_metaplex_metadata(data: &[u8]) -> Option<(String, String, String)> {
+    if data.first() != Some(&METAPLEX_METADATA_V1) {
+        return None;
+    }
+    let mut rest = data.get(METAPLEX_NAME_OFFSET..)?;
+    let mut next = || -> Option<String> {
+        let len = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let bytes = rest.get(4..4 + len)?;
+        rest = &rest[4 + len..];
+        Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string())
+    };
+    Some((next()?, next()?, next()?))
+}
+
+/// `Abcd…wxyz` for mints without a symbol
+fn abbreviate(mint: &Pubkey) -> String {
+    let address = mint.to_string();
+    format!("{}…{}", &address[..4], &address[address.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::program_pack::Pack;
+    use spl_token_2022_interface::extension::{
+        metadata_pointer::MetadataPointer, BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+
+    fn mint_data(decimals: u8) -> Vec<u8> {
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack(
+            Mint {
+                decimals,
+                is_initialized: true,
+                ..Mint::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        data
+    }
+
+    fn metaplex_data(name: &str, symbol: &str, uri: &str) -> Vec<u8> {
+        let mut data = vec![METAPLEX_METADATA_V1];
+        data.extend_from_slice(&[0u8; 64]);
+        // Metaplex stores fixed-size, NUL-padded strings
+        for (value, size) in [(name, 32), (symbol, 10), (uri, 200)] {
+            let mut padded = value.as_bytes().to_vec();
+            padded.resize(size, 0);
+            data.extend_from_slice(&(size as u32).to_le_bytes());
+            data.extend_from_slice(&padded);
+        }
+        data
+    }
+
+    #[test]
+    fn test_metaplex_metadata() {
+        let mint = Pubkey::new_unique();
+        let data = metaplex_data("Sky Dollar", "USDS", "https://example.com/usds.json");
+        let metadata = MintMetadata::from_accounts(&mint, &mint_data(6), Some(&data)).unwrap();
+        assert_eq!(metadata.source, MetadataSource::Metaplex);
+        assert_eq!(metadata.symbol, "USDS");
+        assert_eq!(metadata.name, "Sky Dollar");
+        assert_eq!(metadata.uri.as_deref(), Some("https://example.com/usds.json"));
+        assert_eq!(metadata.format_amount(1_500_000), "1.500000 USDS");
+        assert!(parse_metaplex_metadata(&data[..80]).is_none());
+    }
+
+    #[test]
+    fn test_token_2022_metadata_takes_precedence() {
+        let mint = Pubkey::new_unique();
+        let metadata = TokenMetadata {
+            mint: mint.to_bytes().into(),
+            name: "First Digital USD".to_string(),
+            symbol: "FDUSD".to_string(),
+            uri: String::new(),
+            ..TokenMetadata::default()
+        };
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::MetadataPointer]).unwrap()
+            + metadata.tlv_size_of().unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        state.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        // The mint points at itself for its metadata
+        state.init_extension::<MetadataPointer>(true).unwrap().metadata_address = Some(mint).try_into().unwrap();
+        state.init_variable_len_extension(&metadata, false).unwrap();
+
+        let other = metaplex_data("Other", "OTHER", "");
+        let resolved = MintMetadata::from_accounts(&mint, &data, Some(&other)).unwrap();
+        assert_eq!(resolved.source, MetadataSource::Token2022);
+        assert_eq!(resolved.symbol, "FDUSD");
+        assert_eq!(resolved.uri, None);
+    }
+
+    #[test]
+    fn test_builtin_fallback() {
+        let usdc = MintMetadata::from_accounts(&USDC_MINT, &mint_data(6), None).unwrap();
+        assert_eq!((usdc.symbol.as_str(), usdc.source), ("USDC", MetadataSource::Builtin));
+        assert_eq!(usdc.to_string(), "USDC (USD Coin, 6 decimals)");
+
+        let mint = Pubkey::new_unique();
+        let unknown = MintMetadata::from_accounts(&mint, &mint_data(0), None).unwrap();
+        assert_eq!(unknown.source, MetadataSource::Unknown);
+        assert!(mint.to_string().starts_with(unknown.symbol.split('…').next().unwrap()));
+        assert_eq!(unknown.format_amount(42), format!("42 {}", unknown.symbol));
+    }
+
+    #[test]
+    fn test_format_amount_with_many_decimals() {
+        let mint = Pubkey::new_unique();
+        let mut metadata = MintMetadata::from_accounts(&mint, &mint_data(20), None).unwrap();
+        metadata.symbol = "WIDE".to_string();
+        assert_eq!(metadata.format_amount(u64::MAX), "0.18446744073709551615 WIDE");
+        metadata.decimals = 40;
+        assert_eq!(metadata.format_amount(5), format!("0.{}5 WIDE", "0".repeat(39)));
+    }
+}
@@ -0,0 +1,730 @@
+use std::time::Duration;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    error::{CarrotError, Result},
+    oracle::{decode_oracle_account, OraclePrice},
+    snapshot::{AssetSnapshot, VaultSnapshot},
+    Fee,
+};
+
+/// Decimals of USD values computed by the SDK (1 USD = 10^12)
+pub const VALUE_DECIMALS: u32 = 12;
+
+/// CRT token decimals
+pub const CRT_DECIMALS: u8 = 9;
+
+/// Seconds per year used to pro-rate the annual management fee
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Rounding direction for share/asset conversions
+///
+/// This is protocol math and must match the program; presentation uses `DisplayRounding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round towards zero (in favor of the vault when computing user proceeds)
+    Down,
+    /// Round away from zero
+    Up,
+}
+
+/// Valuation inputs for one vault asset
+#[derive(Debug, Clone)]
+pub struct AssetValuation {
+    pub mint: Pubkey,
+    pub asset_id: u16,
+    pub decimals: u8,
+    /// Oracle price of one whole token in USD
+    pub price: OraclePrice,
+    /// Idle balance held in the vault's token account
+    pub vault_balance: u64,
+    /// Balance deployed into strategies
+    pub strategy_balance: u64,
+}
+
+impl AssetValuation {
+    /// Total balance managed by the vault for this asset
+    pub fn total_balance(&self) -> u64 {
+        self.vault_balance.saturating_add(self.strategy_balance)
+    }
+
+    /// USD value (`VALUE_DECIMALS`) of `amount` base units of this asset
+    pub fn value_of(&self, amount: u64, rounding: Rounding) -> Result<u128> {
+        let price = positive_price(&self.price)?;
+        let gross = (amount as u128).checked_mul(price).ok_or(CarrotError::MathOverflow)?;
+
+        let scale = self.value_scale();
+        if scale >= 0 {
+            gross.checked_mul(pow10(scale as u32)?).ok_or(CarrotError::MathOverflow)
+        } else {
+            mul_div(gross, 1, pow10(scale.unsigned_abs())?, rounding)
+        }
+    }
+
+    /// Amount of this asset (base units) worth `value` USD (`VALUE_DECIMALS`)
+    pub fn amount_for_value(&self, value: u128, rounding: Rounding) -> Result<u64> {
+        let price = positive_price(&self.price)?;
+
+        let scale = self.value_scale();
+        let amount = if scale >= 0 {
+            let divisor = price.checked_mul(pow10(scale as u32)?).ok_or(CarrotError::MathOverflow)?;
+            mul_div(value, 1, divisor, rounding)?
+        } else {
+            mul_div(value, pow10(scale.unsigned_abs())?, price, rounding)?
+        };
+        u64::try_from(amount).map_err(|_| CarrotError::MathOverflow)
+    }
+
+    /// Same asset priced at the bottom of its oracle confidence interval
+    pub fn at_bid(&self) -> Self {
+        Self {
+            price: self.price.bid(),
+            ..self.clone()
+        }
+    }
+
+    /// Same asset priced at the top of its oracle confidence interval
+    pub fn at_ask(&self) -> Self {
+        Self {
+            price: self.price.ask(),
+            ..self.clone()
+        }
+    }
+
+    /// Power of ten converting `amount * price` into `VALUE_DECIMALS` USD
+    fn value_scale(&self) -> i32 {
+        VALUE_DECIMALS as i32 + self.price.exponent - self.decimals as i32
+    }
+}
+
+/// Net asset value of the vault at a given slot
+#[derive(Debug, Clone)]
+pub struct Nav {
+    /// Slot the underlying state was read at
+    pub slot: u64,
+    /// Total CRT supply
+    pub shares_supply: u64,
+    /// Per-asset valuation inputs
+    pub assets: Vec<AssetValuation>,
+    /// Management fee accrued since the vault's last fee update (USD, `VALUE_DECIMALS`),
+    /// owed by share holders and excluded from `total_value`
+    pub pending_management_fee: u128,
+}
+
+impl Nav {
+    /// Compute the NAV from a vault snapshot, decoding each asset's oracle
+    pub fn from_snapshot(snapshot: &VaultSnapshot) -> Result<Self> {
+        let assets = snapshot
+            .assets
+            .iter()
+            .map(|asset_snapshot| Ok(asset_valuation(snapshot, asset_snapshot, oracle_price(asset_snapshot)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            slot: snapshot.slot,
+            shares_supply: snapshot.shares_supply,
+            assets,
+            pending_management_fee: 0,
+        })
+    }
+
+    /// Compute the NAV from whichever assets' oracles can be read, net of the management fee
+    /// accrued until `now` (Unix timestamp)
+    ///
+    /// An asset whose oracle is missing or doesn't decode is valued at `fallback(oracle)`
+    /// when that returns a price, and left out otherwise; either way it is listed in
+    /// `PartialNav::issues`.
+    pub fn from_snapshot_partial<F>(snapshot: &VaultSnapshot, now: i64, fallback: F) -> Result<PartialNav>
+    where
+        F: Fn(&Pubkey) -> Option<OraclePrice>,
+    {
+        let mut assets = Vec::with_capacity(snapshot.assets.len());
+        let mut issues = Vec::new();
+        for asset_snapshot in &snapshot.assets {
+            let asset = &asset_snapshot.asset;
+            let price = match oracle_price(asset_snapshot) {
+                Ok(price) => Some(price),
+                Err(err) => {
+                    let fallback = fallback(&asset.oracle);
+                    log::warn!("oracle {} of {} unavailable: {}", asset.oracle, asset.mint, err);
+                    issues.push(OracleIssue {
+                        mint: asset.mint,
+                        oracle: asset.oracle,
+                        error: err.to_string(),
+                        fallback,
+                    });
+                    fallback
+                }
+            };
+            if let Some(price) = price {
+                assets.push(asset_valuation(snapshot, asset_snapshot, price));
+            }
+        }
+
+        let mut nav = Self {
+            slot: snapshot.slot,
+            shares_supply: snapshot.shares_supply,
+            assets,
+            pending_management_fee: 0,
+        };
+        nav.pending_management_fee = pending_management_fee(nav.gross_value()?, &snapshot.vault.fee, now)?;
+        Ok(PartialNav { nav, issues })
+    }
+
+    /// Compute the NAV from a vault snapshot, net of the management fee accrued
+    /// between the vault's last fee update and `now` (Unix timestamp)
+    pub fn from_snapshot_with_fees(snapshot: &VaultSnapshot, now: i64) -> Result<Self> {
+        let mut nav = Self::from_snapshot(snapshot)?;
+        nav.pending_management_fee = pending_management_fee(nav.gross_value()?, &snapshot.vault.fee, now)?;
+        Ok(nav)
+    }
+
+    /// Valuation inputs of the asset with the given mint
+    pub fn asset(&self, mint: &Pubkey) -> Result<&AssetValuation> {
+        self.assets
+            .iter()
+            .find(|a| &a.mint == mint)
+            .ok_or_else(|| CarrotError::InvalidAsset(mint.to_string()))
+    }
+
+    /// Total value managed by the vault in USD (`VALUE_DECIMALS`), net of the pending management fee
+    pub fn total_value(&self) -> Result<u128> {
+        Ok(self.gross_value()?.saturating_sub(self.pending_management_fee))
+    }
+
+    /// Total value of the vault's assets in USD (`VALUE_DECIMALS`), before fees
+    pub fn gross_value(&self) -> Result<u128> {
+        self.assets.iter().try_fold(0u128, |sum, asset| {
+            let value = asset.value_of(asset.total_balance(), Rounding::Down)?;
+            sum.checked_add(value).ok_or(CarrotError::MathOverflow)
+        })
+    }
+
+    /// USD value of one whole CRT (for display only)
+    pub fn share_price(&self) -> Result<f64> {
+        let one_share = 10u64.pow(CRT_DECIMALS as u32);
+        let value = self.value_of_shares(one_share, Rounding::Down)?;
+        Ok(value as f64 / 10f64.powi(VALUE_DECIMALS as i32))
+    }
+
+    /// Fail with `StaleOraclePrice` if any asset's price was published more than `max_age`
+    /// before `now`
+    ///
+    /// The conservative valuations below only account for the oracles' confidence, not their
+    /// age: check this first before guarding an operation with them.
+    pub fn ensure_fresh_prices(&self, now: i64, max_age: Duration) -> Result<()> {
+        let max_age_secs = i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX);
+        match self
+            .assets
+            .iter()
+            .find(|asset| now.saturating_sub(asset.price.publish_time) > max_age_secs)
+        {
+            Some(asset) => Err(CarrotError::StaleOraclePrice {
+                mint: asset.mint,
+                publish_time: asset.price.publish_time,
+                now,
+                max_age,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The same NAV with every asset valued at the bottom of its oracle confidence interval
+    /// (the bid side, as risk checks require)
+    pub fn conservative(&self) -> Nav {
+        Nav {
+            assets: self.assets.iter().map(AssetValuation::at_bid).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Total value at the bid side of every oracle's confidence interval
+    pub fn conservative_total_value(&self) -> Result<u128> {
+        self.conservative().total_value()
+    }
+
+    /// Least amount of `asset` redeeming `shares` can be worth within the oracles' confidence:
+    /// shares valued at the conservative NAV, paid out at the asset's ask price (before fees)
+    pub fn conservative_withdrawal(&self, shares: u64, asset: &Pubkey) -> Result<u64> {
+        let value = self.conservative().value_of_shares(shares, Rounding::Down)?;
+        self.asset(asset)?.at_ask().amount_for_value(value, Rounding::Down)
+    }
+
+    /// Least CRT depositing `amount` of `asset` can mint within the oracles' confidence:
+    /// the deposit valued at the asset's bid price against the NAV at ask prices
+    pub fn conservative_deposit(&self, amount: u64, asset: &Pubkey) -> Result<u64> {
+        let value = self.asset(asset)?.at_bid().value_of(amount, Rounding::Down)?;
+        let ask_nav = Nav {
+            assets: self.assets.iter().map(AssetValuation::at_ask).collect(),
+            ..self.clone()
+        };
+        ask_nav.shares_for_value(value, Rounding::Down)
+    }
+
+    /// CRT minted for depositing `value` USD (`VALUE_DECIMALS`)
+    /// An empty vault mints at 1 CRT per USD
+    pub fn shares_for_value(&self, value: u128, rounding: Rounding) -> Result<u64> {
+        let total_value = self.total_value()?;
+        let shares = if self.shares_supply == 0 || total_value == 0 {
+            let scale = pow10(VALUE_DECIMALS - CRT_DECIMALS as u32)?;
+            mul_div(value, 1, scale, rounding)?
+        } else {
+            mul_div(value, self.shares_supply as u128, total_value, rounding)?
+        };
+        u64::try_from(shares).map_err(|_| CarrotError::MathOverflow)
+    }
+
+    /// USD value (`VALUE_DECIMALS`) of `shares` CRT
+    pub fn value_of_shares(&self, shares: u64, rounding: Rounding) -> Result<u128> {
+        let total_value = self.total_value()?;
+        if self.shares_supply == 0 || total_value == 0 {
+            let scale = pow10(VALUE_DECIMALS - CRT_DECIMALS as u32)?;
+            return (shares as u128).checked_mul(scale).ok_or(CarrotError::MathOverflow);
+        }
+        mul_div(shares as u128, total_value, self.shares_supply as u128, rounding)
+    }
+}
+
+/// An asset whose oracle couldn't be read or decoded when valuing the vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OracleIssue {
+    pub mint: Pubkey,
+    pub oracle: Pubkey,
+    /// Why the oracle's current price is unavailable
+    pub error: String,
+    /// Earlier price the asset was valued at instead (`None` if it was left out)
+    pub fallback: Option<OraclePrice>,
+}
+
+/// NAV of the vault computed from the assets that could be priced
+///
+/// With assets left out, `nav` undervalues the vault and its share price; use it for
+/// dashboards and reporting, never to size deposits or withdrawals.
+#[derive(Debug, Clone)]
+pub struct PartialNav {
+    /// Valuation of the priced assets, including those at a fallback price
+    pub nav: Nav,
+    /// Assets valued at a fallback price or left out, with the reason
+    pub issues: Vec<OracleIssue>,
+}
+
+impl PartialNav {
+    /// Whether every asset was valued at its current oracle price
+    pub fn is_complete(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Assets left out of `nav` for lack of any price
+    pub fn excluded(&self) -> impl Iterator<Item = &OracleIssue> {
+        self.issues.iter().filter(|issue| issue.fallback.is_none())
+    }
+
+    /// The NAV if every asset was valued at its current price, otherwise the first issue
+    pub fn into_complete(self) -> Result<Nav> {
+        match self.issues.first() {
+            None => Ok(self.nav),
+            Some(issue) => Err(CarrotError::OracleError(format!("{}: {}", issue.oracle, issue.error))),
+        }
+    }
+}
+
+/// Current price of an asset's oracle in a snapshot
+fn oracle_price(asset_snapshot: &AssetSnapshot) -> Result<OraclePrice> {
+    let oracle = asset_snapshot.oracle_account.as_ref().ok_or_else(|| {
+        CarrotError::OracleError(format!("oracle account {} not found", asset_snapshot.asset.oracle))
+    })?;
+    decode_oracle_account(oracle)
+}
+
+/// Valuation inputs of one snapshot asset priced at `price`
+fn asset_valuation(snapshot: &VaultSnapshot, asset_snapshot: &AssetSnapshot, price: OraclePrice) -> AssetValuation {
+    let asset = &asset_snapshot.asset;
+    let strategy_balance = snapshot
+        .vault
+        .strategies
+        .iter()
+        .filter(|s| s.asset_id == asset.asset_id)
+        .fold(0u64, |sum, s| sum.saturating_add(s.balance));
+    AssetValuation {
+        mint: asset.mint,
+        asset_id: asset.asset_id,
+        decimals: asset.decimals,
+        price,
+        vault_balance: asset_snapshot.vault_balance,
+        strategy_balance,
+    }
+}
+
+/// An amount of CRT in base units (9 decimals)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CrtAmount(pub u64);
+
+/// An amount of a vault asset in base units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssetAmount {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl CrtAmount {
+    /// Amount of `asset` these shares are worth at `nav`, before redemption fees
+    ///
+    /// Rounds every step in the given direction; the program rounds down, in favor of the vault.
+    pub fn to_asset_value(&self, nav: &Nav, asset: &Pubkey, rounding: Rounding) -> Result<AssetAmount> {
+        let valuation = nav.asset(asset)?;
+        let value = nav.value_of_shares(self.0, rounding)?;
+        let amount = valuation.amount_for_value(value, rounding)?;
+        Ok(AssetAmount {
+            mint: *asset,
+            amount,
+        })
+    }
+}
+
+impl AssetAmount {
+    /// Create an amount of the asset with the given mint
+    pub fn new(mint: Pubkey, amount: u64) -> Self {
+        Self { mint, amount }
+    }
+
+    /// CRT expected for depositing this amount at `nav`
+    ///
+    /// Rounds every step in the given direction; the program rounds down, in favor of the vault.
+    pub fn to_expected_shares(&self, nav: &Nav, rounding: Rounding) -> Result<CrtAmount> {
+        let valuation = nav.asset(&self.mint)?;
+        let value = valuation.value_of(self.amount, rounding)?;
+        Ok(CrtAmount(nav.shares_for_value(value, rounding)?))
+    }
+}
+
+/// Redemption value of shares in an asset at a NAV, rounded down as the program does
+impl TryFrom<(CrtAmount, &Nav, &Pubkey)> for AssetAmount {
+    type Error = CarrotError;
+
+    fn try_from((shares, nav, asset): (CrtAmount, &Nav, &Pubkey)) -> Result<Self> {
+        shares.to_asset_value(nav, asset, Rounding::Down)
+    }
+}
+
+/// Shares minted for a deposit at a NAV, rounded down as the program does
+impl TryFrom<(AssetAmount, &Nav)> for CrtAmount {
+    type Error = CarrotError;
+
+    fn try_from((amount, nav): (AssetAmount, &Nav)) -> Result<Self> {
+        amount.to_expected_shares(nav, Rounding::Down)
+    }
+}
+
+/// Basis point denominator (100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Deduct a basis-point fee from `amount`, rounding the fee up (in favor of the vault)
+pub fn deduct_fee_bps(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = mul_div(amount as u128, fee_bps as u128, BPS_DENOMINATOR as u128, Rounding::Up)?;
+    Ok(amount.saturating_sub(fee as u64))
+}
+
+/// Management fee accrued on `total_value` from `fee.management_fee_last_update` to `now`,
+/// pro-rated from the annual `management_fee_bps` and rounded up (in favor of the vault)
+/// No fee is pending if the vault never recorded an update
+pub fn pending_management_fee(total_value: u128, fee: &Fee, now: i64) -> Result<u128> {
+    if fee.management_fee_last_update <= 0 || fee.management_fee_bps == 0 {
+        return Ok(0);
+    }
+    let elapsed = now.saturating_sub(fee.management_fee_last_update).max(0) as u128;
+    let annual = mul_div(total_value, fee.management_fee_bps as u128, BPS_DENOMINATOR as u128, Rounding::Up)?;
+    mul_div(annual, elapsed, SECONDS_PER_YEAR as u128, Rounding::Up)
+}
+
+/// Compute `a * b / c` with the given rounding
+pub(crate) fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> Result<u128> {
+    if c == 0 {
+        return Err(CarrotError::MathOverflow);
+    }
+    let product = a.checked_mul(b).ok_or(CarrotError::MathOverflow)?;
+    let quotient = product / c;
+    match rounding {
+        Rounding::Up if product % c != 0 => Ok(quotient + 1),
+        _ => Ok(quotient),
+    }
+}
+
+fn pow10(exponent: u32) -> Result<u128> {
+    10u128.checked_pow(exponent).ok_or(CarrotError::MathOverflow)
+}
+
+fn positive_price(price: &OraclePrice) -> Result<u128> {
+    if price.price <= 0 {
+        return Err(CarrotError::OracleError(format!(
+            "non-positive oracle price {}",
+            price.price
+        )));
+    }
+    Ok(price.price as u128)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_utils::{load_execution_fixtures, ExecutionFixture};
+    use std::{
+        path::{Path, PathBuf},
+        str::FromStr,
+    };
+
+    pub(crate) fn usd_price(price: i64) -> OraclePrice {
+        OraclePrice {
+            price,
+            conf: 0,
+            exponent: -8,
+            publish_time: 0,
+        }
+    }
+
+    pub(crate) fn test_nav(shares_supply: u64, assets: Vec<(Pubkey, u64, i64)>) -> Nav {
+        Nav {
+            slot: 1,
+            shares_supply,
+            assets: assets
+                .into_iter()
+                .enumerate()
+                .map(|(i, (mint, balance, price))| AssetValuation {
+                    mint,
+                    asset_id: i as u16,
+                    decimals: 6,
+                    price: usd_price(price),
+                    vault_balance: balance,
+                    strategy_balance: 0,
+                })
+                .collect(),
+            pending_management_fee: 0,
+        }
+    }
+
+    #[test]
+    fn test_mul_div_rounding() {
+        assert_eq!(mul_div(10, 1, 3, Rounding::Down).unwrap(), 3);
+        assert_eq!(mul_div(10, 1, 3, Rounding::Up).unwrap(), 4);
+        assert_eq!(mul_div(9, 1, 3, Rounding::Up).unwrap(), 3);
+        assert!(mul_div(1, 1, 0, Rounding::Down).is_err());
+    }
+
+    #[test]
+    fn test_deduct_fee_bps() {
+        assert_eq!(deduct_fee_bps(1_000_000, 0).unwrap(), 1_000_000);
+        assert_eq!(deduct_fee_bps(1_000_000, 10).unwrap(), 999_000);
+        // 0.1% of 1,001 is 1.001, rounded up to 2
+        assert_eq!(deduct_fee_bps(1_001, 10).unwrap(), 999);
+    }
+
+    #[test]
+    fn test_asset_value_conversion() {
+        let usdc = Pubkey::new_unique();
+        let nav = test_nav(1, vec![(usdc, 0, 100_000_000)]);
+        let valuation = nav.asset(&usdc).unwrap();
+
+        // 1 USDC at $1.00 is worth 10^12 value units, and back
+        assert_eq!(valuation.value_of(1_000_000, Rounding::Down).unwrap(), 1_000_000_000_000);
+        assert_eq!(valuation.amount_for_value(1_000_000_000_000, Rounding::Down).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn test_share_conversions() {
+        let usdc = Pubkey::new_unique();
+        let usdt = Pubkey::new_unique();
+        // 2,000 USDC + 1,000 USDT at $1 backing 2,500 CRT => 1.2 USD per CRT
+        let nav = test_nav(
+            2_500_000_000_000,
+            vec![(usdc, 2_000_000_000, 100_000_000), (usdt, 1_000_000_000, 100_000_000)],
+        );
+        assert!((nav.share_price().unwrap() - 1.2).abs() < 1e-9);
+
+        // Depositing 12 USDC mints 10 CRT
+        let shares = AssetAmount::new(usdc, 12_000_000).to_expected_shares(&nav, Rounding::Down).unwrap();
+        assert_eq!(shares, CrtAmount(10_000_000_000));
+
+        // Redeeming 10 CRT returns 12 USDT
+        let out = CrtAmount(10_000_000_000).to_asset_value(&nav, &usdt, Rounding::Down).unwrap();
+        assert_eq!(out.amount, 12_000_000);
+
+        // Fractional shares are rounded in the requested direction (833.33 -> 833 or 834)
+        let dust = AssetAmount::new(usdc, 1);
+        assert_eq!(dust.to_expected_shares(&nav, Rounding::Down).unwrap(), CrtAmount(833));
+        assert_eq!(dust.to_expected_shares(&nav, Rounding::Up).unwrap(), CrtAmount(834));
+        // 1 CRT is worth 1.2 USDT base units
+        assert_eq!(CrtAmount(1_000).to_asset_value(&nav, &usdt, Rounding::Down).unwrap().amount, 1);
+        assert_eq!(CrtAmount(1_000).to_asset_value(&nav, &usdt, Rounding::Up).unwrap().amount, 2);
+
+        // The TryFrom conversions round down, as the program does
+        assert_eq!(CrtAmount::try_from((dust, &nav)).unwrap(), CrtAmount(833));
+        assert_eq!(AssetAmount::try_from((CrtAmount(1_000), &nav, &usdt)).unwrap(), AssetAmount::new(usdt, 1));
+        assert!(matches!(
+            AssetAmount::try_from((CrtAmount(1), &nav, &Pubkey::new_unique())),
+            Err(CarrotError::InvalidAsset(_))
+        ));
+    }
+
+    /// Transactions captured by `examples/capture_execution.rs` in `tests/fixtures/<kind>`
+    fn mainnet_fixtures(kind: &str) -> Vec<(PathBuf, ExecutionFixture)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(kind);
+        let fixtures = load_execution_fixtures(&dir).expect("execution fixtures");
+        assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+        fixtures
+    }
+
+    #[test]
+    #[ignore = "needs mainnet deposits captured with examples/capture_execution.rs"]
+    fn test_shares_match_mainnet_issues() {
+        for (path, fixture) in mainnet_fixtures("deposits") {
+            let snapshot = fixture.proof.verify().unwrap();
+            let nav = Nav::from_snapshot_with_fees(&snapshot, fixture.block_time).unwrap();
+            let deposit = AssetAmount::new(Pubkey::from_str(&fixture.asset_mint).unwrap(), fixture.asset_amount);
+            let minted = CrtAmount::try_from((deposit, &nav)).unwrap();
+            assert_eq!(minted, CrtAmount(fixture.shares), "issue {} ({})", fixture.signature, path.display());
+        }
+    }
+
+    #[test]
+    #[ignore = "needs mainnet redemptions captured with examples/capture_execution.rs"]
+    fn test_assets_match_mainnet_redeems() {
+        for (path, fixture) in mainnet_fixtures("redemptions") {
+            let snapshot = fixture.proof.verify().unwrap();
+            let nav = Nav::from_snapshot_with_fees(&snapshot, fixture.block_time).unwrap();
+            let mint = Pubkey::from_str(&fixture.asset_mint).unwrap();
+            let gross = AssetAmount::try_from((CrtAmount(fixture.shares), &nav, &mint)).unwrap();
+            let received = deduct_fee_bps(gross.amount, snapshot.vault.fee.redemption_fee_bps).unwrap();
+            assert_eq!(received, fixture.asset_amount, "redeem {} ({})", fixture.signature, path.display());
+        }
+    }
+
+    #[test]
+    fn test_empty_vault_mints_at_one_usd() {
+        let usdc = Pubkey::new_unique();
+        let nav = test_nav(0, vec![(usdc, 0, 100_000_000)]);
+        let shares = AssetAmount::new(usdc, 5_000_000).to_expected_shares(&nav, Rounding::Down).unwrap();
+        assert_eq!(shares, CrtAmount(5_000_000_000));
+    }
+
+    #[test]
+    fn test_unknown_asset() {
+        let nav = test_nav(1, vec![(Pubkey::new_unique(), 1, 100_000_000)]);
+        let err = AssetAmount::new(Pubkey::new_unique(), 1).to_expected_shares(&nav, Rounding::Down);
+        assert!(matches!(err, Err(CarrotError::InvalidAsset(_))));
+    }
+
+    #[test]
+    fn test_pending_management_fee() {
+        let mut fee = Fee {
+            redemption_fee_bps: 10,
+            management_fee_bps: 100, // 1% per year
+            management_fee_last_update: 1_700_000_000,
+            ..Default::default()
+        };
+        let total = 1_000 * pow10(VALUE_DECIMALS).unwrap();
+        let half_year = 1_700_000_000 + SECONDS_PER_YEAR as i64 / 2;
+        assert_eq!(pending_management_fee(total, &fee, half_year).unwrap(), total / 200);
+        assert_eq!(pending_management_fee(total, &fee, 1_600_000_000).unwrap(), 0);
+
+        fee.management_fee_last_update = 0;
+        assert_eq!(pending_management_fee(total, &fee, half_year).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_fee_aware_withdrawal_output() {
+        // 1,000 CRT backed by 1,100 USDC, 0.5% of value accrued as management fee
+        let mut nav = test_nav(1_000_000_000_000, vec![(Pubkey::new_unique(), 1_100_000_000, 100_000_000)]);
+        let mint = nav.assets[0].mint;
+        nav.pending_management_fee = nav.gross_value().unwrap() / 200;
+        assert_eq!(nav.total_value().unwrap(), 1_094_500_000 * pow10(6).unwrap());
+
+        // 100 CRT redeem for 109.45 USDC before the 0.1% redemption fee
+        let gross = CrtAmount(100_000_000_000).to_asset_value(&nav, &mint, Rounding::Down).unwrap().amount;
+        assert_eq!(gross, 109_450_000);
+        assert_eq!(deduct_fee_bps(gross, 10).unwrap(), 109_340_550);
+    }
+
+    #[test]
+    fn test_conservative_valuation() {
+        let mint = Pubkey::new_unique();
+        let mut nav = test_nav(1_000_000_000_000, vec![(mint, 1_000_000_000, 100_000_000)]);
+        nav.assets[0].price.conf = 1_000_000; // +/- 1%
+
+        assert_eq!(nav.total_value().unwrap(), 1_000 * pow10(VALUE_DECIMALS).unwrap());
+        assert_eq!(nav.conservative_total_value().unwrap(), 990 * pow10(VALUE_DECIMALS).unwrap());
+
+        // 100 CRT: worth 99 USD at bid, paid in USDC priced at 1.01
+        let mid = CrtAmount(100_000_000_000).to_asset_value(&nav, &mint, Rounding::Down).unwrap().amount;
+        let conservative = nav.conservative_withdrawal(100_000_000_000, &mint).unwrap();
+        assert_eq!(mid, 100_000_000);
+        assert_eq!(conservative, 98_019_801);
+        assert!(nav.conservative_deposit(1_000_000, &mint).unwrap() < 1_000_000_000);
+    }
+
+    #[test]
+    fn test_stale_prices_are_rejected() {
+        let mint = Pubkey::new_unique();
+        let mut nav = test_nav(1_000, vec![(mint, 1_000, 100_000_000)]);
+        nav.assets[0].price.publish_time = 1_700_000_000;
+        let max_age = Duration::from_secs(60);
+
+        assert!(nav.ensure_fresh_prices(1_700_000_060, max_age).is_ok());
+        assert!(matches!(
+            nav.ensure_fresh_prices(1_700_000_061, max_age),
+            Err(CarrotError::StaleOraclePrice { mint: stale, publish_time: 1_700_000_000, .. }) if stale == mint
+        ));
+    }
+
+    #[test]
+    fn test_partial_nav_skips_or_falls_back() {
+        use crate::test_utils::usdc_vault;
+        use crate::oracle::{tests::pyth_price_update_data, PYTH_RECEIVER_PROGRAM_ID};
+        use solana_sdk::account::Account;
+
+        let mut vault = usdc_vault().vault;
+        let mut second = vault.assets[0].clone();
+        second.asset_id = 1;
+        second.mint = Pubkey::new_unique();
+        second.oracle = Pubkey::new_unique();
+        vault.assets.push(second);
+        let oracle = Account {
+            owner: PYTH_RECEIVER_PROGRAM_ID,
+            data: pyth_price_update_data(100_000_000, 0, -8),
+            ..Default::default()
+        };
+        let snapshot = VaultSnapshot {
+            slot: 5,
+            shares_supply: 2_000_000_000,
+            assets: vec![
+                AssetSnapshot {
+                    asset: vault.assets[0].clone(),
+                    vault_balance: 1_000_000,
+                    oracle_account: Some(oracle),
+                },
+                AssetSnapshot {
+                    asset: vault.assets[1].clone(),
+                    vault_balance: 1_000_000,
+                    oracle_account: None,
+                },
+            ],
+            vault,
+        };
+        assert!(Nav::from_snapshot(&snapshot).is_err());
+
+        let partial = Nav::from_snapshot_partial(&snapshot, 0, |_| None).unwrap();
+        assert!(!partial.is_complete());
+        assert_eq!(partial.nav.assets.len(), 1);
+        assert_eq!(partial.nav.total_value().unwrap(), 1_000_000_000_000);
+        assert_eq!(partial.excluded().next().unwrap().mint, snapshot.assets[1].asset.mint);
+        assert!(matches!(partial.into_complete(), Err(CarrotError::OracleError(_))));
+
+        let stale = usd_price(99_000_000);
+        let partial = Nav::from_snapshot_partial(&snapshot, 0, |oracle| {
+            (*oracle == snapshot.assets[1].asset.oracle).then_some(stale)
+        })
+        .unwrap();
+        assert_eq!(partial.nav.total_value().unwrap(), 1_990_000_000_000);
+        assert_eq!(partial.issues[0].fallback, Some(stale));
+        assert_eq!(partial.excluded().count(), 0);
+    }
+}
@@ -0,0 +1,196 @@
+//! Transaction building without network access, for air-gapped signing ceremonies
+//!
+//! The online machine exports the vault account data and a recent blockhash (or a durable
+//! nonce value); the offline machine rebuilds the exact transaction from them, signs it and
+//! hands back the bytes for `CarrotClient::send_raw_transaction`.
+
+use std::sync::Arc;
+
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+
+use crate::{
+    client::redeem_instructions,
+    error::{CarrotError, Result},
+    instructions::{
+        build_issue_instruction_with_accounts, AssetOracleAccounts, IssueAccounts, RedeemAccounts,
+        RemainingAccountsResolver,
+    },
+    token::TokenProgram,
+    Vault, VaultHandle, CRT_MINT,
+};
+
+/// Builds complete deposit and withdrawal transactions from caller-supplied vault state
+///
+/// Nothing is fetched, so nothing the online client checks is checked here either: deposit
+/// limits, policies, idle liquidity and transfer hook accounts are the caller's responsibility.
+/// Assets whose mint has a transfer hook can't be redeemed offline.
+#[derive(Debug, Clone)]
+pub struct OfflineBuilder {
+    vault: VaultHandle,
+    recent_blockhash: Hash,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+    remaining_accounts: Arc<dyn RemainingAccountsResolver>,
+}
+
+impl OfflineBuilder {
+    /// Builder for `vault`, with transactions valid for `recent_blockhash`
+    pub fn new(vault: VaultHandle, recent_blockhash: Hash) -> Self {
+        Self {
+            vault,
+            recent_blockhash,
+            compute_unit_limit: None,
+            compute_unit_price: None,
+            remaining_accounts: Arc::new(AssetOracleAccounts),
+        }
+    }
+
+    /// Builder for the vault at `address` whose raw account data is `data`
+    pub fn from_account_data(address: Pubkey, data: &[u8], recent_blockhash: Hash) -> Result<Self> {
+        Ok(Self::new(VaultHandle::new(address, Vault::from_account_data(data)?), recent_blockhash))
+    }
+
+    /// Set the compute-unit limit (the runtime default applies when unset)
+    pub fn with_compute_unit_limit(mut self, limit: u32) -> Self {
+        self.compute_unit_limit = Some(limit);
+        self
+    }
+
+    /// Pay `micro_lamports` per compute unit (no priority fee when unset)
+    pub fn with_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    /// Resolve the remaining accounts of issue and redeem instructions with `resolver`
+    pub fn with_remaining_accounts_resolver(mut self, resolver: Arc<dyn RemainingAccountsResolver>) -> Self {
+        self.remaining_accounts = resolver;
+        self
+    }
+
+    /// Vault state transactions are built against
+    pub fn vault(&self) -> &VaultHandle {
+        &self.vault
+    }
+
+    /// Instructions for a deposit: CRT ATA creation + issue
+    pub fn deposit_instructions(&self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<Vec<Instruction>> {
+        if self.vault.vault.paused {
+            return Err(CarrotError::VaultPaused);
+        }
+        let resolver = self.remaining_accounts.as_ref();
+        let accounts = IssueAccounts::new_with_resolver(&self.vault, user, asset_mint, resolver)?;
+        Ok(vec![
+            TokenProgram::Token2022.create_associated_token_account(user, user, &CRT_MINT),
+            build_issue_instruction_with_accounts(&accounts, amount)?,
+        ])
+    }
+
+    /// Instructions for redeeming `shares` CRT: asset ATA creation + redeem
+    pub fn withdraw_instructions(&self, user: &Pubkey, asset_mint: &Pubkey, shares: u64) -> Result<Vec<Instruction>> {
+        let resolver = self.remaining_accounts.as_ref();
+        let accounts = RedeemAccounts::new_with_resolver(&self.vault, user, asset_mint, resolver)?;
+        redeem_instructions(&self.vault, &accounts, shares, &[])
+    }
+
+    /// Unsigned deposit transaction paid by `user`; sign it with `Transaction::sign`
+    pub fn deposit_transaction(&self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<Transaction> {
+        Ok(self.transaction(&self.deposit_instructions(user, asset_mint, amount)?, user))
+    }
+
+    /// Unsigned withdrawal transaction paid by `user`; sign it with `Transaction::sign`
+    pub fn withdraw_transaction(&self, user: &Pubkey, asset_mint: &Pubkey, shares: u64) -> Result<Transaction> {
+        Ok(self.transaction(&self.withdraw_instructions(user, asset_mint, shares)?, user))
+    }
+
+    /// Unsigned transaction of `instructions` with the configured compute budget prepended
+    pub fn transaction(&self, instructions: &[Instruction], payer: &Pubkey) -> Transaction {
+        let mut all_instructions = Vec::new();
+        if let Some(limit) = self.compute_unit_limit {
+            all_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+        }
+        if let Some(price) = self.compute_unit_price {
+            all_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        all_instructions.extend_from_slice(instructions);
+        let message = Message::new_with_blockhash(&all_instructions, Some(payer), &self.recent_blockhash);
+        Transaction::new_unsigned(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::{decode_instruction, CarrotInstruction};
+    use crate::{Asset, Fee, USDC_MINT, VAULT_ADDRESS};
+    use solana_sdk::{signature::Keypair, signer::Signer};
+
+    fn vault_data(paused: bool) -> Vec<u8> {
+        Vault {
+            authority: Pubkey::new_unique(),
+            shares: CRT_MINT,
+            fee: Fee::default(),
+            paused,
+            asset_index: 1,
+            strategy_index: 0,
+            assets: vec![Asset {
+                asset_id: 0,
+                mint: USDC_MINT,
+                decimals: 6,
+                ata: Pubkey::new_unique(),
+                oracle: Pubkey::new_unique(),
+            }],
+            strategies: vec![],
+        }
+        .to_account_data()
+    }
+
+    #[test]
+    fn test_builds_signable_deposit_offline() {
+        let user = Keypair::new();
+        let blockhash = Hash::new_unique();
+        let builder = OfflineBuilder::from_account_data(VAULT_ADDRESS, &vault_data(false), blockhash)
+            .unwrap()
+            .with_compute_unit_limit(200_000)
+            .with_compute_unit_price(1_000);
+
+        let mut transaction = builder.deposit_transaction(&user.pubkey(), &USDC_MINT, 1_000_000).unwrap();
+        assert_eq!(transaction.message.recent_blockhash, blockhash);
+        assert_eq!(transaction.message.instructions.len(), 4);
+        let issue = transaction.message.instructions[3].clone();
+        let accounts: Vec<Pubkey> = issue
+            .accounts
+            .iter()
+            .map(|index| transaction.message.account_keys[*index as usize])
+            .collect();
+        assert_eq!(
+            decode_instruction(&issue.data, &accounts),
+            Some(CarrotInstruction::Issue {
+                user: user.pubkey(),
+                asset_mint: USDC_MINT,
+                amount: 1_000_000
+            })
+        );
+
+        transaction.sign(&[&user], blockhash);
+        assert!(transaction.verify().is_ok());
+        assert!(builder.withdraw_transaction(&user.pubkey(), &USDC_MINT, 5).is_ok());
+    }
+
+    #[test]
+    fn test_refuses_paused_vault_and_bad_data() {
+        let builder = OfflineBuilder::from_account_data(VAULT_ADDRESS, &vault_data(true), Hash::default()).unwrap();
+        assert!(matches!(
+            builder.deposit_instructions(&Pubkey::new_unique(), &USDC_MINT, 1),
+            Err(CarrotError::VaultPaused)
+        ));
+        assert!(OfflineBuilder::from_account_data(VAULT_ADDRESS, &[0; 16], Hash::default()).is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use borsh::BorshDeserialize;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::error::{CarrotError, Result};
+
+/// Pyth pull oracle (price receiver) program ID
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Pyth push oracle program ID (sponsored feeds, `PriceUpdateV2` accounts at fixed addresses)
+pub const PYTH_PUSH_ORACLE_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+
+/// Legacy Pyth oracle program ID (v2 price accounts)
+pub const PYTH_LEGACY_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+
+/// Switchboard On-Demand program ID
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+/// Anchor discriminator of Pyth `PriceUpdateV2` accounts: sha256("account:PriceUpdateV2")[..8]
+pub(crate) const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+/// Anchor discriminator of Switchboard On-Demand `PullFeedAccountData` accounts
+pub(crate) const PULL_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+
+/// Decimals of Switchboard On-Demand values
+const SWITCHBOARD_PRECISION: i32 = 18;
+
+/// Offsets in a `PullFeedAccountData` account (discriminator included) of the Unix timestamp of
+/// the last update and of the current result's median, standard deviation and slot
+const PULL_FEED_LAST_UPDATE_OFFSET: usize = 8 + 2208;
+const PULL_FEED_VALUE_OFFSET: usize = 8 + 2256;
+const PULL_FEED_STD_DEV_OFFSET: usize = PULL_FEED_VALUE_OFFSET + 16;
+const PULL_FEED_RESULT_SLOT_OFFSET: usize = PULL_FEED_VALUE_OFFSET + 104;
+
+/// Magic number at the start of legacy Pyth accounts
+const PYTH_LEGACY_MAGIC: u32 = 0xa1b2c3d4;
+
+/// Legacy Pyth account type of price accounts
+const PYTH_LEGACY_PRICE_ACCOUNT_TYPE: u32 = 3;
+
+/// Legacy Pyth aggregate status of a price that is currently trading
+const PYTH_LEGACY_STATUS_TRADING: u32 = 1;
+
+/// Oracle provider of an account, detected from its owning program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OracleProvider {
+    /// Pyth push oracle feed (`PriceUpdateV2` owned by the push oracle program)
+    PythPush,
+    /// Pyth pull oracle update (`PriceUpdateV2` owned by the receiver program)
+    PythPull,
+    /// Legacy Pyth v2 price account
+    PythLegacy,
+    /// Switchboard On-Demand pull feed
+    Switchboard,
+    /// Any other owner
+    Unknown(Pubkey),
+}
+
+impl OracleProvider {
+    /// Detect the provider from the program owning the oracle account
+    pub fn from_owner(owner: &Pubkey) -> Self {
+        match *owner {
+            PYTH_PUSH_ORACLE_PROGRAM_ID => Self::PythPush,
+            PYTH_RECEIVER_PROGRAM_ID => Self::PythPull,
+            PYTH_LEGACY_PROGRAM_ID => Self::PythLegacy,
+            SWITCHBOARD_ON_DEMAND_PROGRAM_ID => Self::Switchboard,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Price reported by an oracle: `price * 10^exponent`, with confidence `conf` in the same units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    /// Unix timestamp the price was published at
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Price as a floating point number (for display only)
+    pub fn as_f64(&self) -> f64 {
+        self.price as f64 * 10f64.powi(self.exponent)
+    }
+
+    /// Bottom of the confidence interval (`price - conf`)
+    pub fn bid(&self) -> OraclePrice {
+        let conf = i64::try_from(self.conf).unwrap_or(i64::MAX);
+        OraclePrice {
+            price: self.price.saturating_sub(conf),
+            ..*self
+        }
+    }
+
+    /// Top of the confidence interval (`price + conf`)
+    pub fn ask(&self) -> OraclePrice {
+        let conf = i64::try_from(self.conf).unwrap_or(i64::MAX);
+        OraclePrice {
+            price: self.price.saturating_add(conf),
+            ..*self
+        }
+    }
+}
+
+/// Pyth verification level of a price update
+#[derive(Debug, BorshDeserialize)]
+#[allow(dead_code)]
+enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+/// Pyth price feed message
+#[derive(Debug, BorshDeserialize)]
+#[allow(dead_code)]
+struct PriceFeedMessage {
+    feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    prev_publish_time: i64,
+    ema_price: i64,
+    ema_conf: u64,
+}
+
+/// Pyth `PriceUpdateV2` account (owned by the price receiver program)
+#[derive(Debug, BorshDeserialize)]
+#[allow(dead_code)]
+struct PriceUpdateV2 {
+    write_authority: Pubkey,
+    verification_level: VerificationLevel,
+    price_message: PriceFeedMessage,
+    posted_slot: u64,
+}
+
+/// Decode the price from a Pyth pull oracle `PriceUpdateV2` account
+pub fn decode_pyth_price_update(data: &[u8]) -> Result<OraclePrice> {
+    if data.len() < 8 || data[..8] != PRICE_UPDATE_V2_DISCRIMINATOR {
+        return Err(CarrotError::OracleError(
+            "account is not a Pyth PriceUpdateV2".to_string(),
+        ));
+    }
+
+    let update = PriceUpdateV2::deserialize(&mut &data[8..])
+        .map_err(|e| CarrotError::OracleError(format!("invalid Pyth price update: {}", e)))?;
+
+    let message = update.price_message;
+    Ok(OraclePrice {
+        price: message.price,
+        conf: message.conf,
+        exponent: message.exponent,
+        publish_time: message.publish_time,
+    })
+}
+
+/// Decode the current result of a Switchboard On-Demand pull feed (`PullFeedAccountData`)
+///
+/// The median is the price and its standard deviation the confidence. Values carry 18
+/// decimals; digits are dropped from the end until both fit an `i64`.
+pub fn decode_switchboard_pull_feed(data: &[u8]) -> Result<OraclePrice> {
+    if data.len() < 8 || data[..8] != PULL_FEED_DISCRIMINATOR {
+        return Err(CarrotError::OracleError(
+            "account is not a Switchboard pull feed".to_string(),
+        ));
+    }
+    let read = |offset: usize, len: usize| -> Result<&[u8]> {
+        data.get(offset..offset + len)
+            .ok_or_else(|| CarrotError::OracleError("Switchboard pull feed account too short".to_string()))
+    };
+    let i128_at = |offset| read(offset, 16).map(|b| i128::from_le_bytes(b.try_into().unwrap()));
+    let i64_at = |offset| read(offset, 8).map(|b| i64::from_le_bytes(b.try_into().unwrap()));
+
+    if i64_at(PULL_FEED_RESULT_SLOT_OFFSET)? == 0 {
+        return Err(CarrotError::OracleError(
+            "Switchboard pull feed has no result yet".to_string(),
+        ));
+    }
+    let mut value = i128_at(PULL_FEED_VALUE_OFFSET)?;
+    let mut std_dev = i128_at(PULL_FEED_STD_DEV_OFFSET)?.unsigned_abs();
+    let mut exponent = -SWITCHBOARD_PRECISION;
+    while i64::try_from(value).is_err() || u64::try_from(std_dev).is_err() {
+        value /= 10;
+        std_dev /= 10;
+        exponent += 1;
+    }
+    Ok(OraclePrice {
+        price: value as i64,
+        conf: std_dev as u64,
+        exponent,
+        publish_time: i64_at(PULL_FEED_LAST_UPDATE_OFFSET)?,
+    })
+}
+
+/// Decode an oracle account, routing on the provider detected from its owner
+pub fn decode_oracle_account(account: &Account) -> Result<OraclePrice> {
+    match OracleProvider::from_owner(&account.owner) {
+        OracleProvider::PythPush | OracleProvider::PythPull => decode_pyth_price_update(&account.data),
+        OracleProvider::PythLegacy => decode_pyth_legacy_price(&account.data),
+        OracleProvider::Switchboard => decode_switchboard_pull_feed(&account.data),
+        OracleProvider::Unknown(owner) => Err(CarrotError::OracleError(format!(
+            "oracle account owned by unknown program {}",
+            owner
+        ))),
+    }
+}
+
+/// Decode the aggregate price from a legacy Pyth v2 price account
+///
+/// Fails with `OracleNotTrading` unless the aggregate's status is trading: a halted or
+/// unknown aggregate keeps its last price, which must not be used.
+pub fn decode_pyth_legacy_price(data: &[u8]) -> Result<OraclePrice> {
+    let read = |offset: usize, len: usize| -> Result<&[u8]> {
+        data.get(offset..offset + len)
+            .ok_or_else(|| CarrotError::OracleError("legacy Pyth price account too short".to_string()))
+    };
+    let u32_at = |offset| read(offset, 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    let i64_at = |offset| read(offset, 8).map(|b| i64::from_le_bytes(b.try_into().unwrap()));
+
+    if u32_at(0)? != PYTH_LEGACY_MAGIC || u32_at(8)? != PYTH_LEGACY_PRICE_ACCOUNT_TYPE {
+        return Err(CarrotError::OracleError(
+            "account is not a legacy Pyth price account".to_string(),
+        ));
+    }
+
+    // Layout: expo at 20, timestamp at 96, aggregate price/conf/status at 208/216/224
+    let status = u32_at(224)?;
+    if status != PYTH_LEGACY_STATUS_TRADING {
+        let status = match status {
+            0 => "unknown".to_string(),
+            2 => "halted".to_string(),
+            3 => "auction".to_string(),
+            4 => "ignored".to_string(),
+            other => format!("status {}", other),
+        };
+        return Err(CarrotError::OracleNotTrading { status });
+    }
+    Ok(OraclePrice {
+        price: i64_at(208)?,
+        conf: i64_at(216)? as u64,
+        exponent: u32_at(20)? as i32,
+        publish_time: i64_at(96)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Encode a `PriceUpdateV2` account with the given price
+    pub(crate) fn pyth_price_update_data(price: i64, conf: u64, exponent: i32) -> Vec<u8> {
+        let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[7u8; 32]); // write authority
+        data.push(1); // VerificationLevel::Full
+        data.extend_from_slice(&[9u8; 32]); // feed id
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // publish time
+        data.extend_from_slice(&1_699_999_999i64.to_le_bytes()); // prev publish time
+        data.extend_from_slice(&price.to_le_bytes()); // ema price
+        data.extend_from_slice(&conf.to_le_bytes()); // ema conf
+        data.extend_from_slice(&300_000_000u64.to_le_bytes()); // posted slot
+        data
+    }
+
+    /// Encode a `PullFeedAccountData` account field by field, in declaration order
+    fn switchboard_pull_feed_data(value: i128, std_dev: i128, result_slot: u64) -> Vec<u8> {
+        let mut data = PULL_FEED_DISCRIMINATOR.to_vec();
+        for _ in 0..32 {
+            data.extend_from_slice(&[3u8; 32]); // oracle
+            data.extend_from_slice(&result_slot.to_le_bytes()); // slot
+            data.extend_from_slice(&(result_slot + 1).to_le_bytes()); // landed at
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[4u8; 32]); // authority
+        data.extend_from_slice(&[5u8; 32]); // queue
+        data.extend_from_slice(&[6u8; 32]); // feed hash
+        data.extend_from_slice(&1_690_000_000i64.to_le_bytes()); // initialized at
+        data.extend_from_slice(&0u64.to_le_bytes()); // permissions
+        data.extend_from_slice(&1_000_000_000u64.to_le_bytes()); // max variance
+        data.extend_from_slice(&1u32.to_le_bytes()); // min responses
+        let mut name = [0u8; 32];
+        name[..8].copy_from_slice(b"USDC/USD");
+        data.extend_from_slice(&name);
+        data.extend_from_slice(&[0, 0, 7, 1]); // padding, historical result index, min sample size
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // last update timestamp
+        data.extend_from_slice(&250_000_000u64.to_le_bytes()); // lookup table slot
+        data.extend_from_slice(&[0u8; 32]); // reserved
+        for field in [value, std_dev, value, std_dev * 4, value - std_dev * 2, value + std_dev * 2] {
+            data.extend_from_slice(&field.to_le_bytes()); // value, std dev, mean, range, min, max
+        }
+        data.extend_from_slice(&[5, 0, 0, 0, 0, 0, 0, 0]); // samples, submission index, padding
+        for slot in [result_slot, result_slot.saturating_sub(2), result_slot] {
+            data.extend_from_slice(&slot.to_le_bytes()); // slot, min slot, max slot
+        }
+        data.extend_from_slice(&250u32.to_le_bytes()); // max staleness
+        data.extend_from_slice(&[0u8; 12]); // padding
+        data.extend_from_slice(&[0u8; 32 * 16]); // historical results
+        data.extend_from_slice(&[0u8; 32]); // reserved
+        data.extend_from_slice(&[0u8; 32 * 8]); // submission timestamps
+        data
+    }
+
+    #[test]
+    fn test_decode_pyth_price_update() {
+        let data = pyth_price_update_data(99_985_000, 25_000, -8);
+        let price = decode_pyth_price_update(&data).unwrap();
+        assert_eq!(price.price, 99_985_000);
+        assert_eq!(price.conf, 25_000);
+        assert_eq!(price.exponent, -8);
+        assert_eq!(price.publish_time, 1_700_000_000);
+        assert!((price.as_f64() - 0.99985).abs() < 1e-12);
+        assert_eq!(price.bid().price, 99_960_000);
+        assert_eq!(price.ask().price, 100_010_000);
+    }
+
+    #[test]
+    fn test_decode_routes_by_owner() {
+        let account = |owner: Pubkey, data: Vec<u8>| Account {
+            lamports: 1,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let update = pyth_price_update_data(100_000_000, 0, -8);
+        assert!(decode_oracle_account(&account(PYTH_RECEIVER_PROGRAM_ID, update.clone())).is_ok());
+        assert!(decode_oracle_account(&account(PYTH_PUSH_ORACLE_PROGRAM_ID, update.clone())).is_ok());
+        assert!(decode_oracle_account(&account(Pubkey::new_unique(), update)).is_err());
+
+        let mut legacy = vec![0u8; 240];
+        legacy[0..4].copy_from_slice(&PYTH_LEGACY_MAGIC.to_le_bytes());
+        legacy[8..12].copy_from_slice(&PYTH_LEGACY_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        legacy[20..24].copy_from_slice(&(-8i32).to_le_bytes());
+        legacy[96..104].copy_from_slice(&1_700_000_000i64.to_le_bytes());
+        legacy[208..216].copy_from_slice(&99_990_000i64.to_le_bytes());
+        legacy[216..224].copy_from_slice(&5_000u64.to_le_bytes());
+        legacy[224..228].copy_from_slice(&PYTH_LEGACY_STATUS_TRADING.to_le_bytes());
+        let price = decode_oracle_account(&account(PYTH_LEGACY_PROGRAM_ID, legacy.clone())).unwrap();
+        assert_eq!((price.price, price.conf, price.exponent), (99_990_000, 5_000, -8));
+        assert_eq!(price.publish_time, 1_700_000_000);
+
+        // A halted aggregate still carries its last price, which isn't used
+        legacy[224..228].copy_from_slice(&2u32.to_le_bytes());
+        assert!(matches!(
+            decode_pyth_legacy_price(&legacy),
+            Err(CarrotError::OracleNotTrading { status }) if status == "halted"
+        ));
+
+        assert_eq!(
+            OracleProvider::from_owner(&SWITCHBOARD_ON_DEMAND_PROGRAM_ID),
+            OracleProvider::Switchboard
+        );
+    }
+
+    #[test]
+    fn test_decode_switchboard_pull_feed() {
+        let data = switchboard_pull_feed_data(1_000_100_000_000_000_000, 250_000_000_000_000, 300_000_000);
+        assert_eq!(data.len(), 3_208);
+        let price = decode_switchboard_pull_feed(&data).unwrap();
+        assert_eq!(price.price, 1_000_100_000_000_000_000);
+        assert_eq!(price.conf, 250_000_000_000_000);
+        assert_eq!(price.exponent, -18);
+        assert_eq!(price.publish_time, 1_700_000_000);
+        assert!((price.as_f64() - 1.0001).abs() < 1e-12);
+
+        // 65,000.5 doesn't fit an i64 with 18 decimals: the last digits are dropped
+        let account = Account {
+            lamports: 1,
+            data: switchboard_pull_feed_data(65_000_500_000_000_000_000_000, 12_345_678_900_000_000_000, 300_000_000),
+            owner: SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let price = decode_oracle_account(&account).unwrap();
+        assert_eq!((price.price, price.conf, price.exponent), (6_500_050_000_000_000_000, 1_234_567_890_000_000, -14));
+
+        assert!(decode_switchboard_pull_feed(&switchboard_pull_feed_data(1, 0, 0)).is_err());
+        assert!(decode_switchboard_pull_feed(&data[..2_300]).is_err());
+        assert!(decode_switchboard_pull_feed(&pyth_price_update_data(100_000_000, 0, -8)).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_other_accounts() {
+        assert!(decode_pyth_price_update(&[0u8; 134]).is_err());
+        assert!(decode_pyth_price_update(&PRICE_UPDATE_V2_DISCRIMINATOR).is_err());
+    }
+}
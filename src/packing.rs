@@ -0,0 +1,247 @@
+//! Packing instructions into the smallest transaction that carries them
+//!
+//! Composed operations (ATA creation + compute budget + memo + issue, possibly a swap) can
+//! overflow the 1232-byte packet limit. `Packer` orders the instructions, drops the ones that
+//! do nothing and checks the size as a legacy transaction first, falling back to a v0 message
+//! with address lookup tables only when the legacy one doesn't fit.
+
+use std::collections::HashSet;
+
+use solana_packet::PACKET_DATA_SIZE;
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, AddressLookupTableAccount, Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use solana_sdk_ids::compute_budget;
+
+use crate::error::{CarrotError, Result};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// First data byte of `SetComputeUnitPrice`
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Orders and prunes instructions and picks the transaction format they fit in
+#[derive(Debug, Clone)]
+pub struct Packer {
+    payer: Pubkey,
+    existing_accounts: HashSet<Pubkey>,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+/// Message produced by `Packer::pack`, ready to sign with `VersionedTransaction::try_new`
+#[derive(Debug, Clone)]
+pub struct PackedTransaction {
+    /// Legacy message when it fits, v0 with lookup tables otherwise
+    pub message: VersionedMessage,
+    /// Serialized size of the signed transaction, in bytes
+    pub size: usize,
+    /// Instructions dropped as redundant
+    pub dropped: usize,
+}
+
+impl PackedTransaction {
+    pub fn is_legacy(&self) -> bool {
+        matches!(self.message, VersionedMessage::Legacy(_))
+    }
+}
+
+impl Packer {
+    /// Packer for transactions paid by `payer`
+    pub fn new(payer: Pubkey) -> Self {
+        Self {
+            payer,
+            existing_accounts: HashSet::new(),
+            lookup_tables: Vec::new(),
+        }
+    }
+
+    /// Accounts known to exist; instructions creating them are dropped
+    pub fn with_existing_accounts(mut self, accounts: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.existing_accounts.extend(accounts);
+        self
+    }
+
+    /// Lookup table used when the legacy transaction doesn't fit
+    pub fn with_lookup_table(mut self, table: AddressLookupTableAccount) -> Self {
+        self.lookup_tables.push(table);
+        self
+    }
+
+    /// `instructions` in packing order, without the redundant ones
+    ///
+    /// Compute budget instructions come first, one of each kind (the last one wins, and a zero
+    /// compute-unit price is dropped), then associated token account creations, once per
+    /// account and only for accounts not known to exist, then everything else in its given
+    /// order. Each address is stored once in the message however often it's referenced.
+    pub fn optimize(&self, instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut budget: Vec<Instruction> = Vec::new();
+        let mut creations: Vec<Instruction> = Vec::new();
+        let mut created = HashSet::new();
+        let mut rest = Vec::new();
+        for instruction in instructions {
+            if instruction.program_id == compute_budget::id() {
+                let kind = instruction.data.first();
+                budget.retain(|existing| existing.data.first() != kind);
+                let zero_price = kind == Some(&SET_COMPUTE_UNIT_PRICE) && instruction.data[1..].iter().all(|b| *b == 0);
+                if !zero_price {
+                    budget.push(instruction.clone());
+                }
+            } else if instruction.program_id == spl_associated_token_account::id() {
+                let Some(account) = instruction.accounts.get(1).map(|meta| meta.pubkey) else {
+                    rest.push(instruction.clone());
+                    continue;
+                };
+                if !self.existing_accounts.contains(&account) && created.insert(account) {
+                    creations.push(instruction.clone());
+                }
+            } else {
+                rest.push(instruction.clone());
+            }
+        }
+        budget.into_iter().chain(creations).chain(rest).collect()
+    }
+
+    /// Optimize `instructions` and compile them into a legacy message if it fits in a packet,
+    /// a v0 message using the lookup tables otherwise
+    ///
+    /// Fails with `TransactionTooLarge` if neither fits.
+    pub fn pack(&self, instructions: &[Instruction], recent_blockhash: Hash) -> Result<PackedTransaction> {
+        let optimized = self.optimize(instructions);
+        let dropped = instructions.len() - optimized.len();
+
+        let legacy = Message::new_with_blockhash(&optimized, Some(&self.payer), &recent_blockhash);
+        let legacy = VersionedMessage::Legacy(legacy);
+        let legacy_size = signed_size(&legacy)?;
+        if legacy_size <= PACKET_DATA_SIZE || self.lookup_tables.is_empty() {
+            return fitting(legacy, legacy_size, dropped);
+        }
+
+        let v0 = v0::Message::try_compile(&self.payer, &optimized, &self.lookup_tables, recent_blockhash)
+            .map_err(|e| CarrotError::TransactionFailed(format!("failed to compile v0 message: {}", e)))?;
+        let v0 = VersionedMessage::V0(v0);
+        let v0_size = signed_size(&v0)?;
+        fitting(v0, v0_size, dropped)
+    }
+}
+
+fn fitting(message: VersionedMessage, size: usize, dropped: usize) -> Result<PackedTransaction> {
+    if size > PACKET_DATA_SIZE {
+        return Err(CarrotError::TransactionTooLarge { size });
+    }
+    Ok(PackedTransaction { message, size, dropped })
+}
+
+/// Serialized size of `message` with all its signatures
+fn signed_size(message: &VersionedMessage) -> Result<usize> {
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message: message.clone(),
+    };
+    bincode::serialized_size(&transaction)
+        .map(|size| size as usize)
+        .map_err(|e| CarrotError::TransactionFailed(format!("failed to size transaction: {}", e)))
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Pack `instructions` paid by `payer` (see `Packer`), dropping creations of accounts that
+    /// already exist and falling back to the lookup tables at `lookup_tables`
+    ///
+    /// The result still needs signing; the allowlist is checked as for any built transaction.
+    pub fn pack_instructions(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        lookup_tables: &[Pubkey],
+    ) -> Result<PackedTransaction> {
+        if self.config().verify_programs {
+            self.program_allowlist().verify(instructions)?;
+        }
+        let created: Vec<Pubkey> = instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == spl_associated_token_account::id())
+            .filter_map(|instruction| instruction.accounts.get(1).map(|meta| meta.pubkey))
+            .collect();
+        let existing = created
+            .iter()
+            .zip(self.rpc_client().get_multiple_accounts(&created)?)
+            .filter_map(|(address, account)| account.map(|_| *address));
+
+        let mut packer = Packer::new(*payer).with_existing_accounts(existing);
+        for table in lookup_tables {
+            packer = packer.with_lookup_table(AddressLookupTableAccount {
+                key: *table,
+                addresses: self.get_lookup_table_addresses(table)?,
+            });
+        }
+        let (recent_blockhash, _) = self.latest_blockhash()?;
+        packer.pack(instructions, recent_blockhash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenProgram;
+    use crate::{CRT_MINT, USDC_MINT};
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_sdk::instruction::AccountMeta;
+
+    fn wide_instruction(accounts: &[Pubkey]) -> Instruction {
+        let metas = accounts.iter().map(|account| AccountMeta::new_readonly(*account, false)).collect();
+        Instruction::new_with_bytes(Pubkey::new_unique(), &[0; 8], metas)
+    }
+
+    #[test]
+    fn test_optimize_orders_and_prunes() {
+        let payer = Pubkey::new_unique();
+        let create_crt = TokenProgram::Token2022.create_associated_token_account(&payer, &payer, &CRT_MINT);
+        let create_usdc = TokenProgram::Token.create_associated_token_account(&payer, &payer, &USDC_MINT);
+        let other = wide_instruction(&[Pubkey::new_unique()]);
+        let instructions = vec![
+            other.clone(),
+            create_crt.clone(),
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            create_crt.clone(),
+            create_usdc.clone(),
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(0),
+        ];
+
+        let usdc_ata = create_usdc.accounts[1].pubkey;
+        let packer = Packer::new(payer).with_existing_accounts([usdc_ata]);
+        assert_eq!(
+            packer.optimize(&instructions),
+            vec![ComputeBudgetInstruction::set_compute_unit_limit(200_000), create_crt, other]
+        );
+        let packed = packer.pack(&instructions, Hash::new_unique()).unwrap();
+        assert!(packed.is_legacy());
+        assert_eq!(packed.dropped, 4);
+    }
+
+    #[test]
+    fn test_falls_back_to_lookup_tables() {
+        let payer = Pubkey::new_unique();
+        // 40 distinct addresses (1,280 bytes of keys alone) can't fit in a legacy transaction
+        let addresses: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+        let instructions = [wide_instruction(&addresses)];
+        let blockhash = Hash::new_unique();
+
+        assert!(matches!(
+            Packer::new(payer).pack(&instructions, blockhash),
+            Err(CarrotError::TransactionTooLarge { .. })
+        ));
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses,
+        };
+        let packed = Packer::new(payer).with_lookup_table(table).pack(&instructions, blockhash).unwrap();
+        assert!(!packed.is_legacy());
+        assert!(packed.size <= PACKET_DATA_SIZE);
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+};
+
+use crate::{
+    client::CarrotClient,
+    error::{CarrotError, Result},
+    liquidity::WithdrawalLiquidity,
+    nav::{deduct_fee_bps, AssetAmount, CrtAmount, Nav, Rounding},
+    quote::QuoteSide,
+};
+
+/// Deposits, withdrawals and balances, implemented by `CarrotClient` on-chain and by
+/// `PaperTrader` in memory, so strategy code can run against either
+pub trait VaultTrader {
+    /// Deposit `amount` of `asset_mint`, minting CRT
+    fn deposit(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature>;
+
+    /// Redeem `amount` CRT for `asset_mint`
+    fn withdraw(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature>;
+
+    fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64>;
+
+    fn get_crt_balance(&self, user: &Pubkey) -> Result<u64>;
+
+    /// CRT minted for depositing `amount` of `asset_mint` at the current NAV
+    fn preview_deposit(&self, asset_mint: &Pubkey, amount: u64) -> Result<u64>;
+
+    /// `asset_mint` received for redeeming `shares` CRT at the current NAV, net of the redemption fee
+    fn preview_withdraw(&self, asset_mint: &Pubkey, shares: u64) -> Result<u64>;
+}
+
+impl VaultTrader for CarrotClient {
+    fn deposit(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        CarrotClient::deposit(self, user, asset_mint, amount)
+    }
+
+    fn withdraw(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        CarrotClient::withdraw(self, user, asset_mint, amount)
+    }
+
+    fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64> {
+        CarrotClient::get_asset_balance(self, user, asset_mint)
+    }
+
+    fn get_crt_balance(&self, user: &Pubkey) -> Result<u64> {
+        CarrotClient::get_crt_balance(self, user)
+    }
+
+    fn preview_deposit(&self, asset_mint: &Pubkey, amount: u64) -> Result<u64> {
+        CarrotClient::preview_deposit(self, asset_mint, amount)
+    }
+
+    fn preview_withdraw(&self, asset_mint: &Pubkey, shares: u64) -> Result<u64> {
+        CarrotClient::preview_withdraw(self, asset_mint, shares)
+    }
+}
+
+/// A simulated deposit or withdrawal executed by a `PaperTrader`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaperFill {
+    /// Placeholder signature identifying the fill (never sent to the cluster)
+    pub signature: Signature,
+    pub side: QuoteSide,
+    pub user: Pubkey,
+    pub asset_mint: Pubkey,
+    /// Asset spent (deposit) or received net of the redemption fee (withdrawal)
+    pub asset_amount: u64,
+    /// CRT minted (deposit) or burned (withdrawal)
+    pub shares: u64,
+    /// Redemption fee withheld, in asset units
+    pub fee: u64,
+    /// Unix timestamp of the fill
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Default)]
+struct PaperBook {
+    assets: HashMap<(Pubkey, Pubkey), u64>,
+    shares: HashMap<Pubkey, u64>,
+    fills: Vec<PaperFill>,
+}
+
+/// Paper trading: deposits and withdrawals priced at the live NAV and redemption fee,
+/// settled against in-memory balances instead of the chain
+///
+/// Nothing is signed or sent; balances start at zero until funded with `fund`. Vault-side
+/// checks that would fail on-chain (pause, idle liquidity) still apply. Clones share the
+/// same session.
+#[derive(Clone)]
+pub struct PaperTrader {
+    client: CarrotClient,
+    book: Arc<Mutex<PaperBook>>,
+}
+
+impl PaperTrader {
+    /// Session reading prices through `client`
+    pub fn new(client: CarrotClient) -> Self {
+        Self {
+            client,
+            book: Arc::new(Mutex::new(PaperBook::default())),
+        }
+    }
+
+    /// Credit `user` with `amount` of `asset_mint` to trade with
+    pub fn fund(&self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) {
+        let mut book = self.book();
+        let balance = book.assets.entry((*user, *asset_mint)).or_default();
+        *balance = balance.saturating_add(amount);
+    }
+
+    /// Fills executed so far, oldest first
+    pub fn fills(&self) -> Vec<PaperFill> {
+        self.book().fills.clone()
+    }
+
+    /// USD value (`VALUE_DECIMALS`) of `user`'s simulated CRT at the live NAV
+    pub fn position_value(&self, user: &Pubkey) -> Result<u128> {
+        let shares = self.book().shares.get(user).copied().unwrap_or_default();
+        self.client.fetch_nav()?.value_of_shares(shares, Rounding::Down)
+    }
+
+    fn book(&self) -> std::sync::MutexGuard<'_, PaperBook> {
+        // Balances are updated in single statements under the lock; a panic elsewhere can't
+        // leave them half-written
+        self.book.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn live_nav(&self) -> Result<(Nav, u16)> {
+        let snapshot = self.client.fetch_snapshot()?;
+        if snapshot.vault.paused {
+            return Err(CarrotError::VaultPaused);
+        }
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.client.now())?;
+        Ok((nav, snapshot.vault.fee.redemption_fee_bps))
+    }
+
+    /// Settle a deposit of `amount` at `nav`
+    fn deposit_at(&self, nav: &Nav, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        let shares = AssetAmount::new(*asset_mint, amount).to_expected_shares(nav, Rounding::Down)?.0;
+
+        let mut book = self.book();
+        let available = book.assets.get(&(*user, *asset_mint)).copied().unwrap_or_default();
+        if available < amount {
+            return Err(CarrotError::InsufficientBalance {
+                required: amount,
+                available,
+            });
+        }
+        book.assets.insert((*user, *asset_mint), available - amount);
+        *book.shares.entry(*user).or_default() += shares;
+        Ok(book.record(PaperFill {
+            signature: Signature::default(),
+            side: QuoteSide::Deposit,
+            user: *user,
+            asset_mint: *asset_mint,
+            asset_amount: amount,
+            shares,
+            fee: 0,
+            timestamp: self.client.now(),
+        }))
+    }
+
+    /// Settle a redemption of `shares` CRT at `nav`, charging `fee_bps`
+    fn withdraw_at(
+        &self,
+        nav: &Nav,
+        fee_bps: u16,
+        user: &Pubkey,
+        asset_mint: &Pubkey,
+        shares: u64,
+    ) -> Result<Signature> {
+        WithdrawalLiquidity::from_nav(nav, fee_bps, asset_mint, shares)?.ensure_sufficient()?;
+        let gross = CrtAmount(shares).to_asset_value(nav, asset_mint, Rounding::Down)?.amount;
+        let net = deduct_fee_bps(gross, fee_bps)?;
+
+        let mut book = self.book();
+        let available = book.shares.get(user).copied().unwrap_or_default();
+        if available < shares {
+            return Err(CarrotError::InsufficientBalance {
+                required: shares,
+                available,
+            });
+        }
+        book.shares.insert(*user, available - shares);
+        *book.assets.entry((*user, *asset_mint)).or_default() += net;
+        Ok(book.record(PaperFill {
+            signature: Signature::default(),
+            side: QuoteSide::Withdraw,
+            user: *user,
+            asset_mint: *asset_mint,
+            asset_amount: net,
+            shares,
+            fee: gross - net,
+            timestamp: self.client.now(),
+        }))
+    }
+}
+
+impl fmt::Debug for PaperTrader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaperTrader").field("fills", &self.book().fills.len()).finish()
+    }
+}
+
+impl PaperBook {
+    /// Append `fill` under a sequential placeholder signature and return it
+    fn record(&mut self, mut fill: PaperFill) -> Signature {
+        let mut bytes = [0u8; 64];
+        bytes[..8].copy_from_slice(&(self.fills.len() as u64 + 1).to_le_bytes());
+        let signature = Signature::from(bytes);
+        fill.signature = signature;
+        self.fills.push(fill);
+        signature
+    }
+}
+
+impl VaultTrader for PaperTrader {
+    fn deposit(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        let (nav, _) = self.live_nav()?;
+        self.deposit_at(&nav, &user.pubkey(), asset_mint, amount)
+    }
+
+    fn withdraw(&self, user: &dyn Signer, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        let (nav, fee_bps) = self.live_nav()?;
+        self.withdraw_at(&nav, fee_bps, &user.pubkey(), asset_mint, amount)
+    }
+
+    fn get_asset_balance(&self, user: &Pubkey, asset_mint: &Pubkey) -> Result<u64> {
+        Ok(self.book().assets.get(&(*user, *asset_mint)).copied().unwrap_or_default())
+    }
+
+    fn get_crt_balance(&self, user: &Pubkey) -> Result<u64> {
+        Ok(self.book().shares.get(user).copied().unwrap_or_default())
+    }
+
+    fn preview_deposit(&self, asset_mint: &Pubkey, amount: u64) -> Result<u64> {
+        self.client.preview_deposit(asset_mint, amount)
+    }
+
+    fn preview_withdraw(&self, asset_mint: &Pubkey, shares: u64) -> Result<u64> {
+        self.client.preview_withdraw(asset_mint, shares)
+    }
+}
+
+impl CarrotClient {
+    /// Paper-trading session priced by this client (see `PaperTrader`)
+    pub fn paper_trader(&self) -> PaperTrader {
+        PaperTrader::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nav::tests::test_nav;
+    use crate::USDC_MINT;
+
+    #[test]
+    fn test_paper_round_trip() {
+        let trader = CarrotClient::new("http://localhost:8899".to_string()).paper_trader();
+        let user = Pubkey::new_unique();
+        // 1,000 CRT backed by 1,100 USDC at $1: 1.1 USDC per CRT
+        let nav = test_nav(1_000_000_000_000, vec![(USDC_MINT, 1_100_000_000, 100_000_000)]);
+
+        assert!(matches!(
+            trader.deposit_at(&nav, &user, &USDC_MINT, 11_000_000),
+            Err(CarrotError::InsufficientBalance { available: 0, .. })
+        ));
+        trader.fund(&user, &USDC_MINT, 11_000_000);
+        let first = trader.deposit_at(&nav, &user, &USDC_MINT, 11_000_000).unwrap();
+        assert_eq!(trader.get_asset_balance(&user, &USDC_MINT).unwrap(), 0);
+        assert_eq!(trader.get_crt_balance(&user).unwrap(), 10_000_000_000);
+
+        // 1% redemption fee on 5.5 USDC
+        let second = trader.withdraw_at(&nav, 100, &user, &USDC_MINT, 5_000_000_000).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(trader.get_crt_balance(&user).unwrap(), 5_000_000_000);
+        assert_eq!(trader.get_asset_balance(&user, &USDC_MINT).unwrap(), 5_445_000);
+
+        // Clones share the session
+        let fills = trader.clone().fills();
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[1].side, QuoteSide::Withdraw);
+        assert_eq!(fills[1].fee, 55_000);
+        assert!(trader.withdraw_at(&nav, 100, &user, &USDC_MINT, 5_000_000_001).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+#[cfg(feature = "blocking")]
+use std::sync::Arc;
+
+use crate::{
+    error::{CarrotError, Result},
+    store::StateStore,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, supervisor::TaskGroup};
+
+/// `StateStore` namespace of pause state changes, keyed by zero-padded timestamp
+pub const PAUSE_HISTORY_NAMESPACE: &str = "pause-history";
+
+/// Pause state first observed at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PauseChange {
+    /// Unix timestamp of the observation
+    pub timestamp: i64,
+    pub slot: u64,
+    pub paused: bool,
+}
+
+/// A span during which the vault was paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PausePeriod {
+    /// When the pause was first observed
+    pub paused_at: i64,
+    pub paused_slot: u64,
+    /// When the unpause was first observed (`None` while still paused)
+    pub unpaused_at: Option<i64>,
+    pub unpaused_slot: Option<u64>,
+}
+
+impl PausePeriod {
+    /// How long the pause lasted, up to `now` if it is ongoing
+    pub fn duration(&self, now: i64) -> Duration {
+        let end = self.unpaused_at.unwrap_or(now);
+        Duration::from_secs(end.saturating_sub(self.paused_at).max(0) as u64)
+    }
+}
+
+/// Pause state changes of the vault, e.g. recorded by `spawn_pause_indexer`
+///
+/// Only changes are kept (plus the first observation), so each entry's time is when the change
+/// was first seen: accurate to the interval observations were taken at.
+#[derive(Debug, Clone, Default)]
+pub struct PauseHistory {
+    changes: BTreeMap<i64, PauseChange>,
+}
+
+impl PauseHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load changes saved to `store`
+    pub fn load_from(store: &dyn StateStore) -> Result<Self> {
+        let mut history = Self::new();
+        for (key, value) in store.scan(PAUSE_HISTORY_NAMESPACE)? {
+            let change = std::str::from_utf8(&value)
+                .ok()
+                .and_then(parse_change)
+                .ok_or_else(|| CarrotError::InvalidConfig(format!("malformed pause history entry {}", key)))?;
+            history.changes.insert(change.timestamp, change);
+        }
+        Ok(history)
+    }
+
+    /// Write every change to `store`
+    pub fn save_to(&self, store: &dyn StateStore) -> Result<()> {
+        self.changes.values().try_for_each(|change| save_change(store, change))
+    }
+
+    /// Record the pause state observed at `timestamp`; returns the change if it differs from
+    /// the latest earlier observation
+    pub fn observe(&mut self, timestamp: i64, slot: u64, paused: bool) -> Option<PauseChange> {
+        let previous = self.changes.range(..=timestamp).next_back().map(|(_, change)| change.paused);
+        if previous == Some(paused) {
+            return None;
+        }
+        let change = PauseChange { timestamp, slot, paused };
+        self.changes.insert(timestamp, change);
+        Some(change)
+    }
+
+    /// Recorded changes, oldest first
+    pub fn changes(&self) -> impl Iterator<Item = &PauseChange> {
+        self.changes.values()
+    }
+
+    /// Pause periods, oldest first
+    pub fn periods(&self) -> Vec<PausePeriod> {
+        let mut periods: Vec<PausePeriod> = Vec::new();
+        for change in self.changes.values() {
+            match (change.paused, periods.last_mut()) {
+                (true, Some(last)) if last.unpaused_at.is_none() => {}
+                (true, _) => periods.push(PausePeriod {
+                    paused_at: change.timestamp,
+                    paused_slot: change.slot,
+                    unpaused_at: None,
+                    unpaused_slot: None,
+                }),
+                (false, Some(last)) if last.unpaused_at.is_none() => {
+                    last.unpaused_at = Some(change.timestamp);
+                    last.unpaused_slot = Some(change.slot);
+                }
+                (false, _) => {}
+            }
+        }
+        periods
+    }
+
+    /// Whether the vault was paused at `timestamp` (`None` before the first observation)
+    pub fn paused_at(&self, timestamp: i64) -> Option<bool> {
+        self.changes
+            .range(..=timestamp)
+            .next_back()
+            .map(|(_, change)| change.paused)
+    }
+
+    /// Time the vault spent paused during the `window` ending at `now`
+    pub fn paused_duration(&self, window: Duration, now: i64) -> Duration {
+        let start = now.saturating_sub(window.as_secs() as i64);
+        self.periods()
+            .iter()
+            .map(|period| {
+                let from = period.paused_at.max(start);
+                let until = period.unpaused_at.unwrap_or(now).min(now);
+                until.saturating_sub(from).max(0) as u64
+            })
+            .map(Duration::from_secs)
+            .sum()
+    }
+}
+
+fn save_change(store: &dyn StateStore, change: &PauseChange) -> Result<()> {
+    let key = format!("{:020}", change.timestamp);
+    let value = format!("{},{},{}", change.timestamp, change.slot, change.paused);
+    store.put(PAUSE_HISTORY_NAMESPACE, &key, value.as_bytes())
+}
+
+fn parse_change(line: &str) -> Option<PauseChange> {
+    let mut fields = line.split(',');
+    let mut next = || fields.next().map(str::trim);
+    Some(PauseChange {
+        timestamp: next()?.parse().ok()?,
+        slot: next()?.parse().ok()?,
+        paused: next()?.parse().ok()?,
+    })
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Observe the vault's pause state every `interval` from a supervised task, saving each
+    /// change to `store`
+    pub fn spawn_pause_indexer(&self, group: &mut TaskGroup, store: Arc<dyn StateStore>, interval: Duration) {
+        let client = self.clone();
+        let mut history = None;
+        group.spawn_periodic("pause-indexer", interval, move || {
+            let history = match &mut history {
+                Some(history) => history,
+                None => history.insert(PauseHistory::load_from(store.as_ref())?),
+            };
+            let snapshot = client.fetch_snapshot()?;
+            if let Some(change) = history.observe(client.now(), snapshot.slot, snapshot.vault.paused) {
+                save_change(store.as_ref(), &change)?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Pause history `spawn_pause_indexer` keeps in the configured state store
+    /// (`ClientConfig::with_state_store`)
+    pub fn pause_history(&self) -> Result<PauseHistory> {
+        let store = self.config().state_store.as_ref().ok_or_else(|| {
+            CarrotError::InvalidConfig("pause_history needs a state store with pause history".to_string())
+        })?;
+        PauseHistory::load_from(store.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn test_periods_and_durations() {
+        let mut history = PauseHistory::new();
+        assert!(history.observe(0, 0, false).is_some());
+        assert!(history.observe(60, 1, false).is_none());
+        assert!(history.observe(120, 2, true).is_some());
+        assert!(history.observe(180, 3, true).is_none());
+        assert!(history.observe(600, 4, false).is_some());
+        assert!(history.observe(1_000, 5, true).is_some());
+
+        assert_eq!(
+            history.periods(),
+            [
+                PausePeriod {
+                    paused_at: 120,
+                    paused_slot: 2,
+                    unpaused_at: Some(600),
+                    unpaused_slot: Some(4),
+                },
+                PausePeriod {
+                    paused_at: 1_000,
+                    paused_slot: 5,
+                    unpaused_at: None,
+                    unpaused_slot: None,
+                },
+            ]
+        );
+        assert_eq!(history.periods()[1].duration(1_100), Duration::from_secs(100));
+        assert_eq!(history.paused_at(119), Some(false));
+        assert_eq!(history.paused_at(300), Some(true));
+        assert_eq!(history.paused_at(-1), None);
+        // 120..600 clipped to the window start at 500, plus 1000..1100
+        assert_eq!(history.paused_duration(Duration::from_secs(600), 1_100), Duration::from_secs(200));
+    }
+
+    #[test]
+    fn test_history_round_trips_through_store() {
+        let store = MemoryStore::new();
+        let mut history = PauseHistory::new();
+        history.observe(10, 1, false);
+        history.observe(20, 2, true);
+        history.save_to(&store).unwrap();
+
+        let loaded = PauseHistory::load_from(&store).unwrap();
+        assert_eq!(loaded.changes().collect::<Vec<_>>(), history.changes().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,847 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+
+use crate::{
+    config::ClientConfig,
+    error::{CarrotError, Result},
+    inspect::inspect,
+    instructions::CarrotInstruction,
+    nav::{Nav, Rounding},
+    store::{MemoryStore, StateStore},
+};
+
+/// Namespace of `PolicyUsage` in the `StateStore`
+pub const POLICY_USAGE_NAMESPACE: &str = "policy-usage";
+
+/// Seconds per UTC day, the window of `Policy::max_daily_value`
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Basis points in 100%
+const BPS: u128 = 10_000;
+
+/// Operation checked against a `Policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PolicyAction {
+    Deposit,
+    Withdraw,
+}
+
+impl PolicyAction {
+    /// Name of the action in stored usage entries
+    fn key(self) -> &'static str {
+        match self {
+            PolicyAction::Deposit => "deposit",
+            PolicyAction::Withdraw => "withdraw",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "deposit" => Some(PolicyAction::Deposit),
+            "withdraw" => Some(PolicyAction::Withdraw),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyAction::Deposit => write!(f, "deposit"),
+            PolicyAction::Withdraw => write!(f, "withdrawal"),
+        }
+    }
+}
+
+/// Guardrails the client enforces when building a deposit or withdrawal and again when
+/// broadcasting it
+///
+/// Values are USD (`VALUE_DECIMALS`): deposits are valued at the deposited asset's oracle
+/// price, withdrawals at the current value of the redeemed CRT. Unset fields don't restrict.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Largest value of a single transaction
+    pub max_value_per_tx: Option<u128>,
+    /// Largest cumulative value per UTC day, tracked separately for deposits and withdrawals
+    pub max_daily_value: Option<u128>,
+    /// Assets that may be deposited or withdrawn
+    pub allowed_assets: Option<Vec<Pubkey>>,
+    /// UTC hours `[start, end)` in which transactions may be built and sent; wraps past
+    /// midnight if `start > end` (e.g. `(22, 6)`). Hours are 0-23 and `start != end`.
+    pub allowed_hours: Option<(u8, u8)>,
+    /// Largest share (bps) of a user's cumulative deposits that may enter via an asset
+    /// (e.g. 20% via pyUSD); assets not listed are uncapped
+    pub max_asset_share_bps: Vec<(Pubkey, u16)>,
+}
+
+impl Policy {
+    /// Limit the value of a single transaction
+    pub fn with_max_value_per_tx(mut self, value: u128) -> Self {
+        self.max_value_per_tx = Some(value);
+        self
+    }
+
+    /// Limit the cumulative value per UTC day
+    pub fn with_max_daily_value(mut self, value: u128) -> Self {
+        self.max_daily_value = Some(value);
+        self
+    }
+
+    /// Only allow the given assets
+    pub fn with_allowed_assets(mut self, assets: Vec<Pubkey>) -> Self {
+        self.allowed_assets = Some(assets);
+        self
+    }
+
+    /// Only allow transactions between UTC hours `start` (inclusive) and `end` (exclusive)
+    ///
+    /// Hours above 23 or `start == end` are rejected by `validate` when the client is created.
+    pub fn with_allowed_hours(mut self, start: u8, end: u8) -> Self {
+        self.allowed_hours = Some((start, end));
+        self
+    }
+
+    /// Cap the share of a user's deposits entering via `asset` at `bps`
+    pub fn with_max_asset_share(mut self, asset: Pubkey, bps: u16) -> Self {
+        self.max_asset_share_bps.retain(|(capped, _)| *capped != asset);
+        self.max_asset_share_bps.push((asset, bps));
+        self
+    }
+
+    /// Fail with `InvalidConfig` if the policy can't be enforced as configured
+    pub fn validate(&self) -> Result<()> {
+        if let Some((start, end)) = self.allowed_hours {
+            if start > 23 || end > 23 {
+                return Err(CarrotError::InvalidConfig(format!(
+                    "allowed hours {}-{} must be between 0 and 23",
+                    start, end
+                )));
+            }
+            if start == end {
+                return Err(CarrotError::InvalidConfig(format!(
+                    "allowed hours {}-{} are an empty window",
+                    start, end
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether checking this policy requires valuing the transaction
+    pub fn needs_value(&self) -> bool {
+        self.max_value_per_tx.is_some() || self.max_daily_value.is_some() || !self.max_asset_share_bps.is_empty()
+    }
+
+    /// Share cap (bps) of deposits via `asset`, if any
+    pub fn max_asset_share(&self, asset: &Pubkey) -> Option<u16> {
+        self.max_asset_share_bps
+            .iter()
+            .find(|(capped, _)| capped == asset)
+            .map(|(_, bps)| *bps)
+    }
+
+    /// Value that may be deposited via `asset` once the user's deposits total `total_after`
+    /// (`None` if uncapped)
+    pub fn exposure_headroom(&self, asset: &Pubkey, exposure: &DepositExposure, total_after: u128) -> Option<u128> {
+        let cap = self.max_asset_share(asset)?;
+        let allowed = total_after.saturating_mul(cap as u128) / BPS;
+        Some(allowed.saturating_sub(exposure.deposited(asset)))
+    }
+
+    /// Fail with `PolicyViolation` if depositing `legs` (asset, value) on top of `exposure`
+    /// would bring an asset above its share cap
+    pub fn check_exposure(&self, legs: &[(Pubkey, u128)], exposure: &DepositExposure) -> Result<()> {
+        let mut after = exposure.clone();
+        for (asset, value) in legs {
+            after.record(asset, *value);
+        }
+        let total = after.total();
+        for (asset, _) in legs {
+            let Some(cap) = self.max_asset_share(asset) else {
+                continue;
+            };
+            let deposited = after.deposited(asset);
+            if deposited.saturating_mul(BPS) > total.saturating_mul(cap as u128) {
+                return Err(CarrotError::PolicyViolation(format!(
+                    "deposits via {} would make up {} bps of deposits, above the cap of {} bps",
+                    asset,
+                    deposited.saturating_mul(BPS).checked_div(total).unwrap_or(0),
+                    cap
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Split a deposit worth `value` across `assets` (in order of preference) so no asset
+    /// exceeds its share cap; returns (asset, value) legs, skipping assets the policy disallows
+    pub fn route_deposit(
+        &self,
+        value: u128,
+        assets: &[Pubkey],
+        exposure: &DepositExposure,
+    ) -> Result<Vec<(Pubkey, u128)>> {
+        let total_after = exposure.total().saturating_add(value);
+        let mut remaining = value;
+        let mut legs = Vec::new();
+        for asset in assets {
+            if remaining == 0 {
+                break;
+            }
+            if self.allowed_assets.as_ref().is_some_and(|allowed| !allowed.contains(asset)) {
+                continue;
+            }
+            let take = self
+                .exposure_headroom(asset, exposure, total_after)
+                .map_or(remaining, |headroom| headroom.min(remaining));
+            if take > 0 {
+                legs.push((*asset, take));
+                remaining -= take;
+            }
+        }
+        if remaining > 0 {
+            return Err(CarrotError::PolicyViolation(format!(
+                "only {} of {} can be deposited via the given assets within the exposure caps",
+                value - remaining,
+                value
+            )));
+        }
+        Ok(legs)
+    }
+
+    /// Fail with `PolicyViolation` unless a transaction moving `legs` (asset, value) is allowed
+    /// at unix time `now`, given `used_today` already spent on the same action
+    pub fn check(
+        &self,
+        action: PolicyAction,
+        legs: &[(Pubkey, u128)],
+        used_today: u128,
+        now: i64,
+    ) -> Result<()> {
+        self.validate()?;
+        if let Some((start, end)) = self.allowed_hours {
+            let hour = (now.rem_euclid(SECONDS_PER_DAY) / 3600) as u8;
+            let allowed = if start <= end {
+                (start..end).contains(&hour)
+            } else {
+                hour >= start || hour < end
+            };
+            if !allowed {
+                return Err(CarrotError::PolicyViolation(format!(
+                    "{} at {:02}:00 UTC is outside the allowed hours {:02}:00-{:02}:00",
+                    action, hour, start, end
+                )));
+            }
+        }
+
+        if let Some(assets) = &self.allowed_assets {
+            if let Some((asset, _)) = legs.iter().find(|(asset, _)| !assets.contains(asset)) {
+                return Err(CarrotError::PolicyViolation(format!("{} of {} is not allowed", action, asset)));
+            }
+        }
+
+        let value = legs.iter().fold(0u128, |sum, (_, value)| sum.saturating_add(*value));
+        if let Some(max) = self.max_value_per_tx {
+            if value > max {
+                return Err(CarrotError::PolicyViolation(format!(
+                    "{} worth {} exceeds the per-transaction limit of {}",
+                    action, value, max
+                )));
+            }
+        }
+        if let Some(max) = self.max_daily_value {
+            if used_today.saturating_add(value) > max {
+                return Err(CarrotError::PolicyViolation(format!(
+                    "{} worth {} exceeds the daily limit of {} ({} already used today)",
+                    action, value, max, used_today
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Value a user has deposited per asset, the basis of `Policy::max_asset_share_bps`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepositExposure {
+    by_asset: HashMap<Pubkey, u128>,
+}
+
+impl DepositExposure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a deposit worth `value` via `asset`
+    pub fn record(&mut self, asset: &Pubkey, value: u128) {
+        let deposited = self.by_asset.entry(*asset).or_insert(0);
+        *deposited = deposited.saturating_add(value);
+    }
+
+    /// Take back a deposit counted with `record`
+    fn unrecord(&mut self, asset: &Pubkey, value: u128) {
+        if let Some(deposited) = self.by_asset.get_mut(asset) {
+            *deposited = deposited.saturating_sub(value);
+        }
+    }
+
+    /// Value deposited via `asset`
+    pub fn deposited(&self, asset: &Pubkey) -> u128 {
+        self.by_asset.get(asset).copied().unwrap_or(0)
+    }
+
+    /// Value deposited via every asset
+    pub fn total(&self) -> u128 {
+        self.by_asset.values().fold(0u128, |sum, value| sum.saturating_add(*value))
+    }
+}
+
+/// Legs of one user's deposit or withdrawal, in asset base units for deposits and CRT for
+/// withdrawals, before they are valued for the policy check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PolicyLegs {
+    pub user: Pubkey,
+    pub action: PolicyAction,
+    pub amounts: Vec<(Pubkey, u64)>,
+}
+
+impl PolicyLegs {
+    pub fn new(user: Pubkey, action: PolicyAction, amounts: Vec<(Pubkey, u64)>) -> Self {
+        Self { user, action, amounts }
+    }
+
+    /// Legs of every user of `transaction` with a policy in `config`, one entry per user and action
+    pub fn of_transaction(config: &ClientConfig, transaction: &Transaction) -> Result<Vec<Self>> {
+        let mut grouped: Vec<Self> = Vec::new();
+        for (_, instruction) in inspect(transaction)?.carrot_instructions {
+            let (user, action, asset_mint, amount) = match instruction {
+                CarrotInstruction::Issue { user, asset_mint, amount } => {
+                    (user, PolicyAction::Deposit, asset_mint, amount)
+                }
+                CarrotInstruction::Redeem { user, asset_mint, amount } => {
+                    (user, PolicyAction::Withdraw, asset_mint, amount)
+                }
+            };
+            if config.policy_for(&user).is_none() {
+                continue;
+            }
+            match grouped.iter_mut().find(|legs| legs.user == user && legs.action == action) {
+                Some(legs) => legs.amounts.push((asset_mint, amount)),
+                None => grouped.push(Self::new(user, action, vec![(asset_mint, amount)])),
+            }
+        }
+        Ok(grouped)
+    }
+
+    /// Whether the user's policy in `config` needs the legs valued (see `Policy::needs_value`)
+    pub fn needs_value(&self, config: &ClientConfig) -> bool {
+        config.policy_for(&self.user).is_some_and(Policy::needs_value)
+    }
+
+    /// The legs valued at `nav`; every value is 0 without one
+    pub fn value(&self, nav: Option<&Nav>) -> Result<PolicyCharge> {
+        let legs = self
+            .amounts
+            .iter()
+            .map(|(asset, amount)| {
+                let value = match (nav, self.action) {
+                    (None, _) => 0,
+                    (Some(nav), PolicyAction::Deposit) => nav.asset(asset)?.value_of(*amount, Rounding::Down)?,
+                    (Some(nav), PolicyAction::Withdraw) => nav.value_of_shares(*amount, Rounding::Down)?,
+                };
+                Ok((*asset, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PolicyCharge {
+            user: self.user,
+            action: self.action,
+            legs,
+        })
+    }
+}
+
+/// One user's deposit or withdrawal legs (asset, USD value), as checked and counted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PolicyCharge {
+    pub user: Pubkey,
+    pub action: PolicyAction,
+    pub legs: Vec<(Pubkey, u128)>,
+}
+
+/// Value each user has moved per action during the current UTC day, and deposited per asset
+///
+/// Kept in `ClientConfig::state_store` under `POLICY_USAGE_NAMESPACE` when one is set, in memory
+/// otherwise. Transactions are charged when broadcast and refunded if they fail, keyed by
+/// signature so a rebroadcast isn't counted twice.
+#[derive(Debug, Default)]
+pub struct PolicyUsage {
+    /// Store used when the config has none
+    memory: MemoryStore,
+    /// Serializes the read-modify-write of usage entries
+    lock: Mutex<()>,
+}
+
+impl PolicyUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with `PolicyViolation` unless `charge` is allowed on top of the usage so far,
+    /// without counting it
+    pub(crate) fn check(&self, config: &ClientConfig, charge: &PolicyCharge, now: i64) -> Result<()> {
+        let Some(policy) = config.policy_for(&charge.user) else {
+            return Ok(());
+        };
+        let store = self.store(config);
+        let _guard = self.lock();
+        Self::check_locked(store, policy, charge, now)
+    }
+
+    /// Whether the transaction signed `signature` has already been charged
+    pub(crate) fn is_charged(&self, config: &ClientConfig, signature: &Signature) -> Result<bool> {
+        Ok(self.store(config).get(POLICY_USAGE_NAMESPACE, &charge_key(signature))?.is_some())
+    }
+
+    /// Legs of `transaction` to charge: those of users with a policy in `config`, none if it was
+    /// already charged (a rebroadcast)
+    pub(crate) fn uncharged_legs(&self, config: &ClientConfig, transaction: &Transaction) -> Result<Vec<PolicyLegs>> {
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let legs = PolicyLegs::of_transaction(config, transaction)?;
+        if legs.is_empty() || self.is_charged(config, &signature)? {
+            return Ok(Vec::new());
+        }
+        Ok(legs)
+    }
+
+    /// `charge` the `legs` of `transaction`, valued at `nav`
+    pub(crate) fn charge_transaction(
+        &self,
+        config: &ClientConfig,
+        transaction: &Transaction,
+        legs: &[PolicyLegs],
+        nav: Option<&Nav>,
+        now: i64,
+    ) -> Result<()> {
+        if legs.is_empty() {
+            return Ok(());
+        }
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let charges = legs.iter().map(|legs| legs.value(nav)).collect::<Result<Vec<_>>>()?;
+        self.charge(config, &signature, &charges, now)
+    }
+
+    /// Check the `charges` of the transaction signed `signature` and count them, unless it was
+    /// already charged
+    pub(crate) fn charge(
+        &self,
+        config: &ClientConfig,
+        signature: &Signature,
+        charges: &[PolicyCharge],
+        now: i64,
+    ) -> Result<()> {
+        let store = self.store(config);
+        let key = charge_key(signature);
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        let _guard = self.lock();
+        if store.get(POLICY_USAGE_NAMESPACE, &key)?.is_some() {
+            return Ok(());
+        }
+
+        // Every charge is checked before any is counted, so a rejection counts nothing
+        let charges: Vec<(&Policy, &PolicyCharge)> = charges
+            .iter()
+            .filter_map(|charge| Some((config.policy_for(&charge.user)?, charge)))
+            .collect();
+        for (policy, charge) in &charges {
+            Self::check_locked(store, policy, charge, now)?;
+        }
+        for (_, charge) in &charges {
+            Self::apply(store, charge, day, true)?;
+        }
+        store.put(POLICY_USAGE_NAMESPACE, &key, encode_charges(day, &charges).as_bytes())?;
+
+        // Refunds only reach back to the previous day
+        for (key, value) in store.scan(POLICY_USAGE_NAMESPACE)? {
+            if key.starts_with(CHARGE_PREFIX) && decode_charges(&value)?.0 < day - 1 {
+                store.delete(POLICY_USAGE_NAMESPACE, &key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo the charge of the transaction signed `signature`, if any, once it can't land
+    pub(crate) fn refund(&self, config: &ClientConfig, signature: &Signature) -> Result<()> {
+        let store = self.store(config);
+        let key = charge_key(signature);
+        let _guard = self.lock();
+        let Some(value) = store.get(POLICY_USAGE_NAMESPACE, &key)? else {
+            return Ok(());
+        };
+        let (day, charges) = decode_charges(&value)?;
+        for charge in &charges {
+            Self::apply(store, charge, day, false)?;
+        }
+        store.delete(POLICY_USAGE_NAMESPACE, &key)
+    }
+
+    /// Deposits counted for `user`
+    pub(crate) fn exposure(&self, config: &ClientConfig, user: &Pubkey) -> Result<DepositExposure> {
+        read_exposure(self.store(config), user)
+    }
+
+    /// Count deposits `user` made outside this client (asset, value)
+    pub(crate) fn seed_exposure(
+        &self,
+        config: &ClientConfig,
+        user: &Pubkey,
+        deposits: &[(Pubkey, u128)],
+    ) -> Result<()> {
+        let store = self.store(config);
+        let _guard = self.lock();
+        let mut exposure = read_exposure(store, user)?;
+        for (asset, value) in deposits {
+            exposure.record(asset, *value);
+        }
+        write_exposure(store, user, &exposure)
+    }
+
+    fn store<'a>(&'a self, config: &'a ClientConfig) -> &'a dyn StateStore {
+        config.state_store.as_deref().unwrap_or(&self.memory)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        // Guards no data of its own, a panic elsewhere can't leave anything inconsistent
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn check_locked(store: &dyn StateStore, policy: &Policy, charge: &PolicyCharge, now: i64) -> Result<()> {
+        let used_today = read_daily(store, &charge.user, charge.action, now.div_euclid(SECONDS_PER_DAY))?;
+        policy.check(charge.action, &charge.legs, used_today, now)?;
+        if charge.action == PolicyAction::Deposit {
+            policy.check_exposure(&charge.legs, &read_exposure(store, &charge.user)?)?;
+        }
+        Ok(())
+    }
+
+    /// Add `charge` to the usage of `day` (and the exposure), or take it off again
+    ///
+    /// Only the latest day's usage is stored: taking off a charge of an earlier day leaves it
+    /// alone and only undoes the exposure.
+    fn apply(store: &dyn StateStore, charge: &PolicyCharge, day: i64, add: bool) -> Result<()> {
+        let value = charge.legs.iter().fold(0u128, |sum, (_, value)| sum.saturating_add(*value));
+        let used = read_daily_entry(store, &charge.user, charge.action)?
+            .filter(|(stored_day, _)| *stored_day == day)
+            .map(|(_, used)| used);
+        let used = match (add, used) {
+            (true, used) => Some(used.unwrap_or(0).saturating_add(value)),
+            (false, used) => used.map(|used| used.saturating_sub(value)),
+        };
+        if let Some(used) = used {
+            store.put(
+                POLICY_USAGE_NAMESPACE,
+                &daily_key(&charge.user, charge.action),
+                format!("{}:{}", day, used).as_bytes(),
+            )?;
+        }
+
+        if charge.action == PolicyAction::Deposit {
+            let mut exposure = read_exposure(store, &charge.user)?;
+            for (asset, value) in &charge.legs {
+                if add {
+                    exposure.record(asset, *value);
+                } else {
+                    exposure.unrecord(asset, *value);
+                }
+            }
+            write_exposure(store, &charge.user, &exposure)?;
+        }
+        Ok(())
+    }
+}
+
+const CHARGE_PREFIX: &str = "charge:";
+
+fn charge_key(signature: &Signature) -> String {
+    format!("{}{}", CHARGE_PREFIX, signature)
+}
+
+fn daily_key(user: &Pubkey, action: PolicyAction) -> String {
+    format!("daily:{}:{}", user, action.key())
+}
+
+fn invalid_usage(what: &str) -> CarrotError {
+    CarrotError::InvalidConfig(format!("invalid policy usage entry: {}", what))
+}
+
+/// Value `user` used on `action` during `day`; entries of earlier days count as nothing
+fn read_daily(store: &dyn StateStore, user: &Pubkey, action: PolicyAction, day: i64) -> Result<u128> {
+    Ok(read_daily_entry(store, user, action)?
+        .filter(|(stored_day, _)| *stored_day == day)
+        .map_or(0, |(_, used)| used))
+}
+
+/// Stored day and the value `user` used on `action` during it
+fn read_daily_entry(store: &dyn StateStore, user: &Pubkey, action: PolicyAction) -> Result<Option<(i64, u128)>> {
+    let Some(value) = store.get(POLICY_USAGE_NAMESPACE, &daily_key(user, action))? else {
+        return Ok(None);
+    };
+    let value = String::from_utf8_lossy(&value);
+    let (stored_day, used) = value.split_once(':').ok_or_else(|| invalid_usage(&value))?;
+    let stored_day = stored_day.parse().map_err(|_| invalid_usage(&value))?;
+    let used = used.parse().map_err(|_| invalid_usage(&value))?;
+    Ok(Some((stored_day, used)))
+}
+
+fn read_exposure(store: &dyn StateStore, user: &Pubkey) -> Result<DepositExposure> {
+    let mut exposure = DepositExposure::new();
+    if let Some(value) = store.get(POLICY_USAGE_NAMESPACE, &format!("exposure:{}", user))? {
+        for (asset, value) in parse_legs(&String::from_utf8_lossy(&value))? {
+            exposure.record(&asset, value);
+        }
+    }
+    Ok(exposure)
+}
+
+fn write_exposure(store: &dyn StateStore, user: &Pubkey, exposure: &DepositExposure) -> Result<()> {
+    let mut legs: Vec<(Pubkey, u128)> = exposure.by_asset.iter().map(|(asset, value)| (*asset, *value)).collect();
+    legs.sort();
+    store.put(POLICY_USAGE_NAMESPACE, &format!("exposure:{}", user), format_legs(&legs).as_bytes())
+}
+
+/// `asset=value,asset=value`
+fn format_legs(legs: &[(Pubkey, u128)]) -> String {
+    legs.iter()
+        .map(|(asset, value)| format!("{}={}", asset, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_legs(value: &str) -> Result<Vec<(Pubkey, u128)>> {
+    value
+        .split(',')
+        .filter(|leg| !leg.is_empty())
+        .map(|leg| {
+            let (asset, value) = leg.split_once('=').ok_or_else(|| invalid_usage(leg))?;
+            Ok((
+                Pubkey::from_str(asset).map_err(|_| invalid_usage(leg))?,
+                value.parse().map_err(|_| invalid_usage(leg))?,
+            ))
+        })
+        .collect()
+}
+
+/// `day;user:action:legs;user:action:legs`
+fn encode_charges(day: i64, charges: &[(&Policy, &PolicyCharge)]) -> String {
+    let mut value = day.to_string();
+    for (_, charge) in charges {
+        value.push_str(&format!(";{}:{}:{}", charge.user, charge.action.key(), format_legs(&charge.legs)));
+    }
+    value
+}
+
+fn decode_charges(value: &[u8]) -> Result<(i64, Vec<PolicyCharge>)> {
+    let value = String::from_utf8_lossy(value);
+    let mut fields = value.split(';');
+    let day = fields
+        .next()
+        .and_then(|day| day.parse().ok())
+        .ok_or_else(|| invalid_usage(&value))?;
+    let charges = fields
+        .map(|field| {
+            let mut parts = field.splitn(3, ':');
+            let (Some(user), Some(action), Some(legs)) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(invalid_usage(field));
+            };
+            Ok(PolicyCharge {
+                user: Pubkey::from_str(user).map_err(|_| invalid_usage(field))?,
+                action: PolicyAction::from_key(action).ok_or_else(|| invalid_usage(field))?,
+                legs: parse_legs(legs)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((day, charges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const NOON: i64 = 19_000 * SECONDS_PER_DAY + 12 * 3600;
+
+    #[test]
+    fn test_allowed_hours() {
+        let office = Policy::default().with_allowed_hours(9, 17);
+        assert!(office.check(PolicyAction::Deposit, &[], 0, NOON).is_ok());
+        assert!(office.check(PolicyAction::Deposit, &[], 0, NOON + 6 * 3600).is_err());
+
+        let overnight = Policy::default().with_allowed_hours(22, 6);
+        assert!(overnight.check(PolicyAction::Deposit, &[], 0, NOON).is_err());
+        assert!(overnight.check(PolicyAction::Deposit, &[], 0, NOON + 11 * 3600).is_ok());
+    }
+
+    #[test]
+    fn test_assets_and_per_tx_limit() {
+        let usdc = Pubkey::new_unique();
+        let policy = Policy::default()
+            .with_allowed_assets(vec![usdc])
+            .with_max_value_per_tx(100);
+        assert!(policy.check(PolicyAction::Withdraw, &[(usdc, 100)], 0, NOON).is_ok());
+        assert!(policy.check(PolicyAction::Withdraw, &[(usdc, 101)], 0, NOON).is_err());
+        assert!(matches!(
+            policy.check(PolicyAction::Withdraw, &[(Pubkey::new_unique(), 1)], 0, NOON),
+            Err(CarrotError::PolicyViolation(_))
+        ));
+    }
+
+    fn charge(user: Pubkey, action: PolicyAction, legs: &[(Pubkey, u128)]) -> PolicyCharge {
+        PolicyCharge {
+            user,
+            action,
+            legs: legs.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_allowed_hours_are_validated() {
+        assert!(Policy::default().with_allowed_hours(22, 6).validate().is_ok());
+        assert!(Policy::default().with_allowed_hours(0, 23).validate().is_ok());
+        for (start, end) in [(9, 24), (25, 3), (8, 8)] {
+            let policy = Policy::default().with_allowed_hours(start, end);
+            assert!(matches!(policy.validate(), Err(CarrotError::InvalidConfig(_))));
+            assert!(policy.check(PolicyAction::Deposit, &[], 0, NOON).is_err());
+            let config = ClientConfig::new("http://localhost:8899").with_user_policy(Pubkey::new_unique(), policy);
+            assert!(config.validate_policies().is_err());
+        }
+    }
+
+    #[test]
+    fn test_daily_limit_resets_each_day() {
+        let usdc = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let config = ClientConfig::new("http://localhost:8899").with_policy(Policy::default().with_max_daily_value(100));
+        let usage = PolicyUsage::default();
+        let send = |action, value, now| {
+            let signature = Signature::new_unique();
+            usage.charge(&config, &signature, &[charge(user, action, &[(usdc, value)])], now)
+        };
+
+        assert!(send(PolicyAction::Deposit, 60, NOON).is_ok());
+        assert!(send(PolicyAction::Deposit, 60, NOON).is_err());
+        // Withdrawals are tracked separately
+        assert!(send(PolicyAction::Withdraw, 60, NOON).is_ok());
+        // A rejected transaction doesn't count
+        assert!(send(PolicyAction::Deposit, 40, NOON).is_ok());
+
+        let tomorrow = NOON + SECONDS_PER_DAY;
+        assert!(send(PolicyAction::Deposit, 100, tomorrow).is_ok());
+    }
+
+    #[test]
+    fn test_charged_at_most_once_and_refunded() {
+        let usdc = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStore::new());
+        let config = ClientConfig::new("http://localhost:8899")
+            .with_policy(Policy::default().with_max_daily_value(100))
+            .with_state_store(Arc::clone(&store));
+        let usage = PolicyUsage::default();
+        let deposit = [charge(user, PolicyAction::Deposit, &[(usdc, 60)])];
+        let signature = Signature::new_unique();
+
+        // Checking counts nothing
+        assert!(usage.check(&config, &deposit[0], NOON).is_ok());
+        assert!(usage.check(&config, &deposit[0], NOON).is_ok());
+
+        usage.charge(&config, &signature, &deposit, NOON).unwrap();
+        assert!(usage.is_charged(&config, &signature).unwrap());
+        // A rebroadcast isn't charged again
+        usage.charge(&config, &signature, &deposit, NOON).unwrap();
+        assert!(usage.check(&config, &deposit[0], NOON).is_err());
+
+        // Usage lives in the store: another client's usage sees it
+        let restarted = PolicyUsage::default();
+        assert!(restarted.check(&config, &deposit[0], NOON).is_err());
+        restarted.refund(&config, &signature).unwrap();
+        assert!(!usage.is_charged(&config, &signature).unwrap());
+        assert!(usage.check(&config, &deposit[0], NOON).is_ok());
+        assert_eq!(usage.exposure(&config, &user).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn test_refunding_yesterdays_charge_keeps_todays_usage() {
+        let usdc = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let config = ClientConfig::new("http://localhost:8899").with_policy(Policy::default().with_max_daily_value(100));
+        let usage = PolicyUsage::default();
+        let tomorrow = NOON + SECONDS_PER_DAY;
+        let (yesterdays, todays) = (Signature::new_unique(), Signature::new_unique());
+        usage
+            .charge(&config, &yesterdays, &[charge(user, PolicyAction::Deposit, &[(usdc, 30)])], NOON)
+            .unwrap();
+        usage
+            .charge(&config, &todays, &[charge(user, PolicyAction::Deposit, &[(usdc, 60)])], tomorrow)
+            .unwrap();
+
+        usage.refund(&config, &yesterdays).unwrap();
+        let store = usage.store(&config);
+        assert_eq!(read_daily(store, &user, PolicyAction::Deposit, tomorrow / SECONDS_PER_DAY).unwrap(), 60);
+        assert!(usage
+            .check(&config, &charge(user, PolicyAction::Deposit, &[(usdc, 41)]), tomorrow)
+            .is_err());
+        // The exposure of the refunded deposit is undone all the same
+        assert_eq!(usage.exposure(&config, &user).unwrap().total(), 60);
+    }
+
+    #[test]
+    fn test_asset_share_cap() {
+        let usdc = Pubkey::new_unique();
+        let pyusd = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let config =
+            ClientConfig::new("http://localhost:8899").with_policy(Policy::default().with_max_asset_share(pyusd, 2_000));
+        let usage = PolicyUsage::default();
+        let deposit = |legs: &[(Pubkey, u128)]| {
+            let legs = [charge(user, PolicyAction::Deposit, legs)];
+            usage.charge(&config, &Signature::new_unique(), &legs, NOON)
+        };
+
+        // A first deposit via pyUSD alone would be 100% of the user's deposits
+        assert!(matches!(deposit(&[(pyusd, 10)]), Err(CarrotError::PolicyViolation(_))));
+        assert!(deposit(&[(usdc, 80), (pyusd, 20)]).is_ok());
+        assert!(deposit(&[(pyusd, 1)]).is_err());
+        assert!(deposit(&[(usdc, 40)]).is_ok());
+        assert!(deposit(&[(pyusd, 10)]).is_ok());
+        assert_eq!(usage.exposure(&config, &user).unwrap().deposited(&pyusd), 30);
+        assert_eq!(usage.exposure(&config, &user).unwrap().total(), 150);
+
+        usage.seed_exposure(&config, &user, &[(usdc, 50)]).unwrap();
+        assert_eq!(usage.exposure(&config, &user).unwrap().total(), 200);
+    }
+
+    #[test]
+    fn test_route_deposit_respects_caps() {
+        let usdc = Pubkey::new_unique();
+        let pyusd = Pubkey::new_unique();
+        let policy = Policy::default().with_max_asset_share(pyusd, 2_000);
+        let mut exposure = DepositExposure::new();
+        exposure.record(&usdc, 100);
+
+        // pyUSD preferred, but it may only reach 20% of the 200 total
+        let legs = policy.route_deposit(100, &[pyusd, usdc], &exposure).unwrap();
+        assert_eq!(legs, [(pyusd, 40), (usdc, 60)]);
+        assert!(policy.check_exposure(&legs, &exposure).is_ok());
+
+        assert!(matches!(
+            policy.route_deposit(100, &[pyusd], &exposure),
+            Err(CarrotError::PolicyViolation(_))
+        ));
+        // Disallowed assets are skipped
+        let restricted = policy.clone().with_allowed_assets(vec![pyusd]);
+        assert!(restricted.route_deposit(100, &[usdc, pyusd], &exposure).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! Look-through of strategy balances into the protocols they're deployed in
+//!
+//! `StrategyRecord.balance` is what the vault believes a strategy holds. The decoders here read
+//! the position itself (Kamino Lend kTokens, a Drift subaccount's spot balance) so the two can
+//! be reconciled. Which account backs which strategy isn't stored on-chain, so the caller maps
+//! strategies to decoders; the protocol accounts are checked for their program owner and Anchor
+//! discriminator before any field is read.
+
+use std::fmt;
+use std::sync::Arc;
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::{
+    error::{CarrotError, Result},
+    token::{unpack_token_amount, unpack_token_mint},
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Kamino Lend program ID
+pub const KAMINO_LEND_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+
+/// Drift v2 program ID
+pub const DRIFT_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("dRiftyHA39MWEi3m9aunc5MzRF1JYuBsbn6VPcn33UH");
+
+/// Reads a strategy's underlying position from the accounts of the protocol it's deployed in
+pub trait PositionDecoder: fmt::Debug + Send + Sync {
+    /// Protocol name for reports
+    fn protocol(&self) -> &str;
+
+    /// Accounts the position is read from
+    fn accounts(&self) -> Vec<Pubkey>;
+
+    /// Underlying amount in units of the strategy's asset (`decimals`), from `accounts` as
+    /// fetched (same order as `accounts()`, `None` for missing ones)
+    fn decode(&self, accounts: &[Option<Account>], decimals: u8) -> Result<u64>;
+}
+
+/// Anchor discriminator of a Kamino Lend `Reserve` account
+const KAMINO_RESERVE_DISCRIMINATOR: [u8; 8] = [43, 242, 204, 202, 26, 247, 59, 127];
+/// Offset of `liquidity.available_amount` (u64) in a `Reserve`
+const KAMINO_AVAILABLE_AMOUNT_OFFSET: usize = 224;
+/// Offset of `liquidity.borrowed_amount_sf` (u128) in a `Reserve`
+const KAMINO_BORROWED_AMOUNT_OFFSET: usize = 232;
+/// Offset of the accumulated protocol, accumulated referrer and pending referrer fees (three
+/// u128s) in a `Reserve`
+const KAMINO_FEES_OFFSET: usize = 344;
+/// Offset of `collateral.mint_pubkey` in a `Reserve`
+const KAMINO_COLLATERAL_MINT_OFFSET: usize = 2560;
+/// Offset of `collateral.mint_total_supply` (u64) in a `Reserve`
+const KAMINO_COLLATERAL_SUPPLY_OFFSET: usize = 2592;
+/// Fractional bits of Kamino's scaled fractions (the `_sf` fields)
+const KAMINO_FRACTION_BITS: u32 = 60;
+
+/// Kamino Lend collateral tokens (kTokens) held in a token account, valued at the exchange rate
+/// of the reserve that minted them
+///
+/// The rate is the reserve's total liquidity (available plus borrowed, less unclaimed fees) per
+/// collateral token, as Kamino computes it when redeeming. It moves with the interest accrued by
+/// the last refresh of the reserve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KTokenPosition {
+    pub token_account: Pubkey,
+    /// The Kamino Lend `Reserve` the kTokens are collateral of
+    pub reserve: Pubkey,
+}
+
+impl KTokenPosition {
+    /// kTokens of `reserve` in `token_account`
+    pub fn new(token_account: Pubkey, reserve: Pubkey) -> Self {
+        Self { token_account, reserve }
+    }
+
+    /// Total liquidity of a `Reserve` and the supply of its collateral mint, the exchange rate
+    /// of the reserve's kTokens
+    pub fn exchange_rate(reserve_data: &[u8]) -> Result<(u128, u64)> {
+        let invalid = || CarrotError::InvalidConfig("account is not a Kamino Reserve account".to_string());
+        let available = read_u64(reserve_data, KAMINO_AVAILABLE_AMOUNT_OFFSET).ok_or_else(invalid)?;
+        let borrowed = read_u128(reserve_data, KAMINO_BORROWED_AMOUNT_OFFSET).ok_or_else(invalid)?;
+        let fees = (0..3).try_fold(0u128, |total, i| {
+            let fee = read_u128(reserve_data, KAMINO_FEES_OFFSET + 16 * i).ok_or_else(invalid)?;
+            total.checked_add(fee).ok_or(CarrotError::MathOverflow)
+        })?;
+        let supply = read_u64(reserve_data, KAMINO_COLLATERAL_SUPPLY_OFFSET).ok_or_else(invalid)?;
+        let liquidity = ((available as u128) << KAMINO_FRACTION_BITS)
+            .checked_add(borrowed)
+            .ok_or(CarrotError::MathOverflow)?
+            .saturating_sub(fees);
+        Ok((liquidity >> KAMINO_FRACTION_BITS, supply))
+    }
+}
+
+impl PositionDecoder for KTokenPosition {
+    fn protocol(&self) -> &str {
+        "kamino"
+    }
+
+    fn accounts(&self) -> Vec<Pubkey> {
+        vec![self.token_account, self.reserve]
+    }
+
+    fn decode(&self, accounts: &[Option<Account>], _decimals: u8) -> Result<u64> {
+        let account = required(accounts, 0, &self.token_account)?;
+        let reserve = required(accounts, 1, &self.reserve)?;
+        check_anchor_account(
+            reserve,
+            &self.reserve,
+            &KAMINO_LEND_PROGRAM_ID,
+            &KAMINO_RESERVE_DISCRIMINATOR,
+            "Kamino Reserve",
+        )?;
+        let collateral_mint = reserve
+            .data
+            .get(KAMINO_COLLATERAL_MINT_OFFSET..KAMINO_COLLATERAL_MINT_OFFSET + 32)
+            .and_then(|bytes| Pubkey::try_from(bytes).ok())
+            .ok_or_else(|| CarrotError::InvalidConfig("account is not a Kamino Reserve account".to_string()))?;
+        let mint = unpack_token_mint(&account.data)?;
+        if mint != collateral_mint {
+            return Err(CarrotError::InvalidTokenAccount {
+                account: self.token_account,
+                reason: format!("holds mint {}, expected collateral mint {} of the reserve", mint, collateral_mint),
+            });
+        }
+        let balance = unpack_token_amount(&account.data)?;
+        let (liquidity, supply) = Self::exchange_rate(&reserve.data)?;
+        if supply == 0 {
+            // Kamino mints the first collateral 1:1
+            return Ok(balance);
+        }
+        let value = (balance as u128).checked_mul(liquidity).ok_or(CarrotError::MathOverflow)? / supply as u128;
+        u64::try_from(value).map_err(|_| CarrotError::MathOverflow)
+    }
+}
+
+/// Anchor discriminator of a Drift `User` account
+const DRIFT_USER_DISCRIMINATOR: [u8; 8] = [159, 117, 95, 227, 239, 151, 58, 236];
+/// Anchor discriminator of a Drift `SpotMarket` account
+const DRIFT_SPOT_MARKET_DISCRIMINATOR: [u8; 8] = [100, 177, 8, 107, 168, 65, 65, 39];
+/// Offset of the spot positions in a Drift `User` account (discriminator, authority,
+/// delegate, name)
+const DRIFT_SPOT_POSITIONS_OFFSET: usize = 8 + 32 + 32 + 32;
+/// Size of a Drift `SpotPosition`
+const DRIFT_SPOT_POSITION_SIZE: usize = 40;
+/// Spot positions per Drift `User` account
+const DRIFT_SPOT_POSITIONS: usize = 8;
+/// Offset of `cumulative_deposit_interest` (u128) in a Drift `SpotMarket` account
+const DRIFT_CUMULATIVE_DEPOSIT_INTEREST_OFFSET: usize = 464;
+/// `SpotBalanceType::Borrow`
+const DRIFT_BORROW: u8 = 1;
+
+/// Deposit in one spot market of a Drift subaccount (`User` account), net of borrows
+///
+/// The scaled balance is converted with the market's cumulative deposit interest, read from
+/// its `SpotMarket` account, as Drift's `get_token_amount` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriftSpotPosition {
+    /// The subaccount's `User` account
+    pub user: Pubkey,
+    pub spot_market: Pubkey,
+    pub market_index: u16,
+}
+
+impl DriftSpotPosition {
+    pub fn new(user: Pubkey, spot_market: Pubkey, market_index: u16) -> Self {
+        Self {
+            user,
+            spot_market,
+            market_index,
+        }
+    }
+
+    /// Scaled balance of the position in `market_index` (0 if the subaccount has none);
+    /// negative for a borrow
+    pub fn scaled_balance(user_data: &[u8], market_index: u16) -> Result<i128> {
+        let invalid = || CarrotError::InvalidConfig("account is not a Drift User account".to_string());
+        let end = DRIFT_SPOT_POSITIONS_OFFSET + DRIFT_SPOT_POSITIONS * DRIFT_SPOT_POSITION_SIZE;
+        let positions = user_data.get(DRIFT_SPOT_POSITIONS_OFFSET..end).ok_or_else(invalid)?;
+        for position in positions.chunks_exact(DRIFT_SPOT_POSITION_SIZE) {
+            let scaled_balance = u64::from_le_bytes(position[0..8].try_into().map_err(|_| invalid())?);
+            let index = u16::from_le_bytes([position[32], position[33]]);
+            if index == market_index && scaled_balance > 0 {
+                let scaled_balance = scaled_balance as i128;
+                return Ok(if position[34] == DRIFT_BORROW { -scaled_balance } else { scaled_balance });
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl PositionDecoder for DriftSpotPosition {
+    fn protocol(&self) -> &str {
+        "drift"
+    }
+
+    fn accounts(&self) -> Vec<Pubkey> {
+        vec![self.user, self.spot_market]
+    }
+
+    fn decode(&self, accounts: &[Option<Account>], decimals: u8) -> Result<u64> {
+        let user = required(accounts, 0, &self.user)?;
+        let market = required(accounts, 1, &self.spot_market)?;
+        check_anchor_account(user, &self.user, &DRIFT_PROGRAM_ID, &DRIFT_USER_DISCRIMINATOR, "Drift User")?;
+        check_anchor_account(
+            market,
+            &self.spot_market,
+            &DRIFT_PROGRAM_ID,
+            &DRIFT_SPOT_MARKET_DISCRIMINATOR,
+            "Drift SpotMarket",
+        )?;
+        let scaled_balance = Self::scaled_balance(&user.data, self.market_index)?;
+        if scaled_balance <= 0 {
+            // Borrows aren't an asset of the strategy
+            return Ok(0);
+        }
+        let interest = read_u128(&market.data, DRIFT_CUMULATIVE_DEPOSIT_INTEREST_OFFSET)
+            .ok_or_else(|| CarrotError::InvalidConfig("account is not a Drift SpotMarket account".to_string()))?;
+        // Scaled balances have 9 decimals and interest 10: token = balance * interest / 10^(19 - decimals)
+        let precision = 10u128.checked_pow(19u32.saturating_sub(decimals as u32)).ok_or(CarrotError::MathOverflow)?;
+        let amount = (scaled_balance as u128)
+            .checked_mul(interest)
+            .ok_or(CarrotError::MathOverflow)?
+            / precision;
+        u64::try_from(amount).map_err(|_| CarrotError::MathOverflow)
+    }
+}
+
+fn required<'a>(accounts: &'a [Option<Account>], index: usize, address: &Pubkey) -> Result<&'a Account> {
+    accounts
+        .get(index)
+        .and_then(Option::as_ref)
+        .ok_or_else(|| CarrotError::AccountNotFound(address.to_string()))
+}
+
+/// Check that `account`, fetched from `address`, is owned by `program` and starts with the
+/// Anchor `discriminator` of a `kind` account
+fn check_anchor_account(
+    account: &Account,
+    address: &Pubkey,
+    program: &Pubkey,
+    discriminator: &[u8; 8],
+    kind: &str,
+) -> Result<()> {
+    if account.owner != *program {
+        return Err(CarrotError::InvalidConfig(format!(
+            "{} is owned by {}, not a {} account",
+            address, account.owner, kind
+        )));
+    }
+    if account.data.get(..8) != Some(discriminator.as_slice()) {
+        return Err(CarrotError::InvalidConfig(format!("{} is not a {} account", address, kind)));
+    }
+    Ok(())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)?.try_into().ok().map(u64::from_le_bytes)
+}
+
+fn read_u128(data: &[u8], offset: usize) -> Option<u128> {
+    data.get(offset..offset + 16)?.try_into().ok().map(u128::from_le_bytes)
+}
+
+/// Strategy whose position `decoder` reads
+#[derive(Debug, Clone)]
+pub struct StrategyPosition {
+    pub strategy_id: u16,
+    pub decoder: Arc<dyn PositionDecoder>,
+}
+
+impl StrategyPosition {
+    pub fn new(strategy_id: u16, decoder: Arc<dyn PositionDecoder>) -> Self {
+        Self { strategy_id, decoder }
+    }
+}
+
+/// A strategy's recorded balance next to the position it's deployed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyReconciliation {
+    pub strategy_id: u16,
+    pub protocol: String,
+    pub asset_mint: Pubkey,
+    /// `StrategyRecord.balance`
+    pub recorded: u64,
+    /// Position read from the protocol
+    pub underlying: u64,
+}
+
+impl StrategyReconciliation {
+    /// Underlying minus recorded, in asset units (positive when the position holds more)
+    pub fn difference(&self) -> i128 {
+        self.underlying as i128 - self.recorded as i128
+    }
+
+    /// Whether the two agree within `tolerance_bps` of the recorded balance
+    pub fn is_within(&self, tolerance_bps: u64) -> bool {
+        self.difference().unsigned_abs() * 10_000 <= self.recorded as u128 * tolerance_bps as u128
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Read each strategy's position through its decoder and pair it with the balance the
+    /// vault records, all accounts fetched in one request
+    pub fn reconcile_strategy_positions(&self, positions: &[StrategyPosition]) -> Result<Vec<StrategyReconciliation>> {
+        let vault = self.fetch_vault()?;
+        let addresses: Vec<Pubkey> = positions.iter().flat_map(|position| position.decoder.accounts()).collect();
+        let mut accounts = self.rpc_client().get_multiple_accounts(&addresses)?.into_iter();
+
+        positions
+            .iter()
+            .map(|position| {
+                let fetched: Vec<Option<Account>> = accounts.by_ref().take(position.decoder.accounts().len()).collect();
+                let record = vault
+                    .strategies
+                    .iter()
+                    .find(|record| record.strategy_id == position.strategy_id)
+                    .ok_or_else(|| CarrotError::InvalidConfig(format!("no strategy {}", position.strategy_id)))?;
+                let asset = vault
+                    .assets
+                    .iter()
+                    .find(|asset| asset.asset_id == record.asset_id)
+                    .ok_or_else(|| CarrotError::InvalidAsset(format!("asset id {}", record.asset_id)))?;
+                Ok(StrategyReconciliation {
+                    strategy_id: record.strategy_id,
+                    protocol: position.decoder.protocol().to_string(),
+                    asset_mint: asset.mint,
+                    recorded: record.balance,
+                    underlying: position.decoder.decode(&fetched, asset.decimals)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::program_pack::Pack;
+    use spl_token_2022_interface::state::{Account as TokenAccount, AccountState};
+
+    /// Reserve of 1,000,000 kTokens backed by 900,000 available and 150,000.5 borrowed units,
+    /// less 0.5 of pending referrer fees
+    fn kamino_reserve(collateral_mint: Pubkey) -> Account {
+        let mut data = vec![0u8; 8624];
+        data[..8].copy_from_slice(&KAMINO_RESERVE_DISCRIMINATOR);
+        let half = 1u128 << (KAMINO_FRACTION_BITS - 1);
+        data[KAMINO_AVAILABLE_AMOUNT_OFFSET..KAMINO_AVAILABLE_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&900_000u64.to_le_bytes());
+        data[KAMINO_BORROWED_AMOUNT_OFFSET..KAMINO_BORROWED_AMOUNT_OFFSET + 16]
+            .copy_from_slice(&((150_000u128 << KAMINO_FRACTION_BITS) + half).to_le_bytes());
+        data[KAMINO_FEES_OFFSET + 32..KAMINO_FEES_OFFSET + 48].copy_from_slice(&half.to_le_bytes());
+        data[KAMINO_COLLATERAL_MINT_OFFSET..KAMINO_COLLATERAL_MINT_OFFSET + 32]
+            .copy_from_slice(collateral_mint.as_ref());
+        data[KAMINO_COLLATERAL_SUPPLY_OFFSET..KAMINO_COLLATERAL_SUPPLY_OFFSET + 8]
+            .copy_from_slice(&1_000_000u64.to_le_bytes());
+        Account {
+            data,
+            owner: KAMINO_LEND_PROGRAM_ID,
+            ..Default::default()
+        }
+    }
+
+    fn token_account(mint: Pubkey, amount: u64) -> Account {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        let token_account = TokenAccount {
+            mint,
+            owner: Pubkey::new_unique(),
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        Account {
+            data,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ktoken_position() {
+        let ktoken_mint = Pubkey::new_unique();
+        let position = KTokenPosition::new(Pubkey::new_unique(), Pubkey::new_unique());
+        let reserve = kamino_reserve(ktoken_mint);
+        assert_eq!(KTokenPosition::exchange_rate(&reserve.data).unwrap(), (1_050_000, 1_000_000));
+        let held = token_account(ktoken_mint, 200_000);
+        assert_eq!(position.decode(&[Some(held.clone()), Some(reserve.clone())], 6).unwrap(), 210_000);
+
+        // kTokens of another reserve
+        let other = token_account(Pubkey::new_unique(), 200_000);
+        assert!(matches!(
+            position.decode(&[Some(other), Some(reserve.clone())], 6),
+            Err(CarrotError::InvalidTokenAccount { .. })
+        ));
+        let mut foreign = reserve.clone();
+        foreign.owner = Pubkey::new_unique();
+        assert!(matches!(
+            position.decode(&[Some(held.clone()), Some(foreign)], 6),
+            Err(CarrotError::InvalidConfig(_))
+        ));
+        let mut obligation = reserve;
+        obligation.data[..8].copy_from_slice(&[0; 8]);
+        assert!(matches!(position.decode(&[Some(held), Some(obligation)], 6), Err(CarrotError::InvalidConfig(_))));
+    }
+
+    fn drift_user(market_index: u16, scaled_balance: u64, balance_type: u8) -> Account {
+        let mut data = vec![0u8; DRIFT_SPOT_POSITIONS_OFFSET + DRIFT_SPOT_POSITIONS * DRIFT_SPOT_POSITION_SIZE];
+        data[..8].copy_from_slice(&DRIFT_USER_DISCRIMINATOR);
+        // Second slot, after an empty one
+        let position = DRIFT_SPOT_POSITIONS_OFFSET + DRIFT_SPOT_POSITION_SIZE;
+        data[position..position + 8].copy_from_slice(&scaled_balance.to_le_bytes());
+        data[position + 32..position + 34].copy_from_slice(&market_index.to_le_bytes());
+        data[position + 34] = balance_type;
+        Account {
+            data,
+            owner: DRIFT_PROGRAM_ID,
+            ..Default::default()
+        }
+    }
+
+    fn drift_market(cumulative_deposit_interest: u128) -> Account {
+        let mut data = vec![0u8; DRIFT_CUMULATIVE_DEPOSIT_INTEREST_OFFSET + 32];
+        data[..8].copy_from_slice(&DRIFT_SPOT_MARKET_DISCRIMINATOR);
+        data[DRIFT_CUMULATIVE_DEPOSIT_INTEREST_OFFSET..DRIFT_CUMULATIVE_DEPOSIT_INTEREST_OFFSET + 16]
+            .copy_from_slice(&cumulative_deposit_interest.to_le_bytes());
+        Account {
+            data,
+            owner: DRIFT_PROGRAM_ID,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_drift_spot_position() {
+        let position = DriftSpotPosition::new(Pubkey::new_unique(), Pubkey::new_unique(), 0);
+        // 1,000 scaled units (9 decimals) at 1.05x interest (10 decimals): 1,050 USDC
+        let user = drift_user(0, 1_000_000_000_000, 0);
+        let market = drift_market(10_500_000_000);
+        let amount = position.decode(&[Some(user), Some(market.clone())], 6).unwrap();
+        assert_eq!(amount, 1_050_000_000);
+
+        assert_eq!(position.decode(&[Some(drift_user(0, 5, DRIFT_BORROW)), Some(market.clone())], 6).unwrap(), 0);
+        assert_eq!(position.decode(&[Some(drift_user(1, 5, 0)), Some(market.clone())], 6).unwrap(), 0);
+        assert!(matches!(position.decode(&[None, Some(market.clone())], 6), Err(CarrotError::AccountNotFound(_))));
+    }
+
+    #[test]
+    fn test_drift_accounts_are_checked() {
+        let position = DriftSpotPosition::new(Pubkey::new_unique(), Pubkey::new_unique(), 0);
+        let market = drift_market(10_500_000_000);
+        let mut foreign = drift_user(0, 1_000, 0);
+        foreign.owner = Pubkey::new_unique();
+        assert!(matches!(
+            position.decode(&[Some(foreign), Some(market.clone())], 6),
+            Err(CarrotError::InvalidConfig(_))
+        ));
+        // Accounts passed in the wrong order
+        assert!(matches!(
+            position.decode(&[Some(market), Some(drift_user(0, 1_000, 0))], 6),
+            Err(CarrotError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_reconciliation_tolerance() {
+        let reconciliation = StrategyReconciliation {
+            strategy_id: 1,
+            protocol: "kamino".to_string(),
+            asset_mint: Pubkey::new_unique(),
+            recorded: 1_000_000,
+            underlying: 1_000_900,
+        };
+        assert_eq!(reconciliation.difference(), 900);
+        assert!(reconciliation.is_within(10));
+        assert!(!reconciliation.is_within(5));
+    }
+}
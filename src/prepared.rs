@@ -0,0 +1,90 @@
+use std::time::SystemTime;
+
+use solana_sdk::{signature::Signature, transaction::Transaction};
+
+use crate::{
+    error::{CarrotError, Result},
+    inspect::{inspect, TransactionInspection},
+};
+
+/// A signed transaction built ahead of sending, with the point after which it must not be broadcast
+///
+/// Amounts in a prepared deposit or withdrawal were chosen at build time; sending it much later
+/// would execute at a different share price, so `CarrotClient::send_prepared` refuses expired ones.
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    /// Signed transaction
+    pub transaction: Transaction,
+    /// Last block height at which the transaction's blockhash is valid
+    pub last_valid_block_height: u64,
+    /// Optional wall-clock deadline (from `ClientConfig::prepared_transaction_ttl`)
+    pub expires_at: Option<SystemTime>,
+}
+
+impl PreparedTransaction {
+    /// Signature of the transaction (fee payer signature)
+    pub fn signature(&self) -> Signature {
+        self.transaction.signatures.first().copied().unwrap_or_default()
+    }
+
+    /// Summarize the programs, accounts, signers and Carrot instructions in the transaction
+    pub fn inspect(&self) -> Result<TransactionInspection> {
+        inspect(&self.transaction)
+    }
+
+    /// Whether the transaction has expired at the given block height and time
+    pub fn is_expired(&self, block_height: u64, now: SystemTime) -> bool {
+        self.check_expiry(block_height, now).is_err()
+    }
+
+    /// Fail with `TransactionExpired` if the transaction must no longer be sent
+    pub fn check_expiry(&self, block_height: u64, now: SystemTime) -> Result<()> {
+        if let Some(expires_at) = self.expires_at {
+            if now > expires_at {
+                return Err(CarrotError::TransactionExpired(format!(
+                    "{} passed its deadline",
+                    self.signature()
+                )));
+            }
+        }
+        if block_height > self.last_valid_block_height {
+            return Err(CarrotError::TransactionExpired(format!(
+                "{} blockhash expired at block height {} (current {})",
+                self.signature(),
+                self.last_valid_block_height,
+                block_height
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn prepared(expires_at: Option<SystemTime>) -> PreparedTransaction {
+        PreparedTransaction {
+            transaction: Transaction::default(),
+            last_valid_block_height: 1_000,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_block_height_expiry() {
+        let now = SystemTime::now();
+        let tx = prepared(None);
+        assert!(!tx.is_expired(1_000, now));
+        assert!(tx.is_expired(1_001, now));
+    }
+
+    #[test]
+    fn test_wall_clock_expiry() {
+        let now = SystemTime::now();
+        let tx = prepared(Some(now + Duration::from_secs(30)));
+        assert!(!tx.is_expired(0, now));
+        assert!(tx.is_expired(0, now + Duration::from_secs(31)));
+    }
+}
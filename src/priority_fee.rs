@@ -0,0 +1,184 @@
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{error::Result, CRT_MINT, VAULT_ADDRESS};
+
+/// Policy for deriving the compute-unit price from recent prioritization fees
+///
+/// The price is taken at `percentile` of the fees paid in recent slots for
+/// transactions touching the same accounts, then clamped to `[min, max]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityFeePolicy {
+    /// Percentile of recent fees to pay (0-100)
+    pub percentile: u8,
+    /// Lower bound on the compute-unit price in micro-lamports
+    pub min_micro_lamports: u64,
+    /// Upper bound on the compute-unit price in micro-lamports
+    pub max_micro_lamports: u64,
+}
+
+impl Default for PriorityFeePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 75,
+            min_micro_lamports: 1_000,
+            max_micro_lamports: 2_000_000,
+        }
+    }
+}
+
+impl PriorityFeePolicy {
+    /// Median of recent fees, at most 100k micro-lamports: cheap, for transactions that can wait
+    pub fn low() -> Self {
+        Self::new(50, 1_000, 100_000)
+    }
+
+    /// 75th percentile of recent fees, at most 1M micro-lamports
+    pub fn medium() -> Self {
+        Self::new(75, 1_000, 1_000_000)
+    }
+
+    /// 95th percentile of recent fees, at most 5M micro-lamports: to land during congestion
+    pub fn high() -> Self {
+        Self::new(95, 10_000, 5_000_000)
+    }
+
+    /// Create a policy paying the given percentile, capped to `[min, max]` micro-lamports
+    pub fn new(percentile: u8, min_micro_lamports: u64, max_micro_lamports: u64) -> Self {
+        Self {
+            percentile: percentile.min(100),
+            min_micro_lamports,
+            max_micro_lamports: max_micro_lamports.max(min_micro_lamports),
+        }
+    }
+
+    /// Select the compute-unit price from a set of recent per-slot fees
+    /// Returns the minimum when no samples are available
+    ///
+    /// The fields are public, so they are normalized as in `new`: a percentile above 100 pays
+    /// the highest fee and a maximum below the minimum is raised to it.
+    pub fn select(&self, recent_fees: &[u64]) -> u64 {
+        let min = self.min_micro_lamports;
+        let max = self.max_micro_lamports.max(min);
+        if recent_fees.is_empty() {
+            return min;
+        }
+
+        let mut fees = recent_fees.to_vec();
+        fees.sort_unstable();
+
+        // Nearest-rank percentile
+        let rank = (self.percentile.min(100) as usize * fees.len()).div_ceil(100);
+        let fee = fees[rank.saturating_sub(1)];
+
+        fee.clamp(min, max)
+    }
+}
+
+/// Accounts whose recent fees drive the priority fee for issue/redeem transactions
+/// Both instructions write-lock the vault and the CRT mint
+pub fn priority_fee_accounts() -> Vec<Pubkey> {
+    vec![VAULT_ADDRESS, CRT_MINT]
+}
+
+/// Compute-unit price from the fees recently paid for the accounts issue/redeem write-lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriorityFeeEstimator {
+    policy: PriorityFeePolicy,
+    accounts: Vec<Pubkey>,
+}
+
+impl PriorityFeeEstimator {
+    /// Estimator over `priority_fee_accounts`
+    pub fn new(policy: PriorityFeePolicy) -> Self {
+        Self {
+            policy,
+            accounts: priority_fee_accounts(),
+        }
+    }
+
+    /// Also weigh fees paid for `account`, e.g. the vault's token account of the asset moved
+    pub fn with_account(mut self, account: Pubkey) -> Self {
+        if !self.accounts.contains(&account) {
+            self.accounts.push(account);
+        }
+        self
+    }
+
+    pub fn policy(&self) -> &PriorityFeePolicy {
+        &self.policy
+    }
+
+    /// Accounts passed to `getRecentPrioritizationFees`
+    pub fn accounts(&self) -> &[Pubkey] {
+        &self.accounts
+    }
+
+    /// Fetch recent fees for `accounts` and select the price per the policy
+    pub fn estimate(&self, rpc_client: &RpcClient) -> Result<u64> {
+        let recent_fees = rpc_client.get_recent_prioritization_fees(&self.accounts)?;
+        let fees: Vec<u64> = recent_fees.iter().map(|f| f.prioritization_fee).collect();
+        Ok(self.policy.select(&fees))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_percentile() {
+        let policy = PriorityFeePolicy::new(50, 0, u64::MAX);
+        assert_eq!(policy.select(&[5, 1, 4, 2, 3]), 3);
+
+        let policy = PriorityFeePolicy::new(100, 0, u64::MAX);
+        assert_eq!(policy.select(&[5, 1, 4, 2, 3]), 5);
+
+        let policy = PriorityFeePolicy::new(0, 0, u64::MAX);
+        assert_eq!(policy.select(&[5, 1, 4, 2, 3]), 1);
+    }
+
+    #[test]
+    fn test_select_caps() {
+        let policy = PriorityFeePolicy::new(90, 1_000, 50_000);
+        assert_eq!(policy.select(&[]), 1_000);
+        assert_eq!(policy.select(&[0, 0, 0]), 1_000);
+        assert_eq!(policy.select(&[10_000_000; 4]), 50_000);
+    }
+
+    #[test]
+    fn test_select_normalizes_fields() {
+        let fees = [5, 1, 4, 2, 3];
+        let policy = PriorityFeePolicy {
+            percentile: 250,
+            min_micro_lamports: 0,
+            max_micro_lamports: u64::MAX,
+        };
+        assert_eq!(policy.select(&fees), 5);
+
+        // A maximum below the minimum pays the minimum
+        let policy = PriorityFeePolicy {
+            percentile: 50,
+            min_micro_lamports: 10,
+            max_micro_lamports: 2,
+        };
+        assert_eq!(policy.select(&fees), 10);
+        assert_eq!(policy.select(&[]), 10);
+        assert_eq!(PriorityFeePolicy::new(250, 10, 2), PriorityFeePolicy::new(100, 10, 10));
+    }
+
+    #[test]
+    fn test_presets_are_ordered() {
+        let fees: Vec<u64> = (1..=100).map(|fee| fee * 1_000).collect();
+        let low = PriorityFeePolicy::low().select(&fees);
+        let medium = PriorityFeePolicy::medium().select(&fees);
+        let high = PriorityFeePolicy::high().select(&fees);
+        assert_eq!((low, medium, high), (50_000, 75_000, 95_000));
+        // Caps apply during spikes
+        assert_eq!(PriorityFeePolicy::low().select(&[10_000_000]), 100_000);
+
+        let extra = Pubkey::new_unique();
+        let estimator = PriorityFeeEstimator::new(PriorityFeePolicy::high()).with_account(extra).with_account(extra);
+        assert_eq!(estimator.accounts(), [VAULT_ADDRESS, CRT_MINT, extra]);
+    }
+}
@@ -0,0 +1,338 @@
+//! Tamper-evident trail from raw chain data to reported NAV figures
+//!
+//! A `SnapshotProof` keeps the raw bytes of every account a snapshot was decoded from, the
+//! slot and blockhash they were read at, and the figures reported from them. `verify`
+//! decodes the bytes again and recomputes the figures, so an auditor needs neither the
+//! SDK's word nor an archive node; `verify_proof_blockhash` checks the block against the chain.
+//! Each proof commits to the hash of the one before it, so a chain of proofs can't be
+//! edited, reordered or shortened in the middle without `verify_chain` noticing.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    account::Account,
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+};
+#[cfg(feature = "blocking")]
+use solana_client::rpc_config::RpcBlockConfig;
+#[cfg(feature = "blocking")]
+use solana_commitment_config::CommitmentConfig;
+#[cfg(feature = "blocking")]
+use solana_transaction_status_client_types::TransactionDetails;
+
+use crate::{
+    error::{CarrotError, Result},
+    ledger::{from_hex, to_hex},
+    nav::{Nav, Rounding, CRT_DECIMALS},
+    snapshot::{build_snapshot, snapshot_accounts, VaultSnapshot},
+    Vault,
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Version written to every proof
+pub const PROOF_VERSION: u32 = 1;
+
+/// Domain prefix of proof hashes
+const PROOF_DOMAIN: &[u8] = b"carrot-sdk snapshot proof v1\n";
+
+/// Raw state of an account at the proof's slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawAccount {
+    pub owner: String,
+    pub lamports: u64,
+    /// Account data, hex
+    pub data: String,
+}
+
+/// An account a snapshot was decoded from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenAccount {
+    pub address: String,
+    /// `None` when the account didn't exist at the slot
+    pub account: Option<RawAccount>,
+}
+
+/// Figures reported from a snapshot; USD values have `VALUE_DECIMALS`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenFigures {
+    pub shares_supply: u64,
+    /// Idle balance of each asset, by mint, in vault asset order
+    pub vault_balances: Vec<(String, u64)>,
+    /// `Nav::total_value`, as a decimal string
+    pub total_value: String,
+    /// Value of one whole CRT, as a decimal string
+    pub share_value: String,
+}
+
+impl ProvenFigures {
+    /// Figures of `snapshot`
+    pub fn from_snapshot(snapshot: &VaultSnapshot) -> Result<Self> {
+        let nav = Nav::from_snapshot(snapshot)?;
+        Ok(Self {
+            shares_supply: snapshot.shares_supply,
+            vault_balances: snapshot
+                .assets
+                .iter()
+                .map(|asset| (asset.asset.mint.to_string(), asset.vault_balance))
+                .collect(),
+            total_value: nav.total_value()?.to_string(),
+            share_value: nav.value_of_shares(10u64.pow(CRT_DECIMALS as u32), Rounding::Down)?.to_string(),
+        })
+    }
+}
+
+/// Raw accounts behind a decoded snapshot, with the block they were read at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotProof {
+    pub proof_version: u32,
+    pub slot: u64,
+    /// Hash of the block at `slot`, base58
+    pub blockhash: String,
+    /// `hash()` of the previous proof in the trail, base58
+    pub previous_hash: Option<String>,
+    /// The vault, the CRT mint, then each asset's vault ATA and oracle
+    pub accounts: Vec<ProvenAccount>,
+    pub figures: ProvenFigures,
+}
+
+impl SnapshotProof {
+    /// Proof of `snapshot`, decoded from `accounts` (in `snapshot_accounts` order) read in
+    /// the block `blockhash`, following the proof hashing to `previous_hash`
+    pub fn new(
+        snapshot: &VaultSnapshot,
+        accounts: &[Option<Account>],
+        blockhash: &Hash,
+        previous_hash: Option<&Hash>,
+    ) -> Result<Self> {
+        let addresses = snapshot_accounts(&snapshot.vault);
+        if addresses.len() != accounts.len() {
+            return Err(CarrotError::InvalidProof(format!(
+                "{} accounts for a snapshot of {}",
+                accounts.len(),
+                addresses.len()
+            )));
+        }
+        let accounts = addresses
+            .iter()
+            .zip(accounts)
+            .map(|(address, account)| ProvenAccount {
+                address: address.to_string(),
+                account: account.as_ref().map(|account| RawAccount {
+                    owner: account.owner.to_string(),
+                    lamports: account.lamports,
+                    data: to_hex(&account.data),
+                }),
+            })
+            .collect();
+        Ok(Self {
+            proof_version: PROOF_VERSION,
+            slot: snapshot.slot,
+            blockhash: blockhash.to_string(),
+            previous_hash: previous_hash.map(ToString::to_string),
+            accounts,
+            figures: ProvenFigures::from_snapshot(snapshot)?,
+        })
+    }
+
+    /// Hash committing to everything in the proof, including the previous proof's hash
+    pub fn hash(&self) -> Result<Hash> {
+        Ok(hashv(&[PROOF_DOMAIN, self.to_json()?.as_bytes()]))
+    }
+
+    /// Decode the raw accounts again and check they yield the recorded figures
+    ///
+    /// Fails with `InvalidProof` if the accounts aren't the ones a snapshot of the recorded
+    /// vault reads or the figures don't match; returns the re-decoded snapshot.
+    pub fn verify(&self) -> Result<VaultSnapshot> {
+        if self.proof_version != PROOF_VERSION {
+            return Err(CarrotError::InvalidProof(format!("unsupported version {}", self.proof_version)));
+        }
+        let accounts = self.accounts.iter().map(ProvenAccount::decode).collect::<Result<Vec<_>>>()?;
+        let (addresses, accounts): (Vec<Pubkey>, Vec<Option<Account>>) = accounts.into_iter().unzip();
+
+        let vault_data = accounts
+            .first()
+            .and_then(Option::as_ref)
+            .ok_or_else(|| CarrotError::InvalidProof("no vault account".to_string()))?;
+        let vault = Vault::from_account_data(&vault_data.data)?;
+        if addresses != snapshot_accounts(&vault) {
+            return Err(CarrotError::InvalidProof(
+                "accounts aren't the ones a snapshot of this vault reads".to_string(),
+            ));
+        }
+        let snapshot = build_snapshot(self.slot, &vault, accounts)?
+            .ok_or_else(|| CarrotError::InvalidProof("inconsistent vault assets".to_string()))?;
+        let figures = ProvenFigures::from_snapshot(&snapshot)?;
+        if figures != self.figures {
+            return Err(CarrotError::InvalidProof(format!(
+                "recorded figures {:?} don't match the accounts, which yield {:?}",
+                self.figures, figures
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| CarrotError::InvalidProof(format!("failed to serialize: {}", e)))
+    }
+
+    /// Parse a proof produced by `to_json`; call `verify` before trusting it
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| CarrotError::InvalidProof(format!("malformed proof: {}", e)))
+    }
+}
+
+impl ProvenAccount {
+    fn decode(&self) -> Result<(Pubkey, Option<Account>)> {
+        let invalid = |what: &str| CarrotError::InvalidProof(format!("invalid {} of {}", what, self.address));
+        let address = Pubkey::from_str(&self.address).map_err(|_| invalid("address"))?;
+        let account = self
+            .account
+            .as_ref()
+            .map(|raw| -> Result<Account> {
+                Ok(Account {
+                    owner: Pubkey::from_str(&raw.owner).map_err(|_| invalid("owner"))?,
+                    lamports: raw.lamports,
+                    data: from_hex(&raw.data).ok_or_else(|| invalid("data"))?,
+                    ..Default::default()
+                })
+            })
+            .transpose()?;
+        Ok((address, account))
+    }
+}
+
+/// Verify every proof of a trail, oldest first, and that each follows the one before it
+pub fn verify_chain(proofs: &[SnapshotProof]) -> Result<()> {
+    for (i, proof) in proofs.iter().enumerate() {
+        proof.verify()?;
+        let Some(previous) = i.checked_sub(1).map(|j| &proofs[j]) else {
+            continue;
+        };
+        if proof.previous_hash != Some(previous.hash()?.to_string()) {
+            return Err(CarrotError::InvalidProof(format!(
+                "proof at slot {} doesn't follow the one before",
+                proof.slot
+            )));
+        }
+        if proof.slot <= previous.slot {
+            return Err(CarrotError::InvalidProof(format!("proof at slot {} is out of order", proof.slot)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Fetch a snapshot together with its proof, chained to `previous` if given
+    pub fn fetch_snapshot_with_proof(
+        &self,
+        previous: Option<&SnapshotProof>,
+    ) -> Result<(VaultSnapshot, SnapshotProof)> {
+        let (snapshot, accounts) = self.fetch_snapshot_with_accounts()?;
+        let blockhash = self.block_hash(snapshot.slot)?;
+        let previous_hash = previous.map(SnapshotProof::hash).transpose()?;
+        let proof = SnapshotProof::new(&snapshot, &accounts, &blockhash, previous_hash.as_ref())?;
+        Ok((snapshot, proof))
+    }
+
+    /// Fail with `InvalidProof` unless the block at the proof's slot has its recorded hash
+    pub fn verify_proof_blockhash(&self, proof: &SnapshotProof) -> Result<()> {
+        let blockhash = self.block_hash(proof.slot)?;
+        if blockhash.to_string() != proof.blockhash {
+            return Err(CarrotError::InvalidProof(format!(
+                "block at slot {} has hash {}, proof records {}",
+                proof.slot, blockhash, proof.blockhash
+            )));
+        }
+        Ok(())
+    }
+
+    fn block_hash(&self, slot: u64) -> Result<Hash> {
+        // Blocks can't be read at processed commitment
+        let commitment = if self.rpc_client().commitment().is_finalized() {
+            CommitmentConfig::finalized()
+        } else {
+            CommitmentConfig::confirmed()
+        };
+        let config = RpcBlockConfig {
+            transaction_details: Some(TransactionDetails::None),
+            rewards: Some(false),
+            commitment: Some(commitment),
+            max_supported_transaction_version: Some(0),
+            ..Default::default()
+        };
+        let block = self.rpc_client().get_block_with_config(slot, config)?;
+        Hash::from_str(&block.blockhash)
+            .map_err(|_| CarrotError::InvalidProof(format!("invalid blockhash {}", block.blockhash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::usdc_vault;
+    use crate::oracle::{tests::pyth_price_update_data, PYTH_RECEIVER_PROGRAM_ID};
+    use crate::snapshot::tests::{mint_data, token_account_data};
+
+    fn proof(previous: Option<&SnapshotProof>, slot: u64, shares_supply: u64) -> SnapshotProof {
+        let vault = usdc_vault().vault;
+        let account = |owner, data| {
+            Some(Account {
+                owner,
+                lamports: 1,
+                data,
+                ..Default::default()
+            })
+        };
+        let accounts = vec![
+            account(crate::CARROT_PROGRAM_ID, vault.to_account_data()),
+            account(spl_token_2022_interface::id(), mint_data(shares_supply)),
+            account(spl_token_interface::id(), token_account_data(2_000_000)),
+            account(PYTH_RECEIVER_PROGRAM_ID, pyth_price_update_data(100_000_000, 0, -8)),
+        ];
+        let snapshot = build_snapshot(slot, &vault, accounts.clone()).unwrap().unwrap();
+        let previous_hash = previous.map(|proof| proof.hash().unwrap());
+        SnapshotProof::new(&snapshot, &accounts, &Hash::new_unique(), previous_hash.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let proof = proof(None, 10, 1_000_000_000);
+        assert_eq!(proof.figures.total_value, "2000000000000");
+        let snapshot = SnapshotProof::from_json(&proof.to_json().unwrap()).unwrap().verify().unwrap();
+        assert_eq!(snapshot.assets[0].vault_balance, 2_000_000);
+
+        // Inflated figures
+        let mut inflated = proof.clone();
+        inflated.figures.total_value = "3000000000000".to_string();
+        assert!(matches!(inflated.verify(), Err(CarrotError::InvalidProof(_))));
+        // Edited account bytes: the figures no longer follow
+        let mut edited = proof.clone();
+        let balance = edited.accounts[2].account.as_mut().unwrap();
+        balance.data = to_hex(&token_account_data(3_000_000));
+        assert!(matches!(edited.verify(), Err(CarrotError::InvalidProof(_))));
+        // Swapped account address
+        let mut swapped = proof;
+        swapped.accounts[3].address = Pubkey::new_unique().to_string();
+        assert!(matches!(swapped.verify(), Err(CarrotError::InvalidProof(_))));
+    }
+
+    #[test]
+    fn test_chain_links_proofs() {
+        let first = proof(None, 10, 1_000_000_000);
+        let second = proof(Some(&first), 20, 1_000_000_000);
+        let third = proof(Some(&second), 30, 1_000_000_000);
+        verify_chain(&[first.clone(), second.clone(), third.clone()]).unwrap();
+
+        // Dropping a proof from the middle breaks the link
+        assert!(verify_chain(&[first.clone(), third]).is_err());
+        // So does editing an earlier proof, even consistently
+        let mut rewritten = first;
+        rewritten.blockhash = Hash::new_unique().to_string();
+        assert!(verify_chain(&[rewritten, second]).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig};
+use solana_sdk::account::Account;
+
+use crate::{
+    client::CarrotClient,
+    error::{CarrotError, Result},
+    supervisor::{ShutdownSignal, TaskGroup},
+    Vault, VAULT_ADDRESS,
+};
+
+/// How often a subscription checks for shutdown while no notification arrives
+const NOTIFICATION_POLL: Duration = Duration::from_millis(500);
+
+/// Why a websocket connection ended
+enum Disconnect {
+    Shutdown,
+    Failed(CarrotError),
+}
+
+impl CarrotClient {
+    /// Hand every update of the vault account to `handler` as it happens, with the slot it
+    /// was written at, from a supervised task in `group`
+    ///
+    /// Subscribes over `ClientConfig::ws_endpoints`: when an endpoint can't be reached or
+    /// drops the connection, the next one is tried; once all failed, the task restarts
+    /// with the group's backoff. Updates made while reconnecting are not replayed, so read
+    /// the vault after a reconnect if none may be missed. Websocket connections don't carry
+    /// `ClientConfig::headers`; providers expecting an API key take it in the URL.
+    pub fn subscribe_vault_updates<F>(&self, group: &mut TaskGroup, mut handler: F) -> Result<()>
+    where
+        F: FnMut(u64, &Vault) -> Result<()> + Send + 'static,
+    {
+        let endpoints = self.config().ws_endpoints()?;
+        let commitment = self.config().commitment;
+        // The endpoint that last worked is tried first
+        let mut current = 0;
+        group.spawn("vault-updates", move |shutdown| {
+            let mut last_error = None;
+            for attempt in 0..endpoints.len() {
+                let index = (current + attempt) % endpoints.len();
+                let config = RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(commitment),
+                    ..Default::default()
+                };
+                match watch_vault(&endpoints[index], config, shutdown, &mut handler) {
+                    Disconnect::Shutdown => return Ok(()),
+                    Disconnect::Failed(err) => {
+                        log::warn!("websocket endpoint {} failed: {}", index, err);
+                        last_error = Some(err);
+                    }
+                }
+                current = (index + 1) % endpoints.len();
+            }
+            Err(last_error.unwrap_or_else(|| CarrotError::InvalidConfig("no websocket endpoints".to_string())))
+        });
+        Ok(())
+    }
+}
+
+/// Deliver vault updates from `url` until shutdown or the connection fails
+fn watch_vault<F>(url: &str, config: RpcAccountInfoConfig, shutdown: &ShutdownSignal, handler: &mut F) -> Disconnect
+where
+    F: FnMut(u64, &Vault) -> Result<()>,
+{
+    let (mut subscription, notifications) = match PubsubClient::account_subscribe(url, &VAULT_ADDRESS, Some(config)) {
+        Ok(subscribed) => subscribed,
+        Err(e) => return Disconnect::Failed(CarrotError::Subscription(e.to_string())),
+    };
+    let disconnect = loop {
+        if shutdown.is_triggered() {
+            break Disconnect::Shutdown;
+        }
+        let notification = match notifications.recv_timeout(NOTIFICATION_POLL) {
+            Ok(notification) => notification,
+            Err(e) if e.is_timeout() => continue,
+            Err(_) => break Disconnect::Failed(CarrotError::Subscription("connection closed".to_string())),
+        };
+        let vault = notification
+            .value
+            .decode::<Account>()
+            .ok_or_else(|| CarrotError::Subscription("undecodable vault account".to_string()))
+            .and_then(|account| Vault::from_account_data(&account.data));
+        if let Err(err) = vault.and_then(|vault| handler(notification.context.slot, &vault)) {
+            break Disconnect::Failed(err);
+        }
+    };
+    // Best effort: the socket is closed either way
+    let _ = subscription.send_unsubscribe();
+    let _ = subscription.shutdown();
+    disconnect
+}
@@ -0,0 +1,375 @@
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "blocking")]
+use solana_sdk::{signature::Signature, signer::Signer};
+
+use crate::{
+    effects::ExpectedEffects,
+    error::{CarrotError, Result},
+    nav::{deduct_fee_bps, AssetAmount, CrtAmount, Nav, Rounding, BPS_DENOMINATOR},
+    Fee,
+};
+#[cfg(feature = "blocking")]
+use crate::{
+    client::CarrotClient,
+    compute_units::ComputeUnitLimit,
+    token::{associated_account_len, TokenProgram},
+    CRT_MINT,
+};
+
+/// Slots a quote stays executable for (about one minute, the lifetime of a blockhash)
+pub const QUOTE_VALIDITY_SLOTS: u64 = 150;
+
+/// Base fee per transaction signature, in lamports
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Compute units priced into `QuoteCosts::priority_fee` unless the limit is fixed
+/// (issue/redeem with ATA creation stays well below it)
+pub const QUOTE_COMPUTE_UNITS: u32 = 200_000;
+
+/// Micro-lamports per lamport, the unit of compute-unit prices
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Direction of a quote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteSide {
+    /// Deposit an asset, receive CRT
+    Deposit,
+    /// Redeem CRT, receive an asset
+    Withdraw,
+}
+
+/// Estimated SOL cost of executing a quote, on top of the token amounts (lamports)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuoteCosts {
+    /// Base fee for the transaction's signature
+    pub network_fee: u64,
+    /// Configured priority fee: estimated compute-unit price times the compute-unit limit
+    pub priority_fee: u64,
+    /// Rent deposited into the token account the transaction creates (the CRT account for
+    /// deposits, the asset account for withdrawals), refundable by closing it; zero if it
+    /// exists or the quote isn't for a specific user
+    pub ata_rent: u64,
+}
+
+impl QuoteCosts {
+    /// Total lamports the signer spends
+    pub fn total(&self) -> u64 {
+        self.network_fee
+            .saturating_add(self.priority_fee)
+            .saturating_add(self.ata_rent)
+    }
+}
+
+/// Priced deposit or withdrawal that can be executed with `CarrotClient::execute_quote`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub side: QuoteSide,
+    /// Asset deposited or received
+    pub asset_mint: Pubkey,
+    /// Asset amount for deposits, CRT amount for withdrawals (base units)
+    pub input: u64,
+    /// CRT (deposits) or asset (withdrawals) expected at the quoted NAV, net of fees
+    pub expected_output: u64,
+    /// Least output within the oracles' confidence intervals, net of fees
+    pub conservative_output: u64,
+    /// Least output `execute_quote` accepts, after slippage tolerance
+    /// (applied to `conservative_output` for withdrawals, to `expected_output` for deposits)
+    pub min_output: u64,
+    /// Fee charged on the output (redemption fee for withdrawals)
+    pub fee_bps: u16,
+    /// USD value of one whole CRT at quote time (for display only)
+    pub share_price: f64,
+    /// Last slot at which the quote may be executed
+    pub valid_until_slot: u64,
+    /// Network fee, priority fee and rent (zero when priced with `from_nav` alone)
+    pub costs: QuoteCosts,
+}
+
+impl Quote {
+    /// Price `input` against `nav`, allowing the output to fall `slippage_bps` below expectation
+    ///
+    /// The oracle prices' age isn't checked here; `CarrotClient::get_quote` refuses prices
+    /// older than `ClientConfig::max_oracle_age` with `Nav::ensure_fresh_prices`.
+    pub fn from_nav(
+        nav: &Nav,
+        fee: &Fee,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        input: u64,
+        slippage_bps: u16,
+    ) -> Result<Self> {
+        let (expected_output, conservative_output, fee_bps) = match side {
+            QuoteSide::Deposit => (
+                AssetAmount::new(*asset_mint, input).to_expected_shares(nav, Rounding::Down)?.0,
+                nav.conservative_deposit(input, asset_mint)?,
+                0,
+            ),
+            QuoteSide::Withdraw => {
+                let gross = CrtAmount(input).to_asset_value(nav, asset_mint, Rounding::Down)?.amount;
+                let conservative = nav.conservative_withdrawal(input, asset_mint)?;
+                (
+                    deduct_fee_bps(gross, fee.redemption_fee_bps)?,
+                    deduct_fee_bps(conservative, fee.redemption_fee_bps)?,
+                    fee.redemption_fee_bps,
+                )
+            }
+        };
+        if slippage_bps as u64 > BPS_DENOMINATOR {
+            return Err(CarrotError::InvalidConfig(format!(
+                "slippage of {} bps exceeds 100%",
+                slippage_bps
+            )));
+        }
+
+        // Risk checks require withdrawals to be guarded by the bid-side valuation
+        let guarded_output = match side {
+            QuoteSide::Deposit => expected_output,
+            QuoteSide::Withdraw => conservative_output,
+        };
+
+        Ok(Self {
+            side,
+            asset_mint: *asset_mint,
+            input,
+            expected_output,
+            conservative_output,
+            min_output: deduct_fee_bps(guarded_output, slippage_bps)?,
+            fee_bps,
+            share_price: nav.share_price()?,
+            valid_until_slot: nav.slot + QUOTE_VALIDITY_SLOTS,
+            costs: QuoteCosts::default(),
+        })
+    }
+
+    /// Price each of `inputs` against the same `nav`, e.g. a ladder of sizes for a slider
+    pub fn many_from_nav(
+        nav: &Nav,
+        fee: &Fee,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        inputs: &[u64],
+        slippage_bps: u16,
+    ) -> Result<Vec<Self>> {
+        inputs
+            .iter()
+            .map(|input| Self::from_nav(nav, fee, side, asset_mint, *input, slippage_bps))
+            .collect()
+    }
+
+    /// Balance changes `execute_quote` requires from the simulated transaction
+    pub fn expected_effects(&self) -> ExpectedEffects {
+        match self.side {
+            QuoteSide::Deposit => ExpectedEffects::deposit(self.input, self.min_output),
+            QuoteSide::Withdraw => ExpectedEffects::withdraw(self.input, self.min_output),
+        }
+    }
+
+    /// Fail with `QuoteExpired` once `slot` is past the quote's validity
+    pub fn check_valid(&self, slot: u64) -> Result<()> {
+        if slot > self.valid_until_slot {
+            return Err(CarrotError::QuoteExpired {
+                valid_until_slot: self.valid_until_slot,
+                slot,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Quote a deposit (`input` of `asset_mint`) or withdrawal (`input` CRT) at the current NAV
+    pub fn get_quote(
+        &self,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        input: u64,
+        slippage_bps: u16,
+    ) -> Result<Quote> {
+        self.get_quote_inner(None, side, asset_mint, input, slippage_bps)
+    }
+
+    /// Quote for `user`, with `costs` including the rent of any token account to be created
+    pub fn get_quote_for(
+        &self,
+        user: &Pubkey,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        input: u64,
+        slippage_bps: u16,
+    ) -> Result<Quote> {
+        self.get_quote_inner(Some(user), side, asset_mint, input, slippage_bps)
+    }
+
+    /// Estimate the network fee, priority fee and, given `user`, the rent of token accounts a
+    /// deposit or withdrawal of `asset_mint` would create
+    ///
+    /// The priority fee uses the configured `PriorityFeePolicy` (none without one) and the
+    /// fixed compute-unit limit if configured, `QUOTE_COMPUTE_UNITS` otherwise.
+    pub fn estimate_quote_costs(
+        &self,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        user: Option<&Pubkey>,
+    ) -> Result<QuoteCosts> {
+        let priority_fee = match &self.config().priority_fee {
+            Some(policy) => {
+                let units = match self.config().compute_unit_limit {
+                    ComputeUnitLimit::Fixed(limit) => limit,
+                    _ => QUOTE_COMPUTE_UNITS,
+                };
+                let price = self.estimate_priority_fee(policy)? as u128;
+                let lamports = (price * units as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+                u64::try_from(lamports).map_err(|_| CarrotError::MathOverflow)?
+            }
+            None => 0,
+        };
+        let ata_rent = match user {
+            Some(user) => self.token_account_rent(side, asset_mint, user)?,
+            None => 0,
+        };
+        Ok(QuoteCosts {
+            network_fee: LAMPORTS_PER_SIGNATURE,
+            priority_fee,
+            ata_rent,
+        })
+    }
+
+    /// Rent of the ATA a deposit (CRT) or withdrawal (`asset_mint`) would create for `user`
+    fn token_account_rent(&self, side: QuoteSide, asset_mint: &Pubkey, user: &Pubkey) -> Result<u64> {
+        let mint = match side {
+            QuoteSide::Deposit => CRT_MINT,
+            QuoteSide::Withdraw => *asset_mint,
+        };
+        let program = match side {
+            QuoteSide::Deposit => TokenProgram::Token2022,
+            QuoteSide::Withdraw => self.get_token_program(asset_mint)?,
+        };
+        let ata = program.associated_token_address(user, &mint);
+        let accounts = self
+            .rpc_client()
+            .get_multiple_accounts_with_commitment(&[ata, mint], self.rpc_client().commitment())?
+            .value;
+        if accounts.first().is_some_and(Option::is_some) {
+            return Ok(0);
+        }
+        let mint_account = accounts
+            .into_iter()
+            .nth(1)
+            .flatten()
+            .ok_or_else(|| CarrotError::AccountNotFound(format!("mint {}", mint)))?;
+        let len = associated_account_len(program, &mint_account.data)?;
+        Ok(self.rpc_client().get_minimum_balance_for_rent_exemption(len)?)
+    }
+
+    fn get_quote_inner(
+        &self,
+        user: Option<&Pubkey>,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        input: u64,
+        slippage_bps: u16,
+    ) -> Result<Quote> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        nav.ensure_fresh_prices(self.now(), self.config().max_oracle_age)?;
+        let mut quote = Quote::from_nav(&nav, &snapshot.vault.fee, side, asset_mint, input, slippage_bps)?;
+        quote.costs = self.estimate_quote_costs(side, asset_mint, user)?;
+        Ok(quote)
+    }
+
+    /// Quote every size in `inputs` from a single snapshot, so all quotes share one NAV and slot
+    pub fn quote_many(
+        &self,
+        side: QuoteSide,
+        asset_mint: &Pubkey,
+        inputs: &[u64],
+        slippage_bps: u16,
+    ) -> Result<Vec<Quote>> {
+        let snapshot = self.fetch_snapshot()?;
+        let nav = Nav::from_snapshot_with_fees(&snapshot, self.now())?;
+        nav.ensure_fresh_prices(self.now(), self.config().max_oracle_age)?;
+        let costs = self.estimate_quote_costs(side, asset_mint, None)?;
+        let mut quotes = Quote::many_from_nav(&nav, &snapshot.vault.fee, side, asset_mint, inputs, slippage_bps)?;
+        quotes.iter_mut().for_each(|quote| quote.costs = costs);
+        Ok(quotes)
+    }
+
+    /// Execute a quote, refusing it once expired or if the simulated output is below `min_output`
+    pub fn execute_quote(&self, quote: &Quote, signer: &dyn Signer) -> Result<Signature> {
+        quote.check_valid(self.rpc_client().get_slot()?)?;
+
+        let prepared = match quote.side {
+            QuoteSide::Deposit => self.build_deposit_transaction(signer, &quote.asset_mint, quote.input)?,
+            QuoteSide::Withdraw => self.build_withdraw_transaction(signer, &quote.asset_mint, quote.input)?,
+        };
+        self.send_prepared_with_effects(&prepared, &quote.expected_effects())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nav::tests::test_nav;
+    use crate::USDC_MINT;
+
+    fn fee(redemption_fee_bps: u16) -> Fee {
+        Fee {
+            redemption_fee_bps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_withdraw_quote() {
+        let mint = USDC_MINT;
+        let nav = test_nav(1_000_000_000_000, vec![(mint, 1_100_000_000, 100_000_000)]);
+        let shares = nav.shares_supply / 10;
+        let quote = Quote::from_nav(&nav, &fee(10), QuoteSide::Withdraw, &mint, shares, 50).unwrap();
+
+        let gross = CrtAmount(shares).to_asset_value(&nav, &mint, Rounding::Down).unwrap().amount;
+        assert_eq!(quote.expected_output, deduct_fee_bps(gross, 10).unwrap());
+        assert_eq!(quote.conservative_output, quote.expected_output); // zero confidence interval
+        assert_eq!(quote.min_output, deduct_fee_bps(quote.conservative_output, 50).unwrap());
+        assert_eq!(quote.fee_bps, 10);
+        assert_eq!(quote.valid_until_slot, nav.slot + QUOTE_VALIDITY_SLOTS);
+        assert_eq!(quote.expected_effects(), ExpectedEffects::withdraw(shares, quote.min_output));
+
+        assert!(quote.check_valid(quote.valid_until_slot).is_ok());
+        assert!(matches!(
+            quote.check_valid(quote.valid_until_slot + 1),
+            Err(CarrotError::QuoteExpired { .. })
+        ));
+    }
+
+    #[test]
+    fn test_quote_ladder_matches_single_quotes() {
+        let mint = USDC_MINT;
+        let nav = test_nav(1_000_000_000_000, vec![(mint, 1_100_000_000, 100_000_000)]);
+        let inputs = [1_000_000, 10_000_000, 100_000_000];
+        let quotes = Quote::many_from_nav(&nav, &fee(0), QuoteSide::Deposit, &mint, &inputs, 50).unwrap();
+        assert_eq!(quotes.len(), inputs.len());
+        for (quote, input) in quotes.iter().zip(inputs) {
+            assert_eq!(*quote, Quote::from_nav(&nav, &fee(0), QuoteSide::Deposit, &mint, input, 50).unwrap());
+        }
+        assert!(quotes.windows(2).all(|pair| pair[0].expected_output < pair[1].expected_output));
+    }
+
+    #[test]
+    fn test_costs_total() {
+        let costs = QuoteCosts {
+            network_fee: LAMPORTS_PER_SIGNATURE,
+            priority_fee: 1_000,
+            ata_rent: 2_074_080,
+        };
+        assert_eq!(costs.total(), 2_080_080);
+        assert_eq!(QuoteCosts::default().total(), 0);
+    }
+
+    #[test]
+    fn test_quote_rejects_excessive_slippage() {
+        let mint = USDC_MINT;
+        let nav = test_nav(1_000_000_000_000, vec![(mint, 1_100_000_000, 100_000_000)]);
+        assert!(Quote::from_nav(&nav, &fee(0), QuoteSide::Deposit, &mint, 1_000, 10_001).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+
+use crate::{
+    accounting::ExecutionReport,
+    error::{CarrotError, Result},
+    quote::QuoteSide,
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Domain prefix of receipt messages, so a receipt signature can't be replayed as anything else
+const RECEIPT_DOMAIN: &[u8] = b"carrot-sdk receipt v1\n";
+
+/// Version written to every receipt
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// One deposit or withdrawal attested by a `Receipt`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptLeg {
+    /// `"deposit"` or `"withdraw"`
+    pub operation: String,
+    pub asset_mint: String,
+    pub asset_symbol: String,
+    pub asset_decimals: u8,
+    /// Asset spent (deposit) or received net of the redemption fee (withdrawal)
+    pub asset_amount: u64,
+    /// CRT minted (deposit) or burned (withdrawal)
+    pub shares: u64,
+    /// Redemption fee withheld, in asset units
+    pub fee: u64,
+}
+
+/// Proof of execution of a Carrot transaction, in a stable form integrators can hand out
+///
+/// Addresses and signatures are base58 strings, like the records in `schema`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub receipt_version: u32,
+    /// Transaction signature
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub user: String,
+    pub legs: Vec<ReceiptLeg>,
+    /// Unix timestamp the receipt was signed at
+    pub issued_at: i64,
+    /// Service key the receipt is signed by
+    pub service: String,
+}
+
+impl Receipt {
+    /// Receipt for `report`, to be signed by `service` at `issued_at` (Unix timestamp)
+    pub fn from_report(report: &ExecutionReport, service: &Pubkey, issued_at: i64) -> Self {
+        Self {
+            receipt_version: RECEIPT_VERSION,
+            signature: report.signature.to_string(),
+            slot: report.slot,
+            block_time: report.block_time,
+            user: report.user.to_string(),
+            legs: report
+                .legs
+                .iter()
+                .map(|leg| ReceiptLeg {
+                    operation: match leg.side {
+                        QuoteSide::Deposit => "deposit",
+                        QuoteSide::Withdraw => "withdraw",
+                    }
+                    .to_string(),
+                    asset_mint: leg.asset_mint.to_string(),
+                    asset_symbol: leg.asset_symbol.clone(),
+                    asset_decimals: leg.asset_decimals,
+                    asset_amount: leg.asset_amount,
+                    shares: leg.shares,
+                    fee: leg.fee,
+                })
+                .collect(),
+            issued_at,
+            service: service.to_string(),
+        }
+    }
+
+    /// Bytes the service signs: the domain prefix followed by the receipt's JSON
+    pub fn signing_message(&self) -> Vec<u8> {
+        let mut message = RECEIPT_DOMAIN.to_vec();
+        // Strings and integers only, serializing can't fail
+        message.extend(serde_json::to_vec(self).expect("receipt serializes"));
+        message
+    }
+}
+
+/// A `Receipt` with the service's signature over it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedReceipt {
+    pub receipt: Receipt,
+    /// Signature over `Receipt::signing_message`, base58
+    pub service_signature: String,
+}
+
+impl SignedReceipt {
+    /// Sign a receipt for `report` with the `service` key
+    pub fn sign(service: &Keypair, report: &ExecutionReport, issued_at: i64) -> Self {
+        let receipt = Receipt::from_report(report, &service.pubkey(), issued_at);
+        let service_signature = service.sign_message(&receipt.signing_message()).to_string();
+        Self {
+            receipt,
+            service_signature,
+        }
+    }
+
+    /// Fail with `InvalidReceipt` unless this receipt was signed by `service` and is unaltered
+    pub fn verify(&self, service: &Pubkey) -> Result<()> {
+        let signer = Pubkey::from_str(&self.receipt.service)
+            .map_err(|_| CarrotError::InvalidReceipt(format!("invalid service key {}", self.receipt.service)))?;
+        if signer != *service {
+            return Err(CarrotError::InvalidReceipt(format!(
+                "signed by {}, expected {}",
+                signer, service
+            )));
+        }
+        let signature = Signature::from_str(&self.service_signature)
+            .map_err(|_| CarrotError::InvalidReceipt("malformed service signature".to_string()))?;
+        if !signature.verify(service.as_ref(), &self.receipt.signing_message()) {
+            return Err(CarrotError::InvalidReceipt(format!(
+                "signature doesn't match receipt for {}",
+                self.receipt.signature
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| CarrotError::InvalidReceipt(format!("failed to serialize: {}", e)))
+    }
+
+    /// Parse a receipt produced by `to_json`; call `verify` before trusting it
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| CarrotError::InvalidReceipt(format!("malformed receipt: {}", e)))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Receipt for the confirmed transaction `signature`, signed by the configured service key
+    /// (`ClientConfig::with_receipt_signer`)
+    pub fn issue_receipt(&self, signature: &Signature) -> Result<SignedReceipt> {
+        let signer = self.config().receipt_signer.clone().ok_or_else(|| {
+            CarrotError::InvalidConfig("issue_receipt needs a receipt signer".to_string())
+        })?;
+        let report = self.fetch_execution_report(signature)?;
+        Ok(SignedReceipt::sign(&signer, &report, self.now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::ExecutionLeg;
+    use crate::USDC_MINT;
+
+    fn report() -> ExecutionReport {
+        ExecutionReport {
+            signature: Signature::new_unique(),
+            slot: 42,
+            block_time: Some(1_700_000_000),
+            user: Pubkey::new_unique(),
+            network_fee: 5_000,
+            legs: vec![ExecutionLeg {
+                side: QuoteSide::Deposit,
+                asset_mint: USDC_MINT,
+                asset_symbol: "USDC".to_string(),
+                asset_decimals: 6,
+                asset_amount: 1_000_000,
+                shares: 990_000_000,
+                fee: 0,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_signed_receipt_round_trips_and_verifies() {
+        let service = Keypair::new();
+        let report = report();
+        let signed = SignedReceipt::sign(&service, &report, 1_700_000_100);
+        assert_eq!(signed.receipt.signature, report.signature.to_string());
+        assert_eq!(signed.receipt.legs[0].operation, "deposit");
+
+        let parsed = SignedReceipt::from_json(&signed.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, signed);
+        parsed.verify(&service.pubkey()).unwrap();
+
+        // Wrong service key
+        assert!(matches!(
+            parsed.verify(&Pubkey::new_unique()),
+            Err(CarrotError::InvalidReceipt(_))
+        ));
+
+        // Tampered amount
+        let mut tampered = parsed.clone();
+        tampered.receipt.legs[0].shares += 1;
+        assert!(matches!(
+            tampered.verify(&service.pubkey()),
+            Err(CarrotError::InvalidReceipt(_))
+        ));
+    }
+}
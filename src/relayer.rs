@@ -0,0 +1,440 @@
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+use solana_sanitize::Sanitize;
+use solana_sdk_ids::compute_budget;
+use spl_token_2022_interface::instruction::TokenInstruction;
+#[cfg(feature = "blocking")]
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+#[cfg(feature = "blocking")]
+use solana_sdk::{signature::Signature, signer::Signer};
+
+use crate::{
+    error::{CarrotError, Result},
+    token::TokenProgram,
+    CRT_MINT,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, prepared::PreparedTransaction, transfer_hook::HookedTransfer};
+
+/// `ComputeBudgetInstruction::SetComputeUnitPrice` tag
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+
+/// Token fee a gasless transaction pays its relayer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayerFee {
+    pub mint: Pubkey,
+    /// Base units of `mint`
+    pub amount: u64,
+}
+
+/// What a relayer agrees to pay network fees for (Octane-style)
+///
+/// A transaction is sponsored only if the relayer is its fee payer and appears nowhere else,
+/// except as the funder of the user's CRT account when allowed, and if it transfers at least
+/// the minimum fee in an accepted token to the relayer's associated token account.
+#[derive(Debug, Clone)]
+pub struct RelayerPolicy {
+    relayer: Pubkey,
+    minimum_fees: Vec<(Pubkey, u64)>,
+    fund_account_creation: bool,
+    max_signatures: usize,
+    max_compute_unit_price: Option<u64>,
+}
+
+impl RelayerPolicy {
+    /// Policy of the relayer paying with `relayer`; accepts no fee token until `with_fee` is called
+    pub fn new(relayer: Pubkey) -> Self {
+        Self {
+            relayer,
+            minimum_fees: Vec::new(),
+            fund_account_creation: false,
+            max_signatures: 2,
+            max_compute_unit_price: None,
+        }
+    }
+
+    /// Accept fees in `mint` of at least `minimum` base units
+    pub fn with_fee(mut self, mint: Pubkey, minimum: u64) -> Self {
+        self.minimum_fees.retain(|(accepted, _)| *accepted != mint);
+        self.minimum_fees.push((mint, minimum));
+        self
+    }
+
+    /// Let the relayer fund the creation of the user's CRT account (off by default)
+    ///
+    /// Deposits always include the idempotent creation of the user's CRT account, so relayers
+    /// sponsoring deposits need this, and should price the rent into their minimum fee. Only one
+    /// creation per transaction is funded, of the CRT associated token account of a signer;
+    /// anything else could drain the relayer's SOL into rent the user reclaims.
+    pub fn with_account_creation(mut self, allowed: bool) -> Self {
+        self.fund_account_creation = allowed;
+        self
+    }
+
+    /// Refuse transactions with more than `max` signatures, each costing the relayer a fee (2 by default)
+    pub fn with_max_signatures(mut self, max: usize) -> Self {
+        self.max_signatures = max;
+        self
+    }
+
+    /// Refuse priority fees above `micro_lamports` per compute unit
+    pub fn with_max_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.max_compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    pub fn relayer(&self) -> &Pubkey {
+        &self.relayer
+    }
+
+    /// Check `transaction` against the policy, returning the fee it pays
+    ///
+    /// Only the transaction itself is inspected; `CarrotClient::sponsor_transaction` also
+    /// simulates it before signing.
+    pub fn verify(&self, transaction: &Transaction) -> Result<RelayerFee> {
+        let reject = |reason: String| Err(CarrotError::RelayRejected(reason));
+        if let Err(err) = transaction.sanitize() {
+            return reject(format!("malformed transaction: {}", err));
+        }
+        let message = &transaction.message;
+        if message.account_keys.first() != Some(&self.relayer) {
+            return reject(format!("fee payer is not the relayer {}", self.relayer));
+        }
+        let signatures = message.header.num_required_signatures as usize;
+        if signatures > self.max_signatures {
+            return reject(format!("{} signatures, at most {} accepted", signatures, self.max_signatures));
+        }
+        // Every signature but the relayer's must already be there
+        if transaction.verify_with_results().iter().skip(1).any(|valid| !valid) {
+            return reject("missing or invalid user signature".to_string());
+        }
+        let signers = message.account_keys.get(1..signatures).unwrap_or_default();
+
+        let key = |index: u8| {
+            message.account_keys.get(index as usize).copied().ok_or_else(|| {
+                CarrotError::RelayRejected(format!("account index {} out of bounds", index))
+            })
+        };
+        let mut fee = None;
+        let mut funded_creations = 0;
+        for compiled in &message.instructions {
+            let instruction = Instruction {
+                program_id: key(compiled.program_id_index)?,
+                accounts: compiled
+                    .accounts
+                    .iter()
+                    .map(|&index| {
+                        Ok(AccountMeta {
+                            pubkey: key(index)?,
+                            is_signer: message.is_signer(index as usize),
+                            is_writable: message.is_maybe_writable(index as usize, None),
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+                data: compiled.data.clone(),
+            };
+            if self.check_relayer_use(&instruction, signers)? {
+                funded_creations += 1;
+            }
+            self.check_compute_unit_price(&instruction)?;
+            if let Some(payment) = self.fee_payment(&instruction) {
+                fee = Some(payment);
+            }
+        }
+        if funded_creations > 1 {
+            return reject(format!("{} account creations funded by the relayer, at most 1", funded_creations));
+        }
+
+        let fee = fee.ok_or_else(|| CarrotError::RelayRejected("no fee transfer to the relayer".to_string()))?;
+        let minimum = self
+            .minimum_fees
+            .iter()
+            .find(|(mint, _)| *mint == fee.mint)
+            .map(|(_, minimum)| *minimum)
+            .ok_or_else(|| CarrotError::RelayRejected(format!("fees in {} are not accepted", fee.mint)))?;
+        if fee.amount < minimum {
+            return reject(format!("fee of {} {} is below the minimum of {}", fee.amount, fee.mint, minimum));
+        }
+        Ok(fee)
+    }
+
+    /// Fail if `instruction` could spend from or sign for the relayer; `Ok(true)` if it is an
+    /// allowed, relayer-funded creation of a signer's CRT account
+    fn check_relayer_use(&self, instruction: &Instruction, signers: &[Pubkey]) -> Result<bool> {
+        if instruction.accounts.iter().all(|meta| meta.pubkey != self.relayer) {
+            return Ok(false);
+        }
+        if self.fund_account_creation && self.creates_crt_account(instruction, signers) {
+            return Ok(true);
+        }
+        Err(CarrotError::RelayRejected(format!(
+            "relayer used by an instruction of {}",
+            instruction.program_id
+        )))
+    }
+
+    /// Whether `instruction` creates (idempotently or not) the CRT associated token account of
+    /// one of `signers`, with the relayer as funder only
+    fn creates_crt_account(&self, instruction: &Instruction, signers: &[Pubkey]) -> bool {
+        let [funder, account, wallet, mint, _, token_program, ..] = instruction.accounts.as_slice() else {
+            return false;
+        };
+        let crt_program = TokenProgram::Token2022;
+        instruction.program_id == spl_associated_token_account::id()
+            // `Create` is empty or 0, `CreateIdempotent` 1
+            && matches!(instruction.data.as_slice(), [] | [0] | [1])
+            && funder.pubkey == self.relayer
+            && instruction.accounts.iter().skip(1).all(|meta| meta.pubkey != self.relayer)
+            && signers.contains(&wallet.pubkey)
+            && mint.pubkey == CRT_MINT
+            && token_program.pubkey == crt_program.id()
+            && account.pubkey == crt_program.associated_token_address(&wallet.pubkey, &CRT_MINT)
+    }
+
+    fn check_compute_unit_price(&self, instruction: &Instruction) -> Result<()> {
+        let Some(max) = self.max_compute_unit_price else {
+            return Ok(());
+        };
+        if instruction.program_id != compute_budget::id() {
+            return Ok(());
+        }
+        let price = match instruction.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_PRICE_TAG, rest)) => rest
+                .get(..8)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u64::from_le_bytes)
+                .unwrap_or_default(),
+            _ => return Ok(()),
+        };
+        if price > max {
+            return Err(CarrotError::RelayRejected(format!(
+                "compute-unit price {} exceeds {}",
+                price, max
+            )));
+        }
+        Ok(())
+    }
+
+    /// The fee `instruction` pays, if it is a `transfer_checked` into the relayer's token account
+    fn fee_payment(&self, instruction: &Instruction) -> Option<RelayerFee> {
+        let program = TokenProgram::from_id(&instruction.program_id)?;
+        let TokenInstruction::TransferChecked { amount, .. } = TokenInstruction::unpack(&instruction.data).ok()? else {
+            return None;
+        };
+        let [_, mint, destination, ..] = instruction.accounts.as_slice() else {
+            return None;
+        };
+        (destination.pubkey == program.associated_token_address(&self.relayer, &mint.pubkey)).then_some(RelayerFee {
+            mint: mint.pubkey,
+            amount,
+        })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Deposit transaction paid for by `relayer`, signed by `user` only, for users without SOL
+    ///
+    /// It transfers `fee` of `asset_mint` to the relayer's associated token account (which must
+    /// exist) and lets the relayer fund the user's CRT account. Send the bincode-serialized
+    /// transaction to the relayer, which signs and broadcasts it (`sponsor_transaction`).
+    pub fn build_gasless_deposit_transaction(
+        &self,
+        user: &dyn Signer,
+        relayer: &Pubkey,
+        asset_mint: &Pubkey,
+        amount: u64,
+        fee: u64,
+    ) -> Result<PreparedTransaction> {
+        let user_pubkey = user.pubkey();
+        let program = self.get_token_program(asset_mint)?;
+        let decimals = self.resolve_mint_metadata(asset_mint)?.decimals;
+        let source = program.associated_token_address(&user_pubkey, asset_mint);
+        let destination = program.associated_token_address(relayer, asset_mint);
+        let mut fee_transfer =
+            program.transfer_checked(&source, asset_mint, &destination, &user_pubkey, fee, decimals)?;
+        if program == TokenProgram::Token2022 {
+            fee_transfer.accounts.extend(self.transfer_hook_accounts(&HookedTransfer {
+                source,
+                mint: *asset_mint,
+                destination,
+                authority: user_pubkey,
+                amount: Some(fee),
+            })?);
+        }
+
+        let mut instructions = vec![fee_transfer];
+        instructions.extend(self.sponsored_deposit_instructions(&user_pubkey, relayer, asset_mint, amount)?);
+        self.build_transaction_with_payer(&instructions, relayer, &[user])
+    }
+
+    /// Relayer side of a gasless transaction: check `bytes` against `policy`, simulate, sign as
+    /// fee payer with `relayer` and broadcast
+    ///
+    /// Runs the program allowlist check like `send_raw_transaction`; nothing is signed unless
+    /// the simulation succeeds, so a failing transaction can't cost the relayer its fee.
+    pub fn sponsor_transaction(&self, relayer: &dyn Signer, policy: &RelayerPolicy, bytes: &[u8]) -> Result<Signature> {
+        self.check_approval_not_required()?;
+        if relayer.pubkey() != *policy.relayer() {
+            return Err(CarrotError::InvalidConfig(format!(
+                "relayer key {} doesn't match the policy's {}",
+                relayer.pubkey(),
+                policy.relayer()
+            )));
+        }
+        let mut transaction: Transaction = bincode::deserialize(bytes)
+            .map_err(|e| CarrotError::TransactionFailed(format!("invalid transaction bytes: {}", e)))?;
+        transaction
+            .sanitize()
+            .map_err(|e| CarrotError::RelayRejected(format!("malformed transaction: {}", e)))?;
+        if self.config().verify_programs {
+            self.program_allowlist().verify_transaction(&transaction)?;
+        }
+        let fee = policy.verify(&transaction)?;
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            commitment: Some(self.rpc_client().commitment()),
+            ..Default::default()
+        };
+        let simulation = self.rpc_client().simulate_transaction_with_config(&transaction, config)?.value;
+        if let Some(err) = simulation.err {
+            let logs = simulation.logs.unwrap_or_default().join("\n");
+            return Err(CarrotError::SimulationFailed(format!("{}\n{}", err, logs)));
+        }
+
+        log::info!("sponsoring transaction paying {} of {}", fee.amount, fee.mint);
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction
+            .try_partial_sign(&[relayer], recent_blockhash)
+            .map_err(|e| CarrotError::TransactionFailed(format!("failed to sign: {}", e)))?;
+        self.send_signed(&transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_compute_budget_interface::ComputeBudgetInstruction;
+    use solana_sdk::{
+        hash::Hash,
+        message::Message,
+        signature::{Keypair, Signature},
+        signer::Signer,
+    };
+
+    use crate::{CRT_MINT, USDC_MINT};
+
+    fn gasless(user: &Keypair, relayer: &Pubkey, fee: u64, extra: Vec<Instruction>) -> Transaction {
+        let program = TokenProgram::Token;
+        let fee_transfer = program
+            .transfer_checked(
+                &program.associated_token_address(&user.pubkey(), &USDC_MINT),
+                &USDC_MINT,
+                &program.associated_token_address(relayer, &USDC_MINT),
+                &user.pubkey(),
+                fee,
+                6,
+            )
+            .unwrap();
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_price(1_000), fee_transfer];
+        instructions.extend(extra);
+        let mut transaction = Transaction::new_unsigned(Message::new(&instructions, Some(relayer)));
+        transaction.partial_sign(&[user], Hash::new_unique());
+        transaction
+    }
+
+    #[test]
+    fn test_accepts_fee_paying_transaction() {
+        let user = Keypair::new();
+        let relayer = Pubkey::new_unique();
+        let create_crt_account =
+            TokenProgram::Token2022.create_associated_token_account(&relayer, &user.pubkey(), &CRT_MINT);
+        let transaction = gasless(&user, &relayer, 50_000, vec![create_crt_account]);
+
+        let policy = RelayerPolicy::new(relayer).with_fee(USDC_MINT, 10_000);
+        // Funding the CRT account has to be allowed explicitly
+        assert!(matches!(policy.verify(&transaction), Err(CarrotError::RelayRejected(_))));
+
+        let policy = policy.with_account_creation(true);
+        assert_eq!(
+            policy.verify(&transaction).unwrap(),
+            RelayerFee {
+                mint: USDC_MINT,
+                amount: 50_000
+            }
+        );
+        assert!(policy.clone().with_fee(USDC_MINT, 60_000).verify(&transaction).is_err());
+        assert!(policy.clone().with_max_compute_unit_price(500).verify(&transaction).is_err());
+    }
+
+    #[test]
+    fn test_rejects_transactions_abusing_the_relayer() {
+        let user = Keypair::new();
+        let relayer = Pubkey::new_unique();
+        let policy = RelayerPolicy::new(relayer)
+            .with_fee(USDC_MINT, 10_000)
+            .with_account_creation(true);
+
+        // Relayer isn't the fee payer
+        let transaction = gasless(&user, &relayer, 50_000, vec![]);
+        assert!(RelayerPolicy::new(Pubkey::new_unique())
+            .with_fee(USDC_MINT, 0)
+            .verify(&transaction)
+            .is_err());
+
+        // A second instruction spending the relayer's tokens
+        let program = TokenProgram::Token;
+        let drain = program
+            .transfer_checked(
+                &program.associated_token_address(&relayer, &USDC_MINT),
+                &USDC_MINT,
+                &program.associated_token_address(&user.pubkey(), &USDC_MINT),
+                &relayer,
+                1_000_000,
+                6,
+            )
+            .unwrap();
+        assert!(policy.verify(&gasless(&user, &relayer, 50_000, vec![drain])).is_err());
+
+        // Unsigned by the user
+        let mut unsigned = gasless(&user, &relayer, 50_000, vec![]);
+        unsigned.signatures[1] = Signature::default();
+        assert!(policy.verify(&unsigned).is_err());
+
+        // Out-of-bounds account indices are rejected, not indexed
+        let mut malformed = gasless(&user, &relayer, 50_000, vec![]);
+        malformed.message.instructions[1].accounts[0] = 200;
+        assert!(matches!(policy.verify(&malformed), Err(CarrotError::RelayRejected(_))));
+        let mut malformed = gasless(&user, &relayer, 50_000, vec![]);
+        malformed.message.instructions[0].program_id_index = 200;
+        assert!(matches!(policy.verify(&malformed), Err(CarrotError::RelayRejected(_))));
+    }
+
+    #[test]
+    fn test_funds_only_the_users_crt_account() {
+        let user = Keypair::new();
+        let relayer = Pubkey::new_unique();
+        let policy = RelayerPolicy::new(relayer)
+            .with_fee(USDC_MINT, 10_000)
+            .with_account_creation(true);
+        let create = |owner: &Pubkey, mint: &Pubkey| {
+            TokenProgram::Token2022.create_associated_token_account(&relayer, owner, mint)
+        };
+
+        // Rent drain: the relayer funding accounts the user can close and reclaim
+        let twice = vec![create(&user.pubkey(), &CRT_MINT), create(&user.pubkey(), &CRT_MINT)];
+        assert!(policy.verify(&gasless(&user, &relayer, 50_000, twice)).is_err());
+        let other_mint = vec![create(&user.pubkey(), &USDC_MINT)];
+        assert!(policy.verify(&gasless(&user, &relayer, 50_000, other_mint)).is_err());
+        let other_owner = vec![create(&Pubkey::new_unique(), &CRT_MINT)];
+        assert!(policy.verify(&gasless(&user, &relayer, 50_000, other_owner)).is_err());
+
+        let mut wrong_address = create(&user.pubkey(), &CRT_MINT);
+        wrong_address.accounts[1].pubkey = Pubkey::new_unique();
+        assert!(policy.verify(&gasless(&user, &relayer, 50_000, vec![wrong_address])).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use solana_client::client_error::ClientError;
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+};
+use solana_transaction_error::TransactionError;
+
+use crate::{
+    client::CarrotClient,
+    error::{CarrotError, Result},
+    events::ClientEvent,
+    prepared::PreparedTransaction,
+};
+
+/// How long to wait between status checks while a pinned transaction may still land
+const PINNED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Retry strategy that never has two versions of a transaction live at once
+///
+/// A transaction that wasn't confirmed is resent as-is, same blockhash and signature, for as
+/// long as it could still land. Only once the finalized block height is past its last valid
+/// block height, and the signature is still unknown, is it rebuilt and re-signed with a fresh
+/// blockhash. Without a durable nonce that is the only point where a new signature can't lead
+/// to a double deposit or withdrawal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedRetry {
+    /// Rebuilds with a fresh blockhash after an expiry (0 sends one transaction only)
+    pub max_resigns: usize,
+}
+
+impl Default for PinnedRetry {
+    fn default() -> Self {
+        Self { max_resigns: 3 }
+    }
+}
+
+/// Where a transaction that wasn't confirmed stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PinnedState {
+    Landed,
+    Failed(TransactionError),
+    /// Could still land: only the same transaction may be sent
+    Live,
+    /// Provably never landing: safe to re-sign
+    Expired,
+}
+
+/// State of a transaction valid through `last_valid_block_height`, from the finalized block
+/// height read *before* its signature status
+fn pinned_state(
+    finalized_block_height: u64,
+    last_valid_block_height: u64,
+    status: Option<std::result::Result<(), TransactionError>>,
+) -> PinnedState {
+    match status {
+        Some(Ok(())) => PinnedState::Landed,
+        Some(Err(err)) => PinnedState::Failed(err),
+        // Every block that could include it is finalized, and none does
+        None if finalized_block_height > last_valid_block_height => PinnedState::Expired,
+        None => PinnedState::Live,
+    }
+}
+
+/// Failed status polls of a pinned transaction, retried until they have been failing for `limit`
+#[derive(Debug)]
+struct PollErrors {
+    limit: Duration,
+    failing_since: Option<Instant>,
+}
+
+impl PollErrors {
+    fn new(limit: Duration) -> Self {
+        Self {
+            limit,
+            failing_since: None,
+        }
+    }
+
+    /// The polled value, `None` to poll again after a transient error, or the error once polls
+    /// have kept failing for `limit`
+    fn check<T>(&mut self, polled: std::result::Result<T, ClientError>) -> Result<Option<T>> {
+        match polled {
+            Ok(value) => {
+                self.failing_since = None;
+                Ok(Some(value))
+            }
+            Err(err) => {
+                let failing_since = *self.failing_since.get_or_insert_with(Instant::now);
+                if failing_since.elapsed() >= self.limit {
+                    return Err(err.into());
+                }
+                log::debug!("status poll failed, retrying: {}", err);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl CarrotClient {
+    /// Send the transaction `build` produces, retrying per `retry` (see `PinnedRetry`)
+    ///
+    /// `build` is called again only after the previous transaction provably expired, so it
+    /// should rebuild from current state (e.g. `build_deposit_transaction`). Safe to run from
+    /// several threads at once: each call pins its own transaction.
+    pub fn send_with_retry<F>(&self, mut build: F, retry: &PinnedRetry) -> Result<Signature>
+    where
+        F: FnMut() -> Result<PreparedTransaction>,
+    {
+        self.check_approval_not_required()?;
+        let mut attempt = 0;
+        loop {
+            let prepared = build()?;
+            match self.send_pinned(&prepared)? {
+                Some(signature) => return Ok(signature),
+                None if attempt < retry.max_resigns => {
+                    attempt += 1;
+                    self.emit(ClientEvent::RetryScheduled {
+                        operation: "send_with_retry",
+                        attempt,
+                        delay: Duration::ZERO,
+                    });
+                }
+                None => return Err(CarrotError::TransactionExpired(prepared.signature().to_string())),
+            }
+        }
+    }
+
+    /// Deposit, re-signing only after a provable expiry (see `PinnedRetry`)
+    pub fn deposit_with_retry(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        retry: &PinnedRetry,
+    ) -> Result<Signature> {
+        self.send_with_retry(|| self.build_deposit_transaction(user, asset_mint, amount), retry)
+    }
+
+    /// Withdraw, re-signing only after a provable expiry (see `PinnedRetry`)
+    pub fn withdraw_with_retry(
+        &self,
+        user: &dyn Signer,
+        asset_mint: &Pubkey,
+        amount: u64,
+        retry: &PinnedRetry,
+    ) -> Result<Signature> {
+        self.send_with_retry(|| self.build_withdraw_transaction(user, asset_mint, amount), retry)
+    }
+
+    /// Send `prepared` until it lands (`Some`) or provably expires (`None`)
+    ///
+    /// Status polls failing with RPC errors are retried for up to `finalization_timeout`. An
+    /// expired or failed transaction no longer counts toward policy usage, so the rebuild that
+    /// replaces it is only charged once.
+    fn send_pinned(&self, prepared: &PreparedTransaction) -> Result<Option<Signature>> {
+        let signature = prepared.signature();
+        loop {
+            let err = match self.send_signed(&prepared.transaction) {
+                Ok(signature) => return Ok(Some(signature)),
+                Err(err) if err.is_unconfirmed() => err,
+                Err(err) => return Err(err),
+            };
+            log::debug!("{} unconfirmed, checking before resending: {}", signature, err);
+
+            let mut poll_errors = PollErrors::new(self.config().finalization_timeout);
+            loop {
+                // Height first: a status read after it can't miss a landing the height rules out
+                let polled = self
+                    .rpc_client()
+                    .get_block_height_with_commitment(CommitmentConfig::finalized())
+                    .and_then(|finalized_height| {
+                        let status = self.rpc_client().get_signature_status_with_commitment_and_history(
+                            &signature,
+                            CommitmentConfig::processed(),
+                            true,
+                        )?;
+                        Ok(pinned_state(finalized_height, prepared.last_valid_block_height, status))
+                    });
+                match poll_errors.check(polled)? {
+                    Some(PinnedState::Landed) => return Ok(Some(signature)),
+                    Some(PinnedState::Failed(err)) => {
+                        self.refund_policy(&signature);
+                        return Err(CarrotError::TransactionFailed(err.to_string()));
+                    }
+                    Some(PinnedState::Expired) => {
+                        self.refund_policy(&signature);
+                        return Ok(None);
+                    }
+                    Some(PinnedState::Live) | None => {}
+                }
+                sleep(PINNED_POLL_INTERVAL);
+                // Resend while the blockhash is valid; past that, only wait for finalization
+                let recent_blockhash = &prepared.transaction.message.recent_blockhash;
+                let blockhash_valid = self
+                    .rpc_client()
+                    .is_blockhash_valid(recent_blockhash, CommitmentConfig::processed());
+                if poll_errors.check(blockhash_valid)? == Some(true) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resigns_only_after_finalized_expiry() {
+        assert_eq!(pinned_state(100, 150, None), PinnedState::Live);
+        // Expired at the tip, but a block up to 150 could still be reorged in
+        assert_eq!(pinned_state(150, 150, None), PinnedState::Live);
+        assert_eq!(pinned_state(151, 150, None), PinnedState::Expired);
+        // A landing always wins over the height
+        assert_eq!(pinned_state(151, 150, Some(Ok(()))), PinnedState::Landed);
+        assert_eq!(
+            pinned_state(151, 150, Some(Err(TransactionError::InsufficientFundsForFee))),
+            PinnedState::Failed(TransactionError::InsufficientFundsForFee)
+        );
+
+        assert!(CarrotError::ConfirmationTimeout("sig".to_string()).is_unconfirmed());
+        assert!(!CarrotError::TransactionFailed("custom program error".to_string()).is_unconfirmed());
+    }
+
+    #[test]
+    fn test_poll_errors_are_retried_within_limit() {
+        let transient = || Err::<u64, _>(ClientError::from(std::io::Error::other("connection reset")));
+        let mut patient = PollErrors::new(Duration::from_secs(60));
+        assert_eq!(patient.check(transient()).unwrap(), None);
+        assert_eq!(patient.check(Ok(7)).unwrap(), Some(7));
+        assert_eq!(patient.failing_since, None);
+
+        let mut impatient = PollErrors::new(Duration::ZERO);
+        assert!(matches!(impatient.check(transient()), Err(CarrotError::SolanaClient(_))));
+    }
+}
@@ -0,0 +1,301 @@
+use litesvm::{types::TransactionMetadata, LiteSVM};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::Instruction,
+    message::Message,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
+use solana_sdk_ids::{bpf_loader, bpf_loader_upgradeable};
+
+use crate::{
+    accounts::get_user_crt_ata,
+    client::{accounts_at_slot_config, CarrotClient},
+    error::{CarrotError, Result},
+    instructions::{build_issue_instruction, build_redeem_instruction},
+    token::{unpack_token_amount, TokenProgram},
+    Vault, VaultHandle, CARROT_PROGRAM_ID, LOG_PROGRAM_ID, VAULT_ADDRESS,
+};
+
+/// Length of the `ProgramData` header preceding the ELF in an upgradeable program's data account
+const PROGRAM_DATA_HEADER_LEN: usize = 45;
+
+/// Most accounts a `getMultipleAccounts` request may ask for
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// SOL given to forked users without an account, so they can pay fees and rent
+const SANDBOX_USER_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+/// Mainnet state forked into a local LiteSVM instance, for "what would this do right now"
+/// deposits and withdrawals that cost nothing
+///
+/// Forking copies the Carrot and log programs, the vault, CRT and asset mints, the vault's
+/// token accounts and oracles, and the given users' wallets and token accounts. Signatures
+/// aren't verified, so any user's operations can be executed. Each executed transaction
+/// updates the forked state, so sequences of operations compound.
+pub struct Sandbox {
+    svm: LiteSVM,
+    vault: VaultHandle,
+    slot: u64,
+}
+
+/// Result of an operation executed in a `Sandbox`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxOutcome {
+    /// Change of the user's CRT balance
+    pub crt_delta: i128,
+    /// Change of the user's asset balance
+    pub asset_delta: i128,
+    /// Compute units consumed
+    pub compute_units: u64,
+    /// Program logs
+    pub logs: Vec<String>,
+}
+
+impl Sandbox {
+    /// Fork the state needed to deposit and withdraw as any of `users`
+    ///
+    /// Accounts are read at the slot the endpoint is at (plain RPC can't serve historical
+    /// account state), in requests of up to 100 accounts. Each request after the first is
+    /// read at or after the slot of the one before, so later reads never see older state, but
+    /// a fork of many users may span a few slots. The clock is set to the newest slot read and
+    /// the current time.
+    pub fn fork(client: &CarrotClient, users: &[Pubkey]) -> Result<Self> {
+        Self::fork_at_least(client, users, 0)
+    }
+
+    /// `fork` with every account read at or after `min_context_slot`, e.g. the slot of a
+    /// transaction whose effects the fork must include
+    ///
+    /// Fails with the endpoint's error if it hasn't reached `min_context_slot` yet.
+    pub fn fork_at_least(client: &CarrotClient, users: &[Pubkey], min_context_slot: u64) -> Result<Self> {
+        let vault = client.fetch_vault()?;
+        let mut addresses = vec![VAULT_ADDRESS, vault.shares];
+        for asset in &vault.assets {
+            addresses.extend([asset.mint, asset.ata, asset.oracle]);
+        }
+        for user in users {
+            addresses.extend([*user, get_user_crt_ata(user)]);
+            addresses.extend(vault.assets.iter().map(|asset| {
+                asset
+                    .token_program(&VAULT_ADDRESS)
+                    .associated_token_address(user, &asset.mint)
+            }));
+        }
+
+        let mut slot = min_context_slot;
+        let mut fetched = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let config = accounts_at_slot_config(client.rpc_client().commitment(), slot);
+            let response = client.rpc_client().get_multiple_accounts_with_config(chunk, config)?;
+            slot = slot.max(response.context.slot);
+            fetched.extend(response.value);
+        }
+
+        let mut svm = LiteSVM::new()
+            .with_sigverify(false)
+            .with_blockhash_check(false)
+            // Unsigned transactions all carry the default signature; don't reject them as duplicates
+            .with_transaction_history(0);
+        svm.warp_to_slot(slot);
+        let mut clock: Clock = svm.get_sysvar();
+        clock.unix_timestamp = client.now();
+        svm.set_sysvar(&clock);
+
+        for program_id in [CARROT_PROGRAM_ID, LOG_PROGRAM_ID] {
+            let elf = fetch_program_elf(client, &program_id)?;
+            svm.add_program(program_id, &elf).map_err(sandbox_error)?;
+        }
+
+        // The vault is taken from the forked accounts, not the first fetch
+        let mut forked_vault = vault;
+        for (address, account) in addresses.iter().zip(fetched) {
+            match account {
+                Some(account) => {
+                    if address == &VAULT_ADDRESS {
+                        forked_vault = Vault::from_account_data(&account.data)?;
+                    }
+                    svm.set_account(*address, account).map_err(sandbox_error)?;
+                }
+                None if users.contains(address) => {
+                    svm.airdrop(address, SANDBOX_USER_LAMPORTS)
+                        .map_err(|e| CarrotError::SimulationFailed(e.err.to_string()))?;
+                }
+                None => {}
+            }
+        }
+
+        Ok(Self {
+            svm,
+            vault: VaultHandle::new(VAULT_ADDRESS, forked_vault),
+            slot,
+        })
+    }
+
+    /// Slot the state was forked at
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    /// The underlying LiteSVM instance, e.g. to inspect or override accounts
+    pub fn svm_mut(&mut self) -> &mut LiteSVM {
+        &mut self.svm
+    }
+
+    /// Deposit `amount` of `asset_mint` as `user`
+    pub fn deposit(&mut self, user: &Pubkey, asset_mint: &Pubkey, amount: u64) -> Result<SandboxOutcome> {
+        let instructions = vec![
+            TokenProgram::Token2022.create_associated_token_account(user, user, &self.vault.vault.shares),
+            build_issue_instruction(&self.vault, user, asset_mint, amount)?,
+        ];
+        self.execute_with_deltas(user, asset_mint, &instructions)
+    }
+
+    /// Redeem `shares` CRT for `asset_mint` as `user`
+    pub fn withdraw(&mut self, user: &Pubkey, asset_mint: &Pubkey, shares: u64) -> Result<SandboxOutcome> {
+        let instructions = vec![
+            self.vault
+                .asset_token_program(asset_mint)?
+                .create_associated_token_account(user, user, asset_mint),
+            build_redeem_instruction(&self.vault, user, asset_mint, shares)?,
+        ];
+        self.execute_with_deltas(user, asset_mint, &instructions)
+    }
+
+    /// Execute arbitrary instructions with `payer` as fee payer, updating the forked state
+    ///
+    /// Fails with `SimulationFailed` (including the program logs) if the transaction fails.
+    pub fn execute(&mut self, payer: &Pubkey, instructions: &[Instruction]) -> Result<TransactionMetadata> {
+        let message = Message::new_with_blockhash(instructions, Some(payer), &self.svm.latest_blockhash());
+        self.svm
+            .send_transaction(Transaction::new_unsigned(message))
+            .map_err(|failed| {
+                CarrotError::SimulationFailed(format!("{}\n{}", failed.err, failed.meta.logs.join("\n")))
+            })
+    }
+
+    fn execute_with_deltas(
+        &mut self,
+        user: &Pubkey,
+        asset_mint: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<SandboxOutcome> {
+        let crt_ata = get_user_crt_ata(user);
+        let asset_ata = self
+            .vault
+            .asset_token_program(asset_mint)?
+            .associated_token_address(user, asset_mint);
+        let (crt_before, asset_before) = (self.token_amount(&crt_ata)?, self.token_amount(&asset_ata)?);
+
+        let meta = self.execute(user, instructions)?;
+        Ok(SandboxOutcome {
+            crt_delta: self.token_amount(&crt_ata)? as i128 - crt_before as i128,
+            asset_delta: self.token_amount(&asset_ata)? as i128 - asset_before as i128,
+            compute_units: meta.compute_units_consumed,
+            logs: meta.logs,
+        })
+    }
+
+    /// Amount held by a forked token account, 0 if it doesn't exist
+    fn token_amount(&self, address: &Pubkey) -> Result<u64> {
+        match self.svm.get_account(address) {
+            Some(account) if !account.data.is_empty() => unpack_token_amount(&account.data),
+            _ => Ok(0),
+        }
+    }
+}
+
+/// Executable bytes of a deployed program (upgradeable or not)
+fn fetch_program_elf(client: &CarrotClient, program_id: &Pubkey) -> Result<Vec<u8>> {
+    let program = client.rpc_client().get_account(program_id)?;
+    if program.owner == bpf_loader::id() {
+        return Ok(program.data);
+    }
+    if program.owner != bpf_loader_upgradeable::id() {
+        return Err(CarrotError::SimulationFailed(format!(
+            "{} is owned by unsupported loader {}",
+            program_id, program.owner
+        )));
+    }
+    // UpgradeableLoaderState::Program: u32 variant tag followed by the program data address
+    let program_data = program
+        .data
+        .get(4..36)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(Pubkey::new_from_array)
+        .ok_or_else(|| CarrotError::SimulationFailed(format!("{} has no program data account", program_id)))?;
+    let Account { data, .. } = client.rpc_client().get_account(&program_data)?;
+    data.get(PROGRAM_DATA_HEADER_LEN..)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| CarrotError::SimulationFailed(format!("{} program data is truncated", program_id)))
+}
+
+pub(crate) fn sandbox_error(err: litesvm::error::LiteSVMError) -> CarrotError {
+    CarrotError::SimulationFailed(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fee, CRT_MINT};
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk_ids::system_program;
+
+    /// System program `Transfer` (instruction index 2)
+    fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend(lamports.to_le_bytes());
+        Instruction::new_with_bytes(
+            system_program::id(),
+            &data,
+            vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        )
+    }
+
+    fn empty_sandbox() -> Sandbox {
+        let vault = Vault {
+            authority: Pubkey::new_unique(),
+            shares: CRT_MINT,
+            fee: Fee::default(),
+            paused: false,
+            asset_index: 0,
+            strategy_index: 0,
+            assets: vec![],
+            strategies: vec![],
+        };
+        Sandbox {
+            svm: LiteSVM::new().with_sigverify(false).with_transaction_history(0),
+            vault: VaultHandle::new(VAULT_ADDRESS, vault),
+            slot: 0,
+        }
+    }
+
+    #[test]
+    fn test_execute_updates_state_without_signatures() {
+        let mut sandbox = empty_sandbox();
+        let (user, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+        sandbox.svm_mut().airdrop(&user, LAMPORTS_PER_SOL).unwrap();
+
+        let transfer = transfer(&user, &recipient, 1_000_000);
+        sandbox.execute(&user, std::slice::from_ref(&transfer)).unwrap();
+        // Identical unsigned transactions aren't rejected as duplicates
+        sandbox.execute(&user, &[transfer]).unwrap();
+        assert_eq!(sandbox.svm.get_account(&recipient).unwrap().lamports, 2_000_000);
+    }
+
+    #[test]
+    fn test_failed_execution_is_simulation_error() {
+        let mut sandbox = empty_sandbox();
+        let user = Pubkey::new_unique();
+        sandbox.svm_mut().airdrop(&user, LAMPORTS_PER_SOL).unwrap();
+
+        let overdraw = transfer(&user, &Pubkey::new_unique(), 2 * LAMPORTS_PER_SOL);
+        assert!(matches!(
+            sandbox.execute(&user, &[overdraw]),
+            Err(CarrotError::SimulationFailed(_))
+        ));
+        assert_eq!(sandbox.token_amount(&Pubkey::new_unique()).unwrap(), 0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Stable serialized forms of the data the SDK exports
+//!
+//! Pipelines (Kafka, BigQuery, ...) should consume these instead of the Rust types, which may
+//! change between releases. Addresses and signatures are base58 strings, `u64` amounts are
+//! numbers and every record carries `schema_version`. Fields are only ever added; renaming
+//! or removing one bumps `SCHEMA_VERSION`. With the `protobuf` feature the same records are
+//! available as protobuf messages (`proto/carrot/v1/events.proto`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{CarrotError, Result},
+    history::HistoryEntry,
+    instructions::CarrotInstruction,
+    oracle::decode_oracle_account,
+    snapshot::VaultSnapshot,
+};
+
+/// Version written to every record
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A deposit: `amount` of `asset_mint` issued CRT to `user`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueEvent {
+    pub schema_version: u32,
+    pub signature: String,
+    pub instruction_index: u32,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub user: String,
+    pub asset_mint: String,
+    pub amount: u64,
+}
+
+/// A withdrawal: `shares` CRT redeemed by `user` for `asset_mint`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedeemEvent {
+    pub schema_version: u32,
+    pub signature: String,
+    pub instruction_index: u32,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub user: String,
+    pub asset_mint: String,
+    pub shares: u64,
+}
+
+/// An exported vault event, tagged with `"type": "issue" | "redeem"` in JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VaultEvent {
+    Issue(IssueEvent),
+    Redeem(RedeemEvent),
+}
+
+/// Per-asset state in a `VaultSnapshotRecord`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetState {
+    pub asset_id: u16,
+    pub mint: String,
+    pub decimals: u8,
+    pub vault_ata: String,
+    pub vault_balance: u64,
+    pub oracle: String,
+    /// Oracle price as `price * 10^price_exponent`, absent if the oracle couldn't be decoded
+    pub price: Option<i64>,
+    pub price_exponent: Option<i32>,
+    pub price_publish_time: Option<i64>,
+}
+
+/// Strategy balance in a `VaultSnapshotRecord`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrategyState {
+    pub strategy_id: u16,
+    pub asset_id: u16,
+    pub balance: u64,
+    pub net_earnings: i64,
+}
+
+/// Vault state at one slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultSnapshotRecord {
+    pub schema_version: u32,
+    pub slot: u64,
+    pub authority: String,
+    pub shares_mint: String,
+    pub shares_supply: u64,
+    pub paused: bool,
+    pub redemption_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub performance_fee_bps: u16,
+    pub assets: Vec<AssetState>,
+    pub strategies: Vec<StrategyState>,
+}
+
+impl From<&HistoryEntry> for VaultEvent {
+    fn from(entry: &HistoryEntry) -> Self {
+        let signature = entry.signature.to_string();
+        let instruction_index = entry.instruction_index as u32;
+        match entry.instruction {
+            CarrotInstruction::Issue { user, asset_mint, amount } => VaultEvent::Issue(IssueEvent {
+                schema_version: SCHEMA_VERSION,
+                signature,
+                instruction_index,
+                slot: entry.slot,
+                block_time: entry.block_time,
+                user: user.to_string(),
+                asset_mint: asset_mint.to_string(),
+                amount,
+            }),
+            CarrotInstruction::Redeem { user, asset_mint, amount } => VaultEvent::Redeem(RedeemEvent {
+                schema_version: SCHEMA_VERSION,
+                signature,
+                instruction_index,
+                slot: entry.slot,
+                block_time: entry.block_time,
+                user: user.to_string(),
+                asset_mint: asset_mint.to_string(),
+                shares: amount,
+            }),
+        }
+    }
+}
+
+impl From<&VaultSnapshot> for VaultSnapshotRecord {
+    fn from(snapshot: &VaultSnapshot) -> Self {
+        let vault = &snapshot.vault;
+        Self {
+            schema_version: SCHEMA_VERSION,
+            slot: snapshot.slot,
+            authority: vault.authority.to_string(),
+            shares_mint: vault.shares.to_string(),
+            shares_supply: snapshot.shares_supply,
+            paused: vault.paused,
+            redemption_fee_bps: vault.fee.redemption_fee_bps,
+            management_fee_bps: vault.fee.management_fee_bps,
+            performance_fee_bps: vault.fee.performance_fee_bps,
+            assets: snapshot
+                .assets
+                .iter()
+                .map(|asset_snapshot| {
+                    let asset = &asset_snapshot.asset;
+                    let price = asset_snapshot
+                        .oracle_account
+                        .as_ref()
+                        .and_then(|account| decode_oracle_account(account).ok());
+                    AssetState {
+                        asset_id: asset.asset_id,
+                        mint: asset.mint.to_string(),
+                        decimals: asset.decimals,
+                        vault_ata: asset.ata.to_string(),
+                        vault_balance: asset_snapshot.vault_balance,
+                        oracle: asset.oracle.to_string(),
+                        price: price.map(|p| p.price),
+                        price_exponent: price.map(|p| p.exponent),
+                        price_publish_time: price.map(|p| p.publish_time),
+                    }
+                })
+                .collect(),
+            strategies: vault
+                .strategies
+                .iter()
+                .map(|strategy| StrategyState {
+                    strategy_id: strategy.strategy_id,
+                    asset_id: strategy.asset_id,
+                    balance: strategy.balance,
+                    net_earnings: strategy.net_earnings,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Serialize a record as a single line of JSON (for NDJSON exports)
+pub fn to_json<T: Serialize>(record: &T) -> Result<String> {
+    serde_json::to_string(record).map_err(|e| CarrotError::InvalidConfig(format!("failed to serialize record: {}", e)))
+}
+
+/// Parse a record produced by `to_json`
+pub fn from_json<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| CarrotError::InvalidConfig(format!("invalid record: {}", e)))
+}
+
+/// Protobuf messages matching `proto/carrot/v1/events.proto`
+#[cfg(feature = "protobuf")]
+pub mod proto {
+    pub use prost::Message;
+
+    /// `carrot.v1.IssueEvent`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct IssueEvent {
+        #[prost(uint32, tag = "1")]
+        pub schema_version: u32,
+        #[prost(string, tag = "2")]
+        pub signature: String,
+        #[prost(uint32, tag = "3")]
+        pub instruction_index: u32,
+        #[prost(uint64, tag = "4")]
+        pub slot: u64,
+        #[prost(int64, optional, tag = "5")]
+        pub block_time: Option<i64>,
+        #[prost(string, tag = "6")]
+        pub user: String,
+        #[prost(string, tag = "7")]
+        pub asset_mint: String,
+        #[prost(uint64, tag = "8")]
+        pub amount: u64,
+    }
+
+    /// `carrot.v1.RedeemEvent`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RedeemEvent {
+        #[prost(uint32, tag = "1")]
+        pub schema_version: u32,
+        #[prost(string, tag = "2")]
+        pub signature: String,
+        #[prost(uint32, tag = "3")]
+        pub instruction_index: u32,
+        #[prost(uint64, tag = "4")]
+        pub slot: u64,
+        #[prost(int64, optional, tag = "5")]
+        pub block_time: Option<i64>,
+        #[prost(string, tag = "6")]
+        pub user: String,
+        #[prost(string, tag = "7")]
+        pub asset_mint: String,
+        #[prost(uint64, tag = "8")]
+        pub shares: u64,
+    }
+
+    /// `carrot.v1.VaultEvent`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VaultEvent {
+        #[prost(oneof = "vault_event::Event", tags = "1, 2")]
+        pub event: Option<vault_event::Event>,
+    }
+
+    pub mod vault_event {
+        /// The `event` oneof of `carrot.v1.VaultEvent`
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Event {
+            #[prost(message, tag = "1")]
+            Issue(super::IssueEvent),
+            #[prost(message, tag = "2")]
+            Redeem(super::RedeemEvent),
+        }
+    }
+
+    /// `carrot.v1.AssetState`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AssetState {
+        #[prost(uint32, tag = "1")]
+        pub asset_id: u32,
+        #[prost(string, tag = "2")]
+        pub mint: String,
+        #[prost(uint32, tag = "3")]
+        pub decimals: u32,
+        #[prost(string, tag = "4")]
+        pub vault_ata: String,
+        #[prost(uint64, tag = "5")]
+        pub vault_balance: u64,
+        #[prost(string, tag = "6")]
+        pub oracle: String,
+        #[prost(int64, optional, tag = "7")]
+        pub price: Option<i64>,
+        #[prost(int32, optional, tag = "8")]
+        pub price_exponent: Option<i32>,
+        #[prost(int64, optional, tag = "9")]
+        pub price_publish_time: Option<i64>,
+    }
+
+    /// `carrot.v1.StrategyState`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StrategyState {
+        #[prost(uint32, tag = "1")]
+        pub strategy_id: u32,
+        #[prost(uint32, tag = "2")]
+        pub asset_id: u32,
+        #[prost(uint64, tag = "3")]
+        pub balance: u64,
+        #[prost(int64, tag = "4")]
+        pub net_earnings: i64,
+    }
+
+    /// `carrot.v1.VaultSnapshot`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VaultSnapshot {
+        #[prost(uint32, tag = "1")]
+        pub schema_version: u32,
+        #[prost(uint64, tag = "2")]
+        pub slot: u64,
+        #[prost(string, tag = "3")]
+        pub authority: String,
+        #[prost(string, tag = "4")]
+        pub shares_mint: String,
+        #[prost(uint64, tag = "5")]
+        pub shares_supply: u64,
+        #[prost(bool, tag = "6")]
+        pub paused: bool,
+        #[prost(uint32, tag = "7")]
+        pub redemption_fee_bps: u32,
+        #[prost(uint32, tag = "8")]
+        pub management_fee_bps: u32,
+        #[prost(uint32, tag = "9")]
+        pub performance_fee_bps: u32,
+        #[prost(message, repeated, tag = "10")]
+        pub assets: Vec<AssetState>,
+        #[prost(message, repeated, tag = "11")]
+        pub strategies: Vec<StrategyState>,
+    }
+
+    impl From<&super::VaultEvent> for VaultEvent {
+        fn from(event: &super::VaultEvent) -> Self {
+            let event = match event {
+                super::VaultEvent::Issue(e) => vault_event::Event::Issue(IssueEvent {
+                    schema_version: e.schema_version,
+                    signature: e.signature.clone(),
+                    instruction_index: e.instruction_index,
+                    slot: e.slot,
+                    block_time: e.block_time,
+                    user: e.user.clone(),
+                    asset_mint: e.asset_mint.clone(),
+                    amount: e.amount,
+                }),
+                super::VaultEvent::Redeem(e) => vault_event::Event::Redeem(RedeemEvent {
+                    schema_version: e.schema_version,
+                    signature: e.signature.clone(),
+                    instruction_index: e.instruction_index,
+                    slot: e.slot,
+                    block_time: e.block_time,
+                    user: e.user.clone(),
+                    asset_mint: e.asset_mint.clone(),
+                    shares: e.shares,
+                }),
+            };
+            Self { event: Some(event) }
+        }
+    }
+
+    impl From<&super::VaultSnapshotRecord> for VaultSnapshot {
+        fn from(record: &super::VaultSnapshotRecord) -> Self {
+            Self {
+                schema_version: record.schema_version,
+                slot: record.slot,
+                authority: record.authority.clone(),
+                shares_mint: record.shares_mint.clone(),
+                shares_supply: record.shares_supply,
+                paused: record.paused,
+                redemption_fee_bps: record.redemption_fee_bps.into(),
+                management_fee_bps: record.management_fee_bps.into(),
+                performance_fee_bps: record.performance_fee_bps.into(),
+                assets: record
+                    .assets
+                    .iter()
+                    .map(|asset| AssetState {
+                        asset_id: asset.asset_id.into(),
+                        mint: asset.mint.clone(),
+                        decimals: asset.decimals.into(),
+                        vault_ata: asset.vault_ata.clone(),
+                        vault_balance: asset.vault_balance,
+                        oracle: asset.oracle.clone(),
+                        price: asset.price,
+                        price_exponent: asset.price_exponent,
+                        price_publish_time: asset.price_publish_time,
+                    })
+                    .collect(),
+                strategies: record
+                    .strategies
+                    .iter()
+                    .map(|strategy| StrategyState {
+                        strategy_id: strategy.strategy_id.into(),
+                        asset_id: strategy.asset_id.into(),
+                        balance: strategy.balance,
+                        net_earnings: strategy.net_earnings,
+                    })
+                    .collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::USDC_MINT;
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+    fn redeem_entry() -> HistoryEntry {
+        HistoryEntry {
+            signature: Signature::default(),
+            instruction_index: 2,
+            slot: 10,
+            transaction_index: None,
+            block_time: Some(1_700_000_000),
+            instruction: CarrotInstruction::Redeem {
+                user: Pubkey::default(),
+                asset_mint: USDC_MINT,
+                amount: 42,
+            },
+        }
+    }
+
+    #[test]
+    fn test_event_json_is_stable() {
+        let event = VaultEvent::from(&redeem_entry());
+        let json = to_json(&event).unwrap();
+        // Field names and order are part of the schema
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"type":"redeem","schema_version":1,"signature":"{}","instruction_index":2,"slot":10,"block_time":1700000000,"user":"{}","asset_mint":"{}","shares":42}}"#,
+                Signature::default(),
+                Pubkey::default(),
+                USDC_MINT
+            )
+        );
+        assert_eq!(from_json::<VaultEvent>(&json).unwrap(), event);
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_event_protobuf_round_trip() {
+        use proto::Message;
+
+        let message = proto::VaultEvent::from(&VaultEvent::from(&redeem_entry()));
+        let decoded = proto::VaultEvent::decode(message.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, message);
+        assert!(matches!(decoded.event, Some(proto::vault_event::Event::Redeem(ref e)) if e.shares == 42));
+    }
+}
@@ -0,0 +1,334 @@
+#[cfg(feature = "blocking")]
+use std::time::Duration;
+
+use crate::{
+    error::Result,
+    history::HistoryEntry,
+    schema::{self, VaultEvent, VaultSnapshotRecord},
+    snapshot::VaultSnapshot,
+    VAULT_ADDRESS,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, history::ActivityCursor, subscription::ActivitySubscription, supervisor::TaskGroup};
+
+/// Default topic (Kafka) or subject (NATS) for vault events
+pub const DEFAULT_EVENTS_TOPIC: &str = "carrot.events";
+
+/// Default topic (Kafka) or subject (NATS) for vault snapshots
+pub const DEFAULT_SNAPSHOTS_TOPIC: &str = "carrot.snapshots";
+
+/// Wire format of published records (see the `schema` module)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// One JSON object per message
+    #[default]
+    Json,
+    /// `carrot.v1` protobuf messages
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+}
+
+/// Transport an `EventSink` hands encoded records to
+///
+/// `key` identifies the record (the event key, or the vault for snapshots); Kafka uses it for
+/// partitioning and compaction, NATS as the JetStream message ID for deduplication.
+pub trait Publisher: Send {
+    fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()>;
+
+    /// Block until everything published so far is acknowledged
+    fn flush(&mut self) -> Result<()>;
+}
+
+/// Publishes decoded vault events and snapshots through a `Publisher`
+pub struct EventSink<P> {
+    publisher: P,
+    encoding: Encoding,
+    events_topic: String,
+    snapshots_topic: String,
+}
+
+impl<P: Publisher> EventSink<P> {
+    /// Sink publishing JSON to the default topics
+    pub fn new(publisher: P) -> Self {
+        Self {
+            publisher,
+            encoding: Encoding::default(),
+            events_topic: DEFAULT_EVENTS_TOPIC.to_string(),
+            snapshots_topic: DEFAULT_SNAPSHOTS_TOPIC.to_string(),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_events_topic(mut self, topic: impl Into<String>) -> Self {
+        self.events_topic = topic.into();
+        self
+    }
+
+    pub fn with_snapshots_topic(mut self, topic: impl Into<String>) -> Self {
+        self.snapshots_topic = topic.into();
+        self
+    }
+
+    /// Publish a deposit or withdrawal, keyed by its `EventKey`
+    pub fn publish_entry(&mut self, entry: &HistoryEntry) -> Result<()> {
+        let event = VaultEvent::from(entry);
+        let payload = match self.encoding {
+            Encoding::Json => schema::to_json(&event)?.into_bytes(),
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => {
+                use schema::proto::Message;
+                schema::proto::VaultEvent::from(&event).encode_to_vec()
+            }
+        };
+        self.publisher.publish(&self.events_topic, &entry.key().to_string(), payload)
+    }
+
+    /// Publish the vault's state, keyed by the vault address
+    pub fn publish_snapshot(&mut self, snapshot: &VaultSnapshot) -> Result<()> {
+        let record = VaultSnapshotRecord::from(snapshot);
+        let payload = match self.encoding {
+            Encoding::Json => schema::to_json(&record)?.into_bytes(),
+            #[cfg(feature = "protobuf")]
+            Encoding::Protobuf => {
+                use schema::proto::Message;
+                schema::proto::VaultSnapshot::from(&record).encode_to_vec()
+            }
+        };
+        self.publisher.publish(&self.snapshots_topic, &VAULT_ADDRESS.to_string(), payload)
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.publisher.flush()
+    }
+
+    pub fn publisher(&self) -> &P {
+        &self.publisher
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Publish every deposit and withdrawal into the vault to `sink`, from a supervised task
+    ///
+    /// Built on `subscribe_vault_activity`: each event is flushed before the cursor moves past
+    /// it, so delivery is at-least-once. Consumers deduplicate with the message key.
+    pub fn publish_vault_activity<P>(
+        &self,
+        group: &mut TaskGroup,
+        mut sink: EventSink<P>,
+        start: Option<ActivityCursor>,
+        poll_interval: Duration,
+    ) -> ActivitySubscription
+    where
+        P: Publisher + 'static,
+    {
+        self.subscribe_vault_activity(group, start, poll_interval, move |entry| {
+            sink.publish_entry(entry)?;
+            sink.flush()
+        })
+    }
+
+    /// Publish a vault snapshot to `sink` every `interval`, from a supervised task
+    pub fn publish_snapshots<P>(&self, group: &mut TaskGroup, mut sink: EventSink<P>, interval: Duration)
+    where
+        P: Publisher + 'static,
+    {
+        let client = self.clone();
+        group.spawn_periodic("snapshot-publisher", interval, move || {
+            sink.publish_snapshot(&client.fetch_snapshot()?)?;
+            sink.flush()
+        });
+    }
+}
+
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaPublisher;
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use rdkafka::{
+        error::{KafkaError, RDKafkaErrorCode},
+        producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext},
+        ClientContext,
+    };
+
+    use super::Publisher;
+    use crate::error::{CarrotError, Result};
+
+    /// How long `flush` waits for the brokers to acknowledge
+    const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Records the first failed delivery until the next `flush` reports it
+    #[derive(Default)]
+    struct DeliveryContext {
+        failure: Arc<Mutex<Option<String>>>,
+    }
+
+    impl ClientContext for DeliveryContext {}
+
+    impl ProducerContext for DeliveryContext {
+        type DeliveryOpaque = ();
+
+        fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+            if let Err((err, _)) = result {
+                let mut failure = self.failure.lock().unwrap_or_else(|e| e.into_inner());
+                failure.get_or_insert_with(|| err.to_string());
+            }
+        }
+    }
+
+    /// Publishes to Kafka topics with librdkafka
+    pub struct KafkaPublisher {
+        producer: BaseProducer<DeliveryContext>,
+        failure: Arc<Mutex<Option<String>>>,
+    }
+
+    impl KafkaPublisher {
+        /// Producer for the comma-separated `brokers`
+        pub fn new(brokers: &str) -> Result<Self> {
+            let mut config = rdkafka::ClientConfig::new();
+            config.set("bootstrap.servers", brokers).set("enable.idempotence", "true");
+            Self::from_config(&config)
+        }
+
+        /// Producer with full librdkafka configuration (security, compression, ...)
+        pub fn from_config(config: &rdkafka::ClientConfig) -> Result<Self> {
+            let context = DeliveryContext::default();
+            let failure = Arc::clone(&context.failure);
+            let producer = config
+                .create_with_context(context)
+                .map_err(|e| CarrotError::InvalidConfig(format!("kafka: {}", e)))?;
+            Ok(Self { producer, failure })
+        }
+    }
+
+    impl Publisher for KafkaPublisher {
+        fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+            let mut record = BaseRecord::to(topic).key(key).payload(&payload);
+            loop {
+                match self.producer.send(record) {
+                    Ok(()) => return Ok(()),
+                    Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                        // Serve delivery reports to make room in the local queue
+                        self.producer.poll(Duration::from_millis(100));
+                        record = returned;
+                    }
+                    Err((err, _)) => return Err(CarrotError::PublishFailed(format!("kafka {}: {}", topic, err))),
+                }
+            }
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.producer
+                .flush(FLUSH_TIMEOUT)
+                .map_err(|e| CarrotError::PublishFailed(format!("kafka: {}", e)))?;
+            let failure = self.failure.lock().unwrap_or_else(|e| e.into_inner()).take();
+            match failure {
+                Some(err) => Err(CarrotError::PublishFailed(format!("kafka delivery: {}", err))),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+pub use nats::NatsPublisher;
+
+#[cfg(feature = "nats")]
+mod nats {
+    use async_nats::{header::NATS_MESSAGE_ID, HeaderMap};
+
+    use super::Publisher;
+    use crate::error::{CarrotError, Result};
+
+    /// Publishes to NATS subjects, driving the async client on a private runtime
+    pub struct NatsPublisher {
+        runtime: tokio::runtime::Runtime,
+        client: async_nats::Client,
+    }
+
+    impl NatsPublisher {
+        /// Connect to the server at `url` (e.g. `nats://localhost:4222`)
+        pub fn connect(url: &str) -> Result<Self> {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_all()
+                .build()
+                .map_err(|e| CarrotError::InvalidConfig(format!("nats runtime: {}", e)))?;
+            let client = runtime
+                .block_on(async_nats::connect(url))
+                .map_err(|e| CarrotError::InvalidConfig(format!("nats {}: {}", url, e)))?;
+            Ok(Self { runtime, client })
+        }
+    }
+
+    impl Publisher for NatsPublisher {
+        fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+            let mut headers = HeaderMap::new();
+            headers.insert(NATS_MESSAGE_ID, key);
+            self.runtime
+                .block_on(self.client.publish_with_headers(topic.to_string(), headers, payload.into()))
+                .map_err(|e| CarrotError::PublishFailed(format!("nats {}: {}", topic, e)))
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.runtime
+                .block_on(self.client.flush())
+                .map_err(|e| CarrotError::PublishFailed(format!("nats: {}", e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instructions::CarrotInstruction, USDC_MINT};
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+    #[derive(Default)]
+    struct Recorder {
+        published: Vec<(String, String, Vec<u8>)>,
+    }
+
+    impl Publisher for Recorder {
+        fn publish(&mut self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+            self.published.push((topic.to_string(), key.to_string(), payload));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_entries_are_published_keyed_by_event() {
+        let entry = HistoryEntry {
+            signature: Signature::new_unique(),
+            instruction_index: 1,
+            slot: 7,
+            transaction_index: None,
+            block_time: None,
+            instruction: CarrotInstruction::Issue {
+                user: Pubkey::new_unique(),
+                asset_mint: USDC_MINT,
+                amount: 5,
+            },
+        };
+        let mut sink = EventSink::new(Recorder::default()).with_events_topic("vault-events");
+        sink.publish_entry(&entry).unwrap();
+
+        let (topic, key, payload) = &sink.publisher().published[0];
+        assert_eq!(topic, "vault-events");
+        assert_eq!(key, &entry.key().to_string());
+        let event: VaultEvent = schema::from_json(std::str::from_utf8(payload).unwrap()).unwrap();
+        assert_eq!(event, VaultEvent::from(&entry));
+    }
+}
@@ -0,0 +1,209 @@
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::{
+    error::{CarrotError, Result},
+    oracle::OracleProvider,
+    token::{unpack_mint_supply, unpack_token_amount},
+    Asset, Vault, CRT_MINT, VAULT_ADDRESS,
+};
+
+/// Vault state read at a single slot: the vault account, CRT supply,
+/// vault-side asset balances and raw oracle accounts
+#[derive(Debug, Clone)]
+pub struct VaultSnapshot {
+    /// Slot all accounts were read at
+    pub slot: u64,
+    /// Decoded vault account
+    pub vault: Vault,
+    /// Total CRT supply
+    pub shares_supply: u64,
+    /// Per-asset state, in vault asset order
+    pub assets: Vec<AssetSnapshot>,
+}
+
+/// Per-asset state within a `VaultSnapshot`
+#[derive(Debug, Clone)]
+pub struct AssetSnapshot {
+    /// Asset configuration from the vault
+    pub asset: Asset,
+    /// Idle balance held in the vault's token account for this asset
+    pub vault_balance: u64,
+    /// Raw oracle account (owner and data), if it exists
+    pub oracle_account: Option<Account>,
+}
+
+impl AssetSnapshot {
+    /// Provider of the asset's oracle, detected from the account owner
+    pub fn oracle_provider(&self) -> Option<OracleProvider> {
+        self.oracle_account
+            .as_ref()
+            .map(|account| OracleProvider::from_owner(&account.owner))
+    }
+}
+
+impl VaultSnapshot {
+    /// Find the snapshot of the asset with the given mint
+    pub fn asset(&self, mint: &Pubkey) -> Option<&AssetSnapshot> {
+        self.assets.iter().find(|a| &a.asset.mint == mint)
+    }
+}
+
+/// Accounts read in a single batch to build a snapshot of `vault`:
+/// the vault itself, the CRT mint, then each asset's vault ATA and oracle
+pub(crate) fn snapshot_accounts(vault: &Vault) -> Vec<Pubkey> {
+    let mut accounts = vec![VAULT_ADDRESS, CRT_MINT];
+    for asset in &vault.assets {
+        accounts.push(asset.ata);
+        accounts.push(asset.oracle);
+    }
+    accounts
+}
+
+/// Assemble a snapshot from the batch returned for `snapshot_accounts(expected)`
+/// Returns `None` if the vault's asset list changed between reads, in which case
+/// the batch doesn't contain the right ATAs/oracles and must be re-fetched
+pub(crate) fn build_snapshot(
+    slot: u64,
+    expected: &Vault,
+    accounts: Vec<Option<Account>>,
+) -> Result<Option<VaultSnapshot>> {
+    let mut accounts = accounts.into_iter();
+    let mut next = || accounts.next().flatten();
+
+    let vault_account = next()
+        .ok_or_else(|| CarrotError::AccountNotFound("Vault account not found".to_string()))?;
+    let vault = Vault::from_account_data(&vault_account.data)?;
+    let same_assets = vault.assets.len() == expected.assets.len()
+        && vault
+            .assets
+            .iter()
+            .zip(&expected.assets)
+            .all(|(a, b)| a.ata == b.ata && a.oracle == b.oracle);
+    if !same_assets {
+        return Ok(None);
+    }
+
+    let mint_account =
+        next().ok_or_else(|| CarrotError::AccountNotFound("CRT mint not found".to_string()))?;
+    let shares_supply = unpack_mint_supply(&mint_account.data)?;
+
+    let mut assets = Vec::with_capacity(vault.assets.len());
+    for asset in &vault.assets {
+        let vault_balance = match next() {
+            Some(account) => unpack_token_amount(&account.data)?,
+            None => 0,
+        };
+        let oracle_account = next();
+        assets.push(AssetSnapshot {
+            asset: asset.clone(),
+            vault_balance,
+            oracle_account,
+        });
+    }
+
+    Ok(Some(VaultSnapshot {
+        slot,
+        vault,
+        shares_supply,
+        assets,
+    }))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_utils::test_vault;
+    use solana_sdk::program_pack::Pack;
+    use spl_token_2022_interface::state::{Account as TokenAccount, AccountState, Mint};
+
+    fn test_asset(asset_id: u16) -> Asset {
+        Asset {
+            asset_id,
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            ata: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+        }
+    }
+
+    fn account(data: Vec<u8>) -> Option<Account> {
+        Some(Account {
+            lamports: 1,
+            data,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+
+    pub(crate) fn mint_data(supply: u64) -> Vec<u8> {
+        let mint = Mint {
+            supply,
+            decimals: 9,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+        data
+    }
+
+    pub(crate) fn token_account_data(amount: u64) -> Vec<u8> {
+        let token_account = TokenAccount {
+            mint: Pubkey::new_unique(),
+            owner: VAULT_ADDRESS,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_build_snapshot() {
+        let vault = test_vault(vec![test_asset(0), test_asset(1)]);
+        let accounts = vec![
+            account(vault.to_account_data()),
+            account(mint_data(5_000)),
+            account(token_account_data(100)),
+            account(vec![1, 2, 3]),
+            None, // vault ATA not created yet
+            None,
+        ];
+        assert_eq!(snapshot_accounts(&vault).len(), accounts.len());
+
+        let snapshot = build_snapshot(42, &vault, accounts).unwrap().unwrap();
+        assert_eq!(snapshot.slot, 42);
+        assert_eq!(snapshot.shares_supply, 5_000);
+        assert_eq!(snapshot.assets[0].vault_balance, 100);
+        assert!(snapshot.assets[0].oracle_account.is_some());
+        assert_eq!(snapshot.assets[1].vault_balance, 0);
+        assert!(snapshot.asset(&vault.assets[1].mint).unwrap().oracle_account.is_none());
+    }
+
+    #[test]
+    fn test_build_snapshot_rejects_non_vault_account() {
+        let vault = test_vault(vec![]);
+        let mut data = vault.to_account_data();
+        data[0] ^= 1;
+        assert!(matches!(
+            build_snapshot(1, &vault, vec![account(data), account(mint_data(1))]),
+            Err(CarrotError::InvalidAccountDiscriminator { .. })
+        ));
+    }
+
+    #[test]
+    fn test_build_snapshot_detects_asset_change() {
+        let old_vault = test_vault(vec![test_asset(0)]);
+        let new_vault = test_vault(vec![test_asset(0), test_asset(1)]);
+        let accounts = vec![
+            account(new_vault.to_account_data()),
+            account(mint_data(1)),
+            account(token_account_data(1)),
+            None,
+        ];
+        assert!(build_snapshot(1, &old_vault, accounts).unwrap().is_none());
+    }
+}
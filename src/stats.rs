@@ -0,0 +1,60 @@
+use crate::{fees::FeeAccruals, nav::OracleIssue};
+#[cfg(feature = "blocking")]
+use crate::{
+    client::CarrotClient,
+    error::Result,
+    volume::{VolumeMetrics, DAY_SECONDS},
+};
+
+/// Protocol-wide figures for dashboards and listing sites (USD values in `VALUE_DECIMALS`)
+#[derive(Debug, Clone)]
+pub struct ProtocolStats {
+    /// Slot the vault was read at
+    pub slot: u64,
+    /// Total value locked, net of the pending management fee (excluding assets without a price)
+    pub tvl: u128,
+    /// Total CRT supply
+    pub shares_supply: u64,
+    /// USD value of one whole CRT
+    pub share_price: f64,
+    /// USD value deposited over the last 24 hours
+    pub deposit_volume_24h: u128,
+    /// USD value withdrawn over the last 24 hours (before redemption fees)
+    pub withdraw_volume_24h: u128,
+    /// Wallets holding a non-zero CRT balance
+    pub holder_count: usize,
+    /// Accrued protocol fees
+    pub fees: FeeAccruals,
+    /// Assets valued at a stale price or left out because their oracle couldn't be read
+    pub oracle_issues: Vec<OracleIssue>,
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Aggregate TVL, supply, share price, 24h volume, holder count and fee accruals
+    ///
+    /// Volume is valued at current prices. An unreadable oracle doesn't fail the call: its
+    /// asset is valued as by `fetch_nav_partial` and listed in `oracle_issues`. This scans a
+    /// day of vault transactions and every CRT token account, so it issues many RPC requests;
+    /// cache the result.
+    pub fn get_protocol_stats(&self) -> Result<ProtocolStats> {
+        let snapshot = self.fetch_snapshot()?;
+        let partial = self.partial_nav(&snapshot)?;
+        let nav = &partial.nav;
+        let since = self.now() - DAY_SECONDS;
+        let activity = self.fetch_vault_activity(since)?;
+        let volume = VolumeMetrics::from_entries(&activity, nav, since)?;
+
+        Ok(ProtocolStats {
+            slot: nav.slot,
+            tvl: nav.total_value()?,
+            shares_supply: nav.shares_supply,
+            share_price: nav.share_price()?,
+            deposit_volume_24h: volume.deposit_value,
+            withdraw_volume_24h: volume.redemption_value,
+            holder_count: self.count_crt_holders()?,
+            fees: FeeAccruals::from_nav(nav, &snapshot.vault.fee)?,
+            oracle_issues: partial.issues.clone(),
+        })
+    }
+}
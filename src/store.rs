@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use solana_sdk::signature::Signature;
+
+use crate::{
+    error::{CarrotError, Result},
+    history::ActivityCursor,
+};
+
+/// Namespace of `StateStore::checkpoint`
+pub const CHECKPOINTS_NAMESPACE: &str = "checkpoints";
+
+/// Namespace of `StateStore::mark_processed`
+pub const PROCESSED_NAMESPACE: &str = "processed";
+
+/// Key-value persistence for the SDK's stateful subsystems
+///
+/// Values are grouped by namespace (one per subsystem, e.g. `"ledger"`), so one store can
+/// back the signature ledger, the strategy indexer and subscription checkpoints together.
+/// A `put` is durable once it returns.
+pub trait StateStore: fmt::Debug + Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>>;
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()>;
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()>;
+
+    /// Every entry of `namespace`, ordered by key
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, Vec<u8>)>>;
+
+    /// Cursor saved under `name`, e.g. to resume `subscribe_vault_activity`
+    fn checkpoint(&self, name: &str) -> Result<Option<ActivityCursor>> {
+        let Some(value) = self.get(CHECKPOINTS_NAMESPACE, name)? else {
+            return Ok(None);
+        };
+        let invalid = || CarrotError::InvalidConfig(format!("invalid checkpoint {}", name));
+        let value = String::from_utf8(value).map_err(|_| invalid())?;
+        let (slot, signature) = value.split_once(':').ok_or_else(invalid)?;
+        Ok(Some(ActivityCursor {
+            slot: slot.parse().map_err(|_| invalid())?,
+            signature: Signature::from_str(signature).map_err(|_| invalid())?,
+        }))
+    }
+
+    fn put_checkpoint(&self, name: &str, cursor: &ActivityCursor) -> Result<()> {
+        let value = format!("{}:{}", cursor.slot, cursor.signature);
+        self.put(CHECKPOINTS_NAMESPACE, name, value.as_bytes())
+    }
+
+    /// Whether `signature` was marked processed (replay protection)
+    fn is_processed(&self, signature: &Signature) -> Result<bool> {
+        Ok(self.get(PROCESSED_NAMESPACE, &signature.to_string())?.is_some())
+    }
+
+    fn mark_processed(&self, signature: &Signature) -> Result<()> {
+        self.put(PROCESSED_NAMESPACE, &signature.to_string(), &[])
+    }
+}
+
+/// Store kept in memory only (lost on restart), for tests and short-lived processes
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<BTreeMap<(String, String), Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<(String, String), Vec<u8>>> {
+        // Entries are only replaced whole, a panic elsewhere can't leave them inconsistent
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.lock().get(&(namespace.to_string(), key.to_string())).cloned())
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()> {
+        self.lock().insert((namespace.to_string(), key.to_string()), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        self.lock().remove(&(namespace.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn scan(&self, namespace: &str) -> Result<Vec<(String, Vec<u8>)>> {
+        Ok(self
+            .lock()
+            .iter()
+            .filter(|((ns, _), _)| ns == namespace)
+            .map(|((_, key), value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use self::sled_store::SledStore;
+
+#[cfg(feature = "sled")]
+mod sled_store {
+    use std::path::Path;
+
+    use super::StateStore;
+    use crate::error::{CarrotError, Result};
+
+    /// Store in an embedded sled database, one tree per namespace
+    #[derive(Debug, Clone)]
+    pub struct SledStore {
+        db: sled::Db,
+    }
+
+    impl SledStore {
+        /// Open (or create) the database directory at `path`
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            let path = path.as_ref();
+            let db = sled::open(path).map_err(|e| store_error(&format!("open {}", path.display()), e))?;
+            Ok(Self { db })
+        }
+
+        fn tree(&self, namespace: &str) -> Result<sled::Tree> {
+            self.db.open_tree(namespace).map_err(|e| store_error(namespace, e))
+        }
+    }
+
+    impl StateStore for SledStore {
+        fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+            let value = self.tree(namespace)?.get(key).map_err(|e| store_error(namespace, e))?;
+            Ok(value.map(|v| v.to_vec()))
+        }
+
+        fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()> {
+            let tree = self.tree(namespace)?;
+            tree.insert(key, value).map_err(|e| store_error(namespace, e))?;
+            tree.flush().map_err(|e| store_error(namespace, e))?;
+            Ok(())
+        }
+
+        fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+            let tree = self.tree(namespace)?;
+            tree.remove(key).map_err(|e| store_error(namespace, e))?;
+            tree.flush().map_err(|e| store_error(namespace, e))?;
+            Ok(())
+        }
+
+        fn scan(&self, namespace: &str) -> Result<Vec<(String, Vec<u8>)>> {
+            self.tree(namespace)?
+                .iter()
+                .map(|item| {
+                    let (key, value) = item.map_err(|e| store_error(namespace, e))?;
+                    Ok((String::from_utf8_lossy(&key).into_owned(), value.to_vec()))
+                })
+                .collect()
+        }
+    }
+
+    fn store_error(context: &str, err: sled::Error) -> CarrotError {
+        CarrotError::InvalidConfig(format!("sled store ({}): {}", context, err))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_store::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite_store {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use super::StateStore;
+    use crate::error::{CarrotError, Result};
+
+    /// Store in a SQLite database, in a single `carrot_state` table
+    #[derive(Debug)]
+    pub struct SqliteStore {
+        connection: Mutex<Connection>,
+    }
+
+    impl SqliteStore {
+        /// Open (or create) the database file at `path`
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            let path = path.as_ref();
+            let connection =
+                Connection::open(path).map_err(|e| store_error(&format!("open {}", path.display()), e))?;
+            connection
+                .execute_batch(
+                    "CREATE TABLE IF NOT EXISTS carrot_state (
+                        namespace TEXT NOT NULL,
+                        key TEXT NOT NULL,
+                        value BLOB NOT NULL,
+                        PRIMARY KEY (namespace, key)
+                    )",
+                )
+                .map_err(|e| store_error("create table", e))?;
+            Ok(Self {
+                connection: Mutex::new(connection),
+            })
+        }
+
+        fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+            // Each statement runs on its own, a panic elsewhere can't leave one half-applied
+            self.connection.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    impl StateStore for SqliteStore {
+        fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+            self.lock()
+                .query_row(
+                    "SELECT value FROM carrot_state WHERE namespace = ?1 AND key = ?2",
+                    params![namespace, key],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| store_error(namespace, e))
+        }
+
+        fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<()> {
+            self.lock()
+                .execute(
+                    "INSERT INTO carrot_state (namespace, key, value) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value",
+                    params![namespace, key, value],
+                )
+                .map_err(|e| store_error(namespace, e))?;
+            Ok(())
+        }
+
+        fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+            self.lock()
+                .execute(
+                    "DELETE FROM carrot_state WHERE namespace = ?1 AND key = ?2",
+                    params![namespace, key],
+                )
+                .map_err(|e| store_error(namespace, e))?;
+            Ok(())
+        }
+
+        fn scan(&self, namespace: &str) -> Result<Vec<(String, Vec<u8>)>> {
+            let connection = self.lock();
+            let mut statement = connection
+                .prepare("SELECT key, value FROM carrot_state WHERE namespace = ?1 ORDER BY key")
+                .map_err(|e| store_error(namespace, e))?;
+            let rows = statement
+                .query_map(params![namespace], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| store_error(namespace, e))?;
+            rows.collect::<std::result::Result<_, _>>()
+                .map_err(|e| store_error(namespace, e))
+        }
+    }
+
+    fn store_error(context: &str, err: rusqlite::Error) -> CarrotError {
+        CarrotError::InvalidConfig(format!("sqlite store ({}): {}", context, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(store: &dyn StateStore) {
+        store.put("a", "2", b"two").unwrap();
+        store.put("a", "1", b"one").unwrap();
+        store.put("b", "1", b"other").unwrap();
+        store.put("a", "2", b"TWO").unwrap();
+        assert_eq!(store.get("a", "2").unwrap().as_deref(), Some(&b"TWO"[..]));
+        assert_eq!(
+            store.scan("a").unwrap(),
+            [("1".to_string(), b"one".to_vec()), ("2".to_string(), b"TWO".to_vec())]
+        );
+        store.delete("a", "1").unwrap();
+        assert_eq!(store.get("a", "1").unwrap(), None);
+
+        let cursor = ActivityCursor {
+            slot: 7,
+            signature: Signature::new_unique(),
+        };
+        assert_eq!(store.checkpoint("vault-activity").unwrap(), None);
+        store.put_checkpoint("vault-activity", &cursor).unwrap();
+        assert_eq!(store.checkpoint("vault-activity").unwrap(), Some(cursor));
+
+        assert!(!store.is_processed(&cursor.signature).unwrap());
+        store.mark_processed(&cursor.signature).unwrap();
+        assert!(store.is_processed(&cursor.signature).unwrap());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise(&MemoryStore::new());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_store() {
+        let path = std::env::temp_dir().join(format!("carrot-sled-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        exercise(&SledStore::open(&path).unwrap());
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        let path = std::env::temp_dir().join(format!("carrot-state-{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        exercise(&SqliteStore::open(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+//! Bounded streams of client events and vault activity
+//!
+//! Each stream buffers at most `capacity` items. Client events are emitted from whatever
+//! thread does the work, so a full event stream drops its oldest event instead of stalling
+//! the client; the activity poller instead waits for room, leaving newer activity on-chain
+//! until the consumer catches up. Either way memory stays bounded, and `metrics()` shows how
+//! far behind the consumer is.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+#[cfg(feature = "blocking")]
+use crate::{
+    client::CarrotClient,
+    error::CarrotError,
+    events::ClientEvent,
+    history::{ActivityCursor, HistoryEntry},
+    subscription::ActivitySubscription,
+    supervisor::TaskGroup,
+};
+
+/// How long a waiting producer sleeps between shutdown checks
+const SEND_POLL: Duration = Duration::from_millis(200);
+
+/// Backlog and loss of a `BoundedStream`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamMetrics {
+    pub capacity: usize,
+    /// Items waiting to be consumed
+    pub buffered: usize,
+    /// Most items ever waiting at once
+    pub peak_buffered: usize,
+    /// Items handed to the consumer
+    pub delivered: u64,
+    /// Items discarded because the buffer was full
+    pub dropped: u64,
+    /// Total time the producer spent waiting for room
+    pub producer_wait: Duration,
+}
+
+/// `futures::Stream` over a buffer of at most `capacity` items
+///
+/// Ends once its producer stops; dropping it stops the producer.
+pub struct BoundedStream<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Producing half of a `BoundedStream`
+pub(crate) struct StreamSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// Result of `StreamSender::send_timeout`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SendOutcome<T> {
+    Sent,
+    /// The stream was dropped
+    Closed,
+    /// Still no room after the timeout
+    Full(T),
+}
+
+struct Shared<T> {
+    capacity: usize,
+    state: Mutex<State<T>>,
+    /// Signalled when an item is consumed or the stream dropped
+    space: Condvar,
+}
+
+struct State<T> {
+    buffer: VecDeque<T>,
+    waker: Option<Waker>,
+    sender_closed: bool,
+    receiver_closed: bool,
+    metrics: StreamMetrics,
+}
+
+/// Stream of at most `capacity` (at least 1) buffered items and its sender
+pub(crate) fn bounded<T>(capacity: usize) -> (StreamSender<T>, BoundedStream<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        capacity,
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(capacity),
+            waker: None,
+            sender_closed: false,
+            receiver_closed: false,
+            metrics: StreamMetrics {
+                capacity,
+                ..Default::default()
+            },
+        }),
+        space: Condvar::new(),
+    });
+    (
+        StreamSender {
+            shared: Arc::clone(&shared),
+        },
+        BoundedStream { shared },
+    )
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // Every update leaves the buffer and counters consistent, so ignore poisoning
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> State<T> {
+    fn push(&mut self, item: T) {
+        self.buffer.push_back(item);
+        self.metrics.peak_buffered = self.metrics.peak_buffered.max(self.buffer.len());
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> StreamSender<T> {
+    /// Queue `item`, dropping the oldest buffered item if full; `false` once the stream is gone
+    pub(crate) fn send_dropping_oldest(&self, item: T) -> bool {
+        let mut state = self.shared.lock();
+        if state.receiver_closed {
+            return false;
+        }
+        if state.buffer.len() >= self.shared.capacity {
+            state.buffer.pop_front();
+            state.metrics.dropped += 1;
+        }
+        state.push(item);
+        true
+    }
+
+    /// Queue `item`, waiting up to `timeout` for room
+    pub(crate) fn send_timeout(&self, item: T, timeout: Duration) -> SendOutcome<T> {
+        let started = Instant::now();
+        let mut state = self.shared.lock();
+        while !state.receiver_closed && state.buffer.len() >= self.shared.capacity {
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                state.metrics.producer_wait += started.elapsed();
+                return SendOutcome::Full(item);
+            }
+            state = self
+                .shared
+                .space
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        state.metrics.producer_wait += started.elapsed();
+        if state.receiver_closed {
+            return SendOutcome::Closed;
+        }
+        state.push(item);
+        SendOutcome::Sent
+    }
+
+    /// Whether the stream was dropped
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.lock().receiver_closed
+    }
+}
+
+impl<T> Drop for StreamSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.sender_closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> BoundedStream<T> {
+    /// Current backlog and loss counters
+    pub fn metrics(&self) -> StreamMetrics {
+        let state = self.shared.lock();
+        StreamMetrics {
+            buffered: state.buffer.len(),
+            ..state.metrics
+        }
+    }
+}
+
+impl<T> Stream for BoundedStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.shared.lock();
+        if let Some(item) = state.buffer.pop_front() {
+            state.metrics.delivered += 1;
+            self.shared.space.notify_one();
+            return Poll::Ready(Some(item));
+        }
+        if state.sender_closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for BoundedStream<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_closed = true;
+        self.shared.space.notify_all();
+    }
+}
+
+impl<T> std::fmt::Debug for BoundedStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundedStream").field("metrics", &self.metrics()).finish()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Stream of every event emitted from now on, buffering at most `capacity`
+    ///
+    /// When the consumer falls behind the oldest buffered events are dropped and counted in
+    /// `StreamMetrics::dropped`; the client itself never waits for the consumer.
+    pub fn event_stream(&self, capacity: usize) -> BoundedStream<ClientEvent> {
+        self.subscribe_stream(capacity)
+    }
+
+    /// `subscribe_vault_activity` as a stream buffering at most `capacity` entries
+    ///
+    /// A full stream pauses polling until the consumer catches up, so nothing is dropped:
+    /// the subscription's cursor only moves past entries the stream accepted. Dropping the
+    /// stream ends the subscription.
+    pub fn vault_activity_stream(
+        &self,
+        group: &mut TaskGroup,
+        start: Option<ActivityCursor>,
+        poll_interval: Duration,
+        capacity: usize,
+    ) -> (ActivitySubscription, BoundedStream<HistoryEntry>) {
+        let (sender, stream) = bounded(capacity);
+        let sender = Arc::new(sender);
+        let closed = Arc::clone(&sender);
+        let subscription = self.spawn_vault_activity(
+            group,
+            start,
+            poll_interval,
+            move |entry, shutdown| loop {
+                match sender.send_timeout(entry.clone(), SEND_POLL) {
+                    SendOutcome::Sent => return Ok(()),
+                    // Nothing consumes entries anymore; the task ends before its next poll
+                    SendOutcome::Closed => return Ok(()),
+                    SendOutcome::Full(_) if shutdown.is_triggered() => {
+                        return Err(CarrotError::PublishFailed("activity stream full at shutdown".to_string()))
+                    }
+                    SendOutcome::Full(_) => {}
+                }
+            },
+            move || closed.is_closed(),
+        );
+        (subscription, stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, StreamExt};
+    use std::thread;
+
+    #[test]
+    fn test_drops_oldest_when_full() {
+        let (sender, mut stream) = bounded(2);
+        for i in 0..5 {
+            assert!(sender.send_dropping_oldest(i));
+        }
+        let metrics = stream.metrics();
+        assert_eq!((metrics.buffered, metrics.peak_buffered, metrics.dropped), (2, 2, 3));
+
+        assert_eq!(block_on(stream.next()), Some(3));
+        drop(sender);
+        assert_eq!(block_on(stream.next()), Some(4));
+        assert_eq!(block_on(stream.next()), None);
+        assert_eq!(stream.metrics().delivered, 2);
+    }
+
+    #[test]
+    fn test_waiting_sender_resumes_when_consumed() {
+        let (sender, mut stream) = bounded(1);
+        assert_eq!(sender.send_timeout(1, SEND_POLL), SendOutcome::Sent);
+        assert_eq!(sender.send_timeout(2, Duration::from_millis(1)), SendOutcome::Full(2));
+
+        let producer = thread::spawn(move || {
+            let outcome = sender.send_timeout(2, Duration::from_secs(5));
+            (outcome, sender)
+        });
+        assert_eq!(block_on(stream.next()), Some(1));
+        let (outcome, sender) = producer.join().unwrap();
+        assert_eq!(outcome, SendOutcome::Sent);
+        assert_eq!(block_on(stream.next()), Some(2));
+        assert!(stream.metrics().producer_wait > Duration::ZERO);
+
+        drop(stream);
+        assert!(sender.is_closed());
+        assert_eq!(sender.send_timeout(3, Duration::from_secs(5)), SendOutcome::Closed);
+        assert!(!sender.send_dropping_oldest(3));
+    }
+}
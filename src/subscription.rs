@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    client::CarrotClient,
+    error::Result,
+    events::ClientEvent,
+    history::{ActivityCursor, HistoryEntry, ScanBound},
+    supervisor::{ShutdownSignal, TaskGroup},
+    VAULT_ADDRESS,
+};
+
+/// Handle to a running vault activity subscription
+///
+/// The cursor only moves past a transaction once the handler accepted every entry in it,
+/// so persisting `cursor()` and passing it back to `subscribe_vault_activity` after a
+/// restart resumes without losing events.
+#[derive(Debug, Clone)]
+pub struct ActivitySubscription {
+    cursor: Arc<Mutex<Option<ActivityCursor>>>,
+}
+
+impl ActivitySubscription {
+    /// Newest transaction fully delivered to the handler
+    pub fn cursor(&self) -> Option<ActivityCursor> {
+        *lock(&self.cursor)
+    }
+}
+
+impl CarrotClient {
+    /// Deliver every deposit and withdrawal into the vault to `handler`, oldest first,
+    /// from a supervised task in `group`
+    ///
+    /// Starts after `start`, or at the vault's newest transaction when unset, and polls the
+    /// vault's signature history every `poll_interval`. When a read or the handler fails the
+    /// task is restarted with the group's backoff and backfills everything after the last
+    /// delivered transaction, emitting `ClientEvent::ActivityBackfilled`. Delivery is
+    /// at-least-once: entries of a transaction the handler failed on are delivered again.
+    pub fn subscribe_vault_activity<F>(
+        &self,
+        group: &mut TaskGroup,
+        start: Option<ActivityCursor>,
+        poll_interval: Duration,
+        mut handler: F,
+    ) -> ActivitySubscription
+    where
+        F: FnMut(&HistoryEntry) -> Result<()> + Send + 'static,
+    {
+        self.spawn_vault_activity(group, start, poll_interval, move |entry, _| handler(entry), || false)
+    }
+
+    /// Activity task handing entries to `handler` until shutdown or until `closed` returns true
+    pub(crate) fn spawn_vault_activity<F, C>(
+        &self,
+        group: &mut TaskGroup,
+        start: Option<ActivityCursor>,
+        poll_interval: Duration,
+        mut handler: F,
+        closed: C,
+    ) -> ActivitySubscription
+    where
+        F: FnMut(&HistoryEntry, &ShutdownSignal) -> Result<()> + Send + 'static,
+        C: Fn() -> bool + Send + 'static,
+    {
+        let subscription = ActivitySubscription {
+            cursor: Arc::new(Mutex::new(start)),
+        };
+        let shared = Arc::clone(&subscription.cursor);
+        let client = self.clone();
+        let mut started = start.is_some();
+
+        group.spawn("vault-activity", move |shutdown| {
+            if !started {
+                *lock(&shared) = client.latest_vault_activity_cursor()?;
+                started = true;
+            }
+            let mut reconnected = true;
+            while !closed() {
+                let cursor = *lock(&shared);
+                let bound = cursor.map_or(ScanBound::None, ScanBound::After);
+                let (entries, newest) = client.scan_history(&VAULT_ADDRESS, None, bound)?;
+                if reconnected && !entries.is_empty() {
+                    client.emit(ClientEvent::ActivityBackfilled {
+                        after_slot: cursor.map(|c| c.slot),
+                        entries: entries.len(),
+                    });
+                }
+                reconnected = false;
+
+                deliver(&entries, newest, &shared, &mut |entry| handler(entry, shutdown))?;
+                if shutdown.wait_timeout(poll_interval) {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        subscription
+    }
+}
+
+/// Hand `entries` to `handler`, advancing `cursor` past each transaction once all of its
+/// entries are accepted, and to `newest` once everything is
+fn deliver<F>(
+    entries: &[HistoryEntry],
+    newest: Option<ActivityCursor>,
+    cursor: &Mutex<Option<ActivityCursor>>,
+    handler: &mut F,
+) -> Result<()>
+where
+    F: FnMut(&HistoryEntry) -> Result<()>,
+{
+    for (i, entry) in entries.iter().enumerate() {
+        handler(entry)?;
+        let last_of_transaction = entries.get(i + 1).is_none_or(|next| next.signature != entry.signature);
+        if last_of_transaction {
+            *lock(cursor) = Some(ActivityCursor::at(entry));
+        }
+    }
+    // Transactions without Carrot instructions (or failed ones) don't need to be scanned again
+    if newest.is_some() {
+        *lock(cursor) = newest;
+    }
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // The cursor is replaced whole, a panicking handler can't leave it inconsistent
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::CarrotError, instructions::CarrotInstruction, USDC_MINT};
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+    fn entry(signature: Signature, slot: u64) -> HistoryEntry {
+        HistoryEntry {
+            signature,
+            instruction_index: 0,
+            slot,
+            transaction_index: None,
+            block_time: None,
+            instruction: CarrotInstruction::Issue {
+                user: Pubkey::new_unique(),
+                asset_mint: USDC_MINT,
+                amount: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_cursor_advances_per_transaction() {
+        let (first, second) = (Signature::new_unique(), Signature::new_unique());
+        // Two entries in the second transaction; the handler fails on its last one
+        let entries = [entry(first, 10), entry(second, 11), entry(second, 11)];
+        let cursor = Mutex::new(None);
+        let mut delivered = 0;
+        let result = deliver(&entries, None, &cursor, &mut |_| {
+            delivered += 1;
+            if delivered == 3 {
+                return Err(CarrotError::InvalidConfig("consumer unavailable".to_string()));
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(*cursor.lock().unwrap(), Some(ActivityCursor::at(&entries[0])));
+
+        // Retrying redelivers the whole second transaction, then skips to the newest scanned
+        let newest = ActivityCursor {
+            slot: 12,
+            signature: Signature::new_unique(),
+        };
+        let mut redelivered = Vec::new();
+        deliver(&entries[1..], Some(newest), &cursor, &mut |entry: &HistoryEntry| {
+            redelivered.push(entry.signature);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(redelivered, [second, second]);
+        assert_eq!(*cursor.lock().unwrap(), Some(newest));
+    }
+}
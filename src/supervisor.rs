@@ -0,0 +1,390 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{client::CarrotClient, error::Result, events::ClientEvent};
+
+/// Default delay before the first restart of a failed task
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Default cap on the delay between restarts
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How often `TaskGroup::shutdown` checks whether its tasks have stopped
+const JOIN_POLL: Duration = Duration::from_millis(10);
+
+/// Delay between restarts of a failed task: doubles from `initial` up to `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: DEFAULT_INITIAL_BACKOFF,
+            max: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before restart number `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Spreads restart delays so tasks failing together (e.g. on one RPC outage) don't all
+/// restart at the same moment
+pub trait Jitter: fmt::Debug + Send + Sync {
+    /// Delay to wait instead of the backoff's `delay`
+    fn apply(&self, delay: Duration) -> Duration;
+}
+
+/// Waits between half and all of the backoff delay, drawn from a seeded pseudo-random sequence
+///
+/// Two jitters with the same seed produce the same delays, so tests stay reproducible.
+#[derive(Debug)]
+pub struct SeededJitter {
+    state: Mutex<u64>,
+}
+
+impl SeededJitter {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift never leaves zero
+            state: Mutex::new(seed.max(1)),
+        }
+    }
+
+    /// Seeded from the system time, for production use
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    /// Next value of a xorshift64* sequence
+    fn next(&self) -> u64 {
+        let mut state = lock(&self.state);
+        *state ^= *state >> 12;
+        *state ^= *state << 25;
+        *state ^= *state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl Jitter for SeededJitter {
+    fn apply(&self, delay: Duration) -> Duration {
+        let half = delay / 2;
+        let spread = (delay - half).as_nanos() as u64;
+        half + Duration::from_nanos(self.next() % spread.saturating_add(1))
+    }
+}
+
+/// Shutdown flag shared by a task group and its tasks
+///
+/// Tasks check `is_triggered` between units of work and wait with `wait_timeout` instead of
+/// sleeping, so they stop promptly.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownSignal {
+    /// Ask every task holding this signal to stop
+    pub fn trigger(&self) {
+        let (flag, condvar) = &*self.inner;
+        *lock(flag) = true;
+        condvar.notify_all();
+    }
+
+    /// Whether shutdown was requested
+    pub fn is_triggered(&self) -> bool {
+        *lock(&self.inner.0)
+    }
+
+    /// Wait up to `timeout`, returning early with `true` if shutdown is requested meanwhile
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (flag, condvar) = &*self.inner;
+        let guard = lock(flag);
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, timeout, |triggered| !*triggered)
+            .unwrap_or_else(|e| e.into_inner());
+        *guard
+    }
+}
+
+/// State of a supervised task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStatus {
+    pub name: String,
+    /// Times the task was restarted after failing or panicking
+    pub restarts: u32,
+    /// Error (or panic message) of the most recent failure
+    pub last_error: Option<String>,
+    /// The task returned, either on its own or after shutdown
+    pub finished: bool,
+}
+
+/// Long-running tasks (watchers, subscriptions, backfills) with shared shutdown and restarts
+///
+/// Each task runs on its own thread. A task that returns an error or panics is restarted
+/// after a `Backoff` delay (reset once a run outlasts `Backoff::max`), which is how dropped
+/// connections are re-established; a task that returns `Ok` is done. `shutdown` stops all
+/// tasks and waits for them, and dropping the group signals shutdown without waiting.
+pub struct TaskGroup {
+    shutdown: ShutdownSignal,
+    backoff: Backoff,
+    /// Applied to every restart delay (none by default)
+    jitter: Option<Arc<dyn Jitter>>,
+    client: Option<CarrotClient>,
+    tasks: Vec<(JoinHandle<()>, Arc<Mutex<TaskStatus>>)>,
+}
+
+impl Default for TaskGroup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskGroup {
+    /// Empty group with the default backoff
+    pub fn new() -> Self {
+        Self {
+            shutdown: ShutdownSignal::default(),
+            backoff: Backoff::default(),
+            jitter: None,
+            client: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Set the delay between restarts of tasks spawned from now on
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Randomize the delay between restarts of tasks spawned from now on
+    pub fn with_jitter(mut self, jitter: Arc<dyn Jitter>) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Report restarts as `ClientEvent::TaskRestarting` to `client`'s subscribers
+    pub fn with_events(mut self, client: &CarrotClient) -> Self {
+        self.client = Some(client.clone());
+        self
+    }
+
+    /// Signal shared with the tasks, e.g. to trigger shutdown from a signal handler
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown.clone()
+    }
+
+    /// Run `task` under supervision until it returns `Ok` or the group shuts down
+    ///
+    /// The task receives the shutdown signal and should return once it is triggered.
+    pub fn spawn<F>(&mut self, name: impl Into<String>, mut task: F)
+    where
+        F: FnMut(&ShutdownSignal) -> Result<()> + Send + 'static,
+    {
+        let name = name.into();
+        let status = Arc::new(Mutex::new(TaskStatus {
+            name: name.clone(),
+            restarts: 0,
+            last_error: None,
+            finished: false,
+        }));
+        let shutdown = self.shutdown.clone();
+        let backoff = self.backoff;
+        let jitter = self.jitter.clone();
+        let client = self.client.clone();
+        let task_status = Arc::clone(&status);
+
+        let handle = thread::spawn(move || {
+            let mut attempt = 0;
+            while !shutdown.is_triggered() {
+                let started = Instant::now();
+                let error = match panic::catch_unwind(AssertUnwindSafe(|| task(&shutdown))) {
+                    Ok(Ok(())) => break,
+                    Ok(Err(err)) => err.to_string(),
+                    Err(payload) => panic_message(payload.as_ref()),
+                };
+                if shutdown.is_triggered() {
+                    break;
+                }
+                attempt = if started.elapsed() >= backoff.max { 1 } else { attempt + 1 };
+                let delay = backoff.delay(attempt);
+                let delay = jitter.as_ref().map_or(delay, |jitter| jitter.apply(delay));
+                log::warn!("task {} failed ({}), restarting in {:?}", name, error, delay);
+                {
+                    let mut status = lock(&task_status);
+                    status.restarts += 1;
+                    status.last_error = Some(error.clone());
+                }
+                if let Some(client) = &client {
+                    client.emit(ClientEvent::TaskRestarting {
+                        task: name.clone(),
+                        attempt,
+                        delay,
+                        error,
+                    });
+                }
+                if shutdown.wait_timeout(delay) {
+                    break;
+                }
+            }
+            lock(&task_status).finished = true;
+        });
+        self.tasks.push((handle, status));
+    }
+
+    /// Call `tick` every `interval` until shutdown; failures restart it after the backoff delay
+    pub fn spawn_periodic<F>(&mut self, name: impl Into<String>, interval: Duration, mut tick: F)
+    where
+        F: FnMut() -> Result<()> + Send + 'static,
+    {
+        self.spawn(name, move |shutdown| {
+            loop {
+                tick()?;
+                if shutdown.wait_timeout(interval) {
+                    return Ok(());
+                }
+            }
+        });
+    }
+
+    /// Current state of every task, in spawn order
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.tasks.iter().map(|(_, status)| lock(status).clone()).collect()
+    }
+
+    /// Signal shutdown and wait up to `timeout` for all tasks to return
+    ///
+    /// Tasks still running after `timeout` are detached and reported with `finished: false`.
+    pub fn shutdown(mut self, timeout: Duration) -> Vec<TaskStatus> {
+        self.shutdown.trigger();
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline && !self.tasks.iter().all(|(handle, _)| handle.is_finished()) {
+            thread::sleep(JOIN_POLL);
+        }
+        let statuses = self.status();
+        for (handle, _) in std::mem::take(&mut self.tasks) {
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+        statuses
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        self.shutdown.trigger();
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("panicked: {}", message)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // Only plain values are stored, a panicking task can't leave them inconsistent
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CarrotError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_backoff() -> Backoff {
+        Backoff {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let backoff = Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(5),
+        };
+        let delays: Vec<u64> = (1..=5).map(|attempt| backoff.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_seeded_jitter_is_reproducible() {
+        let delay = Duration::from_secs(8);
+        let first: Vec<Duration> = {
+            let jitter = SeededJitter::new(42);
+            (0..16).map(|_| jitter.apply(delay)).collect()
+        };
+        let jitter = SeededJitter::new(42);
+        let second: Vec<Duration> = (0..16).map(|_| jitter.apply(delay)).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|jittered| *jittered >= delay / 2 && *jittered <= delay));
+        assert!(first.windows(2).any(|pair| pair[0] != pair[1]));
+        assert_eq!(SeededJitter::new(0).apply(Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_restart_until_success() {
+        let mut group = TaskGroup::new().with_backoff(fast_backoff());
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&runs);
+        group.spawn("flaky", move |_| match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => Err(CarrotError::AccountNotFound("connection dropped".to_string())),
+            1 => panic!("subscription closed"),
+            _ => Ok(()),
+        });
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !group.status()[0].finished && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let status = &group.status()[0];
+        assert!(status.finished);
+        assert_eq!(status.restarts, 2);
+        assert_eq!(status.last_error.as_deref(), Some("panicked: subscription closed"));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_shutdown_interrupts_periodic_tasks() {
+        let mut group = TaskGroup::new();
+        let ticks = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&ticks);
+        group.spawn_periodic("poller", Duration::from_secs(3600), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+        while ticks.load(Ordering::SeqCst) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let started = Instant::now();
+        let statuses = group.shutdown(Duration::from_secs(5));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(statuses[0].finished);
+        assert_eq!(statuses[0].restarts, 0);
+    }
+}
@@ -0,0 +1,449 @@
+//! Spans and counters derived from client events, for export to a tracing backend
+//!
+//! `TelemetryRecorder` turns the `ClientEvent` stream into one span per transaction (from its
+//! submission to its confirmation or failure) and monotonic counters. `export_telemetry`
+//! drains them periodically into a `TelemetryExporter`; with the `otlp` feature,
+//! `OtlpExporter` sends them to an OpenTelemetry collector over OTLP/HTTP.
+//!
+//! When `ClientConfig::correlation_id` is set, every transaction carries it in its `SdkMemo` and its
+//! spans use the trace ID it maps to, so they join the caller's distributed trace.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "blocking")]
+use std::{sync::mpsc::RecvTimeoutError, time::Instant};
+
+use solana_sdk::signature::Signature;
+
+use crate::{discriminators::sha256_concat, error::Result, events::ClientEvent};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, supervisor::TaskGroup};
+
+/// Name of the span covering a transaction from submission to outcome
+pub const TRANSACTION_SPAN: &str = "carrot.transaction";
+
+/// How often the exporter task checks for shutdown while no events arrive
+const EXPORT_POLL: Duration = Duration::from_millis(500);
+
+/// Trace ID of spans tagged with `correlation_id`
+///
+/// A W3C trace ID (32 hex digits) is used as is, so spans join the trace it came from; any
+/// other ID is hashed.
+pub fn correlation_trace_id(correlation_id: &str) -> [u8; 16] {
+    let mut trace_id = [0u8; 16];
+    if correlation_id.len() == 32 {
+        let parsed: Option<Vec<u8>> = (0..16)
+            .map(|i| correlation_id.get(2 * i..2 * i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+            .collect();
+        if let Some(bytes) = parsed {
+            trace_id.copy_from_slice(&bytes);
+            return trace_id;
+        }
+    }
+    trace_id.copy_from_slice(&sha256_concat(b"carrot-cid:", correlation_id.as_bytes())[..16]);
+    trace_id
+}
+
+/// A timed operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub name: &'static str,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, String)>,
+    /// Why the operation failed (`None` when it succeeded)
+    pub error: Option<String>,
+}
+
+/// Spans finished and counter totals since the recorder started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelemetryBatch {
+    pub spans: Vec<Span>,
+    /// Cumulative counts, by metric name
+    pub counters: BTreeMap<&'static str, u64>,
+    /// When counting started
+    pub since: SystemTime,
+    pub at: SystemTime,
+}
+
+/// Destination of recorded telemetry
+pub trait TelemetryExporter: Send {
+    fn export(&mut self, batch: &TelemetryBatch) -> Result<()>;
+}
+
+/// Builds spans and counters from client events
+#[derive(Debug, Clone)]
+pub struct TelemetryRecorder {
+    correlation_id: Option<String>,
+    /// Start and latest endpoint of transactions sent but not yet concluded
+    pending: HashMap<Signature, (SystemTime, usize)>,
+    spans: Vec<Span>,
+    counters: BTreeMap<&'static str, u64>,
+    since: SystemTime,
+}
+
+impl TelemetryRecorder {
+    /// Recorder tagging transaction spans with `correlation_id`
+    pub fn new(correlation_id: Option<String>) -> Self {
+        Self {
+            correlation_id,
+            pending: HashMap::new(),
+            spans: Vec::new(),
+            counters: BTreeMap::new(),
+            since: SystemTime::now(),
+        }
+    }
+
+    /// Account for `event`, which happened `at`
+    pub fn record(&mut self, event: &ClientEvent, at: SystemTime) {
+        match event {
+            ClientEvent::VaultFetched => self.count("carrot.vault.fetches", 1),
+            ClientEvent::SnapshotFetched { .. } => self.count("carrot.snapshots", 1),
+            ClientEvent::RetryScheduled { .. } => self.count("carrot.retries", 1),
+            ClientEvent::TxSent { signature, endpoint } => {
+                self.count("carrot.tx.sent", 1);
+                // Rebroadcasts keep the span's original start
+                self.pending.entry(*signature).or_insert((at, *endpoint)).1 = *endpoint;
+            }
+            ClientEvent::TxConfirmed { signature } => {
+                self.count("carrot.tx.confirmed", 1);
+                self.finish(signature, at, None);
+            }
+            ClientEvent::TxFinalized { .. } => self.count("carrot.tx.finalized", 1),
+            ClientEvent::TxFailed { signature, error } => {
+                self.count("carrot.tx.failed", 1);
+                self.finish(signature, at, Some(error.clone()));
+            }
+            ClientEvent::CircuitOpened { .. } => self.count("carrot.circuit_breaker.opened", 1),
+            ClientEvent::TaskRestarting { .. } => self.count("carrot.task.restarts", 1),
+            ClientEvent::ActivityBackfilled { entries, .. } => {
+                self.count("carrot.activity.backfilled", *entries as u64)
+            }
+            ClientEvent::Alert(_) => self.count("carrot.alerts", 1),
+        }
+    }
+
+    /// Take the spans finished so far, with the counter totals
+    pub fn drain(&mut self, at: SystemTime) -> TelemetryBatch {
+        TelemetryBatch {
+            spans: std::mem::take(&mut self.spans),
+            counters: self.counters.clone(),
+            since: self.since,
+            at,
+        }
+    }
+
+    fn count(&mut self, name: &'static str, n: u64) {
+        *self.counters.entry(name).or_default() += n;
+    }
+
+    fn finish(&mut self, signature: &Signature, at: SystemTime, error: Option<String>) {
+        // Failures before submission have no `TxSent`: they span no time
+        let (start, endpoint) = self.pending.remove(signature).unwrap_or((at, 0));
+        let bytes = signature.as_ref();
+        let trace_id = match &self.correlation_id {
+            Some(correlation_id) => correlation_trace_id(correlation_id),
+            None => bytes[..16].try_into().expect("signatures are 64 bytes"),
+        };
+        let mut attributes = vec![
+            ("solana.signature", signature.to_string()),
+            ("carrot.endpoint", endpoint.to_string()),
+        ];
+        if let Some(correlation_id) = &self.correlation_id {
+            attributes.push(("carrot.correlation_id", correlation_id.clone()));
+        }
+        self.spans.push(Span {
+            trace_id,
+            span_id: bytes[16..24].try_into().expect("signatures are 64 bytes"),
+            name: TRANSACTION_SPAN,
+            start,
+            end: at,
+            attributes,
+            error,
+        });
+    }
+}
+
+#[cfg(feature = "otlp")]
+pub use otlp::OtlpExporter;
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde_json::{json, Value};
+
+    use super::{TelemetryBatch, TelemetryExporter};
+    use crate::error::{CarrotError, Result};
+
+    /// Timeout of an export request
+    const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// `SPAN_KIND_CLIENT`
+    const SPAN_KIND_CLIENT: u8 = 3;
+    /// `STATUS_CODE_OK` and `STATUS_CODE_ERROR`
+    const STATUS_OK: u8 = 1;
+    const STATUS_ERROR: u8 = 2;
+    /// `AGGREGATION_TEMPORALITY_CUMULATIVE`
+    const CUMULATIVE: u8 = 2;
+
+    /// Sends telemetry to an OpenTelemetry collector over OTLP/HTTP with JSON encoding
+    pub struct OtlpExporter {
+        endpoint: String,
+        headers: Vec<(String, String)>,
+        service_name: String,
+        http: reqwest::blocking::Client,
+    }
+
+    impl OtlpExporter {
+        /// Exporter posting to the collector at `endpoint` (e.g. `http://localhost:4318`)
+        pub fn new(endpoint: impl Into<String>) -> Result<Self> {
+            let http = reqwest::blocking::Client::builder()
+                .timeout(EXPORT_TIMEOUT)
+                .build()
+                .map_err(|e| CarrotError::InvalidConfig(format!("failed to build HTTP client: {}", e)))?;
+            Ok(Self {
+                endpoint: endpoint.into().trim_end_matches('/').to_string(),
+                headers: Vec::new(),
+                service_name: "carrot-sdk".to_string(),
+                http,
+            })
+        }
+
+        /// Send an extra header with every request (e.g. a collector API key)
+        pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+            self.headers.push((name.into(), value.into()));
+            self
+        }
+
+        /// `service.name` resource attribute (`carrot-sdk` by default)
+        pub fn with_service_name(mut self, service_name: impl Into<String>) -> Self {
+            self.service_name = service_name.into();
+            self
+        }
+
+        fn post(&self, path: &str, body: Value) -> Result<()> {
+            let mut request = self
+                .http
+                .post(format!("{}{}", self.endpoint, path))
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            for (name, value) in &self.headers {
+                request = request.header(name, value);
+            }
+            request
+                .body(body.to_string())
+                .send()
+                .and_then(|response| response.error_for_status())
+                .map(|_| ())
+                .map_err(|e| CarrotError::PublishFailed(format!("OTLP export to {}: {}", path, e.without_url())))
+        }
+    }
+
+    impl TelemetryExporter for OtlpExporter {
+        fn export(&mut self, batch: &TelemetryBatch) -> Result<()> {
+            if !batch.spans.is_empty() {
+                self.post("/v1/traces", traces_json(batch, &self.service_name))?;
+            }
+            if !batch.counters.is_empty() {
+                self.post("/v1/metrics", metrics_json(batch, &self.service_name))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// `ExportTraceServiceRequest` for the spans of `batch`
+    pub(super) fn traces_json(batch: &TelemetryBatch, service_name: &str) -> Value {
+        let spans: Vec<Value> = batch
+            .spans
+            .iter()
+            .map(|span| {
+                let attributes: Vec<Value> = span
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+                    .collect();
+                let status = match &span.error {
+                    Some(error) => json!({ "code": STATUS_ERROR, "message": error }),
+                    None => json!({ "code": STATUS_OK }),
+                };
+                json!({
+                    "traceId": hex(&span.trace_id),
+                    "spanId": hex(&span.span_id),
+                    "name": span.name,
+                    "kind": SPAN_KIND_CLIENT,
+                    "startTimeUnixNano": unix_nanos(span.start),
+                    "endTimeUnixNano": unix_nanos(span.end),
+                    "attributes": attributes,
+                    "status": status,
+                })
+            })
+            .collect();
+        json!({
+            "resourceSpans": [{
+                "resource": resource(service_name),
+                "scopeSpans": [{ "scope": scope(), "spans": spans }],
+            }]
+        })
+    }
+
+    /// `ExportMetricsServiceRequest` for the counters of `batch`, as cumulative monotonic sums
+    pub(super) fn metrics_json(batch: &TelemetryBatch, service_name: &str) -> Value {
+        let metrics: Vec<Value> = batch
+            .counters
+            .iter()
+            .map(|(name, value)| {
+                json!({
+                    "name": name,
+                    "sum": {
+                        "aggregationTemporality": CUMULATIVE,
+                        "isMonotonic": true,
+                        "dataPoints": [{
+                            "asInt": value.to_string(),
+                            "startTimeUnixNano": unix_nanos(batch.since),
+                            "timeUnixNano": unix_nanos(batch.at),
+                        }],
+                    },
+                })
+            })
+            .collect();
+        json!({
+            "resourceMetrics": [{
+                "resource": resource(service_name),
+                "scopeMetrics": [{ "scope": scope(), "metrics": metrics }],
+            }]
+        })
+    }
+
+    fn resource(service_name: &str) -> Value {
+        json!({ "attributes": [{ "key": "service.name", "value": { "stringValue": service_name } }] })
+    }
+
+    fn scope() -> Value {
+        json!({ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    /// 64-bit values are strings in OTLP JSON
+    fn unix_nanos(time: SystemTime) -> String {
+        time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+    }
+
+    /// OTLP JSON encodes trace and span IDs as hex rather than base64
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Record spans and counters from every event emitted from now on and hand them to
+    /// `exporter` every `interval`, from a supervised task
+    ///
+    /// A failed export is logged and its spans dropped; counters are cumulative, so the next
+    /// export carries their totals.
+    pub fn export_telemetry(
+        &self,
+        group: &mut TaskGroup,
+        mut exporter: Box<dyn TelemetryExporter>,
+        interval: Duration,
+    ) {
+        let events = self.subscribe();
+        let mut recorder = TelemetryRecorder::new(self.config().correlation_id.clone());
+        group.spawn("telemetry-exporter", move |shutdown| {
+            let mut next_export = Instant::now() + interval;
+            while !shutdown.is_triggered() {
+                match events.recv_timeout(EXPORT_POLL.min(next_export.saturating_duration_since(Instant::now()))) {
+                    Ok(event) => recorder.record(&event, SystemTime::now()),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                if Instant::now() >= next_export {
+                    next_export = Instant::now() + interval;
+                    if let Err(err) = exporter.export(&recorder.drain(SystemTime::now())) {
+                        log::warn!("failed to export telemetry: {}", err);
+                    }
+                }
+            }
+            // Flush what was recorded since the last export
+            exporter.export(&recorder.drain(SystemTime::now()))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::{Alert, AlertKind, Severity};
+
+    #[test]
+    fn test_correlation_trace_id() {
+        let w3c = "4bf92f3577b34da6a3ce929d0e0e4736";
+        assert_eq!(correlation_trace_id(w3c)[..4], [0x4b, 0xf9, 0x2f, 0x35]);
+        // Anything else is hashed, deterministically
+        assert_eq!(correlation_trace_id("order-42"), correlation_trace_id("order-42"));
+        assert_ne!(correlation_trace_id("order-42"), correlation_trace_id("order-43"));
+    }
+
+    #[test]
+    fn test_records_transaction_spans_and_counters() {
+        let mut recorder = TelemetryRecorder::new(Some("order-42".to_string()));
+        let t0 = SystemTime::now();
+        let (landed, failed) = (Signature::new_unique(), Signature::new_unique());
+        recorder.record(&ClientEvent::TxSent { signature: landed, endpoint: 0 }, t0);
+        recorder.record(&ClientEvent::TxSent { signature: landed, endpoint: 1 }, t0 + Duration::from_secs(1));
+        recorder.record(&ClientEvent::TxConfirmed { signature: landed }, t0 + Duration::from_secs(2));
+        recorder.record(
+            &ClientEvent::TxFailed {
+                signature: failed,
+                error: "blockhash not found".to_string(),
+            },
+            t0 + Duration::from_secs(3),
+        );
+        let alert = Alert {
+            severity: Severity::Info,
+            slot: 1,
+            kind: AlertKind::VaultUnpaused,
+        };
+        recorder.record(&ClientEvent::Alert(alert), t0);
+
+        let batch = recorder.drain(t0 + Duration::from_secs(4));
+        assert_eq!(batch.spans.len(), 2);
+        let span = &batch.spans[0];
+        assert_eq!((span.start, span.end), (t0, t0 + Duration::from_secs(2)));
+        assert_eq!(span.trace_id, correlation_trace_id("order-42"));
+        assert!(span.attributes.contains(&("carrot.endpoint", "1".to_string())));
+        assert_eq!(span.error, None);
+        assert_eq!(batch.spans[1].error.as_deref(), Some("blockhash not found"));
+        assert_eq!(batch.spans[1].start, batch.spans[1].end);
+        assert_eq!(batch.counters["carrot.tx.sent"], 2);
+        assert_eq!(batch.counters["carrot.alerts"], 1);
+
+        // Spans are handed out once, counters keep their totals
+        let batch = recorder.drain(t0 + Duration::from_secs(5));
+        assert!(batch.spans.is_empty());
+        assert_eq!(batch.counters["carrot.tx.failed"], 1);
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_otlp_json() {
+        let mut recorder = TelemetryRecorder::new(Some("4bf92f3577b34da6a3ce929d0e0e4736".to_string()));
+        let signature = Signature::new_unique();
+        recorder.record(&ClientEvent::TxConfirmed { signature }, SystemTime::now());
+        let batch = recorder.drain(SystemTime::now());
+
+        let traces = otlp::traces_json(&batch, "payments");
+        let span = &traces["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+        assert_eq!(span["status"]["code"], 1);
+        let service = &traces["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"];
+        assert_eq!(service, "payments");
+
+        let metrics = otlp::metrics_json(&batch, "payments");
+        let metric = &metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"][0];
+        assert_eq!(metric["name"], "carrot.tx.confirmed");
+        assert_eq!(metric["sum"]["dataPoints"][0]["asInt"], "1");
+    }
+}
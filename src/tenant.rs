@@ -0,0 +1,345 @@
+//! Many customers served from one process over shared RPC connections
+//!
+//! Each tenant gets a `CarrotClient` of its own (policies, deposit limits, priority fees,
+//! memo IDs, events) created with `CarrotClient::with_shared_rpc`, its own signer, a rate limit
+//! on the transactions it sends and counters of what it sent.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+
+use crate::{
+    client::CarrotClient,
+    config::ClientConfig,
+    error::{CarrotError, Result},
+};
+
+/// Most transactions a tenant may send per `window`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_transactions: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max_transactions: u32, window: Duration) -> Self {
+        Self {
+            max_transactions,
+            window,
+        }
+    }
+}
+
+/// Sliding-window counter of attempted transactions
+#[derive(Debug)]
+struct RateLimiter {
+    limit: RateLimit,
+    attempts: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            attempts: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Count an attempt at `now`, or how long until one is allowed
+    fn acquire(&self, now: Instant) -> std::result::Result<(), Duration> {
+        // Attempts are pushed and popped whole, a panic elsewhere can't leave them inconsistent
+        let mut attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+        while attempts
+            .front()
+            .is_some_and(|attempt| now.saturating_duration_since(*attempt) >= self.limit.window)
+        {
+            attempts.pop_front();
+        }
+        if attempts.len() >= self.limit.max_transactions as usize {
+            let oldest = attempts.front().copied().unwrap_or(now);
+            return Err((oldest + self.limit.window).saturating_duration_since(now));
+        }
+        attempts.push_back(now);
+        Ok(())
+    }
+}
+
+/// Counters of a tenant's transactions since it was added
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TenantMetrics {
+    pub deposits: u64,
+    pub withdrawals: u64,
+    /// Deposits and withdrawals that returned an error
+    pub failures: u64,
+    /// Transactions refused by the tenant's rate limit
+    pub rate_limited: u64,
+}
+
+#[derive(Debug, Default)]
+struct MetricCounters {
+    deposits: AtomicU64,
+    withdrawals: AtomicU64,
+    failures: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+/// Configuration of one tenant
+#[derive(Clone)]
+pub struct TenantConfig {
+    /// Client settings; the RPC connection settings are replaced by the manager's
+    pub client: ClientConfig,
+    /// Key signing the tenant's deposits and withdrawals
+    pub signer: Arc<dyn Signer + Send + Sync>,
+    /// Unlimited when unset
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl TenantConfig {
+    pub fn new(client: ClientConfig, signer: Arc<dyn Signer + Send + Sync>) -> Self {
+        Self {
+            client,
+            signer,
+            rate_limit: None,
+        }
+    }
+
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+}
+
+/// One tenant's client, signer, rate limit and metrics
+///
+/// Only transactions sent through `deposit` and `withdraw` are rate limited and counted; the
+/// client from `client()` is for reads, events and anything else.
+#[derive(Clone)]
+pub struct Tenant {
+    inner: Arc<TenantInner>,
+}
+
+struct TenantInner {
+    id: String,
+    client: CarrotClient,
+    signer: Arc<dyn Signer + Send + Sync>,
+    limiter: Option<RateLimiter>,
+    metrics: MetricCounters,
+}
+
+impl Tenant {
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    /// The tenant's client, over the manager's RPC connections
+    pub fn client(&self) -> &CarrotClient {
+        &self.inner.client
+    }
+
+    /// Public key of the tenant's signer
+    pub fn pubkey(&self) -> Pubkey {
+        self.inner.signer.pubkey()
+    }
+
+    /// Deposit with the tenant's signer, within its rate limit
+    pub fn deposit(&self, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        self.acquire()?;
+        self.inner.metrics.deposits.fetch_add(1, Ordering::Relaxed);
+        let result = self.inner.client.deposit(self.inner.signer.as_ref(), asset_mint, amount);
+        self.record(result)
+    }
+
+    /// Withdraw with the tenant's signer, within its rate limit
+    pub fn withdraw(&self, asset_mint: &Pubkey, amount: u64) -> Result<Signature> {
+        self.acquire()?;
+        self.inner.metrics.withdrawals.fetch_add(1, Ordering::Relaxed);
+        let result = self.inner.client.withdraw(self.inner.signer.as_ref(), asset_mint, amount);
+        self.record(result)
+    }
+
+    pub fn metrics(&self) -> TenantMetrics {
+        let counters = &self.inner.metrics;
+        TenantMetrics {
+            deposits: counters.deposits.load(Ordering::Relaxed),
+            withdrawals: counters.withdrawals.load(Ordering::Relaxed),
+            failures: counters.failures.load(Ordering::Relaxed),
+            rate_limited: counters.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+
+    fn acquire(&self) -> Result<()> {
+        let Some(limiter) = &self.inner.limiter else {
+            return Ok(());
+        };
+        limiter.acquire(Instant::now()).map_err(|retry_after| {
+            self.inner.metrics.rate_limited.fetch_add(1, Ordering::Relaxed);
+            CarrotError::RateLimited {
+                tenant: self.inner.id.clone(),
+                retry_after,
+            }
+        })
+    }
+
+    fn record(&self, result: Result<Signature>) -> Result<Signature> {
+        if result.is_err() {
+            self.inner.metrics.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// Tenants sharing the RPC connections of one client
+///
+/// Endpoint failures of any tenant count toward the shared circuit breaker, so an endpoint
+/// that is down is skipped for everyone.
+pub struct TenantManager {
+    pool: CarrotClient,
+    tenants: RwLock<HashMap<String, Tenant>>,
+}
+
+impl TenantManager {
+    /// Manager whose tenants send through the endpoints of `config`
+    pub fn new(config: ClientConfig) -> Result<Self> {
+        Ok(Self::with_pool(CarrotClient::with_config(config)?))
+    }
+
+    /// Manager whose tenants share `pool`'s RPC connections
+    pub fn with_pool(pool: CarrotClient) -> Self {
+        Self {
+            pool,
+            tenants: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The client whose RPC connections the tenants share
+    pub fn pool(&self) -> &CarrotClient {
+        &self.pool
+    }
+
+    /// Add a tenant; fails with `InvalidConfig` if `id` is taken or `config` is invalid
+    pub fn add_tenant(&self, id: impl Into<String>, config: TenantConfig) -> Result<Tenant> {
+        let id = id.into();
+        if config.rate_limit.is_some_and(|limit| limit.max_transactions == 0) {
+            return Err(CarrotError::InvalidConfig(format!("rate limit of tenant {} allows no transactions", id)));
+        }
+        let mut tenants = self.tenants.write().unwrap_or_else(|e| e.into_inner());
+        if tenants.contains_key(&id) {
+            return Err(CarrotError::InvalidConfig(format!("tenant {} already exists", id)));
+        }
+        let tenant = Tenant {
+            inner: Arc::new(TenantInner {
+                id: id.clone(),
+                client: self.pool.with_shared_rpc(config.client)?,
+                signer: config.signer,
+                limiter: config.rate_limit.map(RateLimiter::new),
+                metrics: MetricCounters::default(),
+            }),
+        };
+        tenants.insert(id, tenant.clone());
+        Ok(tenant)
+    }
+
+    /// Remove a tenant; handles already held keep working
+    pub fn remove_tenant(&self, id: &str) -> Option<Tenant> {
+        self.tenants.write().unwrap_or_else(|e| e.into_inner()).remove(id)
+    }
+
+    pub fn tenant(&self, id: &str) -> Result<Tenant> {
+        self.tenants
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+            .ok_or_else(|| CarrotError::InvalidConfig(format!("unknown tenant {}", id)))
+    }
+
+    /// IDs of all tenants, sorted
+    pub fn tenant_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.tenants.read().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Metrics of every tenant, sorted by ID
+    pub fn metrics(&self) -> Vec<(String, TenantMetrics)> {
+        let tenants = self.tenants.read().unwrap_or_else(|e| e.into_inner());
+        let mut metrics: Vec<(String, TenantMetrics)> =
+            tenants.iter().map(|(id, tenant)| (id.clone(), tenant.metrics())).collect();
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{policy::Policy, USDC_MINT, USDT_MINT};
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn test_rate_limiter_window() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(10)));
+        let start = Instant::now();
+        assert!(limiter.acquire(start).is_ok());
+        assert!(limiter.acquire(start + Duration::from_secs(4)).is_ok());
+        assert_eq!(limiter.acquire(start + Duration::from_secs(6)), Err(Duration::from_secs(4)));
+        // The first attempt left the window
+        assert!(limiter.acquire(start + Duration::from_secs(10)).is_ok());
+        assert_eq!(limiter.acquire(start + Duration::from_secs(11)), Err(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_tenants_are_isolated_over_shared_rpc() {
+        let manager = TenantManager::new(ClientConfig::new("http://127.0.0.1:1")).unwrap();
+        // A policy rejecting USDC fails withdrawals before any RPC request
+        let usdt_only = ClientConfig::new("http://ignored:1")
+            .with_policy(Policy::default().with_allowed_assets(vec![USDT_MINT]))
+            .with_integrator_id("acme");
+        let acme = manager
+            .add_tenant(
+                "acme",
+                TenantConfig::new(usdt_only, Arc::new(Keypair::new()))
+                    .with_rate_limit(RateLimit::new(1, Duration::from_secs(3_600))),
+            )
+            .unwrap();
+        let other = manager
+            .add_tenant("other", TenantConfig::new(ClientConfig::new("http://ignored:1"), Arc::new(Keypair::new())))
+            .unwrap();
+        assert!(manager
+            .add_tenant("acme", TenantConfig::new(ClientConfig::new("http://127.0.0.1:1"), Arc::new(Keypair::new())))
+            .is_err());
+
+        assert!(std::ptr::eq(acme.client().rpc_client(), other.client().rpc_client()));
+        assert_eq!(acme.client().config().rpc_url, "http://127.0.0.1:1");
+        assert_eq!(acme.client().config().integrator_id.as_deref(), Some("acme"));
+        assert!(other.client().config().policy.is_none());
+
+        assert!(matches!(acme.withdraw(&USDC_MINT, 1), Err(CarrotError::PolicyViolation(_))));
+        assert!(matches!(
+            acme.deposit(&USDC_MINT, 1),
+            Err(CarrotError::RateLimited { ref tenant, .. }) if tenant == "acme"
+        ));
+        assert_eq!(
+            manager.metrics(),
+            [
+                (
+                    "acme".to_string(),
+                    TenantMetrics {
+                        deposits: 0,
+                        withdrawals: 1,
+                        failures: 1,
+                        rate_limited: 1
+                    }
+                ),
+                ("other".to_string(), TenantMetrics::default()),
+            ]
+        );
+
+        assert!(manager.remove_tenant("acme").is_some());
+        assert_eq!(manager.tenant_ids(), ["other"]);
+        assert!(manager.tenant("acme").is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Fixtures for tests against the SDK
+//!
+//! Vault fixtures and captured mainnet transactions are always available; the LiteSVM fixtures
+//! (deterministic keypairs, mints and funded wallets) need the `sandbox` feature as well.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "sandbox")]
+use litesvm::LiteSVM;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "sandbox")]
+use solana_sdk::{
+    account::Account, native_token::LAMPORTS_PER_SOL, program_pack::Pack, signature::Keypair, signer::Signer,
+    transaction::Transaction,
+};
+#[cfg(feature = "sandbox")]
+use spl_token_2022_interface::{
+    extension::StateWithExtensionsMut,
+    state::{Account as TokenAccount, Mint},
+};
+
+use crate::{
+    accounts::get_vault_asset_ata,
+    error::{CarrotError, Result},
+    proof::SnapshotProof,
+    Asset, Fee, Vault, VaultHandle, CRT_MINT, USDC_MINT, VAULT_ADDRESS,
+};
+#[cfg(feature = "sandbox")]
+use crate::{
+    discriminators::sha256_concat,
+    sandbox::{sandbox_error, Sandbox},
+    token::TokenProgram,
+    PYUSD_MINT, USDT_MINT,
+};
+
+/// Unpaused vault without fees or strategies, holding `assets`
+pub fn test_vault(assets: Vec<Asset>) -> Vault {
+    Vault {
+        authority: Pubkey::new_unique(),
+        shares: CRT_MINT,
+        fee: Fee::default(),
+        paused: false,
+        asset_index: assets.len() as u16,
+        strategy_index: 0,
+        assets,
+        strategies: vec![],
+    }
+}
+
+/// The mainnet vault with USDC as its only asset, held in its real vault ATA
+pub fn usdc_vault() -> VaultHandle {
+    stablecoin_vault(&[USDC_MINT])
+}
+
+/// The mainnet vault holding `mints` (6 decimals, asset IDs in order), each in its real vault ATA
+pub fn stablecoin_vault(mints: &[Pubkey]) -> VaultHandle {
+    let assets = mints
+        .iter()
+        .enumerate()
+        .map(|(i, mint)| Asset {
+            asset_id: i as u16,
+            mint: *mint,
+            decimals: 6,
+            ata: get_vault_asset_ata(mint),
+            oracle: Pubkey::new_unique(),
+        })
+        .collect();
+    VaultHandle::new(VAULT_ADDRESS, test_vault(assets))
+}
+
+/// A mainnet deposit or withdrawal and the vault state it was priced against
+///
+/// Captured by `examples/capture_execution.rs`: the proof is taken right before sending, and
+/// the amounts are what the transaction moved (`fetch_execution_report`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionFixture {
+    pub signature: String,
+    pub asset_mint: String,
+    /// Unix timestamp of the transaction's block
+    pub block_time: i64,
+    /// CRT minted (deposit) or burned (withdrawal), base units
+    pub shares: u64,
+    /// Asset spent (deposit) or received net of the redemption fee (withdrawal), base units
+    pub asset_amount: u64,
+    pub proof: SnapshotProof,
+}
+
+/// Every `.json` fixture in `dir`, in file name order
+///
+/// A missing directory is an `Io` error, not an empty list, so tests can't silently skip.
+pub fn load_execution_fixtures(dir: &Path) -> Result<Vec<(PathBuf, ExecutionFixture)>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| CarrotError::io("read", dir, e))? {
+        let path = entry.map_err(|e| CarrotError::io("read", dir, e))?.path();
+        if path.extension().is_some_and(|extension| extension == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let json = std::fs::read_to_string(&path).map_err(|e| CarrotError::io("read", &path, e))?;
+            let fixture = serde_json::from_str(&json)
+                .map_err(|e| CarrotError::InvalidConfig(format!("invalid fixture {}: {}", path.display(), e)))?;
+            Ok((path, fixture))
+        })
+        .collect()
+}
+
+/// SOL given to each `funded_user`
+#[cfg(feature = "sandbox")]
+pub const FIXTURE_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+/// Keypair derived from `label`, identical across runs and machines
+///
+/// Never use these keys outside tests: anyone who knows the label knows the secret key.
+#[cfg(feature = "sandbox")]
+pub fn deterministic_keypair(label: &str) -> Keypair {
+    Keypair::new_from_array(sha256_concat(b"carrot-sdk test keypair:", label.as_bytes()))
+}
+
+/// Mint authority of mints created by `create_mint`
+#[cfg(feature = "sandbox")]
+pub fn mint_authority() -> Keypair {
+    deterministic_keypair("mint authority")
+}
+
+/// Write an empty mint owned by `program`, with `mint_authority()` as authority
+#[cfg(feature = "sandbox")]
+pub fn create_mint(svm: &mut LiteSVM, mint: &Pubkey, program: TokenProgram, decimals: u8) -> Result<()> {
+    let state = Mint {
+        mint_authority: Some(mint_authority().pubkey()).into(),
+        decimals,
+        is_initialized: true,
+        ..Default::default()
+    };
+    let mut data = vec![0u8; Mint::LEN];
+    Mint::pack(state, &mut data)?;
+    svm.set_account(
+        *mint,
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: program.id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .map_err(sandbox_error)
+}
+
+/// Create the mainnet USDC, USDT, pyUSD and CRT mints under their real token programs
+#[cfg(feature = "sandbox")]
+pub fn create_carrot_mints(svm: &mut LiteSVM) -> Result<()> {
+    for (mint, decimals) in [(USDC_MINT, 6), (USDT_MINT, 6), (PYUSD_MINT, 6), (CRT_MINT, 9)] {
+        create_mint(svm, &mint, TokenProgram::for_mint(&mint), decimals)?;
+    }
+    Ok(())
+}
+
+/// Set `owner`'s balance of `mint` to `amount`, creating their ATA if needed
+///
+/// The mint must already exist (from `create_mint` or a fork). Its supply is adjusted by
+/// the difference, so supply-based math (share price, NAV) stays consistent.
+#[cfg(feature = "sandbox")]
+pub fn set_token_balance(svm: &mut LiteSVM, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Result<Pubkey> {
+    let program = svm
+        .get_account(mint)
+        .and_then(|account| TokenProgram::from_id(&account.owner))
+        .ok_or_else(|| CarrotError::InvalidAsset(format!("{} is not a token mint in the SVM", mint)))?;
+    let ata = program.associated_token_address(owner, mint);
+    if svm.get_account(&ata).is_none() {
+        // The ATA program initializes whatever extensions the mint requires
+        let payer = deterministic_keypair("fixture payer");
+        fund(svm, &payer.pubkey())?;
+        let create = program.create_associated_token_account(&payer.pubkey(), owner, mint);
+        let transaction =
+            Transaction::new_signed_with_payer(&[create], Some(&payer.pubkey()), &[&payer], svm.latest_blockhash());
+        svm.send_transaction(transaction)
+            .map_err(|failed| CarrotError::SimulationFailed(failed.err.to_string()))?;
+    }
+
+    let previous = update(svm, &ata, |data| {
+        let mut account = StateWithExtensionsMut::<TokenAccount>::unpack(data)?;
+        let previous = account.base.amount;
+        account.base.amount = amount;
+        account.pack_base();
+        Ok(previous)
+    })?;
+    update(svm, mint, |data| {
+        let mut state = StateWithExtensionsMut::<Mint>::unpack(data)?;
+        state.base.supply = state.base.supply.saturating_sub(previous).saturating_add(amount);
+        state.pack_base();
+        Ok(())
+    })?;
+    Ok(ata)
+}
+
+/// Deterministic keypair for `label` with `FIXTURE_LAMPORTS` and the given token balances
+#[cfg(feature = "sandbox")]
+pub fn funded_user(svm: &mut LiteSVM, label: &str, balances: &[(Pubkey, u64)]) -> Result<Keypair> {
+    let user = deterministic_keypair(label);
+    fund(svm, &user.pubkey())?;
+    for (mint, amount) in balances {
+        set_token_balance(svm, &user.pubkey(), mint, *amount)?;
+    }
+    Ok(user)
+}
+
+#[cfg(feature = "sandbox")]
+impl Sandbox {
+    /// `funded_user` on the forked state, e.g. a wallet holding 1M USDC against the live vault
+    pub fn funded_user(&mut self, label: &str, balances: &[(Pubkey, u64)]) -> Result<Keypair> {
+        funded_user(self.svm_mut(), label, balances)
+    }
+}
+
+/// Top `address` up to `FIXTURE_LAMPORTS`
+#[cfg(feature = "sandbox")]
+fn fund(svm: &mut LiteSVM, address: &Pubkey) -> Result<()> {
+    let balance = svm.get_balance(address).unwrap_or(0);
+    if balance < FIXTURE_LAMPORTS {
+        svm.airdrop(address, FIXTURE_LAMPORTS - balance)
+            .map_err(|failed| CarrotError::SimulationFailed(failed.err.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Rewrite the data of an existing account
+#[cfg(feature = "sandbox")]
+fn update<T>(svm: &mut LiteSVM, address: &Pubkey, f: impl FnOnce(&mut [u8]) -> Result<T>) -> Result<T> {
+    let mut account = svm
+        .get_account(address)
+        .ok_or_else(|| CarrotError::AccountNotFound(address.to_string()))?;
+    let result = f(&mut account.data)?;
+    svm.set_account(*address, account).map_err(sandbox_error)?;
+    Ok(result)
+}
+
+#[cfg(all(test, feature = "sandbox"))]
+mod tests {
+    use super::*;
+    use crate::{
+        accounts::{get_user_asset_ata, get_user_crt_ata},
+        token::{unpack_mint_supply, unpack_token_amount},
+    };
+
+    fn amount(svm: &LiteSVM, address: &Pubkey) -> u64 {
+        unpack_token_amount(&svm.get_account(address).unwrap().data).unwrap()
+    }
+
+    #[test]
+    fn test_deterministic_keypair_is_stable() {
+        assert_eq!(deterministic_keypair("alice").pubkey(), deterministic_keypair("alice").pubkey());
+        assert_ne!(deterministic_keypair("alice").pubkey(), deterministic_keypair("bob").pubkey());
+    }
+
+    #[test]
+    fn test_funded_user_holds_balances_under_both_token_programs() {
+        let mut svm = LiteSVM::new();
+        create_carrot_mints(&mut svm).unwrap();
+        let user = funded_user(&mut svm, "alice", &[(USDC_MINT, 5_000_000), (PYUSD_MINT, 7_000_000)]).unwrap();
+
+        assert_eq!(svm.get_balance(&user.pubkey()), Some(FIXTURE_LAMPORTS));
+        assert_eq!(amount(&svm, &get_user_asset_ata(&user.pubkey(), &USDC_MINT)), 5_000_000);
+        assert_eq!(amount(&svm, &get_user_asset_ata(&user.pubkey(), &PYUSD_MINT)), 7_000_000);
+        assert_eq!(svm.get_account(&get_user_crt_ata(&user.pubkey())), None);
+    }
+
+    #[test]
+    fn test_set_token_balance_keeps_supply_consistent() {
+        let mut svm = LiteSVM::new();
+        create_carrot_mints(&mut svm).unwrap();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        set_token_balance(&mut svm, &alice, &CRT_MINT, 300).unwrap();
+        set_token_balance(&mut svm, &bob, &CRT_MINT, 200).unwrap();
+        set_token_balance(&mut svm, &alice, &CRT_MINT, 100).unwrap();
+        assert_eq!(unpack_mint_supply(&svm.get_account(&CRT_MINT).unwrap().data).unwrap(), 300);
+
+        assert!(set_token_balance(&mut svm, &alice, &Pubkey::new_unique(), 1).is_err());
+    }
+}
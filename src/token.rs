@@ -0,0 +1,328 @@
+use solana_sdk::{account::Account, instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022_interface::{
+    extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+use crate::{
+    error::{CarrotError, Result},
+    CRT_MINT, PYUSD_MINT, USDC_MINT, USDT_MINT,
+};
+
+/// Token program owning a mint: the original SPL Token program or Token-2022
+///
+/// Account layouts and `transfer_checked` are shared by both programs, so everything here works
+/// for either; only the program ID (and thus ATA addresses) differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenProgram {
+    /// SPL Token (`Tokenkeg...`)
+    Token,
+    /// SPL Token-2022 (`TokenzQd...`)
+    Token2022,
+}
+
+impl TokenProgram {
+    /// Program ID
+    pub fn id(self) -> Pubkey {
+        match self {
+            TokenProgram::Token => spl_token_interface::id(),
+            TokenProgram::Token2022 => spl_token_2022_interface::id(),
+        }
+    }
+
+    /// Program with the given ID (e.g. an account owner), if it is a token program
+    pub fn from_id(id: &Pubkey) -> Option<Self> {
+        if id == &spl_token_interface::id() {
+            Some(TokenProgram::Token)
+        } else if id == &spl_token_2022_interface::id() {
+            Some(TokenProgram::Token2022)
+        } else {
+            None
+        }
+    }
+
+    /// Token program of a mint the SDK ships constants for (CRT, USDC, USDT, pyUSD)
+    pub fn known(mint: &Pubkey) -> Option<Self> {
+        if mint == &PYUSD_MINT || mint == &CRT_MINT {
+            Some(TokenProgram::Token2022)
+        } else if mint == &USDC_MINT || mint == &USDT_MINT {
+            Some(TokenProgram::Token)
+        } else {
+            None
+        }
+    }
+
+    /// Token program of a known Carrot mint
+    /// Token-2022 for pyUSD and CRT, standard Token for everything else (USDC/USDT)
+    ///
+    /// Assets added to the vault later may use either program; prefer
+    /// `VaultHandle::asset_token_program` or `CarrotClient::get_token_program` for them.
+    pub fn for_mint(mint: &Pubkey) -> Self {
+        Self::known(mint).unwrap_or(TokenProgram::Token)
+    }
+
+    /// Associated token account of `owner` for `mint` under this program
+    pub fn associated_token_address(self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.id())
+    }
+
+    /// Idempotent ATA creation for `owner`, paid by `payer`
+    pub fn create_associated_token_account(self, payer: &Pubkey, owner: &Pubkey, mint: &Pubkey) -> Instruction {
+        create_associated_token_account_idempotent(payer, owner, mint, &self.id())
+    }
+
+    /// `transfer_checked` between two token accounts of `mint`, signed by `authority`
+    pub fn transfer_checked(
+        self,
+        source: &Pubkey,
+        mint: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+        decimals: u8,
+    ) -> Result<Instruction> {
+        Ok(spl_token_2022_interface::instruction::transfer_checked(
+            &self.id(),
+            source,
+            mint,
+            destination,
+            authority,
+            &[],
+            amount,
+            decimals,
+        )?)
+    }
+}
+
+/// Size of an associated token account of the mint with account data `mint_data`: the base
+/// account plus, under Token-2022, the extensions the mint requires and immutable owner
+pub fn associated_account_len(program: TokenProgram, mint_data: &[u8]) -> Result<usize> {
+    match program {
+        TokenProgram::Token => Ok(TokenAccount::LEN),
+        TokenProgram::Token2022 => {
+            let mint = StateWithExtensions::<Mint>::unpack(mint_data)?;
+            let mut extensions = ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+            extensions.push(ExtensionType::ImmutableOwner);
+            Ok(ExtensionType::try_calculate_account_len::<TokenAccount>(&extensions)?)
+        }
+    }
+}
+
+/// Read the amount of a Token or Token-2022 token account
+pub(crate) fn unpack_token_amount(data: &[u8]) -> Result<u64> {
+    let account = StateWithExtensions::<TokenAccount>::unpack(data)?;
+    Ok(account.base.amount)
+}
+
+/// Read the mint of a Token or Token-2022 token account
+pub(crate) fn unpack_token_mint(data: &[u8]) -> Result<Pubkey> {
+    let account = StateWithExtensions::<TokenAccount>::unpack(data)?;
+    Ok(account.base.mint)
+}
+
+/// Check that `account` (at `address`) can stand in for `owner`'s ATA of `mint`
+///
+/// It must be an initialized, unfrozen `program` account of `mint` whose owner is `owner`. The
+/// owner field is what counts: accounts whose owner was reassigned away from `owner` (possible
+/// for SPL Token accounts without the immutable-owner extension) are rejected.
+pub fn check_token_account(
+    address: &Pubkey,
+    account: &Account,
+    program: TokenProgram,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<()> {
+    let invalid = |reason: String| CarrotError::InvalidTokenAccount {
+        account: *address,
+        reason,
+    };
+    if account.owner != program.id() {
+        return Err(invalid(format!("owned by program {}, expected {}", account.owner, program.id())));
+    }
+    let token_account = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .map_err(|_| invalid("not a token account".to_string()))?
+        .base;
+    if token_account.mint != *mint {
+        return Err(invalid(format!("holds mint {}, expected {}", token_account.mint, mint)));
+    }
+    if token_account.owner != *owner {
+        return Err(invalid(format!("owned by {}, expected {}", token_account.owner, owner)));
+    }
+    if token_account.state == AccountState::Frozen {
+        return Err(invalid("frozen".to_string()));
+    }
+    Ok(())
+}
+
+/// Read the supply of a Token or Token-2022 mint
+pub(crate) fn unpack_mint_supply(data: &[u8]) -> Result<u64> {
+    let mint = StateWithExtensions::<Mint>::unpack(data)?;
+    Ok(mint.base.supply)
+}
+
+/// Read the decimals of a Token or Token-2022 mint
+pub(crate) fn unpack_mint_decimals(data: &[u8]) -> Result<u8> {
+    let mint = StateWithExtensions::<Mint>::unpack(data)?;
+    Ok(mint.base.decimals)
+}
+
+/// Token-2022 extensions enabled on a mint (always empty for SPL Token mints)
+pub fn mint_extensions(data: &[u8]) -> Result<Vec<ExtensionType>> {
+    let mint = StateWithExtensions::<Mint>::unpack(data)?;
+    Ok(mint.get_extension_types()?)
+}
+
+/// Fee withheld from a transfer of `amount` in `epoch` by the mint with account data `mint_data`
+/// (0 without the Token-2022 transfer fee extension)
+pub fn transfer_fee(mint_data: &[u8], epoch: u64, amount: u64) -> Result<u64> {
+    let mint = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    let Ok(config) = mint.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+    config.calculate_epoch_fee(epoch, amount).ok_or(CarrotError::MathOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::USDC_MINT;
+
+    #[test]
+    fn test_program_ids_round_trip() {
+        for program in [TokenProgram::Token, TokenProgram::Token2022] {
+            assert_eq!(TokenProgram::from_id(&program.id()), Some(program));
+        }
+        assert_eq!(TokenProgram::from_id(&Pubkey::new_unique()), None);
+        assert_eq!(TokenProgram::for_mint(&USDC_MINT), TokenProgram::Token);
+        assert_eq!(TokenProgram::for_mint(&PYUSD_MINT), TokenProgram::Token2022);
+    }
+
+    #[test]
+    fn test_transfer_checked_targets_program() {
+        let (source, destination, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        for program in [TokenProgram::Token, TokenProgram::Token2022] {
+            let ix = program
+                .transfer_checked(&source, &USDC_MINT, &destination, &authority, 5, 6)
+                .unwrap();
+            assert_eq!(ix.program_id, program.id());
+            assert_eq!(ix.accounts[0].pubkey, source);
+        }
+    }
+
+    #[test]
+    fn test_associated_account_len() {
+        let mut mint = vec![0u8; Mint::LEN];
+        Mint::pack(
+            Mint {
+                decimals: 6,
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut mint,
+        )
+        .unwrap();
+        assert_eq!(associated_account_len(TokenProgram::Token, &mint).unwrap(), TokenAccount::LEN);
+        // Account type byte plus the immutable owner TLV header
+        assert_eq!(associated_account_len(TokenProgram::Token2022, &mint).unwrap(), TokenAccount::LEN + 5);
+    }
+
+    fn token_account(owner: Pubkey, state: AccountState) -> Account {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: USDC_MINT,
+                owner,
+                amount: 5,
+                state,
+                ..Default::default()
+            },
+            &mut data,
+        )
+        .unwrap();
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_token_interface::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_check_token_account() {
+        let (address, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = token_account(owner, AccountState::Initialized);
+        let check = |account: &Account, program, owner: &Pubkey, mint: &Pubkey| {
+            check_token_account(&address, account, program, owner, mint)
+        };
+        check(&account, TokenProgram::Token, &owner, &USDC_MINT).unwrap();
+
+        let rejected = [
+            check(&account, TokenProgram::Token2022, &owner, &USDC_MINT),
+            check(&account, TokenProgram::Token, &Pubkey::new_unique(), &USDC_MINT),
+            check(&account, TokenProgram::Token, &owner, &USDT_MINT),
+            check(&token_account(owner, AccountState::Frozen), TokenProgram::Token, &owner, &USDC_MINT),
+        ];
+        for result in rejected {
+            assert!(matches!(result, Err(CarrotError::InvalidTokenAccount { account, .. }) if account == address));
+        }
+    }
+
+    #[test]
+    fn test_plain_mint_has_no_extensions() {
+        let mint = Mint {
+            supply: 1,
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+        assert_eq!(unpack_mint_decimals(&data).unwrap(), 6);
+        assert!(mint_extensions(&data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_transfer_fee() {
+        use spl_token_2022_interface::extension::{
+            transfer_fee::TransferFee, BaseStateWithExtensionsMut, StateWithExtensionsMut,
+        };
+
+        let len = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        state.base = Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        // 1% capped at 5 tokens from epoch 10
+        config.newer_transfer_fee = TransferFee {
+            epoch: 10.into(),
+            maximum_fee: 5_000_000.into(),
+            transfer_fee_basis_points: 100.into(),
+        };
+
+        assert_eq!(transfer_fee(&data, 9, 100_000_000).unwrap(), 0);
+        assert_eq!(transfer_fee(&data, 10, 100_000_000).unwrap(), 1_000_000);
+        assert_eq!(transfer_fee(&data, 10, 1_000_000_000).unwrap(), 5_000_000);
+
+        let mut plain = vec![0u8; Mint::LEN];
+        Mint::pack(
+            Mint {
+                decimals: 6,
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut plain,
+        )
+        .unwrap();
+        assert_eq!(transfer_fee(&plain, 10, 100_000_000).unwrap(), 0);
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+
+use solana_sdk::{account::Account, instruction::AccountMeta, pubkey::Pubkey};
+use spl_token_2022_interface::{
+    extension::{transfer_hook, StateWithExtensions},
+    state::Mint,
+};
+#[cfg(feature = "blocking")]
+use solana_sdk::{instruction::Instruction, signature::Signature, signer::Signer};
+
+use crate::{
+    error::{CarrotError, Result},
+    token::TokenProgram,
+    VaultHandle,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, nav::CRT_DECIMALS, CRT_MINT};
+
+/// Seed of the account listing a hook's extra accounts, followed by the mint
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+
+/// Discriminator of the hook's `Execute` instruction and of its entry in the validation account
+/// sha256("spl-transfer-hook-interface:execute")[..8]
+const EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+
+/// Size of a packed `ExtraAccountMeta`
+const EXTRA_ACCOUNT_META_LEN: usize = 35;
+
+/// `Execute` takes source, mint, destination, authority and the validation account before the extras
+const EXECUTE_FIXED_ACCOUNTS: usize = 5;
+
+/// Discriminators at or above this refer to a PDA of the program at account index `d - 128`
+const EXTERNAL_PDA_BIT: u8 = 1 << 7;
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Accounts to append to `transfer_checked` for `transfer`, empty if its mint has no hook
+    pub fn transfer_hook_accounts(&self, transfer: &HookedTransfer) -> Result<Vec<AccountMeta>> {
+        let mut resolution = HookResolution::new(*transfer);
+        loop {
+            match resolution.step()? {
+                HookStep::Fetch(address) => {
+                    let account = self.fetch_account(&address)?;
+                    resolution.provide(address, account);
+                }
+                HookStep::Resolved(accounts) => return Ok(accounts),
+            }
+        }
+    }
+
+    /// Send `amount` CRT from `owner` to `recipient`'s CRT account, creating it if needed
+    ///
+    /// Resolves the accounts of a transfer hook, should one be enabled on the CRT mint.
+    pub fn transfer_crt(&self, owner: &dyn Signer, recipient: &Pubkey, amount: u64) -> Result<Signature> {
+        let instructions = self.transfer_crt_instructions(&owner.pubkey(), recipient, amount)?;
+        self.send_transaction(&instructions, owner)
+    }
+
+    /// Creation of `recipient`'s CRT account (idempotent) + transfer of `amount` CRT from `owner`'s
+    pub(crate) fn transfer_crt_instructions(
+        &self,
+        owner: &Pubkey,
+        recipient: &Pubkey,
+        amount: u64,
+    ) -> Result<Vec<Instruction>> {
+        let program = TokenProgram::Token2022;
+        let source = program.associated_token_address(owner, &CRT_MINT);
+        let destination = program.associated_token_address(recipient, &CRT_MINT);
+
+        let mut transfer = program.transfer_checked(&source, &CRT_MINT, &destination, owner, amount, CRT_DECIMALS)?;
+        transfer.accounts.extend(self.transfer_hook_accounts(&HookedTransfer {
+            source,
+            mint: CRT_MINT,
+            destination,
+            authority: *owner,
+            amount: Some(amount),
+        })?);
+        Ok(vec![program.create_associated_token_account(owner, recipient, &CRT_MINT), transfer])
+    }
+
+    /// Hook accounts of the vault paying `asset_mint` out to `destination` on redeem
+    ///
+    /// Only Token-2022 assets can have a hook, so other assets cost no extra requests.
+    pub(crate) fn payout_hook_accounts(
+        &self,
+        vault: &VaultHandle,
+        asset_mint: &Pubkey,
+        destination: &Pubkey,
+    ) -> Result<Vec<AccountMeta>> {
+        match payout_transfer(vault, asset_mint, destination)? {
+            Some(transfer) => self.transfer_hook_accounts(&transfer),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn fetch_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        let response = self
+            .rpc_client()
+            .get_account_with_commitment(address, self.rpc_client().commitment())?;
+        Ok(response.value)
+    }
+}
+
+/// The vault's transfer paying `asset_mint` out to `destination` on redeem, `None` unless the
+/// asset is a Token-2022 mint, the only kind that can have a hook
+pub(crate) fn payout_transfer(
+    vault: &VaultHandle,
+    asset_mint: &Pubkey,
+    destination: &Pubkey,
+) -> Result<Option<HookedTransfer>> {
+    if vault.asset_token_program(asset_mint)? != TokenProgram::Token2022 {
+        return Ok(None);
+    }
+    Ok(Some(HookedTransfer {
+        source: vault.asset(asset_mint)?.ata,
+        mint: *asset_mint,
+        destination: *destination,
+        authority: vault.address,
+        // Set on-chain from the vault's prices
+        amount: None,
+    }))
+}
+
+/// Next step of a `HookResolution`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HookStep {
+    /// Fetch this account and `provide` it
+    Fetch(Pubkey),
+    /// The accounts to append to `transfer_checked`
+    Resolved(Vec<AccountMeta>),
+}
+
+/// Resolution of a transfer's hook accounts that asks for one account at a time, so the
+/// blocking and async clients only differ in how they fetch it
+///
+/// Reads the mint, then its validation account, then whatever accounts seed the extra accounts.
+#[derive(Debug, Clone)]
+pub(crate) struct HookResolution {
+    transfer: HookedTransfer,
+    fetched: HashMap<Pubkey, Option<Account>>,
+}
+
+impl HookResolution {
+    pub fn new(transfer: HookedTransfer) -> Self {
+        Self {
+            transfer,
+            fetched: HashMap::new(),
+        }
+    }
+
+    /// Record `account` (`None` if it doesn't exist) as fetched from `address`
+    pub fn provide(&mut self, address: Pubkey, account: Option<Account>) {
+        self.fetched.insert(address, account);
+    }
+
+    /// The account to fetch next, or the accounts once every one needed is known
+    pub fn step(&self) -> Result<HookStep> {
+        let mint = match self.fetched.get(&self.transfer.mint) {
+            None => return Ok(HookStep::Fetch(self.transfer.mint)),
+            Some(None) => return Err(CarrotError::AccountNotFound(format!("mint {}", self.transfer.mint))),
+            Some(Some(mint)) => mint,
+        };
+        if TokenProgram::from_id(&mint.owner) != Some(TokenProgram::Token2022) {
+            return Ok(HookStep::Resolved(Vec::new()));
+        }
+        let Some(hook_program) = transfer_hook_program_id(&mint.data)? else {
+            return Ok(HookStep::Resolved(Vec::new()));
+        };
+
+        let validation = extra_account_metas_address(&self.transfer.mint, &hook_program);
+        let validation_data = match self.fetched.get(&validation) {
+            None => return Ok(HookStep::Fetch(validation)),
+            Some(None) => {
+                return Err(CarrotError::AccountNotFound(format!(
+                    "transfer hook extra account metas {}",
+                    validation
+                )))
+            }
+            Some(Some(account)) => &account.data,
+        };
+        let mut missing = None;
+        let resolved = resolve_hook_accounts(&self.transfer, &hook_program, validation_data, |key| {
+            match self.fetched.get(key) {
+                Some(account) => Ok(account.as_ref().map(|account| account.data.clone())),
+                None => {
+                    missing = Some(*key);
+                    Err(CarrotError::AccountNotFound(key.to_string()))
+                }
+            }
+        });
+        match missing {
+            Some(key) => Ok(HookStep::Fetch(key)),
+            None => resolved.map(HookStep::Resolved),
+        }
+    }
+}
+
+/// Accounts of the `Execute` instruction resolved so far, with their data once fetched
+type ResolvedAccounts = Vec<(AccountMeta, Option<Option<Vec<u8>>>)>;
+
+/// Token transfer to resolve a mint's transfer hook accounts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookedTransfer {
+    pub source: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    /// Owner or delegate of `source`
+    pub authority: Pubkey,
+    /// `None` when not known in advance (e.g. a vault payout); hooks that derive accounts
+    /// from the amount then fail to resolve
+    pub amount: Option<u64>,
+}
+
+/// One entry of a hook's validation account: a fixed address, a PDA or a key read from data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtraAccountMeta {
+    pub discriminator: u8,
+    pub address_config: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Address of the account listing the extra accounts `hook_program` needs for `mint`
+pub fn extra_account_metas_address(mint: &Pubkey, hook_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], hook_program).0
+}
+
+/// Transfer hook program of a Token-2022 mint (`None` without the extension or with it unset)
+pub fn transfer_hook_program_id(mint_data: &[u8]) -> Result<Option<Pubkey>> {
+    let mint = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    Ok(transfer_hook::get_program_id(&mint))
+}
+
+/// Decode the `Execute` entry of a validation account
+pub fn unpack_extra_account_metas(validation_data: &[u8]) -> Result<Vec<ExtraAccountMeta>> {
+    let invalid = |reason: &str| CarrotError::InvalidConfig(format!("invalid extra account metas: {}", reason));
+    // TLV entries: 8-byte discriminator, u32 length, value
+    let mut rest = validation_data;
+    let value = loop {
+        if rest.len() < 12 {
+            return Err(invalid("no Execute entry"));
+        }
+        let length = u32::from_le_bytes(rest[8..12].try_into().unwrap()) as usize;
+        let end = 12usize.checked_add(length).filter(|end| *end <= rest.len());
+        let end = end.ok_or_else(|| invalid("entry exceeds the account"))?;
+        if rest[..8] == EXECUTE_DISCRIMINATOR {
+            break &rest[12..end];
+        }
+        rest = &rest[end..];
+    };
+
+    // Value: u32 count, then the packed metas
+    let count = value.get(..4).ok_or_else(|| invalid("missing count"))?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let metas = &value[4..];
+    if count.checked_mul(EXTRA_ACCOUNT_META_LEN).is_none_or(|len| len > metas.len()) {
+        return Err(invalid("count exceeds the entry"));
+    }
+    Ok(metas
+        .chunks_exact(EXTRA_ACCOUNT_META_LEN)
+        .take(count)
+        .map(|meta| ExtraAccountMeta {
+            discriminator: meta[0],
+            address_config: meta[1..33].try_into().unwrap(),
+            is_signer: meta[33] != 0,
+            is_writable: meta[34] != 0,
+        })
+        .collect())
+}
+
+/// Accounts to append to `transfer_checked` for `transfer` to pass `hook_program`'s checks
+///
+/// `validation_data` is the account at `extra_account_metas_address`, and `fetch` returns the
+/// data of accounts whose contents seed an address. The result is the resolved extra accounts,
+/// then the hook program and the validation account, as Token-2022 expects them.
+pub fn resolve_hook_accounts<F>(
+    transfer: &HookedTransfer,
+    hook_program: &Pubkey,
+    validation_data: &[u8],
+    mut fetch: F,
+) -> Result<Vec<AccountMeta>>
+where
+    F: FnMut(&Pubkey) -> Result<Option<Vec<u8>>>,
+{
+    let validation = extra_account_metas_address(&transfer.mint, hook_program);
+    let mut instruction_data = EXECUTE_DISCRIMINATOR.to_vec();
+    if let Some(amount) = transfer.amount {
+        instruction_data.extend_from_slice(&amount.to_le_bytes());
+    }
+
+    let mut accounts: ResolvedAccounts =
+        [transfer.source, transfer.mint, transfer.destination, transfer.authority, validation]
+            .into_iter()
+            .map(|key| (AccountMeta::new_readonly(key, false), None))
+            .collect();
+
+    for extra in unpack_extra_account_metas(validation_data)? {
+        let pubkey = match extra.discriminator {
+            0 => Pubkey::new_from_array(extra.address_config),
+            1 => resolve_pda(&extra.address_config, hook_program, &instruction_data, &mut accounts, &mut fetch)?,
+            2 => resolve_key_data(&extra.address_config, &instruction_data, &mut accounts, &mut fetch)?,
+            d if d >= EXTERNAL_PDA_BIT => {
+                let index = (d - EXTERNAL_PDA_BIT) as usize;
+                let program = accounts.get(index).ok_or_else(|| missing_account(index))?.0.pubkey;
+                resolve_pda(&extra.address_config, &program, &instruction_data, &mut accounts, &mut fetch)?
+            }
+            d => return Err(invalid_config(&format!("unknown extra account discriminator {}", d))),
+        };
+        // Never escalate an account the transfer already passes with fewer privileges
+        let existing = accounts
+            .iter()
+            .filter(|(meta, _)| meta.pubkey == pubkey)
+            .map(|(meta, _)| (meta.is_signer, meta.is_writable))
+            .reduce(|a, b| (a.0 || b.0, a.1 || b.1));
+        let (is_signer, is_writable) = match existing {
+            Some((signer, writable)) => (extra.is_signer && signer, extra.is_writable && writable),
+            None => (extra.is_signer, extra.is_writable),
+        };
+        let meta = AccountMeta {
+            pubkey,
+            is_signer,
+            is_writable,
+        };
+        accounts.push((meta, None));
+    }
+
+    let mut resolved: Vec<AccountMeta> =
+        accounts.drain(EXECUTE_FIXED_ACCOUNTS..).map(|(meta, _)| meta).collect();
+    resolved.push(AccountMeta::new_readonly(*hook_program, false));
+    resolved.push(AccountMeta::new_readonly(validation, false));
+    Ok(resolved)
+}
+
+/// Data of the account at `index`, fetched once
+fn account_data<'a, F>(accounts: &'a mut ResolvedAccounts, index: usize, fetch: &mut F) -> Result<&'a [u8]>
+where
+    F: FnMut(&Pubkey) -> Result<Option<Vec<u8>>>,
+{
+    let (meta, data) = accounts.get_mut(index).ok_or_else(|| missing_account(index))?;
+    if data.is_none() {
+        *data = Some(fetch(&meta.pubkey)?);
+    }
+    match data {
+        Some(Some(data)) => Ok(data),
+        _ => Err(CarrotError::AccountNotFound(format!("transfer hook seed account {}", meta.pubkey))),
+    }
+}
+
+/// Derive a PDA of `program` from the seeds packed in `config`
+fn resolve_pda<F>(
+    config: &[u8; 32],
+    program: &Pubkey,
+    instruction_data: &[u8],
+    accounts: &mut ResolvedAccounts,
+    fetch: &mut F,
+) -> Result<Pubkey>
+where
+    F: FnMut(&Pubkey) -> Result<Option<Vec<u8>>>,
+{
+    let mut seeds: Vec<Vec<u8>> = Vec::new();
+    let mut i = 0;
+    // Seeds are tag-prefixed and packed back to back; a zero tag ends the list
+    while i < config.len() && config[i] != 0 {
+        let field = |offset: usize| config.get(i + offset).copied().map(usize::from);
+        let truncated = || invalid_config("truncated seed");
+        match config[i] {
+            1 => {
+                let len = field(1).ok_or_else(truncated)?;
+                let bytes = config.get(i + 2..i + 2 + len).ok_or_else(truncated)?;
+                seeds.push(bytes.to_vec());
+                i += 2 + len;
+            }
+            2 => {
+                let (index, len) = (field(1).ok_or_else(truncated)?, field(2).ok_or_else(truncated)?);
+                seeds.push(slice(instruction_data, index, len, "transfer instruction data")?.to_vec());
+                i += 3;
+            }
+            3 => {
+                let index = field(1).ok_or_else(truncated)?;
+                let key = accounts.get(index).ok_or_else(|| missing_account(index))?.0.pubkey;
+                seeds.push(key.to_bytes().to_vec());
+                i += 2;
+            }
+            4 => {
+                let account = field(1).ok_or_else(truncated)?;
+                let (index, len) = (field(2).ok_or_else(truncated)?, field(3).ok_or_else(truncated)?);
+                let data = account_data(accounts, account, fetch)?;
+                seeds.push(slice(data, index, len, "seed account data")?.to_vec());
+                i += 4;
+            }
+            tag => return Err(invalid_config(&format!("unknown seed type {}", tag))),
+        }
+    }
+    let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    Ok(Pubkey::find_program_address(&seeds, program).0)
+}
+
+/// Read a key from the transfer's instruction data or from an account's data
+fn resolve_key_data<F>(
+    config: &[u8; 32],
+    instruction_data: &[u8],
+    accounts: &mut ResolvedAccounts,
+    fetch: &mut F,
+) -> Result<Pubkey>
+where
+    F: FnMut(&Pubkey) -> Result<Option<Vec<u8>>>,
+{
+    let key = match config[0] {
+        1 => slice(instruction_data, config[1] as usize, 32, "transfer instruction data")?,
+        2 => {
+            let data = account_data(accounts, config[1] as usize, fetch)?;
+            slice(data, config[2] as usize, 32, "key account data")?
+        }
+        tag => return Err(invalid_config(&format!("unknown key data type {}", tag))),
+    };
+    Ok(Pubkey::new_from_array(key.try_into().unwrap()))
+}
+
+fn slice<'a>(data: &'a [u8], start: usize, len: usize, what: &str) -> Result<&'a [u8]> {
+    data.get(start..start + len)
+        .ok_or_else(|| invalid_config(&format!("{} too short for bytes {}..{}", what, start, start + len)))
+}
+
+fn missing_account(index: usize) -> CarrotError {
+    invalid_config(&format!("references account {} before it is resolved", index))
+}
+
+fn invalid_config(reason: &str) -> CarrotError {
+    CarrotError::InvalidConfig(format!("transfer hook extra account: {}", reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(discriminator: u8, config: &[u8], is_writable: bool) -> Vec<u8> {
+        let mut bytes = vec![discriminator];
+        let mut address_config = [0u8; 32];
+        address_config[..config.len()].copy_from_slice(config);
+        bytes.extend_from_slice(&address_config);
+        bytes.extend_from_slice(&[0, is_writable as u8]);
+        bytes
+    }
+
+    fn validation_account(metas: &[Vec<u8>]) -> Vec<u8> {
+        let mut value = (metas.len() as u32).to_le_bytes().to_vec();
+        value.extend(metas.concat());
+        let mut data = EXECUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        data.extend(value);
+        data
+    }
+
+    fn transfer() -> HookedTransfer {
+        HookedTransfer {
+            source: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            amount: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_resolves_fixed_pda_and_account_data_keys() {
+        let transfer = transfer();
+        let hook_program = Pubkey::new_unique();
+        let fixed = Pubkey::new_unique();
+        let owner_in_data = Pubkey::new_unique();
+
+        let mut pda_seeds = vec![1, 7];
+        pda_seeds.extend_from_slice(b"counter");
+        pda_seeds.extend_from_slice(&[3, 0]); // source
+        let data = validation_account(&[
+            meta(0, fixed.as_ref(), false),
+            meta(1, &pda_seeds, true),
+            meta(2, &[2, 5, 4], false), // key at offset 4 of extra account 0
+            meta(1, &[2, 8, 8], false), // amount
+        ]);
+        let mut fetched = Vec::new();
+        let accounts = resolve_hook_accounts(&transfer, &hook_program, &data, |key| {
+            fetched.push(*key);
+            let mut data = vec![0u8; 4];
+            data.extend_from_slice(owner_in_data.as_ref());
+            Ok(Some(data))
+        })
+        .unwrap();
+
+        let counter =
+            Pubkey::find_program_address(&[b"counter", transfer.source.as_ref()], &hook_program).0;
+        let by_amount = Pubkey::find_program_address(&[&42u64.to_le_bytes()], &hook_program).0;
+        let keys: Vec<_> = accounts.iter().map(|a| a.pubkey).collect();
+        assert_eq!(
+            keys,
+            [
+                fixed,
+                counter,
+                owner_in_data,
+                by_amount,
+                hook_program,
+                extra_account_metas_address(&transfer.mint, &hook_program)
+            ]
+        );
+        assert!(accounts[1].is_writable && !accounts[0].is_writable);
+        assert_eq!(fetched, [fixed]);
+    }
+
+    #[test]
+    fn test_amount_seed_fails_without_amount() {
+        let data = validation_account(&[meta(1, &[2, 8, 8], false)]);
+        let transfer = HookedTransfer {
+            amount: None,
+            ..transfer()
+        };
+        let result = resolve_hook_accounts(&transfer, &Pubkey::new_unique(), &data, |_| Ok(None));
+        assert!(matches!(result, Err(CarrotError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_transfer_accounts_are_not_escalated() {
+        let transfer = transfer();
+        let data = validation_account(&[meta(0, transfer.destination.as_ref(), true)]);
+        let accounts = resolve_hook_accounts(&transfer, &Pubkey::new_unique(), &data, |_| Ok(None)).unwrap();
+        assert_eq!(accounts[0], AccountMeta::new_readonly(transfer.destination, false));
+    }
+
+    #[test]
+    fn test_resolution_asks_for_the_mint_first() {
+        let transfer = transfer();
+        let mut resolution = HookResolution::new(transfer);
+        assert_eq!(resolution.step().unwrap(), HookStep::Fetch(transfer.mint));
+
+        // SPL Token mints can't have a hook, so nothing else is fetched
+        let mut mint = Account::new(1, 82, &TokenProgram::Token.id());
+        resolution.provide(transfer.mint, Some(mint.clone()));
+        assert_eq!(resolution.step().unwrap(), HookStep::Resolved(Vec::new()));
+
+        mint.owner = TokenProgram::Token2022.id();
+        mint.data[45] = 1; // initialized, no extensions
+        resolution.provide(transfer.mint, Some(mint));
+        assert_eq!(resolution.step().unwrap(), HookStep::Resolved(Vec::new()));
+
+        resolution.provide(transfer.mint, None);
+        assert!(matches!(resolution.step(), Err(CarrotError::AccountNotFound(_))));
+    }
+
+    #[test]
+    fn test_rejects_truncated_validation_account() {
+        let data = validation_account(&[meta(0, &[1; 32], false)]);
+        assert_eq!(unpack_extra_account_metas(&data).unwrap().len(), 1);
+        assert!(unpack_extra_account_metas(&data[..data.len() - 1]).is_err());
+        assert!(unpack_extra_account_metas(&[]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+#[cfg(feature = "blocking")]
+use std::sync::Arc;
+
+use crate::{
+    error::{CarrotError, Result},
+    nav::{Nav, Rounding, CRT_DECIMALS},
+    snapshot::VaultSnapshot,
+    store::StateStore,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, supervisor::TaskGroup};
+
+/// `StateStore` namespace of share price samples, keyed by zero-padded timestamp
+pub const SHARE_PRICE_NAMESPACE: &str = "share-price";
+
+/// Value of one CRT at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharePriceSample {
+    /// Unix timestamp the vault was valued at
+    pub timestamp: i64,
+    pub slot: u64,
+    /// USD value (`VALUE_DECIMALS`) of one whole CRT, net of the pending management fee
+    pub price: u128,
+}
+
+impl SharePriceSample {
+    /// Value one CRT from `snapshot` at `now` (Unix timestamp)
+    pub fn from_snapshot(snapshot: &VaultSnapshot, now: i64) -> Result<Self> {
+        let nav = Nav::from_snapshot_with_fees(snapshot, now)?;
+        Ok(Self {
+            timestamp: now,
+            slot: snapshot.slot,
+            price: nav.value_of_shares(10u64.pow(CRT_DECIMALS as u32), Rounding::Down)?,
+        })
+    }
+}
+
+/// Time-weighted average CRT price over a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Twap {
+    /// USD value (`VALUE_DECIMALS`) of one whole CRT
+    pub price: u128,
+    /// Samples that contributed
+    pub samples: usize,
+    /// Part of the window covered by samples; less than the window when history starts inside it
+    pub coverage: Duration,
+}
+
+/// Share price samples, e.g. taken by `spawn_share_price_sampler` or imported from an archive
+#[derive(Debug, Clone, Default)]
+pub struct SharePriceHistory {
+    samples: BTreeMap<i64, SharePriceSample>,
+}
+
+impl SharePriceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load samples saved to `store`
+    pub fn load_from(store: &dyn StateStore) -> Result<Self> {
+        let mut history = Self::new();
+        for (key, value) in store.scan(SHARE_PRICE_NAMESPACE)? {
+            let sample = std::str::from_utf8(&value)
+                .ok()
+                .and_then(parse_sample)
+                .ok_or_else(|| CarrotError::InvalidConfig(format!("malformed share price sample {}", key)))?;
+            history.record(sample);
+        }
+        Ok(history)
+    }
+
+    /// Write every sample to `store`
+    pub fn save_to(&self, store: &dyn StateStore) -> Result<()> {
+        self.samples.values().try_for_each(|sample| save_sample(store, sample))
+    }
+
+    /// Add a sample; returns `false` (keeping the first) if one exists at the same timestamp
+    pub fn record(&mut self, sample: SharePriceSample) -> bool {
+        if self.samples.contains_key(&sample.timestamp) {
+            return false;
+        }
+        self.samples.insert(sample.timestamp, sample);
+        true
+    }
+
+    /// Samples, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &SharePriceSample> {
+        self.samples.values()
+    }
+
+    /// Time-weighted average price over the `window` ending at `now` (Unix timestamp)
+    ///
+    /// Each sample's price holds until the next one (the newest until `now`), and the last
+    /// sample before the window sets the price at its start. A single outlier sample thus only
+    /// weighs in for the time until the next sample, so sample often enough for that to be short.
+    pub fn twap(&self, window: Duration, now: i64) -> Result<Twap> {
+        let start = now.saturating_sub(window.as_secs() as i64);
+        let anchor = self.samples.range(..=start).next_back();
+        let inside = self.samples.range(start.saturating_add(1)..=now);
+        let points: Vec<&SharePriceSample> = anchor.into_iter().chain(inside).map(|(_, sample)| sample).collect();
+        let Some(first) = points.first() else {
+            return Err(CarrotError::InsufficientHistory(format!("no share price sample at or before {}", now)));
+        };
+
+        let covered_from = first.timestamp.max(start);
+        let mut weighted: u128 = 0;
+        for (index, sample) in points.iter().enumerate() {
+            let from = sample.timestamp.max(start);
+            let until = points.get(index + 1).map_or(now, |next| next.timestamp);
+            let seconds = (until - from) as u128;
+            weighted = sample
+                .price
+                .checked_mul(seconds)
+                .and_then(|value| weighted.checked_add(value))
+                .ok_or(CarrotError::MathOverflow)?;
+        }
+        let covered = (now - covered_from) as u128;
+        // Zero coverage: only a sample taken exactly at `now`
+        let price = weighted.checked_div(covered).unwrap_or(first.price);
+        Ok(Twap {
+            price,
+            samples: points.len(),
+            coverage: Duration::from_secs(covered as u64),
+        })
+    }
+}
+
+fn save_sample(store: &dyn StateStore, sample: &SharePriceSample) -> Result<()> {
+    let key = format!("{:020}", sample.timestamp);
+    let value = format!("{},{},{}", sample.timestamp, sample.slot, sample.price);
+    store.put(SHARE_PRICE_NAMESPACE, &key, value.as_bytes())
+}
+
+fn parse_sample(line: &str) -> Option<SharePriceSample> {
+    let mut fields = line.split(',');
+    let mut next = || fields.next().map(str::trim);
+    Some(SharePriceSample {
+        timestamp: next()?.parse().ok()?,
+        slot: next()?.parse().ok()?,
+        price: next()?.parse().ok()?,
+    })
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Value one CRT at the current slot
+    pub fn sample_share_price(&self) -> Result<SharePriceSample> {
+        SharePriceSample::from_snapshot(&self.fetch_snapshot()?, self.now())
+    }
+
+    /// Sample the share price into `store` every `interval` from a supervised task
+    pub fn spawn_share_price_sampler(&self, group: &mut TaskGroup, store: Arc<dyn StateStore>, interval: Duration) {
+        let client = self.clone();
+        group.spawn_periodic("share-price-sampler", interval, move || {
+            save_sample(store.as_ref(), &client.sample_share_price()?)
+        });
+    }
+
+    /// Time-weighted average CRT price over the `window` ending now
+    ///
+    /// Reads the samples `spawn_share_price_sampler` keeps in the configured state store
+    /// (`ClientConfig::with_state_store`). Check `Twap::coverage` before settling on the price.
+    pub fn share_price_twap(&self, window: Duration) -> Result<Twap> {
+        let store = self.config().state_store.as_ref().ok_or_else(|| {
+            CarrotError::InvalidConfig("share_price_twap needs a state store with share price samples".to_string())
+        })?;
+        SharePriceHistory::load_from(store.as_ref())?.twap(window, self.now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    fn sample(timestamp: i64, price: u128) -> SharePriceSample {
+        SharePriceSample {
+            timestamp,
+            slot: timestamp as u64,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_twap_weights_by_time() {
+        let mut history = SharePriceHistory::new();
+        history.record(sample(0, 100));
+        history.record(sample(600, 110));
+        // Spike lasting one minute
+        history.record(sample(900, 1_000));
+        history.record(sample(960, 110));
+
+        // 100 for 300s (from the anchor), 110 for 300s, 1000 for 60s, 110 for 240s
+        let twap = history.twap(Duration::from_secs(900), 1_200).unwrap();
+        assert_eq!(twap.price, (100 * 300 + 110 * 300 + 1_000 * 60 + 110 * 240) / 900);
+        assert_eq!(twap.samples, 4);
+        assert_eq!(twap.coverage, Duration::from_secs(900));
+
+        // History starting inside the window covers only part of it
+        let twap = history.twap(Duration::from_secs(2_000), 1_200).unwrap();
+        assert_eq!(twap.coverage, Duration::from_secs(1_200));
+
+        assert!(matches!(
+            history.twap(Duration::from_secs(60), -1),
+            Err(CarrotError::InsufficientHistory(_))
+        ));
+    }
+
+    #[test]
+    fn test_history_round_trips_through_store() {
+        let store = MemoryStore::new();
+        let mut history = SharePriceHistory::new();
+        history.record(sample(5, 1_000_000_000_000));
+        assert!(!history.record(sample(5, 1)));
+        history.record(sample(10, 1_000_000_000_001));
+        history.save_to(&store).unwrap();
+
+        let loaded = SharePriceHistory::load_from(&store).unwrap();
+        assert_eq!(loaded.samples().collect::<Vec<_>>(), history.samples().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,318 @@
+//! Startup self-test of the client configuration against the cluster
+//!
+//! `CarrotClient::validate` reads the vault and the mints the SDK hardcodes and probes the
+//! RPC methods the client relies on, so a wrong cluster, a vault migrated to another program
+//! or an endpoint with methods disabled is reported before the first user transaction.
+
+use std::fmt;
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+#[cfg(feature = "blocking")]
+use solana_sdk::signature::Signature;
+
+use crate::{
+    error::{CarrotError, Result},
+    nav::CRT_DECIMALS,
+    token::{unpack_mint_decimals, TokenProgram},
+    Vault, CARROT_PROGRAM_ID, CRT_MINT, PYUSD_MINT, USDC_MINT, USDT_MINT, VAULT_ADDRESS,
+};
+#[cfg(feature = "blocking")]
+use crate::{client::CarrotClient, priority_fee::priority_fee_accounts};
+
+/// Deposit assets the SDK has constants for, with their symbols
+pub const KNOWN_ASSETS: [(Pubkey, &str); 3] = [(USDC_MINT, "USDC"), (USDT_MINT, "USDT"), (PYUSD_MINT, "pyUSD")];
+
+/// Outcome of one check run by `CarrotClient::validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationCheck {
+    pub name: &'static str,
+    /// What is wrong (`None` if the check passed)
+    pub problem: Option<String>,
+}
+
+impl ValidationCheck {
+    fn new(name: &'static str, problems: Vec<String>) -> Self {
+        Self {
+            name,
+            problem: (!problems.is_empty()).then(|| problems.join("; ")),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Every check run by `CarrotClient::validate`, passed or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Slot the accounts were read at
+    pub slot: u64,
+    pub checks: Vec<ValidationCheck>,
+}
+
+impl ValidationReport {
+    /// Whether every check passed
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(ValidationCheck::passed)
+    }
+
+    /// Checks that failed
+    pub fn failures(&self) -> impl Iterator<Item = &ValidationCheck> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+
+    /// The report if every check passed, otherwise `InvalidConfig` listing the failures
+    pub fn into_result(self) -> Result<Self> {
+        if self.is_ok() {
+            return Ok(self);
+        }
+        let failures: Vec<String> = self
+            .failures()
+            .map(|check| format!("{}: {}", check.name, check.problem.as_deref().unwrap_or_default()))
+            .collect();
+        Err(CarrotError::InvalidConfig(format!("startup validation failed: {}", failures.join(", "))))
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Validation at slot {}:", self.slot)?;
+        for check in &self.checks {
+            match &check.problem {
+                None => writeln!(f, "  ok    {}", check.name)?,
+                Some(problem) => writeln!(f, "  FAIL  {}: {}", check.name, problem)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accounts `check_accounts` expects, in order: the vault, the CRT mint and `KNOWN_ASSETS`
+pub fn validation_accounts() -> Vec<Pubkey> {
+    [VAULT_ADDRESS, CRT_MINT]
+        .into_iter()
+        .chain(KNOWN_ASSETS.iter().map(|(mint, _)| *mint))
+        .collect()
+}
+
+/// Check the accounts at `validation_accounts` against the SDK's constants
+///
+/// The vault must be owned by `CARROT_PROGRAM_ID` and decode, its shares mint must be
+/// `CRT_MINT` with `CRT_DECIMALS`, and each of `KNOWN_ASSETS` must be a vault asset whose
+/// recorded decimals match its mint, owned by the token program the SDK expects.
+pub fn check_accounts(accounts: &[Option<Account>]) -> Vec<ValidationCheck> {
+    let account = |index: usize| accounts.get(index).and_then(Option::as_ref);
+    let mut checks = Vec::new();
+
+    let mut problems = Vec::new();
+    let vault = match account(0) {
+        None => {
+            problems.push(format!("vault {} not found", VAULT_ADDRESS));
+            None
+        }
+        Some(vault) if vault.owner != CARROT_PROGRAM_ID => {
+            problems.push(format!("vault is owned by {}, not {}", vault.owner, CARROT_PROGRAM_ID));
+            None
+        }
+        Some(vault) => match Vault::from_account_data(&vault.data) {
+            Ok(vault) => Some(vault),
+            Err(err) => {
+                problems.push(format!("vault doesn't decode: {}", err));
+                None
+            }
+        },
+    };
+    checks.push(ValidationCheck::new("vault", problems));
+    let Some(vault) = vault else {
+        return checks;
+    };
+
+    let mut problems = Vec::new();
+    if vault.shares != CRT_MINT {
+        problems.push(format!("vault shares mint is {}, not {}", vault.shares, CRT_MINT));
+    }
+    match account(1) {
+        None => problems.push(format!("CRT mint {} not found", CRT_MINT)),
+        Some(mint) => match unpack_mint_decimals(&mint.data) {
+            Ok(decimals) if decimals != CRT_DECIMALS => {
+                problems.push(format!("CRT mint has {} decimals, not {}", decimals, CRT_DECIMALS))
+            }
+            Ok(_) => {}
+            Err(err) => problems.push(format!("CRT mint doesn't decode: {}", err)),
+        },
+    }
+    checks.push(ValidationCheck::new("shares mint", problems));
+
+    let mut problems = Vec::new();
+    for (index, (mint, symbol)) in KNOWN_ASSETS.iter().enumerate() {
+        let Some(asset) = vault.assets.iter().find(|asset| asset.mint == *mint) else {
+            problems.push(format!("{} ({}) is not a vault asset", symbol, mint));
+            continue;
+        };
+        let Some(mint_account) = account(2 + index) else {
+            problems.push(format!("{} mint {} not found", symbol, mint));
+            continue;
+        };
+        let expected_program = TokenProgram::for_mint(mint);
+        if mint_account.owner != expected_program.id() {
+            problems.push(format!("{} mint is owned by {}, not {}", symbol, mint_account.owner, expected_program.id()));
+        }
+        match unpack_mint_decimals(&mint_account.data) {
+            Ok(decimals) if decimals != asset.decimals => problems.push(format!(
+                "{} mint has {} decimals, the vault records {}",
+                symbol, decimals, asset.decimals
+            )),
+            Ok(_) => {}
+            Err(err) => problems.push(format!("{} mint doesn't decode: {}", symbol, err)),
+        }
+    }
+    checks.push(ValidationCheck::new("asset mints", problems));
+    checks
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Check the configuration against the cluster before sending anything
+    ///
+    /// Probes the RPC methods the client calls, then reads the vault and the CRT and known
+    /// asset mints in one request and runs `check_accounts` on them. Only fails when the
+    /// accounts can't be read at all; use `ValidationReport::into_result` to turn failed
+    /// checks into an error, or `ClientConfig::with_startup_validation` to do so on creation.
+    pub fn validate(&self) -> Result<ValidationReport> {
+        let rpc = self.rpc_client();
+        let mut checks = Vec::new();
+        let mut probe = |name: &'static str, outcome: std::result::Result<(), String>| {
+            checks.push(ValidationCheck::new(name, outcome.err().into_iter().collect()));
+        };
+        probe("getVersion", rpc.get_version().map(drop).map_err(|e| e.to_string()));
+        probe("getLatestBlockhash", rpc.get_latest_blockhash().map(drop).map_err(|e| e.to_string()));
+        probe("getBlockHeight", rpc.get_block_height().map(drop).map_err(|e| e.to_string()));
+        probe(
+            "getSignatureStatuses",
+            rpc.get_signature_statuses(&[Signature::default()]).map(drop).map_err(|e| e.to_string()),
+        );
+        if self.config().priority_fee.is_some() {
+            probe(
+                "getRecentPrioritizationFees",
+                rpc.get_recent_prioritization_fees(&priority_fee_accounts()).map(drop).map_err(|e| e.to_string()),
+            );
+        }
+
+        let response = rpc.get_multiple_accounts_with_commitment(&validation_accounts(), rpc.commitment())?;
+        checks.extend(check_accounts(&response.value));
+        let report = ValidationReport {
+            slot: response.context.slot,
+            checks,
+        };
+        for failure in report.failures() {
+            log::warn!("validation failed: {}: {}", failure.name, failure.problem.as_deref().unwrap_or_default());
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::usdc_vault;
+    use crate::snapshot::tests::mint_data;
+    use crate::Asset;
+    use solana_sdk::program_pack::Pack;
+    use spl_token_interface::state::Mint;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Option<Account> {
+        Some(Account {
+            lamports: 1,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+
+    fn mint(program: TokenProgram, decimals: u8) -> Option<Account> {
+        let mut data = vec![0u8; Mint::LEN];
+        let state = Mint {
+            decimals,
+            is_initialized: true,
+            ..Default::default()
+        };
+        Mint::pack(state, &mut data).unwrap();
+        account(program.id(), data)
+    }
+
+    fn accounts(vault: &Vault) -> Vec<Option<Account>> {
+        let mut accounts = vec![
+            account(CARROT_PROGRAM_ID, vault.to_account_data()),
+            account(TokenProgram::Token.id(), mint_data(1_000)),
+        ];
+        accounts.extend(KNOWN_ASSETS.iter().map(|(mint_address, _)| mint(TokenProgram::for_mint(mint_address), 6)));
+        accounts
+    }
+
+    fn known_vault() -> Vault {
+        let mut vault = usdc_vault().vault;
+        vault.shares = CRT_MINT;
+        vault.assets = KNOWN_ASSETS
+            .iter()
+            .enumerate()
+            .map(|(index, (mint, _))| Asset {
+                asset_id: index as u16,
+                mint: *mint,
+                decimals: 6,
+                ata: Pubkey::new_unique(),
+                oracle: Pubkey::new_unique(),
+            })
+            .collect();
+        vault
+    }
+
+    fn problems(checks: &[ValidationCheck]) -> Vec<(&'static str, String)> {
+        checks
+            .iter()
+            .filter_map(|check| Some((check.name, check.problem.clone()?)))
+            .collect()
+    }
+
+    #[test]
+    fn test_matching_accounts_pass() {
+        let checks = check_accounts(&accounts(&known_vault()));
+        assert_eq!(checks.len(), 3);
+        assert!(problems(&checks).is_empty());
+
+        let report = ValidationReport { slot: 7, checks };
+        assert!(report.is_ok());
+        assert!(report.clone().into_result().is_ok());
+        assert!(report.to_string().contains("ok    asset mints"));
+    }
+
+    #[test]
+    fn test_misconfigurations_reported() {
+        // Vault owned by another program: nothing else can be checked
+        let vault = known_vault();
+        let mut wrong_owner = accounts(&vault);
+        wrong_owner[0].as_mut().unwrap().owner = Pubkey::new_unique();
+        let checks = check_accounts(&wrong_owner);
+        assert_eq!(checks.len(), 1);
+        assert!(checks[0].problem.as_deref().unwrap().contains("owned by"));
+
+        let mut vault = known_vault();
+        vault.shares = Pubkey::new_unique();
+        vault.assets.retain(|asset| asset.mint != USDT_MINT);
+        let mut mismatched = accounts(&vault);
+        // USDC on-chain with other decimals than the vault records
+        mismatched[2] = mint(TokenProgram::Token, 9);
+        let report = ValidationReport {
+            slot: 7,
+            checks: check_accounts(&mismatched),
+        };
+        let found = problems(&report.checks);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, "shares mint");
+        assert!(found[1].1.contains("USDC mint has 9 decimals"));
+        assert!(found[1].1.contains("USDT"));
+        let err = report.into_result().unwrap_err();
+        assert!(matches!(err, CarrotError::InvalidConfig(ref message) if message.contains("shares mint")));
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use serde::{Serialize, Serializer};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    error::{CarrotError, Result},
+    metadata,
+    nav::{Nav, CRT_DECIMALS},
+    oracle::{decode_oracle_account, OracleProvider},
+    snapshot::VaultSnapshot,
+    Fee,
+};
+
+/// Complete decoded view of the vault, as printed by `carrot vault-info`
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultInfo {
+    #[serde(serialize_with = "display")]
+    pub address: Pubkey,
+    /// Slot the state was read at
+    pub slot: u64,
+    #[serde(serialize_with = "display")]
+    pub authority: Pubkey,
+    #[serde(serialize_with = "display")]
+    pub shares_mint: Pubkey,
+    pub shares_supply: u64,
+    pub paused: bool,
+    pub fee: Fee,
+    /// USD per CRT, `None` if an oracle couldn't be read or no CRT exists
+    pub share_price: Option<f64>,
+    pub assets: Vec<AssetInfo>,
+    pub strategies: Vec<StrategyInfo>,
+}
+
+/// A vault asset with its vault-side balance and oracle price
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetInfo {
+    pub asset_id: u16,
+    #[serde(serialize_with = "display")]
+    pub mint: Pubkey,
+    pub symbol: String,
+    pub decimals: u8,
+    /// Vault token account holding the idle balance
+    #[serde(serialize_with = "display")]
+    pub ata: Pubkey,
+    pub vault_balance: u64,
+    /// Sum of the balances of the asset's strategies
+    pub strategy_balance: u64,
+    #[serde(serialize_with = "display")]
+    pub oracle: Pubkey,
+    /// `None` if the oracle account doesn't exist
+    #[serde(serialize_with = "oracle_provider")]
+    pub oracle_provider: Option<OracleProvider>,
+    /// USD price of one whole token, `None` if the oracle couldn't be decoded
+    pub price: Option<f64>,
+}
+
+/// A strategy record with the symbol of the asset it deploys
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyInfo {
+    pub strategy_id: u16,
+    pub asset_id: u16,
+    pub symbol: String,
+    pub decimals: u8,
+    pub balance: u64,
+    pub net_earnings: i64,
+}
+
+impl VaultInfo {
+    /// Decode a snapshot of the vault at `address`, labeling assets with `symbols`
+    ///
+    /// Assets missing from `symbols` use the built-in symbol or their mint address. Oracles
+    /// that can't be decoded leave the price empty instead of failing.
+    pub fn from_snapshot(address: Pubkey, snapshot: &VaultSnapshot, symbols: &HashMap<Pubkey, String>) -> Self {
+        let vault = &snapshot.vault;
+        let symbol = |mint: &Pubkey| {
+            symbols
+                .get(mint)
+                .cloned()
+                .or_else(|| metadata::builtin(mint).map(|(_, symbol)| symbol.to_string()))
+                .unwrap_or_else(|| mint.to_string())
+        };
+
+        let assets: Vec<AssetInfo> = snapshot
+            .assets
+            .iter()
+            .map(|asset_snapshot| {
+                let asset = &asset_snapshot.asset;
+                AssetInfo {
+                    asset_id: asset.asset_id,
+                    mint: asset.mint,
+                    symbol: symbol(&asset.mint),
+                    decimals: asset.decimals,
+                    ata: asset.ata,
+                    vault_balance: asset_snapshot.vault_balance,
+                    strategy_balance: vault
+                        .strategies
+                        .iter()
+                        .filter(|s| s.asset_id == asset.asset_id)
+                        .fold(0u64, |sum, s| sum.saturating_add(s.balance)),
+                    oracle: asset.oracle,
+                    oracle_provider: asset_snapshot.oracle_provider(),
+                    price: asset_snapshot
+                        .oracle_account
+                        .as_ref()
+                        .and_then(|account| decode_oracle_account(account).ok())
+                        .map(|price| price.as_f64()),
+                }
+            })
+            .collect();
+
+        let strategies = vault
+            .strategies
+            .iter()
+            .map(|strategy| {
+                let asset = assets.iter().find(|a| a.asset_id == strategy.asset_id);
+                StrategyInfo {
+                    strategy_id: strategy.strategy_id,
+                    asset_id: strategy.asset_id,
+                    symbol: asset.map(|a| a.symbol.clone()).unwrap_or_else(|| format!("asset {}", strategy.asset_id)),
+                    decimals: asset.map(|a| a.decimals).unwrap_or(0),
+                    balance: strategy.balance,
+                    net_earnings: strategy.net_earnings,
+                }
+            })
+            .collect();
+
+        Self {
+            address,
+            slot: snapshot.slot,
+            authority: vault.authority,
+            shares_mint: vault.shares,
+            shares_supply: snapshot.shares_supply,
+            paused: vault.paused,
+            fee: vault.fee.clone(),
+            share_price: Nav::from_snapshot(snapshot).and_then(|nav| nav.share_price()).ok(),
+            assets,
+            strategies,
+        }
+    }
+
+    /// Render as aligned plain-text tables
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        let fee = &self.fee;
+        let share_price = self.share_price.map(|p| format!("${:.6}", p)).unwrap_or_else(|| "n/a".to_string());
+        let _ = writeln!(out, "Vault:            {}", self.address);
+        let _ = writeln!(out, "Slot:             {}", self.slot);
+        let _ = writeln!(out, "Authority:        {}", self.authority);
+        let _ = writeln!(out, "Shares mint:      {}", self.shares_mint);
+        let _ = writeln!(out, "CRT supply:       {}", units(self.shares_supply as i128, CRT_DECIMALS));
+        let _ = writeln!(out, "Share price:      {}", share_price);
+        let _ = writeln!(out, "Paused:           {}", self.paused);
+        let _ = writeln!(
+            out,
+            "Redemption fee:   {} bps (accumulated {})",
+            fee.redemption_fee_bps, fee.redemption_fee_accumulated
+        );
+        let _ = writeln!(
+            out,
+            "Management fee:   {} bps (accumulated {}, last update {})",
+            fee.management_fee_bps, fee.management_fee_accumulated, fee.management_fee_last_update
+        );
+        let _ = writeln!(out, "Performance fee:  {} bps", fee.performance_fee_bps);
+
+        let _ = writeln!(out, "\nAssets");
+        let _ = writeln!(
+            out,
+            "{:<4} {:<10} {:>8} {:>20} {:>20} {:>12}  {:<12} {:<44} {:<44}",
+            "id", "symbol", "decimals", "idle", "in strategies", "price", "oracle", "mint", "vault ata"
+        );
+        for asset in &self.assets {
+            let price = asset.price.map(|p| format!("${:.6}", p)).unwrap_or_else(|| "n/a".to_string());
+            let provider = asset.oracle_provider.map(provider_name).unwrap_or("missing");
+            let _ = writeln!(
+                out,
+                "{:<4} {:<10} {:>8} {:>20} {:>20} {:>12}  {:<12} {:<44} {:<44}",
+                asset.asset_id,
+                asset.symbol,
+                asset.decimals,
+                units(asset.vault_balance as i128, asset.decimals),
+                units(asset.strategy_balance as i128, asset.decimals),
+                price,
+                provider,
+                asset.mint,
+                asset.ata
+            );
+        }
+
+        let _ = writeln!(out, "\nStrategies");
+        let _ = writeln!(out, "{:<4} {:<10} {:>20} {:>20}", "id", "asset", "balance", "net earnings");
+        for strategy in &self.strategies {
+            let _ = writeln!(
+                out,
+                "{:<4} {:<10} {:>20} {:>20}",
+                strategy.strategy_id,
+                strategy.symbol,
+                units(strategy.balance as i128, strategy.decimals),
+                units(strategy.net_earnings as i128, strategy.decimals)
+            );
+        }
+        out
+    }
+
+    /// Render as a JSON object (amounts in base units, prices as numbers or null)
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| CarrotError::InvalidConfig(format!("failed to encode vault: {}", e)))
+    }
+}
+
+fn provider_name(provider: OracleProvider) -> &'static str {
+    match provider {
+        OracleProvider::PythPush => "pyth-push",
+        OracleProvider::PythPull => "pyth-pull",
+        OracleProvider::PythLegacy => "pyth-legacy",
+        OracleProvider::Switchboard => "switchboard",
+        OracleProvider::Unknown(_) => "unknown",
+    }
+}
+
+/// Base units as a decimal string with `decimals` fractional digits
+fn units(amount: i128, decimals: u8) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.unsigned_abs();
+    if decimals == 0 {
+        return format!("{}{}", sign, amount);
+    }
+    let scale = 10u128.pow(decimals as u32);
+    format!("{}{}.{:0width$}", sign, amount / scale, amount % scale, width = decimals as usize)
+}
+
+/// Serialize a value by its `Display` form, e.g. a `Pubkey` in base58
+fn display<T: fmt::Display, S: Serializer>(value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn oracle_provider<S: Serializer>(
+    provider: &Option<OracleProvider>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match provider {
+        Some(provider) => serializer.serialize_some(provider_name(*provider)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{snapshot::AssetSnapshot, Asset, StrategyRecord, Vault, CRT_MINT, USDC_MINT, VAULT_ADDRESS};
+
+    fn snapshot() -> VaultSnapshot {
+        let mint = Pubkey::new_unique();
+        let asset = |asset_id, mint| Asset {
+            asset_id,
+            mint,
+            decimals: 6,
+            ata: Pubkey::new_unique(),
+            oracle: Pubkey::new_unique(),
+        };
+        let vault = Vault {
+            authority: Pubkey::new_unique(),
+            shares: CRT_MINT,
+            fee: Fee {
+                redemption_fee_bps: 10,
+                management_fee_bps: 50,
+                management_fee_last_update: 1_700_000_000,
+                performance_fee_bps: 1_000,
+                ..Default::default()
+            },
+            paused: true,
+            asset_index: 2,
+            strategy_index: 1,
+            assets: vec![asset(0, USDC_MINT), asset(1, mint)],
+            strategies: vec![StrategyRecord {
+                strategy_id: 0,
+                asset_id: 0,
+                balance: 2_500_000,
+                net_earnings: -1_250_000,
+            }],
+        };
+        VaultSnapshot {
+            slot: 42,
+            shares_supply: 1_000_000_000,
+            assets: vault
+                .assets
+                .iter()
+                .map(|asset| AssetSnapshot {
+                    asset: asset.clone(),
+                    vault_balance: 1_000_000,
+                    oracle_account: None,
+                })
+                .collect(),
+            vault,
+        }
+    }
+
+    #[test]
+    fn test_decodes_assets_and_strategies() {
+        let snapshot = snapshot();
+        let mint = snapshot.vault.assets[1].mint;
+        let symbols = HashMap::from([(mint, "US\"DS".to_string())]);
+        let info = VaultInfo::from_snapshot(VAULT_ADDRESS, &snapshot, &symbols);
+
+        assert_eq!(info.assets[0].symbol, "USDC");
+        assert_eq!(info.assets[0].strategy_balance, 2_500_000);
+        assert_eq!(info.assets[1].strategy_balance, 0);
+        // Oracles weren't read: prices are unknown, the rest still decodes
+        assert_eq!(info.assets[0].price, None);
+        assert_eq!(info.share_price, None);
+        assert_eq!(info.strategies[0].symbol, "USDC");
+
+        let table = info.to_table();
+        assert!(table.contains("Paused:           true"));
+        assert!(table.contains("-1.250000"));
+        assert!(table.contains("CRT supply:       1.000000000"));
+
+        let json: serde_json::Value = serde_json::from_str(&info.to_json().unwrap()).unwrap();
+        assert_eq!(json["paused"], true);
+        assert_eq!(json["address"], info.address.to_string());
+        assert_eq!(json["assets"][1]["symbol"], "US\"DS");
+        assert_eq!(json["strategies"][0]["net_earnings"], -1_250_000);
+        assert_eq!(json["share_price"], serde_json::Value::Null);
+    }
+}
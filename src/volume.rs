@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    error::{CarrotError, Result},
+    history::HistoryEntry,
+    instructions::CarrotInstruction,
+    nav::{Nav, Rounding},
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// Seconds in a day
+pub const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// Seconds in a week
+pub const WEEK_SECONDS: i64 = 7 * DAY_SECONDS;
+
+/// Deposit and redemption volume of one asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetVolume {
+    pub mint: Pubkey,
+    /// Asset deposited (base units)
+    pub deposited: u64,
+    /// CRT redeemed for this asset (base units)
+    pub redeemed_shares: u64,
+    pub deposit_count: usize,
+    pub redemption_count: usize,
+    /// USD value of deposits (`VALUE_DECIMALS`)
+    pub deposit_value: u128,
+    /// USD value of redemptions before fees (`VALUE_DECIMALS`)
+    pub redemption_value: u128,
+}
+
+/// Volume over a time window, valued at a single NAV
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeMetrics {
+    /// Start of the window (Unix timestamp)
+    pub since: i64,
+    /// USD value of all deposits (`VALUE_DECIMALS`)
+    pub deposit_value: u128,
+    /// USD value of all redemptions before fees (`VALUE_DECIMALS`)
+    pub redemption_value: u128,
+    /// Per-asset breakdown, in vault asset order (assets no longer in the vault last)
+    pub assets: Vec<AssetVolume>,
+}
+
+impl VolumeMetrics {
+    /// Sum the entries at or after `since`, valued at `nav`'s prices
+    ///
+    /// Deposits of assets the vault no longer lists are counted but not valued. Entries with
+    /// the same key (e.g. delivered by two streams) are counted once.
+    pub fn from_entries(entries: &[HistoryEntry], nav: &Nav, since: i64) -> Result<Self> {
+        let mut seen = HashSet::new();
+        let mut assets: Vec<AssetVolume> = nav.assets.iter().map(|a| AssetVolume::new(a.mint)).collect();
+        let mut deposit_value = 0u128;
+        let mut redemption_value = 0u128;
+
+        let in_window = entries.iter().filter(|e| e.block_time.is_some_and(|t| t >= since));
+        for entry in in_window.filter(|e| seen.insert(e.key())) {
+            let index = match assets.iter().position(|a| a.mint == entry.asset_mint()) {
+                Some(index) => index,
+                None => {
+                    assets.push(AssetVolume::new(entry.asset_mint()));
+                    assets.len() - 1
+                }
+            };
+            let volume = &mut assets[index];
+
+            match entry.instruction {
+                CarrotInstruction::Issue { asset_mint, amount, .. } => {
+                    let value = match nav.asset(&asset_mint) {
+                        Ok(asset) => asset.value_of(amount, Rounding::Down)?,
+                        Err(_) => 0,
+                    };
+                    volume.deposited = volume.deposited.saturating_add(amount);
+                    volume.deposit_count += 1;
+                    volume.deposit_value = checked_add(volume.deposit_value, value)?;
+                    deposit_value = checked_add(deposit_value, value)?;
+                }
+                CarrotInstruction::Redeem { amount, .. } => {
+                    let value = nav.value_of_shares(amount, Rounding::Down)?;
+                    volume.redeemed_shares = volume.redeemed_shares.saturating_add(amount);
+                    volume.redemption_count += 1;
+                    volume.redemption_value = checked_add(volume.redemption_value, value)?;
+                    redemption_value = checked_add(redemption_value, value)?;
+                }
+            }
+        }
+
+        Ok(Self {
+            since,
+            deposit_value,
+            redemption_value,
+            assets,
+        })
+    }
+}
+
+impl AssetVolume {
+    fn new(mint: Pubkey) -> Self {
+        Self {
+            mint,
+            deposited: 0,
+            redeemed_shares: 0,
+            deposit_count: 0,
+            redemption_count: 0,
+            deposit_value: 0,
+            redemption_value: 0,
+        }
+    }
+}
+
+/// Rolling 24h and 7d volume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollingVolume {
+    pub day: VolumeMetrics,
+    pub week: VolumeMetrics,
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Rolling 24h and 7d deposit and redemption volume, valued at the current NAV
+    ///
+    /// Decodes every vault transaction of the past week, so this issues many RPC requests.
+    pub fn fetch_rolling_volume(&self) -> Result<RollingVolume> {
+        let now = self.now();
+        let nav = self.fetch_nav()?;
+        let entries = self.fetch_vault_activity(now - WEEK_SECONDS)?;
+        Ok(RollingVolume {
+            day: VolumeMetrics::from_entries(&entries, &nav, now - DAY_SECONDS)?,
+            week: VolumeMetrics::from_entries(&entries, &nav, now - WEEK_SECONDS)?,
+        })
+    }
+}
+
+fn checked_add(a: u128, b: u128) -> Result<u128> {
+    a.checked_add(b).ok_or(CarrotError::MathOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nav::tests::test_nav;
+    use crate::USDC_MINT;
+    use solana_sdk::signature::Signature;
+
+    fn entry(block_time: i64, instruction: CarrotInstruction) -> HistoryEntry {
+        HistoryEntry {
+            signature: Signature::new_unique(),
+            instruction_index: 0,
+            slot: 1,
+            transaction_index: None,
+            block_time: Some(block_time),
+            instruction,
+        }
+    }
+
+    #[test]
+    fn test_volume_windows_and_breakdown() {
+        // 1 CRT = 1.1 USD
+        let nav = test_nav(1_000_000_000_000, vec![(USDC_MINT, 1_100_000_000, 100_000_000)]);
+        let user = Pubkey::new_unique();
+        let delisted = Pubkey::new_unique();
+        let entries = [
+            entry(100, CarrotInstruction::Issue { user, asset_mint: USDC_MINT, amount: 5_000_000 }),
+            entry(200, CarrotInstruction::Issue { user, asset_mint: delisted, amount: 1 }),
+            entry(300, CarrotInstruction::Redeem { user, asset_mint: USDC_MINT, amount: 10_000_000_000 }),
+        ];
+
+        let all = VolumeMetrics::from_entries(&entries, &nav, 0).unwrap();
+        assert_eq!(all.deposit_value, 5_000_000_000_000);
+        assert_eq!(all.redemption_value, 11_000_000_000_000);
+        assert_eq!(all.assets.len(), 2);
+        assert_eq!(all.assets[0].deposit_count, 1);
+        assert_eq!(all.assets[0].redeemed_shares, 10_000_000_000);
+        assert_eq!((all.assets[1].mint, all.assets[1].deposit_value), (delisted, 0));
+
+        let recent = VolumeMetrics::from_entries(&entries, &nav, 250).unwrap();
+        assert_eq!(recent.deposit_value, 0);
+        assert_eq!(recent.assets[0].redemption_count, 1);
+
+        // The same event delivered twice (e.g. by a webhook and a backfill) counts once
+        let doubled = [entries.as_slice(), &entries[..1]].concat();
+        assert_eq!(VolumeMetrics::from_entries(&doubled, &nav, 0).unwrap(), all);
+    }
+}
@@ -0,0 +1,535 @@
+//! Webhook delivery of vault events to non-Rust backends
+//!
+//! `CarrotClient::serve_webhooks` runs the subscription layer and POSTs every deposit,
+//! withdrawal and vault configuration change as a JSON `WebhookEvent` to each configured
+//! endpoint. Requests carry the event type and ID, a Unix timestamp and an HMAC-SHA256
+//! signature of `"<timestamp>.<body>"` keyed with the endpoint's secret:
+//!
+//! ```text
+//! X-Carrot-Event: deposit
+//! X-Carrot-Event-Id: <signature>:<instruction index>
+//! X-Carrot-Timestamp: 1718000000
+//! X-Carrot-Signature: v1=<hex HMAC-SHA256>
+//! ```
+//!
+//! Receivers recompute the signature over the raw body, reject stale timestamps, and
+//! deduplicate on the event ID, which stays the same across retries and redeliveries.
+
+#[cfg(feature = "blocking")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::hash::hashv;
+
+use crate::{
+    alerts::Alert,
+    error::{CarrotError, Result},
+    history::HistoryEntry,
+    ledger::{from_hex, to_hex},
+    schema::{VaultEvent, VaultSnapshotRecord, SCHEMA_VERSION},
+    snapshot::VaultSnapshot,
+};
+#[cfg(feature = "blocking")]
+use crate::{
+    alerts::VaultChangeDetector,
+    client::CarrotClient,
+    clock::{Clock, SystemClock},
+    history::ActivityCursor,
+    schema,
+    subscription::ActivitySubscription,
+    supervisor::{Backoff, ShutdownSignal, TaskGroup},
+};
+
+/// Header naming the event type
+pub const EVENT_TYPE_HEADER: &str = "X-Carrot-Event";
+
+/// Header carrying the event ID
+pub const EVENT_ID_HEADER: &str = "X-Carrot-Event-Id";
+
+/// Header carrying the Unix timestamp the request was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Carrot-Timestamp";
+
+/// Header carrying the request signature
+pub const SIGNATURE_HEADER: &str = "X-Carrot-Signature";
+
+/// Prefix of the signature scheme version
+const SIGNATURE_PREFIX: &str = "v1=";
+
+/// Timeout of a webhook request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default attempts per endpoint before a delivery fails
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// HMAC block size of SHA-256
+const BLOCK_SIZE: usize = 64;
+
+/// What a `WebhookEvent` reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    Deposit,
+    Withdraw,
+    /// The vault was paused or unpaused, or a fee rate changed
+    VaultChange,
+}
+
+impl WebhookEventType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::Deposit => "deposit",
+            WebhookEventType::Withdraw => "withdraw",
+            WebhookEventType::VaultChange => "vault_change",
+        }
+    }
+}
+
+/// Body of a webhook request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub schema_version: u32,
+    /// Stable across retries and redeliveries
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub slot: u64,
+    /// `VaultEvent` for deposits and withdrawals; for vault changes, the `changes` (severity
+    /// and description) and the `snapshot` (`VaultSnapshotRecord`) they were detected in
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    /// Event of a deposit or withdrawal, identified by its `EventKey`
+    pub fn from_entry(entry: &HistoryEntry) -> Result<Self> {
+        let event_type = match entry.kind() {
+            "deposit" => WebhookEventType::Deposit,
+            _ => WebhookEventType::Withdraw,
+        };
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            id: entry.key().to_string(),
+            event_type,
+            slot: entry.slot,
+            data: to_value(&VaultEvent::from(entry))?,
+        })
+    }
+
+    /// Event of the configuration changes detected in `snapshot`, identified by its slot
+    pub fn from_changes(snapshot: &VaultSnapshot, changes: &[Alert]) -> Result<Self> {
+        let changes: Vec<_> = changes
+            .iter()
+            .map(|alert| json!({ "severity": alert.severity.to_string(), "description": alert.to_string() }))
+            .collect();
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            id: format!("vault_change:{}", snapshot.slot),
+            event_type: WebhookEventType::VaultChange,
+            slot: snapshot.slot,
+            data: json!({ "changes": changes, "snapshot": to_value(&VaultSnapshotRecord::from(snapshot))? }),
+        })
+    }
+}
+
+fn to_value<T: Serialize>(record: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(record).map_err(|e| CarrotError::InvalidConfig(format!("failed to serialize event: {}", e)))
+}
+
+/// HMAC-SHA256 of `message` keyed with `key`
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(hashv(&[key]).as_ref());
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner_key = block.map(|byte| byte ^ 0x36);
+    let outer_key = block.map(|byte| byte ^ 0x5c);
+    let inner = hashv(&[&inner_key, message]);
+    hashv(&[&outer_key, inner.as_ref()]).to_bytes()
+}
+
+/// `X-Carrot-Signature` value of `body` sent at `timestamp`
+pub fn sign_payload(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mac = hmac_sha256(secret, &[timestamp.to_string().as_bytes(), b".", body].concat());
+    format!("{}{}", SIGNATURE_PREFIX, to_hex(&mac))
+}
+
+/// Check a received request's signature, rejecting timestamps more than `tolerance` seconds
+/// away from `now` so captured requests can't be replayed later
+pub fn verify_signature(
+    secret: &[u8],
+    timestamp: i64,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+    tolerance: u64,
+) -> Result<()> {
+    if now.abs_diff(timestamp) > tolerance {
+        return Err(CarrotError::InvalidWebhookSignature(format!("timestamp {} is stale", timestamp)));
+    }
+    let received = signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .and_then(from_hex)
+        .ok_or_else(|| CarrotError::InvalidWebhookSignature("malformed signature".to_string()))?;
+    let expected = hmac_sha256(secret, &[timestamp.to_string().as_bytes(), b".", body].concat());
+    // Compare in constant time
+    let difference = received
+        .iter()
+        .zip(expected.iter())
+        .fold(received.len() ^ expected.len(), |acc, (a, b)| acc | (a ^ b) as usize);
+    if difference != 0 {
+        return Err(CarrotError::InvalidWebhookSignature("signature mismatch".to_string()));
+    }
+    Ok(())
+}
+
+/// URL events are POSTed to and the secret signing them
+#[derive(Clone)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    secret: Vec<u8>,
+}
+
+impl WebhookEndpoint {
+    pub fn new(url: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The secret is a credential
+        f.debug_struct("WebhookEndpoint").field("url", &self.url).finish_non_exhaustive()
+    }
+}
+
+/// Outcome of one POST
+#[cfg(feature = "blocking")]
+enum Attempt {
+    Delivered,
+    /// Worth retrying: network errors, timeouts, 408, 429 and 5xx
+    Transient(String),
+    /// The receiver refused the event
+    Rejected(String),
+}
+
+/// Signs and POSTs `WebhookEvent`s to every endpoint, retrying transient failures
+#[cfg(feature = "blocking")]
+pub struct WebhookDispatcher {
+    endpoints: Vec<WebhookEndpoint>,
+    max_attempts: u32,
+    backoff: Backoff,
+    clock: Arc<dyn Clock>,
+    http: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "blocking")]
+impl WebhookDispatcher {
+    /// Dispatcher without endpoints, retrying `DEFAULT_MAX_ATTEMPTS` times with the default backoff
+    pub fn new() -> Result<Self> {
+        let http = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| CarrotError::InvalidConfig(format!("failed to build HTTP client: {}", e)))?;
+        Ok(Self {
+            endpoints: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: Backoff::default(),
+            clock: Arc::new(SystemClock),
+            http,
+        })
+    }
+
+    /// POST events to `url`, signed with `secret`
+    pub fn with_endpoint(mut self, url: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        self.endpoints.push(WebhookEndpoint::new(url, secret));
+        self
+    }
+
+    /// Give up on an endpoint after `attempts` (at least 1) transient failures in a row
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Wait between attempts as set by `backoff`
+    pub fn with_retry_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Timestamp requests with `clock` (the system clock by default); `serve_webhooks` uses the
+    /// client's `ClientConfig::clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn endpoints(&self) -> &[WebhookEndpoint] {
+        &self.endpoints
+    }
+
+    /// POST `event` to every endpoint
+    ///
+    /// An endpoint answering with a 4xx other than 408 or 429 rejected the event: that is
+    /// logged and not retried. Fails when an endpoint still has a transient failure after
+    /// the last attempt, or when `shutdown` is triggered while waiting to retry.
+    pub fn deliver(&self, event: &WebhookEvent, shutdown: &ShutdownSignal) -> Result<()> {
+        let body = schema::to_json(event)?;
+        let mut failures = Vec::new();
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            let mut attempt = 1;
+            loop {
+                match self.post(endpoint, event, &body) {
+                    Attempt::Delivered => break,
+                    Attempt::Rejected(reason) => {
+                        log::warn!("webhook {} rejected event {}: {}", index, event.id, reason);
+                        break;
+                    }
+                    Attempt::Transient(reason) if attempt >= self.max_attempts => {
+                        failures.push(format!("webhook {}: {}", index, reason));
+                        break;
+                    }
+                    Attempt::Transient(reason) => {
+                        log::debug!("webhook {} attempt {} failed: {}", index, attempt, reason);
+                        if shutdown.wait_timeout(self.backoff.delay(attempt)) {
+                            return Err(CarrotError::PublishFailed(format!("webhook {}: shut down", index)));
+                        }
+                        attempt += 1;
+                    }
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(CarrotError::PublishFailed(format!("event {}: {}", event.id, failures.join(", "))))
+        }
+    }
+
+    fn post(&self, endpoint: &WebhookEndpoint, event: &WebhookEvent, body: &str) -> Attempt {
+        let timestamp = self.clock.unix_timestamp();
+        let response = self
+            .http
+            .post(&endpoint.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_TYPE_HEADER, event.event_type.as_str())
+            .header(EVENT_ID_HEADER, &event.id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign_payload(&endpoint.secret, timestamp, body.as_bytes()))
+            .body(body.to_string())
+            .send();
+        match response {
+            Ok(response) if response.status().is_success() => Attempt::Delivered,
+            Ok(response) => {
+                let status = response.status();
+                let transient = status.is_server_error()
+                    || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                    || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                if transient {
+                    Attempt::Transient(status.to_string())
+                } else {
+                    Attempt::Rejected(status.to_string())
+                }
+            }
+            // Leave the URL out: it may carry credentials
+            Err(err) => Attempt::Transient(err.without_url().to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Deliver deposits, withdrawals and vault configuration changes to `dispatcher`'s
+    /// endpoints from supervised tasks in `group`
+    ///
+    /// Deposits and withdrawals come from `subscribe_vault_activity` (starting after `start`)
+    /// and are delivered at-least-once: a transaction is delivered again, to every endpoint,
+    /// until no endpoint fails on it. The vault is compared every `change_interval` with
+    /// `VaultChangeDetector`, and a change is detected again until it was delivered.
+    pub fn serve_webhooks(
+        &self,
+        group: &mut TaskGroup,
+        dispatcher: WebhookDispatcher,
+        start: Option<ActivityCursor>,
+        poll_interval: Duration,
+        change_interval: Duration,
+    ) -> ActivitySubscription {
+        let dispatcher = Arc::new(dispatcher.with_clock(Arc::clone(&self.config().clock)));
+        let changes = Arc::clone(&dispatcher);
+        let client = self.clone();
+        let mut detector = VaultChangeDetector::new();
+        group.spawn("webhook-vault-changes", move |shutdown| loop {
+            let snapshot = client.fetch_snapshot()?;
+            // Only move past the change once it was delivered
+            let mut next = detector.clone();
+            let alerts = next.observe(&snapshot);
+            if !alerts.is_empty() {
+                changes.deliver(&WebhookEvent::from_changes(&snapshot, &alerts)?, shutdown)?;
+            }
+            detector = next;
+            if shutdown.wait_timeout(change_interval) {
+                return Ok(());
+            }
+        });
+        self.spawn_vault_activity(
+            group,
+            start,
+            poll_interval,
+            move |entry, shutdown| dispatcher.deliver(&WebhookEvent::from_entry(entry)?, shutdown),
+            || false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instructions::CarrotInstruction, USDC_MINT};
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+    #[cfg(feature = "blocking")]
+    use std::io::{BufRead, BufReader, Read, Write};
+    #[cfg(feature = "blocking")]
+    use crate::clock::FixedClock;
+    #[cfg(feature = "blocking")]
+    use std::net::TcpListener;
+    #[cfg(feature = "blocking")]
+    use std::thread;
+
+    fn deposit() -> HistoryEntry {
+        HistoryEntry {
+            signature: Signature::new_unique(),
+            instruction_index: 0,
+            slot: 9,
+            transaction_index: None,
+            block_time: Some(1_700_000_000),
+            instruction: CarrotInstruction::Issue {
+                user: Pubkey::new_unique(),
+                asset_mint: USDC_MINT,
+                amount: 5_000_000,
+            },
+        }
+    }
+
+    #[test]
+    fn test_hmac_and_signatures() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // RFC 4231 test case 6: key longer than a block
+        assert_eq!(
+            to_hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+
+        let body = br#"{"id":"x"}"#;
+        let signature = sign_payload(b"secret", 1_000, body);
+        verify_signature(b"secret", 1_000, body, &signature, 1_100, 300).unwrap();
+        assert!(verify_signature(b"other", 1_000, body, &signature, 1_000, 300).is_err());
+        assert!(verify_signature(b"secret", 1_000, b"{}", &signature, 1_000, 300).is_err());
+        assert!(verify_signature(b"secret", 1_000, body, &signature, 1_301, 300).is_err());
+        assert!(verify_signature(b"secret", 1_000, body, "v1=zz", 1_000, 300).is_err());
+    }
+
+    #[test]
+    fn test_event_from_entry() {
+        let entry = deposit();
+        let event = WebhookEvent::from_entry(&entry).unwrap();
+        assert_eq!(event.event_type, WebhookEventType::Deposit);
+        assert_eq!(event.id, entry.key().to_string());
+        let json = crate::schema::to_json(&event).unwrap();
+        assert!(json.contains(r#""type":"deposit""#));
+        assert!(json.contains(r#""amount":5000000"#));
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Answer each request with the next status, returning the requests' headers and bodies
+    fn serve(statuses: &'static [u16]) -> (String, thread::JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            statuses
+                .iter()
+                .map(|status| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut headers = String::new();
+                    let mut length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                            length = value.trim().parse().unwrap();
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        headers.push_str(&line);
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+                    let response = format!("HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                    (headers, String::from_utf8(body).unwrap())
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn header<'a>(headers: &'a str, name: &str) -> &'a str {
+        let prefix = format!("{}: ", name.to_ascii_lowercase());
+        headers
+            .lines()
+            .find_map(|line| line.to_ascii_lowercase().starts_with(&prefix).then(|| &line[prefix.len()..]))
+            .unwrap()
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_delivery_retries_transient_failures() {
+        let fast = Backoff {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(1),
+        };
+        let event = WebhookEvent::from_entry(&deposit()).unwrap();
+
+        let (url, server) = serve(&[503, 200]);
+        let dispatcher = WebhookDispatcher::new()
+            .unwrap()
+            .with_endpoint(url, "s3cret")
+            .with_retry_backoff(fast)
+            .with_clock(Arc::new(FixedClock::new(1_718_000_000)));
+        dispatcher.deliver(&event, &ShutdownSignal::default()).unwrap();
+        let requests = server.join().unwrap();
+        let (headers, body) = &requests[1];
+        assert_eq!(header(headers, EVENT_ID_HEADER), event.id);
+        assert_eq!(header(headers, EVENT_TYPE_HEADER), "deposit");
+        assert_eq!(header(headers, TIMESTAMP_HEADER), "1718000000");
+        let signature = header(headers, SIGNATURE_HEADER);
+        verify_signature(b"s3cret", 1_718_000_000, body.as_bytes(), signature, 1_718_000_030, 60).unwrap();
+
+        // A rejection isn't retried; running out of attempts fails the delivery
+        let (url, server) = serve(&[400]);
+        let dispatcher = WebhookDispatcher::new().unwrap().with_endpoint(url, "s3cret").with_retry_backoff(fast);
+        dispatcher.deliver(&event, &ShutdownSignal::default()).unwrap();
+        server.join().unwrap();
+        let (url, server) = serve(&[500, 500]);
+        let dispatcher = WebhookDispatcher::new()
+            .unwrap()
+            .with_endpoint(url, "s3cret")
+            .with_retry_backoff(fast)
+            .with_max_attempts(2);
+        let err = dispatcher.deliver(&event, &ShutdownSignal::default()).unwrap_err();
+        assert!(matches!(err, CarrotError::PublishFailed(_)));
+        server.join().unwrap();
+    }
+}
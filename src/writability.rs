@@ -0,0 +1,206 @@
+use std::fmt;
+
+use solana_sdk::{account::Account, message::Message, pubkey::Pubkey};
+#[cfg(feature = "blocking")]
+use solana_account_decoder_client_types::UiAccountEncoding;
+#[cfg(feature = "blocking")]
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+#[cfg(feature = "blocking")]
+use solana_sdk::transaction::Transaction;
+
+use crate::{
+    error::{CarrotError, Result},
+    instructions::account_name,
+    CARROT_PROGRAM_ID,
+};
+#[cfg(feature = "blocking")]
+use crate::client::CarrotClient;
+
+/// An account a transaction marks writable but its simulation left untouched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwrittenAccount {
+    pub address: Pubkey,
+    /// Role of the account in each instruction referencing it, e.g. `ix 2: remaining_account[1]`
+    pub roles: Vec<String>,
+}
+
+impl fmt::Display for UnwrittenAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.address, self.roles.join(", "))
+    }
+}
+
+/// Writable accounts of a transaction split by whether its simulation changed them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WritabilityAudit {
+    /// Writable accounts whose lamports, data or owner changed
+    pub written: Vec<Pubkey>,
+    /// Writable accounts left as they were: candidates for read-only
+    pub unwritten: Vec<UnwrittenAccount>,
+}
+
+impl WritabilityAudit {
+    /// Compare the state of `message`'s writable accounts (`writable_accounts` order) before and
+    /// after simulation
+    pub fn from_states(message: &Message, pre: &[Option<Account>], post: &[Option<Account>]) -> Self {
+        let mut audit = Self::default();
+        for (index, address) in writable_accounts(message) {
+            let position = audit.written.len() + audit.unwritten.len();
+            let before = pre.get(position).cloned().flatten();
+            let after = post.get(position).cloned().flatten();
+            if is_changed(before.as_ref(), after.as_ref()) {
+                audit.written.push(address);
+            } else {
+                audit.unwritten.push(UnwrittenAccount {
+                    address,
+                    roles: roles(message, index),
+                });
+            }
+        }
+        audit
+    }
+
+    /// Fail with `OverbroadWritable` if any writable account was left untouched
+    pub fn ensure_strict(&self) -> Result<()> {
+        if self.unwritten.is_empty() {
+            return Ok(());
+        }
+        let accounts: Vec<String> = self.unwritten.iter().map(ToString::to_string).collect();
+        Err(CarrotError::OverbroadWritable(accounts.join("; ")))
+    }
+}
+
+/// Key indexes and addresses of the accounts `message` locks for writing
+pub fn writable_accounts(message: &Message) -> Vec<(usize, Pubkey)> {
+    message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| message.is_maybe_writable(*index, None))
+        .map(|(index, address)| (index, *address))
+        .collect()
+}
+
+fn is_changed(before: Option<&Account>, after: Option<&Account>) -> bool {
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            before.lamports != after.lamports || before.data != after.data || before.owner != after.owner
+        }
+        // Simulations report closed accounts as empty system accounts
+        (None, Some(after)) => after.lamports > 0 || !after.data.is_empty(),
+        (Some(before), None) => before.lamports > 0,
+        (None, None) => false,
+    }
+}
+
+fn roles(message: &Message, key_index: usize) -> Vec<String> {
+    let mut roles = Vec::new();
+    for (ix_index, instruction) in message.instructions.iter().enumerate() {
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        for (position, account) in instruction.accounts.iter().enumerate() {
+            if *account as usize != key_index {
+                continue;
+            }
+            if program_id == CARROT_PROGRAM_ID {
+                roles.push(format!("ix {}: {}", ix_index, account_name(position)));
+            } else {
+                roles.push(format!("ix {}: account {} of {}", ix_index, position, program_id));
+            }
+        }
+    }
+    if key_index == 0 {
+        roles.push("fee payer".to_string());
+    }
+    roles
+}
+
+#[cfg(feature = "blocking")]
+impl CarrotClient {
+    /// Simulate `transaction` and report which of the accounts it marks writable were actually
+    /// written, flagging over-broad writable flags that serialize it against other transactions
+    ///
+    /// An account written back unchanged counts as unwritten; the result reflects this one
+    /// simulation, so branches not taken (e.g. an ATA that already exists) show up too.
+    pub fn audit_writability(&self, transaction: &Transaction) -> Result<WritabilityAudit> {
+        let message = &transaction.message;
+        let addresses: Vec<Pubkey> = writable_accounts(message).into_iter().map(|(_, address)| address).collect();
+        let pre = self.rpc_client().get_multiple_accounts(&addresses)?;
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.rpc_client().commitment()),
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: addresses.iter().map(ToString::to_string).collect(),
+            }),
+            ..Default::default()
+        };
+        let result = self.rpc_client().simulate_transaction_with_config(transaction, config)?.value;
+        if let Some(err) = result.err {
+            let logs = result.logs.unwrap_or_default().join("\n");
+            return Err(CarrotError::SimulationFailed(format!("{}\n{}", err, logs)));
+        }
+        let post = result
+            .accounts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|account| {
+                account
+                    .map(|account| {
+                        account.decode::<Account>().ok_or_else(|| {
+                            CarrotError::SimulationFailed("undecodable simulated account".to_string())
+                        })
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(WritabilityAudit::from_states(message, &pre, &post))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::usdc_vault;
+    use crate::instructions::build_issue_instruction;
+    use crate::USDC_MINT;
+
+    fn account(lamports: u64, data: Vec<u8>) -> Option<Account> {
+        Some(Account {
+            lamports,
+            data,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_flags_untouched_writable_accounts() {
+        let user = Pubkey::new_unique();
+        let issue = build_issue_instruction(&usdc_vault(), &user, &USDC_MINT, 1).unwrap();
+        let message = Message::new(&[issue], Some(&user));
+        let writable = writable_accounts(&message);
+        // Every issue account but the asset mint and programs; the vault asset ATA is also the
+        // first remaining account, the oracle the second
+        assert_eq!(writable.len(), 7);
+
+        let pre: Vec<Option<Account>> = (0..writable.len()).map(|i| account(i as u64 + 1, vec![0])).collect();
+        let mut post = pre.clone();
+        // Everything written but the last two
+        for state in post.iter_mut().take(writable.len() - 2) {
+            state.as_mut().unwrap().data = vec![1];
+        }
+        let audit = WritabilityAudit::from_states(&message, &pre, &post);
+        assert_eq!(audit.written.len(), writable.len() - 2);
+        assert_eq!(audit.unwritten.len(), 2);
+        assert!(audit.unwritten.iter().all(|account| !account.roles.is_empty()));
+        assert!(matches!(audit.ensure_strict(), Err(CarrotError::OverbroadWritable(_))));
+
+        let audit = WritabilityAudit::from_states(&message, &pre, &vec![account(9, vec![]); writable.len()]);
+        assert!(audit.ensure_strict().is_ok());
+        // Created accounts count as written, accounts that stay missing don't
+        assert!(is_changed(None, account(1, vec![]).as_ref()));
+        assert!(!is_changed(None, None));
+    }
+}
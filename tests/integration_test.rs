@@ -1,10 +1,34 @@
 #[cfg(test)]
 mod tests {
     use carrot_sdk_v3::accounts::*;
+    use carrot_sdk_v3::test_utils::{self, usdc_vault};
     use carrot_sdk_v3::*;
     use solana_sdk::pubkey::Pubkey;
     use std::str::FromStr;
 
+    #[test]
+    fn test_vault_account_data_round_trip() {
+        let vault = usdc_vault().vault;
+        let data = vault.to_account_data();
+        assert_eq!(data[..8], VAULT_DISCRIMINATOR);
+        let decoded = Vault::from_account_data(&data).unwrap();
+        assert_eq!(decoded.authority, vault.authority);
+        assert_eq!(decoded.assets[0].mint, USDC_MINT);
+
+        // Another account type, truncated data and an empty slice are errors, not panics
+        let mut other = data.clone();
+        other[0] ^= 1;
+        assert!(matches!(
+            Vault::from_account_data(&other),
+            Err(CarrotError::InvalidAccountDiscriminator { .. })
+        ));
+        assert!(matches!(
+            Vault::from_account_data(&data[..data.len() - 1]),
+            Err(CarrotError::InvalidVaultData)
+        ));
+        assert!(matches!(Vault::from_account_data(&[]), Err(CarrotError::InvalidVaultData)));
+    }
+
     #[test]
     fn test_vault_address_derivation() {
         let (vault, _bump) = derive_vault_address(&CRT_MINT);
@@ -57,4 +81,300 @@ mod tests {
             Pubkey::from_str("FfCRL34rkJiMiX5emNDrYp3MdWH2mES3FvDQyFppqgpJ").unwrap()
         );
     }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_client_config_headers() {
+        let config = ClientConfig::new("http://localhost:8899")
+            .with_timeout(std::time::Duration::from_secs(5))
+            .with_header("x-api-key", "secret-key");
+        assert!(CarrotClient::with_config(config.clone()).is_ok());
+        // Header values must never show up in debug output
+        assert!(!format!("{:?}", config).contains("secret-key"));
+
+        let invalid = ClientConfig::new("http://localhost:8899").with_header("bad header", "value");
+        assert!(matches!(
+            CarrotClient::with_config(invalid),
+            Err(CarrotError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_inspect_issue_transaction() {
+        use solana_sdk::{message::Message, transaction::Transaction};
+
+        let user = Pubkey::from_str("RnGrVx38FRDJUyH6pS6QHFHikbTrs9m1csNiJPWHaZA").unwrap();
+        let ix = instructions::build_issue_instruction(&usdc_vault(), &user, &USDC_MINT, 1_000_000).unwrap();
+        let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&user)));
+
+        let inspection = inspect(&tx).unwrap();
+        assert_eq!(inspection.fee_payer, user);
+        assert_eq!(inspection.signers, vec![user]);
+        assert_eq!(inspection.programs, vec![CARROT_PROGRAM_ID]);
+        assert!(inspection.writable_accounts.contains(&VAULT_ADDRESS));
+        assert!(!inspection.writable_accounts.contains(&USDC_MINT));
+        assert_eq!(
+            inspection.carrot_instructions,
+            vec![(
+                0,
+                CarrotInstruction::Issue {
+                    user,
+                    asset_mint: USDC_MINT,
+                    amount: 1_000_000
+                }
+            )]
+        );
+
+        // Account indices past the end of the keys are reported, not indexed
+        let mut malformed = tx.clone();
+        malformed.message.instructions[0].accounts[0] = malformed.message.account_keys.len() as u8;
+        assert!(matches!(inspect(&malformed), Err(CarrotError::TransactionFailed(_))));
+        malformed.message.instructions[0].program_id_index = u8::MAX;
+        assert!(matches!(inspect(&malformed), Err(CarrotError::TransactionFailed(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_withdrawal_destination_allowlist() {
+        let user = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+
+        let open = CarrotClient::new("http://localhost:8899".to_string());
+        assert!(open.check_withdrawal_destination(&user, &attacker).is_ok());
+
+        let config = ClientConfig::new("http://localhost:8899").with_withdrawal_destinations(vec![treasury]);
+        let client = CarrotClient::with_config(config).unwrap();
+        assert!(client.check_withdrawal_destination(&user, &treasury).is_ok());
+        assert!(client.check_withdrawal_destination(&user, &user).is_ok());
+        assert!(matches!(
+            client.check_withdrawal_destination(&user, &attacker),
+            Err(CarrotError::DestinationNotAllowed(p)) if p == attacker
+        ));
+
+        // Checked before anything is fetched: no RPC server is needed to refuse
+        let signer = solana_sdk::signature::Keypair::new();
+        assert!(matches!(
+            client.deposit_and_forward(&signer, &USDC_MINT, 1_000_000, &attacker),
+            Err(CarrotError::DestinationNotAllowed(p)) if p == attacker
+        ));
+    }
+
+    #[test]
+    fn test_issue_account_names() {
+        use carrot_sdk_v3::instructions::{account_name, build_issue_instruction, ISSUE_REDEEM_ACCOUNT_NAMES};
+
+        let user = Pubkey::new_unique();
+        let ix = build_issue_instruction(&usdc_vault(), &user, &USDC_MINT, 1).unwrap();
+        // One vault asset: its ATA and oracle follow the fixed accounts
+        assert_eq!(ix.accounts.len(), ISSUE_REDEEM_ACCOUNT_NAMES.len() + 2);
+        assert_eq!(account_name(6), "user");
+        assert!(ix.accounts[6].is_signer);
+        assert_eq!(account_name(11), "remaining_account[0]");
+    }
+
+    #[test]
+    fn test_issue_accounts_round_trip() {
+        use carrot_sdk_v3::instructions::{build_issue_instruction, IssueAccounts, ToAccountMetas};
+
+        let user = Pubkey::new_unique();
+        let vault = usdc_vault();
+        let accounts = IssueAccounts::new(&vault, &user, &USDC_MINT).unwrap();
+        assert_eq!(accounts.user_shares_ata, get_user_crt_ata(&user));
+        assert_eq!(accounts.remaining_accounts.len(), 2);
+
+        let ix = build_issue_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+        assert_eq!(ix.accounts, accounts.to_account_metas(None));
+        let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+        assert_eq!(IssueAccounts::from_accounts(&keys), Some(accounts.clone()));
+        assert_eq!(IssueAccounts::from_accounts(&keys[..10]), None);
+
+        // The signer flag can be overridden, e.g. for a CPI signed by a PDA
+        assert!(!accounts.to_account_metas(Some(false))[6].is_signer);
+    }
+
+    #[test]
+    fn test_remaining_accounts_resolver_is_injectable() {
+        use carrot_sdk_v3::instructions::{IssueAccounts, StaticAccounts, ToAccountMetas};
+
+        let user = Pubkey::new_unique();
+        let vault = usdc_vault();
+        let synthetic = vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let resolver = StaticAccounts(synthetic.clone());
+        let accounts = IssueAccounts::new_with_resolver(&vault, &user, &USDC_MINT, &resolver).unwrap();
+        assert_eq!(accounts.remaining_accounts, synthetic);
+        let metas = accounts.to_account_metas(None);
+        assert_eq!(metas.len(), 11 + synthetic.len());
+        assert_eq!(metas[11].pubkey, synthetic[0]);
+
+        // The default resolver is the vault's asset ATAs and oracles
+        let default = IssueAccounts::new(&vault, &user, &USDC_MINT).unwrap();
+        assert_eq!(default.remaining_accounts, vault.vault.get_remaining_accounts());
+    }
+
+    #[test]
+    fn test_derivations_report_lists_instruction_accounts() {
+        use carrot_sdk_v3::instructions::build_issue_instruction;
+
+        let user = Pubkey::new_unique();
+        let vault = usdc_vault();
+        let report = derivations_report(&vault, &user, &USDC_MINT).unwrap();
+        let ix = build_issue_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+
+        // Instruction accounts in order, then the programs of the surrounding transaction
+        assert_eq!(report.len(), ix.accounts.len() + 2);
+        for (entry, meta) in report.iter().zip(&ix.accounts) {
+            assert_eq!(entry.address, meta.pubkey);
+        }
+        let (_, vault_bump) = derive_vault_address(&CRT_MINT);
+        assert!(matches!(&report[0].source, AddressSource::Pda { bump, .. } if *bump == vault_bump));
+        assert!(report[0].to_string().contains("[\"vault\", CRTx"));
+        let (_, ata_bump) = find_vault_asset_ata(&USDC_MINT);
+        assert!(matches!(&report[4].source, AddressSource::Pda { bump, .. } if *bump == ata_bump));
+        assert_eq!(report[6].source, AddressSource::Input);
+        assert_eq!(report[11].source, AddressSource::VaultState);
+    }
+
+    #[test]
+    fn test_asset_added_on_chain_uses_its_token_program() {
+        use carrot_sdk_v3::instructions::IssueAccounts;
+
+        // A Token-2022 stablecoin the SDK has no constant for
+        let mint = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let mut vault = usdc_vault();
+        vault.vault.assets.push(Asset {
+            asset_id: 1,
+            mint,
+            decimals: 6,
+            ata: TokenProgram::Token2022.associated_token_address(&VAULT_ADDRESS, &mint),
+            oracle: Pubkey::new_unique(),
+        });
+
+        assert_eq!(TokenProgram::known(&mint), None);
+        assert_eq!(vault.asset_token_program(&mint).unwrap(), TokenProgram::Token2022);
+        assert_eq!(vault.asset_token_program(&USDC_MINT).unwrap(), TokenProgram::Token);
+
+        let accounts = IssueAccounts::new(&vault, &user, &mint).unwrap();
+        assert_eq!(accounts.asset_token_program, TokenProgram::Token2022.id());
+        assert_eq!(
+            accounts.user_asset_ata,
+            TokenProgram::Token2022.associated_token_address(&user, &mint)
+        );
+        assert_eq!(accounts.remaining_accounts.len(), 4);
+    }
+
+    #[test]
+    fn test_builders_target_given_vault() {
+        use carrot_sdk_v3::instructions::{build_redeem_instruction, build_redeem_instruction_with_accounts, IssueAccounts};
+
+        let user = Pubkey::new_unique();
+        let mut vault = usdc_vault();
+        let ix = build_redeem_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+        assert_eq!(ix.accounts[0].pubkey, VAULT_ADDRESS);
+        assert_eq!(ix.accounts[4].pubkey, get_vault_asset_ata(&USDC_MINT));
+
+        vault.address = Pubkey::new_unique();
+        let ix = build_redeem_instruction(&vault, &user, &USDC_MINT, 1).unwrap();
+        assert_eq!(ix.accounts[0].pubkey, vault.address);
+
+        assert!(matches!(
+            build_redeem_instruction(&vault, &user, &USDT_MINT, 1),
+            Err(CarrotError::InvalidAsset(_))
+        ));
+
+        // Auxiliary token account in place of the ATA
+        let token_account = Pubkey::new_unique();
+        let accounts = IssueAccounts::new(&vault, &user, &USDC_MINT)
+            .unwrap()
+            .with_user_asset_account(token_account);
+        let ix = build_redeem_instruction_with_accounts(&accounts, 1).unwrap();
+        assert_eq!(ix.accounts[5].pubkey, token_account);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_send_raw_transaction_rejects_unsigned() {
+        use solana_sdk::{message::Message, transaction::Transaction};
+
+        let user = Pubkey::new_unique();
+        let ix = instructions::build_issue_instruction(&usdc_vault(), &user, &USDC_MINT, 1).unwrap();
+        let tx = Transaction::new_unsigned(Message::new(&[ix], Some(&user)));
+        let bytes = bincode::serialize(&tx).unwrap();
+
+        // Rejected locally, before any RPC request
+        let client = CarrotClient::new("http://localhost:1".to_string());
+        assert!(matches!(
+            client.send_raw_transaction(&bytes),
+            Err(CarrotError::TransactionFailed(_))
+        ));
+        assert!(client.send_raw_transaction(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_policy_rejects_disallowed_asset_before_rpc() {
+        use solana_sdk::{signature::Keypair, signer::Signer};
+
+        let trader = Keypair::new();
+        let config = ClientConfig::new("http://localhost:1")
+            .with_policy(Policy::default().with_allowed_assets(vec![USDC_MINT]))
+            .with_user_policy(Pubkey::new_unique(), Policy::default());
+        let client = CarrotClient::with_config(config).unwrap();
+        assert!(matches!(
+            client.withdraw(&trader, &USDT_MINT, 1),
+            Err(CarrotError::PolicyViolation(_))
+        ));
+        assert!(matches!(
+            client.prepare_withdraw(&trader.pubkey(), &USDT_MINT, 1),
+            Err(CarrotError::PolicyViolation(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_approval_mode_blocks_unapproved_broadcast() {
+        use solana_sdk::{hash::Hash, message::Message, signature::Keypair, signer::Signer, transaction::Transaction};
+
+        let (requester, approver) = (Keypair::new(), Keypair::new());
+        let ix = instructions::build_issue_instruction(&usdc_vault(), &requester.pubkey(), &USDC_MINT, 1).unwrap();
+        let message = Message::new(&[ix], Some(&requester.pubkey()));
+        let prepared = PreparedTransaction {
+            transaction: Transaction::new(&[&requester], message, Hash::default()),
+            last_valid_block_height: 0,
+            expires_at: None,
+        };
+        let operation = PendingOperation::new(prepared.clone());
+
+        let config = ClientConfig::new("http://localhost:1").with_approvers(vec![approver.pubkey()]);
+        let client = CarrotClient::with_config(config).unwrap();
+        assert!(matches!(client.send_prepared(&prepared), Err(CarrotError::ApprovalRequired)));
+        assert!(matches!(
+            client.send_with_approver(&operation, |op| Ok(Approval::sign(&requester, op))),
+            Err(CarrotError::InvalidApproval(_))
+        ));
+        assert!(matches!(
+            client.send_approved(&operation, &Approval::sign(&Keypair::new(), &operation)),
+            Err(CarrotError::InvalidApproval(_))
+        ));
+    }
+
+    /// Redemptions captured with `examples/capture_execution.rs` in `tests/fixtures/redemptions`
+    #[test]
+    #[ignore = "needs mainnet redemptions captured with examples/capture_execution.rs"]
+    fn test_previews_match_mainnet_redemptions() {
+        use carrot_sdk_v3::nav::deduct_fee_bps;
+
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/redemptions");
+        let fixtures = test_utils::load_execution_fixtures(&dir).expect("redemption fixtures");
+        assert!(!fixtures.is_empty(), "no redemption fixtures in {}", dir.display());
+        for (path, fixture) in fixtures {
+            let snapshot = fixture.proof.verify().unwrap();
+            let nav = Nav::from_snapshot_with_fees(&snapshot, fixture.block_time).unwrap();
+            let mint = Pubkey::from_str(&fixture.asset_mint).unwrap();
+            let gross = CrtAmount(fixture.shares).to_asset_value(&nav, &mint, Rounding::Down).unwrap().amount;
+            let net = deduct_fee_bps(gross, snapshot.vault.fee.redemption_fee_bps).unwrap();
+            assert_eq!(net, fixture.asset_amount, "redemption {} ({})", fixture.signature, path.display());
+        }
+    }
 }